@@ -2,7 +2,7 @@ pub mod memory;
 pub mod names;
 
 use crate::common::error::Result;
-use crate::common::relation::{GlobalId, RelationDesc};
+use crate::common::relation::{GlobalId, RelationDesc, Row};
 use names::{
     DatabaseId, FullObjectName, PartialObjectName, QualifiedObjectName,
     SchemaId,
@@ -21,6 +21,16 @@ const FLOPPY_DB_ID: DatabaseId = DatabaseId(100);
 const FLOPPY_SCHEMA_NAME: &str = "public";
 const FLOPPY_SCHEMA_ID: SchemaId = SchemaId(101);
 
+/// The system tables are under "pg_catalog" schema, the ID is
+/// the same as PostgreSQL's.
+const PG_CATALOG_SCHEMA_NAME: &str = "pg_catalog";
+const PG_CATALOG_SCHEMA_ID: SchemaId = SchemaId(11);
+
+/// The schemas searched for an unqualified name by default.
+pub fn default_search_path() -> Vec<String> {
+    vec![FLOPPY_SCHEMA_NAME.to_string()]
+}
+
 /// A catalog keeps track of SQL objects available to the
 /// planner.
 ///
@@ -50,18 +60,39 @@ const FLOPPY_SCHEMA_ID: SchemaId = SchemaId(101);
 ///     (i.e., because the name was successfully resolved, or was constructed
 ///     based on the output of a prior lookup operation).
 pub trait CatalogStore: fmt::Debug + Send + Sync {
+    /// Resolve `item_name` to an item. An unqualified name is looked
+    /// up in the schemas of `search_path` in order, while a qualified
+    /// name is only looked up in its own schema.
     fn resolve_item(
         &self,
         item_name: &PartialObjectName,
-    ) -> Result<&dyn CatalogItem>;
+        search_path: &[String],
+    ) -> Result<Arc<dyn CatalogItem>>;
+
+    /// Returns the catalog item with `id`.
+    fn get_item(&self, id: &GlobalId) -> Result<Arc<dyn CatalogItem>>;
+
+    /// Removes the table `id`, it is not resolved by the statements
+    /// planned afterwards. The rows of the table are dropped by
+    /// [`TableStore::drop_table`](crate::storage::TableStore::drop_table).
+    fn drop_table(&self, id: &GlobalId) -> Result<()>;
 }
 
 impl<C: CatalogStore + ?Sized + Send + Sync> CatalogStore for Arc<C> {
     fn resolve_item(
         &self,
         item_name: &PartialObjectName,
-    ) -> Result<&dyn CatalogItem> {
-        (**self).resolve_item(item_name)
+        search_path: &[String],
+    ) -> Result<Arc<dyn CatalogItem>> {
+        (**self).resolve_item(item_name, search_path)
+    }
+
+    fn get_item(&self, id: &GlobalId) -> Result<Arc<dyn CatalogItem>> {
+        (**self).get_item(id)
+    }
+
+    fn drop_table(&self, id: &GlobalId) -> Result<()> {
+        (**self).drop_table(id)
     }
 }
 
@@ -92,6 +123,13 @@ pub trait CatalogItem {
     /// Returns the type of the catalog item.
     fn item_type(&self) -> CatalogItemType;
 
+    /// Returns the rows of a system table, which are synthesized by
+    /// the catalog instead of being kept in a
+    /// [`TableStore`](crate::storage::TableStore).
+    fn system_rows(&self) -> Option<&[Row]> {
+        None
+    }
+
     /// A normalized SQL statement that describes how to
     /// creat the catalog item.
     fn create_sql(&self) -> &str;
@@ -1,40 +1,189 @@
 use crate::catalog::{
-    names::{FullObjectName, PartialObjectName, QualifiedObjectName},
-    CatalogItem, CatalogItemType, CatalogStore,
+    names::{
+        FullObjectName, ObjectQualifiers, PartialObjectName,
+        QualifiedObjectName,
+    },
+    CatalogItem, CatalogItemType, CatalogStore, FLOPPY_DB_ID, FLOPPY_SCHEMA_ID,
+    FLOPPY_SCHEMA_NAME, PG_CATALOG_SCHEMA_ID, PG_CATALOG_SCHEMA_NAME,
 };
 use crate::common::{
     self,
     error::{CatalogError, FloppyError},
-    relation::{GlobalId, RelationDesc},
+    relation::{ColumnType, GlobalId, RelationDesc, Row},
+    scalar::{Datum, ScalarType},
 };
+use crate::pgrepr::Type;
 
+use lazy_static::lazy_static;
 use std::borrow::Cow;
 use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+/// The OIDs of user tables start from PostgreSQL's
+/// `FirstNormalObjectId`.
+const FIRST_USER_OID: u32 = 16384;
+/// The IDs of system tables are above the IDs of user tables.
+const FIRST_SYSTEM_ID: GlobalId = 1 << 32;
+/// The OID of the bootstrap superuser, who owns all the tables.
+const SUPERUSER_OID: i64 = 10;
+const PG_CLASS_OID: u32 = 1259;
+const PG_NAMESPACE_OID: u32 = 2615;
+const PG_ATTRIBUTE_OID: u32 = 1249;
+
+lazy_static! {
+    static ref PG_CLASS_DESC: RelationDesc = RelationDesc::new(
+        vec![
+            ColumnType::new(ScalarType::Int64, false),
+            ColumnType::new(ScalarType::Text, false),
+            ColumnType::new(ScalarType::Int64, false),
+            ColumnType::new(ScalarType::Text, false),
+            ColumnType::new(ScalarType::Int64, false),
+        ],
+        vec![
+            "oid".to_string(),
+            "relname".to_string(),
+            "relnamespace".to_string(),
+            "relkind".to_string(),
+            "relowner".to_string(),
+        ],
+        vec![0],
+        vec![],
+    );
+    static ref PG_NAMESPACE_DESC: RelationDesc = RelationDesc::new(
+        vec![
+            ColumnType::new(ScalarType::Int64, false),
+            ColumnType::new(ScalarType::Text, false),
+            ColumnType::new(ScalarType::Int64, false),
+        ],
+        vec![
+            "oid".to_string(),
+            "nspname".to_string(),
+            "nspowner".to_string(),
+        ],
+        vec![0],
+        vec![],
+    );
+    static ref PG_ATTRIBUTE_DESC: RelationDesc = RelationDesc::new(
+        vec![
+            ColumnType::new(ScalarType::Int64, false),
+            ColumnType::new(ScalarType::Text, false),
+            ColumnType::new(ScalarType::Int64, false),
+            ColumnType::new(ScalarType::Int64, false),
+            ColumnType::new(ScalarType::Boolean, false),
+        ],
+        vec![
+            "attrelid".to_string(),
+            "attname".to_string(),
+            "atttypid".to_string(),
+            "attnum".to_string(),
+            "attnotnull".to_string(),
+        ],
+        vec![0, 3],
+        vec![],
+    );
+}
 
 /// An in-memory catalog used in tests that requires a
 /// catalog.
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct MemCatalog {
-    /// the key is an item's name without any qualifier.
-    tables: HashMap<String, MemCatalogItem>,
+    /// the key is an item's name without any qualifier. The tables are
+    /// inserted before the catalog is shared, but they are dropped by
+    /// the statements of the sessions.
+    tables: RwLock<HashMap<String, Arc<MemCatalogItem>>>,
+    /// The read-only system tables in "pg_catalog", their rows are
+    /// synthesized from `tables` while its lock is held.
+    system_tables: RwLock<HashMap<String, Arc<MemCatalogItem>>>,
+    /// The ID of the next created item, the IDs are never reused.
+    next_id: GlobalId,
+}
+
+impl Default for MemCatalog {
+    fn default() -> Self {
+        let catalog = Self {
+            tables: RwLock::default(),
+            system_tables: RwLock::default(),
+            next_id: 1,
+        };
+        catalog.refresh_system_tables(&HashMap::new());
+        catalog
+    }
 }
 
 impl CatalogStore for MemCatalog {
     fn resolve_item(
         &self,
         partial_name: &PartialObjectName,
-    ) -> common::error::Result<&dyn CatalogItem> {
-        if let Some(result) = self.tables.get(&partial_name.item[..]) {
-            return Ok(result);
+        search_path: &[String],
+    ) -> common::error::Result<Arc<dyn CatalogItem>> {
+        let schemas = match &partial_name.schema {
+            Some(schema) => std::slice::from_ref(schema),
+            None => search_path,
+        };
+        for schema in schemas {
+            if let Some(result) = self.schema(schema).and_then(|items| {
+                items.read().unwrap().get(&partial_name.item[..]).cloned()
+            }) {
+                return Ok(result);
+            }
         }
 
         Err(FloppyError::Catalog(CatalogError::TableNotFound(
             partial_name.item.to_string(),
         )))
     }
+
+    fn get_item(
+        &self,
+        id: &GlobalId,
+    ) -> common::error::Result<Arc<dyn CatalogItem>> {
+        let tables = self.tables.read().unwrap();
+        let system_tables = self.system_tables.read().unwrap();
+        match tables
+            .values()
+            .chain(system_tables.values())
+            .find(|item| item.id() == *id)
+        {
+            Some(item) => Ok(item.clone()),
+            None => Err(FloppyError::Catalog(CatalogError::TableNotFound(
+                format!("table not found in catalog: {id}"),
+            ))),
+        }
+    }
+
+    fn drop_table(&self, id: &GlobalId) -> common::error::Result<()> {
+        let mut tables = self.tables.write().unwrap();
+        let count = tables.len();
+        tables.retain(|_, item| item.id() != *id);
+        if tables.len() == count {
+            return Err(FloppyError::Catalog(CatalogError::TableNotFound(
+                format!("table not found in catalog: {id}"),
+            )));
+        }
+        self.refresh_system_tables(&tables);
+        Ok(())
+    }
 }
 
 impl MemCatalog {
+    /// Allocates the ID of a new item, which is greater than the IDs
+    /// of all the existing items. The OID of the item is derived from
+    /// its ID, so it is unique too.
+    pub fn allocate_id(&mut self) -> GlobalId {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    /// Creates a table named `name` with a new ID, and returns the ID.
+    pub fn create_table(&mut self, name: &str, desc: RelationDesc) -> GlobalId {
+        let id = self.allocate_id();
+        self.insert_table(name, id, desc);
+        id
+    }
+
+    /// Inserts a table with the given `id`, the allocated IDs are
+    /// greater than it afterwards.
     #[allow(dead_code)]
     pub fn insert_table(
         &mut self,
@@ -42,19 +191,135 @@ impl MemCatalog {
         id: GlobalId,
         desc: RelationDesc,
     ) {
-        let mut tmp = self.tables.clone();
-        tmp.insert(
-            name.into(),
-            MemCatalogItem::Table {
-                name: name.into(),
-                id,
-                desc,
-            },
-        );
-        self.tables = tmp;
+        let item = MemCatalogItem::Table {
+            name: name.into(),
+            id,
+            desc,
+        };
+        let tables = self.tables.get_mut().unwrap();
+        tables.insert(name.into(), Arc::new(item));
+        self.next_id = self.next_id.max(id + 1);
+        self.refresh_system_tables(&self.tables.read().unwrap());
+    }
+
+    /// The items in `schema`.
+    fn schema(
+        &self,
+        schema: &str,
+    ) -> Option<&RwLock<HashMap<String, Arc<MemCatalogItem>>>> {
+        match schema {
+            FLOPPY_SCHEMA_NAME => Some(&self.tables),
+            PG_CATALOG_SCHEMA_NAME => Some(&self.system_tables),
+            _ => None,
+        }
+    }
+
+    /// Synthesize the rows of the system tables from the items in
+    /// the catalog, `tables` is guarded by the lock of `self.tables`.
+    fn refresh_system_tables(
+        &self,
+        tables: &HashMap<String, Arc<MemCatalogItem>>,
+    ) {
+        let mut system_tables = [
+            ("pg_class", PG_CLASS_OID, &*PG_CLASS_DESC),
+            ("pg_namespace", PG_NAMESPACE_OID, &*PG_NAMESPACE_DESC),
+            ("pg_attribute", PG_ATTRIBUTE_OID, &*PG_ATTRIBUTE_DESC),
+        ]
+        .into_iter()
+        .map(|(name, oid, desc)| {
+            let item = MemCatalogItem::SystemTable {
+                name: QualifiedObjectName {
+                    qualifiers: ObjectQualifiers {
+                        database: FLOPPY_DB_ID,
+                        schema: PG_CATALOG_SCHEMA_ID,
+                    },
+                    item: name.to_string(),
+                },
+                id: FIRST_SYSTEM_ID + GlobalId::from(oid),
+                oid,
+                desc: desc.clone(),
+                rows: vec![],
+            };
+            (name.to_string(), item)
+        })
+        .collect::<HashMap<_, _>>();
+
+        let mut items = tables
+            .values()
+            .map(|item| &**item)
+            .chain(system_tables.values())
+            .collect::<Vec<_>>();
+        items.sort_by_key(|item| item.oid());
+        let pg_class_rows = items.iter().copied().map(pg_class_row).collect();
+        let pg_attribute_rows =
+            items.iter().copied().flat_map(pg_attribute_rows).collect();
+        let pg_namespace_rows = [
+            (PG_CATALOG_SCHEMA_ID, PG_CATALOG_SCHEMA_NAME),
+            (FLOPPY_SCHEMA_ID, FLOPPY_SCHEMA_NAME),
+        ]
+        .into_iter()
+        .map(|(id, name)| {
+            Row::new(vec![
+                Datum::Int64(id.0 as i64),
+                Datum::Text(name.to_string()),
+                Datum::Int64(SUPERUSER_OID),
+            ])
+        })
+        .collect();
+
+        for (name, system_rows) in [
+            ("pg_class", pg_class_rows),
+            ("pg_namespace", pg_namespace_rows),
+            ("pg_attribute", pg_attribute_rows),
+        ] {
+            if let Some(MemCatalogItem::SystemTable { rows, .. }) =
+                system_tables.get_mut(name)
+            {
+                *rows = system_rows;
+            }
+        }
+        *self.system_tables.write().unwrap() = system_tables
+            .into_iter()
+            .map(|(name, item)| (name, Arc::new(item)))
+            .collect();
     }
 }
 
+/// The row of `item` in `pg_class`.
+fn pg_class_row(item: &MemCatalogItem) -> Row {
+    let relkind = match item.item_type() {
+        CatalogItemType::Table => "r",
+        CatalogItemType::Index => "i",
+    };
+    Row::new(vec![
+        Datum::Int64(item.oid().into()),
+        Datum::Text(item.name().item.clone()),
+        Datum::Int64(item.name().qualifiers.schema.0 as i64),
+        Datum::Text(relkind.to_string()),
+        Datum::Int64(SUPERUSER_OID),
+    ])
+}
+
+/// The rows of `item`'s columns in `pg_attribute`.
+fn pg_attribute_rows(item: &MemCatalogItem) -> Vec<Row> {
+    let desc = match item {
+        MemCatalogItem::Table { desc, .. }
+        | MemCatalogItem::SystemTable { desc, .. } => desc,
+    };
+    desc.iter()
+        .enumerate()
+        .map(|(i, (name, ty))| {
+            Row::new(vec![
+                Datum::Int64(item.oid().into()),
+                Datum::Text(name.clone()),
+                Datum::Int64(Type::from(&ty.scalar_type).oid().into()),
+                Datum::Int64(i as i64 + 1),
+                Datum::Boolean(!ty.nullable),
+            ])
+        })
+        .collect()
+}
+
 #[derive(Debug, Clone)]
 pub enum MemCatalogItem {
     Table {
@@ -62,23 +327,34 @@ pub enum MemCatalogItem {
         id: GlobalId,
         desc: RelationDesc,
     },
+    /// A read-only table in "pg_catalog".
+    SystemTable {
+        name: QualifiedObjectName,
+        id: GlobalId,
+        oid: u32,
+        desc: RelationDesc,
+        rows: Vec<Row>,
+    },
 }
 
 impl CatalogItem for MemCatalogItem {
     fn name(&self) -> &QualifiedObjectName {
         match &self {
-            Self::Table { name, .. } => name,
+            Self::Table { name, .. } | Self::SystemTable { name, .. } => name,
         }
     }
 
     fn id(&self) -> GlobalId {
         match &self {
-            Self::Table { id, .. } => *id,
+            Self::Table { id, .. } | Self::SystemTable { id, .. } => *id,
         }
     }
 
     fn oid(&self) -> u32 {
-        unimplemented!()
+        match &self {
+            Self::Table { id, .. } => FIRST_USER_OID + *id as u32,
+            Self::SystemTable { oid, .. } => *oid,
+        }
     }
 
     fn desc(
@@ -86,17 +362,283 @@ impl CatalogItem for MemCatalogItem {
         _: &FullObjectName,
     ) -> common::error::Result<Cow<RelationDesc>> {
         match &self {
-            Self::Table { desc, .. } => Ok(Cow::Borrowed(desc)),
+            Self::Table { desc, .. } | Self::SystemTable { desc, .. } => {
+                Ok(Cow::Borrowed(desc))
+            }
         }
     }
 
     fn item_type(&self) -> CatalogItemType {
         match &self {
-            Self::Table { .. } => CatalogItemType::Table,
+            Self::Table { .. } | Self::SystemTable { .. } => {
+                CatalogItemType::Table
+            }
         }
     }
 
     fn create_sql(&self) -> &str {
         unimplemented!()
     }
+
+    fn system_rows(&self) -> Option<&[Row]> {
+        match &self {
+            Self::Table { .. } => None,
+            Self::SystemTable { rows, .. } => Some(rows),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::names::PartialObjectName;
+    use crate::common::error::Result;
+    use std::collections::HashSet;
+
+    #[test]
+    fn pg_class() -> Result<()> {
+        let mut catalog = MemCatalog::default();
+        let desc = RelationDesc::new(
+            vec![ColumnType::new(ScalarType::Int64, false)],
+            vec!["c1".to_string()],
+            vec![0],
+            vec![],
+        );
+        catalog.insert_table("test", 1, desc);
+
+        let name = PartialObjectName {
+            database: None,
+            schema: Some(PG_CATALOG_SCHEMA_NAME.to_string()),
+            item: "pg_class".to_string(),
+        };
+        let pg_class = catalog.resolve_item(&name, &[])?;
+        let pg_class = catalog.get_item(&pg_class.id())?;
+        let rows = pg_class.system_rows().unwrap();
+        assert_eq!(
+            rows,
+            [
+                Row::new(vec![
+                    Datum::Int64(1249),
+                    Datum::Text("pg_attribute".to_string()),
+                    Datum::Int64(11),
+                    Datum::Text("r".to_string()),
+                    Datum::Int64(10),
+                ]),
+                Row::new(vec![
+                    Datum::Int64(1259),
+                    Datum::Text("pg_class".to_string()),
+                    Datum::Int64(11),
+                    Datum::Text("r".to_string()),
+                    Datum::Int64(10),
+                ]),
+                Row::new(vec![
+                    Datum::Int64(2615),
+                    Datum::Text("pg_namespace".to_string()),
+                    Datum::Int64(11),
+                    Datum::Text("r".to_string()),
+                    Datum::Int64(10),
+                ]),
+                Row::new(vec![
+                    Datum::Int64(16385),
+                    Datum::Text("test".to_string()),
+                    Datum::Int64(101),
+                    Datum::Text("r".to_string()),
+                    Datum::Int64(10),
+                ]),
+            ]
+        );
+
+        let name = PartialObjectName {
+            item: "pg_namespace".to_string(),
+            ..name
+        };
+        let pg_namespace = catalog.resolve_item(&name, &[])?;
+        assert_eq!(
+            pg_namespace.system_rows().unwrap(),
+            [
+                Row::new(vec![
+                    Datum::Int64(11),
+                    Datum::Text("pg_catalog".to_string()),
+                    Datum::Int64(10),
+                ]),
+                Row::new(vec![
+                    Datum::Int64(101),
+                    Datum::Text("public".to_string()),
+                    Datum::Int64(10),
+                ]),
+            ]
+        );
+
+        // a user table is not in "pg_catalog".
+        let name = PartialObjectName {
+            item: "test".to_string(),
+            ..name
+        };
+        assert!(catalog.resolve_item(&name, &[]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn pg_attribute() -> Result<()> {
+        let mut catalog = MemCatalog::default();
+        let desc = RelationDesc::new(
+            vec![
+                ColumnType::new(ScalarType::Int64, false),
+                ColumnType::new(ScalarType::Text, true),
+                ColumnType::new(ScalarType::Float64, true),
+            ],
+            vec!["c1".to_string(), "c2".to_string(), "c3".to_string()],
+            vec![0],
+            vec![],
+        );
+        catalog.insert_table("test", 1, desc);
+
+        let name = PartialObjectName {
+            database: None,
+            schema: Some(PG_CATALOG_SCHEMA_NAME.to_string()),
+            item: "pg_attribute".to_string(),
+        };
+        let pg_attribute = catalog.resolve_item(&name, &[])?;
+        let rows = pg_attribute.system_rows().unwrap();
+        let columns = rows
+            .iter()
+            .filter(|row| row.iter().next() == Some(&Datum::Int64(16385)))
+            .cloned()
+            .collect::<Vec<_>>();
+        let expected = [
+            ("c1", 20, 1, true),
+            ("c2", 25, 2, false),
+            ("c3", 701, 3, false),
+        ]
+        .into_iter()
+        .map(|(name, oid, num, notnull)| {
+            Row::new(vec![
+                Datum::Int64(16385),
+                Datum::Text(name.to_string()),
+                Datum::Int64(oid),
+                Datum::Int64(num),
+                Datum::Boolean(notnull),
+            ])
+        })
+        .collect::<Vec<_>>();
+        assert_eq!(columns, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn allocate_id() -> Result<()> {
+        let mut catalog = MemCatalog::default();
+        catalog.insert_table("t0", 2, RelationDesc::empty());
+        let mut ids = (1..=3)
+            .map(|i| {
+                catalog.create_table(&format!("t{i}"), RelationDesc::empty())
+            })
+            .collect::<Vec<_>>();
+        ids.push(catalog.allocate_id());
+        // the explicitly inserted ID is not reused.
+        assert_eq!(ids, [3, 4, 5, 6]);
+
+        let search_path = crate::catalog::default_search_path();
+        let mut items = (0..=3)
+            .map(|i| {
+                let name = PartialObjectName {
+                    database: None,
+                    schema: None,
+                    item: format!("t{i}"),
+                };
+                let item = catalog.resolve_item(&name, &search_path)?;
+                Ok((item.id(), item.oid()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        items.extend(
+            catalog
+                .system_tables
+                .read()
+                .unwrap()
+                .values()
+                .map(|item| (item.id(), item.oid())),
+        );
+        let ids = items.iter().map(|(id, _)| *id).collect::<HashSet<_>>();
+        let oids = items.iter().map(|(_, oid)| *oid).collect::<HashSet<_>>();
+        assert_eq!(ids.len(), items.len());
+        assert_eq!(oids.len(), items.len());
+        Ok(())
+    }
+
+    #[test]
+    fn search_path() -> Result<()> {
+        let mut catalog = MemCatalog::default();
+        catalog.insert_table("test", 1, RelationDesc::empty());
+        let search_path = crate::catalog::default_search_path();
+        let resolve = |schema: Option<&str>, item: &str| {
+            let name = PartialObjectName {
+                database: None,
+                schema: schema.map(|s| s.to_string()),
+                item: item.to_string(),
+            };
+            catalog
+                .resolve_item(&name, &search_path)
+                .map(|item| item.id())
+        };
+
+        assert_eq!(resolve(None, "test")?, 1);
+        assert_eq!(resolve(Some("public"), "test")?, 1);
+        assert!(resolve(Some("pg_catalog"), "test").is_err());
+        assert!(resolve(Some("other"), "test").is_err());
+
+        // a system table is only resolved when qualified.
+        assert!(resolve(None, "pg_class").is_err());
+        assert!(resolve(Some("pg_catalog"), "pg_class").is_ok());
+
+        // the schemas in the search path are tried in order.
+        let search_path = vec!["pg_catalog".to_string(), "public".to_string()];
+        let name = PartialObjectName {
+            database: None,
+            schema: None,
+            item: "pg_class".to_string(),
+        };
+        assert!(catalog.resolve_item(&name, &search_path).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn drop_table() -> Result<()> {
+        let mut catalog = MemCatalog::default();
+        catalog.insert_table("t1", 1, RelationDesc::empty());
+        catalog.insert_table("t2", 2, RelationDesc::empty());
+        let search_path = crate::catalog::default_search_path();
+        let resolve = |item: &str| {
+            let name = PartialObjectName {
+                database: None,
+                schema: None,
+                item: item.to_string(),
+            };
+            catalog
+                .resolve_item(&name, &search_path)
+                .map(|item| item.id())
+        };
+
+        catalog.drop_table(&1)?;
+        assert!(resolve("t1").is_err());
+        assert!(catalog.get_item(&1).is_err());
+        assert_eq!(resolve("t2")?, 2);
+        assert!(catalog.drop_table(&1).is_err());
+
+        // the dropped table is not in the system tables either.
+        let name = PartialObjectName {
+            database: None,
+            schema: Some(PG_CATALOG_SCHEMA_NAME.to_string()),
+            item: "pg_class".to_string(),
+        };
+        let pg_class = catalog.resolve_item(&name, &[])?;
+        let relnames = pg_class
+            .system_rows()
+            .unwrap()
+            .iter()
+            .map(|row| row.datums()[1].clone())
+            .collect::<Vec<_>>();
+        assert!(relnames.contains(&Datum::Text("t2".to_string())));
+        assert!(!relnames.contains(&Datum::Text("t1".to_string())));
+        Ok(())
+    }
 }
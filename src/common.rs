@@ -1,5 +1,7 @@
+pub mod datetime;
 pub mod error;
 pub mod ivec;
 pub mod operator;
+pub mod ordered_float;
 pub mod relation;
 pub mod scalar;
@@ -0,0 +1,657 @@
+//! Dates, timestamps and intervals.
+//!
+//! Like PostgreSQL, a date is the number of days and a timestamp is
+//! the number of microseconds since `2000-01-01 00:00:00`, in the
+//! proleptic Gregorian calendar. Their text form is ISO 8601 with a
+//! space between the date and the time, eg `2024-01-31 12:30:00.5`.
+//! An interval is a number of months, days and microseconds, whose
+//! text form is like `1 year 2 mons 3 days 04:05:06.5`.
+
+use crate::common::error::{FloppyError, Result};
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+
+/// The number of fractional digits of a second in a timestamp.
+pub const TIMESTAMP_PRECISION: u32 = 6;
+
+pub const USECS_PER_SEC: i64 = 1_000_000;
+pub const USECS_PER_DAY: i64 = 86_400 * USECS_PER_SEC;
+
+/// The days of a month when an interval is compared, as PostgreSQL.
+const DAYS_PER_MONTH: i64 = 30;
+
+/// The days from 1970-01-01, the epoch of [`days_from_civil`], to
+/// 2000-01-01.
+const POSTGRES_EPOCH_DAYS: i64 = 10_957;
+
+/// The days since 1970-01-01 of a date, see
+/// <http://howardhinnant.github.io/date_algorithms.html>.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let month = month as i64;
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5
+        + day as i64
+        - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// The reverse of [`days_from_civil`].
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let doe = days.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = era * 400 + yoe + i64::from(month <= 2);
+    (year, month, day)
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Parse a date in the form `YYYY-MM-DD`, returns the days since
+/// 2000-01-01.
+pub fn parse_date(s: &str) -> Result<i32> {
+    let syntax_error = || invalid_syntax("date", s);
+    let fields = s.trim().split('-').collect::<Vec<&str>>();
+    let [year, month, day] = fields[..] else {
+        return Err(syntax_error());
+    };
+    if year.len() < 4 {
+        return Err(syntax_error());
+    }
+    let year = parse_field::<i64>(year).ok_or_else(syntax_error)?;
+    let month = parse_field::<u32>(month).ok_or_else(syntax_error)?;
+    let day = parse_field::<u32>(day).ok_or_else(syntax_error)?;
+    if !(1..=9999).contains(&year)
+        || !(1..=12).contains(&month)
+        || day == 0
+        || day > days_in_month(year, month)
+    {
+        return Err(out_of_range(s));
+    }
+    Ok((days_from_civil(year, month, day) - POSTGRES_EPOCH_DAYS) as i32)
+}
+
+/// Parse a timestamp in the form `YYYY-MM-DD[ HH:MM[:SS[.FFFFFF]]]`,
+/// the date and the time may also be separated by `T`. Returns the
+/// microseconds since 2000-01-01. The fraction of a second is rounded
+/// to [`TIMESTAMP_PRECISION`] digits.
+pub fn parse_timestamp(s: &str) -> Result<i64> {
+    let syntax_error = || invalid_syntax("timestamp", s);
+    let trimmed = s.trim();
+    let (date, time) = match trimmed.split_once([' ', 'T']) {
+        Some((date, time)) => (date, time.trim_start()),
+        None => (trimmed, "00:00"),
+    };
+    let days = parse_date(date).map_err(|e| match e {
+        FloppyError::InvalidDatetimeFormat(_) => syntax_error(),
+        _ => out_of_range(s),
+    })?;
+
+    let (time, fraction) = time.split_once('.').unwrap_or((time, ""));
+    let fields = time.split(':').collect::<Vec<&str>>();
+    let (hour, minute, second) = match fields[..] {
+        [hour, minute] => (hour, minute, "0"),
+        [hour, minute, second] => (hour, minute, second),
+        _ => return Err(syntax_error()),
+    };
+    if fields.len() == 2 && !fraction.is_empty() {
+        return Err(syntax_error());
+    }
+    let hour = parse_field::<i64>(hour).ok_or_else(syntax_error)?;
+    let minute = parse_field::<i64>(minute).ok_or_else(syntax_error)?;
+    let second = parse_field::<i64>(second).ok_or_else(syntax_error)?;
+    if hour > 23 || minute > 59 || second > 59 {
+        return Err(out_of_range(s));
+    }
+    let usecs = if fraction.is_empty() {
+        0
+    } else {
+        parse_fraction(fraction).ok_or_else(syntax_error)?
+    };
+
+    let secs = (days as i64 * 24 + hour) * 3600 + minute * 60 + second;
+    Ok(secs * USECS_PER_SEC + usecs)
+}
+
+/// A span of time. The months, days and microseconds are kept apart,
+/// as the length of a month or a day in a timestamp varies.
+///
+/// Like PostgreSQL, intervals are equal, hashed and ordered by their
+/// [`Interval::span`] with 30-day months and 24-hour days, so `1 mon`
+/// is equal to `30 days` and `24 hours` is equal to `1 day`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Interval {
+    pub months: i32,
+    pub days: i32,
+    pub micros: i64,
+}
+
+impl Interval {
+    /// The total microseconds of the interval, with 30-day months.
+    pub fn span(&self) -> i128 {
+        let days = self.months as i64 * DAYS_PER_MONTH + self.days as i64;
+        days as i128 * USECS_PER_DAY as i128 + self.micros as i128
+    }
+
+    pub fn checked_add(&self, other: &Interval) -> Result<Interval> {
+        let add = || {
+            Some(Interval {
+                months: self.months.checked_add(other.months)?,
+                days: self.days.checked_add(other.days)?,
+                micros: self.micros.checked_add(other.micros)?,
+            })
+        };
+        add().ok_or_else(interval_out_of_range)
+    }
+
+    pub fn checked_neg(&self) -> Result<Interval> {
+        let neg = || {
+            Some(Interval {
+                months: self.months.checked_neg()?,
+                days: self.days.checked_neg()?,
+                micros: self.micros.checked_neg()?,
+            })
+        };
+        neg().ok_or_else(interval_out_of_range)
+    }
+
+    pub fn checked_sub(&self, other: &Interval) -> Result<Interval> {
+        self.checked_add(&other.checked_neg()?)
+    }
+}
+
+impl PartialEq for Interval {
+    fn eq(&self, other: &Self) -> bool {
+        self.span() == other.span()
+    }
+}
+
+impl Eq for Interval {}
+
+impl Hash for Interval {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.span().hash(state)
+    }
+}
+
+impl Ord for Interval {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.span().cmp(&other.span())
+    }
+}
+
+impl PartialOrd for Interval {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Parse an interval of quantities and units, eg `1 day 2 hours`,
+/// optionally followed by a time `[-]HH:MM[:SS[.FFFFFF]]` and `ago`,
+/// which negates the interval. A quantity without a unit is seconds.
+/// A fraction of a unit larger than a day is carried into the smaller
+/// units, eg `1.5 months` is `1 mon 15 days`.
+pub fn parse_interval(s: &str) -> Result<Interval> {
+    let syntax_error = || invalid_syntax("interval", s);
+    let out_of_range = || {
+        FloppyError::DatetimeFieldOverflow(format!(
+            "interval field value out of range: \"{s}\""
+        ))
+    };
+    let mut tokens = s
+        .trim()
+        .trim_start_matches('@')
+        .split_whitespace()
+        .peekable();
+    if tokens.peek().is_none() {
+        return Err(syntax_error());
+    }
+    let (mut months, mut days, mut micros) = (0i128, 0i128, 0i128);
+    let mut ago = false;
+    while let Some(token) = tokens.next() {
+        if ago {
+            return Err(syntax_error());
+        }
+        if token.eq_ignore_ascii_case("ago") {
+            ago = true;
+            continue;
+        }
+        if token.contains(':') {
+            micros += parse_interval_time(token).ok_or_else(syntax_error)?;
+            continue;
+        }
+        if !token
+            .bytes()
+            .all(|b| b.is_ascii_digit() || matches!(b, b'+' | b'-' | b'.'))
+        {
+            return Err(syntax_error());
+        }
+        let value = token.parse::<f64>().map_err(|_| syntax_error())?;
+        // a quantity followed by a time is days, eg `1 12:00`.
+        let unit = match tokens.peek() {
+            Some(t) if t.contains(':') => "day".to_string(),
+            Some(t) if !t.eq_ignore_ascii_case("ago") => {
+                tokens.next().unwrap().to_lowercase()
+            }
+            _ => "second".to_string(),
+        };
+        let (unit_months, unit_days, unit_micros) = match unit.as_str() {
+            "microsecond" | "microseconds" | "us" | "usec" | "usecs" => {
+                (0, 0, 1)
+            }
+            "millisecond" | "milliseconds" | "ms" | "msec" | "msecs" => {
+                (0, 0, 1_000)
+            }
+            "second" | "seconds" | "s" | "sec" | "secs" => {
+                (0, 0, USECS_PER_SEC)
+            }
+            "minute" | "minutes" | "m" | "min" | "mins" => {
+                (0, 0, 60 * USECS_PER_SEC)
+            }
+            "hour" | "hours" | "h" | "hr" | "hrs" => {
+                (0, 0, 3600 * USECS_PER_SEC)
+            }
+            "day" | "days" | "d" => (0, 1, 0),
+            "week" | "weeks" | "w" => (0, 7, 0),
+            "month" | "months" | "mon" | "mons" => (1, 0, 0),
+            "year" | "years" | "y" | "yr" | "yrs" => (12, 0, 0),
+            _ => return Err(syntax_error()),
+        };
+        // only one of the units is not zero.
+        let value = value * (unit_months + unit_days + unit_micros) as f64;
+        if !value.is_finite() || value.abs() > i64::MAX as f64 {
+            return Err(out_of_range());
+        }
+        let usecs = |fraction: f64| (fraction * USECS_PER_DAY as f64).round();
+        if unit_micros != 0 {
+            micros += value.round() as i128;
+        } else if unit_days != 0 {
+            days += value.trunc() as i128;
+            micros += usecs(value.fract()) as i128;
+        } else if unit_months == 12 {
+            // like PostgreSQL, a fraction of a year is whole months.
+            months += value.trunc() as i128;
+        } else {
+            months += value.trunc() as i128;
+            let value = value.fract() * DAYS_PER_MONTH as f64;
+            days += value.trunc() as i128;
+            micros += usecs(value.fract()) as i128;
+        }
+    }
+
+    let interval = Interval {
+        months: months.try_into().map_err(|_| out_of_range())?,
+        days: days.try_into().map_err(|_| out_of_range())?,
+        micros: micros.try_into().map_err(|_| out_of_range())?,
+    };
+    if ago {
+        interval.checked_neg().map_err(|_| out_of_range())
+    } else {
+        Ok(interval)
+    }
+}
+
+/// The microseconds of the time `[-]HH:MM[:SS[.FFFFFF]]` of an
+/// interval, the hours are not limited to a day.
+fn parse_interval_time(time: &str) -> Option<i128> {
+    let (negative, time) = match time.strip_prefix('-') {
+        Some(time) => (true, time),
+        None => (false, time.strip_prefix('+').unwrap_or(time)),
+    };
+    let (time, fraction) = time.split_once('.').unwrap_or((time, ""));
+    let fields = time.split(':').collect::<Vec<&str>>();
+    let (hour, minute, second) = match fields[..] {
+        [hour, minute] if fraction.is_empty() => (hour, minute, "0"),
+        [hour, minute, second] => (hour, minute, second),
+        _ => return None,
+    };
+    let hour = parse_field::<i64>(hour)?;
+    let minute = parse_field::<i64>(minute)?;
+    let second = parse_field::<i64>(second)?;
+    if minute > 59 || second > 59 {
+        return None;
+    }
+    let usecs = if fraction.is_empty() {
+        0
+    } else {
+        parse_fraction(fraction)?
+    };
+    let secs = hour as i128 * 3600 + minute as i128 * 60 + second as i128;
+    let usecs = secs * USECS_PER_SEC as i128 + usecs as i128;
+    Some(if negative { -usecs } else { usecs })
+}
+
+/// The microseconds of the fraction of a second, rounded half up to
+/// [`TIMESTAMP_PRECISION`] digits. A rounded up `.9999995` is a whole
+/// second.
+fn parse_fraction(fraction: &str) -> Option<i64> {
+    if !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let precision = TIMESTAMP_PRECISION as usize;
+    let digits = format!("{fraction:0<precision$}");
+    let usecs = digits[..precision].parse::<i64>().ok()?;
+    let round_up =
+        matches!(digits.as_bytes().get(precision), Some(b'5'..=b'9'));
+    Some(usecs + i64::from(round_up))
+}
+
+/// A field of digits only, unlike [`str::parse`] a sign is not
+/// allowed.
+fn parse_field<T: std::str::FromStr>(field: &str) -> Option<T> {
+    if field.is_empty() || !field.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    field.parse().ok()
+}
+
+/// Format the days since 2000-01-01 as `YYYY-MM-DD`, with a ` BC`
+/// suffix for the years before 1 AD.
+pub fn format_date(days: i32) -> String {
+    let (year, month, day) = civil_from_days(days as i64 + POSTGRES_EPOCH_DAYS);
+    if year > 0 {
+        format!("{year:04}-{month:02}-{day:02}")
+    } else {
+        format!("{:04}-{month:02}-{day:02} BC", 1 - year)
+    }
+}
+
+/// Format the microseconds since 2000-01-01 as
+/// `YYYY-MM-DD HH:MM:SS[.FFFFFF]`, the trailing zeros of the fraction
+/// are omitted as PostgreSQL.
+pub fn format_timestamp(usecs: i64) -> String {
+    let days = usecs.div_euclid(USECS_PER_DAY);
+    let time = usecs.rem_euclid(USECS_PER_DAY);
+    let (secs, fraction) = (time / USECS_PER_SEC, time % USECS_PER_SEC);
+    let mut date = format_date(days as i32);
+    let bc = date.ends_with(" BC");
+    if bc {
+        date.truncate(date.len() - 3);
+    }
+
+    let mut s = format!(
+        "{date} {:02}:{:02}:{:02}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    );
+    if fraction != 0 {
+        let fraction = format!("{fraction:06}");
+        s.push('.');
+        s.push_str(fraction.trim_end_matches('0'));
+    }
+    if bc {
+        s.push_str(" BC");
+    }
+    s
+}
+
+/// Format an interval as PostgreSQL, eg `1 year 2 mons -3 days
+/// +04:05:06.5`. A positive field after a negative one has an explicit
+/// `+`, the time is omitted if it is zero, unless all the fields are.
+pub fn format_interval(interval: &Interval) -> String {
+    let mut fields = vec![];
+    let mut negative = false;
+    let years = interval.months / 12;
+    let months = interval.months % 12;
+    for (value, unit) in [
+        (years as i64, "year"),
+        (months as i64, "mon"),
+        (interval.days as i64, "day"),
+    ] {
+        if value == 0 {
+            continue;
+        }
+        let sign = if negative && value > 0 { "+" } else { "" };
+        let plural = if value == 1 { "" } else { "s" };
+        fields.push(format!("{sign}{value} {unit}{plural}"));
+        negative |= value < 0;
+    }
+    let micros = interval.micros;
+    if micros != 0 || fields.is_empty() {
+        let sign = match micros {
+            m if m < 0 => "-",
+            _ if negative => "+",
+            _ => "",
+        };
+        let usecs = micros.unsigned_abs();
+        let secs = usecs / USECS_PER_SEC as u64;
+        let fraction = usecs % USECS_PER_SEC as u64;
+        let mut time = format!(
+            "{sign}{:02}:{:02}:{:02}",
+            secs / 3600,
+            secs / 60 % 60,
+            secs % 60
+        );
+        if fraction != 0 {
+            let fraction = format!("{fraction:06}");
+            time.push('.');
+            time.push_str(fraction.trim_end_matches('0'));
+        }
+        fields.push(time);
+    }
+    fields.join(" ")
+}
+
+/// Add an interval to the microseconds since 2000-01-01. Like
+/// PostgreSQL, the months are added first, and the day is clamped to
+/// the last day of the month, eg `2024-01-31 + 1 mon` is `2024-02-29`,
+/// then the days and the microseconds.
+pub fn add_interval(usecs: i64, interval: &Interval) -> Result<i64> {
+    let out_of_range =
+        || FloppyError::DatetimeFieldOverflow("timestamp out of range".into());
+    let mut usecs = usecs;
+    if interval.months != 0 {
+        let days = usecs.div_euclid(USECS_PER_DAY);
+        let time = usecs.rem_euclid(USECS_PER_DAY);
+        let (year, month, day) = civil_from_days(days + POSTGRES_EPOCH_DAYS);
+        let months = year * 12 + month as i64 - 1 + interval.months as i64;
+        let (year, month) = (months.div_euclid(12), months.rem_euclid(12) + 1);
+        let day = day.min(days_in_month(year, month as u32));
+        let days =
+            days_from_civil(year, month as u32, day) - POSTGRES_EPOCH_DAYS;
+        usecs = days
+            .checked_mul(USECS_PER_DAY)
+            .and_then(|u| u.checked_add(time))
+            .ok_or_else(out_of_range)?;
+    }
+    (interval.days as i64)
+        .checked_mul(USECS_PER_DAY)
+        .and_then(|u| usecs.checked_add(u))
+        .and_then(|u| u.checked_add(interval.micros))
+        .ok_or_else(out_of_range)
+}
+
+fn invalid_syntax(ty: &str, s: &str) -> FloppyError {
+    FloppyError::InvalidDatetimeFormat(format!(
+        "invalid input syntax for type {ty}: \"{s}\""
+    ))
+}
+
+fn interval_out_of_range() -> FloppyError {
+    FloppyError::DatetimeFieldOverflow("interval out of range".to_string())
+}
+
+fn out_of_range(s: &str) -> FloppyError {
+    FloppyError::DatetimeFieldOverflow(format!(
+        "date/time field value out of range: \"{s}\""
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn date_round_trip() -> Result<()> {
+        let tests = [
+            ("2000-01-01", 0),
+            ("1999-12-31", -1),
+            ("2000-03-01", 60),
+            ("1970-01-01", -10957),
+            ("2024-02-29", 8825),
+            ("0001-01-01", -730119),
+            ("9999-12-31", 2921939),
+        ];
+        for (s, days) in tests {
+            assert_eq!(parse_date(s)?, days, "{s}");
+            assert_eq!(format_date(days), s);
+        }
+        assert_eq!(parse_date(" 2024-1-2 ")?, parse_date("2024-01-02")?);
+        assert_eq!(format_date(-730120), "0001-12-31 BC");
+
+        for s in ["2024-01", "24-01-01", "2024/01/01", "2024-01-+1", ""] {
+            let e = parse_date(s).unwrap_err();
+            assert_eq!(e.code(), "22007", "{s}");
+        }
+        for s in ["2023-02-29", "2024-13-01", "2024-04-31", "0000-01-01"] {
+            let e = parse_date(s).unwrap_err();
+            assert_eq!(e.code(), "22008", "{s}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn timestamp_round_trip() -> Result<()> {
+        let tests = [
+            ("2000-01-01 00:00:00", 0),
+            ("1999-12-31 23:59:59.999999", -1),
+            ("2000-01-02 01:02:03.5", USECS_PER_DAY + 3_723_500_000),
+            ("1970-01-01 00:00:00", -10957 * USECS_PER_DAY),
+        ];
+        for (s, usecs) in tests {
+            assert_eq!(parse_timestamp(s)?, usecs, "{s}");
+            assert_eq!(format_timestamp(usecs), s);
+        }
+
+        let tests = [
+            ("2000-01-01", "2000-01-01 00:00:00"),
+            ("2000-01-01T12:30", "2000-01-01 12:30:00"),
+            ("2000-01-01  12:30:01.250", "2000-01-01 12:30:01.25"),
+            // rounded to microseconds.
+            ("2000-01-01 00:00:00.0000005", "2000-01-01 00:00:00.000001"),
+            ("2000-01-01 23:59:59.9999999", "2000-01-02 00:00:00"),
+            ("0001-01-01 00:00:00", "0001-01-01 00:00:00"),
+        ];
+        for (s, formatted) in tests {
+            assert_eq!(format_timestamp(parse_timestamp(s)?), formatted);
+        }
+        assert_eq!(
+            format_timestamp(-730120 * USECS_PER_DAY + 1),
+            "0001-12-31 00:00:00.000001 BC"
+        );
+
+        for s in ["2000-01-01 12", "2000-01-01 1:2:3:4", "2000-01-01 12:00+08"]
+        {
+            let e = parse_timestamp(s).unwrap_err();
+            assert_eq!(e.code(), "22007", "{s}");
+        }
+        for s in ["2000-01-01 24:00:00", "2000-01-01 00:60", "2000-02-30"] {
+            let e = parse_timestamp(s).unwrap_err();
+            assert_eq!(e.code(), "22008", "{s}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn interval_round_trip() -> Result<()> {
+        let interval = |months, days, micros| Interval {
+            months,
+            days,
+            micros,
+        };
+        let tests = [
+            ("00:00:00", interval(0, 0, 0)),
+            ("1 day", interval(0, 1, 0)),
+            (
+                "1 year 2 mons 3 days 04:05:06.5",
+                interval(14, 3, 14_706_500_000),
+            ),
+            ("-1 years -2 mons", interval(-14, 0, 0)),
+            ("-1 mons +1 day", interval(-1, 1, 0)),
+            ("1 day -00:00:01", interval(0, 1, -USECS_PER_SEC)),
+            ("-2 days +25:00:00", interval(0, -2, 90_000 * USECS_PER_SEC)),
+        ];
+        for (s, interval) in tests {
+            // the fields are compared, `Eq` is by the span.
+            let parsed = parse_interval(s)?;
+            assert_eq!(format!("{parsed:?}"), format!("{interval:?}"), "{s}");
+            assert_eq!(format_interval(&interval), s);
+        }
+
+        let tests = [
+            ("1 day", "1 day"),
+            ("@ 2 DAYS 3 hours", "2 days 03:00:00"),
+            ("1 week 90 minutes", "7 days 01:30:00"),
+            ("1.5 months", "1 mon 15 days"),
+            ("1.5 years", "1 year 6 mons"),
+            ("0.5 day", "12:00:00"),
+            ("1 12:00", "1 day 12:00:00"),
+            ("10", "00:00:10"),
+            ("1 day 1 hour ago", "-1 days -01:00:00"),
+            ("250 ms", "00:00:00.25"),
+        ];
+        for (s, formatted) in tests {
+            assert_eq!(format_interval(&parse_interval(s)?), formatted, "{s}");
+        }
+
+        for s in ["", "day", "1 fortnight", "1 day ago 2", "1:2:3:4", "1 nan"] {
+            let e = parse_interval(s).unwrap_err();
+            assert_eq!(e.code(), "22007", "{s}");
+        }
+        let e = parse_interval("3000000000 days").unwrap_err();
+        assert_eq!(e.code(), "22008");
+
+        assert!(parse_interval("1 mon")? < parse_interval("31 days")?);
+        assert_eq!(parse_interval("1 mon")?, parse_interval("30 days")?);
+        assert_eq!(parse_interval("24 hours")?, parse_interval("1 day")?);
+        assert!(parse_interval("23 hours")? < parse_interval("1 day")?);
+        Ok(())
+    }
+
+    #[test]
+    fn timestamp_add_interval() -> Result<()> {
+        let tests = [
+            ("2024-01-31 00:00:00", "1 mon", "2024-02-29 00:00:00"),
+            ("2023-01-31 12:00:00", "1 mon", "2023-02-28 12:00:00"),
+            ("2024-02-29 00:00:00", "1 year", "2025-02-28 00:00:00"),
+            ("2024-03-31 00:00:00", "-1 mon", "2024-02-29 00:00:00"),
+            (
+                "2024-01-01 00:00:00",
+                "1 day 01:30:00",
+                "2024-01-02 01:30:00",
+            ),
+            ("2000-01-01 00:00:00", "-1 day", "1999-12-31 00:00:00"),
+            ("2024-12-31 23:00:00", "2 hours", "2025-01-01 01:00:00"),
+            // the months are added before the days.
+            ("2024-01-31 00:00:00", "1 mon 1 day", "2024-03-01 00:00:00"),
+        ];
+        for (ts, interval, expected) in tests {
+            let usecs =
+                add_interval(parse_timestamp(ts)?, &parse_interval(interval)?)?;
+            assert_eq!(format_timestamp(usecs), expected, "{ts} + {interval}");
+        }
+
+        let e = add_interval(i64::MAX, &parse_interval("1 day")?).unwrap_err();
+        assert_eq!(e.code(), "22008");
+        Ok(())
+    }
+}
@@ -1,4 +1,4 @@
-use crate::common::relation::{GlobalId, RelationDesc};
+use crate::common::relation::{GlobalId, IndexKeyDatums, RelationDesc};
 use rust_decimal;
 use sqlparser::parser::ParserError;
 use std::alloc::LayoutError;
@@ -26,10 +26,43 @@ pub enum FloppyError {
     Catalog(CatalogError),
     /// Expression evaluation error
     EvalExpr(String),
+    /// A numeric value, eg the result of an arithmetic
+    /// operation, is out of the range of its type.
+    NumericOutOfRange(String),
+    DivisionByZero,
+    /// A subquery used as an expression returns more than one row.
+    CardinalityViolation,
+    /// The text form of a value is malformed.
+    InvalidTextRepresentation(String),
+    /// A date or timestamp in text is malformed.
+    InvalidDatetimeFormat(String),
+    /// A field of a date or timestamp, eg the month, is out of range.
+    DatetimeFieldOverflow(String),
+    /// A NULL is written to the column, which is not nullable.
+    NotNullViolation(String),
+    /// A row is written with the primary key of another row.
+    UniqueViolation(String),
+    /// The data of `COPY FROM` is malformed.
+    BadCopyFormat(String),
+    /// `COPY FROM STDIN` is aborted by the client with `CopyFail`.
+    CopyFailed(String),
     DC(DCError),
     Storage(String),
     Io(std::io::Error),
     ExecuteReturnedResults,
+    /// A malformed message of PostgreSQL's frontend/backend
+    /// protocol.
+    Protocol(String),
+    /// A statement other than `COMMIT` or `ROLLBACK` is executed in
+    /// a failed transaction.
+    TransactionAborted,
+    /// The type of the parameter `param` of a prepared statement,
+    /// which is specified by the client with its OID, is not
+    /// supported. `param` starts from 1, like `$1`.
+    UnsupportedParamType {
+        param: usize,
+        oid: u32,
+    },
     /// Errors originating from outside Floppy's codebase.
     External(String),
 }
@@ -43,6 +76,12 @@ pub enum CatalogError {
         name: String,
         valid_fields: Option<Vec<String>>,
     },
+    /// More than one column matches a column reference, eg `c1` of
+    /// two joined tables.
+    AmbiguousColumn(String),
+    /// More than one relation in `FROM` has the same name, eg a table
+    /// joined with itself without an alias.
+    DuplicateAlias(String),
 }
 
 #[derive(Debug)]
@@ -78,6 +117,17 @@ pub fn table_not_found_in_storage(table_id: GlobalId) -> FloppyError {
     FloppyError::Storage(format!("table not found in storage: {table_id}"))
 }
 
+/// Create a [`FloppyError::UniqueViolation`] of the primary `key`,
+/// which is formatted like `(1, a)`.
+pub fn unique_violation(key: &IndexKeyDatums) -> FloppyError {
+    let datums = key
+        .datums()
+        .iter()
+        .map(|d| d.to_string())
+        .collect::<Vec<String>>();
+    FloppyError::UniqueViolation(format!("({})", datums.join(", ")))
+}
+
 impl std::fmt::Display for CatalogError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -108,6 +158,106 @@ impl std::fmt::Display for CatalogError {
                 }
                 write!(f, ".")
             }
+            Self::AmbiguousColumn(name) => {
+                write!(f, "column reference \"{name}\" is ambiguous")
+            }
+            Self::DuplicateAlias(name) => {
+                write!(f, "table name \"{name}\" specified more than once")
+            }
+        }
+    }
+}
+
+impl FloppyError {
+    /// The SQLSTATE code of the error, see PostgreSQL's
+    /// `errcodes.txt`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            FloppyError::NotImplemented(_) => "0A000",
+            FloppyError::Internal(_) => "XX000",
+            FloppyError::Parser(_) => "42601",
+            FloppyError::Plan(_) => "42000",
+            FloppyError::Catalog(CatalogError::TableNotFound(_)) => "42P01",
+            FloppyError::Catalog(CatalogError::ColumnNotFound { .. }) => {
+                "42703"
+            }
+            FloppyError::Catalog(CatalogError::AmbiguousColumn(_)) => "42702",
+            FloppyError::Catalog(CatalogError::DuplicateAlias(_)) => "42712",
+            FloppyError::EvalExpr(_) => "22000",
+            FloppyError::NumericOutOfRange(_) => "22003",
+            FloppyError::DivisionByZero => "22012",
+            FloppyError::CardinalityViolation => "21000",
+            FloppyError::InvalidTextRepresentation(_) => "22P02",
+            FloppyError::InvalidDatetimeFormat(_) => "22007",
+            FloppyError::DatetimeFieldOverflow(_) => "22008",
+            FloppyError::NotNullViolation(_) => "23502",
+            FloppyError::UniqueViolation(_) => "23505",
+            FloppyError::BadCopyFormat(_) => "22P04",
+            FloppyError::CopyFailed(_) => "57014",
+            FloppyError::DC(DCError::KeyAlreadyExists(_)) => "23505",
+            FloppyError::DC(_) => "XX000",
+            FloppyError::Storage(_) => "XX000",
+            FloppyError::Io(_) => "58030",
+            FloppyError::ExecuteReturnedResults => "XX000",
+            FloppyError::Protocol(_) => "08P01",
+            FloppyError::TransactionAborted => "25P02",
+            FloppyError::UnsupportedParamType { .. } => "0A000",
+            FloppyError::External(_) => "XX000",
+        }
+    }
+
+    /// The message reported to the client.
+    pub fn message(&self) -> String {
+        match self {
+            FloppyError::Plan(desc)
+            | FloppyError::EvalExpr(desc)
+            | FloppyError::NumericOutOfRange(desc)
+            | FloppyError::InvalidTextRepresentation(desc)
+            | FloppyError::InvalidDatetimeFormat(desc)
+            | FloppyError::DatetimeFieldOverflow(desc)
+            | FloppyError::BadCopyFormat(desc)
+            | FloppyError::Protocol(desc) => desc.clone(),
+            FloppyError::Parser(e) => match e {
+                ParserError::TokenizerError(desc)
+                | ParserError::ParserError(desc) => desc.clone(),
+            },
+            FloppyError::Catalog(CatalogError::TableNotFound(desc)) => {
+                desc.clone()
+            }
+            FloppyError::Catalog(CatalogError::ColumnNotFound {
+                qualifier,
+                name,
+                ..
+            }) => match qualifier {
+                Some(q) => format!("column \"{q}.{name}\" does not exist"),
+                None => format!("column \"{name}\" does not exist"),
+            },
+            FloppyError::Catalog(
+                e @ (CatalogError::AmbiguousColumn(_)
+                | CatalogError::DuplicateAlias(_)),
+            ) => e.to_string(),
+            e => e.to_string(),
+        }
+    }
+
+    /// An optional suggestion to fix the error.
+    pub fn hint(&self) -> Option<String> {
+        match self {
+            FloppyError::Catalog(CatalogError::ColumnNotFound {
+                valid_fields: Some(fields),
+                ..
+            }) => Some(format!(
+                "Valid fields are {}.",
+                fields
+                    .iter()
+                    .map(|name| format!("'{name}'"))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            )),
+            FloppyError::TransactionAborted => {
+                Some("Execute ROLLBACK to end the transaction.".to_string())
+            }
+            _ => None,
         }
     }
 }
@@ -136,6 +286,12 @@ impl From<rust_decimal::Error> for FloppyError {
     }
 }
 
+impl From<tokio_rustls::rustls::Error> for FloppyError {
+    fn from(e: tokio_rustls::rustls::Error) -> Self {
+        FloppyError::External(e.to_string())
+    }
+}
+
 impl From<LayoutError> for FloppyError {
     fn from(e: LayoutError) -> Self {
         FloppyError::External(e.to_string())
@@ -160,6 +316,34 @@ impl std::fmt::Display for FloppyError {
             FloppyError::EvalExpr(desc) => {
                 write!(f, "Expression evaluation error: {desc}")
             }
+            FloppyError::NumericOutOfRange(desc) => {
+                write!(f, "Numeric value out of range: {desc}")
+            }
+            FloppyError::DivisionByZero => write!(f, "division by zero"),
+            FloppyError::CardinalityViolation => write!(
+                f,
+                "more than one row returned by a subquery used as an \
+                 expression"
+            ),
+            FloppyError::InvalidTextRepresentation(desc)
+            | FloppyError::InvalidDatetimeFormat(desc)
+            | FloppyError::DatetimeFieldOverflow(desc) => write!(f, "{desc}"),
+            FloppyError::NotNullViolation(column) => write!(
+                f,
+                "null value in column \"{column}\" violates not-null \
+                 constraint"
+            ),
+            FloppyError::UniqueViolation(key) => write!(
+                f,
+                "duplicate key value violates primary key constraint, \
+                 key {key} already exists"
+            ),
+            FloppyError::BadCopyFormat(desc) => {
+                write!(f, "Bad COPY format: {desc}")
+            }
+            FloppyError::CopyFailed(message) => {
+                write!(f, "COPY from stdin failed: {message}")
+            }
             FloppyError::Storage(desc) => write!(f, "Storage error: {desc}"),
             FloppyError::DC(e) => write!(f, "DC error: {e:?}"),
             FloppyError::Catalog(e) => {
@@ -174,9 +358,84 @@ impl std::fmt::Display for FloppyError {
             FloppyError::ExecuteReturnedResults => {
                 write!(f, "Execute returned results")
             }
+            FloppyError::Protocol(desc) => {
+                write!(f, "Protocol error: {desc}")
+            }
+            FloppyError::TransactionAborted => write!(
+                f,
+                "current transaction is aborted, commands ignored until end \
+                 of transaction block"
+            ),
+            FloppyError::UnsupportedParamType { param, oid } => {
+                write!(f, "unsupported type OID {oid} of parameter ${param}")
+            }
             FloppyError::External(e) => {
                 write!(f, "external error: {e}")
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::relation::ColumnType;
+    use crate::common::scalar::ScalarType;
+
+    #[test]
+    fn sqlstate() {
+        let desc = RelationDesc::new(
+            vec![ColumnType::new(ScalarType::Int64, false)],
+            vec!["c1".to_string()],
+            vec![0],
+            vec![],
+        );
+        let column_not_found = field_not_found(None, "c2", &desc);
+        assert_eq!(column_not_found.code(), "42703");
+        assert_eq!(column_not_found.message(), "column \"c2\" does not exist");
+        assert_eq!(
+            column_not_found.hint().as_deref(),
+            Some("Valid fields are 'c1'.")
+        );
+
+        let table_not_found = table_not_found_in_catalog("t");
+        assert_eq!(table_not_found.code(), "42P01");
+        assert_eq!(table_not_found.hint(), None);
+
+        let syntax = FloppyError::Parser(ParserError::ParserError(
+            "Expected end of statement".to_string(),
+        ));
+        assert_eq!(syntax.code(), "42601");
+        assert_eq!(syntax.message(), "Expected end of statement");
+
+        let internal = FloppyError::Internal("oops".to_string());
+        assert_eq!(internal.code(), "XX000");
+        assert_eq!(internal.message(), internal.to_string());
+
+        let duplicate =
+            FloppyError::DC(DCError::KeyAlreadyExists("k".to_string()));
+        assert_eq!(duplicate.code(), "23505");
+        let unique = FloppyError::UniqueViolation("(1)".to_string());
+        assert_eq!(unique.code(), "23505");
+        assert_eq!(
+            unique.message(),
+            "duplicate key value violates primary key constraint, key (1) \
+             already exists"
+        );
+        assert_eq!(FloppyError::TransactionAborted.code(), "25P02");
+        assert_eq!(
+            FloppyError::NotImplemented("x".to_string()).code(),
+            "0A000"
+        );
+
+        let param_type = FloppyError::UnsupportedParamType {
+            param: 2,
+            oid: 1083,
+        };
+        assert_eq!(param_type.code(), "0A000");
+        assert_eq!(
+            param_type.message(),
+            "unsupported type OID 1083 of parameter $2"
+        );
+    }
+}
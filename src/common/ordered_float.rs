@@ -0,0 +1,87 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+/// A floating point number with a total order, so it can be held by
+/// [`Datum`](crate::common::scalar::Datum).
+///
+/// Same as PostgreSQL, all the NaNs are equal and greater than any
+/// other value, and `-0.0` equals `0.0`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OrderedFloat<T>(pub T);
+
+macro_rules! impl_ordered_float {
+    ($t:ty) => {
+        impl OrderedFloat<$t> {
+            /// The value with a single representation of NaN and zero.
+            pub fn canonical(self) -> $t {
+                if self.0.is_nan() {
+                    <$t>::NAN
+                } else if self.0 == 0.0 {
+                    0.0
+                } else {
+                    self.0
+                }
+            }
+        }
+
+        impl PartialEq for OrderedFloat<$t> {
+            fn eq(&self, other: &Self) -> bool {
+                self.cmp(other) == Ordering::Equal
+            }
+        }
+
+        impl Eq for OrderedFloat<$t> {}
+
+        impl PartialOrd for OrderedFloat<$t> {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl Ord for OrderedFloat<$t> {
+            fn cmp(&self, other: &Self) -> Ordering {
+                self.canonical().total_cmp(&other.canonical())
+            }
+        }
+
+        impl Hash for OrderedFloat<$t> {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                self.canonical().to_bits().hash(state)
+            }
+        }
+
+        impl fmt::Display for OrderedFloat<$t> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+
+        impl From<$t> for OrderedFloat<$t> {
+            fn from(v: $t) -> Self {
+                Self(v)
+            }
+        }
+    };
+}
+
+impl_ordered_float!(f32);
+impl_ordered_float!(f64);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn total_order() {
+        let mut values =
+            [f64::NAN, 1.5, f64::INFINITY, -0.0, -1.0].map(OrderedFloat);
+        values.sort();
+        assert_eq!(
+            values.map(|v| v.0.to_string()),
+            ["-1", "-0", "1.5", "inf", "NaN"]
+        );
+        assert_eq!(OrderedFloat(f32::NAN), OrderedFloat(-f32::NAN));
+        assert_eq!(OrderedFloat(0.0_f32), OrderedFloat(-0.0_f32));
+    }
+}
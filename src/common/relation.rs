@@ -1,8 +1,9 @@
 use crate::common::{
-    error::{field_not_found, FloppyError, Result},
+    error::{field_not_found, CatalogError, FloppyError, Result},
     scalar::{Datum, ScalarType},
 };
 
+use std::fmt;
 use std::ops::{Bound, RangeBounds};
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -85,6 +86,14 @@ pub type ColumnName = String;
 pub struct RelationDesc {
     rel_type: RelationType,
     column_names: Vec<ColumnName>,
+    /// The name of the relation of each column in `FROM`, eg the
+    /// table or its alias, which may qualify the column name.
+    qualifiers: Vec<Option<String>>,
+    /// The default value of each column of a table, which is stored
+    /// when a row is copied without the column.
+    // todo! there is no CREATE TABLE to fold a DEFAULT expression into
+    // the value, nor INSERT to apply it, only COPY FROM fills it in.
+    defaults: Vec<Option<Datum>>,
 }
 
 impl Default for RelationDesc {
@@ -101,6 +110,8 @@ impl RelationDesc {
         secondary_keys: Vec<Vec<usize>>,
     ) -> Self {
         Self {
+            qualifiers: vec![None; column_names.len()],
+            defaults: vec![None; column_names.len()],
             rel_type: RelationType::new(column_types, prim_key, secondary_keys),
             column_names,
         }
@@ -110,9 +121,41 @@ impl RelationDesc {
         Self {
             rel_type: RelationType::empty(),
             column_names: vec![],
+            qualifiers: vec![],
+            defaults: vec![],
         }
     }
 
+    /// Qualifies all the columns with the relation name `qualifier`.
+    pub fn with_qualifier(mut self, qualifier: &str) -> Self {
+        self.qualifiers =
+            vec![Some(qualifier.to_string()); self.qualifiers.len()];
+        self
+    }
+
+    /// Sets the default value of the column `i` to `datum`, which is
+    /// a constant of the type of the column.
+    pub fn with_default(mut self, i: usize, datum: Datum) -> Self {
+        self.defaults[i] = Some(datum);
+        self
+    }
+
+    /// The value of the column `i` of a row copied without it, NULL
+    /// if the column has no default.
+    pub fn default_value(&self, i: usize) -> Datum {
+        self.defaults[i].clone().unwrap_or(Datum::Null)
+    }
+
+    /// The columns qualified by `qualifier`, in order.
+    pub fn qualified_columns(&self, qualifier: &str) -> Vec<usize> {
+        self.qualifiers
+            .iter()
+            .enumerate()
+            .filter(|(_, q)| q.as_deref() == Some(qualifier))
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
     pub fn rel_type(&self) -> &RelationType {
         &self.rel_type
     }
@@ -138,20 +181,53 @@ impl RelationDesc {
         &self.column_names
     }
 
+    /// The column named `column_name`, which must be unique among
+    /// the columns of all the relations.
     pub fn column_idx(&self, column_name: &str) -> Result<usize> {
+        self.resolve_column(None, column_name)
+    }
+
+    /// The column `qualifier.column_name`, eg `t1.c1`.
+    pub fn qualified_column_idx(
+        &self,
+        qualifier: &str,
+        column_name: &str,
+    ) -> Result<usize> {
+        self.resolve_column(Some(qualifier), column_name)
+    }
+
+    fn resolve_column(
+        &self,
+        qualifier: Option<&str>,
+        column_name: &str,
+    ) -> Result<usize> {
         let mut matches = self
             .column_names
             .iter()
+            .zip(&self.qualifiers)
             .enumerate()
-            .filter(|(_, name)| column_name == *name)
+            .filter(|(_, (name, q))| {
+                column_name == *name
+                    && qualifier.map_or(true, |qualifier| {
+                        q.as_deref() == Some(qualifier)
+                    })
+            })
             .map(|(idx, _)| idx);
+        let column_ref = || match qualifier {
+            Some(q) => format!("{q}.{column_name}"),
+            None => column_name.to_string(),
+        };
         match matches.next() {
-            None => Err(field_not_found(None, column_name, self)),
+            None => Err(field_not_found(
+                qualifier.map(str::to_string),
+                column_name,
+                self,
+            )),
             Some(idx) => match matches.next() {
                 None => Ok(idx),
-                Some(_) => Err(FloppyError::Internal(format!(
-                    "duplicated column name: {column_name}"
-                ))),
+                Some(_) => Err(FloppyError::Catalog(
+                    CatalogError::AmbiguousColumn(column_ref()),
+                )),
             },
         }
     }
@@ -172,8 +248,144 @@ impl RelationDesc {
         self.column_names.iter()
     }
 
-    pub fn prim_key(&self) -> Vec<usize> {
-        self.rel_type.prim_key.clone()
+    /// The columns of the primary key, in the order of the key.
+    pub fn primary_key(&self) -> &[usize] {
+        &self.rel_type.prim_key
+    }
+
+    /// The columns of each secondary index, in the order of the key.
+    pub fn secondary_keys(&self) -> &[Vec<usize>] {
+        &self.rel_type.secondary_keys
+    }
+
+    /// Whether `columns` are the leading columns of the primary key,
+    /// in the order of the key.
+    pub fn is_primary_key_prefix(&self, columns: &[usize]) -> bool {
+        !columns.is_empty() && self.primary_key().starts_with(columns)
+    }
+
+    /// The description of the `columns` of this relation, in
+    /// the order of `columns`. Keys that are not entirely in
+    /// `columns` are dropped.
+    pub fn project(&self, columns: &[usize]) -> RelationDesc {
+        let project_key = |key: &Vec<usize>| {
+            key.iter()
+                .map(|i| columns.iter().position(|c| c == i))
+                .collect::<Option<Vec<usize>>>()
+        };
+        let prim_key = project_key(&self.rel_type.prim_key).unwrap_or_default();
+        let secondary_keys = self
+            .rel_type
+            .secondary_keys
+            .iter()
+            .filter_map(project_key)
+            .collect();
+        let rel_desc = RelationDesc::new(
+            columns
+                .iter()
+                .map(|i| self.rel_type.column_type(*i).clone())
+                .collect(),
+            columns
+                .iter()
+                .map(|i| self.column_names[*i].clone())
+                .collect(),
+            prim_key,
+            secondary_keys,
+        );
+        RelationDesc {
+            qualifiers: columns
+                .iter()
+                .map(|i| self.qualifiers[*i].clone())
+                .collect(),
+            defaults: columns
+                .iter()
+                .map(|i| self.defaults[*i].clone())
+                .collect(),
+            ..rel_desc
+        }
+    }
+
+    /// The description of the columns of this relation followed by
+    /// the columns of `other`, eg the output of a join. The keys
+    /// are dropped.
+    pub fn concat(&self, other: &RelationDesc) -> RelationDesc {
+        let rel_desc = RelationDesc::new(
+            self.iter_types()
+                .chain(other.iter_types())
+                .cloned()
+                .collect(),
+            self.iter_names()
+                .chain(other.iter_names())
+                .cloned()
+                .collect(),
+            vec![],
+            vec![],
+        );
+        RelationDesc {
+            qualifiers: self
+                .qualifiers
+                .iter()
+                .chain(other.qualifiers.iter())
+                .cloned()
+                .collect(),
+            defaults: self
+                .defaults
+                .iter()
+                .chain(other.defaults.iter())
+                .cloned()
+                .collect(),
+            ..rel_desc
+        }
+    }
+
+    /// The description of the columns of this relation followed by
+    /// the columns of `other` like [`RelationDesc::concat`], but the
+    /// keys are kept, those of `other` offset by the columns of this
+    /// relation. The primary key is made of both primary keys, which
+    /// identifies a row of the join of the relations.
+    /// It is an error if a column of each relation is qualified by the
+    /// same name.
+    pub fn merge(&self, other: &RelationDesc) -> Result<RelationDesc> {
+        if let Some(qualifier) = self
+            .qualifiers
+            .iter()
+            .flatten()
+            .find(|q| other.qualifiers.iter().flatten().any(|o| o == *q))
+        {
+            return Err(FloppyError::Catalog(CatalogError::DuplicateAlias(
+                qualifier.clone(),
+            )));
+        }
+        let offset = self.column_types().len();
+        let shift = |key: &Vec<usize>| key.iter().map(|i| i + offset).collect();
+        let mut rel_desc = self.concat(other);
+        if !self.primary_key().is_empty() && !other.primary_key().is_empty() {
+            rel_desc.rel_type.prim_key = self
+                .primary_key()
+                .iter()
+                .copied()
+                .chain(other.primary_key().iter().map(|i| i + offset))
+                .collect();
+        }
+        rel_desc.rel_type.secondary_keys = self
+            .rel_type
+            .secondary_keys
+            .iter()
+            .cloned()
+            .chain(other.rel_type.secondary_keys.iter().map(shift))
+            .collect();
+        Ok(rel_desc)
+    }
+
+    /// Returns an error if `row` has a NULL in a column that is not
+    /// nullable.
+    pub fn check_not_null(&self, row: &Row) -> Result<()> {
+        for ((name, ty), datum) in self.iter().zip(row.iter()) {
+            if !ty.nullable && datum.is_null() {
+                return Err(FloppyError::NotNullViolation(name.clone()));
+            }
+        }
+        Ok(())
     }
 }
 
@@ -233,6 +445,14 @@ impl Row {
         Row::new(vec![])
     }
 
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = &Datum> {
+        self.values.iter()
+    }
+
+    pub fn datums(&self) -> &[Datum] {
+        &self.values
+    }
+
     pub fn column_value(&self, index: usize) -> Result<Datum> {
         if index > self.values.len() {
             return Err(FloppyError::Internal(format!(
@@ -244,12 +464,17 @@ impl Row {
         Ok(self.values[index].clone())
     }
 
+    /// The values of `columns`, in the order of `columns`.
+    pub fn project(&self, columns: &[usize]) -> Row {
+        Row::new(columns.iter().map(|i| self.values[*i].clone()).collect())
+    }
+
     pub fn prim_key_datums(
         &self,
         rel_desc: &RelationDesc,
     ) -> Result<IndexKeyDatums> {
-        let prim_key = rel_desc.prim_key();
-        prim_key
+        rel_desc
+            .primary_key()
             .iter()
             .map(|i| {
                 if *i >= self.values.len() {
@@ -281,6 +506,12 @@ pub type GlobalId = u64;
 #[derive(Debug, Clone, PartialEq, Eq, Ord, PartialOrd)]
 pub struct IndexKeyDatums(Vec<Datum>);
 
+impl IndexKeyDatums {
+    pub fn datums(&self) -> &[Datum] {
+        &self.0
+    }
+}
+
 impl FromIterator<Datum> for IndexKeyDatums {
     fn from_iter<T: IntoIterator<Item = Datum>>(iter: T) -> Self {
         let datums: Vec<Datum> = Vec::from_iter(iter);
@@ -309,6 +540,81 @@ pub struct IndexRange {
     pub hi: Bound<IndexKeyDatums>,
 }
 
+impl IndexRange {
+    /// The range of all the keys.
+    pub fn full() -> Self {
+        Self {
+            lo: Bound::Unbounded,
+            hi: Bound::Unbounded,
+        }
+    }
+
+    pub fn is_full(&self) -> bool {
+        matches!((&self.lo, &self.hi), (Bound::Unbounded, Bound::Unbounded))
+    }
+
+    /// The keys in both `self` and `other`.
+    pub fn intersect(self, other: IndexRange) -> Self {
+        let lo = match (self.lo, other.lo) {
+            (Bound::Unbounded, b) | (b, Bound::Unbounded) => b,
+            (Bound::Included(k1), Bound::Included(k2)) => {
+                Bound::Included(k1.max(k2))
+            }
+            (Bound::Excluded(k1), Bound::Excluded(k2)) => {
+                Bound::Excluded(k1.max(k2))
+            }
+            (Bound::Included(k1), Bound::Excluded(k2))
+            | (Bound::Excluded(k2), Bound::Included(k1)) => {
+                if k1 > k2 {
+                    Bound::Included(k1)
+                } else {
+                    Bound::Excluded(k2)
+                }
+            }
+        };
+        let hi = match (self.hi, other.hi) {
+            (Bound::Unbounded, b) | (b, Bound::Unbounded) => b,
+            (Bound::Included(k1), Bound::Included(k2)) => {
+                Bound::Included(k1.min(k2))
+            }
+            (Bound::Excluded(k1), Bound::Excluded(k2)) => {
+                Bound::Excluded(k1.min(k2))
+            }
+            (Bound::Included(k1), Bound::Excluded(k2))
+            | (Bound::Excluded(k2), Bound::Included(k1)) => {
+                if k1 < k2 {
+                    Bound::Included(k1)
+                } else {
+                    Bound::Excluded(k2)
+                }
+            }
+        };
+        Self { lo, hi }
+    }
+}
+
+impl fmt::Display for IndexRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let key = |k: &IndexKeyDatums| {
+            k.0.iter()
+                .map(|d| d.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match &self.lo {
+            Bound::Unbounded => write!(f, "(-inf")?,
+            Bound::Included(k) => write!(f, "[{}", key(k))?,
+            Bound::Excluded(k) => write!(f, "({}", key(k))?,
+        }
+        write!(f, ", ")?;
+        match &self.hi {
+            Bound::Unbounded => write!(f, "+inf)"),
+            Bound::Included(k) => write!(f, "{}]", key(k)),
+            Bound::Excluded(k) => write!(f, "{})", key(k)),
+        }
+    }
+}
+
 impl RangeBounds<IndexKeyDatums> for IndexRange {
     fn start_bound(&self) -> Bound<&IndexKeyDatums> {
         match &self.lo {
@@ -345,4 +651,95 @@ mod tests {
 
         assert!(key_start < key_end);
     }
+
+    #[test]
+    fn column_defaults() {
+        let desc = RelationDesc::new(
+            vec![ColumnType::new(ScalarType::Int64, true); 3],
+            vec!["c1".to_string(), "c2".to_string(), "c3".to_string()],
+            vec![0],
+            vec![],
+        )
+        .with_default(2, Datum::Int64(3));
+        assert_eq!(desc.default_value(0), Datum::Null);
+        assert_eq!(desc.default_value(2), Datum::Int64(3));
+
+        let projected = desc.project(&[2, 0]);
+        assert_eq!(projected.default_value(0), Datum::Int64(3));
+        assert_eq!(projected.default_value(1), Datum::Null);
+        assert_eq!(desc.concat(&desc).default_value(5), Datum::Int64(3));
+    }
+
+    #[test]
+    fn merge() -> Result<()> {
+        let desc = |prim_key, secondary_keys| {
+            RelationDesc::new(
+                vec![ColumnType::new(ScalarType::Int64, false); 2],
+                vec!["c1".to_string(), "c2".to_string()],
+                prim_key,
+                secondary_keys,
+            )
+        };
+        let left = desc(vec![0], vec![vec![1]]).with_qualifier("t1");
+        let right = desc(vec![1, 0], vec![vec![0]]).with_qualifier("t2");
+        let merged = left.merge(&right)?;
+        assert_eq!(merged.column_names(), &["c1", "c2", "c1", "c2"]);
+        assert_eq!(merged.qualified_columns("t1"), [0, 1]);
+        assert_eq!(merged.qualified_columns("t2"), [2, 3]);
+        assert_eq!(merged.primary_key(), [0, 3, 2]);
+        assert_eq!(merged.secondary_keys(), [vec![1], vec![2]]);
+
+        // a relation without a primary key has none after merged.
+        let merged = left.merge(&desc(vec![], vec![]))?;
+        assert!(merged.primary_key().is_empty());
+
+        let err = left.merge(&left).unwrap_err();
+        assert_eq!(err.code(), "42712");
+        assert_eq!(err.message(), "table name \"t1\" specified more than once");
+        Ok(())
+    }
+
+    #[test]
+    fn primary_key() {
+        let desc = RelationDesc::new(
+            vec![ColumnType::new(ScalarType::Int64, false); 3],
+            vec!["c1".to_string(), "c2".to_string(), "c3".to_string()],
+            vec![2, 0],
+            vec![],
+        );
+        assert_eq!(desc.primary_key(), [2, 0]);
+        assert!(desc.is_primary_key_prefix(&[2]));
+        assert!(desc.is_primary_key_prefix(&[2, 0]));
+        assert!(!desc.is_primary_key_prefix(&[]));
+        assert!(!desc.is_primary_key_prefix(&[0]));
+        assert!(!desc.is_primary_key_prefix(&[0, 2]));
+        assert!(!desc.is_primary_key_prefix(&[2, 0, 1]));
+
+        // the key is dropped unless all of its columns are projected.
+        assert_eq!(desc.project(&[0, 2]).primary_key(), [1, 0]);
+        assert!(desc.project(&[1, 2]).primary_key().is_empty());
+    }
+
+    #[test]
+    fn check_not_null() {
+        let desc = RelationDesc::new(
+            vec![
+                ColumnType::new(ScalarType::Int64, false),
+                ColumnType::new(ScalarType::Int64, true),
+            ],
+            vec!["c1".to_string(), "c2".to_string()],
+            vec![0],
+            vec![],
+        );
+        let row = Row::new(vec![Datum::Int64(1), Datum::Null]);
+        assert!(desc.check_not_null(&row).is_ok());
+
+        let row = Row::new(vec![Datum::Null, Datum::Int64(1)]);
+        let err = desc.check_not_null(&row).unwrap_err();
+        assert_eq!(err.code(), "23502");
+        assert_eq!(
+            err.message(),
+            "null value in column \"c1\" violates not-null constraint"
+        );
+    }
 }
@@ -1,8 +1,14 @@
 use crate::common::{
+    datetime::{self, Interval},
     error::{FloppyError, Result},
+    ordered_float::OrderedFloat,
     relation::ColumnType,
 };
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::{Decimal, RoundingStrategy};
+use std::cmp::Ordering;
 use std::fmt::{self, Formatter};
+use std::hash::{Hash, Hasher};
 use std::ops;
 
 /// A single value.
@@ -14,8 +20,28 @@ pub enum Datum {
     Boolean(bool),
     /// A 64-bit signed integer.
     Int64(i64),
+    /// A 32-bit floating point number.
+    Float32(OrderedFloat<f32>),
+    /// A 64-bit floating point number.
+    Float64(OrderedFloat<f64>),
+    /// An exact decimal number.
+    Numeric(Decimal),
     /// A sequence of Unicode codepoints encoded as UTF-8.
     Text(String),
+    /// A date, the number of days since 2000-01-01.
+    Date(i32),
+    /// A timestamp without time zone, the number of microseconds
+    /// since 2000-01-01 00:00:00.
+    Timestamp(i64),
+    /// A span of time of months, days and microseconds.
+    Interval(Interval),
+    /// A sequence of bytes.
+    Bytea(Vec<u8>),
+    /// A universally unique identifier.
+    Uuid([u8; 16]),
+    /// A one-dimensional array, whose elements are of the same type
+    /// or `NULL`.
+    Array(Vec<Datum>),
     /// An unknown value.
     Null,
 }
@@ -31,15 +57,24 @@ impl ops::Add for Datum {
 
     fn add(self, rhs: Self) -> Self::Output {
         match (self, rhs) {
-            (Self::Int64(d1), Self::Int64(d2)) => {
-                d1.checked_add(d2).map_or_else(
-                    || {
-                        Err(FloppyError::EvalExpr(
-                            "integer over flow".to_string(),
-                        ))
-                    },
-                    |v| Ok(Datum::Int64(v)),
-                )
+            (Self::Int64(d1), Self::Int64(d2)) => d1
+                .checked_add(d2)
+                .map(Datum::Int64)
+                .ok_or_else(int64_out_of_range),
+            (Self::Numeric(d1), Self::Numeric(d2)) => d1
+                .checked_add(d2)
+                .map(Datum::Numeric)
+                .ok_or_else(numeric_out_of_range),
+            (Self::Float64(f1), Self::Float64(f2)) => check_float64(
+                f1.0 + f2.0,
+                f1.0.is_infinite() || f2.0.is_infinite(),
+                true,
+            ),
+            (Self::Timestamp(t), Self::Interval(i)) => {
+                datetime::add_interval(t, &i).map(Datum::Timestamp)
+            }
+            (Self::Interval(i1), Self::Interval(i2)) => {
+                i1.checked_add(&i2).map(Datum::Interval)
             }
             _ => Err(FloppyError::Internal(
                 "mismatched type for addition".to_string(),
@@ -53,23 +88,127 @@ impl ops::Sub for Datum {
 
     fn sub(self, rhs: Self) -> Self::Output {
         match (self, rhs) {
-            (Self::Int64(d1), Self::Int64(d2)) => {
-                d1.checked_sub(d2).map_or_else(
-                    || {
-                        Err(FloppyError::EvalExpr(
-                            "integer over flow".to_string(),
-                        ))
-                    },
-                    |v| Ok(Datum::Int64(v)),
+            (Self::Int64(d1), Self::Int64(d2)) => d1
+                .checked_sub(d2)
+                .map(Datum::Int64)
+                .ok_or_else(int64_out_of_range),
+            (Self::Numeric(d1), Self::Numeric(d2)) => d1
+                .checked_sub(d2)
+                .map(Datum::Numeric)
+                .ok_or_else(numeric_out_of_range),
+            (Self::Float64(f1), Self::Float64(f2)) => check_float64(
+                f1.0 - f2.0,
+                f1.0.is_infinite() || f2.0.is_infinite(),
+                true,
+            ),
+            (Self::Timestamp(t), Self::Interval(i)) => {
+                datetime::add_interval(t, &i.checked_neg()?)
+                    .map(Datum::Timestamp)
+            }
+            (Self::Interval(i1), Self::Interval(i2)) => {
+                i1.checked_sub(&i2).map(Datum::Interval)
+            }
+            _ => Err(FloppyError::Internal(
+                "mismatched type for subtraction".to_string(),
+            )),
+        }
+    }
+}
+
+impl ops::Mul for Datum {
+    type Output = Result<Datum>;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        match (self, rhs) {
+            (Self::Int64(d1), Self::Int64(d2)) => d1
+                .checked_mul(d2)
+                .map(Datum::Int64)
+                .ok_or_else(int64_out_of_range),
+            (Self::Numeric(d1), Self::Numeric(d2)) => d1
+                .checked_mul(d2)
+                .map(Datum::Numeric)
+                .ok_or_else(numeric_out_of_range),
+            (Self::Float64(f1), Self::Float64(f2)) => check_float64(
+                f1.0 * f2.0,
+                f1.0.is_infinite() || f2.0.is_infinite(),
+                f1.0 == 0.0 || f2.0 == 0.0,
+            ),
+            _ => Err(FloppyError::Internal(
+                "mismatched type for multiplication".to_string(),
+            )),
+        }
+    }
+}
+
+impl ops::Div for Datum {
+    type Output = Result<Datum>;
+
+    /// Integer division truncates towards zero. Dividing by zero is an
+    /// error, even for floats.
+    fn div(self, rhs: Self) -> Self::Output {
+        match (self, rhs) {
+            (Self::Int64(_), Self::Int64(0)) => {
+                Err(FloppyError::DivisionByZero)
+            }
+            (Self::Int64(d1), Self::Int64(d2)) => d1
+                .checked_div(d2)
+                .map(Datum::Int64)
+                .ok_or_else(int64_out_of_range),
+            (Self::Numeric(d1), Self::Numeric(d2)) => {
+                if d2.is_zero() {
+                    return Err(FloppyError::DivisionByZero);
+                }
+                d1.checked_div(d2)
+                    .map(Datum::Numeric)
+                    .ok_or_else(numeric_out_of_range)
+            }
+            (Self::Float64(f1), Self::Float64(f2)) => {
+                if f2.0 == 0.0 {
+                    return Err(FloppyError::DivisionByZero);
+                }
+                check_float64(
+                    f1.0 / f2.0,
+                    f1.0.is_infinite() || f2.0.is_nan(),
+                    f1.0 == 0.0 || f2.0.is_infinite(),
                 )
             }
             _ => Err(FloppyError::Internal(
-                "mismatched type for addition".to_string(),
+                "mismatched type for division".to_string(),
             )),
         }
     }
 }
 
+/// Checks the result of a float operation as PostgreSQL does: an
+/// infinite result is an overflow unless `inf_is_valid`, e.g. one of the
+/// operands is infinite, and a zero result is an underflow unless
+/// `zero_is_valid`. NaN is a valid result.
+fn check_float64(
+    f: f64,
+    inf_is_valid: bool,
+    zero_is_valid: bool,
+) -> Result<Datum> {
+    if f.is_infinite() && !inf_is_valid {
+        return Err(FloppyError::NumericOutOfRange(
+            "value out of range: overflow".to_string(),
+        ));
+    }
+    if f == 0.0 && !zero_is_valid {
+        return Err(FloppyError::NumericOutOfRange(
+            "value out of range: underflow".to_string(),
+        ));
+    }
+    Ok(Datum::Float64(OrderedFloat(f)))
+}
+
+pub(crate) fn int64_out_of_range() -> FloppyError {
+    FloppyError::NumericOutOfRange("bigint out of range".to_string())
+}
+
+pub(crate) fn numeric_out_of_range() -> FloppyError {
+    FloppyError::NumericOutOfRange("value overflows numeric format".to_string())
+}
+
 impl fmt::Display for Datum {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -81,30 +220,174 @@ impl fmt::Display for Datum {
                 }
             }
             Self::Int64(e) => write!(f, "{e}"),
+            Self::Float32(e) => write!(f, "{e}"),
+            Self::Float64(e) => write!(f, "{e}"),
+            Self::Numeric(e) => write!(f, "{e}"),
             Self::Text(e) => write!(f, "{e}"),
+            Self::Date(d) => write!(f, "{}", datetime::format_date(*d)),
+            Self::Timestamp(t) => {
+                write!(f, "{}", datetime::format_timestamp(*t))
+            }
+            Self::Interval(i) => {
+                write!(f, "{}", datetime::format_interval(i))
+            }
+            Self::Bytea(b) => write!(f, "{}", format_bytea(b)),
+            Self::Uuid(u) => write!(f, "{}", format_uuid(u)),
+            Self::Array(elems) => {
+                let elems = elems.iter().map(|d| match d {
+                    Self::Null => None,
+                    Self::Boolean(b) => Some(if *b { "t" } else { "f" }.into()),
+                    d => Some(d.to_string()),
+                });
+                write!(f, "{}", format_array(elems))
+            }
             Self::Null => write!(f, "NULL"),
         }
     }
 }
 
 impl Datum {
-    pub fn logical_and(&self, other: &Datum) -> Result<Datum> {
+    /// Compares two datums as SQL does. Returns `None` if either
+    /// of them is `NULL` or their types are not comparable. The
+    /// numeric types are compared after promoting the integer or
+    /// numeric to the wider type.
+    ///
+    /// Unlike the derived [`Ord`], which is a total order used by
+    /// the index keys, this is the order of the comparison
+    /// functions and sorting.
+    pub fn compare(&self, other: &Datum) -> Option<Ordering> {
         match (self, other) {
-            (Self::Boolean(d1), Self::Boolean(d2)) => {
-                Ok(Datum::Boolean(*d1 && *d2))
+            (Self::Null, _) | (_, Self::Null) => None,
+            (Self::Boolean(b1), Self::Boolean(b2)) => Some(b1.cmp(b2)),
+            (Self::Int64(i1), Self::Int64(i2)) => Some(i1.cmp(i2)),
+            (Self::Numeric(d1), Self::Numeric(d2)) => Some(d1.cmp(d2)),
+            (Self::Text(s1), Self::Text(s2)) => Some(s1.cmp(s2)),
+            (Self::Date(d1), Self::Date(d2)) => Some(d1.cmp(d2)),
+            (Self::Timestamp(t1), Self::Timestamp(t2)) => Some(t1.cmp(t2)),
+            // intervals of the same span are equal, eg `1 mon` and
+            // `30 days`.
+            (Self::Interval(i1), Self::Interval(i2)) => {
+                Some(i1.span().cmp(&i2.span()))
+            }
+            (Self::Bytea(b1), Self::Bytea(b2)) => Some(b1.cmp(b2)),
+            (Self::Uuid(u1), Self::Uuid(u2)) => Some(u1.cmp(u2)),
+            // like PostgreSQL, a NULL element is greater than the
+            // others, and a shorter array is less if it is a prefix.
+            (Self::Array(a1), Self::Array(a2)) => {
+                for (d1, d2) in a1.iter().zip(a2) {
+                    let ord = match (d1, d2) {
+                        (Self::Null, Self::Null) => Ordering::Equal,
+                        (Self::Null, _) => Ordering::Greater,
+                        (_, Self::Null) => Ordering::Less,
+                        (d1, d2) => d1.compare(d2)?,
+                    };
+                    if ord != Ordering::Equal {
+                        return Some(ord);
+                    }
+                }
+                Some(a1.len().cmp(&a2.len()))
+            }
+            (Self::Int64(i), Self::Numeric(d)) => {
+                Some(Decimal::from(*i).cmp(d))
+            }
+            (Self::Numeric(d), Self::Int64(i)) => {
+                Some(d.cmp(&Decimal::from(*i)))
+            }
+            (d1, d2) => {
+                let f1 = d1.as_f64()?;
+                let f2 = d2.as_f64()?;
+                Some(OrderedFloat(f1).cmp(&OrderedFloat(f2)))
             }
+        }
+    }
+
+    /// Feeds the datum into `state`, the datums that are equal by
+    /// [`Datum::compare`] have equal hashes, eg `Int64(1)` and
+    /// `Numeric(1.0)`, so that the hash-based operators agree with the
+    /// comparison functions. The hash is deterministic, all the
+    /// `NULL`s have the same hash.
+    pub fn hash_into<H: Hasher>(&self, state: &mut H) {
+        // the numeric types are hashed by their common promotion to
+        // `f64`, the other types by their value.
+        match self {
+            Self::Null => 0u8.hash(state),
+            Self::Boolean(b) => (1u8, b).hash(state),
+            Self::Int64(_)
+            | Self::Float32(_)
+            | Self::Float64(_)
+            | Self::Numeric(_) => {
+                (2u8, self.as_f64().map(OrderedFloat)).hash(state)
+            }
+            Self::Text(s) => (3u8, s).hash(state),
+            Self::Date(d) => (4u8, d).hash(state),
+            Self::Timestamp(t) => (5u8, t).hash(state),
+            Self::Bytea(b) => (6u8, b).hash(state),
+            Self::Uuid(u) => (7u8, u).hash(state),
+            Self::Array(elems) => {
+                (8u8, elems.len()).hash(state);
+                for elem in elems {
+                    elem.hash_into(state);
+                }
+            }
+            Self::Interval(i) => (9u8, i.span()).hash(state),
+        }
+    }
+
+    /// The value of a numeric datum as a `f64`.
+    fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Int64(i) => Some(*i as f64),
+            Self::Float32(f) => Some(f.0 as f64),
+            Self::Float64(f) => Some(f.0),
+            Self::Numeric(d) => d.to_f64(),
+            _ => None,
+        }
+    }
+
+    /// The text representation of the datum, as in PostgreSQL's
+    /// output functions. `NULL` stays `NULL`.
+    pub fn cast_to_text(&self) -> Datum {
+        match self {
+            Self::Boolean(b) => Datum::Text(b.to_string()),
+            Self::Text(_) | Self::Null => self.clone(),
+            d => Datum::Text(d.to_string()),
+        }
+    }
+
+    /// `AND` of the three-valued logic: `NULL AND FALSE` is
+    /// `FALSE`, `NULL AND TRUE` is `NULL`.
+    pub fn logical_and(&self, other: &Datum) -> Result<Datum> {
+        match (self, other) {
+            (Self::Boolean(false), Self::Boolean(_) | Self::Null)
+            | (Self::Null, Self::Boolean(false)) => Ok(Datum::Boolean(false)),
+            (Self::Boolean(true), Self::Boolean(d)) => Ok(Datum::Boolean(*d)),
+            (Self::Boolean(true) | Self::Null, Self::Null)
+            | (Self::Null, Self::Boolean(true)) => Ok(Datum::Null),
             _ => Err(FloppyError::Internal("AND type error".to_string())),
         }
     }
 
+    /// `OR` of the three-valued logic: `NULL OR TRUE` is `TRUE`,
+    /// `NULL OR FALSE` is `NULL`.
     pub fn logical_or(&self, other: &Datum) -> Result<Datum> {
         match (self, other) {
-            (Self::Boolean(d1), Self::Boolean(d2)) => {
-                Ok(Datum::Boolean(*d1 || *d2))
-            }
+            (Self::Boolean(true), Self::Boolean(_) | Self::Null)
+            | (Self::Null, Self::Boolean(true)) => Ok(Datum::Boolean(true)),
+            (Self::Boolean(false), Self::Boolean(d)) => Ok(Datum::Boolean(*d)),
+            (Self::Boolean(false) | Self::Null, Self::Null)
+            | (Self::Null, Self::Boolean(false)) => Ok(Datum::Null),
             _ => Err(FloppyError::Internal("OR type error".to_string())),
         }
     }
+
+    /// `NOT NULL` is `NULL`.
+    pub fn logical_not(&self) -> Result<Datum> {
+        match self {
+            Self::Boolean(d) => Ok(Datum::Boolean(!d)),
+            Self::Null => Ok(Datum::Null),
+            _ => Err(FloppyError::Internal("NOT type error".to_string())),
+        }
+    }
 }
 
 /// The type of a [`Datum`].
@@ -117,13 +400,48 @@ pub enum ScalarType {
     Boolean,
     /// The type of [`Datum::Int64`]
     Int64,
+    /// The type of [`Datum::Float32`]
+    Float32,
+    /// The type of [`Datum::Float64`]
+    Float64,
+    /// The type of [`Datum::Numeric`], with optional constraints
+    /// on its precision and scale.
+    Numeric(Option<NumericConstraints>),
     /// The type of [`Datum::String`]
     Text,
+    /// The type of [`Datum::Date`]
+    Date,
+    /// The type of [`Datum::Timestamp`]
+    Timestamp,
+    /// The type of [`Datum::Interval`]
+    Interval,
+    /// The type of [`Datum::Bytea`]
+    Bytea,
+    /// The type of [`Datum::Uuid`]
+    Uuid,
+    /// The type of [`Datum::Array`], with the type of its elements,
+    /// which is not an array.
+    Array(Box<ScalarType>),
 }
 
 impl ScalarType {
     pub fn is_numeric(&self) -> bool {
-        matches!(self, ScalarType::Int64)
+        matches!(
+            self,
+            ScalarType::Int64
+                | ScalarType::Float32
+                | ScalarType::Float64
+                | ScalarType::Numeric(_)
+        )
+    }
+
+    /// Whether `self` and `other` are the same type, ignoring the
+    /// type modifiers, eg the constraints of numerics.
+    pub fn base_eq(&self, other: &ScalarType) -> bool {
+        match (self, other) {
+            (Self::Array(e1), Self::Array(e2)) => e1.base_eq(e2),
+            _ => std::mem::discriminant(self) == std::mem::discriminant(other),
+        }
     }
 
     /// Derive a `ColumnType` from `ScalarType`
@@ -140,14 +458,366 @@ impl fmt::Display for ScalarType {
         match self {
             Self::Boolean => write!(f, "Boolean"),
             Self::Int64 => write!(f, "Int64"),
+            Self::Float32 => write!(f, "Float32"),
+            Self::Float64 => write!(f, "Float64"),
+            Self::Numeric(None) => write!(f, "Numeric"),
+            Self::Numeric(Some(c)) => {
+                write!(f, "Numeric({}, {})", c.precision, c.scale)
+            }
             Self::Text => write!(f, "Text"),
+            Self::Date => write!(f, "Date"),
+            Self::Timestamp => write!(f, "Timestamp"),
+            Self::Interval => write!(f, "Interval"),
+            Self::Bytea => write!(f, "Bytea"),
+            Self::Uuid => write!(f, "Uuid"),
+            Self::Array(elem) => write!(f, "{elem}[]"),
+        }
+    }
+}
+
+/// The maximum precision of [`ScalarType::Numeric`], limited by
+/// [`Decimal`].
+pub const NUMERIC_MAX_PRECISION: u8 = 28;
+
+/// The constraints of `NUMERIC(precision, scale)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumericConstraints {
+    /// The total number of significant digits.
+    pub precision: u8,
+    /// The number of digits after the decimal point.
+    pub scale: u8,
+}
+
+impl NumericConstraints {
+    pub fn new(precision: u8, scale: u8) -> Result<Self> {
+        if precision == 0 || precision > NUMERIC_MAX_PRECISION {
+            return Err(FloppyError::Plan(format!(
+                "NUMERIC precision {precision} must be between 1 and \
+                 {NUMERIC_MAX_PRECISION}"
+            )));
+        }
+        if scale > precision {
+            return Err(FloppyError::Plan(format!(
+                "NUMERIC scale {scale} must be between 0 and precision \
+                 {precision}"
+            )));
+        }
+        Ok(Self { precision, scale })
+    }
+
+    /// The type modifier of PostgreSQL, the precision and scale are
+    /// packed into the upper and lower 16 bits with an offset of 4.
+    pub fn typmod(&self) -> i32 {
+        (((self.precision as i32) << 16) | self.scale as i32) + 4
+    }
+
+    /// The reverse of [`NumericConstraints::typmod`], returns `None`
+    /// if there is no constraint.
+    pub fn from_typmod(typmod: i32) -> Result<Option<Self>> {
+        if typmod < 4 {
+            return Ok(None);
+        }
+        let typmod = typmod - 4;
+        let precision = u8::try_from(typmod >> 16).map_err(|_| {
+            FloppyError::Plan(format!("invalid NUMERIC typmod: {typmod}"))
+        })?;
+        Self::new(precision, (typmod & 0xffff) as u8).map(Some)
+    }
+
+    /// Round `d` to the scale, half away from zero as PostgreSQL.
+    /// Returns an error if there are too many digits before the
+    /// decimal point.
+    pub fn apply(&self, d: Decimal) -> Result<Decimal> {
+        let d = d.round_dp_with_strategy(
+            self.scale as u32,
+            RoundingStrategy::MidpointAwayFromZero,
+        );
+        let max_int_digits = (self.precision - self.scale) as usize;
+        let int_digits =
+            d.trunc().abs().to_string().trim_start_matches('0').len();
+        if int_digits > max_int_digits {
+            return Err(FloppyError::NumericOutOfRange(format!(
+                "numeric field overflow, a field with precision {}, scale \
+                 {} must round to an absolute value less than 10^{}",
+                self.precision, self.scale, max_int_digits
+            )));
+        }
+        Ok(d)
+    }
+}
+
+/// Parse the text form of a bytea, which is either the hex format
+/// `\xDEADBEEF` or, as PostgreSQL's escape format, the bytes of the
+/// text with `\\` for a backslash and `\ooo` for an octal byte.
+pub fn parse_bytea(s: &str) -> Result<Vec<u8>> {
+    let invalid = || {
+        FloppyError::InvalidTextRepresentation(
+            "invalid input syntax for type bytea".to_string(),
+        )
+    };
+    if let Some(hex) = s.strip_prefix("\\x") {
+        let digits = hex
+            .bytes()
+            .filter(|b| !b.is_ascii_whitespace())
+            .map(|b| (b as char).to_digit(16).map(|d| d as u8))
+            .collect::<Option<Vec<u8>>>()
+            .ok_or_else(|| {
+                FloppyError::InvalidTextRepresentation(format!(
+                    "invalid hexadecimal digit in \"{s}\""
+                ))
+            })?;
+        if digits.len() % 2 != 0 {
+            return Err(FloppyError::InvalidTextRepresentation(format!(
+                "invalid hexadecimal data: odd number of digits in \"{s}\""
+            )));
+        }
+        return Ok(digits.chunks(2).map(|d| (d[0] << 4) | d[1]).collect());
+    }
+
+    // an octal escape is 3 digits of at most `\377`.
+    let octal = |d: &[u8]| {
+        matches!(d, [b'0'..=b'3', b'0'..=b'7', b'0'..=b'7'])
+            .then(|| d.iter().fold(0u8, |n, d| (n << 3) | (d - b'0')))
+    };
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut rest = s.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        rest = match (b, tail) {
+            (b'\\', [b'\\', tail @ ..]) => {
+                bytes.push(b'\\');
+                tail
+            }
+            (b'\\', _) => {
+                let byte = tail.get(..3).and_then(octal).ok_or_else(invalid)?;
+                bytes.push(byte);
+                &tail[3..]
+            }
+            (b, tail) => {
+                bytes.push(b);
+                tail
+            }
+        };
+    }
+    Ok(bytes)
+}
+
+/// Format a bytea in the hex format of PostgreSQL, eg `\xdeadbeef`.
+pub fn format_bytea(bytes: &[u8]) -> String {
+    let mut s = String::with_capacity(2 + 2 * bytes.len());
+    s.push_str("\\x");
+    for b in bytes {
+        s.push_str(&format!("{b:02x}"));
+    }
+    s
+}
+
+/// Parse the text form of a UUID, 32 hex digits in either case,
+/// optionally hyphenated as `8-4-4-4-12` and surrounded by braces.
+pub fn parse_uuid(s: &str) -> Result<[u8; 16]> {
+    let invalid = || {
+        FloppyError::InvalidTextRepresentation(format!(
+            "invalid input syntax for type uuid: \"{s}\""
+        ))
+    };
+    let trimmed = s.trim();
+    let trimmed = trimmed
+        .strip_prefix('{')
+        .and_then(|t| t.strip_suffix('}'))
+        .unwrap_or(trimmed);
+    let hex = match trimmed.len() {
+        32 => trimmed.to_string(),
+        36 if trimmed
+            .char_indices()
+            .all(|(i, c)| matches!(i, 8 | 13 | 18 | 23) == (c == '-')) =>
+        {
+            trimmed.replace('-', "")
+        }
+        _ => return Err(invalid()),
+    };
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+    let mut uuid = [0; 16];
+    for (i, byte) in uuid.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16)
+            .map_err(|_| invalid())?;
+    }
+    Ok(uuid)
+}
+
+/// Format a UUID in the canonical form, eg
+/// `a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11`.
+pub fn format_uuid(uuid: &[u8; 16]) -> String {
+    let mut s = String::with_capacity(36);
+    for (i, b) in uuid.iter().enumerate() {
+        if matches!(i, 4 | 6 | 8 | 10) {
+            s.push('-');
+        }
+        s.push_str(&format!("{b:02x}"));
+    }
+    s
+}
+
+/// Format the text of the elements of an array as `{e1,e2}`, `None`
+/// is `NULL`. An element is double quoted if it is empty, `NULL` or
+/// has any of `{},"\` or whitespace, with `"` and `\` escaped by a
+/// backslash.
+pub fn format_array<I>(elems: I) -> String
+where
+    I: IntoIterator<Item = Option<String>>,
+{
+    let mut s = String::from("{");
+    for (i, elem) in elems.into_iter().enumerate() {
+        if i > 0 {
+            s.push(',');
+        }
+        let elem = match elem {
+            Some(elem) => elem,
+            None => {
+                s.push_str("NULL");
+                continue;
+            }
+        };
+        let quote = elem.is_empty()
+            || elem.eq_ignore_ascii_case("NULL")
+            || elem.chars().any(|c| {
+                matches!(c, '{' | '}' | ',' | '"' | '\\') || c.is_whitespace()
+            });
+        if !quote {
+            s.push_str(&elem);
+            continue;
+        }
+        s.push('"');
+        for c in elem.chars() {
+            if matches!(c, '"' | '\\') {
+                s.push('\\');
+            }
+            s.push(c);
+        }
+        s.push('"');
+    }
+    s.push('}');
+    s
+}
+
+/// Parse the text of an array in the form of [`format_array`] to the
+/// text of its elements, an unquoted `NULL` is `None`. Whitespace
+/// around the elements is ignored, a backslash escapes the next
+/// character. Multidimensional arrays are not supported.
+pub fn parse_array(s: &str) -> Result<Vec<Option<String>>> {
+    let malformed = || {
+        FloppyError::InvalidTextRepresentation(format!(
+            "malformed array literal: \"{s}\""
+        ))
+    };
+    let inner = s
+        .trim()
+        .strip_prefix('{')
+        .and_then(|t| t.strip_suffix('}'))
+        .ok_or_else(malformed)?;
+    if inner.trim().is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut elems = vec![];
+    let mut chars = inner.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let mut elem = String::new();
+        let quoted = chars.next_if_eq(&'"').is_some();
+        if quoted {
+            loop {
+                match chars.next().ok_or_else(malformed)? {
+                    '"' => break,
+                    '\\' => elem.push(chars.next().ok_or_else(malformed)?),
+                    c => elem.push(c),
+                }
+            }
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        } else {
+            // the trailing whitespace of an unquoted element is
+            // trimmed, unless escaped.
+            let mut len = 0;
+            while let Some(c) = chars.next_if(|c| *c != ',') {
+                match c {
+                    '{' | '}' | '"' => return Err(malformed()),
+                    '\\' => {
+                        elem.push(chars.next().ok_or_else(malformed)?);
+                        len = elem.len();
+                    }
+                    c => {
+                        elem.push(c);
+                        if !c.is_whitespace() {
+                            len = elem.len();
+                        }
+                    }
+                }
+            }
+            elem.truncate(len);
+            if elem.is_empty() {
+                return Err(malformed());
+            }
+        }
+
+        let is_null = !quoted && elem.eq_ignore_ascii_case("NULL");
+        elems.push(if is_null { None } else { Some(elem) });
+        match chars.next() {
+            Some(',') => {}
+            None => return Ok(elems),
+            Some(_) => return Err(malformed()),
+        }
+    }
+}
+
+/// A [`Datum`] as the key of the hash-based operators, eg hash join
+/// and `DISTINCT`, and of the groups of `GROUP BY`. Two keys are equal
+/// if their datums are equal by [`Datum::compare`] or both are `NULL`,
+/// and it is hashed by [`Datum::hash_into`]. Keys are ordered by
+/// [`Datum::compare`] too, `NULL` is greater than the others.
+#[derive(Debug, Clone)]
+pub struct DatumKey(pub Datum);
+
+impl PartialEq for DatumKey {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (Datum::Null, Datum::Null) => true,
+            (d1, d2) => d1.compare(d2) == Some(Ordering::Equal),
         }
     }
 }
 
+impl Eq for DatumKey {}
+
+impl PartialOrd for DatumKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for DatumKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (&self.0, &other.0) {
+            (Datum::Null, Datum::Null) => Ordering::Equal,
+            (Datum::Null, _) => Ordering::Greater,
+            (_, Datum::Null) => Ordering::Less,
+            // the datums of a key are of the same type, the order of
+            // the others is only for completeness.
+            (d1, d2) => d1.compare(d2).unwrap_or_else(|| d1.cmp(d2)),
+        }
+    }
+}
+
+impl Hash for DatumKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash_into(state)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::hash_map::DefaultHasher;
+    use std::collections::HashSet;
 
     #[test]
     fn datum_equal() {
@@ -164,4 +834,375 @@ mod tests {
         assert_eq!(d2 > d1, true);
         assert_eq!(d1 > d3, true);
     }
+
+    #[test]
+    fn hash_into() {
+        let hash = |d: &Datum| {
+            let mut hasher = DefaultHasher::new();
+            d.hash_into(&mut hasher);
+            hasher.finish()
+        };
+        let equal = [
+            vec![
+                Datum::Int64(1),
+                Datum::Float32(OrderedFloat(1.0)),
+                Datum::Float64(OrderedFloat(1.0)),
+                Datum::Numeric(Decimal::new(100, 2)),
+            ],
+            vec![
+                Datum::Float64(OrderedFloat(0.0)),
+                Datum::Float64(OrderedFloat(-0.0)),
+                Datum::Numeric(Decimal::new(0, 3)),
+            ],
+            vec![
+                Datum::Array(vec![Datum::Int64(1), Datum::Null]),
+                Datum::Array(vec![
+                    Datum::Numeric(Decimal::new(10, 1)),
+                    Datum::Null,
+                ]),
+            ],
+            vec![
+                Datum::Interval(Interval {
+                    months: 1,
+                    ..Default::default()
+                }),
+                Datum::Interval(Interval {
+                    days: 30,
+                    ..Default::default()
+                }),
+            ],
+            vec![Datum::Null, Datum::Null],
+        ];
+        for datums in equal {
+            for d in &datums[1..] {
+                assert_eq!(datums[0].compare(d).is_some(), !d.is_null());
+                assert_eq!(hash(&datums[0]), hash(d), "{} {}", datums[0], d);
+                assert!(DatumKey(datums[0].clone()) == DatumKey(d.clone()));
+            }
+        }
+
+        // the hash is the same every time in a process.
+        let datums = [
+            Datum::Null,
+            Datum::Boolean(true),
+            Datum::Int64(42),
+            Datum::Text("abc".to_string()),
+            Datum::Array(vec![Datum::Text("a".to_string())]),
+        ];
+        let hashes = datums.iter().map(hash).collect::<Vec<_>>();
+        assert_eq!(hashes, datums.iter().map(hash).collect::<Vec<_>>());
+        assert_eq!(hashes.iter().collect::<HashSet<_>>().len(), datums.len());
+
+        let keys = [
+            Datum::Int64(1),
+            Datum::Numeric(Decimal::new(10, 1)),
+            Datum::Int64(2),
+            Datum::Null,
+            Datum::Null,
+        ]
+        .into_iter()
+        .map(DatumKey)
+        .collect::<HashSet<_>>();
+        assert_eq!(keys.len(), 3);
+
+        let key = |d| DatumKey(d);
+        assert!(
+            key(Datum::Int64(2)) < key(Datum::Numeric(Decimal::new(25, 1)))
+        );
+        assert!(key(Datum::Float64(OrderedFloat(1e9))) < key(Datum::Null));
+        assert_eq!(
+            key(Datum::Int64(2)).cmp(&key(Datum::Numeric(Decimal::new(20, 1)))),
+            Ordering::Equal
+        );
+    }
+
+    #[test]
+    fn compare() {
+        let int = Datum::Int64;
+        let numeric =
+            |n: i64, scale: u32| Datum::Numeric(Decimal::new(n, scale));
+        let float = |f: f64| Datum::Float64(OrderedFloat(f));
+        let text = |s: &str| Datum::Text(s.to_string());
+        let tests = [
+            (int(1), int(2), Some(Ordering::Less)),
+            (int(i64::MAX), int(i64::MIN), Some(Ordering::Greater)),
+            (int(2), numeric(20, 1), Some(Ordering::Equal)),
+            (numeric(25, 1), int(2), Some(Ordering::Greater)),
+            (numeric(-15, 1), int(-1), Some(Ordering::Less)),
+            (int(3), float(2.5), Some(Ordering::Greater)),
+            (float(2.5), numeric(25, 1), Some(Ordering::Equal)),
+            (
+                Datum::Float32(OrderedFloat(1.5)),
+                float(1.5),
+                Some(Ordering::Equal),
+            ),
+            (text("abc"), text("b"), Some(Ordering::Less)),
+            (text("b"), text("abc"), Some(Ordering::Greater)),
+            (text(""), text(""), Some(Ordering::Equal)),
+            (
+                Datum::Boolean(false),
+                Datum::Boolean(true),
+                Some(Ordering::Less),
+            ),
+            (Datum::Null, int(1), None),
+            (text("a"), Datum::Null, None),
+            (Datum::Null, Datum::Null, None),
+            (text("1"), int(1), None),
+            (Datum::Date(-1), Datum::Date(0), Some(Ordering::Less)),
+            (
+                Datum::Timestamp(1),
+                Datum::Timestamp(-1),
+                Some(Ordering::Greater),
+            ),
+            (Datum::Date(0), Datum::Timestamp(0), None),
+            (
+                Datum::Interval(Interval {
+                    days: 1,
+                    ..Default::default()
+                }),
+                Datum::Interval(Interval {
+                    micros: 25 * 3600 * 1_000_000,
+                    ..Default::default()
+                }),
+                Some(Ordering::Less),
+            ),
+            (
+                Datum::Bytea(vec![1]),
+                Datum::Bytea(vec![1, 0]),
+                Some(Ordering::Less),
+            ),
+            (
+                Datum::Uuid([0xff; 16]),
+                Datum::Uuid([0; 16]),
+                Some(Ordering::Greater),
+            ),
+            (
+                Datum::Array(vec![int(1), int(2)]),
+                Datum::Array(vec![int(1), int(3)]),
+                Some(Ordering::Less),
+            ),
+            (
+                Datum::Array(vec![int(1)]),
+                Datum::Array(vec![int(1), int(0)]),
+                Some(Ordering::Less),
+            ),
+            (
+                Datum::Array(vec![Datum::Null]),
+                Datum::Array(vec![int(i64::MAX)]),
+                Some(Ordering::Greater),
+            ),
+            (
+                Datum::Array(vec![int(1), Datum::Null]),
+                Datum::Array(vec![int(1), Datum::Null]),
+                Some(Ordering::Equal),
+            ),
+            (
+                Datum::Array(vec![]),
+                Datum::Array(vec![]),
+                Some(Ordering::Equal),
+            ),
+            (Datum::Boolean(true), int(1), None),
+        ];
+        for (d1, d2, expected) in tests {
+            assert_eq!(d1.compare(&d2), expected, "{d1:?} {d2:?}");
+        }
+    }
+
+    #[test]
+    fn numeric_typmod() -> Result<()> {
+        let c = NumericConstraints::new(10, 2)?;
+        assert_eq!(c.typmod(), (10 << 16 | 2) + 4);
+        assert_eq!(NumericConstraints::from_typmod(c.typmod())?, Some(c));
+        assert_eq!(NumericConstraints::from_typmod(-1)?, None);
+        assert!(NumericConstraints::new(29, 0).is_err());
+        assert!(NumericConstraints::new(2, 3).is_err());
+
+        let c = NumericConstraints::new(4, 2)?;
+        assert_eq!(c.apply(Decimal::new(12345, 3))?, Decimal::new(1235, 2));
+        assert!(c.apply(Decimal::new(100, 0)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn numeric_arithmetic() -> Result<()> {
+        let d1 = Datum::Numeric(Decimal::new(125, 2));
+        let d2 = Datum::Numeric(Decimal::new(5, 1));
+        assert_eq!(
+            (d1.clone() + d2.clone())?,
+            Datum::Numeric(Decimal::new(175, 2))
+        );
+        assert_eq!((d1 - d2)?, Datum::Numeric(Decimal::new(75, 2)));
+        assert!(
+            (Datum::Numeric(Decimal::MAX) + Datum::Numeric(Decimal::ONE))
+                .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn bytea_text() -> Result<()> {
+        let tests: [(&str, &[u8]); 6] = [
+            ("\\xDEADBEEF", &[0xde, 0xad, 0xbe, 0xef]),
+            ("\\x", &[]),
+            ("\\x 01 0a", &[0x01, 0x0a]),
+            ("", &[]),
+            ("ab\\\\c", b"ab\\c"),
+            ("\\000\\377", &[0, 0xff]),
+        ];
+        for (s, bytes) in tests {
+            assert_eq!(parse_bytea(s)?, bytes, "{s}");
+        }
+        assert_eq!(format_bytea(&[0xde, 0xad, 0x00]), "\\xdead00");
+        assert_eq!(format_bytea(&[]), "\\x");
+
+        for s in ["\\xabc", "\\xgg", "a\\b", "\\400"] {
+            let e = parse_bytea(s).unwrap_err();
+            assert_eq!(e.code(), "22P02", "{s}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn uuid_text() -> Result<()> {
+        let uuid = [
+            0xa0, 0xee, 0xbc, 0x99, 0x9c, 0x0b, 0x4e, 0xf8, 0xbb, 0x6d, 0x6b,
+            0xb9, 0xbd, 0x38, 0x0a, 0x11,
+        ];
+        let canonical = "a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11";
+        for s in [
+            canonical,
+            "A0EEBC99-9C0B-4EF8-BB6D-6BB9BD380A11",
+            "{a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11}",
+            "a0eebc999c0b4ef8bb6d6bb9bd380a11",
+            " a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11 ",
+        ] {
+            assert_eq!(parse_uuid(s)?, uuid, "{s}");
+        }
+        assert_eq!(format_uuid(&uuid), canonical);
+        assert_eq!(
+            format_uuid(&[0; 16]),
+            "00000000-0000-0000-0000-000000000000"
+        );
+
+        for s in [
+            "",
+            "a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a1",
+            "a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a1g",
+            "a0eebc999-c0b-4ef8-bb6d-6bb9bd380a11",
+            "{a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11",
+            "+0eebc999c0b4ef8bb6d6bb9bd380a11",
+            "é0eebc999c0b4ef8bb6d6bb9bd380a1",
+        ] {
+            let e = parse_uuid(s).unwrap_err();
+            assert_eq!(e.code(), "22P02", "{s}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn array_text() {
+        let text = |s: &str| Some(s.to_string());
+        let tests = [
+            (vec![text("1"), text("2"), text("3")], "{1,2,3}"),
+            (vec![], "{}"),
+            (vec![text("a"), None], "{a,NULL}"),
+            (vec![text(""), text("null")], r#"{"","null"}"#),
+            (vec![text("a b"), text("{x}")], r#"{"a b","{x}"}"#),
+            (
+                vec![text(r#"say "hi""#), text(r"a\b")],
+                r#"{"say \"hi\"","a\\b"}"#,
+            ),
+        ];
+        for (elems, expected) in tests {
+            assert_eq!(format_array(elems), expected);
+        }
+        let datum = Datum::Array(vec![
+            Datum::Boolean(true),
+            Datum::Null,
+            Datum::Boolean(false),
+        ]);
+        assert_eq!(datum.to_string(), "{t,NULL,f}");
+    }
+
+    #[test]
+    fn array_parse() -> Result<()> {
+        let text = |s: &str| Some(s.to_string());
+        let tests = [
+            ("{1,2,3}", vec![text("1"), text("2"), text("3")]),
+            (" { } ", vec![]),
+            ("{ a , NULL,null }", vec![text("a"), None, None]),
+            (r#"{"","NULL"}"#, vec![text(""), text("NULL")]),
+            (
+                r#"{"say \"hi\"",a\,b}"#,
+                vec![text(r#"say "hi""#), text("a,b")],
+            ),
+            (r"{a\ }", vec![text("a ")]),
+        ];
+        for (s, elems) in tests {
+            assert_eq!(parse_array(s)?, elems, "{s}");
+        }
+        for s in ["1,2", "{1,,2}", "{1,}", r#"{"a"b}"#, "{{1},{2}}", r#"{"a}"#]
+        {
+            let e = parse_array(s).unwrap_err();
+            assert_eq!(e.code(), "22P02", "{s}");
+        }
+
+        // the quoting of format_array is reversed.
+        let elems = vec![text(r#"a "b"\"#), text(" "), None, text("{}")];
+        assert_eq!(parse_array(&format_array(elems.clone()))?, elems);
+        Ok(())
+    }
+
+    #[test]
+    fn arithmetic_overflow() {
+        let overflows = [
+            Datum::Int64(i64::MAX) + Datum::Int64(1),
+            Datum::Int64(i64::MIN) + Datum::Int64(-1),
+            Datum::Int64(i64::MIN) - Datum::Int64(1),
+            Datum::Int64(i64::MAX) - Datum::Int64(-1),
+            Datum::Numeric(Decimal::MAX) + Datum::Numeric(Decimal::ONE),
+            Datum::Numeric(Decimal::MIN) - Datum::Numeric(Decimal::ONE),
+        ];
+        for result in overflows {
+            match result {
+                Err(e) => assert_eq!(e.code(), "22003", "{e}"),
+                Ok(d) => panic!("expect overflow, got {d}"),
+            }
+        }
+        assert_eq!(
+            (Datum::Int64(i64::MAX) + Datum::Int64(i64::MIN)).ok(),
+            Some(Datum::Int64(-1))
+        );
+    }
+
+    #[test]
+    fn interval_arithmetic() -> Result<()> {
+        let ts = |s| datetime::parse_timestamp(s).map(Datum::Timestamp);
+        let interval = |s| datetime::parse_interval(s).map(Datum::Interval);
+        assert_eq!(
+            (ts("2024-01-31 10:00:00")? + interval("1 mon 2 hours")?)?,
+            ts("2024-02-29 12:00:00")?
+        );
+        assert_eq!(
+            (ts("2024-03-31 00:00:00")? - interval("1 mon 1 day")?)?,
+            ts("2024-02-28 00:00:00")?
+        );
+        assert_eq!(
+            (interval("1 day 01:00:00")? + interval("1 mon -02:00:00")?)?,
+            interval("1 mon 1 day -01:00:00")?
+        );
+        assert_eq!(
+            (interval("1 day")? - interval("1 year")?)?,
+            interval("-1 years +1 day")?
+        );
+
+        let e = (ts("2000-01-01")?
+            - Datum::Interval(Interval {
+                micros: i64::MIN,
+                ..Default::default()
+            }))
+        .unwrap_err();
+        assert_eq!(e.code(), "22008");
+        Ok(())
+    }
 }
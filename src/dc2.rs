@@ -1,10 +1,12 @@
 mod buf;
 mod bufmgr;
-mod codec;
+pub(crate) mod codec;
 mod eviction;
 mod lp;
 mod meta;
 mod node;
 mod opaque;
 mod page;
-mod tree;
+pub(crate) mod slice;
+pub(crate) mod tree;
+pub(crate) mod wal;
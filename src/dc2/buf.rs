@@ -16,6 +16,18 @@ impl PinGuard {
         PinGuard(Arc::new(PinGuardInner::new(buf)))
     }
 
+    /// Lock the buffer if no one else holds the lock.
+    pub fn try_lock(&self) -> Option<LockGuard> {
+        let guard = self.0.buf.inner.state.try_lock().ok()?;
+        Some(LockGuard {
+            pin_guard: self.clone(),
+            guard: unsafe {
+                // transmute to a 'static Guard.
+                mem::transmute(guard)
+            },
+        })
+    }
+
     pub fn lock(&self) -> LockGuard {
         LockGuard {
             pin_guard: self.clone(),
@@ -90,6 +102,22 @@ impl Buffer {
             inner: Arc::new(inner),
         }
     }
+
+    /// Returns true if the buffer is held by any [`PinGuard`].
+    /// A pinned buffer can not be evicted.
+    pub fn is_pinned(&self) -> bool {
+        self.inner.pin_count.load(Ordering::Acquire) > 0
+    }
+
+    /// Returns true if the page is modified since it was written to
+    /// disk. A buffer locked by others is taken as dirty, since it may
+    /// be modified at the moment.
+    pub fn is_dirty(&self) -> bool {
+        self.inner
+            .state
+            .try_lock()
+            .map_or(true, |state| state.is_dirty)
+    }
 }
 
 /// Shared state for a buffer. Operations on this struct should
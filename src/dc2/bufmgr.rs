@@ -1,16 +1,17 @@
 use crate::common::error::{DCError, FloppyError, Result};
 
 use crate::dc2::{
-    buf::{Buffer, PinGuard},
+    buf::{Buffer, LockGuard, PinGuard},
     eviction::EvictionPool,
-    page::{Page, PageId, PAGE_SIZE},
+    meta::MetaPage,
+    page::{Page, PageId},
+    wal::{Wal, CHECKPOINT_LOG_SIZE},
 };
 use crate::env::*;
 use dashmap::DashMap;
-use std::sync::Arc;
 use std::{
     path::{Path, PathBuf},
-    sync::atomic::{AtomicU32, Ordering},
+    sync::atomic::{AtomicU32, AtomicU64, Ordering},
 };
 
 /// BufferPool manages the in memory cache AND file usage of pages.
@@ -39,7 +40,34 @@ pub(crate) struct BufMgr<E: Env> {
     active_pages: DashMap<PageId, Buffer>,
     eviction_pages: EvictionPool,
     file_path: PathBuf,
+    /// The size of every page of the file.
+    page_size: usize,
     next_page_id: AtomicU32,
+    stats: Stats,
+    wal: Wal<E>,
+}
+
+/// Counters of the buffer pool.
+#[derive(Default)]
+struct Stats {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+}
+
+/// A snapshot of the buffer pool counters, see [`BufMgr::stats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct BufMgrStats {
+    /// The number of [`BufMgr::fix_page`] calls served from memory.
+    pub hits: u64,
+    /// The number of [`BufMgr::fix_page`] calls that read the disk.
+    pub misses: u64,
+    /// The number of pages evicted from the buffer pool.
+    pub evictions: u64,
+    /// The number of modified pages not written to disk yet.
+    pub dirty_pages: usize,
+    /// The number of pages held in the buffer pool.
+    pub active_pages: usize,
 }
 
 impl<E> BufMgr<E>
@@ -48,43 +76,99 @@ where
 {
     /// Open the file at the given path. If the file does not exist, create it.
     /// Page 0 is initialized with an empty freelist page header.
+    /// The write-ahead log of the file is replayed before any page is read.
+    /// The pages of an existing file must be of `page_size`.
     pub async fn open<P: AsRef<Path>>(
         env: E,
         path: P,
         pool_size: usize,
+        page_size: usize,
     ) -> Result<Self> {
         let file = env.open_file(path.as_ref()).await?;
-        let size = file.file_size().await;
-        let next_page_id = if size == 0 {
-            let mut meta_page = Page::alloc(PAGE_SIZE)?;
-            meta_page.init(0);
+        if file.file_size().await == 0 {
+            let mut meta_page = Page::alloc(page_size)?;
+            meta_page.init(MetaPage::opaque_size());
 
             file.write_at(meta_page.data(), 0).await?;
             file.sync_all().await?;
-            1
         } else {
-            size / PAGE_SIZE
-        };
+            // the opaque space of the meta page ends at the end of the
+            // page.
+            let mut header = Page::alloc(Page::header_size())?;
+            file.read_exact_at(header.raw_data_mut(), 0).await?;
+            header.set_inited();
+            let file_page_size =
+                header.get_opaque() as usize + MetaPage::opaque_size();
+            if file_page_size != page_size {
+                return Err(FloppyError::Internal(format!(
+                    "the page size of {:?} is {file_page_size}, not {page_size}",
+                    path.as_ref()
+                )));
+            }
+        }
+        let wal = Wal::open(&env, path.as_ref(), &file, page_size).await?;
+        // recovery may extend the file with pages that were never written.
+        let next_page_id = file.file_size().await / page_size;
 
         Ok(Self {
             env,
             active_pages: DashMap::new(),
             eviction_pages: EvictionPool::new(pool_size),
             file_path: path.as_ref().to_path_buf(),
+            page_size,
             next_page_id: AtomicU32::new(next_page_id as u32),
+            stats: Stats::default(),
+            wal,
         })
     }
 
+    /// The size of every page of the file.
+    pub fn page_size(&self) -> usize {
+        self.page_size
+    }
+
+    /// Returns a snapshot of the buffer pool counters. Pages locked by
+    /// others are not counted as dirty pages, so that we never wait for
+    /// a lock here.
+    pub fn stats(&self) -> BufMgrStats {
+        let bufs = self
+            .active_pages
+            .iter()
+            .map(|e| e.value().clone())
+            .collect::<Vec<Buffer>>();
+        let active_pages = bufs.len();
+        let dirty_pages = bufs
+            .into_iter()
+            .filter(|buf| {
+                PinGuard::new(buf.clone())
+                    .try_lock()
+                    .map_or(false, |lock_guard| lock_guard.is_dirty)
+            })
+            .count();
+        BufMgrStats {
+            hits: self.stats.hits.load(Ordering::Relaxed),
+            misses: self.stats.misses.load(Ordering::Relaxed),
+            evictions: self.stats.evictions.load(Ordering::Relaxed),
+            dirty_pages,
+            active_pages,
+        }
+    }
+
     /// Allocate a new page from buffer pool. This happens when a node in the
     /// tree splits.
     /// To allocate a page, we first check if there is a free page in the
     /// freelist. If there is, we return the page. Otherwise, we extend the
     /// file and return the new page.
     pub async fn alloc_page(&self) -> Result<PinGuard> {
+        self.reserve_frame().await?;
         let page_id: PageId = self.next_page_id.fetch_add(1, Ordering::Release);
-        let buf = Buffer::new(page_id, Page::alloc(PAGE_SIZE)?);
-        self.active_pages.insert(page_id, buf.clone());
-        let pin_guard = PinGuard::new(buf);
+        let buf = Buffer::new(page_id, Page::alloc(self.page_size)?);
+        let pin_guard = PinGuard::new(buf.clone());
+        self.active_pages.insert(page_id, buf);
+        self.eviction_pages.insert(page_id);
+        // a new page must be written to disk even if it is never
+        // modified.
+        pin_guard.lock().is_dirty = true;
         Ok(pin_guard)
     }
 
@@ -96,9 +180,67 @@ where
         todo!()
     }
 
-    /// Flush the page content to disk.
-    pub async fn flush_page(&self, _page: &Page) -> Result<()> {
-        todo!()
+    /// Log the images of the pages locked by `guards` as a single atomic
+    /// change and mark them dirty. The pages must be logged before they
+    /// are unlocked.
+    pub async fn log_pages(&self, guards: &mut [&mut LockGuard]) -> Result<()> {
+        self.wal.append(guards).await?;
+        for guard in guards.iter_mut() {
+            guard.is_dirty = true;
+        }
+        Ok(())
+    }
+
+    /// Make every logged change durable without writing the pages.
+    pub async fn flush_log(&self) -> Result<()> {
+        self.wal.flush_all().await
+    }
+
+    /// Write every dirty page to disk so that the log before it is not
+    /// needed by recovery, and remove that part of the log.
+    pub async fn checkpoint(&self) -> Result<()> {
+        let lsn = self.wal.begin_checkpoint().await?;
+        self.flush_all().await?;
+        self.wal.end_checkpoint(lsn).await
+    }
+
+    /// Returns true if the log has grown large enough since the last
+    /// checkpoint.
+    pub async fn needs_checkpoint(&self) -> bool {
+        self.wal.log_size().await >= CHECKPOINT_LOG_SIZE
+    }
+
+    /// Flush the page content to disk. The log is flushed up to the
+    /// page's lsn first.
+    pub async fn flush_page(&self, page_id: PageId, page: &Page) -> Result<()> {
+        self.wal.flush(page.get_lsn()).await?;
+        let file = self.env.open_file(self.file_path.as_path()).await?;
+        let pos = page_id as u64 * self.page_size as u64;
+        file.write_at(page.data(), pos).await?;
+        Ok(())
+    }
+
+    /// Write every dirty page in the buffer pool to disk and sync
+    /// the file.
+    pub async fn flush_all(&self) -> Result<()> {
+        self.wal.flush_all().await?;
+        let bufs = self
+            .active_pages
+            .iter()
+            .map(|e| e.value().clone())
+            .collect::<Vec<Buffer>>();
+        for buf in bufs {
+            let pin_guard = PinGuard::new(buf);
+            let mut lock_guard = pin_guard.lock();
+            if lock_guard.is_dirty {
+                self.flush_page(lock_guard.page_id, &lock_guard.page)
+                    .await?;
+                lock_guard.is_dirty = false;
+            }
+        }
+        let file = self.env.open_file(self.file_path.as_path()).await?;
+        file.sync_all().await?;
+        Ok(())
     }
 
     /// Fix and lock a page frame in the buffer pool.
@@ -111,34 +253,227 @@ where
             ))));
         }
 
-        let entry = self.active_pages.get(&page_id);
-        if let Some(entry) = entry {
-            let frame = entry.value();
-            Ok(PinGuard::new(frame.clone()))
-        } else {
-            let buf = self.eviction_pages.evict();
-            let pin_guard = PinGuard::new(buf.clone());
-            let mut lock_guard = pin_guard.lock();
+        // the page is pinned while the entry is held, so that it can not
+        // be evicted in between.
+        let pin_guard = self
+            .active_pages
+            .get(&page_id)
+            .map(|entry| PinGuard::new(entry.value().clone()));
+        if let Some(pin_guard) = pin_guard {
+            self.stats.hits.fetch_add(1, Ordering::Relaxed);
+            self.eviction_pages.insert(page_id);
+            return Ok(pin_guard);
+        }
 
-            if lock_guard.is_dirty {
-                self.flush_page(&lock_guard.page).await?;
+        self.stats.misses.fetch_add(1, Ordering::Relaxed);
+        self.reserve_frame().await?;
+        let mut page = Page::alloc(self.page_size)?;
+        self.read_page(page_id, &mut page).await?;
+        let buf = Buffer::new(page_id, page);
+        // another task may have read the same page concurrently,
+        // the first one wins.
+        let pin_guard = PinGuard::new(
+            self.active_pages
+                .entry(page_id)
+                .or_insert(buf)
+                .value()
+                .clone(),
+        );
+        self.eviction_pages.insert(page_id);
+        Ok(pin_guard)
+    }
+
+    /// Evict pages until there is room for a new page in the buffer pool.
+    /// If every page is pinned, the pool grows beyond its size until some
+    /// of the pages are unpinned.
+    async fn reserve_frame(&self) -> Result<()> {
+        while self.active_pages.len() >= self.eviction_pages.pool_size() {
+            if !self.evict().await? {
+                break;
             }
+        }
+        Ok(())
+    }
+
+    /// Evict the least recently used page that is not pinned, a dirty
+    /// page is written back to disk before it is evicted.
+    /// Returns false if there is no page can be evicted.
+    async fn evict(&self) -> Result<bool> {
+        let mut cursor = 0;
+        while let Some(page_id) =
+            self.eviction_pages.next_candidate(&mut cursor)
+        {
+            if self.try_evict(page_id).await? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Write back and evict the page unless it is in use.
+    /// Returns true if the page is evicted.
+    async fn try_evict(&self, page_id: PageId) -> Result<bool> {
+        let buf = match self.active_pages.get(&page_id) {
+            Some(entry) => entry.value().clone(),
+            None => {
+                self.eviction_pages.delete(page_id);
+                return Ok(false);
+            }
+        };
+        if buf.is_pinned() || !self.write_back(buf).await? {
+            return Ok(false);
+        }
+        Ok(self.remove_clean(page_id))
+    }
 
-            self.read_page(page_id, &mut lock_guard.page).await?;
+    /// Write the page of `buf` to disk if it is dirty.
+    /// Returns false if the page is locked by others.
+    async fn write_back(&self, buf: Buffer) -> Result<bool> {
+        let pin_guard = PinGuard::new(buf);
+        let mut lock_guard = match pin_guard.try_lock() {
+            Some(lock_guard) => lock_guard,
+            None => return Ok(false),
+        };
+        if lock_guard.is_dirty {
+            self.flush_page(lock_guard.page_id, &lock_guard.page)
+                .await?;
             lock_guard.is_dirty = false;
-            lock_guard.page_id = page_id;
-            drop(lock_guard);
-            self.active_pages.insert(page_id, buf.clone());
-            Ok(pin_guard)
         }
+        Ok(true)
+    }
+
+    /// Remove the page from the buffer pool if it is neither pinned
+    /// nor dirty. The page may be pinned and modified again after it
+    /// is written back, then it is kept, otherwise the change is lost.
+    fn remove_clean(&self, page_id: PageId) -> bool {
+        let removed = self
+            .active_pages
+            .remove_if(&page_id, |_, buf| !buf.is_pinned() && !buf.is_dirty())
+            .is_some();
+        if removed {
+            self.eviction_pages.delete(page_id);
+            self.stats.evictions.fetch_add(1, Ordering::Relaxed);
+        }
+        removed
     }
 
     async fn read_page(&self, page_id: PageId, page: &mut Page) -> Result<()> {
         let file = self.env.open_file(self.file_path.as_path()).await?;
-        let pos = page_id as u64 * PAGE_SIZE as u64;
+        let pos = page_id as u64 * self.page_size as u64;
         match file.read_exact_at(page.raw_data_mut(), pos).await {
             Err(e) => Err(FloppyError::Io(e)),
-            Ok(_) => Ok(()),
+            Ok(_) => {
+                page.set_inited();
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dc2::codec::Record;
+    use crate::dc2::page::PAGE_SIZE;
+    use crate::dc2::wal::remove_wal_files;
+    use crate::env::stdenv::StdEnv;
+
+    #[tokio::test]
+    async fn evict_pages() -> Result<()> {
+        let path = "tmp_test_bufmgr_evict_pages";
+        let _ = std::fs::remove_file(path);
+        remove_wal_files(path.as_ref());
+        let pool_size = 10;
+        let page_count = 100;
+        let buf_mgr = BufMgr::open(StdEnv, path, pool_size, PAGE_SIZE).await?;
+
+        let mut page_ids = vec![];
+        for _ in 0..page_count {
+            let pin_guard = buf_mgr.alloc_page().await?;
+            let mut lock_guard = pin_guard.lock();
+            let page_id = lock_guard.page_id;
+            let v = page_id.to_be_bytes();
+            lock_guard.page.init(0);
+            lock_guard.page.insert_slot(
+                Record {
+                    key: v.as_slice(),
+                    value: v.as_slice(),
+                },
+                1,
+            )?;
+            page_ids.push(page_id);
+        }
+        assert!(buf_mgr.active_pages.len() <= pool_size);
+
+        for page_id in page_ids.iter().chain(page_ids.iter()) {
+            let pin_guard = buf_mgr.fix_page(*page_id).await?;
+            let lock_guard = pin_guard.lock();
+            let slot = lock_guard.page.get_slot(1)?;
+            let key = Record::<&[u8]>::decode_key(slot);
+            assert_eq!(key, page_id.to_be_bytes().as_slice());
         }
+        assert!(buf_mgr.active_pages.len() <= pool_size);
+
+        let stats = buf_mgr.stats();
+        assert_eq!(stats.misses, page_count as u64 * 2);
+        assert!(stats.evictions >= (page_count * 3 - pool_size) as u64);
+        assert_eq!(stats.active_pages, buf_mgr.active_pages.len());
+        assert!(stats.dirty_pages <= stats.active_pages);
+
+        // pinned pages are never evicted.
+        let pinned = buf_mgr.fix_page(page_ids[0]).await?;
+        for page_id in &page_ids[1..] {
+            buf_mgr.fix_page(*page_id).await?;
+        }
+        assert!(buf_mgr.active_pages.contains_key(&page_ids[0]));
+        drop(pinned);
+
+        std::fs::remove_file(path)?;
+        remove_wal_files(path.as_ref());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn evict_modified_after_write_back() -> Result<()> {
+        let path = "tmp_test_bufmgr_evict_modified_after_write_back";
+        let _ = std::fs::remove_file(path);
+        remove_wal_files(path.as_ref());
+        let buf_mgr = BufMgr::open(StdEnv, path, 10, PAGE_SIZE).await?;
+        let page_id = {
+            let pin_guard = buf_mgr.alloc_page().await?;
+            let mut lock_guard = pin_guard.lock();
+            lock_guard.page.init(0);
+            lock_guard.page_id
+        };
+
+        let buf = buf_mgr.active_pages.get(&page_id).unwrap().clone();
+        assert!(buf_mgr.write_back(buf).await?);
+        // a writer modifies the page before it is removed.
+        {
+            let pin_guard = buf_mgr.fix_page(page_id).await?;
+            let mut lock_guard = pin_guard.lock();
+            let v = page_id.to_be_bytes();
+            let record = Record {
+                key: v.as_slice(),
+                value: v.as_slice(),
+            };
+            lock_guard.page.insert_slot(record, 1)?;
+            buf_mgr.log_pages(&mut [&mut lock_guard]).await?;
+        }
+        assert!(!buf_mgr.remove_clean(page_id));
+        assert!(buf_mgr.active_pages.contains_key(&page_id));
+
+        // the change is written back by the next eviction.
+        assert!(buf_mgr.try_evict(page_id).await?);
+        assert!(!buf_mgr.active_pages.contains_key(&page_id));
+        let pin_guard = buf_mgr.fix_page(page_id).await?;
+        let lock_guard = pin_guard.lock();
+        let key = Record::<&[u8]>::decode_key(lock_guard.page.get_slot(1)?);
+        assert_eq!(key, page_id.to_be_bytes().as_slice());
+        drop(lock_guard);
+
+        std::fs::remove_file(path)?;
+        remove_wal_files(path.as_ref());
+        Ok(())
     }
 }
@@ -1,23 +1,102 @@
-use crate::dc2::buf::Buffer;
+use crate::dc2::page::PageId;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Bound;
+use std::sync::Mutex;
 
-pub(crate) struct EvictionPool {}
+/// `EvictionPool` tracks the pages in the buffer pool in least
+/// recently used order.
+pub(crate) struct EvictionPool {
+    pool_size: usize,
+    lru: Mutex<Lru>,
+}
+
+#[derive(Default)]
+struct Lru {
+    /// A logical clock that increases on every access.
+    tick: u64,
+    /// The last access tick of each page.
+    ticks: HashMap<PageId, u64>,
+    /// Pages ordered by their last access tick.
+    order: BTreeMap<u64, PageId>,
+}
 
 impl EvictionPool {
-    pub fn new(_pool_size: usize) -> EvictionPool {
-        Self {}
+    pub fn new(pool_size: usize) -> EvictionPool {
+        Self {
+            pool_size,
+            lru: Mutex::new(Lru::default()),
+        }
+    }
+
+    /// The number of pages the buffer pool can hold before
+    /// evicting.
+    pub fn pool_size(&self) -> usize {
+        self.pool_size
+    }
+
+    /// Makes the page a candidate for eviction, or marks it as the
+    /// most recently used if it is already a candidate.
+    pub fn insert(&self, page_id: PageId) {
+        let mut lru = self.lru.lock().unwrap();
+        lru.tick += 1;
+        let tick = lru.tick;
+        if let Some(old) = lru.ticks.insert(page_id, tick) {
+            lru.order.remove(&old);
+        }
+        lru.order.insert(tick, page_id);
+    }
+
+    /// Ensures the page is no longer a candidate for eviction.
+    pub fn delete(&self, page_id: PageId) {
+        let mut lru = self.lru.lock().unwrap();
+        if let Some(tick) = lru.ticks.remove(&page_id) {
+            lru.order.remove(&tick);
+        }
+    }
+
+    /// Returns the least recently used candidate for eviction after
+    /// `cursor`, and moves `cursor` past it. A cursor starts at 0.
+    /// [`BufMgr`] should skip the pages that are pinned.
+    pub fn next_candidate(&self, cursor: &mut u64) -> Option<PageId> {
+        let lru = self.lru.lock().unwrap();
+        let (tick, page_id) = lru
+            .order
+            .range((Bound::Excluded(*cursor), Bound::Unbounded))
+            .next()?;
+        *cursor = *tick;
+        Some(*page_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidates(pool: &EvictionPool) -> Vec<PageId> {
+        let mut cursor = 0;
+        std::iter::from_fn(|| pool.next_candidate(&mut cursor)).collect()
     }
 
-    /// Makes the page held by `BufferFrame` a candidate for eviction.
-    pub fn insert(&self, _frame: Buffer) {}
+    #[test]
+    fn lru_order() {
+        let pool = EvictionPool::new(3);
+        pool.insert(1);
+        pool.insert(2);
+        pool.insert(3);
+        assert_eq!(candidates(&pool), vec![1, 2, 3]);
+
+        pool.insert(1);
+        assert_eq!(candidates(&pool), vec![2, 3, 1]);
 
-    /// Ensures the page held by `BufferFrame` is no longer a candidate for
-    /// eviction.
-    pub fn delete(&self, _frame: Buffer) {}
+        pool.delete(3);
+        assert_eq!(candidates(&pool), vec![2, 1]);
 
-    /// Evicts a page previously marked as a candidate for eviction (if any),
-    /// following the LRU eviction strategy.
-    /// [`BufMgr`] can use this frame after call this method.
-    pub fn evict(&self) -> Buffer {
-        todo!()
+        // a page used again after the cursor is visited again.
+        let mut cursor = 0;
+        assert_eq!(pool.next_candidate(&mut cursor), Some(2));
+        pool.insert(2);
+        assert_eq!(pool.next_candidate(&mut cursor), Some(1));
+        assert_eq!(pool.next_candidate(&mut cursor), Some(2));
+        assert_eq!(pool.next_candidate(&mut cursor), None);
     }
 }
@@ -2,12 +2,12 @@
 /// - [`PageOffset`] is the byte offset within a page.
 /// - [`LinePointer`] is a pointer on a page which contains a [`PageOffset`].
 /// - [`LpOffset`] is a 1 based index into an array of [`LinePointer`].
-use crate::dc2::page::PAGE_SIZE;
+use crate::dc2::page::MAX_PAGE_SIZE;
 use std::mem;
 
 /// PageOffset is the byte offset within a page starts at 0.
 /// Only 15 bits are used, see [`LinePointer`] definition below.
-/// So in theory, the maximus page size is 32 KB, we use 8 KB by default.
+/// So the maximum page size is 32 KB, we use 8 KB by default.
 pub(crate) type PageOffset = u16;
 
 /// A line pointer on a page. The 32 bit is arranged as:
@@ -97,7 +97,7 @@ pub type SlotId = u16;
 const INVALID_OFFSET_NUMBER: SlotId = 0;
 const FIRST_OFFSET_NUMBER: SlotId = 1;
 const MAX_OFFSET_NUMBER: SlotId =
-    (PAGE_SIZE / mem::size_of::<LinePointer>()) as SlotId;
+    (MAX_PAGE_SIZE / mem::size_of::<LinePointer>()) as SlotId;
 
 pub(crate) fn is_valid_slot_id(slot_id: SlotId) -> bool {
     slot_id != INVALID_OFFSET_NUMBER && slot_id <= MAX_OFFSET_NUMBER
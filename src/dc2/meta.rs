@@ -17,7 +17,7 @@ impl<'a> MetaPage<'a> {
         Self { page }
     }
 
-    fn opaque_size() -> usize {
+    pub fn opaque_size() -> usize {
         mem::size_of::<PageId>()
     }
 
@@ -2,8 +2,7 @@ use crate::common::{
     error::{DCError, FloppyError, Result},
     ivec::IVec,
 };
-use crate::dc2::lp::{LinePointer, PageOffset};
-use crate::dc2::page::PAGE_SIZE;
+use crate::dc2::lp::LinePointer;
 use crate::dc2::{
     codec::{Codec, Decoder, Record},
     lp::SlotId,
@@ -11,7 +10,9 @@ use crate::dc2::{
     page::{Page, PageId},
 };
 use paste::paste;
-use std::{cmp::Ordering, fmt, marker::PhantomData, mem};
+use std::{
+    borrow::Cow, cmp::Ordering, fmt, marker::PhantomData, mem, ops::Range,
+};
 
 pub(crate) trait NodeKey:
     AsRef<[u8]> + Codec + Ord + fmt::Debug
@@ -42,6 +43,7 @@ const BTP_LEAF: u16 = 1 << 0; // leaf page
 const BTP_ROOT: u16 = 1 << 1; // root page
 const BTP_META: u16 = 1 << 3; // meta page
 const BTP_INCOMPLETE_SPLIT: u16 = 1 << 7; // right sibling's downlink is missing
+const BTP_PREFIX: u16 = 1 << 9; // keys are stored without a common prefix
 
 pub(super) struct Node<'a> {
     page: &'a mut Page,
@@ -68,8 +70,16 @@ impl<'a> Node<'a> {
             + mem::size_of::<NodeFlags>()
     }
 
-    pub fn will_overfull(&self, record_size: usize) -> bool {
+    /// Whether a record of `record_size` bytes doesn't fit in the
+    /// node, or the node already has `max_records` records if it is
+    /// given.
+    pub fn will_overfull(
+        &self,
+        record_size: usize,
+        max_records: Option<usize>,
+    ) -> bool {
         self.page.get_record_free_space() < record_size
+            || max_records.map_or(false, |max| num_records(self) >= max)
     }
 
     /// We assumes we can fit at least three items per page
@@ -78,8 +88,8 @@ impl<'a> Node<'a> {
     /// work sometimes, but could cause failures later on depending on
     /// what else gets put on their page
     ///
-    pub fn max_record_size() -> usize {
-        (PAGE_SIZE - Page::header_size() - Node::opaque_size()) / 3
+    pub fn max_record_size(page_size: usize) -> usize {
+        (page_size - Page::header_size() - Node::opaque_size()) / 3
     }
 
     #[inline(always)]
@@ -89,7 +99,7 @@ impl<'a> Node<'a> {
 
     #[inline(always)]
     pub fn is_root(&self) -> bool {
-        (self.get_flags() & BTP_LEAF) != 0
+        (self.get_flags() & BTP_ROOT) != 0
     }
 
     #[inline(always)]
@@ -97,6 +107,11 @@ impl<'a> Node<'a> {
         (self.get_flags() & BTP_INCOMPLETE_SPLIT) != 0
     }
 
+    #[inline(always)]
+    pub fn is_prefix_compressed(&self) -> bool {
+        (self.get_flags() & BTP_PREFIX) != 0
+    }
+
     #[inline(always)]
     pub fn is_leftmost(&self) -> bool {
         self.get_left_sibling() == 0
@@ -153,13 +168,13 @@ impl<'a, 'b, V> Iterator for NodeIterator<'a, 'b, V>
 where
     V: NodeValue,
 {
-    type Item = (&'a [u8], V);
+    type Item = (Cow<'a, [u8]>, V);
     fn next(&mut self) -> Option<Self::Item> {
         let max_slot = self.node.page.max_slot();
         if self.next_slot <= max_slot {
-            let record = get_record(self.node, self.next_slot).unwrap();
+            let entry = get_entry(self.node, self.next_slot).unwrap();
             self.next_slot += 1;
-            Some((record.key, record.value))
+            Some(entry)
         } else {
             None
         }
@@ -168,7 +183,7 @@ where
 
 pub(super) fn new_iterator<'a, 'b: 'a, V>(
     node: &'b Node<'a>,
-) -> impl 'a + 'b + Iterator<Item = (&'a [u8], V)>
+) -> impl 'a + 'b + Iterator<Item = (Cow<'a, [u8]>, V)>
 where
     V: NodeValue + 'a,
 {
@@ -176,6 +191,45 @@ where
     NodeIterator::new(node, next_slot)
 }
 
+pub(super) struct NodeRevIterator<'a, 'b: 'a, V> {
+    node: &'b Node<'a>,
+    next_slot: SlotId,
+    first_slot: SlotId,
+    _marker: PhantomData<V>,
+}
+
+impl<'a, 'b, V> Iterator for NodeRevIterator<'a, 'b, V>
+where
+    V: NodeValue,
+{
+    type Item = (Cow<'a, [u8]>, V);
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next_slot >= self.first_slot {
+            let entry = get_entry(self.node, self.next_slot).unwrap();
+            self.next_slot -= 1;
+            Some(entry)
+        } else {
+            None
+        }
+    }
+}
+
+/// Iterate the data records of `node` in descending key order.
+/// The high key is not included.
+pub(super) fn new_rev_iterator<'a, 'b: 'a, V>(
+    node: &'b Node<'a>,
+) -> impl 'a + 'b + Iterator<Item = (Cow<'a, [u8]>, V)>
+where
+    V: NodeValue + 'a,
+{
+    NodeRevIterator {
+        node,
+        next_slot: node.page.max_slot(),
+        first_slot: first_data_slot(node),
+        _marker: PhantomData::default(),
+    }
+}
+
 pub(super) struct NodeRangeIterator<'a, 'b: 'a, V> {
     node: &'b Node<'a>,
     next_slot: SlotId,
@@ -184,15 +238,14 @@ pub(super) struct NodeRangeIterator<'a, 'b: 'a, V> {
 }
 
 impl<'a, 'b, V> NodeRangeIterator<'a, 'b, V> {
-    fn new(
-        node: &'b Node<'a>,
-        next_slot: SlotId,
-        max_exclusive_slot: SlotId,
-    ) -> Self {
+    /// The slots before the first data slot, ie the high key and the
+    /// common prefix, and the ones past the max slot are not in the
+    /// range.
+    fn new(node: &'b Node<'a>, range: Range<SlotId>) -> Self {
         Self {
             node,
-            next_slot,
-            max_exclusive_slot,
+            next_slot: range.start.max(first_data_slot(node)),
+            max_exclusive_slot: range.end.min(node.page.max_slot() + 1),
             _marker: PhantomData::default(),
         }
     }
@@ -202,29 +255,32 @@ impl<'a, 'b, V> Iterator for NodeRangeIterator<'a, 'b, V>
 where
     V: NodeValue,
 {
-    type Item = (&'a [u8], V);
+    type Item = (Cow<'a, [u8]>, V);
     fn next(&mut self) -> Option<Self::Item> {
-        let max_slot = self.node.page.max_slot();
-        if self.next_slot <= max_slot
-            && self.next_slot < self.max_exclusive_slot
-        {
-            let record = get_record(self.node, self.next_slot).unwrap();
+        if self.next_slot < self.max_exclusive_slot {
+            let entry = get_entry(self.node, self.next_slot).unwrap();
             self.next_slot += 1;
-            Some((record.key, record.value))
+            Some(entry)
         } else {
             None
         }
     }
 }
 
+/// Iterate the data records in the half-open slot `range` of `node`.
+pub(super) fn new_range_iterator<'a, 'b: 'a, V>(
+    node: &'b Node<'a>,
+    range: Range<SlotId>,
+) -> NodeRangeIterator<'a, 'b, V> {
+    NodeRangeIterator::new(node, range)
+}
+
 pub(super) fn split_at<'a, 'b: 'a, V>(
     node: &'b Node<'a>,
     split_slot: SlotId,
 ) -> (NodeRangeIterator<'a, 'b, V>, NodeRangeIterator<'a, 'b, V>) {
-    let first_slot = first_data_slot(node);
-    let max_slot = node.page.max_slot();
-    let left = NodeRangeIterator::<V>::new(node, first_slot, split_slot);
-    let right = NodeRangeIterator::<V>::new(node, split_slot, max_slot + 1);
+    let left = NodeRangeIterator::<V>::new(node, 0..split_slot);
+    let right = NodeRangeIterator::<V>::new(node, split_slot..SlotId::MAX);
     (left, right)
 }
 
@@ -237,6 +293,52 @@ where
     Ok(unsafe { Record::decode_from(&mut dec) })
 }
 
+/// Get the key and the value of a data record. If the node is prefix
+/// compressed, the key is reconstructed from the common prefix and the
+/// stored suffix.
+fn get_entry<'a, V>(node: &'a Node, slot: SlotId) -> Result<(Cow<'a, [u8]>, V)>
+where
+    V: NodeValue,
+{
+    let record = get_record::<V>(node, slot)?;
+    let key = if node.is_prefix_compressed() {
+        Cow::Owned([key_prefix(node)?, record.key].concat())
+    } else {
+        Cow::Borrowed(record.key)
+    };
+    Ok((key, record.value))
+}
+
+/// Returns the common prefix of the keys in a prefix compressed node,
+/// it is stored in the slot following the high key.
+fn key_prefix<'a>(node: &'a Node) -> Result<&'a [u8]> {
+    if node.is_prefix_compressed() {
+        let slot = node.page.get_slot(prefix_slot(node))?;
+        Ok(Record::<&[u8]>::decode_key(slot))
+    } else {
+        Ok(&[])
+    }
+}
+
+fn prefix_slot(node: &Node) -> SlotId {
+    if node.is_rightmost() {
+        1
+    } else {
+        2
+    }
+}
+
+/// Compare a key stored as `prefix` + `suffix` with `target` without
+/// reconstructing the key.
+fn cmp_prefixed_key(prefix: &[u8], suffix: &[u8], target: &[u8]) -> Ordering {
+    let len = prefix.len().min(target.len());
+    match prefix[..len].cmp(&target[..len]) {
+        Ordering::Equal if target.len() < prefix.len() => Ordering::Greater,
+        Ordering::Equal => suffix.cmp(&target[len..]),
+        other => other,
+    }
+}
+
 /// Find a value in the leaf node. When [`Tree`] identifies the correct
 /// leaf node, it calls this function to get the value.
 /// The logic of following the right sibling ("move right") is handled
@@ -245,10 +347,8 @@ pub(super) fn find_in_leaf(node: &Node, target: &[u8]) -> Result<Option<IVec>> {
     match rank(node, target) {
         Err(_) => Ok(None),
         Ok(slot_id) => {
-            let slot_content = node.page.get_slot(slot_id)?;
-            Ok(Some(
-                Record::<&[u8]>::decode_value(slot_content, target).into(),
-            ))
+            let record = get_record::<&[u8]>(node, slot_id)?;
+            Ok(Some(record.value.into()))
         }
     }
 }
@@ -261,8 +361,35 @@ pub(super) fn find_child(node: &Node, target: &[u8]) -> Result<PageId> {
         Err(slot) => slot,
         Ok(slot) => slot,
     };
-    let slot_content = node.page.get_slot(slot_id - 1)?;
-    Ok(Record::decode_value(slot_content, target))
+    let record = get_record::<PageId>(node, slot_id - 1)?;
+    Ok(record.value)
+}
+
+/// Returns true if the internal node has the downlink of `page_id`
+/// whose key is `key`.
+pub(super) fn has_downlink(
+    node: &Node,
+    key: &[u8],
+    page_id: PageId,
+) -> Result<bool> {
+    match rank(node, key) {
+        Ok(slot_id) => {
+            Ok(get_record::<PageId>(node, slot_id)?.value == page_id)
+        }
+        Err(_) => Ok(false),
+    }
+}
+
+/// The downlink of the node's right sibling has been inserted into the
+/// parent, the split is complete.
+pub(super) fn clear_incomplete_split(node: &mut Node) {
+    node.set_flags(node.get_flags() & !BTP_INCOMPLETE_SPLIT);
+}
+
+/// Find the rightmost child ([`PageId`]) of a internal node.
+pub(super) fn last_child(node: &Node) -> Result<PageId> {
+    let record = get_record::<PageId>(node, node.page.max_slot())?;
+    Ok(record.value)
 }
 
 /// Insert a pair of key value into leaf node.
@@ -274,14 +401,64 @@ pub(super) fn insert_leaf_node(
     let key = record.key;
     validate_insertion_key(node, key)?;
 
+    let prefix = key_prefix(node)?;
+    if !key.starts_with(prefix) {
+        return rebuild_leaf_node(node, record);
+    }
     match rank(node, key) {
-        Err(slot_id) => node.page.insert_slot(record, slot_id),
+        Err(slot_id) => node.page.insert_slot(
+            Record {
+                key: &key[prefix.len()..],
+                value: record.value,
+            },
+            slot_id,
+        ),
         Ok(slot_id) => Err(FloppyError::DC(DCError::KeyAlreadyExists(
             format!("key already existed, key = {key:?}, slot_id = {slot_id:}"),
         ))),
     }
 }
 
+/// Returns the space needed to insert `record` into a leaf node.
+/// If the key doesn't share the common prefix of a prefix compressed
+/// node, the node has to be rebuilt with a shorter prefix, we assume
+/// the worst case that every key grows by the length of the prefix.
+pub(super) fn leaf_insert_size(node: &Node, record: &Record<&[u8]>) -> usize {
+    let prefix = key_prefix(node).unwrap();
+    if record.key.starts_with(prefix) {
+        record.encode_size() - prefix.len()
+    } else {
+        let num_records =
+            (node.page.max_slot() + 1 - first_data_slot(node)) as usize;
+        record.encode_size() + prefix.len() * num_records
+    }
+}
+
+/// Rebuild a prefix compressed leaf node with a new `record` whose key
+/// doesn't share the node's common prefix.
+fn rebuild_leaf_node(node: &mut Node, record: Record<&[u8]>) -> Result<()> {
+    let insert_size = leaf_insert_size(node, &record);
+    if insert_size > node.page.get_record_free_space() {
+        return Err(FloppyError::DC(DCError::SpaceExhaustedInPage(format!(
+            "page exhausted when rebuild leaf node, key = {:?}",
+            record.key
+        ))));
+    }
+
+    let mut tmp_page = Page::copy_from(node.page)?;
+    let tmp_node = Node::from_page(&mut tmp_page);
+    let entries = collect_records(&tmp_node, record)?;
+    build_node(
+        node,
+        tmp_node.get_tree_level(),
+        tmp_node.get_flags() & !BTP_PREFIX,
+        tmp_node.get_left_sibling(),
+        tmp_node.get_right_sibling(),
+        high_key(&tmp_node)?,
+        &as_records(&entries),
+    )
+}
+
 /// Insert into a internal node. Insertion happens when
 /// a leaf node A splits into A' and A'' where A and A' has
 /// the same page id, and A'' is the new page.
@@ -295,32 +472,40 @@ pub(super) fn insert_internal_node(
     validate_insertion_key(node, key)?;
 
     match rank(node, key) {
-        Err(slot_id) => node.page.insert_slot(record, slot_id - 1),
+        Err(slot_id) => node.page.insert_slot(record, slot_id),
         Ok(slot_id) => Err(FloppyError::DC(DCError::KeyAlreadyExists(
             format!("key already existed, key = {key:?}, slot_id = {slot_id:}"),
         ))),
     }
 }
 
-pub(super) fn init_root<K>(
+/// Initialize a new root node with two children. This happens when
+/// the old root splits into `left_pid` and `right_pid`, `key` is the
+/// high key of the left child.
+pub(super) fn init_root(
     node: &mut Node,
+    level: TreeLevel,
     key: &[u8],
     left_pid: PageId,
     right_pid: PageId,
 ) -> Result<()> {
     let minus_infinity = [0; 0];
-    let first_record = Record {
-        key: minus_infinity.as_slice(),
-        value: left_pid,
-    };
-    let first_data_slot = first_data_slot(node);
-    node.page.insert_slot(first_record, first_data_slot)?;
+    let records = [
+        Record {
+            key: minus_infinity.as_slice(),
+            value: left_pid,
+        },
+        Record {
+            key,
+            value: right_pid,
+        },
+    ];
+    build_node(node, level, BTP_ROOT, 0, 0, None, &records)
+}
 
-    let second_record = Record {
-        key,
-        value: right_pid,
-    };
-    node.page.insert_slot(second_record, first_data_slot + 1)
+/// Initialize an empty leaf node which is also the root of a tree.
+pub(super) fn init_leaf_root(node: &mut Node) -> Result<()> {
+    build_node::<&[u8]>(node, 0, BTP_LEAF | BTP_ROOT, 0, 0, None, &[])
 }
 
 /// Update of high key. This happens when node split.
@@ -353,8 +538,12 @@ fn validate_insertion_key(node: &Node, key: &[u8]) -> Result<()> {
     }
 }
 
-pub fn validate_record_size(record_size: usize) -> Result<()> {
-    if record_size + mem::size_of::<LinePointer>() > Node::max_record_size() {
+pub fn validate_record_size(
+    record_size: usize,
+    page_size: usize,
+) -> Result<()> {
+    let max_record_size = Node::max_record_size(page_size);
+    if record_size + mem::size_of::<LinePointer>() > max_record_size {
         Err(FloppyError::DC(DCError::RecordSizeExceeded(
             "cannot insert a record longer than 1/3 of page size".to_string(),
         )))
@@ -380,15 +569,21 @@ pub(super) fn high_key<'a>(node: &'a Node) -> Result<Option<&'a [u8]>> {
         Ok(None)
     } else {
         let slot = node.page.get_slot(1)?;
-        Ok(Some(slot))
+        Ok(Some(Record::<&[u8]>::decode_key(slot)))
     }
 }
 
+/// The number of records of `node`, excluding the high key and the
+/// common prefix of the keys.
+pub(super) fn num_records(node: &Node) -> usize {
+    (node.page.max_slot() + 1 - first_data_slot(node)) as usize
+}
+
 pub(super) fn first_data_slot(node: &Node) -> SlotId {
-    if node.is_rightmost() {
-        1
+    if node.is_prefix_compressed() {
+        prefix_slot(node) + 1
     } else {
-        2
+        prefix_slot(node)
     }
 }
 
@@ -420,22 +615,23 @@ pub(super) fn rank(
     target: &[u8],
 ) -> std::result::Result<SlotId, SlotId> {
     let first_is_minus_infinity = !node.is_leaf();
-    let mut size = node.page.max_slot();
+    let prefix = key_prefix(node).unwrap();
     let first_data_slot = first_data_slot(node);
     let mut left = first_data_slot;
     // slot_id starts with 1, `right` should be initialized with
-    // `size + 1` to take into account the case where there is
+    // `max_slot + 1` to take into account the case where there is
     // only one slot.
-    let mut right = size + 1;
+    let mut right = node.page.max_slot() + 1;
+    let mut size = right.saturating_sub(left);
     while left < right {
         let mid = left + size / 2;
         let cmp = if first_is_minus_infinity && mid == first_data_slot {
-            Ordering::Greater
+            Ordering::Less
         } else {
             let slot_content = node.page.get_slot(mid).unwrap();
             let mut dec = Decoder::new(slot_content);
             let slot_key = unsafe { <&[u8]>::decode_from(&mut dec) };
-            slot_key.cmp(target)
+            cmp_prefixed_key(prefix, slot_key, target)
         };
 
         if cmp == Ordering::Less {
@@ -452,145 +648,340 @@ pub(super) fn rank(
     Err(left)
 }
 
-/// Find a split location in a node.
-/// * Input
-/// - `node` is [`Node`] we would like to split.
-/// - `key` is the new record's key.
-/// - `insert_size` is the new record's encoded size.
-/// * Output
-/// - The page offset where new record will be inserted.
-/// - A boolean value indicates whether the new record is in left or right page.
-///
-/// The basic idea is to imagine that a new record is already in the node, and
-/// split the node into half to make left and right page equally sized.
-pub(super) fn split_location<V>(
-    node: &Node,
-    key: &[u8],
-    insert_size: usize,
-) -> Result<SplitLocation>
+/// Collect the data records of `node` and the new `record` in key
+/// order. The high key is not included.
+fn collect_records<'a, V>(
+    node: &'a Node,
+    record: Record<'a, V>,
+) -> Result<Vec<(Cow<'a, [u8]>, V)>>
 where
     V: NodeValue,
 {
-    let new_record_slot = match rank(node, key) {
+    let new_record_slot = match rank(node, record.key) {
         Err(s) => Ok(s),
-        Ok(_) => Err(FloppyError::DC(DCError::KeyAlreadyExists(
-            "key already exists when finding split location".to_string(),
-        ))),
+        Ok(_) => Err(FloppyError::DC(DCError::KeyAlreadyExists(format!(
+            "key already existed, key = {:?}",
+            record.key
+        )))),
     }?;
 
-    // 1. let space = get the total used space + new record size
-    // 2. let half = space/2
-    // 3. iterator through slots and calculate the accumulated size on the way,
-    //    until we find a first slot when the accumulated size >= half.
-    //    We need to consider the new record during the iteration. The result of
-    //    this iteration is target slot, target offset.
-    //    slot >= target slot goes to the right page.
-    // 4.
-    //
-    let half_size = node.page.get_used_size() + insert_size / 2;
-    let mut acc_size = 0;
     let first_data_slot = first_data_slot(node);
-    let max_slot = node.page.max_slot();
-
-    fn acc_slot_size(
-        node: &Node,
-        acc_size: usize,
-        slot_id: SlotId,
-    ) -> Result<usize> {
-        let content = node.page.get_slot(slot_id)?;
-        Ok(acc_size + content.len() + mem::size_of::<LinePointer>())
-    }
-
-    if new_record_slot > max_slot {
-        assert_eq!(new_record_slot, max_slot + 1);
-        for slot_iter in first_data_slot..=max_slot {
-            acc_size = acc_slot_size(node, acc_size, slot_iter)?;
-            if acc_size >= half_size {
-                // If the first data's size >= half_size, we use the second.
-                // It is ok here since we guaranteed we can have at least two data items
-                // in each page.
-                let split_slot = adjust_split_slot(node, slot_iter);
-                let high_key =
-                    IVec::from(get_record::<V>(node, split_slot - 1)?.key);
-                return Ok(SplitLocation {
-                    high_key,
-                    split_slot,
-                    new_record_slot,
-                });
-            }
-        }
-        let high_key =
-            IVec::from(get_record::<V>(node, new_record_slot - 1)?.key);
-        Ok(SplitLocation {
-            high_key,
-            split_slot: new_record_slot,
-            new_record_slot,
+    let mut entries = (first_data_slot..=node.page.max_slot())
+        .map(|slot_id| get_entry::<V>(node, slot_id))
+        .collect::<Result<Vec<(Cow<[u8]>, V)>>>()?;
+    entries.insert(
+        (new_record_slot - first_data_slot) as usize,
+        (Cow::Borrowed(record.key), record.value),
+    );
+    Ok(entries)
+}
+
+fn as_records<'a, V>(entries: &'a [(Cow<[u8]>, V)]) -> Vec<Record<'a, V>>
+where
+    V: NodeValue + Copy,
+{
+    entries
+        .iter()
+        .map(|(key, value)| Record {
+            key: key.as_ref(),
+            value: *value,
         })
+        .collect()
+}
+
+/// Returns the common prefix of the keys of sorted `records` if storing
+/// it once saves space, otherwise returns an empty slice.
+fn compressible_prefix<'a, V>(records: &[Record<'a, V>]) -> &'a [u8] {
+    let (first, last) = match (records.first(), records.last()) {
+        (Some(first), Some(last)) => (first.key, last.key),
+        _ => return &[],
+    };
+    // the common prefix of the first and the last key is shared by
+    // all the keys in between.
+    let len = first.iter().zip(last).take_while(|(a, b)| a == b).count();
+    let prefix = &first[..len];
+    // the prefix is stored in its own slot with an empty value.
+    let overhead = prefix.encode_size()
+        + mem::size_of::<u16>()
+        + mem::size_of::<LinePointer>();
+    if len * records.len() > overhead {
+        prefix
     } else {
-        for slot_iter in first_data_slot..new_record_slot {
-            acc_size = acc_slot_size(node, acc_size, slot_iter)?;
-            let split_slot = adjust_split_slot(node, slot_iter);
-            let high_key =
-                IVec::from(get_record::<V>(node, split_slot - 1)?.key);
-            if acc_size >= half_size {
-                return Ok(SplitLocation {
-                    high_key,
-                    split_slot,
-                    new_record_slot,
-                });
-            }
-        }
-        // haven't found the split point. handle the new record slot first.
-        acc_size += insert_size + mem::size_of::<LinePointer>();
-        if acc_size >= half_size {
-            let split_slot = adjust_split_slot(node, new_record_slot);
-            return Ok(SplitLocation {
-                high_key: IVec::from(
-                    get_record::<V>(node, split_slot - 1)?.key,
-                ),
-                split_slot,
-                new_record_slot,
-            });
-        }
+        &[]
+    }
+}
 
-        for slot_iter in new_record_slot..=max_slot {
-            acc_size = acc_slot_size(node, acc_size, slot_iter)?;
-            if acc_size >= half_size {
-                let split_slot = adjust_split_slot(node, slot_iter);
-                return Ok(SplitLocation {
-                    high_key: IVec::from(
-                        get_record::<V>(node, split_slot - 1)?.key,
-                    ),
-                    split_slot,
-                    new_record_slot,
-                });
-            }
+/// Find a split location in a sorted list of records. Records before
+/// the returned index go to the left node, the others go to the right
+/// node, so that both nodes are roughly equally sized. Both nodes have
+/// at least one record.
+fn split_index<V>(records: &[Record<V>]) -> usize
+where
+    V: NodeValue,
+{
+    let slot_size =
+        |r: &Record<V>| r.encode_size() + mem::size_of::<LinePointer>();
+    let half_size = records.iter().map(slot_size).sum::<usize>() / 2;
+    let mut acc_size = 0;
+    for (i, r) in records.iter().enumerate() {
+        acc_size += slot_size(r);
+        if acc_size >= half_size {
+            return (i + 1).clamp(1, records.len() - 1);
         }
-        Err(FloppyError::Internal(
-            "cannot find a split point".to_string(),
-        ))
     }
+    records.len() - 1
 }
 
-fn adjust_split_slot(node: &Node, split_slot: SlotId) -> SlotId {
-    let first_data = first_data_slot(node);
-    if split_slot == first_data {
-        split_slot + 1
+/// The space a leaf node built from sorted `records` with `high_key`
+/// takes, `records` are prefix compressed as [`build_node`] does.
+fn leaf_node_size<V>(records: &[Record<V>], high_key: Option<&[u8]>) -> usize
+where
+    V: NodeValue,
+{
+    let prefix = compressible_prefix(records);
+    let prefix_size = if prefix.is_empty() {
+        0
     } else {
-        split_slot
+        prefix.encode_size()
+            + mem::size_of::<u16>()
+            + mem::size_of::<LinePointer>()
+    };
+    let high_key_size = high_key.map_or(0, |key| {
+        key.encode_size()
+            + mem::size_of::<u16>()
+            + mem::size_of::<LinePointer>()
+    });
+    let records_size = records
+        .iter()
+        .map(|r| r.encode_size() - prefix.len() + mem::size_of::<LinePointer>())
+        .sum::<usize>();
+    prefix_size + high_key_size + records_size
+}
+
+/// Format `node` and fill it with `records`, which must be sorted.
+/// `high_key` must be `None` iff `right_sibling` is zero.
+/// Leaf nodes are prefix compressed if their keys share a long enough
+/// prefix, the [`BTP_PREFIX`] bit of `flags` is ignored.
+fn build_node<V>(
+    node: &mut Node,
+    level: TreeLevel,
+    flags: NodeFlags,
+    left_sibling: PageId,
+    right_sibling: PageId,
+    high_key: Option<&[u8]>,
+    records: &[Record<V>],
+) -> Result<()>
+where
+    V: NodeValue + Copy,
+{
+    let prefix = if (flags & BTP_LEAF) != 0 {
+        compressible_prefix(records)
+    } else {
+        &[][..]
+    };
+    node.format_page();
+    node.set_left_sibling(left_sibling);
+    node.set_right_sibling(right_sibling);
+    node.set_tree_level(level);
+    if prefix.is_empty() {
+        node.set_flags(flags & !BTP_PREFIX);
+    } else {
+        node.set_flags(flags | BTP_PREFIX);
+    }
+    if let Some(key) = high_key {
+        set_high_key(node, key)?;
+    }
+    if !prefix.is_empty() {
+        let value: [u8; 0] = [0; 0];
+        let record = Record {
+            key: prefix,
+            value: value.as_slice(),
+        };
+        node.page.insert_slot(record, prefix_slot(node))?;
+    }
+
+    let first_slot = first_data_slot(node);
+    for (slot_id, r) in (first_slot..).zip(records) {
+        let record = Record {
+            key: &r.key[prefix.len()..],
+            value: r.value,
+        };
+        node.page.insert_slot(record, slot_id)?;
+    }
+    Ok(())
+}
+
+/// Initialize a node built by bulk loading, `records` must be sorted.
+/// The first downlink of an internal node is replaced by minus
+/// infinity. `high_key` must be `None` iff `right_sibling` is zero.
+pub(super) fn init_bulk_node<V>(
+    node: &mut Node,
+    level: TreeLevel,
+    is_root: bool,
+    left_sibling: PageId,
+    right_sibling: PageId,
+    high_key: Option<&[u8]>,
+    records: &[Record<V>],
+) -> Result<()>
+where
+    V: NodeValue + Copy,
+{
+    let mut flags = if level == 0 { BTP_LEAF } else { 0 };
+    if is_root {
+        flags |= BTP_ROOT;
+    }
+    let mut records = records
+        .iter()
+        .map(|r| Record {
+            key: r.key,
+            value: r.value,
+        })
+        .collect::<Vec<Record<V>>>();
+    if level > 0 {
+        records[0].key = &[];
+    }
+    build_node(
+        node,
+        level,
+        flags,
+        left_sibling,
+        right_sibling,
+        high_key,
+        &records,
+    )
+}
+
+/// Partition sorted `records` into nodes of `page_size` pages for bulk
+/// loading. Each node is filled up to `fill_factor` percent of its
+/// space, leaving room for a high key. Returns the exclusive end index
+/// of each node, every node has at least one record.
+pub(super) fn pack_records<V>(
+    records: &[Record<V>],
+    fill_factor: usize,
+    page_size: usize,
+) -> Vec<usize>
+where
+    V: NodeValue,
+{
+    let slot_size =
+        |r: &Record<V>| r.encode_size() + mem::size_of::<LinePointer>();
+    // a high key is stored with an empty value.
+    let high_key_size = |key: &[u8]| {
+        key.encode_size()
+            + mem::size_of::<u16>()
+            + mem::size_of::<LinePointer>()
+    };
+    let capacity = page_size - Page::header_size() - Node::opaque_size();
+    let target = capacity * fill_factor.clamp(1, 100) / 100;
+
+    let mut ends = vec![];
+    let mut acc_size = 0;
+    for (i, r) in records.iter().enumerate() {
+        // The high key of a leaf node is its last key, the high key of
+        // a internal node is the first key of its right sibling.
+        let max_high_key = match records.get(i + 1) {
+            Some(next) if next.key.len() > r.key.len() => next.key,
+            _ => r.key,
+        };
+        let size = slot_size(r);
+        if acc_size > 0
+            && acc_size + size + high_key_size(max_high_key) > target
+        {
+            ends.push(i);
+            acc_size = 0;
+        }
+        acc_size += size;
     }
+    ends.push(records.len());
+    ends
 }
 
-pub struct SplitLocation {
-    pub high_key: IVec,
-    /// [1, split_slot) will be on the left node,
-    /// [split_slot,..) will be on the right node.
-    pub split_slot: SlotId,
-    /// When `new_record_slot` < `split_slot`, the new record will be inserted
-    /// into left node.
-    /// When `new_record_slot` >= `split_slot`, the new record will be inserted
-    /// into right node.
-    pub new_record_slot: SlotId,
+/// Split `node` (whose page is `left_pid`) into itself and a new right
+/// node `right` (whose page is `right_pid`), and insert `record` into
+/// one of them.
+///
+/// The left node keeps keys less than or equal to the returned high
+/// key, the right node takes the rest along with the left node's
+/// original high key and right sibling. For internal nodes, the first
+/// key on the right node becomes the high key of the left node and is
+/// replaced by minus infinity.
+///
+/// The caller is responsible for updating the left sibling of the
+/// original right sibling and inserting the downlink into the parent.
+/// The left node is marked [`BTP_INCOMPLETE_SPLIT`] until the downlink
+/// is inserted. The right node keeps the flag of the original node since
+/// it takes over the original right sibling.
+pub(super) fn split_node<V>(
+    node: &mut Node,
+    left_pid: PageId,
+    right: &mut Node,
+    right_pid: PageId,
+    record: Record<V>,
+) -> Result<IVec>
+where
+    V: NodeValue + Copy,
+{
+    let mut tmp_page = Page::copy_from(node.page)?;
+    let tmp_node = Node::from_page(&mut tmp_page);
+    let entries = collect_records(&tmp_node, record)?;
+    let mut left_records = as_records(&entries);
+    let mut split_index = split_index(&left_records);
+
+    let is_leaf = tmp_node.is_leaf();
+    if is_leaf {
+        // the halves are split by their uncompressed size but may not
+        // share the prefix of the original node, e.g. when the new key
+        // doesn't have it. Move the split point until both halves fit.
+        let capacity = tmp_node.page.data().len()
+            - Page::header_size()
+            - Node::opaque_size();
+        let right_high_key = high_key(&tmp_node)?;
+        while split_index < left_records.len() - 1
+            && leaf_node_size(&left_records[split_index..], right_high_key)
+                > capacity
+        {
+            split_index += 1;
+        }
+        while split_index > 1
+            && leaf_node_size(
+                &left_records[..split_index],
+                Some(left_records[split_index - 1].key),
+            ) > capacity
+        {
+            split_index -= 1;
+        }
+    }
+    let mut right_records = left_records.split_off(split_index);
+
+    let split_key = if is_leaf {
+        IVec::from(left_records[split_index - 1].key)
+    } else {
+        let key = IVec::from(right_records[0].key);
+        right_records[0].key = &[];
+        key
+    };
+
+    let level = tmp_node.get_tree_level();
+    let flags = tmp_node.get_flags() & !BTP_ROOT;
+    build_node(
+        right,
+        level,
+        flags,
+        left_pid,
+        tmp_node.get_right_sibling(),
+        high_key(&tmp_node)?,
+        &right_records,
+    )?;
+    build_node(
+        node,
+        level,
+        flags | BTP_INCOMPLETE_SPLIT,
+        tmp_node.get_left_sibling(),
+        right_pid,
+        Some(split_key.as_ref()),
+        &left_records,
+    )?;
+    Ok(split_key)
 }
 
 #[cfg(test)]
@@ -693,9 +1084,103 @@ mod tests {
         }
 
         let mut iter = new_iterator::<&[u8]>(&node);
-        assert_eq!(iter.next().unwrap().0, b"1");
-        assert_eq!(iter.next().unwrap().0, b"2");
-        assert_eq!(iter.next().unwrap().0, b"3");
+        assert_eq!(iter.next().unwrap().0.as_ref(), b"1");
+        assert_eq!(iter.next().unwrap().0.as_ref(), b"2");
+        assert_eq!(iter.next().unwrap().0.as_ref(), b"3");
+        Ok(())
+    }
+
+    #[test]
+    fn test_rev_iterator() -> Result<()> {
+        let mut page = Page::alloc(PAGE_SIZE)?;
+        let mut node = init_single_leaf(&mut page);
+
+        {
+            let mut iter = new_rev_iterator::<&[u8]>(&node);
+            assert!(iter.next().is_none());
+        }
+
+        let vec = [b"1", b"3", b"2"];
+        for v in vec.iter() {
+            insert_leaf_node(
+                &mut node,
+                Record {
+                    key: (*v).as_slice(),
+                    value: (*v).as_slice(),
+                },
+            )?;
+        }
+
+        let mut iter = new_rev_iterator::<&[u8]>(&node);
+        assert_eq!(iter.next().unwrap().0.as_ref(), b"3");
+        assert_eq!(iter.next().unwrap().0.as_ref(), b"2");
+        assert_eq!(iter.next().unwrap().0.as_ref(), b"1");
+        assert!(iter.next().is_none());
+
+        // the high key of a node other than the rightmost one is not a
+        // data record.
+        let mut page = Page::alloc(PAGE_SIZE)?;
+        let mut node = init_single_leaf(&mut page);
+        node.set_right_sibling(2);
+        set_high_key(&mut node, b"9")?;
+        for v in vec.iter() {
+            insert_leaf_node(
+                &mut node,
+                Record {
+                    key: (*v).as_slice(),
+                    value: (*v).as_slice(),
+                },
+            )?;
+        }
+        let keys = new_rev_iterator::<&[u8]>(&node)
+            .map(|(k, _)| k.into_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(keys, [b"3", b"2", b"1"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_range_iterator() -> Result<()> {
+        let mut page = Page::alloc(PAGE_SIZE)?;
+        let mut node = init_single_leaf(&mut page);
+        let size = 10;
+        for i in 0..size {
+            let key = [i];
+            insert_leaf_node(
+                &mut node,
+                Record {
+                    key: key.as_slice(),
+                    value: key.as_slice(),
+                },
+            )?;
+        }
+        let first = first_data_slot(&node);
+        let max = node.page.max_slot();
+        let keys = |range: Range<SlotId>| {
+            new_range_iterator::<&[u8]>(&node, range)
+                .map(|(k, _)| k[0])
+                .collect::<Vec<u8>>()
+        };
+
+        assert_eq!(keys(first..max + 1), (0..size).collect::<Vec<_>>());
+        assert_eq!(keys(first..first + 1), [0]);
+        assert_eq!(keys(max..max + 1), [size - 1]);
+        assert_eq!(keys(first + 3..first + 5), [3, 4]);
+        assert!(keys(first..first).is_empty());
+        assert!(keys(max + 1..max + 1).is_empty());
+        assert!(keys(first + 5..first + 3).is_empty());
+        // the slots past the max slot are not read.
+        assert_eq!(keys(max..max + 10), [size - 1]);
+        assert!(keys(max + 1..max + 2).is_empty());
+        // nor the slots before the first data slot.
+        assert_eq!(keys(0..first + 1), [0]);
+
+        // the halves cover all the records without overlapping.
+        for split in [0, first, first + 4, max, max + 1] {
+            let (left, right) = split_at::<&[u8]>(&node, split);
+            let keys = left.chain(right).map(|(k, _)| k[0]).collect::<Vec<_>>();
+            assert_eq!(keys, (0..size).collect::<Vec<_>>(), "{split}");
+        }
         Ok(())
     }
 
@@ -734,12 +1219,141 @@ mod tests {
         // iterator through the model.
         let model_iter = model.iter();
         let node_iter = new_iterator::<&[u8]>(&node);
-        assert!(model_iter
-            .eq_by(node_iter, |(mk, mv), (nk, nv)| { mk == nk && mv == nv }));
+        assert!(model_iter.eq_by(node_iter, |(mk, mv), (nk, nv)| {
+            nk.as_ref() == mk && mv == nv
+        }));
         println!("compared {compare_size} records");
         Ok(())
     }
 
+    #[test]
+    fn test_prefix_compression() -> Result<()> {
+        let prefix = "a-long-shared-key-prefix-";
+        let keys = (0..100)
+            .map(|i| format!("{prefix}{i:04}"))
+            .collect::<Vec<String>>();
+
+        // a node filled by insertion is not compressed.
+        let mut plain_page = Page::alloc(PAGE_SIZE)?;
+        let mut plain = init_single_leaf(&mut plain_page);
+        for key in keys.iter() {
+            let record = Record {
+                key: key.as_bytes(),
+                value: key.as_bytes(),
+            };
+            insert_leaf_node(&mut plain, record)?;
+        }
+        assert!(!plain.is_prefix_compressed());
+
+        let mut page = Page::alloc(PAGE_SIZE)?;
+        let mut node = Node::from_page(&mut page);
+        let records = keys
+            .iter()
+            .map(|key| Record {
+                key: key.as_bytes(),
+                value: key.as_bytes(),
+            })
+            .collect::<Vec<Record<&[u8]>>>();
+        build_node(&mut node, 0, BTP_LEAF | BTP_ROOT, 0, 0, None, &records)?;
+        assert!(node.is_prefix_compressed());
+        assert!(key_prefix(&node)?.starts_with(prefix.as_bytes()));
+        assert!(node.page.get_used_size() < plain.page.get_used_size());
+
+        for key in keys.iter() {
+            let value = find_in_leaf(&node, key.as_bytes())?.unwrap();
+            assert_eq!(value, key.as_bytes());
+        }
+        assert!(find_in_leaf(&node, b"a-long")?.is_none());
+        assert!(new_iterator::<&[u8]>(&node)
+            .map(|(k, _)| k.into_owned())
+            .eq(keys.iter().map(|k| k.as_bytes().to_vec())));
+        assert!(new_rev_iterator::<&[u8]>(&node)
+            .map(|(k, _)| k.into_owned())
+            .eq(keys.iter().rev().map(|k| k.as_bytes().to_vec())));
+
+        // a key sharing the prefix is stored as a suffix.
+        let key = format!("{prefix}0100");
+        insert_leaf_node(
+            &mut node,
+            Record {
+                key: key.as_bytes(),
+                value: b"v".as_slice(),
+            },
+        )?;
+        assert!(node.is_prefix_compressed());
+        assert_eq!(find_in_leaf(&node, key.as_bytes())?.unwrap(), b"v");
+
+        // a key without the prefix makes the node rebuilt with a shorter
+        // prefix.
+        insert_leaf_node(
+            &mut node,
+            Record {
+                key: b"a-long-key",
+                value: b"v".as_slice(),
+            },
+        )?;
+        assert_eq!(key_prefix(&node)?, b"a-long-");
+        assert_eq!(find_in_leaf(&node, b"a-long-key")?.unwrap(), b"v");
+        for key in keys.iter() {
+            let value = find_in_leaf(&node, key.as_bytes())?.unwrap();
+            assert_eq!(value, key.as_bytes());
+        }
+        assert_eq!(new_iterator::<&[u8]>(&node).count(), keys.len() + 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_prefix_compressed() -> Result<()> {
+        let prefix = "a-long-shared-key-prefix-";
+        let mut page = Page::alloc(PAGE_SIZE)?;
+        let mut node = Node::from_page(&mut page);
+        let keys = (0..2)
+            .map(|i| format!("{prefix}{i:08}"))
+            .collect::<Vec<String>>();
+        let records = keys
+            .iter()
+            .map(|key| Record {
+                key: key.as_bytes(),
+                value: b"v".as_slice(),
+            })
+            .collect::<Vec<Record<&[u8]>>>();
+        build_node(&mut node, 0, BTP_LEAF | BTP_ROOT, 0, 0, None, &records)?;
+        assert!(node.is_prefix_compressed());
+
+        // fill the node with suffixes, the uncompressed keys would take
+        // more than a page.
+        let mut num_keys = keys.len();
+        loop {
+            let key = format!("{prefix}{num_keys:08}");
+            let record = Record {
+                key: key.as_bytes(),
+                value: b"v".as_slice(),
+            };
+            if node.will_overfull(leaf_insert_size(&node, &record), None) {
+                break;
+            }
+            insert_leaf_node(&mut node, record)?;
+            num_keys += 1;
+        }
+
+        // the new key doesn't share the prefix, so the right half is
+        // not compressed.
+        let mut right_page = Page::alloc(PAGE_SIZE)?;
+        let mut right = Node::from_page(&mut right_page);
+        let record = Record {
+            key: b"b-key-without-prefix".as_slice(),
+            value: b"v".as_slice(),
+        };
+        let split_key = split_node(&mut node, 1, &mut right, 2, record)?;
+        assert!(node.is_prefix_compressed());
+        assert!(split_key.starts_with(prefix.as_bytes()));
+        assert!(find_in_leaf(&right, b"b-key-without-prefix")?.is_some());
+        let left_keys = new_iterator::<&[u8]>(&node).count();
+        let right_keys = new_iterator::<&[u8]>(&right).count();
+        assert_eq!(left_keys + right_keys, num_keys + 1);
+        Ok(())
+    }
+
     mod pt {
         use super::*;
         use proptest::prelude::*;
@@ -36,12 +36,35 @@ use crate::{
 };
 use paste::paste;
 use std::{
-    alloc::{alloc, Layout},
+    alloc::{alloc, dealloc, Layout},
     mem, ptr, slice,
 };
 
+/// The default page size of a tree, see `TreeOptions::page_size`.
 pub const PAGE_SIZE: usize = 1024 * 8;
 
+/// The smallest page size, which holds a few records of the keys of
+/// tens of bytes.
+pub const MIN_PAGE_SIZE: usize = 512;
+
+/// The largest page size, the offsets in a page are 15 bits, see
+/// [`LinePointer`].
+pub const MAX_PAGE_SIZE: usize = 1024 * 32;
+
+/// Returns an error unless `size` is a power of two between
+/// [`MIN_PAGE_SIZE`] and [`MAX_PAGE_SIZE`].
+pub fn validate_page_size(size: usize) -> Result<()> {
+    if size.is_power_of_two() && (MIN_PAGE_SIZE..=MAX_PAGE_SIZE).contains(&size)
+    {
+        Ok(())
+    } else {
+        Err(FloppyError::Internal(format!(
+            "page size must be a power of two between {MIN_PAGE_SIZE} and \
+             {MAX_PAGE_SIZE}: {size}"
+        )))
+    }
+}
+
 pub type PageId = u32;
 
 impl Codec for PageId {
@@ -75,7 +98,7 @@ type PageFlags = u8; // dead, may or may not have storage
 /// opaque     - 2 bytes to the start of opaque space used by upper layer.
 ///
 /// "offset" in `lower`, `upper`, `opaque` starts at 0.
-/// The page's offset is in the range: [0, 1024 * 8)
+/// The page's offset is in the range: [0, page size)
 /// The page's free space's offset is in the range [`lower`, `upper`).
 /// The number of bytes in the free space is `upper` - `lower`.
 pub struct Page {
@@ -84,6 +107,19 @@ pub struct Page {
     inited: bool,
 }
 
+// `Page` owns its buffer exclusively, just like a `Box<[u8]>`.
+unsafe impl Send for Page {}
+unsafe impl Sync for Page {}
+
+impl Drop for Page {
+    fn drop(&mut self) {
+        let layout =
+            Layout::from_size_align(self.size, mem::size_of::<usize>())
+                .unwrap();
+        unsafe { dealloc(self.buf.as_ptr(), layout) }
+    }
+}
+
 macro_rules! header_data_accessor {
     ($name:ident, $t:ty) => {
         paste! {
@@ -128,13 +164,14 @@ impl Page {
     }
 
     pub fn copy_from(page: &Page) -> Result<Self> {
-        let new_page = Self::alloc(page.size)?;
+        let mut new_page = Self::alloc(page.size)?;
         unsafe {
             new_page
                 .buf
                 .as_ptr()
                 .copy_from_nonoverlapping(page.buf.as_ptr(), page.size)
         };
+        new_page.inited = page.inited;
         Ok(new_page)
     }
 
@@ -142,6 +179,12 @@ impl Page {
         unsafe { slice::from_raw_parts_mut(self.buf.as_ptr(), self.size) }
     }
 
+    /// Mark the page as initialized after its content is filled
+    /// through [`Page::raw_data_mut`], eg read from disk.
+    pub fn set_inited(&mut self) {
+        self.inited = true;
+    }
+
     pub fn init(&mut self, opaque_size: usize) {
         unsafe { ptr::write_bytes(self.buf.as_ptr(), 0, self.size) }
         self.inited = true;
@@ -0,0 +1,63 @@
+use std::fmt;
+use std::ops::{Deref, Range};
+use std::sync::Arc;
+
+/// A part of a copy of a page, which is shared by all the parts of the
+/// page, so a range scan copies a leaf once instead of copying each of
+/// its keys and values.
+///
+/// The slices can't borrow the buffer frame of the page even while it
+/// is pinned, its content may change as soon as the lock of the page is
+/// released, eg by an insert or a split. The copy is freed when the
+/// last slice of it is dropped.
+#[derive(Clone)]
+pub(crate) struct PageSlice {
+    page: Arc<[u8]>,
+    range: Range<usize>,
+}
+
+impl PageSlice {
+    /// Returns the slice of `page`, the copy of the page data at
+    /// `base`, for `part` of the page data. `part` is copied if it
+    /// is not within the page data, eg a key restored from a prefix.
+    pub fn new(page: &Arc<[u8]>, base: *const u8, part: &[u8]) -> Self {
+        let start = (part.as_ptr() as usize).wrapping_sub(base as usize);
+        if start <= page.len() && part.len() <= page.len() - start {
+            Self {
+                page: page.clone(),
+                range: start..start + part.len(),
+            }
+        } else {
+            Self {
+                page: Arc::from(part),
+                range: 0..part.len(),
+            }
+        }
+    }
+
+    /// Whether both slices are of the same copy of a page.
+    #[cfg(test)]
+    pub fn same_page(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.page, &other.page)
+    }
+}
+
+impl Deref for PageSlice {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.page[self.range.clone()]
+    }
+}
+
+impl AsRef<[u8]> for PageSlice {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl fmt::Debug for PageSlice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_ref(), f)
+    }
+}
@@ -2,23 +2,46 @@ use crate::common::{
     error::{FloppyError, Result},
     ivec::IVec,
 };
-use crate::dc2::page::Page;
 use crate::dc2::{
     buf::{LockGuard, PinGuard},
-    bufmgr::BufMgr,
+    bufmgr::{BufMgr, BufMgrStats},
     codec::{Codec, Record},
     meta::MetaPage,
     node::{
-        compare_high_key, find_child, insert_leaf_node, split_at,
-        split_location, validate_record_size, Node,
+        clear_incomplete_split, compare_high_key, find_child, find_in_leaf,
+        has_downlink, high_key, init_bulk_node, init_leaf_root, init_root,
+        insert_internal_node, insert_leaf_node, last_child, leaf_insert_size,
+        new_iterator, new_rev_iterator, pack_records, split_node,
+        validate_record_size, Node, NodeValue,
     },
+    page::{validate_page_size, PageId, PAGE_SIZE},
+    slice::PageSlice,
 };
 use crate::env::Env;
 use std::cmp::Ordering;
+use std::ops::{Bound, RangeBounds};
 use std::path::Path;
+use std::sync::Arc;
+
+/// The percentage of a node's space filled by [`Tree::bulk_load`].
+const BULK_LOAD_FILL_FACTOR: usize = 90;
 
 pub(crate) struct Tree<E: Env> {
     buf_mgr: BufMgr<E>,
+    options: TreeOptions,
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TreeOptions {
+    /// The maximum number of records of a node, which must be at
+    /// least 2. Nodes split when they run out of space by default,
+    /// a small number makes the tree deep with few records, eg in
+    /// tests.
+    pub max_records_per_node: Option<usize>,
+    /// The size of the pages of the tree, [`PAGE_SIZE`] by default.
+    /// A tree must be opened with the page size it is created with,
+    /// small pages split often, eg in tests.
+    pub page_size: Option<usize>,
 }
 
 impl<E> Tree<E>
@@ -26,12 +49,160 @@ where
     E: Env,
 {
     pub async fn open<P: AsRef<Path>>(path: P, env: E) -> Result<Self> {
-        let buf_mgr = BufMgr::open(env, path, 1000).await?;
-        Ok(Self { buf_mgr })
+        Self::open_with_options(path, env, TreeOptions::default()).await
+    }
+
+    pub async fn open_with_options<P: AsRef<Path>>(
+        path: P,
+        env: E,
+        options: TreeOptions,
+    ) -> Result<Self> {
+        if options.max_records_per_node.map_or(false, |max| max < 2) {
+            return Err(FloppyError::Internal(format!(
+                "a node must hold at least 2 records: {options:?}"
+            )));
+        }
+        let page_size = options.page_size.unwrap_or(PAGE_SIZE);
+        validate_page_size(page_size)?;
+        let buf_mgr = BufMgr::open(env, path, 1000, page_size).await?;
+        Ok(Self { buf_mgr, options })
     }
 
     pub async fn get<K: AsRef<[u8]>>(&self, key: K) -> Result<Option<IVec>> {
-        todo!()
+        let (mut lock_guard, _) = self.find_leaf(key.as_ref()).await?;
+        let node = Node::from_page(&mut lock_guard.page);
+        find_in_leaf(&node, key.as_ref())
+    }
+
+    /// Returns the key value pairs within `range` in key order.
+    pub async fn range<R>(&self, range: R) -> Result<Vec<(IVec, IVec)>>
+    where
+        R: RangeBounds<[u8]>,
+    {
+        let start: &[u8] = match range.start_bound() {
+            Bound::Included(k) | Bound::Excluded(k) => k,
+            // the empty key is less than any other key, so we can
+            // find the leftmost leaf with it.
+            Bound::Unbounded => &[],
+        };
+        let past_end = |key: &[u8]| match range.end_bound() {
+            Bound::Included(k) => key > k,
+            Bound::Excluded(k) => key >= k,
+            Bound::Unbounded => false,
+        };
+
+        let (mut lock_guard, _) = self.find_leaf(start).await?;
+        let mut result = vec![];
+        loop {
+            let node = Node::from_page(&mut lock_guard.page);
+            for (key, value) in new_iterator::<&[u8]>(&node) {
+                if past_end(&key) {
+                    return Ok(result);
+                }
+                if range.contains(key.as_ref()) {
+                    result.push((IVec::from(key.as_ref()), IVec::from(value)));
+                }
+            }
+
+            if node.is_rightmost() {
+                return Ok(result);
+            }
+            let page_id = node.get_right_sibling();
+            drop(lock_guard);
+            lock_guard = self.buf_mgr.fix_page(page_id).await?.lock();
+        }
+    }
+
+    /// Returns the key value pairs within `range` in key order like
+    /// [`Tree::range`], but the records of a leaf share a single copy
+    /// of it instead of being allocated one by one. The values can't
+    /// borrow the pinned buffer frame, as the page may change once its
+    /// lock is released, see [`PageSlice`].
+    pub async fn range_shared<R>(
+        &self,
+        range: R,
+    ) -> Result<Vec<(PageSlice, PageSlice)>>
+    where
+        R: RangeBounds<[u8]>,
+    {
+        let start: &[u8] = match range.start_bound() {
+            Bound::Included(k) | Bound::Excluded(k) => k,
+            // the empty key is less than any other key, so we can
+            // find the leftmost leaf with it.
+            Bound::Unbounded => &[],
+        };
+        let past_end = |key: &[u8]| match range.end_bound() {
+            Bound::Included(k) => key > k,
+            Bound::Excluded(k) => key >= k,
+            Bound::Unbounded => false,
+        };
+
+        let (mut lock_guard, _) = self.find_leaf(start).await?;
+        let mut result = vec![];
+        loop {
+            let data = lock_guard.page.data();
+            let (page, base) = (Arc::<[u8]>::from(data), data.as_ptr());
+            let node = Node::from_page(&mut lock_guard.page);
+            for (key, value) in new_iterator::<&[u8]>(&node) {
+                if past_end(&key) {
+                    return Ok(result);
+                }
+                if range.contains(key.as_ref()) {
+                    result.push((
+                        PageSlice::new(&page, base, &key),
+                        PageSlice::new(&page, base, value),
+                    ));
+                }
+            }
+
+            if node.is_rightmost() {
+                return Ok(result);
+            }
+            let page_id = node.get_right_sibling();
+            drop(lock_guard);
+            lock_guard = self.buf_mgr.fix_page(page_id).await?.lock();
+        }
+    }
+
+    /// Returns the key value pairs within `range` in descending key
+    /// order. Leaves are visited from right to left by following the
+    /// left sibling pointers.
+    pub async fn range_rev<R>(&self, range: R) -> Result<Vec<(IVec, IVec)>>
+    where
+        R: RangeBounds<[u8]>,
+    {
+        let before_start = |key: &[u8]| match range.start_bound() {
+            Bound::Included(k) => key < k,
+            Bound::Excluded(k) => key <= k,
+            Bound::Unbounded => false,
+        };
+
+        let mut lock_guard = match range.end_bound() {
+            Bound::Included(k) | Bound::Excluded(k) => {
+                self.find_leaf(k).await?.0
+            }
+            Bound::Unbounded => self.find_rightmost_leaf().await?,
+        };
+        let mut result = vec![];
+        loop {
+            let node = Node::from_page(&mut lock_guard.page);
+            for (key, value) in new_rev_iterator::<&[u8]>(&node) {
+                if before_start(&key) {
+                    return Ok(result);
+                }
+                if range.contains(key.as_ref()) {
+                    result.push((IVec::from(key.as_ref()), IVec::from(value)));
+                }
+            }
+
+            if node.is_leftmost() {
+                return Ok(result);
+            }
+            let page_id = node.get_left_sibling();
+            let right_pid = lock_guard.page_id;
+            drop(lock_guard);
+            lock_guard = self.move_left(page_id, right_pid).await?;
+        }
     }
 
     pub async fn insert<K, V>(&self, key: K, value: V) -> Result<()>
@@ -43,20 +214,260 @@ where
             key: key.as_ref(),
             value: value.as_ref(),
         };
-        validate_record_size(record.encode_size())?;
+        validate_record_size(record.encode_size(), self.buf_mgr.page_size())?;
 
         let (mut lock_guard, stack) = self.find_leaf(key.as_ref()).await?;
         let mut node = Node::from_page(&mut lock_guard.page);
 
-        if node.will_overfull(record.encode_size()) {
-            // need split
-            // when to drop lock guard?
-            todo!()
+        if node.will_overfull(
+            leaf_insert_size(&node, &record),
+            self.options.max_records_per_node,
+        ) {
+            self.split(lock_guard, stack, record).await?;
         } else {
-            insert_leaf_node(&mut node, record)
+            insert_leaf_node(&mut node, record)?;
+            self.buf_mgr.log_pages(&mut [&mut lock_guard]).await?;
+            drop(lock_guard);
+        }
+        if self.buf_mgr.needs_checkpoint().await {
+            self.checkpoint().await?;
+        }
+        Ok(())
+    }
+
+    /// Load records into an empty tree. If the keys of `iter` are
+    /// strictly ascending, leaves are filled up to
+    /// [`BULK_LOAD_FILL_FACTOR`] and the internal levels are built bottom
+    /// up. Otherwise, or if the tree already has a root, the records are
+    /// inserted one at a time.
+    pub async fn bulk_load<K, V>(
+        &self,
+        iter: impl Iterator<Item = (K, V)>,
+    ) -> Result<()>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        let entries = iter.collect::<Vec<(K, V)>>();
+        let is_sorted = entries
+            .windows(2)
+            .all(|w| w[0].0.as_ref() < w[1].0.as_ref());
+
+        // hold the lock of meta page so that no root is created
+        // concurrently.
+        let meta_pin = self.buf_mgr.fix_page(0).await?;
+        let mut meta_guard = meta_pin.lock();
+        let root_id = MetaPage::from_page(&mut meta_guard.page).get_root();
+        if !is_sorted || root_id != 0 || entries.is_empty() {
+            drop(meta_guard);
+            for (key, value) in entries {
+                self.insert(key, value).await?;
+            }
+            return Ok(());
+        }
+
+        let records = entries
+            .iter()
+            .map(|(k, v)| Record {
+                key: k.as_ref(),
+                value: v.as_ref(),
+            })
+            .collect::<Vec<Record<&[u8]>>>();
+        for record in records.iter() {
+            validate_record_size(
+                record.encode_size(),
+                self.buf_mgr.page_size(),
+            )?;
+        }
+
+        let mut level = 0;
+        let mut nodes = self.build_level(level, &records).await?;
+        while nodes.len() > 1 {
+            level += 1;
+            nodes = {
+                let downlinks = nodes
+                    .iter()
+                    .map(|(low_key, page_id)| Record {
+                        key: low_key.as_ref(),
+                        value: *page_id,
+                    })
+                    .collect::<Vec<Record<PageId>>>();
+                self.build_level(level, &downlinks).await?
+            };
+        }
+
+        MetaPage::from_page(&mut meta_guard.page).set_root(nodes[0].1);
+        self.buf_mgr.log_pages(&mut [&mut meta_guard]).await
+    }
+
+    /// Write all the dirty pages to disk.
+    pub async fn flush(&self) -> Result<()> {
+        self.buf_mgr.flush_all().await
+    }
+
+    /// Write all the dirty pages to disk and remove the log before
+    /// them, so that recovery doesn't replay it. A checkpoint is also
+    /// made when the log grows beyond [`CHECKPOINT_LOG_SIZE`] after an
+    /// insert.
+    ///
+    /// [`CHECKPOINT_LOG_SIZE`]: crate::dc2::wal::CHECKPOINT_LOG_SIZE
+    pub async fn checkpoint(&self) -> Result<()> {
+        self.buf_mgr.checkpoint().await
+    }
+
+    /// Write all the dirty pages to disk and close the tree.
+    pub async fn close(self) -> Result<()> {
+        self.checkpoint().await
+    }
+
+    /// Returns the counters of the buffer pool.
+    pub fn stats(&self) -> BufMgrStats {
+        self.buf_mgr.stats()
+    }
+
+    /// Checks the structure of the tree, returns an error describing the
+    /// first violation found:
+    ///
+    /// * the keys of each node are strictly ascending,
+    /// * the keys of a node are within the range of its downlink in the
+    ///   parent, ie greater than the key of the downlink and not greater
+    ///   than the key of the next downlink,
+    /// * the high key of a non-rightmost node is the key of the next
+    ///   downlink, unless the downlink of its right sibling is not
+    ///   inserted yet,
+    /// * the left sibling of a node's right sibling is the node,
+    /// * the children of a node are one level lower, so all the leaves
+    ///   are at level zero.
+    ///
+    /// The tree must not be changed concurrently.
+    #[cfg(test)]
+    pub async fn validate(&self) -> Result<()> {
+        let root_pid = self.get_root().await?.lock().page_id;
+        let mut lock_guard = self.buf_mgr.fix_page(root_pid).await?.lock();
+        let root = Node::from_page(&mut lock_guard.page);
+        if !root.is_leftmost() || !root.is_rightmost() {
+            return Err(invalid_node(root_pid, "the root has siblings"));
+        }
+        let root_level = root.get_tree_level();
+        drop(lock_guard);
+
+        // the first node of a downlink, its level, and the range of its
+        // keys, `None` is unbounded.
+        let mut downlinks = vec![(root_pid, root_level, None, None)];
+        while let Some((mut page_id, level, mut low, high)) = downlinks.pop() {
+            // the nodes split from the first node whose downlinks are not
+            // inserted yet are checked with it.
+            loop {
+                let mut lock_guard =
+                    self.buf_mgr.fix_page(page_id).await?.lock();
+                let node = Node::from_page(&mut lock_guard.page);
+                let invalid = |msg: &str| Err(invalid_node(page_id, msg));
+                if node.get_tree_level() != level {
+                    return invalid(&format!("the level is not {level}"));
+                }
+                if node.is_leaf() != (level == 0) {
+                    return invalid("only the nodes at level 0 are leaves");
+                }
+                if node.is_root() != (page_id == root_pid) {
+                    return invalid("the root flag is wrong");
+                }
+                if node.is_leftmost() != low.is_none() {
+                    return invalid("the leftmost node has a low key");
+                }
+
+                let entries = if node.is_leaf() {
+                    new_iterator::<&[u8]>(&node)
+                        .map(|(key, _)| (IVec::from(key.as_ref()), 0))
+                        .collect::<Vec<(IVec, PageId)>>()
+                } else {
+                    new_iterator::<PageId>(&node)
+                        .map(|(key, child)| (IVec::from(key.as_ref()), child))
+                        .collect()
+                };
+                // the first key of an internal node is minus infinity.
+                let keys = if node.is_leaf() {
+                    &entries[..]
+                } else {
+                    match entries.first() {
+                        Some((key, _)) if key.is_empty() => &entries[1..],
+                        _ => return invalid("the first key is not empty"),
+                    }
+                };
+                if !keys.windows(2).all(|w| w[0].0 < w[1].0) {
+                    return invalid("the keys are not ascending");
+                }
+                let high_key = high_key(&node)?.map(IVec::from);
+                if let (Some(low), Some((first, _))) = (&low, keys.first()) {
+                    if first <= low {
+                        return invalid(
+                            "a key is not greater than the low key",
+                        );
+                    }
+                }
+                if let (Some(high), Some((last, _))) = (&high_key, keys.last())
+                {
+                    // a leaf's high key is its last key after a split.
+                    if last > high || (!node.is_leaf() && last == high) {
+                        return invalid("a key is greater than the high key");
+                    }
+                }
+                let is_last = match (&high_key, &high) {
+                    (None, None) => true,
+                    (None, Some(_)) => {
+                        return invalid("the rightmost node has a downlink key")
+                    }
+                    (Some(key), Some(high)) if key == high => true,
+                    (Some(key), Some(high)) if key > high => {
+                        return invalid("the high key is out of the range")
+                    }
+                    (Some(_), _) if !node.is_incomplete_split() => {
+                        return invalid(
+                            "the downlink of the right sibling is missing",
+                        )
+                    }
+                    (Some(_), _) => false,
+                };
+
+                for (i, (key, child)) in entries.iter().enumerate() {
+                    if node.is_leaf() {
+                        break;
+                    }
+                    let child_low = match i {
+                        0 => low.clone(),
+                        _ => Some(key.clone()),
+                    };
+                    let child_high = match entries.get(i + 1) {
+                        Some((next, _)) => Some(next.clone()),
+                        None => high_key.clone(),
+                    };
+                    downlinks.push((*child, level - 1, child_low, child_high));
+                }
+
+                let right_pid = node.get_right_sibling();
+                drop(lock_guard);
+                if right_pid != 0 {
+                    let mut lock_guard =
+                        self.buf_mgr.fix_page(right_pid).await?.lock();
+                    let right = Node::from_page(&mut lock_guard.page);
+                    if right.get_left_sibling() != page_id {
+                        return Err(invalid_node(
+                            right_pid,
+                            &format!("the left sibling is not {page_id}"),
+                        ));
+                    }
+                }
+                if is_last {
+                    break;
+                }
+                low = high_key;
+                page_id = right_pid;
+            }
         }
+        Ok(())
     }
 
+    /// Find the leaf that may contain `key`. Incomplete splits of the
+    /// nodes visited on the way down are finished.
     async fn find_leaf(
         &self,
         key: &[u8],
@@ -64,9 +475,22 @@ where
         let mut lock_guard = self.get_root().await?.lock();
         let mut stack = vec![];
         loop {
-            lock_guard = self.move_right(key, lock_guard).await?;
             let node = Node::from_page(&mut lock_guard.page);
-            if node.is_leaf() {
+            if node.is_incomplete_split() {
+                // a split was interrupted by a crash before the downlink
+                // of the right sibling is inserted, finish it and start
+                // over from the root.
+                let high_key = IVec::from(high_key(&node)?.unwrap());
+                let right_pid = node.get_right_sibling();
+                self.insert_downlink(lock_guard, stack, high_key, right_pid)
+                    .await?;
+                lock_guard = self.get_root().await?.lock();
+                stack = vec![];
+            } else if compare_high_key(&node, key) == Ordering::Greater {
+                let page_id = node.get_right_sibling();
+                drop(lock_guard);
+                lock_guard = self.buf_mgr.fix_page(page_id).await?.lock();
+            } else if node.is_leaf() {
                 return Ok((lock_guard, stack));
             } else {
                 let page_id = find_child(&node, key)?;
@@ -80,59 +504,221 @@ where
         }
     }
 
-    async fn split(
+    /// Build a level of linked nodes at `level` from sorted `records`.
+    /// Returns the low key (the high key of its left sibling) and the
+    /// page id of each node, which are the downlinks of the parent
+    /// level.
+    async fn build_level<V>(
+        &self,
+        level: u32,
+        records: &[Record<'_, V>],
+    ) -> Result<Vec<(IVec, PageId)>>
+    where
+        V: NodeValue + Copy,
+    {
+        let ends = pack_records(
+            records,
+            BULK_LOAD_FILL_FACTOR,
+            self.buf_mgr.page_size(),
+        );
+        let mut pins = vec![];
+        for _ in 0..ends.len() {
+            pins.push(self.buf_mgr.alloc_page().await?);
+        }
+        let page_ids = pins
+            .iter()
+            .map(|pin| pin.lock().page_id)
+            .collect::<Vec<PageId>>();
+
+        let mut nodes = vec![];
+        let mut low_key = IVec::default();
+        let mut start = 0;
+        for (i, &end) in ends.iter().enumerate() {
+            let left_sibling = if i == 0 { 0 } else { page_ids[i - 1] };
+            let (right_sibling, high_key) = match page_ids.get(i + 1) {
+                None => (0, None),
+                Some(&right_sibling) if level == 0 => {
+                    (right_sibling, Some(IVec::from(records[end - 1].key)))
+                }
+                Some(&right_sibling) => {
+                    (right_sibling, Some(IVec::from(records[end].key)))
+                }
+            };
+
+            let mut lock_guard = pins[i].lock();
+            init_bulk_node(
+                &mut Node::from_page(&mut lock_guard.page),
+                level,
+                ends.len() == 1,
+                left_sibling,
+                right_sibling,
+                high_key.as_deref(),
+                &records[start..end],
+            )?;
+            self.buf_mgr.log_pages(&mut [&mut lock_guard]).await?;
+
+            nodes.push((low_key, page_ids[i]));
+            low_key = high_key.unwrap_or_default();
+            start = end;
+        }
+        Ok(nodes)
+    }
+
+    /// Split the node locked by `lock_guard` and insert `record` into it.
+    /// The downlink of the new right node is inserted into the parent,
+    /// which may split the parent recursively. `stack` contains the
+    /// ancestors visited when we find the node.
+    async fn split<V>(
+        &self,
+        mut lock_guard: LockGuard,
+        stack: Vec<PinGuard>,
+        record: Record<'_, V>,
+    ) -> Result<()>
+    where
+        V: NodeValue + Copy,
+    {
+        let (high_key, right_pid) =
+            self.split_node(&mut lock_guard, record).await?;
+        self.insert_downlink(lock_guard, stack, high_key, right_pid)
+            .await
+    }
+
+    /// Insert the downlink of `right_pid` into the parent of the node
+    /// locked by `lock_guard`, whose split is incomplete. `high_key` is
+    /// the high key of the node. The parent may split recursively.
+    ///
+    /// The child is locked until its downlink is inserted, so that no
+    /// one else finishes the same split. A crash may happen after the
+    /// parent is logged but before the child is, so an existing
+    /// downlink is not inserted again.
+    async fn insert_downlink(
         &self,
         mut lock_guard: LockGuard,
-        record: Record<'_, &[u8]>,
+        mut stack: Vec<PinGuard>,
+        mut high_key: IVec,
+        mut right_pid: PageId,
     ) -> Result<()> {
-        // make a new tmp page and copy the current page's content.
-        let mut tmp_page = Page::copy_from(&lock_guard.page)?;
-        let tmp_node = Node::from_page(&mut tmp_page);
-        let loc = split_location::<&[u8]>(
-            &tmp_node,
-            record.key,
-            record.encode_size(),
-        )?;
+        loop {
+            let parent_pin = match stack.pop() {
+                Some(parent_pin) => parent_pin,
+                None => {
+                    return self
+                        .new_root(&mut lock_guard, &high_key, right_pid)
+                        .await
+                }
+            };
 
-        // make a new new right page.
-        let right_pin = self.buf_mgr.alloc_page().await?;
-        let mut right_lock_guard = right_pin.lock();
-        let mut right_node = Node::from_page(&mut right_lock_guard.page);
-        let (left_iter, right_iter) =
-            split_at::<&[u8]>(&tmp_node, loc.split_slot);
-        // copy to right page.
-        for r in right_iter {
-            insert_leaf_node(
-                &mut right_node,
-                Record {
-                    key: r.0,
-                    value: r.1,
-                },
-            )?;
+            // the parent may have been split since we visited it.
+            let mut parent_guard =
+                self.move_right(&high_key, parent_pin.lock()).await?;
+            let record = Record {
+                key: high_key.as_ref(),
+                value: right_pid,
+            };
+            let mut node = Node::from_page(&mut parent_guard.page);
+            if has_downlink(&node, &high_key, right_pid)? {
+                clear_incomplete_split(&mut Node::from_page(
+                    &mut lock_guard.page,
+                ));
+                return self.buf_mgr.log_pages(&mut [&mut lock_guard]).await;
+            }
+            if !node.will_overfull(
+                record.encode_size(),
+                self.options.max_records_per_node,
+            ) {
+                insert_internal_node(&mut node, record)?;
+                clear_incomplete_split(&mut Node::from_page(
+                    &mut lock_guard.page,
+                ));
+                return self
+                    .buf_mgr
+                    .log_pages(&mut [&mut parent_guard, &mut lock_guard])
+                    .await;
+            }
+            let (parent_high_key, parent_right_pid) =
+                self.split_node(&mut parent_guard, record).await?;
+            clear_incomplete_split(&mut Node::from_page(&mut lock_guard.page));
+            self.buf_mgr.log_pages(&mut [&mut lock_guard]).await?;
+
+            lock_guard = parent_guard;
+            high_key = parent_high_key;
+            right_pid = parent_right_pid;
         }
+    }
+
+    /// Split a single node into a new right node, returns the high key
+    /// of the left node and the page id of the right node. The left, the
+    /// right and the original right sibling are logged as one change.
+    async fn split_node<V>(
+        &self,
+        lock_guard: &mut LockGuard,
+        record: Record<'_, V>,
+    ) -> Result<(IVec, PageId)>
+    where
+        V: NodeValue + Copy,
+    {
+        let right_pin = self.buf_mgr.alloc_page().await?;
+        let mut right_guard = right_pin.lock();
+        let left_pid = lock_guard.page_id;
+        let right_pid = right_guard.page_id;
 
-        // copy to left page.
         let mut left_node = Node::from_page(&mut lock_guard.page);
-        left_node.clear_records();
-        for r in left_iter {
-            insert_leaf_node(
-                &mut left_node,
-                Record {
-                    key: r.0,
-                    value: r.1,
-                },
-            )?;
-        }
+        let mut right_node = Node::from_page(&mut right_guard.page);
+        let high_key = split_node(
+            &mut left_node,
+            left_pid,
+            &mut right_node,
+            right_pid,
+            record,
+        )?;
+        let sibling_pid = right_node.get_right_sibling();
 
-        if loc.new_record_slot < loc.split_slot {
-            // new record is at left node
-            insert_leaf_node(&mut left_node, record)?;
+        // fix the left sibling of the original right sibling.
+        if sibling_pid != 0 {
+            let sibling_pin = self.buf_mgr.fix_page(sibling_pid).await?;
+            let mut sibling_guard = sibling_pin.lock();
+            Node::from_page(&mut sibling_guard.page)
+                .set_left_sibling(right_pid);
+            self.buf_mgr
+                .log_pages(&mut [
+                    lock_guard,
+                    &mut right_guard,
+                    &mut sibling_guard,
+                ])
+                .await?;
         } else {
-            // new record is at right node
-            insert_leaf_node(&mut right_node, record)?;
+            self.buf_mgr
+                .log_pages(&mut [lock_guard, &mut right_guard])
+                .await?;
         }
-        // todo insert high key and right page into parent.
-        todo!()
+        Ok((high_key, right_pid))
+    }
+
+    /// The root node locked by `lock_guard` has been split, make a new
+    /// root pointing to the two halves. This completes the split of the
+    /// old root.
+    async fn new_root(
+        &self,
+        lock_guard: &mut LockGuard,
+        high_key: &[u8],
+        right_pid: PageId,
+    ) -> Result<()> {
+        let left_pid = lock_guard.page_id;
+        let level = Node::from_page(&mut lock_guard.page).get_tree_level();
+
+        let root_pin = self.buf_mgr.alloc_page().await?;
+        let mut root_guard = root_pin.lock();
+        let root_pid = root_guard.page_id;
+        let mut root_node = Node::from_page(&mut root_guard.page);
+        init_root(&mut root_node, level + 1, high_key, left_pid, right_pid)?;
+        clear_incomplete_split(&mut Node::from_page(&mut lock_guard.page));
+
+        let meta_pin = self.buf_mgr.fix_page(0).await?;
+        let mut meta_guard = meta_pin.lock();
+        MetaPage::from_page(&mut meta_guard.page).set_root(root_pid);
+        self.buf_mgr
+            .log_pages(&mut [&mut root_guard, &mut meta_guard, lock_guard])
+            .await
     }
 
     async fn move_right(
@@ -153,20 +739,776 @@ where
         }
     }
 
+    /// Lock `page_id`, the left sibling of `right_pid` when we released
+    /// `right_pid`. It may have been split since then, so we move right
+    /// until we reach the node whose right sibling is `right_pid`.
+    async fn move_left(
+        &self,
+        mut page_id: PageId,
+        right_pid: PageId,
+    ) -> Result<LockGuard> {
+        loop {
+            let mut lock_guard = self.buf_mgr.fix_page(page_id).await?.lock();
+            let node = Node::from_page(&mut lock_guard.page);
+            if node.is_rightmost() || node.get_right_sibling() == right_pid {
+                return Ok(lock_guard);
+            }
+            page_id = node.get_right_sibling();
+        }
+    }
+
+    /// Find the rightmost leaf by following the last downlink of each
+    /// level.
+    async fn find_rightmost_leaf(&self) -> Result<LockGuard> {
+        let mut lock_guard = self.get_root().await?.lock();
+        loop {
+            let node = Node::from_page(&mut lock_guard.page);
+            let page_id = if !node.is_rightmost() {
+                // the node has been split since we visited its parent.
+                node.get_right_sibling()
+            } else if node.is_leaf() {
+                return Ok(lock_guard);
+            } else {
+                last_child(&node)?
+            };
+            drop(lock_guard);
+            lock_guard = self.buf_mgr.fix_page(page_id).await?.lock();
+        }
+    }
+
     async fn get_root(&self) -> Result<PinGuard> {
-        let pin_guard = self.buf_mgr.fix_page(0).await?;
+        let meta_pin = self.buf_mgr.fix_page(0).await?;
+        let mut meta_guard = meta_pin.lock();
+        let root_id = MetaPage::from_page(&mut meta_guard.page).get_root();
+        if root_id != 0 {
+            drop(meta_guard);
+            return self.buf_mgr.fix_page(root_id).await;
+        }
+
+        // the tree is empty, make a leaf node as the root. We hold the
+        // lock of meta page so that only one root is created.
+        let pin_guard = self.buf_mgr.alloc_page().await?;
         let mut lock_guard = pin_guard.lock();
-        let page = &mut lock_guard.page;
-        let meta_page = MetaPage::from_page(page);
-        let root_id = meta_page.get_root();
-        if root_id == 0 {
-            let pin_guard = self.buf_mgr.alloc_page().await?;
-            let mut lock_guard = pin_guard.lock();
-            let mut node = Node::from_page(&mut lock_guard.page);
-            node.format_page();
-            Ok(pin_guard)
-        } else {
-            self.buf_mgr.fix_page(root_id).await
+        init_leaf_root(&mut Node::from_page(&mut lock_guard.page))?;
+        let root_id = lock_guard.page_id;
+        MetaPage::from_page(&mut meta_guard.page).set_root(root_id);
+        self.buf_mgr
+            .log_pages(&mut [&mut lock_guard, &mut meta_guard])
+            .await?;
+        drop(lock_guard);
+        Ok(pin_guard)
+    }
+}
+
+/// The error of a violation found by [`Tree::validate`].
+#[cfg(test)]
+fn invalid_node(page_id: PageId, msg: &str) -> FloppyError {
+    FloppyError::Internal(format!("invalid node {page_id}: {msg}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dc2::node::num_records;
+    use crate::dc2::page::{MAX_PAGE_SIZE, MIN_PAGE_SIZE};
+    use crate::dc2::wal::{list_segments, remove_wal_files, segment_path};
+    use crate::env::stdenv::StdEnv;
+    use rand::{seq::SliceRandom, thread_rng};
+
+    /// Remove the data file and the log of a tree.
+    fn remove_tree_files(path: &str) {
+        let _ = std::fs::remove_file(path);
+        remove_wal_files(path.as_ref());
+    }
+
+    #[tokio::test]
+    async fn insert_get_range() -> Result<()> {
+        let path = "tmp_test_tree_insert_get_range";
+        remove_tree_files(path);
+        let tree = Tree::open(path, StdEnv).await?;
+
+        let mut keys: Vec<u32> = (0..5000).collect();
+        keys.shuffle(&mut thread_rng());
+        for k in keys.iter() {
+            let key = k.to_be_bytes();
+            tree.insert(key, format!("value-{k}")).await?;
+        }
+
+        for k in keys.iter() {
+            let value = tree.get(k.to_be_bytes()).await?.expect("key exists");
+            assert_eq!(value, format!("value-{k}").as_bytes());
+        }
+        assert!(tree.get(10000u32.to_be_bytes()).await?.is_none());
+        tree.validate().await?;
+        assert!(matches!(
+            tree.insert(1u32.to_be_bytes(), b"dup").await,
+            Err(FloppyError::DC(_))
+        ));
+
+        let all = tree.range::<std::ops::RangeFull>(..).await?;
+        assert_eq!(all.len(), 5000);
+        assert!(all.windows(2).all(|w| w[0].0 < w[1].0));
+
+        let lo = 100u32.to_be_bytes();
+        let hi = 200u32.to_be_bytes();
+        let records = tree
+            .range::<(Bound<&[u8]>, Bound<&[u8]>)>((
+                Bound::Excluded(lo.as_slice()),
+                Bound::Included(hi.as_slice()),
+            ))
+            .await?;
+        let expected = (101u32..=200)
+            .map(|k| IVec::from(k.to_be_bytes().as_slice()))
+            .collect::<Vec<IVec>>();
+        assert_eq!(
+            records.into_iter().map(|r| r.0).collect::<Vec<IVec>>(),
+            expected
+        );
+
+        remove_tree_files(path);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn range_shared() -> Result<()> {
+        let path = "tmp_test_tree_range_shared";
+        remove_tree_files(path);
+        let tree = Tree::open(path, StdEnv).await?;
+        for k in 0..5000u32 {
+            tree.insert(k.to_be_bytes(), format!("value-{k}")).await?;
+        }
+
+        let records = tree.range_shared::<std::ops::RangeFull>(..).await?;
+        let copied = tree.range::<std::ops::RangeFull>(..).await?;
+        assert_eq!(records.len(), 5000);
+        assert!(records.iter().zip(copied.iter()).all(|(r, c)| {
+            r.0.as_ref() == c.0.as_ref() && r.1.as_ref() == c.1.as_ref()
+        }));
+
+        // the values are allocated once per leaf instead of per record.
+        let mut leaves = 1;
+        let (mut lock_guard, _) = tree.find_leaf(&[]).await?;
+        loop {
+            let node = Node::from_page(&mut lock_guard.page);
+            if node.is_rightmost() {
+                break;
+            }
+            let page_id = node.get_right_sibling();
+            drop(lock_guard);
+            lock_guard = tree.buf_mgr.fix_page(page_id).await?.lock();
+            leaves += 1;
+        }
+        drop(lock_guard);
+        let copies = 1 + records
+            .windows(2)
+            .filter(|w| !w[0].1.same_page(&w[1].1))
+            .count();
+        assert!(copies <= leaves, "{copies} copies of {leaves} leaves");
+        assert!(copies * 10 < records.len());
+
+        remove_tree_files(path);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn max_records_per_node() -> Result<()> {
+        for max in [2usize, 3, 5] {
+            let path = format!("tmp_test_tree_max_records_per_node_{max}");
+            remove_tree_files(&path);
+            let options = TreeOptions {
+                max_records_per_node: Some(max),
+                ..Default::default()
+            };
+            let tree = Tree::open_with_options(&path, StdEnv, options).await?;
+
+            let mut keys: Vec<u32> = (0..500).collect();
+            keys.shuffle(&mut thread_rng());
+            for k in keys.iter() {
+                tree.insert(k.to_be_bytes(), format!("value-{k}")).await?;
+            }
+            for k in keys.iter() {
+                let value =
+                    tree.get(k.to_be_bytes()).await?.expect("key exists");
+                assert_eq!(value, format!("value-{k}").as_bytes());
+            }
+            let all = tree.range::<std::ops::RangeFull>(..).await?;
+            assert_eq!(all.len(), 500);
+            assert!(all.windows(2).all(|w| w[0].0 < w[1].0));
+            tree.validate().await?;
+
+            // no leaf has more than `max` records, so the tree is at
+            // least as high as a full tree.
+            let (mut lock_guard, _) = tree.find_leaf(&[]).await?;
+            loop {
+                let node = Node::from_page(&mut lock_guard.page);
+                assert!(num_records(&node) <= max);
+                if node.is_rightmost() {
+                    break;
+                }
+                let page_id = node.get_right_sibling();
+                drop(lock_guard);
+                lock_guard = tree.buf_mgr.fix_page(page_id).await?.lock();
+            }
+            drop(lock_guard);
+            let root_pin = tree.get_root().await?;
+            let level = Node::from_page(&mut root_pin.lock().page)
+                .get_tree_level() as usize;
+            assert!(max.pow(level as u32 + 1) >= 500, "max = {max}");
+
+            remove_tree_files(&path);
+        }
+
+        let options = TreeOptions {
+            max_records_per_node: Some(1),
+            ..Default::default()
+        };
+        let path = "tmp_test_tree_max_records_per_node";
+        assert!(Tree::open_with_options(path, StdEnv, options)
+            .await
+            .is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn page_size() -> Result<()> {
+        let mut levels = vec![];
+        for page_size in [MIN_PAGE_SIZE, PAGE_SIZE] {
+            let path = format!("tmp_test_tree_page_size_{page_size}");
+            remove_tree_files(&path);
+            let options = TreeOptions {
+                page_size: Some(page_size),
+                ..Default::default()
+            };
+            let tree =
+                Tree::open_with_options(&path, StdEnv, options.clone()).await?;
+            let mut keys: Vec<u32> = (0..2000).collect();
+            keys.shuffle(&mut thread_rng());
+            for k in keys.iter() {
+                tree.insert(k.to_be_bytes(), format!("value-{k}")).await?;
+            }
+            tree.validate().await?;
+            let root_pin = tree.get_root().await?;
+            levels.push(
+                Node::from_page(&mut root_pin.lock().page).get_tree_level(),
+            );
+            drop(root_pin);
+
+            // a record of a third of a small page is too large.
+            let large = vec![0; MIN_PAGE_SIZE / 3];
+            let result = tree.insert(u32::MAX.to_be_bytes(), &large).await;
+            assert_eq!(result.is_err(), page_size == MIN_PAGE_SIZE);
+
+            // only the log is durable, the pages are recovered from it
+            // with the same size.
+            tree.buf_mgr.flush_log().await?;
+            drop(tree);
+            let tree = Tree::open_with_options(&path, StdEnv, options).await?;
+            for k in keys.iter() {
+                let value =
+                    tree.get(k.to_be_bytes()).await?.expect("key exists");
+                assert_eq!(value, format!("value-{k}").as_bytes());
+            }
+            let (lo, hi) = (100u32.to_be_bytes(), 200u32.to_be_bytes());
+            let records = tree
+                .range::<(Bound<&[u8]>, Bound<&[u8]>)>((
+                    Bound::Included(lo.as_slice()),
+                    Bound::Excluded(hi.as_slice()),
+                ))
+                .await?;
+            assert_eq!(records.len(), 100);
+            tree.close().await?;
+
+            // a tree can't be opened with a different page size.
+            let options = TreeOptions {
+                page_size: Some(page_size * 2),
+                ..Default::default()
+            };
+            assert!(Tree::open_with_options(&path, StdEnv, options)
+                .await
+                .is_err());
+            remove_tree_files(&path);
+        }
+        assert!(levels[0] > levels[1], "{levels:?}");
+
+        for page_size in [MIN_PAGE_SIZE / 2, PAGE_SIZE + 1, MAX_PAGE_SIZE * 2] {
+            let options = TreeOptions {
+                page_size: Some(page_size),
+                ..Default::default()
+            };
+            let path = "tmp_test_tree_invalid_page_size";
+            assert!(Tree::open_with_options(path, StdEnv, options)
+                .await
+                .is_err());
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn validate() -> Result<()> {
+        let path = "tmp_test_tree_validate";
+        remove_tree_files(path);
+        let options = TreeOptions {
+            max_records_per_node: Some(4),
+            ..Default::default()
+        };
+        let tree = Tree::open_with_options(path, StdEnv, options).await?;
+        for k in 0..100u32 {
+            tree.insert(k.to_be_bytes(), k.to_be_bytes()).await?;
+        }
+        tree.validate().await?;
+
+        // break the sibling link of a leaf.
+        let (mut lock_guard, _) = tree.find_leaf(&50u32.to_be_bytes()).await?;
+        let mut node = Node::from_page(&mut lock_guard.page);
+        let left_pid = node.get_left_sibling();
+        node.set_left_sibling(left_pid + 1000);
+        drop(lock_guard);
+        let err = tree.validate().await.unwrap_err();
+        assert!(err.to_string().contains("left sibling"), "{err}");
+
+        remove_tree_files(path);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn close_and_reopen() -> Result<()> {
+        let path = "tmp_test_tree_close_and_reopen";
+        remove_tree_files(path);
+        let tree = Tree::open(path, StdEnv).await?;
+        for k in 0..3000u32 {
+            tree.insert(k.to_be_bytes(), format!("value-{k}")).await?;
+        }
+        let stats = tree.stats();
+        assert!(stats.hits > 0);
+        assert!(stats.active_pages > 1);
+        assert!(stats.dirty_pages > 0);
+
+        tree.flush().await?;
+        assert_eq!(tree.stats().dirty_pages, 0);
+        tree.insert(3000u32.to_be_bytes(), "value-3000").await?;
+        assert!(tree.stats().dirty_pages > 0);
+        tree.close().await?;
+
+        let tree = Tree::open(path, StdEnv).await?;
+        for k in 0..=3000u32 {
+            let value = tree.get(k.to_be_bytes()).await?.expect("key exists");
+            assert_eq!(value, format!("value-{k}").as_bytes());
+        }
+        let stats = tree.stats();
+        // every page is read from disk after reopen.
+        assert!(stats.misses >= stats.active_pages as u64);
+        assert_eq!(stats.dirty_pages, 0);
+
+        remove_tree_files(path);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn bulk_load() -> Result<()> {
+        let bulk_path = "tmp_test_tree_bulk_load";
+        let insert_path = "tmp_test_tree_bulk_load_insert";
+        remove_tree_files(bulk_path);
+        remove_tree_files(insert_path);
+        let bulk_tree = Tree::open(bulk_path, StdEnv).await?;
+        let insert_tree = Tree::open(insert_path, StdEnv).await?;
+
+        let entries = (0..20000u32)
+            .map(|k| (k.to_be_bytes(), format!("value-{k}")))
+            .collect::<Vec<_>>();
+
+        bulk_tree.bulk_load(entries.clone().into_iter()).await?;
+        for (key, value) in entries.iter() {
+            insert_tree.insert(key, value).await?;
+        }
+
+        let bulk = bulk_tree.range::<std::ops::RangeFull>(..).await?;
+        let insert = insert_tree.range::<std::ops::RangeFull>(..).await?;
+        assert_eq!(bulk.len(), 20000);
+        assert_eq!(bulk, insert);
+        assert_eq!(bulk_tree.range_rev::<std::ops::RangeFull>(..).await?, {
+            let mut rev = insert;
+            rev.reverse();
+            rev
+        });
+        for (key, value) in entries.iter() {
+            let found = bulk_tree.get(key).await?.expect("key exists");
+            assert_eq!(found, value.as_bytes());
+        }
+
+        // the bulk loaded tree accepts inserts which split its nodes.
+        for k in 20000u32..25000 {
+            bulk_tree.insert(k.to_be_bytes(), b"more").await?;
+        }
+        bulk_tree.validate().await?;
+        let all = bulk_tree.range::<std::ops::RangeFull>(..).await?;
+        assert_eq!(all.len(), 25000);
+        assert!(all.windows(2).all(|w| w[0].0 < w[1].0));
+
+        remove_tree_files(bulk_path);
+        remove_tree_files(insert_path);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn bulk_load_unsorted() -> Result<()> {
+        let path = "tmp_test_tree_bulk_load_unsorted";
+        remove_tree_files(path);
+        let tree = Tree::open(path, StdEnv).await?;
+
+        let mut keys: Vec<u32> = (0..3000).collect();
+        keys.shuffle(&mut thread_rng());
+        tree.bulk_load(keys.iter().map(|k| (k.to_be_bytes(), k.to_be_bytes())))
+            .await?;
+
+        let all = tree.range::<std::ops::RangeFull>(..).await?;
+        let expected = (0..3000u32)
+            .map(|k| IVec::from(k.to_be_bytes().as_slice()))
+            .collect::<Vec<IVec>>();
+        assert_eq!(
+            all.into_iter().map(|r| r.0).collect::<Vec<IVec>>(),
+            expected
+        );
+
+        remove_tree_files(path);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn insert_shared_prefix() -> Result<()> {
+        let path = "tmp_test_tree_insert_shared_prefix";
+        remove_tree_files(path);
+        let tree = Tree::open(path, StdEnv).await?;
+
+        let mut keys: Vec<u32> = (0..5000).collect();
+        keys.shuffle(&mut thread_rng());
+        let key = |k: &u32| format!("a-long-shared-key-prefix-{k:08}");
+        for k in keys.iter() {
+            tree.insert(key(k), k.to_be_bytes()).await?;
+        }
+        tree.insert("b-key-without-prefix", b"b").await?;
+
+        for k in keys.iter() {
+            let value = tree.get(key(k)).await?.expect("key exists");
+            assert_eq!(value, k.to_be_bytes());
+        }
+        let all = tree.range::<std::ops::RangeFull>(..).await?;
+        assert_eq!(all.len(), 5001);
+        assert!(all.windows(2).all(|w| w[0].0 < w[1].0));
+        assert_eq!(all[5000].0, b"b-key-without-prefix");
+
+        remove_tree_files(path);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn range_rev() -> Result<()> {
+        let path = "tmp_test_tree_range_rev";
+        remove_tree_files(path);
+        let tree = Tree::open(path, StdEnv).await?;
+
+        let mut keys: Vec<u32> = (0..5000).collect();
+        keys.shuffle(&mut thread_rng());
+        for k in keys.iter() {
+            tree.insert(k.to_be_bytes(), format!("value-{k}")).await?;
+        }
+
+        // 5000 records span multiple leaves.
+        let all = tree.range_rev::<std::ops::RangeFull>(..).await?;
+        assert_eq!(all.len(), 5000);
+        assert!(all.windows(2).all(|w| w[0].0 > w[1].0));
+        assert_eq!(all[0].0, 4999u32.to_be_bytes().as_slice());
+        assert_eq!(all[0].1, b"value-4999".as_slice());
+        assert_eq!(all[4999].0, 0u32.to_be_bytes().as_slice());
+
+        let lo = 100u32.to_be_bytes();
+        let hi = 3000u32.to_be_bytes();
+        let records = tree
+            .range_rev::<(Bound<&[u8]>, Bound<&[u8]>)>((
+                Bound::Included(lo.as_slice()),
+                Bound::Excluded(hi.as_slice()),
+            ))
+            .await?;
+        let expected = (100u32..3000)
+            .rev()
+            .map(|k| IVec::from(k.to_be_bytes().as_slice()))
+            .collect::<Vec<IVec>>();
+        assert_eq!(
+            records.into_iter().map(|r| r.0).collect::<Vec<IVec>>(),
+            expected
+        );
+
+        remove_tree_files(path);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn recover_partial_split() -> Result<()> {
+        let path = "tmp_test_tree_recover_partial_split";
+        remove_tree_files(path);
+        let tree = Tree::open(path, StdEnv).await?;
+        for k in (0..6000u32).step_by(2) {
+            tree.insert(k.to_be_bytes(), format!("value-{k}")).await?;
+        }
+        tree.flush().await?;
+        for k in (6000..8000u32).step_by(2) {
+            tree.insert(k.to_be_bytes(), format!("value-{k}")).await?;
+        }
+
+        // split a leaf but crash before its downlink is inserted into
+        // the parent. Only the log is durable.
+        let key = 1001u32.to_be_bytes();
+        let (mut lock_guard, _) = tree.find_leaf(&key).await?;
+        let record = Record {
+            key: key.as_slice(),
+            value: b"value-1001".as_slice(),
+        };
+        tree.split_node(&mut lock_guard, record).await?;
+        drop(lock_guard);
+        tree.buf_mgr.flush_log().await?;
+        drop(tree);
+
+        // a record torn by the crash is ignored.
+        let last_segment =
+            *list_segments(&StdEnv, path.as_ref())?.last().unwrap();
+        {
+            use std::io::Write;
+            let mut wal = std::fs::OpenOptions::new()
+                .append(true)
+                .open(segment_path(path.as_ref(), last_segment))?;
+            wal.write_all(&[0xff; 100])?;
+        }
+
+        let tree = Tree::open(path, StdEnv).await?;
+        let mut keys = (0..8000u32).step_by(2).collect::<Vec<u32>>();
+        keys.push(1001);
+        keys.sort();
+        for k in keys.iter() {
+            let value = tree.get(k.to_be_bytes()).await?.expect("key exists");
+            assert_eq!(value, format!("value-{k}").as_bytes());
+        }
+        let all = tree.range::<std::ops::RangeFull>(..).await?;
+        let expected = keys
+            .iter()
+            .map(|k| IVec::from(k.to_be_bytes().as_slice()))
+            .collect::<Vec<IVec>>();
+        assert_eq!(
+            all.into_iter().map(|r| r.0).collect::<Vec<IVec>>(),
+            expected
+        );
+        let all = tree.range_rev::<std::ops::RangeFull>(..).await?;
+        assert_eq!(all.len(), keys.len());
+
+        // the recovered tree keeps splitting around the partial split.
+        for k in (1..1000u32).step_by(2) {
+            tree.insert(k.to_be_bytes(), format!("value-{k}")).await?;
+        }
+        let all = tree.range::<std::ops::RangeFull>(..).await?;
+        assert_eq!(all.len(), keys.len() + 500);
+        assert!(all.windows(2).all(|w| w[0].0 < w[1].0));
+        tree.validate().await?;
+        tree.close().await?;
+
+        remove_tree_files(path);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn finish_incomplete_split() -> Result<()> {
+        let path = "tmp_test_tree_finish_incomplete_split";
+        remove_tree_files(path);
+        let tree = Tree::open(path, StdEnv).await?;
+        for k in (0..10u32).step_by(2) {
+            tree.insert(k.to_be_bytes(), k.to_be_bytes()).await?;
+        }
+
+        // the root leaf splits but the new root is not made.
+        let key = 5u32.to_be_bytes();
+        let (mut lock_guard, stack) = tree.find_leaf(&key).await?;
+        assert!(stack.is_empty());
+        let old_root = lock_guard.page_id;
+        let record = Record {
+            key: key.as_slice(),
+            value: key.as_slice(),
+        };
+        tree.split_node(&mut lock_guard, record).await?;
+        assert!(Node::from_page(&mut lock_guard.page).is_incomplete_split());
+        drop(lock_guard);
+
+        assert_eq!(tree.get(key).await?, Some(IVec::from(key.as_slice())));
+        let root_pin = tree.get_root().await?;
+        assert_ne!(root_pin.lock().page_id, old_root);
+        let mut lock_guard = tree.buf_mgr.fix_page(old_root).await?.lock();
+        assert!(!Node::from_page(&mut lock_guard.page).is_incomplete_split());
+        drop(lock_guard);
+
+        for k in (10..5000u32).step_by(2) {
+            tree.insert(k.to_be_bytes(), k.to_be_bytes()).await?;
+        }
+
+        // a leaf splits but its downlink is not inserted into the parent.
+        let key = 1001u32.to_be_bytes();
+        let (mut lock_guard, stack) = tree.find_leaf(&key).await?;
+        let left_pid = lock_guard.page_id;
+        let record = Record {
+            key: key.as_slice(),
+            value: key.as_slice(),
+        };
+        let (high_key, right_pid) =
+            tree.split_node(&mut lock_guard, record).await?;
+        drop(lock_guard);
+        let mut parent_guard = stack.last().unwrap().lock();
+        assert!(!has_downlink(
+            &Node::from_page(&mut parent_guard.page),
+            &high_key,
+            right_pid
+        )?);
+        drop(parent_guard);
+
+        // a later traversal through the left node finishes the split.
+        let left_key = 1000u32.to_be_bytes();
+        assert!(tree.get(left_key).await?.is_some());
+        let mut lock_guard = tree.buf_mgr.fix_page(left_pid).await?.lock();
+        assert!(!Node::from_page(&mut lock_guard.page).is_incomplete_split());
+        drop(lock_guard);
+        let mut parent_guard = stack.last().unwrap().lock();
+        assert!(has_downlink(
+            &Node::from_page(&mut parent_guard.page),
+            &high_key,
+            right_pid
+        )?);
+        drop(parent_guard);
+
+        tree.validate().await?;
+        let all = tree.range::<std::ops::RangeFull>(..).await?;
+        assert_eq!(all.len(), 2502);
+        assert!(all.windows(2).all(|w| w[0].0 < w[1].0));
+
+        remove_tree_files(path);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn checkpoint() -> Result<()> {
+        let path = "tmp_test_tree_checkpoint";
+        remove_tree_files(path);
+        let tree = Tree::open(path, StdEnv).await?;
+        for k in 0..3000u32 {
+            tree.insert(k.to_be_bytes(), format!("value-{k}")).await?;
+        }
+        let segments = list_segments(&StdEnv, path.as_ref())?;
+        assert!(segments.len() > 1);
+
+        tree.checkpoint().await?;
+        let checkpoint_segments = list_segments(&StdEnv, path.as_ref())?;
+        assert_eq!(checkpoint_segments.len(), 1);
+        assert!(checkpoint_segments[0] > *segments.last().unwrap());
+
+        // crash after more inserts, only the log is durable.
+        for k in 3000..4000u32 {
+            tree.insert(k.to_be_bytes(), format!("value-{k}")).await?;
+        }
+        tree.buf_mgr.flush_log().await?;
+        drop(tree);
+
+        let tree = Tree::open(path, StdEnv).await?;
+        assert_eq!(
+            list_segments(&StdEnv, path.as_ref())?[0],
+            checkpoint_segments[0]
+        );
+        for k in 0..4000u32 {
+            let value = tree.get(k.to_be_bytes()).await?.expect("key exists");
+            assert_eq!(value, format!("value-{k}").as_bytes());
+        }
+        tree.close().await?;
+        assert_eq!(list_segments(&StdEnv, path.as_ref())?.len(), 1);
+
+        remove_tree_files(path);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn recover_torn_page() -> Result<()> {
+        let path = "tmp_test_tree_recover_torn_page";
+        remove_tree_files(path);
+        let tree = Tree::open(path, StdEnv).await?;
+        for k in (0..3000u32).step_by(2) {
+            tree.insert(k.to_be_bytes(), format!("value-{k}")).await?;
+        }
+        tree.flush().await?;
+        let (lock_guard, _) = tree.find_leaf(&1001u32.to_be_bytes()).await?;
+        let page_id = lock_guard.page_id;
+        drop(lock_guard);
+        for k in (1..3000u32).step_by(2) {
+            tree.insert(k.to_be_bytes(), format!("value-{k}")).await?;
+        }
+        tree.buf_mgr.flush_log().await?;
+
+        // the crash tears the write of a leaf, its first sector with the
+        // new header is on disk but the rest of it is old.
+        let lock_guard = tree.buf_mgr.fix_page(page_id).await?.lock();
+        {
+            use std::os::unix::fs::FileExt;
+            let file = std::fs::OpenOptions::new().write(true).open(path)?;
+            let pos = page_id as u64 * PAGE_SIZE as u64;
+            file.write_all_at(&lock_guard.page.data()[..512], pos)?;
+        }
+        drop(lock_guard);
+        drop(tree);
+
+        let tree = Tree::open(path, StdEnv).await?;
+        for k in 0..3000u32 {
+            let value = tree.get(k.to_be_bytes()).await?.expect("key exists");
+            assert_eq!(value, format!("value-{k}").as_bytes());
+        }
+        tree.validate().await?;
+        tree.close().await?;
+
+        remove_tree_files(path);
+        Ok(())
+    }
+
+    mod pt {
+        use super::*;
+        use futures::executor::block_on;
+        use proptest::collection::vec;
+        use proptest::prelude::*;
+        use std::collections::BTreeSet;
+
+        proptest! {
+            #[test]
+            fn insert_and_validate(
+                keys in vec(any::<u16>(), 0..300),
+                max_records in 2..6usize,
+            ) {
+                let path = "tmp_test_tree_pt_insert_and_validate";
+                remove_tree_files(path);
+                let options = TreeOptions {
+                    max_records_per_node: Some(max_records),
+                    ..Default::default()
+                };
+                let tree =
+                    block_on(Tree::open_with_options(path, StdEnv, options))
+                        .unwrap();
+                let mut inserted = BTreeSet::new();
+                for k in keys {
+                    if inserted.insert(k) {
+                        block_on(tree.insert(k.to_be_bytes(), k.to_be_bytes())).unwrap();
+                    }
+                }
+                block_on(tree.validate()).unwrap();
+
+                let all = block_on(tree.range::<std::ops::RangeFull>(..)).unwrap();
+                let expected = inserted
+                    .iter()
+                    .map(|k| IVec::from(k.to_be_bytes().as_slice()))
+                    .collect::<Vec<IVec>>();
+                prop_assert_eq!(
+                    all.into_iter().map(|r| r.0).collect::<Vec<IVec>>(),
+                    expected
+                );
+                drop(tree);
+                remove_tree_files(path);
+            }
         }
     }
 }
@@ -0,0 +1,418 @@
+/// [`Wal`] is a redo only write-ahead log of page images.
+///
+/// Every atomic change of the tree, e.g. inserting into a leaf or
+/// splitting a node, is logged as a single record that contains the
+/// images of all the pages it modified. The `lsn` in the header of
+/// these pages is set to the end of the record. Before a page is
+/// written to the data file, the log is flushed up to the page's `lsn`.
+///
+/// The log is split into segment files, named by the [`Lsn`] they start
+/// at. A new segment is started when the current one exceeds
+/// [`SEGMENT_SIZE`] and at every checkpoint. A checkpoint writes all the
+/// dirty pages to the data file, then records its [`Lsn`] in the
+/// checkpoint file and removes the segments before it.
+///
+/// When the tree is opened, records after the last checkpoint are
+/// replayed in order. Every page image is written to the data file even
+/// if the `lsn` of the page on disk is newer, since the write of that
+/// page may be torn by the crash, leaving a new header over an old body.
+/// The last image of a page in the log is the newest, so replaying them
+/// all in order ends with the page before the crash.
+///
+/// A record is arranged as:
+///
+/// start    - 8 bytes [`Lsn`] where the record starts.
+/// length   - 4 bytes length of the payload.
+/// checksum - 4 bytes checksum of the payload.
+/// payload  - a list of 4 bytes [`PageId`] and the page image.
+///
+/// A record torn by a crash fails the checksum, it and everything after
+/// it are ignored.
+use crate::common::error::{FloppyError, Result};
+use crate::dc2::{buf::LockGuard, page::PageId};
+use crate::env::*;
+use futures::lock::Mutex;
+use std::{
+    mem,
+    path::{Path, PathBuf},
+};
+
+/// Log sequence number, it is the byte offset in the log.
+pub(crate) type Lsn = u64;
+
+/// A segment is closed once it grows beyond this size.
+const SEGMENT_SIZE: u64 = 4 << 20;
+
+/// The size of the log since the last checkpoint that triggers a new
+/// checkpoint, see [`Wal::log_size`].
+pub(crate) const CHECKPOINT_LOG_SIZE: u64 = 64 << 20;
+
+const RECORD_HEADER_SIZE: usize =
+    mem::size_of::<Lsn>() + 2 * mem::size_of::<u32>();
+
+/// The size of a page image of a record, the [`PageId`] and the page.
+fn page_image_size(page_size: usize) -> usize {
+    mem::size_of::<PageId>() + page_size
+}
+
+/// Returns the path of the log segment starts at `lsn`.
+pub(crate) fn segment_path(path: &Path, lsn: Lsn) -> PathBuf {
+    let mut segment_path = path.as_os_str().to_owned();
+    segment_path.push(format!(".wal.{lsn:016x}"));
+    PathBuf::from(segment_path)
+}
+
+/// Returns the path of the file that records the last checkpoint.
+fn checkpoint_path(path: &Path) -> PathBuf {
+    let mut checkpoint_path = path.as_os_str().to_owned();
+    checkpoint_path.push(".ckpt");
+    PathBuf::from(checkpoint_path)
+}
+
+/// Returns the directory of the data file at `path`.
+fn dir_path(path: &Path) -> &Path {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    }
+}
+
+/// Returns the start [`Lsn`] of the log segments of the data file at
+/// `path` in ascending order.
+pub(crate) fn list_segments<E: Env>(env: &E, path: &Path) -> Result<Vec<Lsn>> {
+    let mut prefix = path.file_name().unwrap_or_default().to_owned();
+    prefix.push(".wal.");
+    let prefix = prefix.to_string_lossy().into_owned();
+
+    let mut segments = vec![];
+    for entry in env.read_dir(dir_path(path))? {
+        let name = entry?.file_name();
+        let lsn = name
+            .to_str()
+            .and_then(|name| name.strip_prefix(&prefix))
+            .and_then(|lsn| Lsn::from_str_radix(lsn, 16).ok());
+        if let Some(lsn) = lsn {
+            segments.push(lsn);
+        }
+    }
+    segments.sort_unstable();
+    Ok(segments)
+}
+
+/// Remove the log of the data file at `path`.
+#[cfg(test)]
+pub(crate) fn remove_wal_files(path: &Path) {
+    use crate::env::stdenv::StdEnv;
+    for lsn in list_segments(&StdEnv, path).unwrap_or_default() {
+        let _ = std::fs::remove_file(segment_path(path, lsn));
+    }
+    let _ = std::fs::remove_file(checkpoint_path(path));
+}
+
+pub(crate) struct Wal<E: Env> {
+    env: E,
+    /// The path of the data file.
+    path: PathBuf,
+    /// The size of the pages of the data file.
+    page_size: usize,
+    state: Mutex<WalState<E>>,
+}
+
+struct WalState<E: Env> {
+    /// The segment new records are written to.
+    segment: E::PositionalReaderWriter,
+    /// The lsn where `segment` starts.
+    segment_lsn: Lsn,
+    /// Records appended but not written to the segment yet.
+    buf: Vec<u8>,
+    /// The lsn where `buf` starts.
+    buf_lsn: Lsn,
+    /// The log is durable up to this lsn.
+    flushed_lsn: Lsn,
+    /// The lsn of the last checkpoint, recovery starts from here.
+    checkpoint_lsn: Lsn,
+}
+
+impl<E> Wal<E>
+where
+    E: Env,
+{
+    /// Open the log of the data file at `path` and replay it onto
+    /// `data_file`, whose pages are of `page_size`. New records are
+    /// appended after the last valid record.
+    pub async fn open(
+        env: &E,
+        path: &Path,
+        data_file: &E::PositionalReaderWriter,
+        page_size: usize,
+    ) -> Result<Self> {
+        let checkpoint_lsn = Self::read_checkpoint(env, path).await?;
+        let mut end_lsn = checkpoint_lsn;
+        let mut current = None;
+        let mut replayed = false;
+        for lsn in list_segments(env, path)? {
+            // segments before the checkpoint are not needed, and
+            // segments after the end of the log are never completed.
+            if lsn != end_lsn {
+                env.remove_file(segment_path(path, lsn)).await?;
+                continue;
+            }
+            let segment = env.open_file(segment_path(path, lsn)).await?;
+            let segment_end =
+                Self::recover(&segment, lsn, data_file, page_size).await?;
+            replayed |= segment_end > lsn;
+            end_lsn = segment_end;
+            current = Some((segment, lsn));
+        }
+        if replayed {
+            data_file.sync_all().await?;
+        }
+
+        let (segment, segment_lsn) = match current {
+            Some(current) => current,
+            None => (Self::create_segment(env, path, end_lsn).await?, end_lsn),
+        };
+        Ok(Self {
+            env: env.clone(),
+            path: path.to_path_buf(),
+            page_size,
+            state: Mutex::new(WalState {
+                segment,
+                segment_lsn,
+                buf: vec![],
+                buf_lsn: end_lsn,
+                flushed_lsn: end_lsn,
+                checkpoint_lsn,
+            }),
+        })
+    }
+
+    /// Log the images of the pages locked by `guards` as a single
+    /// record and set their `lsn` to the end of the record.
+    /// The record is not durable until [`Wal::flush`] is called.
+    pub async fn append(&self, guards: &mut [&mut LockGuard]) -> Result<Lsn> {
+        let len = guards.len() * page_image_size(self.page_size);
+        let mut state = self.state.lock().await;
+        let start = state.buf_lsn + state.buf.len() as Lsn;
+        let end = start + (RECORD_HEADER_SIZE + len) as Lsn;
+        if start > state.segment_lsn && end - state.segment_lsn > SEGMENT_SIZE {
+            self.switch_segment(&mut state).await?;
+        }
+
+        let mut payload = Vec::with_capacity(len);
+        for guard in guards.iter_mut() {
+            guard.page.set_lsn(end);
+            payload.extend(guard.page_id.to_le_bytes());
+            payload.extend(guard.page.data());
+        }
+        state.buf.extend(start.to_le_bytes());
+        state.buf.extend((len as u32).to_le_bytes());
+        state.buf.extend(checksum(&payload).to_le_bytes());
+        state.buf.extend(payload);
+        Ok(end)
+    }
+
+    /// Make the log durable up to `lsn`.
+    pub async fn flush(&self, lsn: Lsn) -> Result<()> {
+        let mut state = self.state.lock().await;
+        if lsn <= state.flushed_lsn {
+            return Ok(());
+        }
+        Self::write_buf(&mut state).await?;
+        state.segment.sync_data().await?;
+        state.flushed_lsn = state.buf_lsn;
+        Ok(())
+    }
+
+    /// Make every appended record durable.
+    pub async fn flush_all(&self) -> Result<()> {
+        self.flush(Lsn::MAX).await
+    }
+
+    /// Returns the size of the log since the last checkpoint.
+    pub async fn log_size(&self) -> u64 {
+        let state = self.state.lock().await;
+        state.buf_lsn + state.buf.len() as Lsn - state.checkpoint_lsn
+    }
+
+    /// Start a new segment for the records after the checkpoint,
+    /// returns the lsn of the checkpoint. The checkpoint is completed
+    /// by [`Wal::end_checkpoint`] once every page modified before it is
+    /// written to the data file.
+    pub async fn begin_checkpoint(&self) -> Result<Lsn> {
+        let mut state = self.state.lock().await;
+        if state.buf_lsn + state.buf.len() as Lsn > state.segment_lsn {
+            self.switch_segment(&mut state).await?;
+        }
+        Ok(state.segment_lsn)
+    }
+
+    /// Record the checkpoint at `lsn` and remove the segments before it.
+    pub async fn end_checkpoint(&self, lsn: Lsn) -> Result<()> {
+        let mut state = self.state.lock().await;
+        if lsn <= state.checkpoint_lsn {
+            return Ok(());
+        }
+
+        // the checkpoint file is replaced atomically.
+        let path = checkpoint_path(&self.path);
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        let mut data = lsn.to_le_bytes().to_vec();
+        data.extend(checksum(&data).to_le_bytes());
+        let file = self.env.open_file(&tmp_path).await?;
+        file.write_exact_at(&data, 0).await?;
+        file.sync_all().await?;
+        self.env.rename(&tmp_path, &path).await?;
+        self.env
+            .open_dir(dir_path(&self.path))
+            .await?
+            .sync_all()
+            .await?;
+        state.checkpoint_lsn = lsn;
+
+        for segment_lsn in list_segments(&self.env, &self.path)? {
+            if segment_lsn < lsn {
+                self.env
+                    .remove_file(segment_path(&self.path, segment_lsn))
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the lsn of the last checkpoint, zero if there is none.
+    async fn read_checkpoint(env: &E, path: &Path) -> Result<Lsn> {
+        let path = checkpoint_path(path);
+        if env.metadata(&path).await.is_err() {
+            return Ok(0);
+        }
+        let file = env.open_file(&path).await?;
+        let mut data = [0; mem::size_of::<Lsn>() + mem::size_of::<u32>()];
+        file.read_exact_at(&mut data, 0).await?;
+        let (lsn, sum) = data.split_at(mem::size_of::<Lsn>());
+        if checksum(lsn) != u32::from_le_bytes(sum.try_into().unwrap()) {
+            return Err(FloppyError::Internal(format!(
+                "corrupted checkpoint file {path:?}"
+            )));
+        }
+        Ok(Lsn::from_le_bytes(lsn.try_into().unwrap()))
+    }
+
+    /// Close the current segment and start a new one at the end of the
+    /// log.
+    async fn switch_segment(&self, state: &mut WalState<E>) -> Result<()> {
+        Self::write_buf(state).await?;
+        state.segment.sync_data().await?;
+        state.flushed_lsn = state.buf_lsn;
+        state.segment =
+            Self::create_segment(&self.env, &self.path, state.buf_lsn).await?;
+        state.segment_lsn = state.buf_lsn;
+        Ok(())
+    }
+
+    async fn create_segment(
+        env: &E,
+        path: &Path,
+        lsn: Lsn,
+    ) -> Result<E::PositionalReaderWriter> {
+        let segment = env.open_file(segment_path(path, lsn)).await?;
+        segment.sync_all().await?;
+        env.open_dir(dir_path(path)).await?.sync_all().await?;
+        Ok(segment)
+    }
+
+    /// Write the appended records to the current segment.
+    async fn write_buf(state: &mut WalState<E>) -> Result<()> {
+        if !state.buf.is_empty() {
+            let pos = state.buf_lsn - state.segment_lsn;
+            state.segment.write_exact_at(&state.buf, pos).await?;
+            state.buf_lsn += state.buf.len() as Lsn;
+            state.buf.clear();
+        }
+        Ok(())
+    }
+
+    /// Replay the valid records of `segment` starts at `segment_lsn` onto
+    /// `data_file`, returns the end of the last valid record.
+    async fn recover(
+        segment: &E::PositionalReaderWriter,
+        segment_lsn: Lsn,
+        data_file: &E::PositionalReaderWriter,
+        page_size: usize,
+    ) -> Result<Lsn> {
+        let size = segment.file_size().await as Lsn;
+        let mut lsn = segment_lsn;
+        while let Some(payload) =
+            Self::read_record(segment, segment_lsn, lsn, size, page_size)
+                .await?
+        {
+            lsn += (RECORD_HEADER_SIZE + payload.len()) as Lsn;
+            for image in payload.chunks(page_image_size(page_size)) {
+                let (page_id, data) = image.split_at(mem::size_of::<PageId>());
+                let page_id =
+                    PageId::from_le_bytes(page_id.try_into().unwrap());
+                Self::redo_page(data_file, page_id, data).await?;
+            }
+        }
+        Ok(lsn)
+    }
+
+    /// Read the payload of the record starts at `lsn`. Returns `None` if
+    /// there is no valid record.
+    async fn read_record(
+        segment: &E::PositionalReaderWriter,
+        segment_lsn: Lsn,
+        lsn: Lsn,
+        size: Lsn,
+        page_size: usize,
+    ) -> Result<Option<Vec<u8>>> {
+        let pos = lsn - segment_lsn;
+        if pos + RECORD_HEADER_SIZE as Lsn > size {
+            return Ok(None);
+        }
+        let mut header = [0; RECORD_HEADER_SIZE];
+        segment.read_exact_at(&mut header, pos).await?;
+        let start = Lsn::from_le_bytes(header[0..8].try_into().unwrap());
+        let len = u32::from_le_bytes(header[8..12].try_into().unwrap());
+        let sum = u32::from_le_bytes(header[12..16].try_into().unwrap());
+        let len = len as usize;
+        if start != lsn
+            || len % page_image_size(page_size) != 0
+            || pos + (RECORD_HEADER_SIZE + len) as Lsn > size
+        {
+            return Ok(None);
+        }
+
+        let mut payload = vec![0; len];
+        segment
+            .read_exact_at(&mut payload, pos + RECORD_HEADER_SIZE as Lsn)
+            .await?;
+        if checksum(&payload) != sum {
+            return Ok(None);
+        }
+        Ok(Some(payload))
+    }
+
+    /// Write the page image `data` to `data_file`, the page is of the
+    /// size of `data`. The `lsn` of the page on disk is not trusted, the
+    /// page may be torn.
+    async fn redo_page(
+        data_file: &E::PositionalReaderWriter,
+        page_id: PageId,
+        data: &[u8],
+    ) -> Result<()> {
+        let pos = page_id as u64 * data.len() as u64;
+        data_file
+            .write_exact_at(data, pos)
+            .await
+            .map_err(FloppyError::Io)
+    }
+}
+
+/// FNV-1a hash of `data`, used to detect torn records.
+fn checksum(data: &[u8]) -> u32 {
+    data.iter().fold(0x811c9dc5, |hash, b| {
+        (hash ^ *b as u32).wrapping_mul(0x01000193)
+    })
+}
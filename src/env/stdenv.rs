@@ -146,7 +146,7 @@ impl super::PositionalReader for PositionalReaderWriter {
     }
 
     async fn file_size(&self) -> usize {
-        todo!()
+        self.0.metadata().map(|m| m.len() as usize).unwrap_or(0)
     }
 }
 
@@ -10,9 +10,12 @@
 
 mod catalog;
 mod common;
+// the former data component, superseded by dc2 and not compiled.
 // mod dc;
 mod dc2;
 mod env;
+mod pgrepr;
+mod pgwire;
 mod session;
 mod sql;
 mod storage;
@@ -0,0 +1,11 @@
+//! Representation of values in PostgreSQL's frontend/backend protocol.
+//!
+//! Rows are converted to protocol values for a given set of types, for
+//! example by [`value::values_from_row`], and then encoded in text or
+//! binary format.
+
+pub mod copy;
+pub mod types;
+pub mod value;
+
+pub use types::{Format, Type};
@@ -0,0 +1,161 @@
+//! The text format of `COPY`.
+//!
+//! See <https://www.postgresql.org/docs/current/sql-copy.html>.
+
+use crate::common::error::{FloppyError, Result};
+use crate::common::relation::Row;
+use crate::common::scalar::Datum;
+use crate::pgrepr::{value, Format, Type};
+
+/// Decode the rows in `data`, whose columns are of `types`. Each line
+/// is a row, the columns are separated by tabs and `\N` is a NULL.
+/// A line of `\.` marks the end of the data.
+pub fn decode_text(data: &[u8], types: &[Type]) -> Result<Vec<Vec<Datum>>> {
+    let data = std::str::from_utf8(data).map_err(|e| {
+        FloppyError::BadCopyFormat(format!("invalid UTF-8 data: {e}"))
+    })?;
+    let mut rows = vec![];
+    for (i, line) in data.lines().enumerate() {
+        if line == "\\." {
+            break;
+        }
+        let line_no = i + 1;
+        let fields = line.split('\t').collect::<Vec<_>>();
+        if fields.len() < types.len() {
+            return Err(FloppyError::BadCopyFormat(format!(
+                "missing data for column {} (line {line_no})",
+                fields.len() + 1
+            )));
+        }
+        if fields.len() > types.len() {
+            return Err(FloppyError::BadCopyFormat(format!(
+                "extra data after last expected column (line {line_no})"
+            )));
+        }
+        let row = fields
+            .iter()
+            .zip(types)
+            .map(|(field, ty)| match *field {
+                "\\N" => Ok(Datum::Null),
+                _ => {
+                    value::decode(*ty, Format::Text, unescape(field).as_bytes())
+                        .map_err(|e| {
+                            FloppyError::BadCopyFormat(format!(
+                                "{} (line {line_no})",
+                                e.message()
+                            ))
+                        })
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+/// Encode `row`, whose columns are of `types`, as a line in the
+/// format decoded by [`decode_text`].
+pub fn encode_text(row: &Row, types: &[Type]) -> Result<Vec<u8>> {
+    let mut line = vec![];
+    for (i, v) in value::values_from_row(row, types)?.iter().enumerate() {
+        if i > 0 {
+            line.push(b'\t');
+        }
+        match v {
+            Some(v) => escape(&v.encode(Format::Text), &mut line),
+            None => line.extend(b"\\N"),
+        }
+    }
+    line.push(b'\n');
+    Ok(line)
+}
+
+/// Append `field` to `buf`, with the characters that have a special
+/// meaning in the text format escaped.
+fn escape(field: &[u8], buf: &mut Vec<u8>) {
+    for b in field {
+        match b {
+            b'\\' => buf.extend(b"\\\\"),
+            b'\x08' => buf.extend(b"\\b"),
+            b'\x0c' => buf.extend(b"\\f"),
+            b'\n' => buf.extend(b"\\n"),
+            b'\r' => buf.extend(b"\\r"),
+            b'\t' => buf.extend(b"\\t"),
+            b'\x0b' => buf.extend(b"\\v"),
+            b => buf.push(*b),
+        }
+    }
+}
+
+/// Replace the backslash escapes in `field`, a backslash followed by
+/// any other character is that character.
+fn unescape(field: &str) -> String {
+    let mut s = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            s.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('b') => s.push('\x08'),
+            Some('f') => s.push('\x0c'),
+            Some('n') => s.push('\n'),
+            Some('r') => s.push('\r'),
+            Some('t') => s.push('\t'),
+            Some('v') => s.push('\x0b'),
+            Some(c) => s.push(c),
+            None => {}
+        }
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_rows() -> Result<()> {
+        let data = b"1\ta\\tb\n2\t\\N\n\\.\n3\tignored\n";
+        let rows = decode_text(data, &[Type::Int8, Type::Text])?;
+        assert_eq!(
+            rows,
+            vec![
+                vec![Datum::Int64(1), Datum::Text("a\tb".to_string())],
+                vec![Datum::Int64(2), Datum::Null],
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn encode_rows() -> Result<()> {
+        let types = [Type::Int8, Type::Text];
+        let rows = [
+            Row::new(vec![Datum::Int64(1), Datum::Text("a\tb\\".to_string())]),
+            Row::new(vec![Datum::Int64(2), Datum::Null]),
+        ];
+        let mut data = vec![];
+        for row in &rows {
+            data.extend(encode_text(row, &types)?);
+        }
+        assert_eq!(data, b"1\ta\\tb\\\\\n2\t\\N\n");
+        // the encoded rows are decoded back.
+        let decoded = decode_text(&data, &types)?;
+        assert_eq!(
+            decoded,
+            rows.map(|row| row.iter().cloned().collect::<Vec<_>>())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn decode_malformed_rows() {
+        let types = [Type::Int8, Type::Int8];
+        for data in [&b"1\n"[..], b"1\t2\t3\n", b"1\tx\n"] {
+            let e = decode_text(data, &types).unwrap_err();
+            assert_eq!(e.code(), "22P04", "{data:?}");
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use crate::common::error::{FloppyError, Result};
+use crate::common::scalar::ScalarType;
+
+/// The type of a value in PostgreSQL's frontend/backend protocol.
+///
+/// The OIDs are from PostgreSQL's `pg_type.dat`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Type {
+    Bool,
+    Int2,
+    Int4,
+    Int8,
+    Float4,
+    Float8,
+    Numeric,
+    Text,
+    Date,
+    Timestamp,
+    Interval,
+    Bytea,
+    Uuid,
+    /// A one-dimensional array of the element type, which is not an
+    /// array.
+    Array(&'static Type),
+}
+
+/// The element types of arrays and the OIDs of their array types.
+static ARRAY_TYPES: [(Type, u32); 13] = [
+    (Type::Bool, 1000),
+    (Type::Int2, 1005),
+    (Type::Int4, 1007),
+    (Type::Int8, 1016),
+    (Type::Float4, 1021),
+    (Type::Float8, 1022),
+    (Type::Numeric, 1231),
+    (Type::Text, 1009),
+    (Type::Date, 1182),
+    (Type::Timestamp, 1115),
+    (Type::Interval, 1187),
+    (Type::Bytea, 1001),
+    (Type::Uuid, 2951),
+];
+
+impl Type {
+    /// Returns the type of `oid`. An `oid` of zero means the type
+    /// is unspecified and should be inferred, `None` is returned.
+    pub fn from_oid(oid: u32) -> Result<Option<Type>> {
+        match oid {
+            0 => Ok(None),
+            16 => Ok(Some(Self::Bool)),
+            21 => Ok(Some(Self::Int2)),
+            23 => Ok(Some(Self::Int4)),
+            20 => Ok(Some(Self::Int8)),
+            700 => Ok(Some(Self::Float4)),
+            701 => Ok(Some(Self::Float8)),
+            1700 => Ok(Some(Self::Numeric)),
+            25 => Ok(Some(Self::Text)),
+            1082 => Ok(Some(Self::Date)),
+            1114 => Ok(Some(Self::Timestamp)),
+            1186 => Ok(Some(Self::Interval)),
+            17 => Ok(Some(Self::Bytea)),
+            2950 => Ok(Some(Self::Uuid)),
+            _ => match ARRAY_TYPES.iter().find(|(_, o)| *o == oid) {
+                Some((elem, _)) => Ok(Some(Self::Array(elem))),
+                None => Err(FloppyError::NotImplemented(format!(
+                    "unsupported type oid: {oid}"
+                ))),
+            },
+        }
+    }
+
+    /// The array type whose elements are of this type. Like
+    /// PostgreSQL, an array of arrays is of the same type as its
+    /// elements.
+    pub fn array(&self) -> Type {
+        match self {
+            Self::Array(_) => *self,
+            _ => {
+                let (elem, _) = ARRAY_TYPES
+                    .iter()
+                    .find(|(elem, _)| elem == self)
+                    .expect("every type has an array type");
+                Self::Array(elem)
+            }
+        }
+    }
+
+    /// The type of the elements if this is an array type.
+    pub fn inner(&self) -> Option<&'static Type> {
+        match self {
+            Self::Array(elem) => Some(elem),
+            _ => None,
+        }
+    }
+
+    pub fn oid(&self) -> u32 {
+        match self {
+            Self::Bool => 16,
+            Self::Int2 => 21,
+            Self::Int4 => 23,
+            Self::Int8 => 20,
+            Self::Float4 => 700,
+            Self::Float8 => 701,
+            Self::Numeric => 1700,
+            Self::Text => 25,
+            Self::Date => 1082,
+            Self::Timestamp => 1114,
+            Self::Interval => 1186,
+            Self::Bytea => 17,
+            Self::Uuid => 2950,
+            Self::Array(elem) => ARRAY_TYPES
+                .iter()
+                .find(|(e, _)| e == *elem)
+                .map(|(_, oid)| *oid)
+                .expect("every type has an array type"),
+        }
+    }
+
+    /// The number of bytes of the type, -1 for variable length types.
+    pub fn typlen(&self) -> i16 {
+        match self {
+            Self::Bool => 1,
+            Self::Int2 => 2,
+            Self::Int4 => 4,
+            Self::Int8 => 8,
+            Self::Float4 => 4,
+            Self::Float8 => 8,
+            Self::Date => 4,
+            Self::Timestamp => 8,
+            Self::Interval => 16,
+            Self::Uuid => 16,
+            Self::Numeric | Self::Text | Self::Bytea | Self::Array(_) => -1,
+        }
+    }
+
+    /// The [`ScalarType`] that holds values of this type.
+    pub fn scalar_type(&self) -> ScalarType {
+        match self {
+            Self::Bool => ScalarType::Boolean,
+            Self::Int2 | Self::Int4 | Self::Int8 => ScalarType::Int64,
+            Self::Float4 => ScalarType::Float32,
+            Self::Float8 => ScalarType::Float64,
+            Self::Numeric => ScalarType::Numeric(None),
+            Self::Text => ScalarType::Text,
+            Self::Date => ScalarType::Date,
+            Self::Timestamp => ScalarType::Timestamp,
+            Self::Interval => ScalarType::Interval,
+            Self::Bytea => ScalarType::Bytea,
+            Self::Uuid => ScalarType::Uuid,
+            Self::Array(elem) => {
+                ScalarType::Array(Box::new(elem.scalar_type()))
+            }
+        }
+    }
+}
+
+impl From<&ScalarType> for Type {
+    fn from(ty: &ScalarType) -> Self {
+        match ty {
+            ScalarType::Boolean => Self::Bool,
+            ScalarType::Int64 => Self::Int8,
+            ScalarType::Float32 => Self::Float4,
+            ScalarType::Float64 => Self::Float8,
+            ScalarType::Numeric(_) => Self::Numeric,
+            ScalarType::Text => Self::Text,
+            ScalarType::Date => Self::Date,
+            ScalarType::Timestamp => Self::Timestamp,
+            ScalarType::Interval => Self::Interval,
+            ScalarType::Bytea => Self::Bytea,
+            ScalarType::Uuid => Self::Uuid,
+            ScalarType::Array(elem) => Self::from(elem.as_ref()).array(),
+        }
+    }
+}
+
+/// The type modifier of `ty`, eg the precision and scale of a
+/// numeric, -1 if there is no modifier.
+pub fn typmod(ty: &ScalarType) -> i32 {
+    match ty {
+        ScalarType::Numeric(Some(c)) => c.typmod(),
+        ScalarType::Array(elem) => typmod(elem),
+        _ => -1,
+    }
+}
+
+/// The format of a value in PostgreSQL's frontend/backend protocol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Binary,
+}
+
+impl TryFrom<i16> for Format {
+    type Error = FloppyError;
+
+    fn try_from(code: i16) -> Result<Self> {
+        match code {
+            0 => Ok(Self::Text),
+            1 => Ok(Self::Binary),
+            _ => Err(FloppyError::Protocol(format!(
+                "unknown format code: {code}"
+            ))),
+        }
+    }
+}
+
+impl From<Format> for i16 {
+    fn from(format: Format) -> Self {
+        match format {
+            Format::Text => 0,
+            Format::Binary => 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::scalar::NumericConstraints;
+
+    #[test]
+    fn scalar_type_conversion() -> Result<()> {
+        let tests = [
+            (700, Type::Float4, ScalarType::Float32),
+            (701, Type::Float8, ScalarType::Float64),
+            (20, Type::Int8, ScalarType::Int64),
+            (1082, Type::Date, ScalarType::Date),
+            (1114, Type::Timestamp, ScalarType::Timestamp),
+            (1186, Type::Interval, ScalarType::Interval),
+            (17, Type::Bytea, ScalarType::Bytea),
+            (2950, Type::Uuid, ScalarType::Uuid),
+        ];
+        for (oid, ty, scalar_type) in tests {
+            assert_eq!(Type::from_oid(oid)?, Some(ty));
+            assert_eq!(ty.oid(), oid);
+            assert_eq!(ty.scalar_type(), scalar_type);
+            assert_eq!(Type::from(&scalar_type), ty);
+        }
+        assert_eq!(Type::Float4.typlen(), 4);
+        assert_eq!(Type::from_oid(0)?, None);
+
+        assert_eq!(Type::from_oid(1700)?, Some(Type::Numeric));
+        let numeric = ScalarType::Numeric(Some(NumericConstraints::new(5, 2)?));
+        assert_eq!(Type::from(&numeric), Type::Numeric);
+        assert_eq!(typmod(&numeric), 327686);
+        assert_eq!(typmod(&ScalarType::Numeric(None)), -1);
+        assert_eq!(Type::Date.typlen(), 4);
+        assert_eq!(Type::Timestamp.typlen(), 8);
+        assert_eq!(Type::Uuid.typlen(), 16);
+        assert_eq!(Type::Interval.typlen(), 16);
+        assert_eq!(Type::from_oid(1187)?, Some(Type::Interval.array()));
+        assert_eq!(typmod(&ScalarType::Timestamp), -1);
+
+        let int_array = ScalarType::Array(Box::new(ScalarType::Int64));
+        let ty = Type::from(&int_array);
+        assert_eq!(ty.oid(), 1016);
+        assert_eq!(ty.inner(), Some(&Type::Int8));
+        assert_eq!(ty.typlen(), -1);
+        assert_eq!(ty.scalar_type(), int_array);
+        assert_eq!(Type::from_oid(1007)?, Some(Type::Int4.array()));
+        assert_eq!(Type::from_oid(1000)?.unwrap().inner(), Some(&Type::Bool));
+        assert_eq!(Type::Text.array().array(), Type::Text.array());
+        assert_eq!(Type::Text.inner(), None);
+        // time without a date is not supported.
+        assert!(Type::from_oid(1083).is_err());
+        Ok(())
+    }
+}
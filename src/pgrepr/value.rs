@@ -0,0 +1,907 @@
+use crate::common::datetime::{self, Interval};
+use crate::common::error::{FloppyError, Result};
+use crate::common::relation::Row;
+use crate::common::scalar::{
+    format_array, format_bytea, format_uuid, parse_array, parse_bytea,
+    parse_uuid, Datum,
+};
+use crate::pgrepr::{Format, Type};
+use rust_decimal::Decimal;
+
+/// Decode a non-NULL value of type `ty` in `format`, eg the value
+/// of a parameter in `Bind`.
+pub fn decode(ty: Type, format: Format, raw: &[u8]) -> Result<Datum> {
+    match (ty, format) {
+        (Type::Int2 | Type::Int4 | Type::Int8, Format::Text) => {
+            let s = std::str::from_utf8(raw).map_err(|e| invalid(ty, e))?;
+            let n = match ty {
+                Type::Int2 => s.parse::<i16>().map(i64::from),
+                Type::Int4 => s.parse::<i32>().map(i64::from),
+                _ => s.parse::<i64>(),
+            };
+            n.map(Datum::Int64).map_err(|e| invalid(ty, e))
+        }
+        (Type::Int2, Format::Binary) => {
+            let bytes = raw.try_into().map_err(|e| invalid(ty, e))?;
+            Ok(Datum::Int64(i16::from_be_bytes(bytes).into()))
+        }
+        (Type::Int4, Format::Binary) => {
+            let bytes = raw.try_into().map_err(|e| invalid(ty, e))?;
+            Ok(Datum::Int64(i32::from_be_bytes(bytes).into()))
+        }
+        (Type::Int8, Format::Binary) => {
+            let bytes = raw.try_into().map_err(|e| invalid(ty, e))?;
+            Ok(Datum::Int64(i64::from_be_bytes(bytes)))
+        }
+        (Type::Float4, Format::Text) => {
+            let s = std::str::from_utf8(raw).map_err(|e| invalid(ty, e))?;
+            let f = s.trim().parse::<f32>().map_err(|e| invalid(ty, e))?;
+            Ok(Datum::Float32(f.into()))
+        }
+        (Type::Float8, Format::Text) => {
+            let s = std::str::from_utf8(raw).map_err(|e| invalid(ty, e))?;
+            let f = s.trim().parse::<f64>().map_err(|e| invalid(ty, e))?;
+            Ok(Datum::Float64(f.into()))
+        }
+        (Type::Float4, Format::Binary) => {
+            let bytes = raw.try_into().map_err(|e| invalid(ty, e))?;
+            Ok(Datum::Float32(f32::from_be_bytes(bytes).into()))
+        }
+        (Type::Float8, Format::Binary) => {
+            let bytes = raw.try_into().map_err(|e| invalid(ty, e))?;
+            Ok(Datum::Float64(f64::from_be_bytes(bytes).into()))
+        }
+        (Type::Numeric, Format::Text) => {
+            let s = std::str::from_utf8(raw).map_err(|e| invalid(ty, e))?;
+            Decimal::from_str_exact(s.trim())
+                .or_else(|_| Decimal::from_scientific(s.trim()))
+                .map(Datum::Numeric)
+                .map_err(|e| invalid(ty, e))
+        }
+        (Type::Numeric, Format::Binary) => {
+            decode_numeric_binary(raw).map(Datum::Numeric)
+        }
+        (Type::Bool, Format::Text) => {
+            let s = std::str::from_utf8(raw).map_err(|e| invalid(ty, e))?;
+            match s.trim().to_ascii_lowercase().as_str() {
+                "t" | "true" | "y" | "yes" | "on" | "1" => {
+                    Ok(Datum::Boolean(true))
+                }
+                "f" | "false" | "n" | "no" | "off" | "0" => {
+                    Ok(Datum::Boolean(false))
+                }
+                _ => Err(invalid(ty, s)),
+            }
+        }
+        (Type::Bool, Format::Binary) => match raw {
+            [b] => Ok(Datum::Boolean(*b != 0)),
+            _ => Err(invalid(ty, format!("{} bytes", raw.len()))),
+        },
+        (Type::Text, _) => {
+            let s = std::str::from_utf8(raw).map_err(|e| invalid(ty, e))?;
+            Ok(Datum::Text(s.to_string()))
+        }
+        (Type::Date, Format::Text) => {
+            let s = std::str::from_utf8(raw).map_err(|e| invalid(ty, e))?;
+            datetime::parse_date(s).map(Datum::Date)
+        }
+        (Type::Date, Format::Binary) => {
+            let bytes = raw.try_into().map_err(|e| invalid(ty, e))?;
+            Ok(Datum::Date(i32::from_be_bytes(bytes)))
+        }
+        (Type::Timestamp, Format::Text) => {
+            let s = std::str::from_utf8(raw).map_err(|e| invalid(ty, e))?;
+            datetime::parse_timestamp(s).map(Datum::Timestamp)
+        }
+        (Type::Timestamp, Format::Binary) => {
+            let bytes = raw.try_into().map_err(|e| invalid(ty, e))?;
+            Ok(Datum::Timestamp(i64::from_be_bytes(bytes)))
+        }
+        (Type::Interval, Format::Text) => {
+            let s = std::str::from_utf8(raw).map_err(|e| invalid(ty, e))?;
+            datetime::parse_interval(s).map(Datum::Interval)
+        }
+        (Type::Interval, Format::Binary) => {
+            let bytes: [u8; 16] = raw.try_into().map_err(|e| invalid(ty, e))?;
+            Ok(Datum::Interval(Interval {
+                micros: i64::from_be_bytes(bytes[..8].try_into().unwrap()),
+                days: i32::from_be_bytes(bytes[8..12].try_into().unwrap()),
+                months: i32::from_be_bytes(bytes[12..].try_into().unwrap()),
+            }))
+        }
+        (Type::Bytea, Format::Text) => {
+            let s = std::str::from_utf8(raw).map_err(|e| invalid(ty, e))?;
+            parse_bytea(s).map(Datum::Bytea)
+        }
+        (Type::Bytea, Format::Binary) => Ok(Datum::Bytea(raw.to_vec())),
+        (Type::Uuid, Format::Text) => {
+            let s = std::str::from_utf8(raw).map_err(|e| invalid(ty, e))?;
+            parse_uuid(s).map(Datum::Uuid)
+        }
+        (Type::Uuid, Format::Binary) => {
+            let bytes = raw.try_into().map_err(|e| invalid(ty, e))?;
+            Ok(Datum::Uuid(bytes))
+        }
+        (Type::Array(elem), Format::Text) => {
+            let s = std::str::from_utf8(raw).map_err(|e| invalid(ty, e))?;
+            parse_array(s)?
+                .into_iter()
+                .map(|e| match e {
+                    Some(e) => decode(*elem, format, e.as_bytes()),
+                    None => Ok(Datum::Null),
+                })
+                .collect::<Result<Vec<Datum>>>()
+                .map(Datum::Array)
+        }
+        (Type::Array(elem), Format::Binary) => {
+            decode_array_binary(*elem, raw).map(Datum::Array)
+        }
+    }
+}
+
+/// A value in PostgreSQL's frontend/backend protocol, the variant
+/// determines its [`Type`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Int2(i16),
+    Int4(i32),
+    Int8(i64),
+    Float4(f32),
+    Float8(f64),
+    Numeric(Decimal),
+    Text(String),
+    /// The days since 2000-01-01.
+    Date(i32),
+    /// The microseconds since 2000-01-01 00:00:00.
+    Timestamp(i64),
+    Interval(Interval),
+    Bytea(Vec<u8>),
+    Uuid([u8; 16]),
+    /// An array of the element type, `None` is a NULL element.
+    Array(Type, Vec<Option<Value>>),
+}
+
+impl Value {
+    /// Convert `datum` to a value of type `ty`. Returns `None` for
+    /// NULL.
+    pub fn from_datum(datum: &Datum, ty: Type) -> Result<Option<Value>> {
+        let value = match (ty, datum) {
+            (_, Datum::Null) => return Ok(None),
+            (Type::Bool, Datum::Boolean(b)) => Value::Bool(*b),
+            (Type::Int2, Datum::Int64(n)) => {
+                Value::Int2(i16::try_from(*n).map_err(|e| invalid(ty, e))?)
+            }
+            (Type::Int4, Datum::Int64(n)) => {
+                Value::Int4(i32::try_from(*n).map_err(|e| invalid(ty, e))?)
+            }
+            (Type::Int8, Datum::Int64(n)) => Value::Int8(*n),
+            (Type::Float4, Datum::Float32(f)) => Value::Float4(f.0),
+            (Type::Float8, Datum::Float64(f)) => Value::Float8(f.0),
+            (Type::Numeric, Datum::Numeric(d)) => Value::Numeric(*d),
+            (Type::Text, Datum::Text(s)) => Value::Text(s.clone()),
+            (Type::Date, Datum::Date(d)) => Value::Date(*d),
+            (Type::Timestamp, Datum::Timestamp(t)) => Value::Timestamp(*t),
+            (Type::Interval, Datum::Interval(i)) => Value::Interval(*i),
+            (Type::Bytea, Datum::Bytea(b)) => Value::Bytea(b.clone()),
+            (Type::Uuid, Datum::Uuid(u)) => Value::Uuid(*u),
+            (Type::Array(elem), Datum::Array(elems)) => Value::Array(
+                *elem,
+                elems
+                    .iter()
+                    .map(|d| Value::from_datum(d, *elem))
+                    .collect::<Result<_>>()?,
+            ),
+            _ => {
+                return Err(FloppyError::Internal(format!(
+                    "cannot convert {datum:?} to {ty:?}"
+                )))
+            }
+        };
+        Ok(Some(value))
+    }
+
+    pub fn ty(&self) -> Type {
+        match self {
+            Self::Bool(_) => Type::Bool,
+            Self::Int2(_) => Type::Int2,
+            Self::Int4(_) => Type::Int4,
+            Self::Int8(_) => Type::Int8,
+            Self::Float4(_) => Type::Float4,
+            Self::Float8(_) => Type::Float8,
+            Self::Numeric(_) => Type::Numeric,
+            Self::Text(_) => Type::Text,
+            Self::Date(_) => Type::Date,
+            Self::Timestamp(_) => Type::Timestamp,
+            Self::Interval(_) => Type::Interval,
+            Self::Bytea(_) => Type::Bytea,
+            Self::Uuid(_) => Type::Uuid,
+            Self::Array(elem, _) => elem.array(),
+        }
+    }
+
+    /// Encode the value in `format`.
+    pub fn encode(&self, format: Format) -> Vec<u8> {
+        match (self, format) {
+            (Self::Bool(b), Format::Text) => vec![if *b { b't' } else { b'f' }],
+            (Self::Bool(b), Format::Binary) => vec![u8::from(*b)],
+            (Self::Int2(n), Format::Text) => n.to_string().into_bytes(),
+            (Self::Int4(n), Format::Text) => n.to_string().into_bytes(),
+            (Self::Int8(n), Format::Text) => n.to_string().into_bytes(),
+            (Self::Int2(n), Format::Binary) => n.to_be_bytes().to_vec(),
+            (Self::Int4(n), Format::Binary) => n.to_be_bytes().to_vec(),
+            (Self::Int8(n), Format::Binary) => n.to_be_bytes().to_vec(),
+            (Self::Float4(f), Format::Text) => float_text(*f),
+            (Self::Float8(f), Format::Text) => float_text(*f),
+            (Self::Float4(f), Format::Binary) => f.to_be_bytes().to_vec(),
+            (Self::Float8(f), Format::Binary) => f.to_be_bytes().to_vec(),
+            (Self::Numeric(d), Format::Text) => d.to_string().into_bytes(),
+            (Self::Numeric(d), Format::Binary) => encode_numeric_binary(d),
+            (Self::Text(s), _) => s.as_bytes().to_vec(),
+            (Self::Date(d), Format::Text) => {
+                datetime::format_date(*d).into_bytes()
+            }
+            (Self::Date(d), Format::Binary) => d.to_be_bytes().to_vec(),
+            (Self::Timestamp(t), Format::Text) => {
+                datetime::format_timestamp(*t).into_bytes()
+            }
+            (Self::Timestamp(t), Format::Binary) => t.to_be_bytes().to_vec(),
+            (Self::Interval(i), Format::Text) => {
+                datetime::format_interval(i).into_bytes()
+            }
+            // the microseconds, days and months, as PostgreSQL.
+            (Self::Interval(i), Format::Binary) => [
+                &i.micros.to_be_bytes()[..],
+                &i.days.to_be_bytes(),
+                &i.months.to_be_bytes(),
+            ]
+            .concat(),
+            (Self::Bytea(b), Format::Text) => format_bytea(b).into_bytes(),
+            (Self::Bytea(b), Format::Binary) => b.clone(),
+            (Self::Uuid(u), Format::Text) => format_uuid(u).into_bytes(),
+            (Self::Uuid(u), Format::Binary) => u.to_vec(),
+            (Self::Array(_, elems), Format::Text) => {
+                let elems = elems.iter().map(|e| {
+                    e.as_ref().map(|e| {
+                        String::from_utf8_lossy(&e.encode(format)).into_owned()
+                    })
+                });
+                format_array(elems).into_bytes()
+            }
+            (Self::Array(elem, elems), Format::Binary) => {
+                encode_array_binary(*elem, elems)
+            }
+        }
+    }
+}
+
+/// Convert the datums of `row` to values of `types`. The types
+/// disambiguate the datums that can be represented by multiple
+/// types, eg a [`Datum::Int64`] can be an `Int2`, `Int4` or `Int8`.
+pub fn values_from_row(
+    row: &Row,
+    types: &[Type],
+) -> Result<Vec<Option<Value>>> {
+    if row.iter().len() != types.len() {
+        return Err(FloppyError::Internal(format!(
+            "expect {} columns, got {}",
+            types.len(),
+            row.iter().len()
+        )));
+    }
+    row.iter()
+        .zip(types)
+        .map(|(datum, ty)| Value::from_datum(datum, *ty))
+        .collect()
+}
+
+/// Encode a value of type `ty` in `format`, eg a column of
+/// `DataRow`. Returns `None` for NULL.
+pub fn encode(
+    ty: Type,
+    format: Format,
+    datum: &Datum,
+) -> Result<Option<Vec<u8>>> {
+    Ok(Value::from_datum(datum, ty)?.map(|v| v.encode(format)))
+}
+
+/// Format a float in text the same as PostgreSQL, which spells the
+/// special values differently from Rust. The float is formatted in the
+/// shortest digits of its own width, so a `float4` of `1.1` is `1.1`
+/// rather than the digits of the `f64` it widens to.
+fn float_text<F: Copy + Into<f64> + ToString>(f: F) -> Vec<u8> {
+    let wide: f64 = f.into();
+    let s = if wide.is_nan() {
+        "NaN".to_string()
+    } else if wide == f64::INFINITY {
+        "Infinity".to_string()
+    } else if wide == f64::NEG_INFINITY {
+        "-Infinity".to_string()
+    } else {
+        f.to_string()
+    };
+    s.into_bytes()
+}
+
+/// The sign of a negative numeric in binary format.
+const NUMERIC_NEG: u16 = 0x4000;
+/// The sign of a NaN numeric in binary format.
+const NUMERIC_NAN: u16 = 0xC000;
+
+/// Encode a numeric in binary format, which is a header of
+/// `ndigits`, `weight`, `sign` and `dscale` followed by `ndigits`
+/// base-10000 digits. The value is `digits[i] * 10000^(weight - i)`.
+fn encode_numeric_binary(d: &Decimal) -> Vec<u8> {
+    let s = d.abs().to_string();
+    let (int, frac) = s.split_once('.').unwrap_or((&s, ""));
+    let int = int.trim_start_matches('0');
+    // pad both parts to groups of 4 decimal digits.
+    let int = format!("{}{int}", "0".repeat((4 - int.len() % 4) % 4));
+    let frac = format!("{frac}{}", "0".repeat((4 - frac.len() % 4) % 4));
+    let mut digits = int
+        .as_bytes()
+        .chunks(4)
+        .chain(frac.as_bytes().chunks(4))
+        .map(|c| std::str::from_utf8(c).unwrap().parse::<i16>().unwrap())
+        .collect::<Vec<i16>>();
+    let mut weight = (int.len() / 4) as i16 - 1;
+
+    let leading_zeros = digits.iter().take_while(|d| **d == 0).count();
+    digits.drain(..leading_zeros);
+    weight -= leading_zeros as i16;
+    while digits.last() == Some(&0) {
+        digits.pop();
+    }
+    if digits.is_empty() {
+        weight = 0;
+    }
+
+    let sign = if d.is_sign_negative() && !d.is_zero() {
+        NUMERIC_NEG
+    } else {
+        0
+    };
+    let mut buf = Vec::with_capacity(8 + 2 * digits.len());
+    buf.extend((digits.len() as i16).to_be_bytes());
+    buf.extend(weight.to_be_bytes());
+    buf.extend(sign.to_be_bytes());
+    buf.extend((d.scale() as u16).to_be_bytes());
+    for digit in digits {
+        buf.extend(digit.to_be_bytes());
+    }
+    buf
+}
+
+fn decode_numeric_binary(raw: &[u8]) -> Result<Decimal> {
+    let ty = Type::Numeric;
+    if raw.len() < 8 {
+        return Err(invalid(ty, format!("{} bytes", raw.len())));
+    }
+    let ndigits = i16::from_be_bytes([raw[0], raw[1]]);
+    let weight = i16::from_be_bytes([raw[2], raw[3]]) as i32;
+    let sign = u16::from_be_bytes([raw[4], raw[5]]);
+    let dscale = u16::from_be_bytes([raw[6], raw[7]]) as u32;
+    if ndigits < 0 || raw.len() != 8 + 2 * ndigits as usize {
+        return Err(invalid(ty, format!("{} bytes", raw.len())));
+    }
+    match sign {
+        0 | NUMERIC_NEG => {}
+        NUMERIC_NAN => {
+            return Err(FloppyError::NotImplemented("NaN numeric".to_string()))
+        }
+        _ => return Err(invalid(ty, format!("sign {sign:#x}"))),
+    }
+
+    let out_of_range = || invalid(ty, "out of range");
+    let mut mantissa: i128 = 0;
+    for c in raw[8..].chunks(2) {
+        let digit = i16::from_be_bytes([c[0], c[1]]);
+        if !(0..10000).contains(&digit) {
+            return Err(invalid(ty, format!("digit {digit}")));
+        }
+        mantissa = mantissa
+            .checked_mul(10000)
+            .and_then(|m| m.checked_add(digit.into()))
+            .ok_or_else(out_of_range)?;
+    }
+
+    // the value is `mantissa * 10000^exp`.
+    let exp = weight - (ndigits as i32 - 1);
+    let mut d = if exp >= 0 {
+        let mut d = Decimal::try_from_i128_with_scale(mantissa, 0)
+            .map_err(|_| out_of_range())?;
+        for _ in 0..exp {
+            d = d
+                .checked_mul(Decimal::from(10000))
+                .ok_or_else(out_of_range)?;
+        }
+        d
+    } else {
+        Decimal::try_from_i128_with_scale(mantissa, (-exp * 4) as u32)
+            .map_err(|_| out_of_range())?
+    };
+    d.rescale(dscale);
+    d.set_sign_negative(sign == NUMERIC_NEG);
+    Ok(d)
+}
+
+/// Encode a one-dimensional array in binary format, which is a
+/// header of `ndim`, `has_null` and the element type, followed by the
+/// size and lower bound of each dimension, then the elements each
+/// prefixed by its length, -1 for NULL. An empty array has no
+/// dimensions.
+fn encode_array_binary(elem: Type, elems: &[Option<Value>]) -> Vec<u8> {
+    let ndim = i32::from(!elems.is_empty());
+    let has_null = i32::from(elems.iter().any(Option::is_none));
+    let mut buf = vec![];
+    buf.extend(ndim.to_be_bytes());
+    buf.extend(has_null.to_be_bytes());
+    buf.extend(elem.oid().to_be_bytes());
+    if ndim == 1 {
+        buf.extend((elems.len() as i32).to_be_bytes());
+        // the lower bound.
+        buf.extend(1i32.to_be_bytes());
+    }
+    for e in elems {
+        match e {
+            Some(e) => {
+                let bytes = e.encode(Format::Binary);
+                buf.extend((bytes.len() as i32).to_be_bytes());
+                buf.extend(bytes);
+            }
+            None => buf.extend((-1i32).to_be_bytes()),
+        }
+    }
+    buf
+}
+
+fn decode_array_binary(elem: Type, raw: &[u8]) -> Result<Vec<Datum>> {
+    /// Split `n` bytes off the front of `rest`.
+    fn take<'a>(rest: &mut &'a [u8], n: usize) -> Option<&'a [u8]> {
+        if rest.len() < n {
+            return None;
+        }
+        let (bytes, tail) = rest.split_at(n);
+        *rest = tail;
+        Some(bytes)
+    }
+    fn take_i32(rest: &mut &[u8]) -> Option<i32> {
+        take(rest, 4).map(|b| i32::from_be_bytes(b.try_into().unwrap()))
+    }
+
+    let ty = elem.array();
+    let truncated = || invalid(ty, format!("{} bytes", raw.len()));
+    let mut rest = raw;
+    let ndim = take_i32(&mut rest).ok_or_else(truncated)?;
+    let _has_null = take_i32(&mut rest).ok_or_else(truncated)?;
+    let oid = take_i32(&mut rest).ok_or_else(truncated)? as u32;
+    if oid != elem.oid() {
+        return Err(invalid(ty, format!("element type oid {oid}")));
+    }
+    let len = match ndim {
+        0 => 0,
+        1 => {
+            let len = take_i32(&mut rest).ok_or_else(truncated)?;
+            let _lower_bound = take_i32(&mut rest).ok_or_else(truncated)?;
+            usize::try_from(len).map_err(|e| invalid(ty, e))?
+        }
+        _ => {
+            return Err(FloppyError::NotImplemented(
+                "multidimensional arrays".to_string(),
+            ))
+        }
+    };
+
+    let mut elems = vec![];
+    for _ in 0..len {
+        let elem_len = take_i32(&mut rest).ok_or_else(truncated)?;
+        if elem_len == -1 {
+            elems.push(Datum::Null);
+            continue;
+        }
+        let elem_len = usize::try_from(elem_len).map_err(|e| invalid(ty, e))?;
+        let bytes = take(&mut rest, elem_len).ok_or_else(truncated)?;
+        elems.push(decode(elem, Format::Binary, bytes)?);
+    }
+    if !rest.is_empty() {
+        return Err(truncated());
+    }
+    Ok(elems)
+}
+
+fn invalid<E: std::fmt::Display>(ty: Type, e: E) -> FloppyError {
+    FloppyError::Protocol(format!("invalid {ty:?} value: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_int() -> Result<()> {
+        assert_eq!(
+            decode(Type::Int8, Format::Text, b"-42")?,
+            Datum::Int64(-42)
+        );
+        assert_eq!(
+            decode(Type::Int4, Format::Binary, &7i32.to_be_bytes())?,
+            Datum::Int64(7)
+        );
+        assert!(decode(Type::Int2, Format::Text, b"65536").is_err());
+        assert!(
+            decode(Type::Int8, Format::Binary, &7i32.to_be_bytes()).is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn int_round_trip() -> Result<()> {
+        let tests = [
+            (Type::Int2, i16::MIN.into(), 2),
+            (Type::Int2, i16::MAX.into(), 2),
+            (Type::Int4, i32::MIN.into(), 4),
+            (Type::Int4, -1, 4),
+            (Type::Int8, i64::MIN, 8),
+            (Type::Int8, i64::MAX, 8),
+        ];
+        for (ty, n, len) in tests {
+            let datum = Datum::Int64(n);
+            let text = encode(ty, Format::Text, &datum)?.unwrap();
+            assert_eq!(text, n.to_string().into_bytes());
+            assert_eq!(decode(ty, Format::Text, &text)?, datum);
+
+            let binary = encode(ty, Format::Binary, &datum)?.unwrap();
+            assert_eq!(binary.len(), len);
+            assert_eq!(binary, n.to_be_bytes()[8 - len..]);
+            assert_eq!(decode(ty, Format::Binary, &binary)?, datum);
+        }
+
+        assert_eq!(encode(Type::Int4, Format::Binary, &Datum::Null)?, None);
+        assert_eq!(encode(Type::Int8, Format::Text, &Datum::Null)?, None);
+        // out of range.
+        let datum = Datum::Int64(i64::from(i16::MAX) + 1);
+        assert!(encode(Type::Int2, Format::Binary, &datum).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn bool_and_text() -> Result<()> {
+        let tests: [(Type, Datum, &[u8], &[u8]); 4] = [
+            (Type::Bool, Datum::Boolean(true), b"t", &[1]),
+            (Type::Bool, Datum::Boolean(false), b"f", &[0]),
+            (
+                Type::Text,
+                Datum::Text("floppy".to_string()),
+                b"floppy",
+                b"floppy",
+            ),
+            (Type::Text, Datum::Text("".to_string()), b"", b""),
+        ];
+        for (ty, datum, text, binary) in tests {
+            assert_eq!(encode(ty, Format::Text, &datum)?.unwrap(), text);
+            assert_eq!(encode(ty, Format::Binary, &datum)?.unwrap(), binary);
+            assert_eq!(decode(ty, Format::Text, text)?, datum);
+            assert_eq!(decode(ty, Format::Binary, binary)?, datum);
+        }
+
+        for ty in [Type::Bool, Type::Text] {
+            for format in [Format::Text, Format::Binary] {
+                assert_eq!(encode(ty, format, &Datum::Null)?, None);
+            }
+        }
+
+        assert_eq!(
+            decode(Type::Bool, Format::Text, b" TRUE")?,
+            Datum::Boolean(true)
+        );
+        assert!(decode(Type::Bool, Format::Text, b"maybe").is_err());
+        assert!(decode(Type::Bool, Format::Binary, &[1, 0]).is_err());
+        assert!(decode(Type::Text, Format::Text, &[0xff]).is_err());
+        assert!(encode(Type::Bool, Format::Text, &Datum::Int64(1)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn float_round_trip() -> Result<()> {
+        let tests = [
+            (Type::Float4, Datum::Float32(1.5.into()), "1.5"),
+            (Type::Float4, Datum::Float32(1.1.into()), "1.1"),
+            (Type::Float8, Datum::Float64(1.1.into()), "1.1"),
+            (Type::Float4, Datum::Float32(f32::NAN.into()), "NaN"),
+            (Type::Float8, Datum::Float64((-0.25).into()), "-0.25"),
+            (
+                Type::Float8,
+                Datum::Float64(f64::INFINITY.into()),
+                "Infinity",
+            ),
+            (
+                Type::Float8,
+                Datum::Float64(f64::NEG_INFINITY.into()),
+                "-Infinity",
+            ),
+        ];
+        for (ty, datum, text) in tests {
+            let encoded = encode(ty, Format::Text, &datum)?.unwrap();
+            assert_eq!(encoded, text.as_bytes());
+            assert_eq!(decode(ty, Format::Text, &encoded)?, datum);
+
+            let encoded = encode(ty, Format::Binary, &datum)?.unwrap();
+            assert_eq!(encoded.len(), ty.typlen() as usize);
+            assert_eq!(decode(ty, Format::Binary, &encoded)?, datum);
+        }
+        assert!(decode(Type::Float8, Format::Text, b"1.5x").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn numeric_round_trip() -> Result<()> {
+        let tests: [(&str, &[i16]); 7] = [
+            ("0", &[0, 0, 0, 0]),
+            ("1.25", &[2, 0, 0, 2, 1, 2500]),
+            ("-12345.678", &[3, 1, 0x4000, 3, 1, 2345, 6780]),
+            ("0.0001", &[1, -1, 0, 4, 1]),
+            ("100000000", &[1, 2, 0, 0, 1]),
+            ("0.50", &[1, -1, 0, 2, 5000]),
+            ("-0.00000012", &[1, -2, 0x4000, 8, 12]),
+        ];
+        for (text, binary) in tests {
+            let datum = Datum::Numeric(Decimal::from_str_exact(text).unwrap());
+            let binary = binary
+                .iter()
+                .flat_map(|n| n.to_be_bytes())
+                .collect::<Vec<u8>>();
+
+            let encoded = encode(Type::Numeric, Format::Text, &datum)?;
+            assert_eq!(encoded.unwrap(), text.as_bytes());
+            let encoded = encode(Type::Numeric, Format::Binary, &datum)?;
+            assert_eq!(encoded.unwrap(), binary, "{text}");
+
+            for (format, raw) in
+                [(Format::Text, text.as_bytes()), (Format::Binary, &binary)]
+            {
+                match decode(Type::Numeric, format, raw)? {
+                    // the scale is also preserved.
+                    Datum::Numeric(d) => assert_eq!(d.to_string(), text),
+                    d => panic!("unexpected datum: {d:?}"),
+                }
+            }
+        }
+
+        let nan = [0, 0, 0xC000_u16 as i16, 0].map(i16::to_be_bytes).concat();
+        assert!(decode(Type::Numeric, Format::Binary, &nan).is_err());
+        assert!(decode(Type::Numeric, Format::Text, b"1.2.3").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn datetime_round_trip() -> Result<()> {
+        let tests: [(Type, Datum, &str, &[u8]); 4] = [
+            (
+                Type::Date,
+                Datum::Date(8766),
+                "2024-01-01",
+                &[0, 0, 0x22, 0x3e],
+            ),
+            (
+                Type::Date,
+                Datum::Date(-1),
+                "1999-12-31",
+                &[0xff, 0xff, 0xff, 0xff],
+            ),
+            (
+                Type::Timestamp,
+                Datum::Timestamp(1_500_000),
+                "2000-01-01 00:00:01.5",
+                &[0, 0, 0, 0, 0, 0x16, 0xe3, 0x60],
+            ),
+            (
+                Type::Timestamp,
+                Datum::Timestamp(757_425_600_000_000),
+                "2024-01-01 12:00:00",
+                &757_425_600_000_000_i64.to_be_bytes(),
+            ),
+        ];
+        for (ty, datum, text, binary) in tests {
+            let encoded = encode(ty, Format::Text, &datum)?.unwrap();
+            assert_eq!(encoded, text.as_bytes());
+            assert_eq!(decode(ty, Format::Text, &encoded)?, datum);
+
+            let encoded = encode(ty, Format::Binary, &datum)?.unwrap();
+            assert_eq!(encoded, binary);
+            assert_eq!(encoded.len(), ty.typlen() as usize);
+            assert_eq!(decode(ty, Format::Binary, &encoded)?, datum);
+        }
+        assert!(decode(Type::Date, Format::Text, b"2024-02-30").is_err());
+        assert!(decode(Type::Timestamp, Format::Binary, &[0; 4]).is_err());
+        assert!(encode(Type::Date, Format::Text, &Datum::Timestamp(0)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn bytea_round_trip() -> Result<()> {
+        let tests: [(&[u8], &str); 3] = [
+            (&[0xde, 0xad, 0xbe, 0xef], "\\xdeadbeef"),
+            (&[0, b'\\', 0xff], "\\x005cff"),
+            (&[], "\\x"),
+        ];
+        for (bytes, text) in tests {
+            let datum = Datum::Bytea(bytes.to_vec());
+            let encoded = encode(Type::Bytea, Format::Text, &datum)?.unwrap();
+            assert_eq!(encoded, text.as_bytes());
+            assert_eq!(decode(Type::Bytea, Format::Text, &encoded)?, datum);
+
+            let encoded = encode(Type::Bytea, Format::Binary, &datum)?.unwrap();
+            assert_eq!(encoded, bytes);
+            assert_eq!(decode(Type::Bytea, Format::Binary, &encoded)?, datum);
+        }
+        // the escape format is also accepted.
+        assert_eq!(
+            decode(Type::Bytea, Format::Text, b"a\\\\b")?,
+            Datum::Bytea(b"a\\b".to_vec())
+        );
+        assert!(decode(Type::Bytea, Format::Text, b"\\xabc").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn uuid_round_trip() -> Result<()> {
+        let uuid = [
+            0xa0, 0xee, 0xbc, 0x99, 0x9c, 0x0b, 0x4e, 0xf8, 0xbb, 0x6d, 0x6b,
+            0xb9, 0xbd, 0x38, 0x0a, 0x11,
+        ];
+        let datum = Datum::Uuid(uuid);
+        let text = b"a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11";
+        assert_eq!(encode(Type::Uuid, Format::Text, &datum)?.unwrap(), text);
+        assert_eq!(decode(Type::Uuid, Format::Text, text)?, datum);
+
+        let encoded = encode(Type::Uuid, Format::Binary, &datum)?.unwrap();
+        assert_eq!(encoded, uuid);
+        assert_eq!(encoded.len(), Type::Uuid.typlen() as usize);
+        assert_eq!(decode(Type::Uuid, Format::Binary, &encoded)?, datum);
+
+        assert!(decode(Type::Uuid, Format::Text, b"a0eebc99").is_err());
+        assert!(decode(Type::Uuid, Format::Binary, &uuid[1..]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn interval_round_trip() -> Result<()> {
+        let datum = Datum::Interval(Interval {
+            months: 14,
+            days: -3,
+            micros: 14_706_500_000,
+        });
+        let text = b"1 year 2 mons -3 days +04:05:06.5";
+        let ty = Type::Interval;
+        assert_eq!(encode(ty, Format::Text, &datum)?.unwrap(), text);
+        assert_eq!(decode(ty, Format::Text, text)?, datum);
+
+        let encoded = encode(ty, Format::Binary, &datum)?.unwrap();
+        assert_eq!(
+            encoded,
+            [0, 0, 0, 3, 108, 147, 97, 160, 255, 255, 255, 253, 0, 0, 0, 14]
+        );
+        assert_eq!(encoded.len(), ty.typlen() as usize);
+        assert_eq!(decode(ty, Format::Binary, &encoded)?, datum);
+
+        assert!(decode(ty, Format::Text, b"1 fortnight").is_err());
+        assert!(decode(ty, Format::Binary, &encoded[1..]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn array_round_trip() -> Result<()> {
+        let int8_array = Type::Int8.array();
+        let datum = Datum::Array(vec![
+            Datum::Int64(1),
+            Datum::Int64(2),
+            Datum::Int64(3),
+        ]);
+        let text = encode(int8_array, Format::Text, &datum)?.unwrap();
+        assert_eq!(text, b"{1,2,3}");
+        assert_eq!(decode(int8_array, Format::Text, &text)?, datum);
+
+        let binary = encode(int8_array, Format::Binary, &datum)?.unwrap();
+        let mut expected = [1, 0, 20, 3, 1].map(i32::to_be_bytes).concat();
+        for n in [1i64, 2, 3] {
+            expected.extend(8i32.to_be_bytes());
+            expected.extend(n.to_be_bytes());
+        }
+        assert_eq!(binary, expected);
+        assert_eq!(decode(int8_array, Format::Binary, &binary)?, datum);
+
+        let text_array = Type::Text.array();
+        let tests: [(Datum, &str); 3] = [
+            (Datum::Array(vec![]), "{}"),
+            (
+                Datum::Array(vec![
+                    Datum::Text("a b".to_string()),
+                    Datum::Null,
+                    Datum::Text("NULL".to_string()),
+                ]),
+                r#"{"a b",NULL,"NULL"}"#,
+            ),
+            (
+                Datum::Array(vec![Datum::Text("{}".to_string())]),
+                r#"{"{}"}"#,
+            ),
+        ];
+        for (datum, text) in tests {
+            let encoded = encode(text_array, Format::Text, &datum)?.unwrap();
+            assert_eq!(encoded, text.as_bytes());
+            assert_eq!(decode(text_array, Format::Text, &encoded)?, datum);
+
+            let encoded = encode(text_array, Format::Binary, &datum)?.unwrap();
+            assert_eq!(decode(text_array, Format::Binary, &encoded)?, datum);
+        }
+        let empty =
+            encode(text_array, Format::Binary, &Datum::Array(vec![]))?.unwrap();
+        assert_eq!(empty, [0, 0, 25].map(i32::to_be_bytes).concat());
+
+        // the elements are converted to the element type.
+        let int2_array = Type::Int2.array();
+        let datum = Datum::Array(vec![Datum::Int64(1), Datum::Null]);
+        let value = Value::from_datum(&datum, int2_array)?.unwrap();
+        assert_eq!(
+            value,
+            Value::Array(Type::Int2, vec![Some(Value::Int2(1)), None])
+        );
+        assert_eq!(value.ty(), int2_array);
+        let datum = Datum::Array(vec![Datum::Int64(i64::MAX)]);
+        assert!(encode(int2_array, Format::Text, &datum).is_err());
+
+        assert!(decode(int8_array, Format::Text, b"{1,x}").is_err());
+        assert!(decode(int8_array, Format::Text, b"1,2").is_err());
+        // the element type does not match.
+        assert!(decode(int8_array, Format::Binary, &empty).is_err());
+        assert!(decode(int8_array, Format::Binary, &binary[..20]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn mixed_row() -> Result<()> {
+        let row = Row::new(vec![
+            Datum::Int64(1),
+            Datum::Int64(2),
+            Datum::Int64(3),
+            Datum::Boolean(true),
+            Datum::Null,
+            Datum::Text("a".to_string()),
+            Datum::Numeric(Decimal::new(125, 2)),
+        ]);
+        let types = [
+            Type::Int2,
+            Type::Int4,
+            Type::Int8,
+            Type::Bool,
+            Type::Float8,
+            Type::Text,
+            Type::Numeric,
+        ];
+        let values = values_from_row(&row, &types)?;
+        assert_eq!(
+            values,
+            vec![
+                Some(Value::Int2(1)),
+                Some(Value::Int4(2)),
+                Some(Value::Int8(3)),
+                Some(Value::Bool(true)),
+                None,
+                Some(Value::Text("a".to_string())),
+                Some(Value::Numeric(Decimal::new(125, 2))),
+            ]
+        );
+        for (value, ty) in values.iter().zip(types) {
+            if let Some(value) = value {
+                assert_eq!(value.ty(), ty);
+            }
+        }
+
+        // mismatched types or number of columns.
+        assert!(values_from_row(&row, &types[1..]).is_err());
+        let mut types = types;
+        types[0] = Type::Text;
+        assert!(values_from_row(&row, &types).is_err());
+        Ok(())
+    }
+}
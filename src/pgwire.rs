@@ -0,0 +1,6 @@
+//! An implementation of PostgreSQL's frontend/backend protocol.
+
+mod codec;
+mod message;
+pub mod server;
+pub mod tls;
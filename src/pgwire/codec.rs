@@ -0,0 +1,503 @@
+//! Encoding and decoding of the messages of PostgreSQL's
+//! frontend/backend protocol.
+//!
+//! See <https://www.postgresql.org/docs/current/protocol-message-formats.html>.
+
+use crate::common::error::{FloppyError, Result};
+use crate::pgrepr::Format;
+use crate::pgwire::message::{
+    BackendMessage, ErrorResponse, FrontendMessage, FrontendStartupMessage,
+    ObjectKind, VERSION_CANCEL, VERSION_GSSENC, VERSION_SSL,
+};
+use bytes::{Buf, BufMut, BytesMut};
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// The maximum size of a message, including the length field.
+const MAX_MESSAGE_SIZE: usize = 1 << 24;
+
+/// Read the startup message. Returns `None` if the connection is
+/// closed.
+pub async fn decode_startup<R>(
+    r: &mut R,
+) -> Result<Option<FrontendStartupMessage>>
+where
+    R: AsyncRead + Unpin,
+{
+    let len = match r.read_i32().await {
+        Ok(len) => len,
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let body = read_body(r, len).await?;
+    let mut buf = body.as_slice();
+
+    let version = get_i32(&mut buf)?;
+    let message = match version {
+        VERSION_SSL => FrontendStartupMessage::SslRequest,
+        VERSION_GSSENC => FrontendStartupMessage::GssEncRequest,
+        VERSION_CANCEL => FrontendStartupMessage::CancelRequest {
+            conn_id: get_i32(&mut buf)? as u32,
+            secret_key: get_i32(&mut buf)? as u32,
+        },
+        _ => {
+            let mut params = HashMap::new();
+            loop {
+                let name = get_cstr(&mut buf)?;
+                if name.is_empty() {
+                    break;
+                }
+                let value = get_cstr(&mut buf)?;
+                params.insert(name, value);
+            }
+            FrontendStartupMessage::Startup { version, params }
+        }
+    };
+    Ok(Some(message))
+}
+
+/// Read a message sent after startup. Returns `None` if the
+/// connection is closed.
+pub async fn read_message<R>(r: &mut R) -> Result<Option<FrontendMessage>>
+where
+    R: AsyncRead + Unpin,
+{
+    let tag = match r.read_u8().await {
+        Ok(tag) => tag,
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let len = r.read_i32().await?;
+    let body = read_body(r, len).await?;
+    decode_frontend(tag, &body).map(Some)
+}
+
+/// Decode the body of a message with type byte `tag`.
+pub fn decode_frontend(tag: u8, body: &[u8]) -> Result<FrontendMessage> {
+    let mut buf = body;
+    let message = match tag {
+        b'Q' => FrontendMessage::Query {
+            sql: get_cstr(&mut buf)?,
+        },
+        b'P' => {
+            let name = get_cstr(&mut buf)?;
+            let sql = get_cstr(&mut buf)?;
+            let count = get_i16(&mut buf)?;
+            let param_types = (0..count)
+                .map(|_| get_i32(&mut buf).map(|oid| oid as u32))
+                .collect::<Result<Vec<u32>>>()?;
+            FrontendMessage::Parse {
+                name,
+                sql,
+                param_types,
+            }
+        }
+        b'B' => {
+            let portal_name = get_cstr(&mut buf)?;
+            let statement_name = get_cstr(&mut buf)?;
+            let param_formats = get_formats(&mut buf)?;
+            let count = get_i16(&mut buf)?;
+            let params = (0..count)
+                .map(|_| {
+                    let len = get_i32(&mut buf)?;
+                    if len < 0 {
+                        return Ok(None);
+                    }
+                    ensure_remaining(buf, len as usize)?;
+                    let value = buf[..len as usize].to_vec();
+                    buf.advance(len as usize);
+                    Ok(Some(value))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let result_formats = get_formats(&mut buf)?;
+            FrontendMessage::Bind {
+                portal_name,
+                statement_name,
+                param_formats,
+                params,
+                result_formats,
+            }
+        }
+        b'D' => FrontendMessage::Describe {
+            kind: get_object_kind(&mut buf)?,
+            name: get_cstr(&mut buf)?,
+        },
+        b'E' => FrontendMessage::Execute {
+            portal_name: get_cstr(&mut buf)?,
+            max_rows: get_i32(&mut buf)?,
+        },
+        b'S' => FrontendMessage::Sync,
+        b'd' => {
+            let data = buf.to_vec();
+            buf.advance(buf.len());
+            FrontendMessage::CopyData(data)
+        }
+        b'c' => FrontendMessage::CopyDone,
+        b'f' => FrontendMessage::CopyFail {
+            message: get_cstr(&mut buf)?,
+        },
+        b'X' => FrontendMessage::Terminate,
+        _ => {
+            return Err(FloppyError::Protocol(format!(
+                "unknown message type: {:?}",
+                tag as char
+            )))
+        }
+    };
+    if buf.has_remaining() {
+        return Err(FloppyError::Protocol(format!(
+            "invalid message length of message type {:?}",
+            tag as char
+        )));
+    }
+    Ok(message)
+}
+
+/// Encode a message sent by the client.
+pub fn encode_frontend(message: &FrontendMessage, buf: &mut BytesMut) {
+    match message {
+        FrontendMessage::Query { sql } => {
+            write_message(buf, b'Q', |buf| put_cstr(buf, sql))
+        }
+        FrontendMessage::Parse {
+            name,
+            sql,
+            param_types,
+        } => write_message(buf, b'P', |buf| {
+            put_cstr(buf, name);
+            put_cstr(buf, sql);
+            buf.put_i16(param_types.len() as i16);
+            for oid in param_types {
+                buf.put_u32(*oid);
+            }
+        }),
+        FrontendMessage::Bind {
+            portal_name,
+            statement_name,
+            param_formats,
+            params,
+            result_formats,
+        } => write_message(buf, b'B', |buf| {
+            put_cstr(buf, portal_name);
+            put_cstr(buf, statement_name);
+            put_formats(buf, param_formats);
+            buf.put_i16(params.len() as i16);
+            for param in params {
+                match param {
+                    Some(v) => {
+                        buf.put_i32(v.len() as i32);
+                        buf.put_slice(v);
+                    }
+                    None => buf.put_i32(-1),
+                }
+            }
+            put_formats(buf, result_formats);
+        }),
+        FrontendMessage::Describe { kind, name } => {
+            write_message(buf, b'D', |buf| {
+                buf.put_u8(kind.as_u8());
+                put_cstr(buf, name);
+            })
+        }
+        FrontendMessage::Execute {
+            portal_name,
+            max_rows,
+        } => write_message(buf, b'E', |buf| {
+            put_cstr(buf, portal_name);
+            buf.put_i32(*max_rows);
+        }),
+        FrontendMessage::Sync => write_message(buf, b'S', |_| {}),
+        FrontendMessage::CopyData(data) => {
+            write_message(buf, b'd', |buf| buf.put_slice(data))
+        }
+        FrontendMessage::CopyDone => write_message(buf, b'c', |_| {}),
+        FrontendMessage::CopyFail { message } => {
+            write_message(buf, b'f', |buf| put_cstr(buf, message))
+        }
+        FrontendMessage::Terminate => write_message(buf, b'X', |_| {}),
+    }
+}
+
+/// Encode a message sent by the server.
+pub fn encode_backend(message: &BackendMessage, buf: &mut BytesMut) {
+    match message {
+        BackendMessage::AuthenticationOk => {
+            write_message(buf, b'R', |buf| buf.put_i32(0))
+        }
+        BackendMessage::ParameterStatus { name, value } => {
+            write_message(buf, b'S', |buf| {
+                put_cstr(buf, name);
+                put_cstr(buf, value);
+            })
+        }
+        BackendMessage::BackendKeyData {
+            conn_id,
+            secret_key,
+        } => write_message(buf, b'K', |buf| {
+            buf.put_u32(*conn_id);
+            buf.put_u32(*secret_key);
+        }),
+        BackendMessage::ReadyForQuery(status) => {
+            write_message(buf, b'Z', |buf| buf.put_u8(status.as_u8()))
+        }
+        BackendMessage::RowDescription(fields) => {
+            write_message(buf, b'T', |buf| {
+                buf.put_i16(fields.len() as i16);
+                for f in fields {
+                    put_cstr(buf, &f.name);
+                    buf.put_u32(f.table_id);
+                    buf.put_i16(f.column_id);
+                    buf.put_u32(f.type_oid);
+                    buf.put_i16(f.type_len);
+                    buf.put_i32(f.type_mod);
+                    buf.put_i16(f.format);
+                }
+            })
+        }
+        BackendMessage::DataRow(values) => write_message(buf, b'D', |buf| {
+            buf.put_i16(values.len() as i16);
+            for value in values {
+                match value {
+                    Some(v) => {
+                        buf.put_i32(v.len() as i32);
+                        buf.put_slice(v);
+                    }
+                    None => buf.put_i32(-1),
+                }
+            }
+        }),
+        BackendMessage::ParseComplete => write_message(buf, b'1', |_| {}),
+        BackendMessage::BindComplete => write_message(buf, b'2', |_| {}),
+        BackendMessage::ParameterDescription(oids) => {
+            write_message(buf, b't', |buf| {
+                buf.put_i16(oids.len() as i16);
+                for oid in oids {
+                    buf.put_u32(*oid);
+                }
+            })
+        }
+        BackendMessage::NoData => write_message(buf, b'n', |_| {}),
+        BackendMessage::PortalSuspended => write_message(buf, b's', |_| {}),
+        BackendMessage::EmptyQueryResponse => write_message(buf, b'I', |_| {}),
+        BackendMessage::CommandComplete { tag } => {
+            write_message(buf, b'C', |buf| put_cstr(buf, tag))
+        }
+        BackendMessage::ErrorResponse(e) => {
+            write_message(buf, b'E', |buf| put_error_fields(buf, e))
+        }
+        BackendMessage::CopyInResponse {
+            format,
+            column_formats,
+        } => write_message(buf, b'G', |buf| {
+            buf.put_i8(i16::from(*format) as i8);
+            put_formats(buf, column_formats);
+        }),
+        BackendMessage::CopyOutResponse {
+            format,
+            column_formats,
+        } => write_message(buf, b'H', |buf| {
+            buf.put_i8(i16::from(*format) as i8);
+            put_formats(buf, column_formats);
+        }),
+        BackendMessage::CopyData(data) => {
+            write_message(buf, b'd', |buf| buf.put_slice(data))
+        }
+        BackendMessage::CopyDone => write_message(buf, b'c', |_| {}),
+        BackendMessage::NoticeResponse(e) => {
+            write_message(buf, b'N', |buf| put_error_fields(buf, e))
+        }
+    }
+}
+
+/// Write the fields of `ErrorResponse` or `NoticeResponse`.
+fn put_error_fields(buf: &mut BytesMut, e: &ErrorResponse) {
+    buf.put_u8(b'S');
+    put_cstr(buf, e.severity.as_str());
+    buf.put_u8(b'V');
+    put_cstr(buf, e.severity.as_str());
+    buf.put_u8(b'C');
+    put_cstr(buf, e.code);
+    buf.put_u8(b'M');
+    put_cstr(buf, &e.message);
+    if let Some(hint) = &e.hint {
+        buf.put_u8(b'H');
+        put_cstr(buf, hint);
+    }
+    buf.put_u8(0);
+}
+
+/// Write a message with type byte `tag`, `f` writes the body.
+fn write_message<F>(buf: &mut BytesMut, tag: u8, f: F)
+where
+    F: FnOnce(&mut BytesMut),
+{
+    buf.put_u8(tag);
+    let start = buf.len();
+    buf.put_i32(0);
+    f(buf);
+    let len = (buf.len() - start) as i32;
+    buf[start..start + 4].copy_from_slice(&len.to_be_bytes());
+}
+
+/// Read the rest of a message whose length field is `len`.
+async fn read_body<R>(r: &mut R, len: i32) -> Result<Vec<u8>>
+where
+    R: AsyncRead + Unpin,
+{
+    let len = len as usize;
+    if !(4..=MAX_MESSAGE_SIZE).contains(&len) {
+        return Err(FloppyError::Protocol(format!(
+            "invalid message length: {len}"
+        )));
+    }
+    let mut body = vec![0; len - 4];
+    r.read_exact(&mut body).await?;
+    Ok(body)
+}
+
+fn put_cstr(buf: &mut BytesMut, s: &str) {
+    buf.put_slice(s.as_bytes());
+    buf.put_u8(0);
+}
+
+fn get_cstr(buf: &mut &[u8]) -> Result<String> {
+    let pos = buf.iter().position(|b| *b == 0).ok_or_else(|| {
+        FloppyError::Protocol("string is not null terminated".to_string())
+    })?;
+    let s = std::str::from_utf8(&buf[..pos])
+        .map_err(|e| FloppyError::Protocol(format!("invalid string: {e}")))?
+        .to_string();
+    buf.advance(pos + 1);
+    Ok(s)
+}
+
+fn put_formats(buf: &mut BytesMut, formats: &[Format]) {
+    buf.put_i16(formats.len() as i16);
+    for format in formats {
+        buf.put_i16((*format).into());
+    }
+}
+
+fn get_formats(buf: &mut &[u8]) -> Result<Vec<Format>> {
+    let count = get_i16(buf)?;
+    (0..count)
+        .map(|_| Format::try_from(get_i16(buf)?))
+        .collect()
+}
+
+fn get_object_kind(buf: &mut &[u8]) -> Result<ObjectKind> {
+    ensure_remaining(buf, 1)?;
+    match buf.get_u8() {
+        b'S' => Ok(ObjectKind::Statement),
+        b'P' => Ok(ObjectKind::Portal),
+        kind => Err(FloppyError::Protocol(format!(
+            "invalid object kind: {:?}",
+            kind as char
+        ))),
+    }
+}
+
+fn ensure_remaining(buf: &[u8], n: usize) -> Result<()> {
+    if buf.len() < n {
+        Err(FloppyError::Protocol(
+            "unexpected end of message".to_string(),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+fn get_i16(buf: &mut &[u8]) -> Result<i16> {
+    ensure_remaining(buf, 2)?;
+    Ok(buf.get_i16())
+}
+
+fn get_i32(buf: &mut &[u8]) -> Result<i32> {
+    ensure_remaining(buf, 4)?;
+    Ok(buf.get_i32())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn frontend_round_trip() -> Result<()> {
+        let messages = vec![
+            FrontendMessage::Query {
+                sql: "SELECT 1".to_string(),
+            },
+            FrontendMessage::Parse {
+                name: "s1".to_string(),
+                sql: "SELECT * FROM test WHERE c1 > $1".to_string(),
+                param_types: vec![20],
+            },
+            FrontendMessage::Parse {
+                name: "".to_string(),
+                sql: "SELECT 1".to_string(),
+                param_types: vec![],
+            },
+            FrontendMessage::Bind {
+                portal_name: "p1".to_string(),
+                statement_name: "s1".to_string(),
+                param_formats: vec![Format::Text, Format::Binary],
+                params: vec![Some(b"1".to_vec()), None],
+                result_formats: vec![Format::Binary],
+            },
+            FrontendMessage::Describe {
+                kind: ObjectKind::Statement,
+                name: "s1".to_string(),
+            },
+            FrontendMessage::Describe {
+                kind: ObjectKind::Portal,
+                name: "".to_string(),
+            },
+            FrontendMessage::Execute {
+                portal_name: "p1".to_string(),
+                max_rows: 30,
+            },
+            FrontendMessage::Sync,
+            FrontendMessage::CopyData(b"1\t2\n".to_vec()),
+            FrontendMessage::CopyDone,
+            FrontendMessage::CopyFail {
+                message: "canceled".to_string(),
+            },
+            FrontendMessage::Terminate,
+        ];
+        let mut buf = BytesMut::new();
+        for m in &messages {
+            encode_frontend(m, &mut buf);
+        }
+
+        let mut r = &buf[..];
+        for m in &messages {
+            assert_eq!(read_message(&mut r).await?.as_ref(), Some(m));
+        }
+        assert_eq!(read_message(&mut r).await?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn data_row_null() {
+        let mut buf = BytesMut::new();
+        let row = vec![Some(b"".to_vec()), None];
+        encode_backend(&BackendMessage::DataRow(row), &mut buf);
+        let mut expected = vec![b'D'];
+        expected.extend(14_i32.to_be_bytes());
+        expected.extend(2_i16.to_be_bytes());
+        // an empty value.
+        expected.extend(0_i32.to_be_bytes());
+        // NULL.
+        expected.extend((-1_i32).to_be_bytes());
+        assert_eq!(buf.as_ref(), expected);
+    }
+
+    #[test]
+    fn unknown_message_type() {
+        assert!(decode_frontend(b'?', &[]).is_err());
+        // missing the parameter types.
+        assert!(decode_frontend(b'P', b"s1\0SELECT 1\0").is_err());
+        assert!(decode_frontend(b'D', b"Xs1\0").is_err());
+    }
+}
@@ -0,0 +1,252 @@
+use crate::common::error::FloppyError;
+use crate::pgrepr::Format;
+use crate::session::{Notice, NoticeSeverity, TransactionState};
+use std::collections::HashMap;
+
+/// The protocol version 3.0.
+pub const VERSION_3: i32 = 196608;
+/// A magic protocol version requesting for SSL encryption.
+pub const VERSION_SSL: i32 = (1234 << 16) + 5679;
+/// A magic protocol version requesting for GSSAPI encryption.
+pub const VERSION_GSSENC: i32 = (1234 << 16) + 5680;
+/// A magic protocol version requesting to cancel a query.
+pub const VERSION_CANCEL: i32 = (1234 << 16) + 5678;
+
+/// The response to `SslRequest` or `GssEncRequest` if the server
+/// does not support the encryption.
+pub const REJECT_ENCRYPTION: u8 = b'N';
+/// The response to `SslRequest` if the server is going to start the
+/// TLS handshake.
+pub const ACCEPT_SSL: u8 = b'S';
+
+/// The first message sent by the client. It has no type byte.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FrontendStartupMessage {
+    Startup {
+        version: i32,
+        params: HashMap<String, String>,
+    },
+    SslRequest,
+    GssEncRequest,
+    CancelRequest {
+        conn_id: u32,
+        secret_key: u32,
+    },
+}
+
+/// Messages sent by the client after startup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FrontendMessage {
+    /// Execute a simple query.
+    Query { sql: String },
+    /// Create a prepared statement. `name` is empty for the unnamed
+    /// prepared statement. An OID of zero in `param_types` leaves the
+    /// type of the parameter unspecified.
+    Parse {
+        name: String,
+        sql: String,
+        param_types: Vec<u32>,
+    },
+    /// Bind parameters to a prepared statement to create a portal.
+    /// `portal_name` and `statement_name` can be empty for the unnamed
+    /// portal and prepared statement.
+    Bind {
+        portal_name: String,
+        statement_name: String,
+        /// The formats of the parameters. Zero format means all the
+        /// parameters are text, one format applies to all of them.
+        param_formats: Vec<Format>,
+        /// The values of the parameters, `None` is a NULL.
+        params: Vec<Option<Vec<u8>>>,
+        /// The formats of the result columns, in the same way as
+        /// `param_formats`.
+        result_formats: Vec<Format>,
+    },
+    /// Describe a prepared statement or a portal.
+    Describe { kind: ObjectKind, name: String },
+    /// Execute a portal, `max_rows` of zero means no limit.
+    Execute { portal_name: String, max_rows: i32 },
+    /// End of an extended query.
+    Sync,
+    /// A chunk of the data of `COPY FROM STDIN`.
+    CopyData(Vec<u8>),
+    /// The data of `COPY FROM STDIN` is completed.
+    CopyDone,
+    /// Abort `COPY FROM STDIN`, `message` is the reason.
+    CopyFail { message: String },
+    /// Close the connection.
+    Terminate,
+}
+
+/// The kind of object `Describe` refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectKind {
+    Statement,
+    Portal,
+}
+
+impl ObjectKind {
+    pub fn as_u8(&self) -> u8 {
+        match self {
+            Self::Statement => b'S',
+            Self::Portal => b'P',
+        }
+    }
+}
+
+/// Messages sent by the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BackendMessage {
+    AuthenticationOk,
+    /// The current value of a run-time parameter of the server.
+    ParameterStatus {
+        name: String,
+        value: String,
+    },
+    /// The key the client should use to send `CancelRequest`.
+    BackendKeyData {
+        conn_id: u32,
+        secret_key: u32,
+    },
+    ReadyForQuery(TransactionStatus),
+    RowDescription(Vec<FieldDescription>),
+    /// A row of encoded values, `None` is a NULL.
+    DataRow(Vec<Option<Vec<u8>>>),
+    ParseComplete,
+    BindComplete,
+    /// The OIDs of the parameters of a prepared statement.
+    ParameterDescription(Vec<u32>),
+    /// The statement returns no rows.
+    NoData,
+    /// The portal has more rows after an `Execute` with a row limit.
+    PortalSuspended,
+    /// The response to an empty query string instead of
+    /// `CommandComplete`.
+    EmptyQueryResponse,
+    /// A statement is completed, `tag` is the command tag, eg `SELECT 1`.
+    CommandComplete {
+        tag: String,
+    },
+    ErrorResponse(ErrorResponse),
+    /// The server is ready to receive the data of `COPY FROM STDIN`.
+    /// `format` is the overall format, and `column_formats` are the
+    /// formats of the columns.
+    CopyInResponse {
+        format: Format,
+        column_formats: Vec<Format>,
+    },
+    /// The server starts to send the data of `COPY TO STDOUT`, the
+    /// fields are the same as `CopyInResponse`.
+    CopyOutResponse {
+        format: Format,
+        column_formats: Vec<Format>,
+    },
+    /// A chunk of the data of `COPY TO STDOUT`.
+    CopyData(Vec<u8>),
+    /// The data of `COPY TO STDOUT` is completed.
+    CopyDone,
+    /// A message that is not an error, eg a warning. It has the
+    /// fields of `ErrorResponse`.
+    NoticeResponse(ErrorResponse),
+}
+
+/// The transaction status reported in `ReadyForQuery`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionStatus {
+    /// Not in a transaction block.
+    Idle,
+    /// In a transaction block.
+    InTransaction,
+    /// In a failed transaction block.
+    Failed,
+}
+
+impl From<&TransactionState> for TransactionStatus {
+    fn from(state: &TransactionState) -> Self {
+        match state {
+            TransactionState::Default => Self::Idle,
+            TransactionState::Started(_)
+            | TransactionState::InTransaction(_)
+            | TransactionState::InTransactionImplicit(_) => Self::InTransaction,
+            TransactionState::Failed(_) => Self::Failed,
+        }
+    }
+}
+
+impl TransactionStatus {
+    pub fn as_u8(&self) -> u8 {
+        match self {
+            Self::Idle => b'I',
+            Self::InTransaction => b'T',
+            Self::Failed => b'E',
+        }
+    }
+}
+
+/// The description of a field in `RowDescription`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldDescription {
+    pub name: String,
+    /// The OID of the table if the field is a column of a table,
+    /// otherwise zero.
+    pub table_id: u32,
+    /// The attribute number of the column, otherwise zero.
+    pub column_id: i16,
+    pub type_oid: u32,
+    pub type_len: i16,
+    pub type_mod: i32,
+    /// The format code of the field, zero for text and one for binary.
+    pub format: i16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorResponse {
+    pub severity: Severity,
+    /// The SQLSTATE code of the error.
+    pub code: &'static str,
+    pub message: String,
+    pub hint: Option<String>,
+}
+
+impl ErrorResponse {
+    pub fn error(e: &FloppyError) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: e.code(),
+            message: e.message(),
+            hint: e.hint(),
+        }
+    }
+
+    pub fn notice(notice: &Notice) -> Self {
+        let severity = match notice.severity {
+            NoticeSeverity::Warning => Severity::Warning,
+            NoticeSeverity::Notice => Severity::Notice,
+        };
+        Self {
+            severity,
+            code: notice.code,
+            message: notice.message.clone(),
+            hint: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Fatal,
+    Warning,
+    Notice,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Error => "ERROR",
+            Self::Fatal => "FATAL",
+            Self::Warning => "WARNING",
+            Self::Notice => "NOTICE",
+        }
+    }
+}
@@ -0,0 +1,1869 @@
+use crate::common::error::{FloppyError, Result};
+use crate::common::relation::{RelationDesc, Row};
+use crate::common::scalar::Datum;
+use crate::pgrepr::{copy, types, value, Format, Type};
+use crate::pgwire::codec;
+use crate::pgwire::message::{
+    BackendMessage, ErrorResponse, FieldDescription, FrontendMessage,
+    FrontendStartupMessage, ObjectKind, Severity, TransactionStatus,
+    ACCEPT_SSL, REJECT_ENCRYPTION, VERSION_3,
+};
+use crate::pgwire::tls::{AsyncStream, TlsAcceptor};
+use crate::session::{CopyIn, ExecuteResponse, Session};
+use bytes::BytesMut;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+
+/// Serve a client connection with `session` until the client
+/// terminates or closes the connection.
+pub async fn serve<S>(stream: S, session: &mut Session) -> Result<()>
+where
+    S: AsyncStream + 'static,
+{
+    serve_with_tls(stream, session, None).await
+}
+
+/// Same as [`serve`], but if `tls` is not `None`, the connection is
+/// upgraded to TLS when the client requests SSL encryption.
+pub async fn serve_with_tls<S>(
+    stream: S,
+    session: &mut Session,
+    tls: Option<&dyn TlsAcceptor>,
+) -> Result<()>
+where
+    S: AsyncStream + 'static,
+{
+    let mut conn = Conn::new(stream, session);
+    match (conn.startup(tls.is_some()).await?, tls) {
+        (Startup::Ready, _) => conn.run().await,
+        (Startup::Tls, Some(tls)) => {
+            let stream = tls.accept(Box::new(conn.stream)).await?;
+            let mut conn = Conn::new(stream, conn.session);
+            // a second `SslRequest` is rejected.
+            match conn.startup(false).await? {
+                Startup::Ready => conn.run().await,
+                _ => Ok(()),
+            }
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The result of the startup phase.
+enum Startup {
+    /// The connection is ready for queries.
+    Ready,
+    /// The client requested SSL and the server accepted it, the
+    /// startup continues after the TLS handshake.
+    Tls,
+    /// The connection should be closed.
+    Closed,
+}
+
+struct Conn<'a, S> {
+    stream: S,
+    session: &'a mut Session,
+    /// Messages that have not been written to `stream`.
+    buf: BytesMut,
+    /// After an error in an extended query, messages are ignored
+    /// until `Sync`.
+    ignore_till_sync: bool,
+}
+
+impl<'a, S> Conn<'a, S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    fn new(stream: S, session: &'a mut Session) -> Self {
+        Self {
+            stream,
+            session,
+            buf: BytesMut::new(),
+            ignore_till_sync: false,
+        }
+    }
+
+    /// Negotiate the protocol. SSL is accepted if `tls` is true.
+    async fn startup(&mut self, tls: bool) -> Result<Startup> {
+        loop {
+            match codec::decode_startup(&mut self.stream).await? {
+                None => return Ok(Startup::Closed),
+                Some(FrontendStartupMessage::SslRequest) if tls => {
+                    self.stream.write_u8(ACCEPT_SSL).await?;
+                    self.stream.flush().await?;
+                    return Ok(Startup::Tls);
+                }
+                Some(
+                    FrontendStartupMessage::SslRequest
+                    | FrontendStartupMessage::GssEncRequest,
+                ) => {
+                    self.stream.write_u8(REJECT_ENCRYPTION).await?;
+                    self.stream.flush().await?;
+                }
+                // todo! cancel the running query of the session.
+                Some(FrontendStartupMessage::CancelRequest { .. }) => {
+                    return Ok(Startup::Closed)
+                }
+                Some(FrontendStartupMessage::Startup { version, .. }) => {
+                    if version != VERSION_3 {
+                        self.send(BackendMessage::ErrorResponse(
+                            ErrorResponse {
+                                severity: Severity::Fatal,
+                                code: "08P01",
+                                message: format!(
+                                    "unsupported frontend protocol {}.{}",
+                                    version >> 16,
+                                    version & 0xffff
+                                ),
+                                hint: None,
+                            },
+                        ));
+                        self.flush().await?;
+                        return Ok(Startup::Closed);
+                    }
+                    break;
+                }
+            }
+        }
+
+        self.send(BackendMessage::AuthenticationOk);
+        for (name, value) in self.session.reported_vars() {
+            self.send(BackendMessage::ParameterStatus { name, value });
+        }
+        self.send(BackendMessage::BackendKeyData {
+            conn_id: self.session.conn_id(),
+            secret_key: self.session.secret_key(),
+        });
+        self.send(BackendMessage::ReadyForQuery(TransactionStatus::Idle));
+        self.flush().await?;
+        Ok(Startup::Ready)
+    }
+
+    /// Serve the messages until the client terminates or closes the
+    /// connection, which ends the session.
+    async fn run(&mut self) -> Result<()> {
+        let result = self.serve_messages().await;
+        self.session.terminate();
+        result
+    }
+
+    async fn serve_messages(&mut self) -> Result<()> {
+        loop {
+            let message = match codec::read_message(&mut self.stream).await? {
+                None | Some(FrontendMessage::Terminate) => return Ok(()),
+                Some(message) => message,
+            };
+
+            match message {
+                FrontendMessage::Query { sql } => {
+                    if let Err(e) = self.query(&sql).await {
+                        self.send_error(e);
+                    }
+                    self.send_ready_for_query();
+                }
+                FrontendMessage::Sync => {
+                    self.ignore_till_sync = false;
+                    self.send_ready_for_query();
+                }
+                // the client may still be sending the data after
+                // `COPY FROM STDIN` fails, like PostgreSQL they are
+                // ignored.
+                FrontendMessage::CopyData(_)
+                | FrontendMessage::CopyDone
+                | FrontendMessage::CopyFail { .. } => {}
+                _ if self.ignore_till_sync => {}
+                FrontendMessage::Parse {
+                    name,
+                    sql,
+                    param_types,
+                } => {
+                    if let Err(e) = self.parse(name, &sql, &param_types) {
+                        self.send_error(e);
+                        self.ignore_till_sync = true;
+                    }
+                }
+                FrontendMessage::Bind {
+                    portal_name,
+                    statement_name,
+                    param_formats,
+                    params,
+                    result_formats,
+                } => {
+                    if let Err(e) = self.bind(
+                        portal_name,
+                        &statement_name,
+                        &param_formats,
+                        &params,
+                        &result_formats,
+                    ) {
+                        self.send_error(e);
+                        self.ignore_till_sync = true;
+                    }
+                }
+                FrontendMessage::Describe { kind, name } => {
+                    if let Err(e) = self.describe(kind, &name) {
+                        self.send_error(e);
+                        self.ignore_till_sync = true;
+                    }
+                }
+                FrontendMessage::Execute {
+                    portal_name,
+                    max_rows,
+                } => {
+                    if let Err(e) = self.execute(&portal_name, max_rows).await {
+                        self.send_error(e);
+                        self.ignore_till_sync = true;
+                    }
+                }
+                FrontendMessage::Terminate => unreachable!(),
+            }
+            self.flush().await?;
+        }
+    }
+
+    /// Execute the statements of a simple query, it stops at the
+    /// first failed statement.
+    async fn query(&mut self, sql: &str) -> Result<()> {
+        let result = self.execute_batch(sql).await;
+        self.session.end_batch(result)
+    }
+
+    async fn execute_batch(&mut self, sql: &str) -> Result<()> {
+        let statements = self.session.start_batch(sql)?;
+        if statements.is_empty() {
+            self.send_command_complete(&ExecuteResponse::EmptyQuery);
+        }
+        for statement in statements {
+            let resp = self.session.execute_statement(statement).await?;
+            self.send_query_response(&resp).await?;
+        }
+        Ok(())
+    }
+
+    async fn send_query_response(
+        &mut self,
+        resp: &ExecuteResponse,
+    ) -> Result<()> {
+        if let ExecuteResponse::CopyIn(copy_in) = resp {
+            return self.copy_in(copy_in).await;
+        }
+        if let ExecuteResponse::CopyOut { desc, rows } = resp {
+            self.copy_out(desc, rows)?;
+        }
+        if let ExecuteResponse::Rows { desc, rows }
+        | ExecuteResponse::Fetched { desc, rows }
+        | ExecuteResponse::ShowVariable { desc, rows } = resp
+        {
+            // the results of a simple query are always in text.
+            self.send(BackendMessage::RowDescription(row_description(
+                desc,
+                &[],
+            )));
+            for row in rows {
+                self.send(BackendMessage::DataRow(data_row(row, desc, &[])?));
+            }
+        }
+        self.send_command_complete(resp);
+        Ok(())
+    }
+
+    fn parse(
+        &mut self,
+        name: String,
+        sql: &str,
+        param_oids: &[u32],
+    ) -> Result<()> {
+        let param_types = param_oids
+            .iter()
+            .enumerate()
+            .map(|(i, oid)| match Type::from_oid(*oid) {
+                Ok(ty) => Ok(ty),
+                Err(FloppyError::NotImplemented(_)) => {
+                    Err(FloppyError::UnsupportedParamType {
+                        param: i + 1,
+                        oid: *oid,
+                    })
+                }
+                Err(e) => Err(e),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        self.session.prepare(name, sql, &param_types)?;
+        self.send(BackendMessage::ParseComplete);
+        Ok(())
+    }
+
+    fn bind(
+        &mut self,
+        portal_name: String,
+        statement_name: &str,
+        param_formats: &[Format],
+        params: &[Option<Vec<u8>>],
+        result_formats: &[Format],
+    ) -> Result<()> {
+        // the unnamed portal is closed even if the bind fails.
+        if portal_name.is_empty() {
+            self.session.close_portal(&portal_name);
+        }
+        let stmt = self
+            .session
+            .get_prepared_statement(statement_name)
+            .ok_or_else(|| {
+                FloppyError::Plan(format!(
+                    "prepared statement \"{statement_name}\" does not exist"
+                ))
+            })?;
+        let param_types = stmt.param_types();
+        if params.len() != param_types.len() {
+            return Err(FloppyError::Plan(format!(
+                "bind message supplies {} parameters, but prepared \
+                 statement \"{statement_name}\" requires {}",
+                params.len(),
+                param_types.len()
+            )));
+        }
+        let param_formats = expand_formats(param_formats, params.len())?;
+        let bound_params = params
+            .iter()
+            .zip(param_types)
+            .zip(param_formats)
+            .map(|((raw, ty), format)| match raw {
+                None => Ok(Datum::Null),
+                Some(raw) => value::decode(*ty, format, raw),
+            })
+            .collect::<Result<Vec<Datum>>>()?;
+        let result_formats =
+            expand_formats(result_formats, stmt.desc().arity())?;
+
+        self.session.declare_portal(
+            portal_name,
+            statement_name,
+            bound_params,
+            result_formats,
+        )?;
+        self.send(BackendMessage::BindComplete);
+        Ok(())
+    }
+
+    fn describe(&mut self, kind: ObjectKind, name: &str) -> Result<()> {
+        let (desc, formats) = match kind {
+            ObjectKind::Statement => {
+                let stmt = self
+                    .session
+                    .get_prepared_statement(name)
+                    .ok_or_else(|| {
+                        FloppyError::Plan(format!(
+                            "prepared statement \"{name}\" does not exist"
+                        ))
+                    })?;
+                let desc = stmt.desc().clone();
+                self.send(BackendMessage::ParameterDescription(
+                    stmt.param_types().iter().map(Type::oid).collect(),
+                ));
+                // the formats are unknown until the statement is bound.
+                let formats = vec![Format::Text; desc.arity()];
+                (desc, formats)
+            }
+            ObjectKind::Portal => {
+                let portal =
+                    self.session.get_portal(name).ok_or_else(|| {
+                        FloppyError::Plan(format!(
+                            "portal \"{name}\" does not exist"
+                        ))
+                    })?;
+                (portal.desc.clone(), portal.result_formats.clone())
+            }
+        };
+        match &desc.rel_desc {
+            Some(rel_desc) => self.send(BackendMessage::RowDescription(
+                row_description(rel_desc, &formats),
+            )),
+            None => self.send(BackendMessage::NoData),
+        }
+        Ok(())
+    }
+
+    async fn execute(
+        &mut self,
+        portal_name: &str,
+        max_rows: i32,
+    ) -> Result<()> {
+        let max_rows = if max_rows > 0 {
+            Some(max_rows as usize)
+        } else {
+            None
+        };
+        let (portal_desc, formats) = match self.session.get_portal(portal_name)
+        {
+            Some(portal) => (
+                portal.desc.rel_desc.clone().unwrap_or_default(),
+                portal.result_formats.clone(),
+            ),
+            None => Default::default(),
+        };
+        let resp = self.session.execute_portal(portal_name, max_rows).await?;
+        if let ExecuteResponse::CopyIn(copy_in) = &resp {
+            return self.copy_in(copy_in).await;
+        }
+        if let ExecuteResponse::CopyOut { desc, rows } = &resp {
+            self.copy_out(desc, rows)?;
+            self.send_command_complete(&resp);
+            return Ok(());
+        }
+        let (desc, rows) = match &resp {
+            ExecuteResponse::Rows { desc, rows }
+            | ExecuteResponse::Fetched { desc, rows }
+            | ExecuteResponse::ShowVariable { desc, rows } => (desc, rows),
+            ExecuteResponse::PortalSuspended { rows } => (&portal_desc, rows),
+            _ => (&portal_desc, &vec![]),
+        };
+        for row in rows {
+            let values = data_row(row, desc, &formats)?;
+            self.send(BackendMessage::DataRow(values));
+        }
+        match resp {
+            ExecuteResponse::PortalSuspended { .. } => {
+                self.send(BackendMessage::PortalSuspended)
+            }
+            resp => self.send_command_complete(&resp),
+        }
+        Ok(())
+    }
+
+    /// Receive the data of `COPY FROM STDIN` and insert the rows.
+    async fn copy_in(&mut self, copy_in: &CopyIn) -> Result<()> {
+        self.send(BackendMessage::CopyInResponse {
+            format: Format::Text,
+            column_formats: vec![Format::Text; copy_in.columns.len()],
+        });
+        self.flush().await?;
+        let mut data = vec![];
+        loop {
+            match codec::read_message(&mut self.stream).await? {
+                Some(FrontendMessage::CopyData(chunk)) => data.extend(chunk),
+                Some(FrontendMessage::CopyDone) => break,
+                Some(FrontendMessage::CopyFail { message }) => {
+                    return Err(self.session.fail_copy(message));
+                }
+                // like PostgreSQL, they are ignored during the copy.
+                Some(FrontendMessage::Sync) => {}
+                Some(message) => {
+                    return Err(FloppyError::Protocol(format!(
+                        "unexpected message during COPY from stdin: \
+                         {message:?}"
+                    )))
+                }
+                None => {
+                    return Err(FloppyError::Protocol(
+                        "unexpected EOF during COPY from stdin".to_string(),
+                    ))
+                }
+            }
+        }
+        let resp = self.session.copy_from(copy_in, &data)?;
+        self.send_command_complete(&resp);
+        Ok(())
+    }
+
+    /// Send `rows` of `COPY TO STDOUT`, a row in each `CopyData`.
+    fn copy_out(&mut self, desc: &RelationDesc, rows: &[Row]) -> Result<()> {
+        let types = desc
+            .iter_types()
+            .map(|typ| Type::from(&typ.scalar_type))
+            .collect::<Vec<_>>();
+        self.send(BackendMessage::CopyOutResponse {
+            format: Format::Text,
+            column_formats: vec![Format::Text; types.len()],
+        });
+        for row in rows {
+            self.send(BackendMessage::CopyData(copy::encode_text(
+                row, &types,
+            )?));
+        }
+        self.send(BackendMessage::CopyDone);
+        Ok(())
+    }
+
+    fn send_command_complete(&mut self, resp: &ExecuteResponse) {
+        if let ExecuteResponse::EmptyQuery = resp {
+            self.send(BackendMessage::EmptyQueryResponse);
+        }
+        if let Some(tag) = resp.tag() {
+            self.send(BackendMessage::CommandComplete { tag });
+        }
+        if let ExecuteResponse::SetVariable {
+            name,
+            value,
+            reported: true,
+        } = resp
+        {
+            self.send(BackendMessage::ParameterStatus {
+                name: name.clone(),
+                value: value.clone(),
+            });
+        }
+    }
+
+    fn send_ready_for_query(&mut self) {
+        for notice in self.session.drain_notices() {
+            self.send(BackendMessage::NoticeResponse(ErrorResponse::notice(
+                &notice,
+            )));
+        }
+        let status = TransactionStatus::from(self.session.transaction());
+        self.send(BackendMessage::ReadyForQuery(status));
+    }
+
+    fn send_error(&mut self, e: FloppyError) {
+        self.send(BackendMessage::ErrorResponse(ErrorResponse::error(&e)));
+    }
+
+    fn send(&mut self, message: BackendMessage) {
+        codec::encode_backend(&message, &mut self.buf);
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        self.stream.write_all(&self.buf).await?;
+        self.stream.flush().await?;
+        self.buf.clear();
+        Ok(())
+    }
+}
+
+/// Expand the format codes in `Bind` to one format for each of the
+/// `n` values.
+fn expand_formats(formats: &[Format], n: usize) -> Result<Vec<Format>> {
+    match formats.len() {
+        0 => Ok(vec![Format::Text; n]),
+        1 => Ok(vec![formats[0]; n]),
+        len if len == n => Ok(formats.to_vec()),
+        len => Err(FloppyError::Protocol(format!(
+            "expect {n} format codes, got {len}"
+        ))),
+    }
+}
+
+/// The format of the `i`th column, text if `formats` does not
+/// specify it.
+fn column_format(formats: &[Format], i: usize) -> Format {
+    formats.get(i).copied().unwrap_or(Format::Text)
+}
+
+fn row_description(
+    desc: &RelationDesc,
+    formats: &[Format],
+) -> Vec<FieldDescription> {
+    desc.iter()
+        .enumerate()
+        .map(|(i, (name, typ))| {
+            let ty = Type::from(&typ.scalar_type);
+            FieldDescription {
+                name: name.clone(),
+                table_id: 0,
+                column_id: 0,
+                type_oid: ty.oid(),
+                type_len: ty.typlen(),
+                type_mod: types::typmod(&typ.scalar_type),
+                format: column_format(formats, i).into(),
+            }
+        })
+        .collect()
+}
+
+fn data_row(
+    row: &Row,
+    desc: &RelationDesc,
+    formats: &[Format],
+) -> Result<Vec<Option<Vec<u8>>>> {
+    let types = desc
+        .iter_types()
+        .map(|typ| Type::from(&typ.scalar_type))
+        .collect::<Vec<_>>();
+    let values = value::values_from_row(row, &types)?
+        .into_iter()
+        .enumerate()
+        .map(|(i, v)| v.map(|v| v.encode(column_format(formats, i))))
+        .collect();
+    Ok(values)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::common::scalar::ScalarType;
+    use crate::pgwire::message::VERSION_SSL;
+    use crate::pgwire::tls;
+    use crate::session::TransactionState;
+    use crate::test_util::seeder;
+    use bytes::Buf;
+    use std::collections::HashMap;
+    use tokio::io::AsyncReadExt;
+
+    /// A client that talks to the server in tests.
+    pub(crate) struct TestClient {
+        pub stream: Box<dyn AsyncStream>,
+    }
+
+    impl TestClient {
+        pub async fn startup(&mut self) -> Result<Vec<(u8, Vec<u8>)>> {
+            let mut buf = BytesMut::new();
+            let params = b"user\0floppy\0\0";
+            buf.extend_from_slice(&(8 + params.len() as i32).to_be_bytes());
+            buf.extend_from_slice(&VERSION_3.to_be_bytes());
+            buf.extend_from_slice(params);
+            self.stream.write_all(&buf).await?;
+            self.read_till_ready().await
+        }
+
+        /// Send `SslRequest`, returns the response of the server.
+        pub async fn ssl_request(&mut self) -> Result<u8> {
+            self.stream.write_i32(8).await?;
+            self.stream.write_i32(VERSION_SSL).await?;
+            Ok(self.stream.read_u8().await?)
+        }
+
+        pub async fn send(
+            &mut self,
+            messages: &[FrontendMessage],
+        ) -> Result<()> {
+            let mut buf = BytesMut::new();
+            for m in messages {
+                codec::encode_frontend(m, &mut buf);
+            }
+            self.stream.write_all(&buf).await?;
+            Ok(())
+        }
+
+        /// Send a simple query, returns the messages till
+        /// `ReadyForQuery`.
+        pub async fn query(&mut self, sql: &str) -> Result<Vec<(u8, Vec<u8>)>> {
+            self.send(&[FrontendMessage::Query {
+                sql: sql.to_string(),
+            }])
+            .await?;
+            self.read_till_ready().await
+        }
+
+        /// Read a message, returns its type byte and body.
+        pub async fn read_message(&mut self) -> Result<(u8, Vec<u8>)> {
+            let tag = self.stream.read_u8().await?;
+            let len = self.stream.read_i32().await? as usize;
+            let mut body = vec![0; len - 4];
+            self.stream.read_exact(&mut body).await?;
+            Ok((tag, body))
+        }
+
+        /// Read messages until `ReadyForQuery`.
+        pub async fn read_till_ready(&mut self) -> Result<Vec<(u8, Vec<u8>)>> {
+            let mut messages = vec![];
+            loop {
+                let message = self.read_message().await?;
+                let ready = message.0 == b'Z';
+                messages.push(message);
+                if ready {
+                    return Ok(messages);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn tags(messages: &[(u8, Vec<u8>)]) -> Vec<u8> {
+        messages.iter().map(|(tag, _)| *tag).collect()
+    }
+
+    /// Returns the values of the `DataRow` messages in text.
+    pub(crate) fn data_rows(messages: &[(u8, Vec<u8>)]) -> Vec<Vec<String>> {
+        messages
+            .iter()
+            .filter(|(tag, _)| *tag == b'D')
+            .map(|(_, body)| {
+                let mut buf = body.as_slice();
+                let count = buf.get_i16();
+                (0..count)
+                    .map(|_| {
+                        let len = buf.get_i32();
+                        if len < 0 {
+                            return "NULL".to_string();
+                        }
+                        let v = buf[..len as usize].to_vec();
+                        buf.advance(len as usize);
+                        String::from_utf8(v).unwrap()
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Returns the fields of an `ErrorResponse` or a `NoticeResponse`
+    /// keyed by their types, each is a type byte followed by a string.
+    pub(crate) fn error_fields(body: &[u8]) -> HashMap<u8, String> {
+        body.split(|b| *b == 0)
+            .filter(|field| !field.is_empty())
+            .map(|field| {
+                let value = String::from_utf8(field[1..].to_vec()).unwrap();
+                (field[0], value)
+            })
+            .collect()
+    }
+
+    /// Returns the names and type OIDs of the fields of a
+    /// `RowDescription`.
+    pub(crate) fn row_fields(body: &[u8]) -> Vec<(String, u32)> {
+        let mut buf = body;
+        let count = buf.get_i16();
+        (0..count)
+            .map(|_| {
+                let pos = buf.iter().position(|b| *b == 0).unwrap();
+                let name = String::from_utf8(buf[..pos].to_vec()).unwrap();
+                buf.advance(pos + 1);
+                buf.advance(6);
+                let oid = buf.get_u32();
+                buf.advance(8);
+                (name, oid)
+            })
+            .collect()
+    }
+
+    pub(crate) async fn run_test<F, Fut>(
+        session: &mut Session,
+        f: F,
+    ) -> Result<()>
+    where
+        F: FnOnce(TestClient) -> Fut,
+        Fut: std::future::Future<Output = Result<()>>,
+    {
+        run_test_with_tls(session, None, f).await
+    }
+
+    pub(crate) async fn run_test_with_tls<F, Fut>(
+        session: &mut Session,
+        tls: Option<&dyn TlsAcceptor>,
+        f: F,
+    ) -> Result<()>
+    where
+        F: FnOnce(TestClient) -> Fut,
+        Fut: std::future::Future<Output = Result<()>>,
+    {
+        let (client, server) = tokio::io::duplex(1 << 16);
+        let (server, client) = tokio::join!(
+            serve_with_tls(server, session, tls),
+            f(TestClient {
+                stream: Box::new(client)
+            })
+        );
+        server?;
+        client
+    }
+
+    pub(crate) fn test_session(n: i64) -> Result<Session> {
+        let rows = (0..n)
+            .map(|i| Row::new(vec![Datum::Int64(i), Datum::Int64(i * 10)]))
+            .collect::<Vec<Row>>();
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&rows)?;
+        Ok(Session::new(1, catalog_store, table_store))
+    }
+
+    #[tokio::test]
+    async fn startup() -> Result<()> {
+        let mut session = test_session(10)?;
+        let secret_key = session.secret_key();
+        run_test(&mut session, |mut client| async move {
+            let messages = client.startup().await?;
+            assert_eq!(tags(&messages), b"RSSSSSSKZ");
+
+            let params = messages
+                .iter()
+                .filter(|(tag, _)| *tag == b'S')
+                .map(|(_, body)| {
+                    let mut parts = body.split(|b| *b == 0);
+                    let name = parts.next().unwrap();
+                    let value = parts.next().unwrap();
+                    (
+                        String::from_utf8(name.to_vec()).unwrap(),
+                        String::from_utf8(value.to_vec()).unwrap(),
+                    )
+                })
+                .collect::<HashMap<_, _>>();
+            assert_eq!(params["server_version"], "14.0");
+            assert_eq!(params["client_encoding"], "UTF8");
+            assert_eq!(params["DateStyle"], "ISO, MDY");
+            assert_eq!(params["integer_datetimes"], "on");
+
+            let (_, key) = &messages[messages.len() - 2];
+            let mut key = key.as_slice();
+            assert_eq!(key.get_u32(), 1);
+            assert_eq!(key.get_u32(), secret_key);
+
+            client.send(&[FrontendMessage::Terminate]).await?;
+            Ok(())
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn set_and_show() -> Result<()> {
+        let mut session = test_session(0)?;
+        run_test(&mut session, |mut client| async move {
+            client.startup().await?;
+            let query = |sql: &str| FrontendMessage::Query {
+                sql: sql.to_string(),
+            };
+
+            // a change of a reported parameter is sent to the client.
+            client
+                .send(&[query("SET client_encoding = 'UTF8'")])
+                .await?;
+            let messages = client.read_till_ready().await?;
+            assert_eq!(tags(&messages), b"CSZ");
+            assert_eq!(messages[1].1, b"client_encoding\0UTF8\0");
+
+            client
+                .send(&[query("SET floppy.greeting TO hello")])
+                .await?;
+            assert_eq!(tags(&client.read_till_ready().await?), b"CZ");
+            client.send(&[query("SHOW floppy.greeting")]).await?;
+            let messages = client.read_till_ready().await?;
+            assert_eq!(tags(&messages), b"TDCZ");
+            assert_eq!(
+                row_fields(&messages[0].1),
+                [("floppy.greeting".to_string(), 25)]
+            );
+            assert_eq!(data_rows(&messages), [["hello"]]);
+            assert_eq!(messages[2].1, b"SHOW\0");
+
+            client.send(&[FrontendMessage::Terminate]).await?;
+            Ok(())
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn ssl_request() -> Result<()> {
+        let mut session = test_session(10)?;
+        run_test(&mut session, |mut client| async move {
+            assert_eq!(client.ssl_request().await?, REJECT_ENCRYPTION);
+            assert_eq!(tags(&client.startup().await?).last(), Some(&b'Z'));
+            client.send(&[FrontendMessage::Terminate]).await?;
+            Ok(())
+        })
+        .await?;
+
+        let (acceptor, connector) = tls::tests::test_tls()?;
+        run_test_with_tls(
+            &mut session,
+            Some(&acceptor),
+            |mut client| async move {
+                assert_eq!(client.ssl_request().await?, ACCEPT_SSL);
+                client.stream =
+                    tls::tests::connect(&connector, client.stream).await?;
+                // SSL is requested only once.
+                assert_eq!(client.ssl_request().await?, REJECT_ENCRYPTION);
+                assert_eq!(tags(&client.startup().await?).last(), Some(&b'Z'));
+                client
+                    .send(&[FrontendMessage::Query {
+                        sql: "SELECT c1 FROM test".to_string(),
+                    }])
+                    .await?;
+                let messages = client.read_till_ready().await?;
+                assert_eq!(data_rows(&messages).len(), 10);
+                client.send(&[FrontendMessage::Terminate]).await?;
+                Ok(())
+            },
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn simple_query() -> Result<()> {
+        let mut session = test_session(10)?;
+        run_test(&mut session, |mut client| async move {
+            client.startup().await?;
+
+            client
+                .send(&[FrontendMessage::Query {
+                    sql: "SELECT c2 FROM test".to_string(),
+                }])
+                .await?;
+            let messages = client.read_till_ready().await?;
+            assert_eq!(messages.first().unwrap().0, b'T');
+            assert_eq!(messages.last().unwrap().0, b'Z');
+            assert_eq!(
+                data_rows(&messages),
+                (0..10)
+                    .map(|i| vec![(i * 10).to_string()])
+                    .collect::<Vec<_>>()
+            );
+            let (tag, body) = &messages[messages.len() - 2];
+            assert_eq!((*tag, body.as_slice()), (b'C', &b"SELECT 10\0"[..]));
+
+            client
+                .send(&[FrontendMessage::Query {
+                    sql: "BEGIN".to_string(),
+                }])
+                .await?;
+            let messages = client.read_till_ready().await?;
+            assert_eq!(messages[0], (b'C', b"BEGIN\0".to_vec()));
+
+            client
+                .send(&[FrontendMessage::Query {
+                    sql: "SELECT c3 FROM test".to_string(),
+                }])
+                .await?;
+            let messages = client.read_till_ready().await?;
+            assert_eq!(tags(&messages), vec![b'E', b'Z']);
+
+            client.send(&[FrontendMessage::Terminate]).await?;
+            Ok(())
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn notice() -> Result<()> {
+        let mut session = test_session(0)?;
+        run_test(&mut session, |mut client| async move {
+            client.startup().await?;
+            client
+                .send(&[FrontendMessage::Query {
+                    sql: "COMMIT".to_string(),
+                }])
+                .await?;
+            let messages = client.read_till_ready().await?;
+            assert_eq!(tags(&messages), vec![b'C', b'N', b'Z']);
+            let fields = error_fields(&messages[1].1);
+            assert_eq!(fields[&b'S'], "WARNING");
+            assert_eq!(fields[&b'C'], "25P01");
+            assert_eq!(fields[&b'M'], "there is no transaction in progress");
+
+            client.send(&[FrontendMessage::Terminate]).await?;
+            Ok(())
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn copy_from_stdin() -> Result<()> {
+        let mut session = test_session(0)?;
+        run_test(&mut session, |mut client| async move {
+            client.startup().await?;
+            let query = |sql: &str| {
+                [FrontendMessage::Query {
+                    sql: sql.to_string(),
+                }]
+            };
+
+            client.send(&query("COPY test FROM STDIN")).await?;
+            let (tag, body) = client.read_message().await?;
+            assert_eq!(tag, b'G');
+            // text format of 2 columns.
+            assert_eq!(body, vec![0, 0, 2, 0, 0, 0, 0]);
+            client
+                .send(&[
+                    FrontendMessage::CopyData(b"1\t10\n2\t".to_vec()),
+                    FrontendMessage::CopyData(b"20\n".to_vec()),
+                    FrontendMessage::CopyDone,
+                ])
+                .await?;
+            let messages = client.read_till_ready().await?;
+            assert_eq!(tags(&messages), b"CZ");
+            assert_eq!(messages[0].1, b"COPY 2\0");
+
+            client.send(&query("SELECT * FROM test")).await?;
+            let messages = client.read_till_ready().await?;
+            assert_eq!(data_rows(&messages), [["1", "10"], ["2", "20"]]);
+
+            // a malformed row, a NULL in a NOT NULL column, and a copy
+            // aborted by the client.
+            for (sql, message) in [
+                (
+                    "COPY test FROM STDIN",
+                    FrontendMessage::CopyData(b"3\tx\n".to_vec()),
+                ),
+                (
+                    "COPY test (c1) FROM STDIN",
+                    FrontendMessage::CopyData(b"3\n".to_vec()),
+                ),
+                (
+                    "COPY test FROM STDIN",
+                    FrontendMessage::CopyFail {
+                        message: "canceled".to_string(),
+                    },
+                ),
+            ] {
+                client.send(&query(sql)).await?;
+                assert_eq!(client.read_message().await?.0, b'G');
+                client.send(&[message, FrontendMessage::CopyDone]).await?;
+                let messages = client.read_till_ready().await?;
+                assert_eq!(tags(&messages), b"EZ", "{sql}");
+            }
+
+            client.send(&query("SELECT * FROM test")).await?;
+            let messages = client.read_till_ready().await?;
+            assert_eq!(data_rows(&messages).len(), 2);
+
+            client.send(&[FrontendMessage::Terminate]).await?;
+            Ok(())
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn copy_to_stdout() -> Result<()> {
+        let mut session = test_session(3)?;
+        run_test(&mut session, |mut client| async move {
+            client.startup().await?;
+            client
+                .send(&[FrontendMessage::Query {
+                    sql: "COPY test (c2, c1) TO STDOUT".to_string(),
+                }])
+                .await?;
+            let messages = client.read_till_ready().await?;
+            assert_eq!(tags(&messages), b"HdddcCZ");
+            // text format of 2 columns.
+            assert_eq!(messages[0].1, vec![0, 0, 2, 0, 0, 0, 0]);
+            let data = messages
+                .iter()
+                .filter(|(tag, _)| *tag == b'd')
+                .flat_map(|(_, body)| body.clone())
+                .collect::<Vec<_>>();
+            assert_eq!(data, b"0\t0\n10\t1\n20\t2\n");
+            assert_eq!(messages[5].1, b"COPY 3\0");
+
+            client.send(&[FrontendMessage::Terminate]).await?;
+            Ok(())
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn terminate() -> Result<()> {
+        let mut session = test_session(10)?;
+        run_test(&mut session, |mut client| async move {
+            client.startup().await?;
+            for sql in ["BEGIN", "DECLARE c CURSOR FOR SELECT * FROM test"] {
+                client
+                    .send(&[FrontendMessage::Query {
+                        sql: sql.to_string(),
+                    }])
+                    .await?;
+                let messages = client.read_till_ready().await?;
+                assert_eq!(tags(&messages), b"CZ");
+            }
+            client.send(&[FrontendMessage::Terminate]).await?;
+            // the server closes the connection without sending more
+            // messages.
+            let mut buf = vec![];
+            client.stream.read_to_end(&mut buf).await?;
+            assert!(buf.is_empty());
+            Ok(())
+        })
+        .await?;
+        // the open transaction is rolled back.
+        assert!(matches!(session.transaction(), TransactionState::Default));
+        assert!(session.execute("FETCH c").await.is_err());
+
+        // the same for a closed connection.
+        run_test(&mut session, |mut client| async move {
+            client.startup().await?;
+            client
+                .send(&[FrontendMessage::Query {
+                    sql: "BEGIN".to_string(),
+                }])
+                .await?;
+            client.read_till_ready().await?;
+            Ok(())
+        })
+        .await?;
+        assert!(matches!(session.transaction(), TransactionState::Default));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn transaction_status() -> Result<()> {
+        let mut session = test_session(10)?;
+        run_test(&mut session, |mut client| async move {
+            client.startup().await?;
+            for (sql, status) in [
+                // a statement outside of a transaction block is
+                // committed, or rolled back if it fails.
+                ("SELECT * FROM test", b'I'),
+                ("UPDATE test SET c2 = 11 WHERE c1 = 1", b'I'),
+                ("SELECT c3 FROM test", b'I'),
+                ("BEGIN", b'T'),
+                ("SELECT * FROM test", b'T'),
+                ("SELECT c3 FROM test", b'E'),
+                ("SELECT * FROM test", b'E'),
+                ("ROLLBACK", b'I'),
+                // the implicit transaction ends with the query.
+                ("SELECT 1; SELECT 2", b'I'),
+            ] {
+                let messages = client.query(sql).await?;
+                assert_eq!(
+                    messages.last(),
+                    Some(&(b'Z', vec![status])),
+                    "{sql}"
+                );
+            }
+            // the auto-committed write is kept by the rollback.
+            let messages =
+                client.query("SELECT c2 FROM test WHERE c1 = 1").await?;
+            assert_eq!(data_rows(&messages), [["11"]]);
+
+            // the same for the extended query protocol.
+            client
+                .send(&[
+                    FrontendMessage::Parse {
+                        name: "".to_string(),
+                        sql: "BEGIN".to_string(),
+                        param_types: vec![],
+                    },
+                    FrontendMessage::Bind {
+                        portal_name: "".to_string(),
+                        statement_name: "".to_string(),
+                        param_formats: vec![],
+                        params: vec![],
+                        result_formats: vec![],
+                    },
+                    FrontendMessage::Execute {
+                        portal_name: "".to_string(),
+                        max_rows: 0,
+                    },
+                    FrontendMessage::Sync,
+                ])
+                .await?;
+            let messages = client.read_till_ready().await?;
+            assert_eq!(tags(&messages), b"12CZ");
+            assert_eq!(messages[3].1, vec![b'T']);
+            client.send(&[FrontendMessage::Terminate]).await?;
+            Ok(())
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn portal_in_failed_transaction() -> Result<()> {
+        let mut session = test_session(10)?;
+        run_test(&mut session, |mut client| async move {
+            client.startup().await?;
+            client.query("BEGIN").await?;
+            let execute = |portal_name: &str| FrontendMessage::Execute {
+                portal_name: portal_name.to_string(),
+                max_rows: 0,
+            };
+            client
+                .send(&[
+                    FrontendMessage::Parse {
+                        name: "s1".to_string(),
+                        sql: "SELECT 10 / (c1 - 1) FROM test".to_string(),
+                        param_types: vec![],
+                    },
+                    FrontendMessage::Bind {
+                        portal_name: "p1".to_string(),
+                        statement_name: "s1".to_string(),
+                        param_formats: vec![],
+                        params: vec![],
+                        result_formats: vec![],
+                    },
+                    FrontendMessage::Sync,
+                ])
+                .await?;
+            let messages = client.read_till_ready().await?;
+            assert_eq!(tags(&messages), b"12Z");
+            assert_eq!(messages[2].1, vec![b'T']);
+
+            // the error of the portal fails the transaction.
+            client.send(&[execute("p1"), FrontendMessage::Sync]).await?;
+            let messages = client.read_till_ready().await?;
+            assert_eq!(tags(&messages), b"EZ");
+            assert_eq!(error_fields(&messages[0].1)[&b'C'], "22012");
+            assert_eq!(messages[1].1, vec![b'E']);
+
+            // then the portals are neither bound nor executed.
+            client
+                .send(&[
+                    FrontendMessage::Bind {
+                        portal_name: "p2".to_string(),
+                        statement_name: "s1".to_string(),
+                        param_formats: vec![],
+                        params: vec![],
+                        result_formats: vec![],
+                    },
+                    FrontendMessage::Sync,
+                    execute("p1"),
+                    FrontendMessage::Sync,
+                ])
+                .await?;
+            for _ in 0..2 {
+                let messages = client.read_till_ready().await?;
+                assert_eq!(tags(&messages), b"EZ");
+                assert_eq!(error_fields(&messages[0].1)[&b'C'], "25P02");
+                assert_eq!(messages[1].1, vec![b'E']);
+            }
+
+            let messages = client.query("ROLLBACK").await?;
+            assert_eq!(messages.last(), Some(&(b'Z', vec![b'I'])));
+            client.send(&[FrontendMessage::Terminate]).await?;
+            Ok(())
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn multi_statement_query() -> Result<()> {
+        let mut session = test_session(10)?;
+        run_test(&mut session, |mut client| async move {
+            client.startup().await?;
+
+            let messages = client.query("SELECT 1; SELECT 2").await?;
+            assert_eq!(tags(&messages), b"TDCTDCZ");
+            assert_eq!(data_rows(&messages), [["1"], ["2"]]);
+
+            // the statements run in an implicit transaction, which
+            // is committed at the end.
+            let messages = client
+                .query(
+                    "DECLARE c CURSOR FOR SELECT * FROM test; FETCH 2 FROM c",
+                )
+                .await?;
+            assert_eq!(tags(&messages), b"CTDDCZ");
+            assert_eq!(tags(&client.query("FETCH 1 FROM c").await?), b"EZ");
+
+            // a failed statement aborts the rest, and the implicit
+            // transaction is rolled back.
+            let messages = client
+                .query(
+                    "DECLARE c CURSOR FOR SELECT * FROM test; SELECT 1 / 0; \
+                 SELECT 1",
+                )
+                .await?;
+            assert_eq!(tags(&messages), b"CEZ");
+            assert_eq!(tags(&client.query("FETCH 1 FROM c").await?), b"EZ");
+
+            client.send(&[FrontendMessage::Terminate]).await?;
+            Ok(())
+        })
+        .await?;
+        assert!(matches!(session.transaction(), TransactionState::Default));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn empty_query() -> Result<()> {
+        let mut session = test_session(10)?;
+        run_test(&mut session, |mut client| async move {
+            client.startup().await?;
+            for sql in ["", ";", "  \n", "; ;"] {
+                let messages = client.query(sql).await?;
+                assert_eq!(messages, [(b'I', vec![]), (b'Z', vec![b'I'])]);
+            }
+
+            // an empty prepared statement has no data.
+            client
+                .send(&[
+                    FrontendMessage::Parse {
+                        name: "".to_string(),
+                        sql: "".to_string(),
+                        param_types: vec![],
+                    },
+                    FrontendMessage::Bind {
+                        portal_name: "".to_string(),
+                        statement_name: "".to_string(),
+                        param_formats: vec![],
+                        params: vec![],
+                        result_formats: vec![],
+                    },
+                    FrontendMessage::Describe {
+                        kind: ObjectKind::Portal,
+                        name: "".to_string(),
+                    },
+                    FrontendMessage::Execute {
+                        portal_name: "".to_string(),
+                        max_rows: 0,
+                    },
+                    FrontendMessage::Sync,
+                ])
+                .await?;
+            assert_eq!(tags(&client.read_till_ready().await?), b"12nIZ");
+
+            client.send(&[FrontendMessage::Terminate]).await?;
+            Ok(())
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn parse() -> Result<()> {
+        let mut session = test_session(10)?;
+        run_test(&mut session, |mut client| async move {
+            client.startup().await?;
+            client
+                .send(&[
+                    FrontendMessage::Parse {
+                        name: "s1".to_string(),
+                        sql: "SELECT c2 FROM test WHERE c1 > $1".to_string(),
+                        param_types: vec![20],
+                    },
+                    FrontendMessage::Sync,
+                ])
+                .await?;
+            assert_eq!(
+                tags(&client.read_till_ready().await?),
+                vec![b'1', b'Z']
+            );
+
+            // an error skips messages until `Sync`.
+            client
+                .send(&[
+                    FrontendMessage::Parse {
+                        name: "s2".to_string(),
+                        sql: "SELECT c3 FROM test".to_string(),
+                        param_types: vec![],
+                    },
+                    FrontendMessage::Parse {
+                        name: "s3".to_string(),
+                        sql: "SELECT 1".to_string(),
+                        param_types: vec![],
+                    },
+                    FrontendMessage::Sync,
+                ])
+                .await?;
+            assert_eq!(
+                tags(&client.read_till_ready().await?),
+                vec![b'E', b'Z']
+            );
+            client.send(&[FrontendMessage::Terminate]).await?;
+            Ok(())
+        })
+        .await?;
+
+        let stmt = session.get_prepared_statement("s1").expect("prepared");
+        assert_eq!(stmt.desc().param_types, vec![ScalarType::Int64]);
+        assert_eq!(stmt.desc().arity(), 1);
+        assert!(session.get_prepared_statement("s2").is_none());
+        assert!(session.get_prepared_statement("s3").is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn unsupported_param_type() -> Result<()> {
+        let mut session = test_session(10)?;
+        run_test(&mut session, |mut client| async move {
+            client.startup().await?;
+            // 1083 is the OID of `time`.
+            client
+                .send(&[
+                    FrontendMessage::Parse {
+                        name: "s1".to_string(),
+                        sql: "SELECT c1 FROM test WHERE c1 > $1 AND c2 < $2"
+                            .to_string(),
+                        param_types: vec![20, 1083],
+                    },
+                    FrontendMessage::Bind {
+                        portal_name: "p1".to_string(),
+                        statement_name: "s1".to_string(),
+                        param_formats: vec![],
+                        params: vec![Some(b"1".to_vec()), Some(b"2".to_vec())],
+                        result_formats: vec![],
+                    },
+                    FrontendMessage::Sync,
+                ])
+                .await?;
+            let messages = client.read_till_ready().await?;
+            assert_eq!(tags(&messages), vec![b'E', b'Z']);
+            let fields = error_fields(&messages[0].1);
+            assert_eq!(fields[&b'S'], "ERROR");
+            assert_eq!(fields[&b'C'], "0A000");
+            assert_eq!(
+                fields[&b'M'],
+                "unsupported type OID 1083 of parameter $2"
+            );
+            client.send(&[FrontendMessage::Terminate]).await?;
+            Ok(())
+        })
+        .await?;
+
+        assert!(session.get_prepared_statement("s1").is_none());
+        assert!(session.get_portal("p1").is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn bind() -> Result<()> {
+        let mut session = test_session(10)?;
+        run_test(&mut session, |mut client| async move {
+            client.startup().await?;
+            client
+                .send(&[
+                    FrontendMessage::Parse {
+                        name: "s1".to_string(),
+                        sql: "SELECT c1 + $1, c2 + $2 FROM test".to_string(),
+                        param_types: vec![20, 20],
+                    },
+                    FrontendMessage::Bind {
+                        portal_name: "p1".to_string(),
+                        statement_name: "s1".to_string(),
+                        param_formats: vec![Format::Text, Format::Binary],
+                        params: vec![
+                            Some(b"5".to_vec()),
+                            Some(7i64.to_be_bytes().to_vec()),
+                        ],
+                        result_formats: vec![Format::Binary],
+                    },
+                    FrontendMessage::Sync,
+                ])
+                .await?;
+            assert_eq!(
+                tags(&client.read_till_ready().await?),
+                vec![b'1', b'2', b'Z']
+            );
+
+            // the number of parameters does not match.
+            client
+                .send(&[
+                    FrontendMessage::Bind {
+                        portal_name: "p2".to_string(),
+                        statement_name: "s1".to_string(),
+                        param_formats: vec![],
+                        params: vec![Some(b"5".to_vec())],
+                        result_formats: vec![],
+                    },
+                    FrontendMessage::Sync,
+                ])
+                .await?;
+            assert_eq!(
+                tags(&client.read_till_ready().await?),
+                vec![b'E', b'Z']
+            );
+            client.send(&[FrontendMessage::Terminate]).await?;
+            Ok(())
+        })
+        .await?;
+
+        let portal = session.get_portal("p1").expect("portal exists");
+        assert_eq!(portal.bound_params, vec![Datum::Int64(5), Datum::Int64(7)]);
+        assert_eq!(portal.result_formats, vec![Format::Binary, Format::Binary]);
+        assert!(session.get_portal("p2").is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn bind_declared_param_types() -> Result<()> {
+        let mut session = test_session(10)?;
+        run_test(&mut session, |mut client| async move {
+            client.startup().await?;
+            // the type of $3 is not declared and is inferred.
+            client
+                .send(&[
+                    FrontendMessage::Parse {
+                        name: "s1".to_string(),
+                        sql: "SELECT c1 + $1, c2 + $2 FROM test WHERE c1 < $3"
+                            .to_string(),
+                        param_types: vec![23, 21],
+                    },
+                    FrontendMessage::Describe {
+                        kind: ObjectKind::Statement,
+                        name: "s1".to_string(),
+                    },
+                    FrontendMessage::Bind {
+                        portal_name: "p1".to_string(),
+                        statement_name: "s1".to_string(),
+                        param_formats: vec![Format::Binary],
+                        params: vec![
+                            Some(5i32.to_be_bytes().to_vec()),
+                            Some((-7i16).to_be_bytes().to_vec()),
+                            Some(3i64.to_be_bytes().to_vec()),
+                        ],
+                        result_formats: vec![],
+                    },
+                    FrontendMessage::Sync,
+                ])
+                .await?;
+            let messages = client.read_till_ready().await?;
+            assert_eq!(tags(&messages), b"1tT2Z");
+
+            let mut params = messages[1].1.as_slice();
+            assert_eq!(params.get_i16(), 3);
+            assert_eq!(params.get_u32(), Type::Int4.oid());
+            assert_eq!(params.get_u32(), Type::Int2.oid());
+            assert_eq!(params.get_u32(), Type::Int8.oid());
+            client.send(&[FrontendMessage::Terminate]).await?;
+            Ok(())
+        })
+        .await?;
+
+        let portal = session.get_portal("p1").expect("portal exists");
+        assert_eq!(
+            portal.bound_params,
+            vec![Datum::Int64(5), Datum::Int64(-7), Datum::Int64(3)]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn update_with_params() -> Result<()> {
+        let mut session = test_session(10)?;
+        run_test(&mut session, |mut client| async move {
+            client.startup().await?;
+            // the types of the parameters are inferred from the columns.
+            client
+                .send(&[
+                    FrontendMessage::Parse {
+                        name: "s1".to_string(),
+                        sql: "UPDATE test SET c2 = $2 WHERE c1 = $1"
+                            .to_string(),
+                        param_types: vec![],
+                    },
+                    FrontendMessage::Describe {
+                        kind: ObjectKind::Statement,
+                        name: "s1".to_string(),
+                    },
+                    FrontendMessage::Bind {
+                        portal_name: "".to_string(),
+                        statement_name: "s1".to_string(),
+                        param_formats: vec![],
+                        params: vec![
+                            Some(b"1".to_vec()),
+                            Some(b"100".to_vec()),
+                        ],
+                        result_formats: vec![],
+                    },
+                    FrontendMessage::Execute {
+                        portal_name: "".to_string(),
+                        max_rows: 0,
+                    },
+                    FrontendMessage::Sync,
+                ])
+                .await?;
+            let messages = client.read_till_ready().await?;
+            assert_eq!(tags(&messages), b"1tn2CZ");
+            let mut params = messages[1].1.as_slice();
+            assert_eq!(params.get_i16(), 2);
+            assert_eq!(params.get_u32(), Type::Int8.oid());
+            assert_eq!(params.get_u32(), Type::Int8.oid());
+            assert_eq!(messages[4].1, b"UPDATE 1\0");
+
+            let messages =
+                client.query("SELECT c2 FROM test WHERE c1 < 3").await?;
+            assert_eq!(data_rows(&messages), [["0"], ["100"], ["20"]]);
+            client.send(&[FrontendMessage::Terminate]).await?;
+            Ok(())
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn unnamed_statement_and_portal() -> Result<()> {
+        let mut session = test_session(10)?;
+        run_test(&mut session, |mut client| async move {
+            client.startup().await?;
+            let mut messages = vec![];
+            let mut expected = vec![];
+            for i in 1..=3 {
+                messages.extend([
+                    FrontendMessage::Parse {
+                        name: "".to_string(),
+                        sql: "SELECT c1 FROM test WHERE c1 < $1".to_string(),
+                        param_types: vec![20],
+                    },
+                    FrontendMessage::Bind {
+                        portal_name: "".to_string(),
+                        statement_name: "".to_string(),
+                        param_formats: vec![],
+                        params: vec![Some(i.to_string().into_bytes())],
+                        result_formats: vec![],
+                    },
+                    FrontendMessage::Execute {
+                        portal_name: "".to_string(),
+                        max_rows: 0,
+                    },
+                ]);
+                expected.extend([b'1', b'2']);
+                expected.extend(vec![b'D'; i]);
+                expected.push(b'C');
+            }
+            messages.push(FrontendMessage::Sync);
+            expected.push(b'Z');
+            client.send(&messages).await?;
+            assert_eq!(tags(&client.read_till_ready().await?), expected);
+
+            // a failed bind closes the unnamed portal.
+            client
+                .send(&[
+                    FrontendMessage::Bind {
+                        portal_name: "".to_string(),
+                        statement_name: "".to_string(),
+                        param_formats: vec![],
+                        params: vec![],
+                        result_formats: vec![],
+                    },
+                    FrontendMessage::Sync,
+                    FrontendMessage::Execute {
+                        portal_name: "".to_string(),
+                        max_rows: 0,
+                    },
+                    FrontendMessage::Sync,
+                ])
+                .await?;
+            assert_eq!(
+                tags(&client.read_till_ready().await?),
+                vec![b'E', b'Z']
+            );
+            assert_eq!(
+                tags(&client.read_till_ready().await?),
+                vec![b'E', b'Z']
+            );
+            client.send(&[FrontendMessage::Terminate]).await?;
+            Ok(())
+        })
+        .await?;
+
+        assert!(session.get_prepared_statement("").is_some());
+        assert!(session.get_portal("").is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn execute_with_row_limit() -> Result<()> {
+        let mut session = test_session(100)?;
+        run_test(&mut session, |mut client| async move {
+            client.startup().await?;
+            let execute = FrontendMessage::Execute {
+                portal_name: "p1".to_string(),
+                max_rows: 30,
+            };
+            client
+                .send(&[
+                    FrontendMessage::Parse {
+                        name: "".to_string(),
+                        sql: "SELECT c1 FROM test".to_string(),
+                        param_types: vec![],
+                    },
+                    FrontendMessage::Bind {
+                        portal_name: "p1".to_string(),
+                        statement_name: "".to_string(),
+                        param_formats: vec![],
+                        params: vec![],
+                        result_formats: vec![],
+                    },
+                    execute.clone(),
+                    execute.clone(),
+                    execute.clone(),
+                    execute,
+                    FrontendMessage::Sync,
+                ])
+                .await?;
+            let messages = client.read_till_ready().await?;
+            let mut expected = vec![b'1', b'2'];
+            for _ in 0..3 {
+                expected.extend([b'D'; 30]);
+                expected.push(b's');
+            }
+            expected.extend([b'D'; 10]);
+            expected.extend(b"CZ");
+            assert_eq!(tags(&messages), expected);
+            assert_eq!(
+                data_rows(&messages),
+                (0..100).map(|i| vec![i.to_string()]).collect::<Vec<_>>()
+            );
+            let (_, tag) = &messages[messages.len() - 2];
+            assert_eq!(tag.as_slice(), b"SELECT 10\0");
+
+            client.send(&[FrontendMessage::Terminate]).await?;
+            Ok(())
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn describe() -> Result<()> {
+        let mut session = test_session(10)?;
+        run_test(&mut session, |mut client| async move {
+            client.startup().await?;
+            client
+                .send(&[
+                    FrontendMessage::Parse {
+                        name: "s1".to_string(),
+                        sql: "SELECT c2, c1 FROM test WHERE c1 > $1"
+                            .to_string(),
+                        param_types: vec![20],
+                    },
+                    FrontendMessage::Describe {
+                        kind: ObjectKind::Statement,
+                        name: "s1".to_string(),
+                    },
+                    FrontendMessage::Bind {
+                        portal_name: "p1".to_string(),
+                        statement_name: "s1".to_string(),
+                        param_formats: vec![],
+                        params: vec![Some(b"1".to_vec())],
+                        result_formats: vec![],
+                    },
+                    FrontendMessage::Describe {
+                        kind: ObjectKind::Portal,
+                        name: "p1".to_string(),
+                    },
+                    FrontendMessage::Parse {
+                        name: "s2".to_string(),
+                        sql: "BEGIN".to_string(),
+                        param_types: vec![],
+                    },
+                    FrontendMessage::Describe {
+                        kind: ObjectKind::Statement,
+                        name: "s2".to_string(),
+                    },
+                    FrontendMessage::Sync,
+                ])
+                .await?;
+            let messages = client.read_till_ready().await?;
+            assert_eq!(tags(&messages), b"1tT2T1tnZ");
+
+            let mut params = messages[1].1.as_slice();
+            assert_eq!(params.get_i16(), 1);
+            assert_eq!(params.get_u32(), 20);
+            assert_eq!(messages[6].1, 0_i16.to_be_bytes());
+
+            for (_, body) in [&messages[2], &messages[4]] {
+                let fields = row_fields(body);
+                assert_eq!(fields.len(), 2);
+                assert_eq!(fields[0].0, "c2");
+                assert!(fields.iter().all(|(_, oid)| *oid == 20));
+            }
+
+            // an unknown portal.
+            client
+                .send(&[
+                    FrontendMessage::Describe {
+                        kind: ObjectKind::Portal,
+                        name: "p2".to_string(),
+                    },
+                    FrontendMessage::Sync,
+                ])
+                .await?;
+            assert_eq!(tags(&client.read_till_ready().await?), b"EZ");
+            client.send(&[FrontendMessage::Terminate]).await?;
+            Ok(())
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn describe_inferred_parameters() -> Result<()> {
+        let mut session = test_session(10)?;
+        run_test(&mut session, |mut client| async move {
+            client.startup().await?;
+            // the integer literals are int8, unlike the int4 of
+            // PostgreSQL, and so is the parameter added to one. In `s2`,
+            // the `$1` typed by the `WHERE` clause is also known to the
+            // projection, which is analyzed in another context.
+            for (name, sql) in [
+                ("s1", "SELECT $1 + 1"),
+                ("s2", "SELECT $1 FROM test WHERE c1 < $1"),
+            ] {
+                client
+                    .send(&[
+                        FrontendMessage::Parse {
+                            name: name.to_string(),
+                            sql: sql.to_string(),
+                            param_types: vec![],
+                        },
+                        FrontendMessage::Describe {
+                            kind: ObjectKind::Statement,
+                            name: name.to_string(),
+                        },
+                        FrontendMessage::Sync,
+                    ])
+                    .await?;
+                let messages = client.read_till_ready().await?;
+                assert_eq!(tags(&messages), b"1tTZ", "{sql}");
+
+                let mut params = messages[1].1.as_slice();
+                assert_eq!(params.get_i16(), 1);
+                assert_eq!(params.get_u32(), Type::Int8.oid());
+            }
+            client.send(&[FrontendMessage::Terminate]).await?;
+            Ok(())
+        })
+        .await?;
+
+        for name in ["s1", "s2"] {
+            let stmt = session.get_prepared_statement(name).expect("prepared");
+            assert_eq!(stmt.desc().param_types, vec![ScalarType::Int64]);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn binary_results() -> Result<()> {
+        let mut session = test_session(3)?;
+        run_test(&mut session, |mut client| async move {
+            client.startup().await?;
+            client
+                .send(&[
+                    FrontendMessage::Parse {
+                        name: "".to_string(),
+                        sql: "SELECT c1, c2 FROM test".to_string(),
+                        param_types: vec![],
+                    },
+                    FrontendMessage::Bind {
+                        portal_name: "".to_string(),
+                        statement_name: "".to_string(),
+                        param_formats: vec![],
+                        params: vec![],
+                        result_formats: vec![Format::Text, Format::Binary],
+                    },
+                    FrontendMessage::Describe {
+                        kind: ObjectKind::Portal,
+                        name: "".to_string(),
+                    },
+                    FrontendMessage::Execute {
+                        portal_name: "".to_string(),
+                        max_rows: 0,
+                    },
+                    FrontendMessage::Sync,
+                ])
+                .await?;
+            let messages = client.read_till_ready().await?;
+            assert_eq!(tags(&messages), b"12TDDDCZ");
+
+            // the format codes of the fields.
+            let fields = &messages[2].1;
+            assert_eq!(fields[fields.len() - 2..], 1_i16.to_be_bytes());
+
+            let (_, row) = &messages[4];
+            let mut buf = row.as_slice();
+            assert_eq!(buf.get_i16(), 2);
+            assert_eq!(buf.get_i32(), 1);
+            assert_eq!(buf.get_u8(), b'1');
+            assert_eq!(buf.get_i32(), 8);
+            assert_eq!(buf.get_i64(), 10);
+            client.send(&[FrontendMessage::Terminate]).await?;
+            Ok(())
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn numeric() -> Result<()> {
+        let mut session = test_session(1)?;
+        run_test(&mut session, |mut client| async move {
+            client.startup().await?;
+            client
+                .send(&[FrontendMessage::Query {
+                    sql: "SELECT 1.25 + 1, 0.50".to_string(),
+                }])
+                .await?;
+            let messages = client.read_till_ready().await?;
+            assert_eq!(tags(&messages), b"TDCZ");
+            assert_eq!(
+                row_fields(&messages[0].1)
+                    .into_iter()
+                    .map(|(_, oid)| oid)
+                    .collect::<Vec<_>>(),
+                vec![1700, 1700]
+            );
+            assert_eq!(data_rows(&messages), vec![vec!["2.25", "0.50"]]);
+            client.send(&[FrontendMessage::Terminate]).await?;
+            Ok(())
+        })
+        .await
+    }
+}
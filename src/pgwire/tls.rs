@@ -0,0 +1,177 @@
+//! TLS support of the server, the encryption itself is provided by
+//! an implementation of [`TlsAcceptor`], eg [`RustlsAcceptor`].
+
+use crate::common::error::{FloppyError, Result};
+use async_trait::async_trait;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_rustls::rustls::{Certificate, PrivateKey, ServerConfig};
+
+/// A bidirectional stream of a client connection.
+pub trait AsyncStream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<S> AsyncStream for S where S: AsyncRead + AsyncWrite + Unpin + Send {}
+
+/// Performs the server side TLS handshake.
+#[async_trait]
+pub trait TlsAcceptor: Send + Sync {
+    /// Upgrade the cleartext `stream` to an encrypted one.
+    async fn accept(
+        &self,
+        stream: Box<dyn AsyncStream>,
+    ) -> Result<Box<dyn AsyncStream>>;
+}
+
+/// A [`TlsAcceptor`] based on `tokio-rustls`, it presents a single
+/// certificate chain and does not authenticate the clients.
+pub struct RustlsAcceptor {
+    acceptor: tokio_rustls::TlsAcceptor,
+}
+
+impl RustlsAcceptor {
+    /// Create an acceptor from the PEM encoded certificate chain `cert`,
+    /// the server's certificate comes first, and its private `key` in
+    /// PKCS #8, PKCS #1 or SEC1.
+    pub fn from_pem(cert: &[u8], key: &[u8]) -> Result<Self> {
+        let certs = rustls_pemfile::certs(&mut &cert[..])?
+            .into_iter()
+            .map(Certificate)
+            .collect::<Vec<_>>();
+        if certs.is_empty() {
+            return Err(FloppyError::External(
+                "no certificate found in PEM".to_string(),
+            ));
+        }
+        let key = rustls_pemfile::read_all(&mut &key[..])?
+            .into_iter()
+            .find_map(|item| match item {
+                rustls_pemfile::Item::PKCS8Key(key)
+                | rustls_pemfile::Item::RSAKey(key)
+                | rustls_pemfile::Item::ECKey(key) => Some(PrivateKey(key)),
+                _ => None,
+            })
+            .ok_or_else(|| {
+                FloppyError::External("no private key found in PEM".to_string())
+            })?;
+        let config = ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_single_cert(certs, key)?;
+        Ok(Self {
+            acceptor: tokio_rustls::TlsAcceptor::from(Arc::new(config)),
+        })
+    }
+
+    /// Same as [`RustlsAcceptor::from_pem`], but the certificate chain
+    /// and the key are read from the files at `cert_path` and
+    /// `key_path`.
+    pub fn from_pem_files<P: AsRef<Path>>(
+        cert_path: P,
+        key_path: P,
+    ) -> Result<Self> {
+        let cert = std::fs::read(cert_path)?;
+        let key = std::fs::read(key_path)?;
+        Self::from_pem(&cert, &key)
+    }
+}
+
+#[async_trait]
+impl TlsAcceptor for RustlsAcceptor {
+    async fn accept(
+        &self,
+        stream: Box<dyn AsyncStream>,
+    ) -> Result<Box<dyn AsyncStream>> {
+        let stream = self.acceptor.accept(stream).await?;
+        Ok(Box::new(stream))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_rustls::rustls::{ClientConfig, RootCertStore, ServerName};
+    use tokio_rustls::TlsConnector;
+
+    /// Returns an acceptor with a self-signed certificate for
+    /// `localhost`, and a connector which trusts it.
+    pub(crate) fn test_tls() -> Result<(RustlsAcceptor, TlsConnector)> {
+        let cert =
+            rcgen::generate_simple_self_signed(vec!["localhost".to_string()])
+                .map_err(|e| FloppyError::External(e.to_string()))?;
+        let cert_pem = cert
+            .serialize_pem()
+            .map_err(|e| FloppyError::External(e.to_string()))?;
+        let acceptor = RustlsAcceptor::from_pem(
+            cert_pem.as_bytes(),
+            cert.serialize_private_key_pem().as_bytes(),
+        )?;
+
+        let der = cert
+            .serialize_der()
+            .map_err(|e| FloppyError::External(e.to_string()))?;
+        let mut roots = RootCertStore::empty();
+        roots
+            .add(&Certificate(der))
+            .map_err(|e| FloppyError::External(e.to_string()))?;
+        let config = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        Ok((acceptor, TlsConnector::from(Arc::new(config))))
+    }
+
+    /// Connect to the server over TLS at the other end of `stream`.
+    pub(crate) async fn connect<S>(
+        connector: &TlsConnector,
+        stream: S,
+    ) -> Result<Box<dyn AsyncStream>>
+    where
+        S: AsyncStream + 'static,
+    {
+        let name = ServerName::try_from("localhost").unwrap();
+        Ok(Box::new(connector.connect(name, stream).await?))
+    }
+
+    #[tokio::test]
+    async fn handshake() -> Result<()> {
+        let (acceptor, connector) = test_tls()?;
+        let (client, server) = tokio::io::duplex(1 << 16);
+        let (server, client) = tokio::join!(
+            async {
+                let mut stream = acceptor.accept(Box::new(server)).await?;
+                let mut buf = [0; 4];
+                stream.read_exact(&mut buf).await?;
+                stream.write_all(&buf).await?;
+                stream.flush().await?;
+                Ok::<_, FloppyError>(buf)
+            },
+            async {
+                let mut stream = connect(&connector, client).await?;
+                stream.write_all(b"ping").await?;
+                stream.flush().await?;
+                let mut buf = [0; 4];
+                stream.read_exact(&mut buf).await?;
+                Ok::<_, FloppyError>(buf)
+            }
+        );
+        assert_eq!(&server?, b"ping");
+        assert_eq!(&client?, b"ping");
+
+        // a client which does not trust the certificate fails the
+        // handshake.
+        let (_, other) = test_tls()?;
+        let (client, server) = tokio::io::duplex(1 << 16);
+        let (server, client) = tokio::join!(
+            acceptor.accept(Box::new(server)),
+            connect(&other, client)
+        );
+        assert!(client.is_err());
+        assert!(server.is_err());
+
+        // a key is required.
+        assert!(RustlsAcceptor::from_pem(b"", b"").is_err());
+        Ok(())
+    }
+}
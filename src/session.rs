@@ -1,18 +1,62 @@
+use crate::catalog::names::{FullObjectName, PartialObjectName};
 use crate::catalog::CatalogStore;
-use crate::common::error::Result;
-use crate::common::relation::StatementDesc;
+use crate::common::error::{
+    unique_violation, CatalogError, FloppyError, Result,
+};
+use crate::common::relation::{
+    ColumnType, GlobalId, IndexRange, RelationDesc, Row, StatementDesc,
+};
+use crate::common::scalar::{Datum, ScalarType};
+use crate::pgrepr::{copy, Format, Type};
+use crate::sql::context::{ExecutionContext, StatementContext};
+use crate::sql::physical_plan::RowStream;
+use crate::sql::{analyzer, planner};
+use crate::storage::txn::{TxnTableStore, Writes};
 use crate::storage::TableStore;
-use sqlparser::ast::Statement;
-use std::collections::HashMap;
+use futures::StreamExt;
+use sqlparser::ast::{
+    CloseCursor, CopyTarget, DataType, Expr as SqlExpr, FetchDirection, Ident,
+    ObjectName, ObjectType, Query, SetVariableValue, Statement,
+    Value as SqlValue,
+};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::ops::Bound;
 use std::sync::Arc;
 
+/// The run-time parameters with their default values. The reported
+/// ones are sent to the client after authentication and whenever
+/// they are changed, like PostgreSQL's `GUC_REPORT`.
+const DEFAULT_VARS: &[(&str, &str, bool)] = &[
+    ("server_version", "14.0", true),
+    ("server_encoding", "UTF8", true),
+    ("client_encoding", "UTF8", true),
+    ("DateStyle", "ISO, MDY", true),
+    ("integer_datetimes", "on", true),
+    ("standard_conforming_strings", "on", true),
+    ("search_path", "public", false),
+];
+
 /// A session to the database state.
 #[derive(Debug)]
 pub struct Session {
     conn_id: u32,
+    /// The key to authenticate the cancel requests of the session.
+    secret_key: u32,
     catalog_store: Arc<dyn CatalogStore>,
     table_store: Arc<dyn TableStore>,
     prepared_statements: HashMap<String, PreparedStatement>,
+    /// Portals created by binding parameters to prepared statements,
+    /// keyed by their names.
+    portals: HashMap<String, Portal>,
+    transaction: TransactionState,
+    /// Cursors declared by `DECLARE`, keyed by their names.
+    cursors: HashMap<String, Cursor>,
+    /// The run-time parameters, keyed by their lowercase names.
+    vars: HashMap<String, Var>,
+    /// Notices raised by the statements, until they are sent to the
+    /// client.
+    notices: Vec<Notice>,
 }
 
 impl Session {
@@ -20,13 +64,1390 @@ impl Session {
         todo!()
     }
 
-    pub fn prepare(_sql: &str) -> Result<PreparedStatement> {
-        todo!()
+    pub fn new(
+        conn_id: u32,
+        catalog_store: Arc<dyn CatalogStore>,
+        table_store: Arc<dyn TableStore>,
+    ) -> Self {
+        Self {
+            conn_id,
+            secret_key: rand::random(),
+            catalog_store,
+            table_store,
+            prepared_statements: HashMap::new(),
+            portals: HashMap::new(),
+            transaction: TransactionState::Default,
+            cursors: HashMap::new(),
+            vars: DEFAULT_VARS
+                .iter()
+                .map(|(name, value, _)| {
+                    let var = Var {
+                        name: name.to_string(),
+                        value: value.to_string(),
+                    };
+                    (name.to_lowercase(), var)
+                })
+                .collect(),
+            notices: vec![],
+        }
+    }
+
+    pub fn conn_id(&self) -> u32 {
+        self.conn_id
+    }
+
+    pub fn secret_key(&self) -> u32 {
+        self.secret_key
+    }
+
+    /// The value of the run-time parameter `name`.
+    pub fn var(&self, name: &str) -> Option<&str> {
+        self.vars
+            .get(&name.to_lowercase())
+            .map(|var| var.value.as_str())
+    }
+
+    /// Set the run-time parameter `name` to `value`. Like PostgreSQL,
+    /// unknown parameters are accepted as custom ones.
+    pub fn set_var(&mut self, name: &str, value: String) {
+        let var = self.vars.entry(name.to_lowercase()).or_insert(Var {
+            name: name.to_string(),
+            value: String::new(),
+        });
+        var.value = value;
     }
 
-    pub fn execute(_sql: &str) -> Result<()> {
+    /// Reset the run-time parameter `name` to its default value, a
+    /// custom parameter is reset to an empty string.
+    pub fn reset_var(&mut self, name: &str) {
+        match DEFAULT_VARS
+            .iter()
+            .find(|(n, _, _)| n.eq_ignore_ascii_case(name))
+        {
+            Some((_, value, _)) => self.set_var(name, value.to_string()),
+            None => self.set_var(name, String::new()),
+        }
+    }
+
+    /// The run-time parameters reported to the client, with their
+    /// current values.
+    pub fn reported_vars(&self) -> Vec<(String, String)> {
+        DEFAULT_VARS
+            .iter()
+            .filter(|(_, _, reported)| *reported)
+            .map(|(name, _, _)| {
+                (name.to_string(), self.var(name).unwrap().to_string())
+            })
+            .collect()
+    }
+
+    /// End the session after the client disconnects. The open
+    /// transaction is rolled back with its cursors, and the notices
+    /// not sent are dropped.
+    pub fn terminate(&mut self) {
+        self.rollback_txn();
+        self.notices.clear();
+    }
+
+    /// Queue `notice` to be sent to the client.
+    pub fn add_notice(&mut self, notice: Notice) {
+        self.notices.push(notice);
+    }
+
+    /// Remove and return the queued notices.
+    pub fn drain_notices(&mut self) -> Vec<Notice> {
+        std::mem::take(&mut self.notices)
+    }
+
+    /// Create a prepared statement named `name`, it replaces the
+    /// existing one with the same name. `param_types` are the types of
+    /// the parameters specified by the client, `None` means the type
+    /// should be inferred.
+    /// The unnamed statement is closed even if the new one fails to be
+    /// prepared.
+    pub fn prepare(
+        &mut self,
+        name: String,
+        sql: &str,
+        param_types: &[Option<Type>],
+    ) -> Result<()> {
+        if name.is_empty() {
+            self.prepared_statements.remove(&name);
+        }
+        let mut statements = planner::parse(sql)?;
+        if statements.len() > 1 {
+            return Err(FloppyError::Plan(
+                "cannot insert multiple commands into a prepared statement"
+                    .to_string(),
+            ));
+        }
+        self.prepare_statement(name, statements.pop(), param_types)
+    }
+
+    fn prepare_statement(
+        &mut self,
+        name: String,
+        stmt: Option<Statement>,
+        param_types: &[Option<Type>],
+    ) -> Result<()> {
+        let desc = match &stmt {
+            // the types of the parameters not declared are inferred by
+            // planning the statement.
+            Some(stmt @ (Statement::Query(_) | Statement::Update { .. })) => {
+                let scx = self.statement_context();
+                for (i, ty) in param_types.iter().enumerate() {
+                    if let Some(ty) = ty {
+                        scx.param_types
+                            .borrow_mut()
+                            .insert(i + 1, ty.scalar_type());
+                    }
+                }
+                let (_, desc) = planner::plan_statement(&scx, stmt)?;
+                desc
+            }
+            _ => StatementDesc {
+                rel_desc: None,
+                param_types: param_types
+                    .iter()
+                    .flatten()
+                    .map(Type::scalar_type)
+                    .collect(),
+            },
+        };
+        // a parameter declared as int4 is an int64 in the plan, but the
+        // client still sends it as an int4.
+        let wire_types = desc
+            .param_types
+            .iter()
+            .enumerate()
+            .map(|(i, ty)| {
+                param_types
+                    .get(i)
+                    .copied()
+                    .flatten()
+                    .unwrap_or_else(|| Type::from(ty))
+            })
+            .collect();
+        self.prepared_statements.insert(
+            name,
+            PreparedStatement {
+                stmt,
+                desc,
+                param_types: wire_types,
+            },
+        );
+        Ok(())
+    }
+
+    pub fn get_prepared_statement(
+        &self,
+        name: &str,
+    ) -> Option<&PreparedStatement> {
+        self.prepared_statements.get(name)
+    }
+
+    /// Create a portal named `portal_name` by binding `bound_params` to
+    /// the prepared statement `statement_name`.
+    /// The unnamed portal is replaced, it is closed even if the new one
+    /// fails to be declared, but it is an error to redeclare a named
+    /// portal.
+    pub fn declare_portal(
+        &mut self,
+        portal_name: String,
+        statement_name: &str,
+        bound_params: Vec<Datum>,
+        result_formats: Vec<Format>,
+    ) -> Result<()> {
+        if portal_name.is_empty() {
+            self.close_portal(&portal_name);
+        }
+        let stmt =
+            self.prepared_statements
+                .get(statement_name)
+                .ok_or_else(|| {
+                    FloppyError::Plan(format!(
+                    "prepared statement \"{statement_name}\" does not exist"
+                ))
+                })?;
+        self.check_not_aborted(&stmt.stmt)?;
+        if !portal_name.is_empty() && self.portals.contains_key(&portal_name) {
+            return Err(FloppyError::Plan(format!(
+                "portal \"{portal_name}\" already exists"
+            )));
+        }
+        let param_count = stmt.desc.param_types.len();
+        if bound_params.len() != param_count {
+            return Err(FloppyError::Plan(format!(
+                "bind message supplies {} parameters, but prepared \
+                 statement \"{statement_name}\" requires {param_count}",
+                bound_params.len()
+            )));
+        }
+
+        let portal = Portal {
+            stmt: stmt.stmt.clone(),
+            desc: stmt.desc.clone(),
+            bound_params,
+            result_formats,
+            state: PortalState::NotStarted,
+        };
+        self.portals.insert(portal_name, portal);
         Ok(())
     }
+
+    pub fn get_portal(&self, name: &str) -> Option<&Portal> {
+        self.portals.get(name)
+    }
+
+    /// Remove the portal `name` if it exists.
+    pub fn close_portal(&mut self, name: &str) {
+        self.portals.remove(name);
+    }
+
+    /// Execute a single statement.
+    pub async fn execute(&mut self, sql: &str) -> Result<ExecuteResponse> {
+        let mut statements = planner::parse(sql)?;
+        if statements.len() != 1 {
+            return Err(FloppyError::NotImplemented(format!(
+                "expect exactly one statement, got {}",
+                statements.len()
+            )));
+        }
+        self.execute_statement(statements.remove(0)).await
+    }
+
+    /// Parse the statements of a simple query, which are executed by
+    /// [`Session::execute_statement`] one by one. More than one
+    /// statements run in an implicit transaction, it is started here
+    /// and ended by [`Session::end_batch`]. An empty or blank query
+    /// has no statements.
+    pub fn start_batch(&mut self, sql: &str) -> Result<Vec<Statement>> {
+        let statements = planner::parse(sql)?;
+        if statements.len() > 1
+            && matches!(self.transaction, TransactionState::Default)
+        {
+            self.start_txn(TransactionKind::Implicit)?;
+        }
+        Ok(statements)
+    }
+
+    /// End a simple query with the `result` of its statements. The
+    /// implicit transaction is committed if they succeeded, otherwise
+    /// it is rolled back.
+    pub fn end_batch(&mut self, result: Result<()>) -> Result<()> {
+        if let TransactionState::InTransactionImplicit(_) = self.transaction {
+            match &result {
+                Ok(()) => self.commit_txn()?,
+                Err(_) => self.rollback_txn(),
+            }
+        }
+        result
+    }
+
+    pub async fn execute_statement(
+        &mut self,
+        statement: Statement,
+    ) -> Result<ExecuteResponse> {
+        self.execute_bound(statement, &[], &[]).await
+    }
+
+    /// Execute `statement` whose parameters `$1..$n` are bound to
+    /// `params` of `param_types`, eg the statement of a portal.
+    async fn execute_bound(
+        &mut self,
+        statement: Statement,
+        param_types: &[ScalarType],
+        params: &[Datum],
+    ) -> Result<ExecuteResponse> {
+        match statement {
+            Statement::StartTransaction { .. } => {
+                if let TransactionState::InTransaction(_) = self.transaction {
+                    self.add_notice(Notice::warning(
+                        "25001",
+                        "there is already a transaction in progress",
+                    ));
+                }
+                self.start_txn(TransactionKind::Explicit)?;
+                Ok(ExecuteResponse::StartedTransaction)
+            }
+            Statement::Commit { .. } => {
+                self.warn_no_transaction();
+                self.commit_txn()?;
+                Ok(ExecuteResponse::TransactionExited { commit: true })
+            }
+            Statement::Rollback { .. } => {
+                self.warn_no_transaction();
+                self.rollback_txn();
+                Ok(ExecuteResponse::TransactionExited { commit: false })
+            }
+            statement => {
+                if let TransactionState::Failed(_) = self.transaction {
+                    return Err(FloppyError::TransactionAborted);
+                }
+                // a statement outside of a transaction block is
+                // committed when it completes.
+                let auto_commit =
+                    matches!(self.transaction, TransactionState::Default);
+                if auto_commit {
+                    self.start_txn(TransactionKind::Single)?;
+                }
+                let result = self
+                    .execute_in_transaction(statement, param_types, params)
+                    .await;
+                match &result {
+                    Ok(_) if auto_commit => self.commit_txn()?,
+                    Ok(_) => {}
+                    Err(_) => self.fail_txn(),
+                }
+                result
+            }
+        }
+    }
+
+    async fn execute_in_transaction(
+        &mut self,
+        statement: Statement,
+        param_types: &[ScalarType],
+        params: &[Datum],
+    ) -> Result<ExecuteResponse> {
+        match statement {
+            Statement::Savepoint { name } => {
+                self.declare_savepoint(name.value)?;
+                Ok(ExecuteResponse::CreatedSavepoint)
+            }
+            Statement::SetVariable {
+                local,
+                hivevar,
+                variable,
+                value,
+            } => {
+                if local {
+                    return Err(FloppyError::NotImplemented(
+                        "SET LOCAL".to_string(),
+                    ));
+                }
+                if hivevar {
+                    return Err(FloppyError::NotImplemented(
+                        "SET HIVEVAR".to_string(),
+                    ));
+                }
+                self.set(&variable, &value)
+            }
+            Statement::ShowVariable { variable } => self.show(&variable),
+            Statement::Declare {
+                name, hold, query, ..
+            } => self.declare(name.value, hold, *query),
+            Statement::Fetch {
+                name,
+                direction,
+                into,
+            } => {
+                if into.is_some() {
+                    return Err(FloppyError::NotImplemented(
+                        "FETCH INTO".to_string(),
+                    ));
+                }
+                self.fetch(&name.value, direction).await
+            }
+            Statement::Close { cursor } => self.close(cursor),
+            Statement::Prepare {
+                name,
+                data_types,
+                statement,
+            } => self.prepare_sql(name.value, &data_types, *statement),
+            Statement::Execute { name, parameters } => {
+                self.execute_prepared(&name.value, &parameters).await
+            }
+            Statement::Deallocate { name, .. } => self.deallocate(&name.value),
+            Statement::Drop {
+                object_type: ObjectType::Table,
+                if_exists,
+                names,
+                ..
+            } => self.drop_tables(&names, if_exists),
+            Statement::Copy {
+                table_name,
+                columns,
+                to,
+                target,
+                options,
+                legacy_options,
+                values,
+            } => {
+                if !options.is_empty() || !legacy_options.is_empty() {
+                    return Err(FloppyError::NotImplemented(
+                        "COPY options".to_string(),
+                    ));
+                }
+                match (to, target) {
+                    (false, CopyTarget::Stdin) => {
+                        if !values.is_empty() {
+                            return Err(FloppyError::NotImplemented(
+                                "COPY FROM STDIN with inline data".to_string(),
+                            ));
+                        }
+                        self.copy_in(&table_name, &columns)
+                    }
+                    (true, CopyTarget::Stdout) => {
+                        self.copy_out(&table_name, &columns).await
+                    }
+                    (true, target) => Err(FloppyError::NotImplemented(
+                        format!("COPY TO {target}"),
+                    )),
+                    (false, target) => Err(FloppyError::NotImplemented(
+                        format!("COPY FROM {target}"),
+                    )),
+                }
+            }
+            statement @ Statement::Update { .. } => {
+                let scx = self.statement_context();
+                bind_params(&scx, param_types, params);
+                let (_, stream) = self.stream(&scx, &statement)?;
+                let rows = stream.collect::<Vec<Result<Row>>>().await;
+                let rows = rows.into_iter().collect::<Result<Vec<Row>>>()?;
+                Ok(ExecuteResponse::Updated(rows.len()))
+            }
+            statement => {
+                let scx = self.statement_context();
+                bind_params(&scx, param_types, params);
+                let (desc, stream) = self.stream(&scx, &statement)?;
+                let rows = stream.collect::<Vec<Result<Row>>>().await;
+                Ok(ExecuteResponse::Rows {
+                    desc,
+                    rows: rows.into_iter().collect::<Result<Vec<Row>>>()?,
+                })
+            }
+        }
+    }
+
+    /// Execute the portal `name`, at most `max_rows` rows are returned
+    /// if it is not `None`.
+    /// A portal that produces rows is suspended after returning
+    /// `max_rows` rows, executing it again resumes it. Like a statement
+    /// of a simple query, an error fails the transaction.
+    pub async fn execute_portal(
+        &mut self,
+        name: &str,
+        max_rows: Option<usize>,
+    ) -> Result<ExecuteResponse> {
+        let result = self.execute_portal_rows(name, max_rows).await;
+        if result.is_err() {
+            self.fail_txn();
+        }
+        result
+    }
+
+    async fn execute_portal_rows(
+        &mut self,
+        name: &str,
+        max_rows: Option<usize>,
+    ) -> Result<ExecuteResponse> {
+        let portal = self.portals.get(name).ok_or_else(|| {
+            FloppyError::Plan(format!("portal \"{name}\" does not exist"))
+        })?;
+        self.check_not_aborted(&portal.stmt)?;
+        if let PortalState::NotStarted = portal.state {
+            match portal.stmt.clone() {
+                Some(stmt @ Statement::Query(_)) => {
+                    let scx = self.statement_context();
+                    bind_params(
+                        &scx,
+                        &portal.desc.param_types,
+                        &portal.bound_params,
+                    );
+                    let (_, stream) = self.stream(&scx, &stmt)?;
+                    let portal = self.portals.get_mut(name).unwrap();
+                    portal.state = PortalState::InProgress(stream);
+                }
+                Some(stmt) => {
+                    let param_types = portal.desc.param_types.clone();
+                    let params = portal.bound_params.clone();
+                    self.portals.get_mut(name).unwrap().state =
+                        PortalState::Completed;
+                    return self
+                        .execute_bound(stmt, &param_types, &params)
+                        .await;
+                }
+                None => {
+                    self.portals.get_mut(name).unwrap().state =
+                        PortalState::Completed;
+                    return Ok(ExecuteResponse::EmptyQuery);
+                }
+            }
+        }
+
+        let portal = self.portals.get_mut(name).unwrap();
+        let desc = portal.desc.rel_desc.clone().unwrap_or_default();
+        let stream = match &mut portal.state {
+            PortalState::InProgress(stream) => stream,
+            // a completed portal returns no more rows.
+            _ => {
+                return Ok(ExecuteResponse::Rows { desc, rows: vec![] });
+            }
+        };
+
+        let mut rows = vec![];
+        while max_rows != Some(rows.len()) {
+            match stream.next().await {
+                Some(row) => rows.push(row?),
+                None => {
+                    portal.state = PortalState::Completed;
+                    return Ok(ExecuteResponse::Rows { desc, rows });
+                }
+            }
+        }
+        Ok(ExecuteResponse::PortalSuspended { rows })
+    }
+
+    /// Returns [`FloppyError::TransactionAborted`] if the transaction
+    /// is failed, unless `stmt` ends it by `COMMIT` or `ROLLBACK`.
+    fn check_not_aborted(&self, stmt: &Option<Statement>) -> Result<()> {
+        match (&self.transaction, stmt) {
+            (
+                TransactionState::Failed(_),
+                Some(Statement::Commit { .. } | Statement::Rollback { .. }),
+            ) => Ok(()),
+            (TransactionState::Failed(_), _) => {
+                Err(FloppyError::TransactionAborted)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Insert the rows in `data` of `COPY FROM STDIN`. All the rows
+    /// are decoded before any of them is inserted.
+    pub fn copy_from(
+        &mut self,
+        copy_in: &CopyIn,
+        data: &[u8],
+    ) -> Result<ExecuteResponse> {
+        let result = self.insert_copied_rows(copy_in, data);
+        if result.is_err() {
+            self.fail_txn();
+        }
+        result
+    }
+
+    /// Abort `COPY FROM STDIN` after the client sends `CopyFail`
+    /// with `message`.
+    pub fn fail_copy(&mut self, message: String) -> FloppyError {
+        self.fail_txn();
+        FloppyError::CopyFailed(message)
+    }
+
+    fn insert_copied_rows(
+        &self,
+        copy_in: &CopyIn,
+        data: &[u8],
+    ) -> Result<ExecuteResponse> {
+        let rel_desc = &copy_in.rel_desc;
+        let types = copy_in
+            .columns
+            .iter()
+            .map(|i| Ok(Type::from(&rel_desc.column_type(*i)?.scalar_type)))
+            .collect::<Result<Vec<_>>>()?;
+        let rows = copy::decode_text(data, &types)?
+            .into_iter()
+            .map(|datums| {
+                // the columns not copied have their default values.
+                let mut row = (0..rel_desc.column_types().len())
+                    .map(|i| rel_desc.default_value(i))
+                    .collect::<Vec<_>>();
+                for (i, datum) in copy_in.columns.iter().zip(datums) {
+                    row[*i] = datum;
+                }
+                let row = Row::new(row);
+                rel_desc.check_not_null(&row)?;
+                Ok(row)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let table_store = self.table_store();
+        // the rows overwrite the ones of the same keys when they are
+        // inserted, so the keys are checked before any row is written.
+        let mut keys = BTreeSet::new();
+        for row in &rows {
+            let key = row.prim_key_datums(rel_desc)?;
+            let range = IndexRange {
+                lo: Bound::Included(key.clone()),
+                hi: Bound::Included(key.clone()),
+            };
+            let exists = table_store
+                .primary_index_range(&copy_in.table_id, &range, None)?
+                .next()
+                .transpose()?
+                .is_some();
+            if exists || !keys.insert(key.clone()) {
+                return Err(unique_violation(&key));
+            }
+        }
+        for row in &rows {
+            table_store.insert(&copy_in.table_id, row)?;
+        }
+        Ok(ExecuteResponse::Copied(rows.len()))
+    }
+
+    /// Start `COPY FROM STDIN` into `columns` of the table
+    /// `table_name`, or all its columns if `columns` is empty.
+    fn copy_in(
+        &self,
+        table_name: &ObjectName,
+        columns: &[Ident],
+    ) -> Result<ExecuteResponse> {
+        let scx = self.statement_context();
+        let partial_name: PartialObjectName = table_name.try_into()?;
+        let item = scx.catalog.resolve_item(&partial_name, &scx.search_path)?;
+        let full_name: FullObjectName = partial_name.into();
+        if item.system_rows().is_some() {
+            return Err(FloppyError::Plan(format!(
+                "cannot copy to system table \"{}\"",
+                full_name.item
+            )));
+        }
+        let rel_desc = item.desc(&full_name)?.into_owned();
+        let columns = if columns.is_empty() {
+            (0..rel_desc.column_types().len()).collect()
+        } else {
+            let mut ids = vec![];
+            for column in columns {
+                let id = rel_desc.column_idx(&column.value)?;
+                if ids.contains(&id) {
+                    return Err(FloppyError::Plan(format!(
+                        "column \"{}\" specified more than once",
+                        column.value
+                    )));
+                }
+                ids.push(id);
+            }
+            ids
+        };
+        Ok(ExecuteResponse::CopyIn(CopyIn {
+            table_id: item.id(),
+            rel_desc,
+            columns,
+        }))
+    }
+
+    /// Create a prepared statement by `PREPARE`, unlike the
+    /// protocol's `Parse` it is an error if `name` exists.
+    fn prepare_sql(
+        &mut self,
+        name: String,
+        data_types: &[DataType],
+        statement: Statement,
+    ) -> Result<ExecuteResponse> {
+        if self.prepared_statements.contains_key(&name) {
+            return Err(FloppyError::Plan(format!(
+                "prepared statement \"{name}\" already exists"
+            )));
+        }
+        if !matches!(statement, Statement::Query(_)) {
+            return Err(FloppyError::NotImplemented(format!(
+                "PREPARE {statement}"
+            )));
+        }
+        let param_types = data_types
+            .iter()
+            .map(|ty| {
+                analyzer::transform_data_type(ty)
+                    .map(|ty| Some(Type::from(&ty)))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        self.prepare_statement(name, Some(statement), &param_types)?;
+        Ok(ExecuteResponse::Prepared)
+    }
+
+    /// Execute the prepared statement `name` by `EXECUTE` with
+    /// `params`.
+    async fn execute_prepared(
+        &mut self,
+        name: &str,
+        params: &[SqlExpr],
+    ) -> Result<ExecuteResponse> {
+        let stmt = self.prepared_statements.get(name).ok_or_else(|| {
+            FloppyError::Plan(format!(
+                "prepared statement \"{name}\" does not exist"
+            ))
+        })?;
+        let param_types = &stmt.desc.param_types;
+        if params.len() != param_types.len() {
+            return Err(FloppyError::Plan(format!(
+                "wrong number of parameters for prepared statement \
+                 \"{name}\", expected {} parameters but got {}",
+                param_types.len(),
+                params.len()
+            )));
+        }
+        let scx = self.statement_context();
+        let params =
+            analyzer::transform_execute_params(&scx, params, param_types)?;
+        match &stmt.stmt {
+            Some(stmt @ Statement::Query(_)) => {
+                bind_params(&scx, param_types, &params);
+                let (desc, stream) = self.stream(&scx, stmt)?;
+                let rows = stream.collect::<Vec<Result<Row>>>().await;
+                Ok(ExecuteResponse::Rows {
+                    desc,
+                    rows: rows.into_iter().collect::<Result<Vec<Row>>>()?,
+                })
+            }
+            // only queries can be prepared by `PREPARE`.
+            stmt => Err(FloppyError::NotImplemented(format!(
+                "EXECUTE of {}",
+                stmt.as_ref().map_or("empty query".to_string(), |stmt| {
+                    stmt.to_string()
+                })
+            ))),
+        }
+    }
+
+    /// Remove the prepared statement `name` by `DEALLOCATE`.
+    fn deallocate(&mut self, name: &str) -> Result<ExecuteResponse> {
+        if self.prepared_statements.remove(name).is_none() {
+            return Err(FloppyError::Plan(format!(
+                "prepared statement \"{name}\" does not exist"
+            )));
+        }
+        Ok(ExecuteResponse::Deallocated)
+    }
+
+    /// Drop the tables `names` by `DROP TABLE`, a missing table is
+    /// skipped with a notice if `if_exists` is true.
+    fn drop_tables(
+        &mut self,
+        names: &[ObjectName],
+        if_exists: bool,
+    ) -> Result<ExecuteResponse> {
+        let scx = self.statement_context();
+        let mut ids = vec![];
+        for name in names {
+            let partial_name: PartialObjectName = name.try_into()?;
+            match scx.catalog.resolve_item(&partial_name, &scx.search_path) {
+                Ok(item) if item.system_rows().is_some() => {
+                    return Err(FloppyError::Plan(format!(
+                        "cannot drop system table \"{}\"",
+                        partial_name.item
+                    )));
+                }
+                Ok(item) => ids.push(item.id()),
+                Err(FloppyError::Catalog(CatalogError::TableNotFound(_)))
+                    if if_exists =>
+                {
+                    self.add_notice(Notice::notice(
+                        "00000",
+                        &format!(
+                            "table \"{}\" does not exist, skipping",
+                            partial_name.item
+                        ),
+                    ));
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        // the table is dropped right away instead of when the
+        // transaction is committed, so a statement in a transaction
+        // block can't drop it, see `TxnTableStore::drop_table`.
+        let table_store = match &self.transaction {
+            TransactionState::Started(_) => self.table_store.clone(),
+            _ => self.table_store(),
+        };
+        for id in &ids {
+            table_store.drop_table(id)?;
+            self.catalog_store.drop_table(id)?;
+        }
+        Ok(ExecuteResponse::DroppedTable)
+    }
+
+    /// Read `columns` of the table `table_name` for `COPY TO STDOUT`,
+    /// or all its columns if `columns` is empty.
+    async fn copy_out(
+        &self,
+        table_name: &ObjectName,
+        columns: &[Ident],
+    ) -> Result<ExecuteResponse> {
+        let columns = if columns.is_empty() {
+            "*".to_string()
+        } else {
+            columns
+                .iter()
+                .map(|column| column.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let mut statements =
+            planner::parse(&format!("SELECT {columns} FROM {table_name}"))?;
+        let scx = self.statement_context();
+        let (desc, stream) = self.stream(&scx, &statements.remove(0))?;
+        let rows = stream.collect::<Vec<Result<Row>>>().await;
+        Ok(ExecuteResponse::CopyOut {
+            desc,
+            rows: rows.into_iter().collect::<Result<Vec<Row>>>()?,
+        })
+    }
+
+    fn statement_context(&self) -> StatementContext {
+        let mut scx = StatementContext::new(self.catalog_store.clone());
+        if let Some(search_path) = self.var("search_path") {
+            scx.search_path = search_path
+                .split(',')
+                .map(|schema| schema.trim().trim_matches('"').to_string())
+                .filter(|schema| !schema.is_empty())
+                .collect();
+        }
+        scx
+    }
+
+    fn stream(
+        &self,
+        scx: &StatementContext,
+        statement: &Statement,
+    ) -> Result<(RelationDesc, RowStream)> {
+        let (plan, desc) = planner::plan_statement(scx, statement)?;
+        let exec_ctx = ExecutionContext::new(
+            self.catalog_store.clone(),
+            self.table_store(),
+        );
+        let stream = plan.stream(Arc::new(exec_ctx))?;
+        Ok((desc.rel_desc.unwrap_or_default(), stream))
+    }
+
+    pub fn transaction(&self) -> &TransactionState {
+        &self.transaction
+    }
+
+    /// The table store seen by the statements, which is the one of
+    /// the current transaction if there is one.
+    fn table_store(&self) -> Arc<dyn TableStore> {
+        match &self.transaction {
+            TransactionState::Default => self.table_store.clone(),
+            TransactionState::Started(txn)
+            | TransactionState::InTransaction(txn)
+            | TransactionState::InTransactionImplicit(txn)
+            | TransactionState::Failed(txn) => txn.store.clone(),
+        }
+    }
+
+    fn new_txn(&self) -> Transaction {
+        let store = TxnTableStore::new(
+            self.table_store.clone(),
+            self.catalog_store.clone(),
+        );
+        Transaction {
+            store: Arc::new(store),
+            savepoints: vec![],
+        }
+    }
+
+    /// Start a transaction of `kind`.
+    /// `BEGIN` in an implicit transaction turns it into an explicit
+    /// one, and it is a no-op in an explicit transaction.
+    pub fn start_txn(&mut self, kind: TransactionKind) -> Result<()> {
+        let state =
+            std::mem::replace(&mut self.transaction, TransactionState::Default);
+        self.transaction = match (state, kind) {
+            (TransactionState::Default, TransactionKind::Single) => {
+                TransactionState::Started(self.new_txn())
+            }
+            (TransactionState::Default, TransactionKind::Implicit) => {
+                TransactionState::InTransactionImplicit(self.new_txn())
+            }
+            (TransactionState::Default, TransactionKind::Explicit) => {
+                TransactionState::InTransaction(self.new_txn())
+            }
+            (
+                TransactionState::Started(txn)
+                | TransactionState::InTransactionImplicit(txn),
+                TransactionKind::Explicit,
+            ) => TransactionState::InTransaction(txn),
+            (state @ TransactionState::Failed(_), _) => {
+                self.transaction = state;
+                return Err(FloppyError::TransactionAborted);
+            }
+            (state, _) => state,
+        };
+        Ok(())
+    }
+
+    /// Commit the current transaction, its writes are seen by the
+    /// other sessions afterwards. A failed transaction is rolled back
+    /// and [`FloppyError::TransactionAborted`] is returned.
+    pub fn commit_txn(&mut self) -> Result<()> {
+        let state =
+            std::mem::replace(&mut self.transaction, TransactionState::Default);
+        self.end_transaction();
+        match state {
+            TransactionState::Default => Ok(()),
+            TransactionState::Started(txn)
+            | TransactionState::InTransaction(txn)
+            | TransactionState::InTransactionImplicit(txn) => {
+                txn.store.commit()
+            }
+            TransactionState::Failed(_) => Err(FloppyError::TransactionAborted),
+        }
+    }
+
+    /// Rollback the current transaction, its writes are dropped. It
+    /// is a no-op if there is no transaction.
+    pub fn rollback_txn(&mut self) {
+        self.end_transaction();
+    }
+
+    /// Mark the current transaction as failed after an error. An
+    /// implicit transaction is rolled back immediately, while an
+    /// explicit one is `Failed` until `COMMIT` or `ROLLBACK`.
+    fn fail_txn(&mut self) {
+        let state =
+            std::mem::replace(&mut self.transaction, TransactionState::Default);
+        match state {
+            TransactionState::InTransaction(txn)
+            | TransactionState::Failed(txn) => {
+                self.transaction = TransactionState::Failed(txn);
+            }
+            TransactionState::Started(_)
+            | TransactionState::InTransactionImplicit(_) => {
+                self.end_transaction()
+            }
+            TransactionState::Default => {}
+        }
+    }
+
+    /// Create a savepoint named `name` in the current transaction.
+    /// A savepoint with the same name is shadowed until the new one
+    /// is released.
+    pub fn declare_savepoint(&mut self, name: String) -> Result<()> {
+        match &mut self.transaction {
+            TransactionState::InTransaction(txn) => {
+                let cursors = self.cursors.keys().cloned().collect();
+                let writes = txn.store.writes();
+                txn.savepoints.push(Savepoint {
+                    name,
+                    cursors,
+                    writes,
+                });
+                Ok(())
+            }
+            TransactionState::Failed(_) => Err(FloppyError::TransactionAborted),
+            _ => Err(FloppyError::Plan(
+                "SAVEPOINT can only be used in transaction blocks".to_string(),
+            )),
+        }
+    }
+
+    /// Rollback the current transaction to the savepoint `name`, the
+    /// savepoints created after it are destroyed, the cursors declared
+    /// after it are closed and the writes made after it are dropped.
+    /// The savepoint itself is kept, and a failed transaction is
+    /// restored.
+    pub fn rollback_to_savepoint(&mut self, name: &str) -> Result<()> {
+        let state =
+            std::mem::replace(&mut self.transaction, TransactionState::Default);
+        let mut txn = match state {
+            TransactionState::InTransaction(txn)
+            | TransactionState::Failed(txn) => txn,
+            state => {
+                self.transaction = state;
+                return Err(FloppyError::Plan(
+                    "ROLLBACK TO SAVEPOINT can only be used in transaction \
+                     blocks"
+                        .to_string(),
+                ));
+            }
+        };
+        match txn.savepoints.iter().rposition(|sp| sp.name == name) {
+            Some(pos) => {
+                txn.savepoints.truncate(pos + 1);
+                let savepoint = &txn.savepoints[pos];
+                self.cursors
+                    .retain(|name, _| savepoint.cursors.contains(name));
+                txn.store.restore(savepoint.writes.clone());
+                self.transaction = TransactionState::InTransaction(txn);
+                Ok(())
+            }
+            None => {
+                // PostgreSQL keeps the transaction failed.
+                self.transaction = TransactionState::Failed(txn);
+                Err(FloppyError::Plan(format!(
+                    "savepoint \"{name}\" does not exist"
+                )))
+            }
+        }
+    }
+
+    /// Destroy the savepoint `name` and the savepoints created after
+    /// it, the changes made after it are kept.
+    pub fn release_savepoint(&mut self, name: &str) -> Result<()> {
+        match &mut self.transaction {
+            TransactionState::InTransaction(txn) => {
+                match txn.savepoints.iter().rposition(|sp| sp.name == name) {
+                    Some(pos) => {
+                        txn.savepoints.truncate(pos);
+                        Ok(())
+                    }
+                    None => Err(FloppyError::Plan(format!(
+                        "savepoint \"{name}\" does not exist"
+                    ))),
+                }
+            }
+            TransactionState::Failed(_) => Err(FloppyError::TransactionAborted),
+            _ => Err(FloppyError::Plan(
+                "RELEASE SAVEPOINT can only be used in transaction blocks"
+                    .to_string(),
+            )),
+        }
+    }
+
+    /// Warn that `COMMIT` or `ROLLBACK` is executed outside of a
+    /// transaction block.
+    fn warn_no_transaction(&mut self) {
+        if let TransactionState::Default = self.transaction {
+            self.add_notice(Notice::warning(
+                "25P01",
+                "there is no transaction in progress",
+            ));
+        }
+    }
+
+    /// End the current transaction, cursors opened in the
+    /// transaction are closed.
+    fn end_transaction(&mut self) {
+        self.transaction = TransactionState::Default;
+        self.cursors.clear();
+    }
+
+    fn set(
+        &mut self,
+        variable: &ObjectName,
+        value: &[SetVariableValue],
+    ) -> Result<ExecuteResponse> {
+        let name = variable.to_string();
+        match value {
+            [SetVariableValue::Ident(ident)]
+                if ident.value.eq_ignore_ascii_case("default") =>
+            {
+                self.reset_var(&name)
+            }
+            value => {
+                let value = value
+                    .iter()
+                    .map(|v| match v {
+                        SetVariableValue::Ident(ident) => ident.value.clone(),
+                        SetVariableValue::Literal(
+                            SqlValue::SingleQuotedString(s),
+                        ) => s.clone(),
+                        SetVariableValue::Literal(SqlValue::Number(n, _)) => {
+                            n.clone()
+                        }
+                        SetVariableValue::Literal(v) => v.to_string(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                self.set_var(&name, value)
+            }
+        }
+        let var = &self.vars[&name.to_lowercase()];
+        let reported = DEFAULT_VARS
+            .iter()
+            .any(|(n, _, reported)| *reported && n.eq_ignore_ascii_case(&name));
+        Ok(ExecuteResponse::SetVariable {
+            name: var.name.clone(),
+            value: var.value.clone(),
+            reported,
+        })
+    }
+
+    fn show(&self, variable: &[Ident]) -> Result<ExecuteResponse> {
+        let name = variable
+            .iter()
+            .map(|ident| ident.value.as_str())
+            .collect::<Vec<_>>()
+            .join(".");
+        let value = self.var(&name).ok_or_else(|| {
+            FloppyError::Plan(format!(
+                "unrecognized configuration parameter \"{name}\""
+            ))
+        })?;
+        let desc = RelationDesc::new(
+            vec![ColumnType::new(ScalarType::Text, false)],
+            vec![name.to_lowercase()],
+            vec![],
+            vec![],
+        );
+        Ok(ExecuteResponse::ShowVariable {
+            desc,
+            rows: vec![Row::new(vec![Datum::Text(value.to_string())])],
+        })
+    }
+
+    fn declare(
+        &mut self,
+        name: String,
+        hold: Option<bool>,
+        query: Query,
+    ) -> Result<ExecuteResponse> {
+        if hold == Some(true) {
+            return Err(FloppyError::NotImplemented(
+                "DECLARE CURSOR WITH HOLD".to_string(),
+            ));
+        }
+        if !matches!(
+            self.transaction,
+            TransactionState::InTransaction(_)
+                | TransactionState::InTransactionImplicit(_)
+        ) {
+            return Err(FloppyError::Plan(
+                "DECLARE CURSOR can only be used in transaction blocks"
+                    .to_string(),
+            ));
+        }
+        if self.cursors.contains_key(&name) {
+            return Err(FloppyError::Plan(format!(
+                "cursor \"{name}\" already exists"
+            )));
+        }
+
+        let scx = self.statement_context();
+        let (desc, stream) =
+            self.stream(&scx, &Statement::Query(Box::new(query)))?;
+        self.cursors.insert(name, Cursor { desc, stream });
+        Ok(ExecuteResponse::DeclaredCursor)
+    }
+
+    async fn fetch(
+        &mut self,
+        name: &str,
+        direction: FetchDirection,
+    ) -> Result<ExecuteResponse> {
+        let count = match direction {
+            FetchDirection::Next | FetchDirection::Forward { limit: None } => {
+                Some(1)
+            }
+            FetchDirection::Count { limit }
+            | FetchDirection::Forward { limit: Some(limit) } => {
+                Some(fetch_count(&limit)?)
+            }
+            FetchDirection::All | FetchDirection::ForwardAll => None,
+            _ => {
+                return Err(FloppyError::NotImplemented(format!(
+                    "FETCH {direction}"
+                )))
+            }
+        };
+        let cursor = self.cursors.get_mut(name).ok_or_else(|| {
+            FloppyError::Plan(format!("cursor \"{name}\" does not exist"))
+        })?;
+        Ok(ExecuteResponse::Fetched {
+            desc: cursor.desc.clone(),
+            rows: cursor.fetch(count).await?,
+        })
+    }
+
+    fn close(&mut self, cursor: CloseCursor) -> Result<ExecuteResponse> {
+        match cursor {
+            CloseCursor::All => self.cursors.clear(),
+            CloseCursor::Specific { name } => {
+                if self.cursors.remove(&name.value).is_none() {
+                    return Err(FloppyError::Plan(format!(
+                        "cursor \"{name}\" does not exist"
+                    )));
+                }
+            }
+        }
+        Ok(ExecuteResponse::ClosedCursor)
+    }
+}
+
+fn fetch_count(limit: &SqlValue) -> Result<usize> {
+    match limit {
+        SqlValue::Number(n, _) => n.parse::<usize>().map_err(|_| {
+            FloppyError::Plan(format!("invalid FETCH count: {n}"))
+        }),
+        _ => Err(FloppyError::Plan(format!("invalid FETCH count: {limit}"))),
+    }
+}
+
+/// Set the parameters of the statement planned with `scx` to
+/// `params` of `param_types`.
+fn bind_params(
+    scx: &StatementContext,
+    param_types: &[ScalarType],
+    params: &[Datum],
+) {
+    for (i, (ty, datum)) in param_types.iter().zip(params).enumerate() {
+        scx.param_types.borrow_mut().insert(i + 1, ty.clone());
+        scx.param_values.borrow_mut().insert(i + 1, datum.clone());
+    }
+}
+
+/// The response of [`Session::execute`].
+#[derive(Debug)]
+pub enum ExecuteResponse {
+    StartedTransaction,
+    /// The transaction is committed, or rolled back if `commit` is
+    /// false.
+    TransactionExited {
+        commit: bool,
+    },
+    CreatedSavepoint,
+    /// A run-time parameter is set, `reported` is true if the change
+    /// should be reported to the client.
+    SetVariable {
+        name: String,
+        value: String,
+        reported: bool,
+    },
+    /// The value of a run-time parameter returned by `SHOW`.
+    ShowVariable {
+        desc: RelationDesc,
+        rows: Vec<Row>,
+    },
+    DeclaredCursor,
+    ClosedCursor,
+    Prepared,
+    Deallocated,
+    DroppedTable,
+    /// Rows returned by a query.
+    Rows {
+        desc: RelationDesc,
+        rows: Vec<Row>,
+    },
+    /// Rows returned by `FETCH`.
+    Fetched {
+        desc: RelationDesc,
+        rows: Vec<Row>,
+    },
+    /// Rows returned by a portal which has more rows to return.
+    PortalSuspended {
+        rows: Vec<Row>,
+    },
+    /// The number of rows inserted.
+    Inserted(usize),
+    /// The number of rows updated.
+    Updated(usize),
+    /// The number of rows deleted.
+    Deleted(usize),
+    /// `COPY FROM STDIN` is waiting for the data from the client.
+    CopyIn(CopyIn),
+    /// The number of rows copied by `COPY FROM STDIN`.
+    Copied(usize),
+    /// Rows returned by `COPY TO STDOUT`.
+    CopyOut {
+        desc: RelationDesc,
+        rows: Vec<Row>,
+    },
+    /// The query string has no statements.
+    EmptyQuery,
+}
+
+impl ExecuteResponse {
+    /// The command tag reported to the client when the statement is
+    /// completed, in the same format as PostgreSQL.
+    /// A suspended portal or a pending `COPY FROM STDIN` is not
+    /// completed and has no tag, nor does an empty query.
+    pub fn tag(&self) -> Option<String> {
+        let tag = match self {
+            Self::StartedTransaction => "BEGIN".to_string(),
+            Self::TransactionExited { commit: true } => "COMMIT".to_string(),
+            Self::TransactionExited { commit: false } => "ROLLBACK".to_string(),
+            Self::CreatedSavepoint => "SAVEPOINT".to_string(),
+            Self::SetVariable { .. } => "SET".to_string(),
+            Self::ShowVariable { .. } => "SHOW".to_string(),
+            Self::DeclaredCursor => "DECLARE CURSOR".to_string(),
+            Self::ClosedCursor => "CLOSE CURSOR".to_string(),
+            Self::Prepared => "PREPARE".to_string(),
+            Self::Deallocated => "DEALLOCATE".to_string(),
+            Self::DroppedTable => "DROP TABLE".to_string(),
+            Self::Rows { rows, .. } => format!("SELECT {}", rows.len()),
+            Self::Fetched { rows, .. } => format!("FETCH {}", rows.len()),
+            Self::PortalSuspended { .. }
+            | Self::CopyIn(_)
+            | Self::EmptyQuery => return None,
+            // the second number is the OID of the inserted row, which
+            // is always zero.
+            Self::Inserted(n) => format!("INSERT 0 {n}"),
+            Self::Updated(n) => format!("UPDATE {n}"),
+            Self::Deleted(n) => format!("DELETE {n}"),
+            Self::Copied(n) => format!("COPY {n}"),
+            Self::CopyOut { rows, .. } => format!("COPY {}", rows.len()),
+        };
+        Some(tag)
+    }
+}
+
+/// The target of `COPY FROM STDIN`.
+#[derive(Debug, Clone)]
+pub struct CopyIn {
+    pub table_id: GlobalId,
+    pub rel_desc: RelationDesc,
+    /// The columns of the table in the copied data.
+    pub columns: Vec<usize>,
+}
+
+/// A message reported to the client without failing the statement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notice {
+    pub severity: NoticeSeverity,
+    /// The SQLSTATE code of the notice.
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl Notice {
+    pub fn warning(code: &'static str, message: &str) -> Self {
+        Self {
+            severity: NoticeSeverity::Warning,
+            code,
+            message: message.to_string(),
+        }
+    }
+
+    pub fn notice(code: &'static str, message: &str) -> Self {
+        Self {
+            severity: NoticeSeverity::Notice,
+            code,
+            message: message.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoticeSeverity {
+    Warning,
+    Notice,
+}
+
+/// A run-time parameter of a session.
+#[derive(Debug, Clone)]
+struct Var {
+    /// The name as it is set, reported to the client.
+    name: String,
+    value: String,
+}
+
+/// A cursor is a query whose execution can be resumed,
+/// its rows are retrieved by `FETCH` in chunks.
+struct Cursor {
+    desc: RelationDesc,
+    stream: RowStream,
+}
+
+impl Cursor {
+    /// Fetch at most `count` rows from the cursor, or all the
+    /// remaining rows if `count` is `None`.
+    async fn fetch(&mut self, count: Option<usize>) -> Result<Vec<Row>> {
+        let mut rows = vec![];
+        while count != Some(rows.len()) {
+            match self.stream.next().await {
+                Some(row) => rows.push(row?),
+                None => break,
+            }
+        }
+        Ok(rows)
+    }
+}
+
+impl fmt::Debug for Cursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cursor").field("desc", &self.desc).finish()
+    }
 }
 
 /// A prepared statement.
@@ -34,6 +1455,58 @@ impl Session {
 pub struct PreparedStatement {
     stmt: Option<Statement>,
     desc: StatementDesc,
+    /// The types of the parameters on the wire, the ones declared by
+    /// the client or else the inferred ones.
+    param_types: Vec<Type>,
+}
+
+impl PreparedStatement {
+    /// The statement, `None` for an empty query.
+    pub fn stmt(&self) -> Option<&Statement> {
+        self.stmt.as_ref()
+    }
+
+    pub fn desc(&self) -> &StatementDesc {
+        &self.desc
+    }
+
+    /// The types the parameters are sent in, see
+    /// [`Session::prepare`].
+    pub fn param_types(&self) -> &[Type] {
+        &self.param_types
+    }
+}
+
+/// A portal is a prepared statement with its parameters bound,
+/// ready to be executed.
+#[derive(Debug)]
+pub struct Portal {
+    /// The statement, `None` for an empty query.
+    pub stmt: Option<Statement>,
+    pub desc: StatementDesc,
+    /// The values of the parameters.
+    pub bound_params: Vec<Datum>,
+    /// The formats of the result columns, one for each column.
+    pub result_formats: Vec<Format>,
+    pub state: PortalState,
+}
+
+/// The execution state of a [`Portal`].
+pub enum PortalState {
+    NotStarted,
+    /// The portal is suspended after returning some rows.
+    InProgress(RowStream),
+    Completed,
+}
+
+impl fmt::Debug for PortalState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotStarted => write!(f, "NotStarted"),
+            Self::InProgress(_) => write!(f, "InProgress"),
+            Self::Completed => write!(f, "Completed"),
+        }
+    }
 }
 
 /// The transaction status of a session.
@@ -78,4 +1551,733 @@ impl TransactionState {
 }
 
 #[derive(Debug, Clone)]
-pub struct Transaction {}
+pub struct Transaction {
+    /// The table store of the statements in the transaction, which
+    /// reads the snapshot taken when the transaction is started and
+    /// keeps the writes until they are committed.
+    store: Arc<TxnTableStore>,
+    /// Savepoints of the transaction, the most recent one is the last.
+    savepoints: Vec<Savepoint>,
+}
+
+/// A savepoint marks a point of a transaction that can be rolled
+/// back to.
+#[derive(Debug, Clone)]
+struct Savepoint {
+    name: String,
+    /// The cursors declared before the savepoint.
+    cursors: HashSet<String>,
+    /// The writes of the transaction made before the savepoint.
+    writes: Writes,
+}
+
+/// The way a transaction is started.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionKind {
+    /// A transaction of a single statement, see
+    /// [`TransactionState::Started`].
+    Single,
+    /// A transaction of a multi-statement query, see
+    /// [`TransactionState::InTransactionImplicit`].
+    Implicit,
+    /// A transaction started by `BEGIN`.
+    Explicit,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::memory::MemCatalog;
+    use crate::common::scalar::Datum;
+    use crate::storage::memory::MemoryEngine;
+    use crate::test_util::seeder;
+
+    #[tokio::test]
+    async fn declare_fetch_close() -> Result<()> {
+        let rows = (0..100)
+            .map(|i| Row::new(vec![Datum::Int64(i), Datum::Int64(i * 10)]))
+            .collect::<Vec<Row>>();
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&rows)?;
+        let mut session = Session::new(1, catalog_store, table_store);
+
+        let sql = "DECLARE c CURSOR FOR SELECT * FROM test";
+        assert!(session.execute(sql).await.is_err());
+
+        session.execute("BEGIN").await?;
+        session.execute(sql).await?;
+        let mut fetched = vec![];
+        loop {
+            match session.execute("FETCH 30 FROM c").await? {
+                ExecuteResponse::Fetched { rows: r, .. } if r.is_empty() => {
+                    break
+                }
+                ExecuteResponse::Fetched { rows: r, .. } => {
+                    assert!(r.len() <= 30);
+                    fetched.extend(r);
+                }
+                r => panic!("unexpected response: {r:?}"),
+            }
+        }
+        assert_eq!(fetched, rows);
+
+        session.execute("CLOSE c").await?;
+        assert!(session.execute("FETCH 1 FROM c").await.is_err());
+        session.execute("ROLLBACK").await?;
+
+        session.execute("BEGIN").await?;
+        session.execute(sql).await?;
+        session.execute("COMMIT").await?;
+        assert!(session.execute("FETCH NEXT FROM c").await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn scan_system_tables() -> Result<()> {
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&vec![])?;
+        let mut session = Session::new(1, catalog_store, table_store);
+        let sql = "SELECT relname, relkind FROM pg_catalog.pg_class";
+        match session.execute(sql).await? {
+            ExecuteResponse::Rows { rows, .. } => {
+                assert!(rows.contains(&Row::new(vec![
+                    Datum::Text("test".to_string()),
+                    Datum::Text("r".to_string()),
+                ])))
+            }
+            r => panic!("unexpected response: {r:?}"),
+        }
+
+        let sql = "SELECT attname, atttypid FROM pg_catalog.pg_attribute";
+        match session.execute(sql).await? {
+            ExecuteResponse::Rows { rows, .. } => {
+                assert!(rows.contains(&Row::new(vec![
+                    Datum::Text("c2".to_string()),
+                    Datum::Int64(20),
+                ])))
+            }
+            r => panic!("unexpected response: {r:?}"),
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn command_tags() -> Result<()> {
+        let rows = (0..10)
+            .map(|i| Row::new(vec![Datum::Int64(i), Datum::Int64(i * 10)]))
+            .collect::<Vec<Row>>();
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&rows)?;
+        let mut session = Session::new(1, catalog_store, table_store);
+
+        let tests = [
+            ("BEGIN", "BEGIN"),
+            ("SELECT * FROM test", "SELECT 10"),
+            ("DECLARE c CURSOR FOR SELECT * FROM test", "DECLARE CURSOR"),
+            ("FETCH 3 FROM c", "FETCH 3"),
+            ("FETCH ALL FROM c", "FETCH 7"),
+            ("FETCH NEXT FROM c", "FETCH 0"),
+            ("CLOSE c", "CLOSE CURSOR"),
+            ("UPDATE test SET c2 = c2 + 1 WHERE c1 < 2", "UPDATE 2"),
+            ("COMMIT", "COMMIT"),
+            ("ROLLBACK", "ROLLBACK"),
+        ];
+        for (sql, tag) in tests {
+            let resp = session.execute(sql).await?;
+            assert_eq!(resp.tag().as_deref(), Some(tag), "{sql}");
+        }
+
+        let tests = [
+            (ExecuteResponse::Inserted(1), "INSERT 0 1"),
+            (ExecuteResponse::Updated(2), "UPDATE 2"),
+            (ExecuteResponse::Deleted(0), "DELETE 0"),
+        ];
+        for (resp, tag) in tests {
+            assert_eq!(resp.tag().as_deref(), Some(tag));
+        }
+        let resp = ExecuteResponse::PortalSuspended { rows: vec![] };
+        assert_eq!(resp.tag(), None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn declare_portal() -> Result<()> {
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&vec![])?;
+        let mut session = Session::new(1, catalog_store, table_store);
+        session.prepare(
+            "s1".to_string(),
+            "SELECT c1 + $1 FROM test",
+            &[Some(Type::Int8)],
+        )?;
+        session.prepare("".to_string(), "SELECT c2 FROM test", &[])?;
+
+        session.declare_portal(
+            "p1".to_string(),
+            "s1",
+            vec![Datum::Int64(1)],
+            vec![],
+        )?;
+        let portal = session.get_portal("p1").expect("portal exists");
+        assert_eq!(portal.bound_params, vec![Datum::Int64(1)]);
+        assert_eq!(portal.desc.param_types, vec![ScalarType::Int64]);
+        assert!(matches!(portal.state, PortalState::NotStarted));
+
+        // a named portal can not be redeclared.
+        let err = session.declare_portal("p1".to_string(), "", vec![], vec![]);
+        assert!(err.is_err());
+        assert_eq!(session.get_portal("p1").unwrap().bound_params.len(), 1);
+
+        // the unnamed portal is replaced.
+        session.declare_portal(
+            "".to_string(),
+            "s1",
+            vec![Datum::Int64(2)],
+            vec![],
+        )?;
+        session.declare_portal("".to_string(), "", vec![], vec![])?;
+        let portal = session.get_portal("").expect("portal exists");
+        assert!(portal.bound_params.is_empty());
+
+        // the statement does not exist.
+        assert!(session
+            .declare_portal("p2".to_string(), "s2", vec![], vec![])
+            .is_err());
+
+        // a failed declaration still closes the unnamed portal, and a
+        // failed preparation the unnamed statement.
+        assert!(session
+            .declare_portal("".to_string(), "s2", vec![], vec![])
+            .is_err());
+        assert!(session.get_portal("").is_none());
+        assert!(session.prepare("".to_string(), "SELECT c3", &[]).is_err());
+        assert!(session.get_prepared_statement("").is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn transaction_state() -> Result<()> {
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&vec![])?;
+        let mut session = Session::new(1, catalog_store, table_store);
+        let state = |s: &Session| {
+            let state = format!("{:?}", s.transaction());
+            state.split('(').next().unwrap().to_string()
+        };
+        assert_eq!(state(&session), "Default");
+
+        session.start_txn(TransactionKind::Single)?;
+        assert_eq!(state(&session), "Started");
+        session.start_txn(TransactionKind::Explicit)?;
+        assert_eq!(state(&session), "InTransaction");
+        session.start_txn(TransactionKind::Single)?;
+        assert_eq!(state(&session), "InTransaction");
+        session.commit_txn()?;
+        assert_eq!(state(&session), "Default");
+
+        session.start_txn(TransactionKind::Implicit)?;
+        assert_eq!(state(&session), "InTransactionImplicit");
+        session.rollback_txn();
+        assert_eq!(state(&session), "Default");
+
+        // a single statement is committed when it completes.
+        session.execute("SELECT * FROM test").await?;
+        assert_eq!(state(&session), "Default");
+        assert!(session.execute("SELECT c3 FROM test").await.is_err());
+        assert_eq!(state(&session), "Default");
+
+        // an error fails an explicit transaction.
+        session.execute("BEGIN").await?;
+        assert!(session.execute("SELECT c3 FROM test").await.is_err());
+        assert_eq!(state(&session), "Failed");
+        let err = session.execute("SELECT * FROM test").await.unwrap_err();
+        assert!(matches!(err, FloppyError::TransactionAborted));
+        assert!(session.start_txn(TransactionKind::Explicit).is_err());
+        let err = session.execute("COMMIT").await.unwrap_err();
+        assert!(matches!(err, FloppyError::TransactionAborted));
+        assert_eq!(state(&session), "Default");
+
+        session.execute("BEGIN").await?;
+        assert!(session.execute("SELECT c3 FROM test").await.is_err());
+        session.execute("ROLLBACK").await?;
+        assert_eq!(state(&session), "Default");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn transaction_notices() -> Result<()> {
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&vec![])?;
+        let mut session = Session::new(1, catalog_store, table_store);
+
+        session.execute("BEGIN").await?;
+        assert_eq!(session.drain_notices(), vec![]);
+        session.execute("BEGIN").await?;
+        assert_eq!(
+            session.drain_notices(),
+            vec![Notice::warning(
+                "25001",
+                "there is already a transaction in progress"
+            )]
+        );
+        session.execute("COMMIT").await?;
+        assert_eq!(session.drain_notices(), vec![]);
+        for sql in ["ROLLBACK", "COMMIT"] {
+            let resp = session.execute(sql).await?;
+            assert_eq!(resp.tag().as_deref(), Some(sql));
+            assert_eq!(
+                session.drain_notices(),
+                vec![Notice::warning(
+                    "25P01",
+                    "there is no transaction in progress"
+                )],
+                "{sql}"
+            );
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn savepoints() -> Result<()> {
+        let rows = (0..10)
+            .map(|i| Row::new(vec![Datum::Int64(i), Datum::Int64(i * 10)]))
+            .collect::<Vec<Row>>();
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&rows)?;
+        let mut session = Session::new(1, catalog_store, table_store);
+        let savepoints = |s: &Session| match s.transaction() {
+            TransactionState::InTransaction(txn) => txn
+                .savepoints
+                .iter()
+                .map(|sp| sp.name.clone())
+                .collect::<Vec<_>>(),
+            state => panic!("unexpected state: {state:?}"),
+        };
+
+        assert!(session.execute("SAVEPOINT a").await.is_err());
+        session.execute("BEGIN").await?;
+        session
+            .execute("DECLARE c1 CURSOR FOR SELECT * FROM test")
+            .await?;
+        let resp = session.execute("SAVEPOINT a").await?;
+        assert_eq!(resp.tag().unwrap(), "SAVEPOINT");
+        session
+            .execute("DECLARE c2 CURSOR FOR SELECT * FROM test")
+            .await?;
+        session.execute("SAVEPOINT b").await?;
+        session
+            .execute("DECLARE c3 CURSOR FOR SELECT * FROM test")
+            .await?;
+        assert_eq!(savepoints(&session), ["a", "b"]);
+
+        session.rollback_to_savepoint("a")?;
+        assert_eq!(savepoints(&session), ["a"]);
+        assert!(session.rollback_to_savepoint("b").is_err());
+        session.rollback_to_savepoint("a")?;
+        // the cursor declared before the savepoint remains.
+        session.execute("FETCH 1 FROM c1").await?;
+        assert!(session.execute("FETCH 1 FROM c2").await.is_err());
+
+        // rolling back to a savepoint recovers a failed transaction.
+        assert!(matches!(session.transaction(), TransactionState::Failed(_)));
+        session.rollback_to_savepoint("a")?;
+        session.execute("FETCH 1 FROM c1").await?;
+
+        session.execute("SAVEPOINT b").await?;
+        session.execute("SAVEPOINT a").await?;
+        assert_eq!(savepoints(&session), ["a", "b", "a"]);
+        session.release_savepoint("b")?;
+        assert_eq!(savepoints(&session), ["a"]);
+        assert!(session.release_savepoint("b").is_err());
+
+        session.execute("COMMIT").await?;
+        assert!(session.release_savepoint("a").is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn read_your_writes() -> Result<()> {
+        let rows = (0..3)
+            .map(|i| Row::new(vec![Datum::Int64(i), Datum::Int64(i * 10)]))
+            .collect::<Vec<Row>>();
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&rows)?;
+        let mut s1 =
+            Session::new(1, catalog_store.clone(), table_store.clone());
+        let mut s2 = Session::new(2, catalog_store, table_store);
+        let sql = "SELECT c2 FROM test WHERE c1 = 1";
+        let c2 = |resp: ExecuteResponse| match resp {
+            ExecuteResponse::Rows { rows, .. } => rows
+                .iter()
+                .map(|r| r.datums()[0].clone())
+                .collect::<Vec<_>>(),
+            r => panic!("unexpected response: {r:?}"),
+        };
+
+        s1.execute("BEGIN").await?;
+        s1.execute("UPDATE test SET c2 = 11 WHERE c1 = 1").await?;
+        assert_eq!(c2(s1.execute(sql).await?), [Datum::Int64(11)]);
+        // the other session doesn't see the write until it is
+        // committed.
+        assert_eq!(c2(s2.execute(sql).await?), [Datum::Int64(10)]);
+        s1.execute("SAVEPOINT a").await?;
+        s1.execute("UPDATE test SET c2 = 12 WHERE c1 = 1").await?;
+        assert_eq!(c2(s1.execute(sql).await?), [Datum::Int64(12)]);
+        s1.rollback_to_savepoint("a")?;
+        assert_eq!(c2(s1.execute(sql).await?), [Datum::Int64(11)]);
+        s1.execute("COMMIT").await?;
+        assert_eq!(c2(s2.execute(sql).await?), [Datum::Int64(11)]);
+
+        // the writes of a rolled back transaction are dropped.
+        s1.execute("BEGIN").await?;
+        s1.execute("UPDATE test SET c2 = 13 WHERE c1 = 1").await?;
+        assert_eq!(c2(s1.execute(sql).await?), [Datum::Int64(13)]);
+        s1.execute("ROLLBACK").await?;
+        assert_eq!(c2(s1.execute(sql).await?), [Datum::Int64(11)]);
+        assert_eq!(c2(s2.execute(sql).await?), [Datum::Int64(11)]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn failed_update_is_rolled_back() -> Result<()> {
+        let rows = (0..10)
+            .map(|i| Row::new(vec![Datum::Int64(i), Datum::Int64(i * 10)]))
+            .collect::<Vec<Row>>();
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&rows)?;
+        let mut session = Session::new(1, catalog_store, table_store);
+        let sql = "SELECT c1 FROM test WHERE c1 > 3";
+        let c1 = |resp: ExecuteResponse| match resp {
+            ExecuteResponse::Rows { rows, .. } => rows
+                .iter()
+                .map(|r| r.datums()[0].clone())
+                .collect::<Vec<_>>(),
+            r => panic!("unexpected response: {r:?}"),
+        };
+        let unchanged = (4..10).map(Datum::Int64).collect::<Vec<_>>();
+
+        // 4 and 5 are moved to 11 and 10 before 6 is moved onto 9.
+        let update = "UPDATE test SET c1 = 15 - c1 WHERE c1 > 3";
+        let e = session.execute(update).await.unwrap_err();
+        assert_eq!(e.code(), "23505");
+        assert_eq!(c1(session.execute(sql).await?), unchanged);
+
+        session.execute("BEGIN").await?;
+        let e = session.execute(update).await.unwrap_err();
+        assert_eq!(e.code(), "23505");
+        session.execute("ROLLBACK").await?;
+        assert_eq!(c1(session.execute(sql).await?), unchanged);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn snapshot_isolation() -> Result<()> {
+        let rows = (0..3)
+            .map(|i| Row::new(vec![Datum::Int64(i), Datum::Int64(i * 10)]))
+            .collect::<Vec<Row>>();
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&rows)?;
+        let mut s1 =
+            Session::new(1, catalog_store.clone(), table_store.clone());
+        let mut s2 = Session::new(2, catalog_store, table_store);
+        let sql = "SELECT c2 FROM test WHERE c1 = 1";
+        let c2 = |resp: ExecuteResponse| match resp {
+            ExecuteResponse::Rows { rows, .. } => rows
+                .iter()
+                .map(|r| r.datums()[0].clone())
+                .collect::<Vec<_>>(),
+            r => panic!("unexpected response: {r:?}"),
+        };
+
+        s1.execute("BEGIN").await?;
+        assert_eq!(c2(s1.execute(sql).await?), [Datum::Int64(10)]);
+        s2.execute("UPDATE test SET c2 = 11 WHERE c1 = 1").await?;
+        // the transaction doesn't see the commit after it is started,
+        // while a new one does.
+        assert_eq!(c2(s1.execute(sql).await?), [Datum::Int64(10)]);
+        assert_eq!(c2(s2.execute(sql).await?), [Datum::Int64(11)]);
+        s1.execute("COMMIT").await?;
+        assert_eq!(c2(s1.execute(sql).await?), [Datum::Int64(11)]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn prepare_execute_deallocate() -> Result<()> {
+        let rows = (0..10)
+            .map(|i| Row::new(vec![Datum::Int64(i), Datum::Int64(i * 10)]))
+            .collect::<Vec<Row>>();
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&rows)?;
+        let mut session = Session::new(1, catalog_store, table_store);
+
+        let resp = session
+            .execute("PREPARE s1 (int8) AS SELECT c2 FROM test WHERE c1 < $1")
+            .await?;
+        assert_eq!(resp.tag().unwrap(), "PREPARE");
+        let stmt = session.get_prepared_statement("s1").unwrap();
+        assert_eq!(stmt.desc().param_types, vec![ScalarType::Int64]);
+        // re-preparing the same name is an error.
+        assert!(session.execute("PREPARE s1 AS SELECT 1").await.is_err());
+
+        match session.execute("EXECUTE s1 (1 + 2)").await? {
+            ExecuteResponse::Rows { rows, .. } => assert_eq!(
+                rows,
+                [0, 10, 20].map(|i| Row::new(vec![Datum::Int64(i)]))
+            ),
+            resp => panic!("unexpected response {resp:?}"),
+        }
+        // the number of parameters doesn't match.
+        assert!(session.execute("EXECUTE s1").await.is_err());
+        assert!(session.execute("EXECUTE s1 (1, 2)").await.is_err());
+
+        let resp = session.execute("DEALLOCATE s1").await?;
+        assert_eq!(resp.tag().unwrap(), "DEALLOCATE");
+        assert!(session.get_prepared_statement("s1").is_none());
+        assert!(session.execute("EXECUTE s1 (1)").await.is_err());
+        assert!(session.execute("DEALLOCATE s1").await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn set_and_show() -> Result<()> {
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&vec![])?;
+        let mut session = Session::new(1, catalog_store, table_store);
+        let show = |resp: ExecuteResponse| match resp {
+            ExecuteResponse::ShowVariable { rows, .. } => rows,
+            r => panic!("unexpected response: {r:?}"),
+        };
+
+        // unknown parameters are accepted.
+        let resp = session.execute("SET myapp.mode = 'fast'").await?;
+        assert_eq!(resp.tag().unwrap(), "SET");
+        let rows = show(session.execute("SHOW myapp.mode").await?);
+        assert_eq!(rows, [Row::new(vec![Datum::Text("fast".to_string())])]);
+        session.execute("SET myapp.mode TO DEFAULT").await?;
+        assert_eq!(session.var("myapp.mode"), Some(""));
+        assert!(session.execute("SHOW myapp.other").await.is_err());
+
+        // names are case insensitive.
+        session.execute("SET datestyle TO ISO, DMY").await?;
+        assert_eq!(session.var("DateStyle"), Some("ISO, DMY"));
+        let rows = show(session.execute("SHOW DATESTYLE").await?);
+        assert_eq!(rows, [Row::new(vec![Datum::Text("ISO, DMY".to_string())])]);
+
+        assert!(session.execute("SELECT * FROM pg_class").await.is_err());
+        session
+            .execute("SET search_path = pg_catalog, public")
+            .await?;
+        session.execute("SELECT * FROM pg_class").await?;
+        session.execute("SELECT * FROM test").await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn select_without_from() -> Result<()> {
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&vec![])?;
+        let mut session = Session::new(1, catalog_store, table_store);
+        let tests = [
+            ("SELECT 1 + 1", vec![Datum::Int64(2)]),
+            (
+                "SELECT 1, 'hello'",
+                vec![Datum::Int64(1), Datum::Text("hello".to_string())],
+            ),
+        ];
+        for (sql, expected) in tests {
+            match session.execute(sql).await? {
+                ExecuteResponse::Rows { rows, .. } => {
+                    assert_eq!(rows, [Row::new(expected)], "{sql}")
+                }
+                r => panic!("unexpected response: {r:?}"),
+            }
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn copy_column_defaults() -> Result<()> {
+        let rel_desc = RelationDesc::new(
+            vec![
+                ColumnType::new(ScalarType::Int64, false),
+                ColumnType::new(ScalarType::Int64, false),
+                ColumnType::new(ScalarType::Text, true),
+            ],
+            vec!["c1".to_string(), "c2".to_string(), "c3".to_string()],
+            vec![0],
+            vec![],
+        )
+        .with_default(1, Datum::Int64(7));
+        let mut catalog = MemCatalog::default();
+        catalog.insert_table("t", 1, rel_desc.clone());
+        let table_store = Arc::new(MemoryEngine::new(rel_desc));
+        let mut session = Session::new(1, Arc::new(catalog), table_store);
+
+        let copy_in = match session.execute("COPY t (c1) FROM STDIN").await? {
+            ExecuteResponse::CopyIn(copy_in) => copy_in,
+            r => panic!("unexpected response: {r:?}"),
+        };
+        session.copy_from(&copy_in, b"1\n2\n")?;
+        let rows = match session.execute("SELECT * FROM t").await? {
+            ExecuteResponse::Rows { rows, .. } => rows,
+            r => panic!("unexpected response: {r:?}"),
+        };
+        // c2 has a default, c3 is NULL without one.
+        let expected = [1, 2].map(|i| {
+            Row::new(vec![Datum::Int64(i), Datum::Int64(7), Datum::Null])
+        });
+        assert_eq!(rows, expected);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn copy_duplicate_key() -> Result<()> {
+        let rows = (0..3)
+            .map(|i| Row::new(vec![Datum::Int64(i), Datum::Int64(i * 10)]))
+            .collect::<Vec<Row>>();
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&rows)?;
+        let mut session = Session::new(1, catalog_store, table_store);
+
+        // the key of an existing row, or of an earlier copied row.
+        for data in [b"5\t50\n1\t10\n".as_slice(), b"5\t50\n5\t50\n"] {
+            let sql = "COPY test FROM STDIN";
+            let copy_in = match session.execute(sql).await? {
+                ExecuteResponse::CopyIn(copy_in) => copy_in,
+                r => panic!("unexpected response: {r:?}"),
+            };
+            let e = session.copy_from(&copy_in, data).unwrap_err();
+            assert_eq!(e.code(), "23505");
+        }
+        // none of the rows is copied.
+        match session.execute("SELECT * FROM test").await? {
+            ExecuteResponse::Rows { rows: r, .. } => assert_eq!(r, rows),
+            r => panic!("unexpected response: {r:?}"),
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn float_arithmetic() -> Result<()> {
+        let rows = (1..4)
+            .map(|i| Row::new(vec![Datum::Int64(i), Datum::Int64(i * 10)]))
+            .collect::<Vec<Row>>();
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&rows)?;
+        let mut session = Session::new(1, catalog_store, table_store);
+        let numeric = |s: &str| Datum::Numeric(s.parse().unwrap());
+        let float = |f: f64| Datum::Float64(f.into());
+        let query = |resp: ExecuteResponse| match resp {
+            ExecuteResponse::Rows { rows, .. } => rows,
+            r => panic!("unexpected response: {r:?}"),
+        };
+
+        // a decimal literal is a numeric, as in PostgreSQL.
+        let tests = [
+            ("SELECT 1.5 + 2", numeric("3.5")),
+            ("SELECT 7 / 2", Datum::Int64(3)),
+            ("SELECT 7.0 / 2", numeric("3.5")),
+            ("SELECT 2 * 2.5", numeric("5.0")),
+        ];
+        for (sql, expected) in tests {
+            let rows = query(session.execute(sql).await?);
+            assert_eq!(rows, [Row::new(vec![expected])], "{sql}");
+        }
+        assert!(session.execute("SELECT 1 / 0").await.is_err());
+        assert!(session.execute("SELECT 1.0 / 0").await.is_err());
+
+        session
+            .execute(
+                "PREPARE f (double precision, double precision) AS \
+                 SELECT $1 + $2, $1 - $2, $1 * $2, $1 / $2, $1 > $2",
+            )
+            .await?;
+        let tests = [
+            (
+                "EXECUTE f (3, 1.5)",
+                vec![
+                    float(4.5),
+                    float(1.5),
+                    float(4.5),
+                    float(2.0),
+                    Datum::Boolean(true),
+                ],
+            ),
+            // like PostgreSQL, NaN is greater than any other float.
+            (
+                "EXECUTE f ('NaN', 'Infinity')",
+                vec![
+                    float(f64::NAN),
+                    float(f64::NAN),
+                    float(f64::NAN),
+                    float(f64::NAN),
+                    Datum::Boolean(true),
+                ],
+            ),
+        ];
+        for (sql, expected) in tests {
+            let rows = query(session.execute(sql).await?);
+            assert_eq!(rows, [Row::new(expected)], "{sql}");
+        }
+        for sql in [
+            "EXECUTE f (1, 0)",
+            "EXECUTE f ('1e308', '1e-308')",
+            "EXECUTE f ('1e-308', '1e308')",
+        ] {
+            assert!(session.execute(sql).await.is_err(), "{sql}");
+        }
+
+        // integer columns are converted to float at runtime.
+        session
+            .execute("PREPARE g (double precision) AS SELECT c1 * $1 FROM test")
+            .await?;
+        let rows = query(session.execute("EXECUTE g (0.5)").await?);
+        assert_eq!(rows, [0.5, 1.0, 1.5].map(|f| Row::new(vec![float(f)])));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn drop_table() -> Result<()> {
+        let rows = (0..3)
+            .map(|i| Row::new(vec![Datum::Int64(i), Datum::Int64(i * 10)]))
+            .collect::<Vec<Row>>();
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&rows)?;
+        let mut session = Session::new(1, catalog_store, table_store.clone());
+
+        // the drop can't be rolled back with a transaction block.
+        session.execute("BEGIN").await?;
+        let e = session.execute("DROP TABLE test").await.unwrap_err();
+        assert_eq!(e.code(), "0A000");
+        session.execute("ROLLBACK").await?;
+        let e = session
+            .execute("DROP TABLE pg_catalog.pg_class")
+            .await
+            .unwrap_err();
+        assert_eq!(e.code(), "42000");
+
+        let resp = session.execute("DROP TABLE test").await?;
+        assert_eq!(resp.tag().as_deref(), Some("DROP TABLE"));
+        let e = session.execute("SELECT * FROM test").await.unwrap_err();
+        assert_eq!(e.code(), "42P01");
+        assert_eq!(table_store.full_scan(&1)?.count(), 0);
+        let sql = "SELECT relname FROM pg_catalog.pg_class";
+        match session.execute(sql).await? {
+            ExecuteResponse::Rows { rows, .. } => assert!(!rows
+                .contains(&Row::new(vec![Datum::Text("test".to_string())]))),
+            r => panic!("unexpected response: {r:?}"),
+        }
+
+        let e = session.execute("DROP TABLE test").await.unwrap_err();
+        assert_eq!(e.code(), "42P01");
+        session.execute("DROP TABLE IF EXISTS test").await?;
+        assert_eq!(
+            session.drain_notices(),
+            [Notice::notice(
+                "00000",
+                "table \"test\" does not exist, skipping"
+            )]
+        );
+        Ok(())
+    }
+}
@@ -4,8 +4,9 @@ pub mod analyzer;
 pub mod context;
 mod ddl;
 mod logical_plan;
+mod optimizer;
 pub mod physical_plan;
-mod planner;
+pub mod planner;
 mod primitive;
 mod visitor;
 
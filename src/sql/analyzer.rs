@@ -1,18 +1,30 @@
 use super::context::{ExprContext, StatementContext};
-use super::primitive::expr::{self, wildcard_column_ref, CoercibleExpr, Expr};
-use super::primitive::func::{add, gt};
+use super::primitive::agg::{self, AggregateExpr};
+use super::primitive::expr::{
+    self, wildcard_column_ref, CoercibleExpr, Expr, ScalarSubquery,
+};
+use super::primitive::func::{
+    add, and, array_create, call_unary, compare, concat, div, modulo, mul,
+    negate, not, numeric_unary, or, round_scale, sub, substring, BinaryFunc,
+    UnaryFunc,
+};
+use super::primitive::sort::SortExpr;
 use super::LogicalPlan;
 use crate::catalog::names::{FullObjectName, PartialObjectName};
 use crate::catalog::CatalogStore;
-use crate::common::error::{FloppyError, Result};
+use crate::common::datetime;
+use crate::common::error::{CatalogError, FloppyError, Result};
 use crate::common::relation::{
-    ColumnName, ColumnRef, ColumnType, RelationDesc,
+    ColumnName, ColumnRef, ColumnType, IndexRange, RelationDesc, Row,
 };
-use crate::common::scalar::ScalarType;
+use crate::common::scalar::{Datum, NumericConstraints, ScalarType};
 use sqlparser::ast::{
-    BinaryOperator, Expr as AstExpr, Ident as AstIdent, Query as AstQuery,
-    Select, SelectItem, SetExpr, Statement as SqlStatement, TableFactor,
-    TableWithJoins, Value as SqlValue,
+    Array as AstArray, Assignment, BinaryOperator, DataType, Expr as AstExpr,
+    Function as AstFunction, FunctionArg, FunctionArgExpr, Ident as AstIdent,
+    JoinConstraint, JoinOperator, Offset, OrderByExpr, Query as AstQuery,
+    Select, SelectItem, SetExpr, SetOperator, Statement as SqlStatement,
+    TableAlias, TableFactor, TableWithJoins, UnaryOperator, Value as SqlValue,
+    Values, With,
 };
 use std::sync::Arc;
 
@@ -22,12 +34,114 @@ pub fn transform_statement(
 ) -> Result<LogicalPlan> {
     match s {
         SqlStatement::Query(q) => transform_query(scx, q),
+        SqlStatement::Update {
+            table,
+            assignments,
+            from,
+            selection,
+        } => transform_update(scx, table, assignments, from, selection),
+        SqlStatement::Explain { analyze: true, .. } => {
+            Err(FloppyError::NotImplemented("EXPLAIN ANALYZE".to_string()))
+        }
+        SqlStatement::Explain {
+            verbose, statement, ..
+        } => Ok(LogicalPlan::Explain {
+            plan: Box::new(transform_statement(scx, statement)?),
+            verbose: *verbose,
+        }),
+        // the transaction control statements change the state of the
+        // session, they are executed by `Session::execute_statement`
+        // rather than planned.
+        SqlStatement::StartTransaction { .. }
+        | SqlStatement::Commit { .. }
+        | SqlStatement::Rollback { .. } => {
+            Err(FloppyError::Plan(format!("{s} cannot be planned")))
+        }
         _ => Err(FloppyError::NotImplemented(format!(
             "statement not implemented yet: {s}",
         ))),
     }
 }
 
+fn transform_update(
+    scx: &StatementContext,
+    table: &TableWithJoins,
+    assignments: &[Assignment],
+    from: &Option<TableWithJoins>,
+    selection: &Option<AstExpr>,
+) -> Result<LogicalPlan> {
+    if from.is_some() {
+        return Err(FloppyError::NotImplemented("UPDATE ... FROM".to_string()));
+    }
+    let name = match &table.relation {
+        TableFactor::Table {
+            name,
+            alias: None,
+            args: None,
+            ..
+        } if table.joins.is_empty() => name,
+        _ => {
+            return Err(FloppyError::NotImplemented(format!(
+                "UPDATE {table} not implemented yet",
+            )))
+        }
+    };
+    let partial_object_name: PartialObjectName = name.try_into()?;
+    let item = scx
+        .catalog
+        .resolve_item(&partial_object_name, &scx.search_path)?;
+    let full_name: FullObjectName = partial_object_name.into();
+    if item.system_rows().is_some() {
+        return Err(FloppyError::Plan(format!(
+            "cannot update system table \"{}\"",
+            full_name.item
+        )));
+    }
+    let rel_desc = item.desc(&full_name)?.into_owned();
+    let ecx = ExprContext {
+        scx: Arc::new(scx.clone()),
+        rel_desc: Arc::new(rel_desc.clone()),
+    };
+
+    let mut columns = vec![];
+    for assignment in assignments {
+        let column = match &assignment.id[..] {
+            [column] => column,
+            _ => {
+                return Err(FloppyError::NotImplemented(format!(
+                    "assignment {assignment} not implemented yet",
+                )))
+            }
+        };
+        let id = rel_desc.column_idx(&column.value)?;
+        if columns.iter().any(|(i, _)| *i == id) {
+            return Err(FloppyError::Plan(format!(
+                "multiple assignments to same column \"{}\"",
+                column.value
+            )));
+        }
+        let ty = &rel_desc.column_type(id)?.scalar_type;
+        let expr =
+            transform_expr(&ecx, &assignment.value)?.cast_to(&ecx, ty)?;
+        columns.push((id, expr));
+    }
+
+    let filter = match selection {
+        Some(selection) => Some(
+            transform_expr(&ecx, selection)?
+                .type_as(&ecx, &ScalarType::Boolean)?,
+        ),
+        None => None,
+    };
+    Ok(LogicalPlan::Update {
+        table_id: item.id(),
+        rel_desc,
+        name: full_name,
+        assignments: columns,
+        filter,
+    })
+}
+
 /// transform_query translate [`sqlparser::ast::Query`] into
 /// a logical sql [`PlannedQuery`] which contains
 /// [`LogicalPlan`] and [`RelationDesc`].
@@ -35,9 +149,187 @@ pub(crate) fn transform_query(
     scx: &StatementContext,
     query: &AstQuery,
 ) -> Result<LogicalPlan> {
-    let set_expr = &query.body;
-    transform_set_expr(scx, set_expr)
-    // todo! order_by, limit, offset, fetch
+    let mut shadowed = vec![];
+    let planned_query = transform_with(scx, &query.with, &mut shadowed)
+        .and_then(|_| {
+            let set_expr = &query.body;
+            let planned_query = transform_set_expr(scx, set_expr)?;
+            let planned_query =
+                transform_order_by(scx, planned_query, &query.order_by)?;
+            transform_limit(scx, planned_query, &query.limit, &query.offset)
+            // todo! fetch
+        });
+    // the CTEs are only visible in the query that defines them.
+    let mut ctes = scx.ctes.borrow_mut();
+    for (name, cte) in shadowed.into_iter().rev() {
+        match cte {
+            Some(cte) => ctes.insert(name, cte),
+            None => ctes.remove(&name),
+        };
+    }
+    planned_query
+}
+
+/// Plans the CTEs of `WITH` in turn and registers them in `scx`, so
+/// that a CTE may be referred to as a table by the later CTEs and the
+/// query. The CTE previously registered under the name of each CTE,
+/// if any, is appended to `shadowed`.
+fn transform_with(
+    scx: &StatementContext,
+    with: &Option<With>,
+    shadowed: &mut Vec<(String, Option<LogicalPlan>)>,
+) -> Result<()> {
+    let Some(with) = with else {
+        return Ok(());
+    };
+    if with.recursive {
+        return Err(FloppyError::NotImplemented(
+            "WITH RECURSIVE not implemented yet".to_string(),
+        ));
+    }
+    for cte in &with.cte_tables {
+        let plan = transform_query(scx, &cte.query)?;
+        let plan = if cte.alias.columns.is_empty() {
+            plan
+        } else {
+            transform_column_alias(plan, &cte.alias)?
+        };
+        let name = cte.alias.name.value.clone();
+        let previous = scx.ctes.borrow_mut().insert(name.clone(), plan);
+        shadowed.push((name, previous));
+    }
+    Ok(())
+}
+
+/// A key of `ORDER BY` is an output column, referred to by its name or
+/// position, or an expression of the input columns of the projection.
+/// The rows are sorted before the projection if there is one, so that
+/// the keys may refer to the columns that are not in the output.
+fn transform_order_by(
+    scx: &StatementContext,
+    plan: LogicalPlan,
+    order_by: &[OrderByExpr],
+) -> Result<LogicalPlan> {
+    if order_by.is_empty() {
+        return Ok(plan);
+    }
+    let rel_desc = plan.rel_desc();
+    let (exprs, input, projection_desc) = match plan {
+        LogicalPlan::Projection {
+            exprs,
+            input,
+            rel_desc,
+        } => (exprs, *input, Some(rel_desc)),
+        plan => (wildcard_column_ref(&rel_desc), plan, None),
+    };
+    let ecx = ExprContext {
+        scx: Arc::new(scx.clone()),
+        rel_desc: Arc::new(input.rel_desc()),
+    };
+    let keys = order_by
+        .iter()
+        .map(
+            |OrderByExpr {
+                 expr,
+                 asc,
+                 nulls_first,
+             }| {
+                let expr = match output_column(&rel_desc, expr)? {
+                    Some(i) => exprs[i].clone(),
+                    None => transform_expr(&ecx, expr)?.type_as_any(&ecx)?,
+                };
+                let asc = asc.unwrap_or(true);
+                Ok(SortExpr {
+                    expr,
+                    asc,
+                    nulls_first: nulls_first.unwrap_or(!asc),
+                })
+            },
+        )
+        .collect::<Result<Vec<_>>>()?;
+    let sort = LogicalPlan::Sort {
+        input: Box::new(input),
+        keys,
+    };
+    Ok(match projection_desc {
+        Some(rel_desc) => LogicalPlan::Projection {
+            exprs,
+            input: Box::new(sort),
+            rel_desc,
+        },
+        None => sort,
+    })
+}
+
+fn transform_limit(
+    scx: &StatementContext,
+    plan: LogicalPlan,
+    limit: &Option<AstExpr>,
+    offset: &Option<Offset>,
+) -> Result<LogicalPlan> {
+    if limit.is_none() && offset.is_none() {
+        return Ok(plan);
+    }
+    let limit = match limit {
+        Some(limit) => row_count(scx, limit, "LIMIT")?,
+        None => None,
+    };
+    let offset = match offset {
+        Some(offset) => row_count(scx, &offset.value, "OFFSET")?.unwrap_or(0),
+        None => 0,
+    };
+    Ok(LogicalPlan::Limit {
+        input: Box::new(plan),
+        limit,
+        offset,
+    })
+}
+
+/// The number of rows of `LIMIT` or `OFFSET`, which is a constant
+/// expression. Returns `None` if it is NULL, like PostgreSQL.
+fn row_count(
+    scx: &StatementContext,
+    expr: &AstExpr,
+    clause: &str,
+) -> Result<Option<usize>> {
+    let ecx = ExprContext {
+        scx: Arc::new(scx.clone()),
+        rel_desc: Arc::new(RelationDesc::empty()),
+    };
+    let expr = transform_expr(&ecx, expr)?.type_as(&ecx, &ScalarType::Int64)?;
+    match expr.evaluate(&ecx, &Row::empty())? {
+        Datum::Null => Ok(None),
+        Datum::Int64(n) if n >= 0 => Ok(Some(n as usize)),
+        Datum::Int64(_) => {
+            Err(FloppyError::Plan(format!("{clause} must not be negative")))
+        }
+        other => Err(FloppyError::Internal(format!(
+            "{clause} evaluates to non integer: {other:?}"
+        ))),
+    }
+}
+
+/// The output column of `rel_desc` that the `ORDER BY` key `expr`
+/// refers to, if it is a column name or a position.
+fn output_column(
+    rel_desc: &RelationDesc,
+    expr: &AstExpr,
+) -> Result<Option<usize>> {
+    match expr {
+        AstExpr::Value(SqlValue::Number(n, _)) => {
+            let arity = rel_desc.column_types().len();
+            match n.parse::<usize>() {
+                Ok(position) if (1..=arity).contains(&position) => {
+                    Ok(Some(position - 1))
+                }
+                _ => Err(FloppyError::Plan(format!(
+                    "ORDER BY position {n} is not in select list"
+                ))),
+            }
+        }
+        AstExpr::Identifier(name) => Ok(rel_desc.column_idx(&name.value).ok()),
+        _ => Ok(None),
+    }
 }
 
 fn transform_set_expr(
@@ -46,12 +338,206 @@ fn transform_set_expr(
 ) -> Result<LogicalPlan> {
     match set_expr {
         SetExpr::Select(select) => transform_select(scx, select),
+        SetExpr::Values(values) => transform_values(scx, values),
+        SetExpr::SetOperation {
+            op: SetOperator::Union,
+            all,
+            left,
+            right,
+        } => transform_union(scx, left, right, *all),
         _ => Err(FloppyError::NotImplemented(format!(
             "Query {set_expr} not implemented yet",
         ))),
     }
 }
 
+/// Every row of `VALUES` has the same number of columns, the type of
+/// a column is the common type of the values in it.
+fn transform_values(
+    scx: &StatementContext,
+    values: &Values,
+) -> Result<LogicalPlan> {
+    let ecx = ExprContext {
+        scx: Arc::new(scx.clone()),
+        rel_desc: Arc::new(RelationDesc::empty()),
+    };
+    let rows = values
+        .0
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|e| transform_expr(&ecx, e))
+                .collect::<Result<Vec<_>>>()
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let arity = rows.first().map_or(0, |row| row.len());
+    if rows.iter().any(|row| row.len() != arity) {
+        return Err(FloppyError::Plan(
+            "VALUES lists must all be the same length".to_string(),
+        ));
+    }
+
+    let scalar_types = (0..arity)
+        .map(|i| {
+            let types = rows
+                .iter()
+                .filter_map(|row| row[i].typ(&ecx))
+                .map(|ty| ty.scalar_type)
+                .collect::<Vec<_>>();
+            unify_types("VALUES", &types)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let rows = rows
+        .iter()
+        .map(|row| {
+            row.iter()
+                .zip(scalar_types.iter())
+                .map(|(e, ty)| e.cast_to(&ecx, ty))
+                .collect::<Result<Vec<_>>>()
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let column_types = scalar_types
+        .into_iter()
+        .enumerate()
+        .map(|(i, ty)| {
+            let nullable = rows.iter().any(|row| row[i].typ(&ecx).nullable);
+            ColumnType::new(ty, nullable)
+        })
+        .collect::<Vec<_>>();
+    let column_names = (1..=arity)
+        .map(|i| format!("column{i}"))
+        .collect::<Vec<_>>();
+    Ok(LogicalPlan::Values {
+        rows,
+        rel_desc: RelationDesc::new(column_types, column_names, vec![], vec![]),
+    })
+}
+
+/// The inputs of `UNION` have the same number of columns, the type of
+/// a column is the common type of the columns of the inputs.
+fn transform_union(
+    scx: &StatementContext,
+    left: &SetExpr,
+    right: &SetExpr,
+    all: bool,
+) -> Result<LogicalPlan> {
+    let left = transform_set_expr(scx, left)?;
+    let right = transform_set_expr(scx, right)?;
+    let left_desc = left.rel_desc();
+    let right_desc = right.rel_desc();
+    if left_desc.column_types().len() != right_desc.column_types().len() {
+        return Err(FloppyError::Plan(
+            "each UNION query must have the same number of columns".to_string(),
+        ));
+    }
+
+    let column_types = left_desc
+        .iter_types()
+        .zip(right_desc.iter_types())
+        .map(|(ty1, ty2)| {
+            let types = [ty1.scalar_type.clone(), ty2.scalar_type.clone()];
+            let scalar_type = unify_types("UNION", &types)?;
+            Ok(ColumnType::new(scalar_type, ty1.nullable || ty2.nullable))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let scalar_types = column_types
+        .iter()
+        .map(|ty| ty.scalar_type.clone())
+        .collect::<Vec<_>>();
+
+    let mut inputs = vec![];
+    for input in [left, right] {
+        match cast_columns(scx, input, &scalar_types)? {
+            LogicalPlan::Union {
+                inputs: nested,
+                all: nested_all,
+                ..
+            } if nested_all == all => inputs.extend(nested),
+            input => inputs.push(input),
+        }
+    }
+    Ok(LogicalPlan::Union {
+        inputs,
+        all,
+        rel_desc: RelationDesc::new(
+            column_types,
+            left_desc.column_names().clone(),
+            vec![],
+            vec![],
+        ),
+    })
+}
+
+/// Casts the columns of `input` to `scalar_types`. The expressions
+/// of a projection are cast directly, other inputs are wrapped in a
+/// projection that casts their columns.
+fn cast_columns(
+    scx: &StatementContext,
+    input: LogicalPlan,
+    scalar_types: &[ScalarType],
+) -> Result<LogicalPlan> {
+    let rel_desc = input.rel_desc();
+    if rel_desc
+        .iter_types()
+        .zip(scalar_types)
+        .all(|(ty, scalar_type)| ty.scalar_type.base_eq(scalar_type))
+    {
+        return Ok(input);
+    }
+    let (exprs, input) = match input {
+        LogicalPlan::Projection { exprs, input, .. } => (exprs, *input),
+        input => (wildcard_column_ref(&rel_desc), input),
+    };
+    let ecx = ExprContext {
+        scx: Arc::new(scx.clone()),
+        rel_desc: Arc::new(input.rel_desc()),
+    };
+    let exprs = exprs
+        .iter()
+        .zip(scalar_types)
+        .map(|(e, ty)| e.cast_to(&ecx, ty))
+        .collect::<Result<Vec<_>>>()?;
+    let column_types = exprs.iter().map(|e| e.typ(&ecx)).collect();
+    Ok(LogicalPlan::Projection {
+        exprs,
+        input: Box::new(input),
+        rel_desc: RelationDesc::new(
+            column_types,
+            rel_desc.column_names().clone(),
+            vec![],
+            vec![],
+        ),
+    })
+}
+
+/// The common type of `types` in the `context` clause, numeric types
+/// are promoted to numeric, or to float if any of them is a float.
+/// Values of unknown type are text.
+fn unify_types(context: &str, types: &[ScalarType]) -> Result<ScalarType> {
+    let first = match types.first() {
+        Some(ty) => ty,
+        None => return Ok(ScalarType::Text),
+    };
+    if types.iter().all(|ty| ty.base_eq(first)) {
+        return Ok(first.clone());
+    }
+    if types.iter().all(|ty| ty.is_numeric()) {
+        let is_float = types
+            .iter()
+            .any(|ty| matches!(ty, ScalarType::Float32 | ScalarType::Float64));
+        return Ok(if is_float {
+            ScalarType::Float64
+        } else {
+            ScalarType::Numeric(None)
+        });
+    }
+    let other = types.iter().find(|ty| !ty.base_eq(first)).unwrap();
+    Err(FloppyError::Plan(format!(
+        "{context} types {first} and {other} cannot be matched"
+    )))
+}
+
 fn transform_select(
     scx: &StatementContext,
     select: &Select,
@@ -59,47 +545,139 @@ fn transform_select(
     let planned_query = transform_table_with_joins(scx, &select.from)?;
     let planned_query =
         transform_filter(scx, planned_query, &select.selection)?;
-    transform_projection(scx, planned_query, &select.projection)
+    let planned_query = transform_aggregate(
+        scx,
+        planned_query,
+        &select.group_by,
+        &select.projection,
+        &select.having,
+    )?;
+    // aggregates in `HAVING` refer to the output of the aggregate.
+    let planned_query = transform_filter(scx, planned_query, &select.having)?;
+    let planned_query =
+        transform_projection(scx, planned_query, &select.projection)?;
+    if select.distinct {
+        Ok(LogicalPlan::Distinct {
+            input: Box::new(planned_query),
+        })
+    } else {
+        Ok(planned_query)
+    }
 }
 
 fn transform_table_with_joins(
     scx: &StatementContext,
-    from: &Vec<TableWithJoins>,
+    from: &[TableWithJoins],
 ) -> Result<LogicalPlan> {
-    if from.is_empty() {
+    // the relations separated by commas are cross joined.
+    let mut plans = from.iter().map(|t| transform_joins(scx, t));
+    let Some(plan) = plans.next() else {
         return Ok(LogicalPlan::Empty);
-    }
+    };
+    plans.fold(plan, |left, right| {
+        let (left, right) = (left?, right?);
+        // checks that the relations have different names.
+        left.rel_desc().merge(&right.rel_desc())?;
+        Ok(LogicalPlan::Join {
+            left: Box::new(left),
+            right: Box::new(right),
+            predicate: expr::literal_true(),
+        })
+    })
+}
 
-    // we only consider single table without Join for now.
-    // todo JOIN
-    if from.len() > 1 {
-        return Err(FloppyError::NotImplemented(format!(
-            "FROM with multiple table is not implemented yet: {from:?}",
-        )));
+/// Joins the relation of `table` with the relations of its `JOIN`s
+/// from left to right. Only inner and cross joins are supported.
+fn transform_joins(
+    scx: &StatementContext,
+    table: &TableWithJoins,
+) -> Result<LogicalPlan> {
+    let mut plan = transform_table_factor(scx, &table.relation)?;
+    for join in &table.joins {
+        let right = transform_table_factor(scx, &join.relation)?;
+        let rel_desc = plan.rel_desc().merge(&right.rel_desc())?;
+        let predicate = match &join.join_operator {
+            JoinOperator::CrossJoin => expr::literal_true(),
+            JoinOperator::Inner(JoinConstraint::On(on)) => {
+                let ecx = ExprContext {
+                    scx: Arc::new(scx.clone()),
+                    rel_desc: Arc::new(rel_desc),
+                };
+                transform_expr(&ecx, on)?.type_as(&ecx, &ScalarType::Boolean)?
+            }
+            _ => {
+                return Err(FloppyError::NotImplemented(format!(
+                    "join {join} not implemented yet",
+                )))
+            }
+        };
+        plan = LogicalPlan::Join {
+            left: Box::new(plan),
+            right: Box::new(right),
+            predicate,
+        };
     }
+    Ok(plan)
+}
 
-    let table_factor = &from[0].relation;
+fn transform_table_factor(
+    scx: &StatementContext,
+    table_factor: &TableFactor,
+) -> Result<LogicalPlan> {
     match table_factor {
-        // alias, args, with_hints are not supported
-        TableFactor::Table { alias: Some(_), .. } => {
-            Err(FloppyError::NotImplemented(format!(
-                "table alias {table_factor} not implemented yet",
-            )))
-        }
+        // args, with_hints are not supported
         TableFactor::Table { args: Some(_), .. } => {
             Err(FloppyError::NotImplemented(format!(
                 "table args {table_factor} not implemented yet",
             )))
         }
-        TableFactor::Table { name, .. } => {
+        TableFactor::Table { name, alias, .. } => {
             let partial_object_name: PartialObjectName = name.try_into()?;
-            let table = scx.catalog.resolve_item(&partial_object_name)?;
-            let full_name: FullObjectName = partial_object_name.into();
-            Ok(LogicalPlan::Table {
-                table_id: table.id(),
-                rel_desc: table.desc(&full_name)?.into_owned(),
-                name: full_name,
-            })
+            // an unqualified name refers to a CTE before a table.
+            let cte = match partial_object_name.schema {
+                None => {
+                    scx.ctes.borrow().get(&partial_object_name.item).cloned()
+                }
+                Some(_) => None,
+            };
+            let plan = match cte {
+                Some(cte) => cte,
+                None => {
+                    let table = scx
+                        .catalog
+                        .resolve_item(&partial_object_name, &scx.search_path)?;
+                    let full_name: FullObjectName =
+                        partial_object_name.clone().into();
+                    LogicalPlan::Table {
+                        table_id: table.id(),
+                        rel_desc: table.desc(&full_name)?.into_owned(),
+                        name: full_name,
+                        range: IndexRange::full(),
+                        projection: None,
+                    }
+                }
+            };
+            transform_table_alias(
+                plan,
+                &partial_object_name.item,
+                alias.as_ref(),
+            )
+        }
+        TableFactor::Derived { lateral: true, .. } => {
+            Err(FloppyError::NotImplemented(format!(
+                "LATERAL {table_factor} not implemented yet",
+            )))
+        }
+        TableFactor::Derived {
+            subquery, alias, ..
+        } => {
+            let alias = alias.as_ref().ok_or_else(|| {
+                FloppyError::Plan(
+                    "subquery in FROM must have an alias".to_string(),
+                )
+            })?;
+            let plan = transform_query(scx, subquery)?;
+            transform_table_alias(plan, &alias.name.value, Some(alias))
         }
         _ => Err(FloppyError::NotImplemented(format!(
             "table factor {table_factor} not implemented yet",
@@ -107,6 +685,88 @@ fn transform_table_with_joins(
     }
 }
 
+/// Names the relation `plan` in `FROM` by `alias`, or by `name` if
+/// there is no alias, which qualifies its columns. The columns may be
+/// renamed by the alias too.
+fn transform_table_alias(
+    plan: LogicalPlan,
+    name: &str,
+    alias: Option<&TableAlias>,
+) -> Result<LogicalPlan> {
+    let (plan, name) = match alias {
+        Some(alias) if !alias.columns.is_empty() => (
+            transform_column_alias(plan, alias)?,
+            alias.name.value.as_str(),
+        ),
+        Some(alias) => (plan, alias.name.value.as_str()),
+        None => (plan, name),
+    };
+    Ok(match plan {
+        LogicalPlan::Table {
+            table_id,
+            rel_desc,
+            name: table_name,
+            range,
+            projection,
+        } => LogicalPlan::Table {
+            table_id,
+            rel_desc: rel_desc.with_qualifier(name),
+            name: table_name,
+            range,
+            projection,
+        },
+        LogicalPlan::Projection {
+            exprs,
+            input,
+            rel_desc,
+        } => LogicalPlan::Projection {
+            exprs,
+            input,
+            rel_desc: rel_desc.with_qualifier(name),
+        },
+        plan => {
+            let rel_desc = plan.rel_desc().with_qualifier(name);
+            LogicalPlan::Projection {
+                exprs: wildcard_column_ref(&rel_desc),
+                input: Box::new(plan),
+                rel_desc,
+            }
+        }
+    })
+}
+
+/// Renames the leading columns of `input` to the columns of `alias`.
+fn transform_column_alias(
+    input: LogicalPlan,
+    alias: &TableAlias,
+) -> Result<LogicalPlan> {
+    let rel_desc = input.rel_desc();
+    let arity = rel_desc.column_types().len();
+    if alias.columns.len() > arity {
+        return Err(FloppyError::Plan(format!(
+            "table \"{}\" has {arity} columns available but {} columns \
+             specified",
+            alias.name,
+            alias.columns.len()
+        )));
+    }
+    let mut column_names = rel_desc.column_names().clone();
+    for (name, column) in column_names.iter_mut().zip(alias.columns.iter()) {
+        *name = column.value.clone();
+    }
+    let rel_desc = RelationDesc::new(
+        rel_desc.column_types().clone(),
+        column_names,
+        vec![],
+        vec![],
+    );
+    Ok(LogicalPlan::Projection {
+        exprs: wildcard_column_ref(&input.rel_desc()),
+        input: Box::new(input),
+        rel_desc,
+    })
+}
+
 fn transform_filter(
     scx: &StatementContext,
     input: LogicalPlan,
@@ -129,6 +789,116 @@ fn transform_filter(
     }
 }
 
+/// Plan the `GROUP BY` clause and the aggregate function calls
+/// in the select list and `HAVING`. Each aggregate call becomes a
+/// column of the output relation named after the call itself, so
+/// that the projection can refer to it by name.
+fn transform_aggregate(
+    scx: &StatementContext,
+    input: LogicalPlan,
+    group_by: &[AstExpr],
+    projection: &[SelectItem],
+    having: &Option<AstExpr>,
+) -> Result<LogicalPlan> {
+    let mut funcs = vec![];
+    for item in projection {
+        match item {
+            SelectItem::UnnamedExpr(expr)
+            | SelectItem::ExprWithAlias { expr, .. } => {
+                collect_aggregates(expr, &mut funcs)
+            }
+            _ => {}
+        }
+    }
+    if let Some(having) = having {
+        collect_aggregates(having, &mut funcs);
+    }
+
+    if funcs.is_empty() && group_by.is_empty() && having.is_none() {
+        return Ok(input);
+    }
+
+    let ecx = ExprContext {
+        scx: Arc::new(scx.clone()),
+        rel_desc: Arc::new(input.rel_desc()),
+    };
+
+    let group_by = group_by
+        .iter()
+        .map(|e| transform_expr(&ecx, e)?.type_as_any(&ecx))
+        .collect::<Result<Vec<Expr>>>()?;
+
+    let aggregates = funcs
+        .iter()
+        .map(|f| transform_aggregate_func(&ecx, f))
+        .collect::<Result<Vec<AggregateExpr>>>()?;
+
+    let mut column_names = group_by
+        .iter()
+        .map(|e| match e {
+            Expr::Column(ColumnRef { name, .. }) => name.to_string(),
+            _ => format!("{e}"),
+        })
+        .collect::<Vec<String>>();
+    column_names.extend(funcs.iter().map(|f| f.to_string()));
+
+    let mut column_types = group_by
+        .iter()
+        .map(|e| e.typ(&ecx))
+        .collect::<Vec<ColumnType>>();
+    column_types.extend(aggregates.iter().map(|a| a.typ(&ecx)));
+
+    Ok(LogicalPlan::Aggregate {
+        input: Box::new(input),
+        group_by,
+        aggregates,
+        rel_desc: RelationDesc::new(column_types, column_names, vec![], vec![]),
+    })
+}
+
+/// Collect the distinct aggregate function calls in `expr`.
+fn collect_aggregates<'a>(expr: &'a AstExpr, funcs: &mut Vec<&'a AstFunction>) {
+    match expr {
+        AstExpr::Function(f) if agg::is_aggregate_func(&f.name.to_string()) => {
+            if !funcs.iter().any(|e| e.to_string() == f.to_string()) {
+                funcs.push(f);
+            }
+        }
+        AstExpr::BinaryOp { left, right, .. } => {
+            collect_aggregates(left, funcs);
+            collect_aggregates(right, funcs);
+        }
+        AstExpr::UnaryOp { expr, .. } | AstExpr::Nested(expr) => {
+            collect_aggregates(expr, funcs)
+        }
+        _ => {}
+    }
+}
+
+fn transform_aggregate_func(
+    ecx: &ExprContext,
+    func: &AstFunction,
+) -> Result<AggregateExpr> {
+    if func.args.len() != 1 {
+        return Err(FloppyError::Plan(format!(
+            "aggregate function {func} takes exactly one argument"
+        )));
+    }
+
+    let expr = match &func.args[0] {
+        FunctionArg::Unnamed(FunctionArgExpr::Wildcard) => None,
+        FunctionArg::Unnamed(FunctionArgExpr::Expr(e)) => {
+            Some(transform_expr(ecx, e)?.type_as_any(ecx)?)
+        }
+        arg => {
+            return Err(FloppyError::NotImplemented(format!(
+                "aggregate argument not supported: {arg}"
+            )))
+        }
+    };
+    agg::aggregate(ecx, &func.name.to_string(), expr, func.distinct)
+}
+
 struct ProjectionCtx {
     expr: Expr,
     column_name: ColumnName,
@@ -159,12 +929,10 @@ fn transform_projection(
             //     typ,
             // })
         })
-        .collect::<Result<Vec<Vec<CoercibleExpr>>>>()?;
+        .collect::<Result<Vec<Vec<(CoercibleExpr, Option<ColumnName>)>>>>()?;
 
-    let exprs = exprs
-        .into_iter()
-        .flat_map(|e| e.into_iter())
-        .collect::<Vec<CoercibleExpr>>();
+    let (exprs, aliases): (Vec<CoercibleExpr>, Vec<Option<ColumnName>>) =
+        exprs.into_iter().flat_map(|e| e.into_iter()).unzip();
 
     let exprs = exprs
         .into_iter()
@@ -181,8 +949,10 @@ fn transform_projection(
     //     .collect::<Vec<ColumnName>>();
     let column_names = exprs
         .iter()
-        .map(|e| match e {
-            Expr::Column(ColumnRef { name, .. }) => name.to_string(),
+        .zip(aliases)
+        .map(|(e, alias)| match (e, alias) {
+            (_, Some(alias)) => alias,
+            (Expr::Column(ColumnRef { name, .. }), None) => name.to_string(),
             _ => "?column?".to_string(),
         })
         .collect::<Vec<String>>();
@@ -203,16 +973,42 @@ fn transform_projection(
     })
 }
 
+/// The expressions of a select item and their aliases, if any.
 fn transform_select_item(
     ecx: &ExprContext,
     item: &SelectItem,
-) -> Result<Vec<CoercibleExpr>> {
+) -> Result<Vec<(CoercibleExpr, Option<ColumnName>)>> {
     match item {
-        SelectItem::UnnamedExpr(expr) => Ok(vec![transform_expr(ecx, expr)?]),
+        SelectItem::UnnamedExpr(expr) => {
+            Ok(vec![(transform_expr(ecx, expr)?, None)])
+        }
+        SelectItem::ExprWithAlias { expr, alias } => Ok(vec![(
+            transform_expr(ecx, expr)?,
+            Some(alias.value.clone()),
+        )]),
         SelectItem::Wildcard => Ok(wildcard_column_ref(&ecx.rel_desc)
             .into_iter()
-            .map(|e| e.into())
-            .collect::<Vec<CoercibleExpr>>()),
+            .map(|e| (e.into(), None))
+            .collect()),
+        // `t.*` is the columns of the relation `t` in `FROM`.
+        SelectItem::QualifiedWildcard(name) => {
+            let qualifier = &name.0[name.0.len() - 1].value;
+            let columns = ecx.rel_desc.qualified_columns(qualifier);
+            if columns.is_empty() {
+                return Err(FloppyError::Catalog(CatalogError::TableNotFound(
+                    format!(
+                        "missing FROM-clause entry for table \"{qualifier}\""
+                    ),
+                )));
+            }
+            Ok(columns
+                .into_iter()
+                .map(|id| {
+                    let name = ecx.rel_desc.column_name(id).to_string();
+                    (Expr::Column(ColumnRef { id, name }).into(), None)
+                })
+                .collect())
+        }
         _ => Err(FloppyError::NotImplemented(format!(
             "select item not supported: {item}",
         ))),
@@ -226,15 +1022,270 @@ pub(crate) fn transform_expr(
     match sql_expr {
         AstExpr::Value(v) => transform_literal(ecx, v),
         AstExpr::Identifier(name) => transform_identifier(ecx, name),
+        AstExpr::CompoundIdentifier(names) => {
+            transform_compound_identifier(ecx, names)
+        }
         AstExpr::BinaryOp { left, op, right } => {
             transform_binary_op(ecx, left, op, right)
         }
+        AstExpr::Function(func) => transform_function(ecx, func),
+        AstExpr::Between {
+            expr,
+            negated,
+            low,
+            high,
+        } => transform_between(ecx, expr, *negated, low, high),
+        AstExpr::Nested(expr) => transform_expr(ecx, expr),
+        AstExpr::Subquery(query) => transform_scalar_subquery(ecx, query),
+        AstExpr::Array(AstArray { elem, .. }) => transform_array(ecx, elem),
+        // `DATE '2024-01-01'`, the string is cast to the type.
+        AstExpr::TypedString { data_type, value } => {
+            let ty = transform_data_type(data_type)?;
+            let expr = CoercibleExpr::LiteralString(value.clone());
+            Ok(expr.cast_to(ecx, &ty)?.into())
+        }
+        AstExpr::Substring {
+            expr,
+            substring_from,
+            substring_for,
+        } => transform_substring(
+            ecx,
+            expr,
+            substring_from.as_deref(),
+            substring_for.as_deref(),
+        ),
+        AstExpr::IsNull(expr) => {
+            let expr = transform_expr(ecx, expr)?.type_as_any(ecx)?;
+            Ok(call_unary(UnaryFunc::IsNull, expr).into())
+        }
+        AstExpr::IsNotNull(expr) => {
+            let expr = transform_expr(ecx, expr)?.type_as_any(ecx)?;
+            Ok(not(call_unary(UnaryFunc::IsNull, expr)).into())
+        }
+        AstExpr::IsTrue(expr) => {
+            let expr = transform_expr(ecx, expr)?;
+            let expr = expr.type_as(ecx, &ScalarType::Boolean)?;
+            Ok(call_unary(UnaryFunc::IsTrue, expr).into())
+        }
+        AstExpr::IsFalse(expr) => {
+            let expr = transform_expr(ecx, expr)?;
+            let expr = expr.type_as(ecx, &ScalarType::Boolean)?;
+            Ok(call_unary(UnaryFunc::IsFalse, expr).into())
+        }
+        AstExpr::UnaryOp {
+            op: UnaryOperator::Not,
+            expr,
+        } => {
+            let expr = transform_expr(ecx, expr)?;
+            Ok(not(expr.type_as(ecx, &ScalarType::Boolean)?).into())
+        }
+        AstExpr::UnaryOp {
+            op: UnaryOperator::Minus,
+            expr,
+        } => {
+            let expr = transform_expr(ecx, expr)?.type_as_any(ecx)?;
+            Ok(negate(ecx, &expr)?.into())
+        }
         _ => Err(FloppyError::NotImplemented(format!(
             "Unsupported expression {sql_expr}",
         ))),
     }
 }
 
+/// A subquery used as an expression, which returns a single column.
+/// It may not refer to the columns of the outer query.
+fn transform_scalar_subquery(
+    ecx: &ExprContext,
+    query: &AstQuery,
+) -> Result<CoercibleExpr> {
+    let plan = transform_query(&ecx.scx, query)?;
+    let column_type = match plan.rel_desc().column_types().as_slice() {
+        [column_type] => column_type.clone(),
+        _ => {
+            return Err(FloppyError::Plan(
+                "subquery must return only one column".to_string(),
+            ))
+        }
+    };
+    Ok(Expr::ScalarSubquery(ScalarSubquery::new(plan, column_type)).into())
+}
+
+fn transform_function(
+    ecx: &ExprContext,
+    func: &AstFunction,
+) -> Result<CoercibleExpr> {
+    if agg::is_aggregate_func(&func.name.to_string()) {
+        // aggregates are computed by `LogicalPlan::Aggregate`, which
+        // outputs a column named after the function call.
+        let name = func.to_string();
+        return match ecx.rel_desc.column_idx(&name) {
+            Ok(id) => Ok(Expr::Column(ColumnRef { id, name }).into()),
+            Err(_) => Err(FloppyError::Plan(format!(
+                "aggregate functions are not allowed here: {func}"
+            ))),
+        };
+    }
+    let name = func.name.to_string().to_lowercase();
+    let is_scalar_func = matches!(
+        name.as_str(),
+        "length"
+            | "upper"
+            | "lower"
+            | "abs"
+            | "ceil"
+            | "ceiling"
+            | "floor"
+            | "round"
+            | "mod"
+    );
+    if !is_scalar_func {
+        return Err(FloppyError::NotImplemented(format!(
+            "function not implemented yet: {func}"
+        )));
+    }
+    let args = func
+        .args
+        .iter()
+        .map(|arg| match arg {
+            FunctionArg::Unnamed(FunctionArgExpr::Expr(e)) => {
+                transform_expr(ecx, e)
+            }
+            arg => Err(FloppyError::NotImplemented(format!(
+                "function argument not supported: {arg}"
+            ))),
+        })
+        .collect::<Result<Vec<CoercibleExpr>>>()?;
+    let expr = match (name.as_str(), &args[..]) {
+        ("length", [arg]) => {
+            call_unary(UnaryFunc::Length, arg.type_as(ecx, &ScalarType::Text)?)
+        }
+        ("upper", [arg]) => {
+            call_unary(UnaryFunc::Upper, arg.type_as(ecx, &ScalarType::Text)?)
+        }
+        ("lower", [arg]) => {
+            call_unary(UnaryFunc::Lower, arg.type_as(ecx, &ScalarType::Text)?)
+        }
+        ("abs" | "floor" | "round", [arg]) => {
+            numeric_unary(ecx, &name, &arg.type_as_any(ecx)?)?
+        }
+        ("ceil" | "ceiling", [arg]) => {
+            numeric_unary(ecx, "ceil", &arg.type_as_any(ecx)?)?
+        }
+        ("round", [arg, scale]) => {
+            let arg = arg.type_as_any(ecx)?;
+            let arg = match arg.typ(ecx).scalar_type {
+                // an integer is rounded as a numeric.
+                ScalarType::Int64 => {
+                    arg.cast_to(ecx, &ScalarType::Numeric(None))?
+                }
+                _ => arg,
+            };
+            round_scale(ecx, &arg, &scale.type_as(ecx, &ScalarType::Int64)?)?
+        }
+        ("mod", [left, right]) => {
+            return transform_bop_modulo(ecx, left.clone(), right.clone())
+        }
+        _ => {
+            return Err(FloppyError::Plan(format!(
+                "function {} does not take {} arguments",
+                func.name,
+                args.len()
+            )))
+        }
+    };
+    Ok(expr.into())
+}
+
+/// `SUBSTRING(expr [FROM start] [FOR count])`, `start` is 1 if it
+/// is omitted.
+fn transform_substring(
+    ecx: &ExprContext,
+    expr: &AstExpr,
+    from: Option<&AstExpr>,
+    count: Option<&AstExpr>,
+) -> Result<CoercibleExpr> {
+    let mut exprs =
+        vec![transform_expr(ecx, expr)?.type_as(ecx, &ScalarType::Text)?];
+    exprs.push(match from {
+        Some(from) => {
+            transform_expr(ecx, from)?.type_as(ecx, &ScalarType::Int64)?
+        }
+        None => expr::literal_i64(1),
+    });
+    if let Some(count) = count {
+        exprs.push(
+            transform_expr(ecx, count)?.type_as(ecx, &ScalarType::Int64)?,
+        );
+    }
+    Ok(substring(exprs).into())
+}
+
+/// `ARRAY[e1, e2, ...]`, the elements are converted to their common
+/// type, see [`array_elem_type`].
+fn transform_array(
+    ecx: &ExprContext,
+    elems: &[AstExpr],
+) -> Result<CoercibleExpr> {
+    let elems = elems
+        .iter()
+        .map(|e| transform_expr(ecx, e))
+        .collect::<Result<Vec<CoercibleExpr>>>()?;
+    let elem_type = array_elem_type(ecx, &elems)?;
+    let exprs = elems
+        .iter()
+        .map(|e| e.cast_to(ecx, &elem_type))
+        .collect::<Result<Vec<Expr>>>()?;
+    Ok(array_create(elem_type, exprs).into())
+}
+
+/// The common type of the elements of an array. Integers, numerics
+/// and floats are unified to the widest of them, the other types must
+/// be the same. Like PostgreSQL, the elements are text if none of
+/// them has a type, eg `ARRAY['a', NULL]`.
+fn array_elem_type(
+    ecx: &ExprContext,
+    elems: &[CoercibleExpr],
+) -> Result<ScalarType> {
+    if elems.is_empty() {
+        return Err(FloppyError::Plan(
+            "cannot determine type of empty array".to_string(),
+        ));
+    }
+    let mut elem_type: Option<ScalarType> = None;
+    for ty in elems.iter().filter_map(|e| e.typ(ecx)) {
+        let ty = ty.scalar_type;
+        if let ScalarType::Array(_) = ty {
+            return Err(FloppyError::NotImplemented(
+                "multidimensional arrays".to_string(),
+            ));
+        }
+        elem_type = Some(match elem_type {
+            None => ty,
+            Some(prev) if prev == ty => prev,
+            Some(prev) if prev.is_numeric() && ty.is_numeric() => {
+                match (prev, ty) {
+                    (ScalarType::Float32, ScalarType::Float32) => {
+                        ScalarType::Float32
+                    }
+                    (ScalarType::Float32 | ScalarType::Float64, _)
+                    | (_, ScalarType::Float32 | ScalarType::Float64) => {
+                        ScalarType::Float64
+                    }
+                    // an integer is promoted to numeric, and numerics of
+                    // different constraints are unconstrained.
+                    _ => ScalarType::Numeric(None),
+                }
+            }
+            Some(prev) => {
+                return Err(FloppyError::Plan(format!(
+                    "ARRAY types {prev} and {ty} cannot be matched"
+                )))
+            }
+        });
+    }
+    Ok(elem_type.unwrap_or(ScalarType::Text))
+}
+
 fn transform_literal(
     ecx: &ExprContext,
     literal: &SqlValue,
@@ -250,6 +1301,26 @@ fn transform_literal(
         SqlValue::Boolean(b) => Ok(expr::literal_boolean(*b).into()),
         SqlValue::Null => Ok(CoercibleExpr::LiteralNull),
         SqlValue::Placeholder(p) => transform_parameter(ecx, p.to_string()),
+        SqlValue::Interval {
+            value,
+            leading_field,
+            leading_precision: None,
+            last_field: None,
+            fractional_seconds_precision: None,
+        } => match value.as_ref() {
+            AstExpr::Value(SqlValue::SingleQuotedString(s)) => {
+                // `INTERVAL '2' DAY` is a quantity of the field.
+                let s = match leading_field {
+                    Some(field) => format!("{s} {field}"),
+                    None => s.to_string(),
+                };
+                let interval = datetime::parse_interval(&s)?;
+                Ok(expr::literal_interval(interval).into())
+            }
+            _ => Err(FloppyError::NotImplemented(format!(
+                "interval must be a string literal: {literal}"
+            ))),
+        },
         _ => Err(FloppyError::NotImplemented(format!(
             "literal not supported: {literal}",
         ))),
@@ -266,6 +1337,27 @@ fn transform_identifier(
     Ok(Expr::Column(ColumnRef { id, name }).into())
 }
 
+/// A column qualified by the name of its relation, eg `t1.c1`.
+fn transform_compound_identifier(
+    ecx: &ExprContext,
+    names: &[AstIdent],
+) -> Result<CoercibleExpr> {
+    let [qualifier, name] = names else {
+        return Err(FloppyError::NotImplemented(format!(
+            "column reference {} not implemented yet",
+            names
+                .iter()
+                .map(|i| i.value.as_str())
+                .collect::<Vec<_>>()
+                .join("."),
+        )));
+    };
+    let rel_desc = ecx.rel_desc.clone();
+    let id = rel_desc.qualified_column_idx(&qualifier.value, &name.value)?;
+    let name = rel_desc.column_name(id).to_string();
+    Ok(Expr::Column(ColumnRef { id, name }).into())
+}
+
 fn transform_binary_op(
     ecx: &ExprContext,
     left: &AstExpr,
@@ -277,6 +1369,8 @@ fn transform_binary_op(
     match op {
         BinaryOperator::Plus => transform_bop_plus(ecx, left, right),
         BinaryOperator::Minus => transform_bop_minus(ecx, left, right),
+        BinaryOperator::Multiply => transform_bop_multiply(ecx, left, right),
+        BinaryOperator::Divide => transform_bop_divide(ecx, left, right),
         BinaryOperator::Gt => transform_bop_gt(ecx, left, right),
         BinaryOperator::Lt => transform_bop_lt(ecx, left, right),
         BinaryOperator::GtEq => transform_bop_gte(ecx, left, right),
@@ -285,6 +1379,8 @@ fn transform_binary_op(
         BinaryOperator::NotEq => transform_bop_neq(ecx, left, right),
         BinaryOperator::And => transform_bop_and(ecx, left, right),
         BinaryOperator::Or => transform_bop_or(ecx, left, right),
+        BinaryOperator::Modulo => transform_bop_modulo(ecx, left, right),
+        BinaryOperator::StringConcat => transform_bop_concat(ecx, left, right),
         _ => Err(FloppyError::NotImplemented(format!(
             "binary op not implemented: {op:?}",
         ))),
@@ -322,25 +1418,83 @@ fn transform_parameter(ecx: &ExprContext, p: String) -> Result<CoercibleExpr> {
 /// SELECT '1' + NULL;
 /// ```
 ///
-///  At least one of the expression is a numeric type.
+///  At least one of the expression is a numeric type, or a timestamp
+///  or an interval, see [`interval_op_cast`].
 fn transform_bop_plus(
     ecx: &ExprContext,
     cexpr1: CoercibleExpr,
     cexpr2: CoercibleExpr,
 ) -> Result<CoercibleExpr> {
-    let expr1 = cexpr1.type_as_any(ecx)?;
-    let expr2 = cexpr2.type_as_any(ecx)?;
-
-    let (expr1, expr2) = numeric_op_cast(ecx, expr1, expr2)?;
+    let (expr1, expr2) = match interval_op_cast(ecx, &cexpr1, &cexpr2)? {
+        Some(operands) => operands,
+        None => {
+            let (expr1, expr2) = coerce_operands(ecx, cexpr1, cexpr2)?;
+            numeric_op_cast(ecx, expr1, expr2)?
+        }
+    };
     add(ecx, &expr1, &expr2).map(|e| e.into())
 }
 
 fn transform_bop_minus(
-    _ecx: &ExprContext,
-    _left: CoercibleExpr,
-    _right: CoercibleExpr,
+    ecx: &ExprContext,
+    cexpr1: CoercibleExpr,
+    cexpr2: CoercibleExpr,
+) -> Result<CoercibleExpr> {
+    let (expr1, expr2) = match interval_op_cast(ecx, &cexpr1, &cexpr2)? {
+        Some(operands) => operands,
+        None => {
+            let (expr1, expr2) = coerce_operands(ecx, cexpr1, cexpr2)?;
+            numeric_op_cast(ecx, expr1, expr2)?
+        }
+    };
+    sub(ecx, &expr1, &expr2).map(|e| e.into())
+}
+
+/// The operands of `+` or `-` if either of them is a timestamp or an
+/// interval, the other operand is an interval if its type is not
+/// determined, eg `SELECT TIMESTAMP '2024-01-01' + '1 day'`. Returns
+/// `None` for the other operands.
+fn interval_op_cast(
+    ecx: &ExprContext,
+    cexpr1: &CoercibleExpr,
+    cexpr2: &CoercibleExpr,
+) -> Result<Option<(Expr, Expr)>> {
+    let is_temporal = |cexpr: &CoercibleExpr| {
+        cexpr.typ(ecx).map_or(false, |ty| {
+            matches!(
+                ty.scalar_type,
+                ScalarType::Timestamp | ScalarType::Interval
+            )
+        })
+    };
+    if !is_temporal(cexpr1) && !is_temporal(cexpr2) {
+        return Ok(None);
+    }
+    let operand = |cexpr: &CoercibleExpr| match cexpr.typ(ecx) {
+        Some(_) => cexpr.type_as_any(ecx),
+        None => cexpr.cast_to(ecx, &ScalarType::Interval),
+    };
+    Ok(Some((operand(cexpr1)?, operand(cexpr2)?)))
+}
+
+fn transform_bop_multiply(
+    ecx: &ExprContext,
+    cexpr1: CoercibleExpr,
+    cexpr2: CoercibleExpr,
+) -> Result<CoercibleExpr> {
+    let (expr1, expr2) = coerce_operands(ecx, cexpr1, cexpr2)?;
+    let (expr1, expr2) = numeric_op_cast(ecx, expr1, expr2)?;
+    mul(ecx, &expr1, &expr2).map(|e| e.into())
+}
+
+fn transform_bop_divide(
+    ecx: &ExprContext,
+    cexpr1: CoercibleExpr,
+    cexpr2: CoercibleExpr,
 ) -> Result<CoercibleExpr> {
-    unimplemented!()
+    let (expr1, expr2) = coerce_operands(ecx, cexpr1, cexpr2)?;
+    let (expr1, expr2) = numeric_op_cast(ecx, expr1, expr2)?;
+    div(ecx, &expr1, &expr2).map(|e| e.into())
 }
 
 fn transform_bop_gt(
@@ -348,67 +1502,160 @@ fn transform_bop_gt(
     left: CoercibleExpr,
     right: CoercibleExpr,
 ) -> Result<CoercibleExpr> {
-    let expr1 = left.type_as_any(ecx)?;
-    let expr2 = right.type_as_any(ecx)?;
-
-    let (expr1, expr2) = numeric_op_cast(ecx, expr1, expr2)?;
-    gt(ecx, &expr1, &expr2).map(|e| e.into())
+    transform_bop_compare(ecx, BinaryFunc::Gt, left, right)
 }
 
 fn transform_bop_lt(
-    _ecx: &ExprContext,
-    _left: CoercibleExpr,
-    _right: CoercibleExpr,
+    ecx: &ExprContext,
+    left: CoercibleExpr,
+    right: CoercibleExpr,
 ) -> Result<CoercibleExpr> {
-    unimplemented!()
+    transform_bop_compare(ecx, BinaryFunc::Lt, left, right)
 }
 
 fn transform_bop_gte(
-    _ecx: &ExprContext,
-    _left: CoercibleExpr,
-    _right: CoercibleExpr,
+    ecx: &ExprContext,
+    left: CoercibleExpr,
+    right: CoercibleExpr,
 ) -> Result<CoercibleExpr> {
-    unimplemented!()
+    transform_bop_compare(ecx, BinaryFunc::Gte, left, right)
 }
 
 fn transform_bop_lte(
-    _ecx: &ExprContext,
-    _left: CoercibleExpr,
-    _right: CoercibleExpr,
+    ecx: &ExprContext,
+    left: CoercibleExpr,
+    right: CoercibleExpr,
 ) -> Result<CoercibleExpr> {
-    unimplemented!()
+    transform_bop_compare(ecx, BinaryFunc::Lte, left, right)
 }
 
 fn transform_bop_eq(
-    _ecx: &ExprContext,
-    _left: CoercibleExpr,
-    _right: CoercibleExpr,
+    ecx: &ExprContext,
+    left: CoercibleExpr,
+    right: CoercibleExpr,
 ) -> Result<CoercibleExpr> {
-    unimplemented!()
+    transform_bop_compare(ecx, BinaryFunc::Eq, left, right)
 }
 
 fn transform_bop_neq(
-    _ecx: &ExprContext,
-    _left: CoercibleExpr,
-    _right: CoercibleExpr,
+    ecx: &ExprContext,
+    left: CoercibleExpr,
+    right: CoercibleExpr,
 ) -> Result<CoercibleExpr> {
-    unimplemented!()
+    transform_bop_compare(ecx, BinaryFunc::NotEq, left, right)
+}
+
+/// An operand of unknown type, like `$1` or `'1'`, takes the type of
+/// the other operand. Numeric operands are cast to a common type.
+/// An operand of unknown type, eg `NULL` or `$1`, has the type of
+/// the other operand.
+fn coerce_operands(
+    ecx: &ExprContext,
+    left: CoercibleExpr,
+    right: CoercibleExpr,
+) -> Result<(Expr, Expr)> {
+    match (left.typ(ecx), right.typ(ecx)) {
+        (Some(ty), None) => {
+            Ok((left.type_as_any(ecx)?, right.cast_to(ecx, &ty.scalar_type)?))
+        }
+        (None, Some(ty)) => {
+            Ok((left.cast_to(ecx, &ty.scalar_type)?, right.type_as_any(ecx)?))
+        }
+        _ => Ok((left.type_as_any(ecx)?, right.type_as_any(ecx)?)),
+    }
+}
+
+fn transform_bop_compare(
+    ecx: &ExprContext,
+    func: BinaryFunc,
+    left: CoercibleExpr,
+    right: CoercibleExpr,
+) -> Result<CoercibleExpr> {
+    let (expr1, expr2) = coerce_operands(ecx, left, right)?;
+    let ty1 = expr1.typ(ecx).scalar_type;
+    let ty2 = expr2.typ(ecx).scalar_type;
+    let (expr1, expr2) = if ty1.is_numeric() && ty2.is_numeric() {
+        numeric_op_cast(ecx, expr1, expr2)?
+    } else if ty1.base_eq(&ty2) {
+        (expr1, expr2)
+    } else {
+        return Err(FloppyError::Plan(format!(
+            "operator does not exist: {ty1} {func} {ty2}"
+        )));
+    };
+    compare(ecx, func, &expr1, &expr2).map(|e| e.into())
 }
 
 fn transform_bop_and(
-    _ecx: &ExprContext,
-    _left: CoercibleExpr,
-    _right: CoercibleExpr,
+    ecx: &ExprContext,
+    left: CoercibleExpr,
+    right: CoercibleExpr,
 ) -> Result<CoercibleExpr> {
-    unimplemented!()
+    let left = left.type_as(ecx, &ScalarType::Boolean)?;
+    let right = right.type_as(ecx, &ScalarType::Boolean)?;
+    Ok(and(vec![left, right]).into())
 }
 
 fn transform_bop_or(
-    _ecx: &ExprContext,
-    _left: CoercibleExpr,
-    _right: CoercibleExpr,
+    ecx: &ExprContext,
+    left: CoercibleExpr,
+    right: CoercibleExpr,
+) -> Result<CoercibleExpr> {
+    let left = left.type_as(ecx, &ScalarType::Boolean)?;
+    let right = right.type_as(ecx, &ScalarType::Boolean)?;
+    Ok(or(vec![left, right]).into())
+}
+
+fn transform_bop_modulo(
+    ecx: &ExprContext,
+    cexpr1: CoercibleExpr,
+    cexpr2: CoercibleExpr,
+) -> Result<CoercibleExpr> {
+    let (expr1, expr2) = coerce_operands(ecx, cexpr1, cexpr2)?;
+    let (expr1, expr2) = numeric_op_cast(ecx, expr1, expr2)?;
+    modulo(ecx, &expr1, &expr2).map(|e| e.into())
+}
+
+/// The operands whose type is not known, eg `'a' || 1`, are
+/// text.
+fn transform_bop_concat(
+    ecx: &ExprContext,
+    left: CoercibleExpr,
+    right: CoercibleExpr,
+) -> Result<CoercibleExpr> {
+    let left = left.type_as_any(ecx)?;
+    let right = right.type_as_any(ecx)?;
+    concat(ecx, &left, &right).map(|e| e.into())
+}
+
+/// `expr BETWEEN low AND high` is `expr >= low AND expr <= high`.
+fn transform_between(
+    ecx: &ExprContext,
+    expr: &AstExpr,
+    negated: bool,
+    low: &AstExpr,
+    high: &AstExpr,
 ) -> Result<CoercibleExpr> {
-    unimplemented!()
+    let expr = transform_expr(ecx, expr)?;
+    let low = transform_expr(ecx, low)?;
+    let high = transform_expr(ecx, high)?;
+    let (func1, func2) = if negated {
+        (BinaryFunc::Lt, BinaryFunc::Gt)
+    } else {
+        (BinaryFunc::Gte, BinaryFunc::Lte)
+    };
+    let cmp1 = transform_bop_compare(ecx, func1, expr.clone(), low)?;
+    // the type of a parameter is determined by the first comparison.
+    let expr = match expr {
+        CoercibleExpr::Parameter(n) => Expr::Parameter(n).into(),
+        expr => expr,
+    };
+    let cmp2 = transform_bop_compare(ecx, func2, expr, high)?;
+    if negated {
+        transform_bop_or(ecx, cmp1, cmp2)
+    } else {
+        transform_bop_and(ecx, cmp1, cmp2)
+    }
 }
 
 fn numeric_op_cast(
@@ -423,6 +1670,25 @@ fn numeric_op_cast(
 
     match (is_c1_numeric, is_c2_numeric) {
         (true, true) => match (c1_type.scalar_type, c2_type.scalar_type) {
+            (ScalarType::Numeric(_), ScalarType::Numeric(_)) => {
+                Ok((expr1, expr2))
+            }
+            // an integer is promoted to numeric.
+            (ScalarType::Int64, ScalarType::Numeric(_)) => {
+                let expr1 = expr1.cast_to(ecx, &ScalarType::Numeric(None))?;
+                Ok((expr1, expr2))
+            }
+            (ScalarType::Numeric(_), ScalarType::Int64) => {
+                let expr2 = expr2.cast_to(ecx, &ScalarType::Numeric(None))?;
+                Ok((expr1, expr2))
+            }
+            // if either side is a float, both sides are floats.
+            (ScalarType::Float32 | ScalarType::Float64, _)
+            | (_, ScalarType::Float32 | ScalarType::Float64) => {
+                let expr1 = expr1.cast_to(ecx, &ScalarType::Float64)?;
+                let expr2 = expr2.cast_to(ecx, &ScalarType::Float64)?;
+                Ok((expr1, expr2))
+            }
             (ScalarType::Int64, _) | (_, ScalarType::Int64) => {
                 let expr1 = expr1.cast_to(ecx, &ScalarType::Int64)?;
                 let expr2 = expr2.cast_to(ecx, &ScalarType::Int64)?;
@@ -444,6 +1710,91 @@ fn numeric_op_cast(
     }
 }
 
+/// Evaluate the parameters of `EXECUTE` as datums of `param_types`.
+pub(crate) fn transform_execute_params(
+    scx: &StatementContext,
+    params: &[AstExpr],
+    param_types: &[ScalarType],
+) -> Result<Vec<Datum>> {
+    // the parameters can't refer to any column.
+    let ecx = ExprContext {
+        scx: Arc::new(scx.clone()),
+        rel_desc: Arc::new(RelationDesc::default()),
+    };
+    let row = Row::new(vec![]);
+    params
+        .iter()
+        .zip(param_types)
+        .map(|(param, ty)| {
+            transform_expr(&ecx, param)?
+                .cast_to(&ecx, ty)?
+                .evaluate(&ecx, &row)
+        })
+        .collect()
+}
+
+/// The scalar type of a SQL data type, eg in `PREPARE`.
+pub(crate) fn transform_data_type(data_type: &DataType) -> Result<ScalarType> {
+    let ty = match data_type {
+        DataType::Boolean => ScalarType::Boolean,
+        DataType::SmallInt(_) | DataType::Int(_) | DataType::BigInt(_) => {
+            ScalarType::Int64
+        }
+        DataType::Real => ScalarType::Float32,
+        // like PostgreSQL, `float(p)` is `real` for p <= 24.
+        DataType::Float(Some(p)) if *p <= 24 => ScalarType::Float32,
+        DataType::Float(_) | DataType::Double => ScalarType::Float64,
+        DataType::Decimal(None, _) => ScalarType::Numeric(None),
+        DataType::Decimal(Some(precision), scale) => {
+            let to_u8 = |n: u64| {
+                u8::try_from(n).map_err(|_| {
+                    FloppyError::Plan(format!("invalid NUMERIC modifier {n}"))
+                })
+            };
+            ScalarType::Numeric(Some(NumericConstraints::new(
+                to_u8(*precision)?,
+                to_u8(scale.unwrap_or(0))?,
+            )?))
+        }
+        DataType::Char(_)
+        | DataType::Varchar(_)
+        | DataType::Text
+        | DataType::String => ScalarType::Text,
+        DataType::Date => ScalarType::Date,
+        DataType::Timestamp => ScalarType::Timestamp,
+        DataType::Interval => ScalarType::Interval,
+        DataType::Bytea => ScalarType::Bytea,
+        DataType::Uuid => ScalarType::Uuid,
+        DataType::Array(elem) => match transform_data_type(elem)? {
+            ScalarType::Array(_) => {
+                return Err(FloppyError::NotImplemented(
+                    "multidimensional arrays".to_string(),
+                ))
+            }
+            elem => ScalarType::Array(Box::new(elem)),
+        },
+        DataType::Custom(name) => {
+            match name.to_string().to_lowercase().as_str() {
+                "int2" | "int4" | "int8" => ScalarType::Int64,
+                "float4" => ScalarType::Float32,
+                "float8" => ScalarType::Float64,
+                "bool" => ScalarType::Boolean,
+                _ => {
+                    return Err(FloppyError::Plan(format!(
+                        "type \"{name}\" does not exist"
+                    )))
+                }
+            }
+        }
+        _ => {
+            return Err(FloppyError::NotImplemented(format!(
+                "type {data_type}"
+            )))
+        }
+    };
+    Ok(ty)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -454,6 +1805,7 @@ mod tests {
     use sqlparser::dialect::PostgreSqlDialect;
     use sqlparser::parser::Parser;
     use std::cell::RefCell;
+    use std::rc::Rc;
     use std::sync::Arc;
 
     fn logical_plan(scx: &StatementContext, sql: &str) -> Result<LogicalPlan> {
@@ -487,8 +1839,10 @@ mod tests {
     fn select_no_relation_single_column() {
         let scx = StatementContext {
             catalog: Arc::new(catalog::memory::MemCatalog::default()),
-            param_types: RefCell::default(),
+            search_path: catalog::default_search_path(),
+            param_types: Rc::default(),
             param_values: RefCell::default(),
+            ctes: RefCell::default(),
         };
 
         quick_test_eq(&scx, "SELECT 1", "Projection: Int64(1)\n  EmptyTable")
@@ -549,6 +1903,23 @@ mod tests {
             .contains("Could not choose a best candidate operator"));
     }
 
+    #[test]
+    fn parameter_typed_twice() -> Result<()> {
+        // both the operands of `=` are typed as text.
+        let scx = StatementContext::new(seeder::seed_catalog());
+        logical_plan(&scx, "SELECT $1 = $1")?;
+        assert_eq!(scx.param_types.borrow().get(&1), Some(&ScalarType::Text));
+
+        // `$1` is typed as text by `||`, then as boolean by `AND`.
+        let scx = StatementContext::new(seeder::seed_catalog());
+        let err = logical_plan(&scx, "SELECT $1 AND $1 || 'a'").unwrap_err();
+        assert!(matches!(err, FloppyError::Plan(_)));
+        assert!(err
+            .to_string()
+            .contains("inconsistent types deduced for parameter $1"));
+        Ok(())
+    }
+
     #[test]
     fn select_table_not_exists() {
         let catalog = seeder::seed_catalog();
@@ -606,4 +1977,253 @@ mod tests {
         )
         .expect("SELECT c1 FROM test WHERE c2 > 100");
     }
+
+    #[test]
+    fn select_concat() {
+        let catalog = seeder::seed_catalog();
+        let scx = StatementContext::new(Arc::new(catalog));
+
+        quick_test_eq(
+            &scx,
+            "SELECT 'a' || c1 FROM test",
+            "Projection: Text(a) || c1\n  Table: test",
+        )
+        .expect("SELECT 'a' || c1 FROM test");
+
+        quick_test_eq(
+            &scx,
+            "SELECT 'a' || NULL",
+            "Projection: Text(a) || Text(NULL)\n  EmptyTable",
+        )
+        .expect("SELECT 'a' || NULL");
+
+        let err = quick_test_fail(&scx, "SELECT c1 || c2 FROM test")
+            .expect_err("sql error");
+        assert!(matches!(err, FloppyError::Plan(_)), "{err}");
+    }
+
+    #[test]
+    fn select_string_function() {
+        let catalog = seeder::seed_catalog();
+        let scx = StatementContext::new(Arc::new(catalog));
+
+        quick_test_eq(
+            &scx,
+            "SELECT upper('a'), LENGTH('abc'), lower(NULL)",
+            "Projection: upper(Text(a)), length(Text(abc)), \
+             lower(Text(NULL))\n  EmptyTable",
+        )
+        .expect("SELECT upper('a'), LENGTH('abc'), lower(NULL)");
+
+        quick_test_eq(
+            &scx,
+            "SELECT substring('abc', 2, 1), substring('abc' FROM 2)",
+            "Projection: substring(Text(abc), Int64(2), Int64(1)), \
+             substring(Text(abc), Int64(2))\n  EmptyTable",
+        )
+        .expect("SELECT substring('abc', 2, 1), substring('abc' FROM 2)");
+
+        let err = quick_test_fail(&scx, "SELECT upper(c1) FROM test")
+            .expect_err("sql error");
+        assert!(matches!(err, FloppyError::Plan(_)), "{err}");
+
+        let err = quick_test_fail(&scx, "SELECT upper('a', 'b')")
+            .expect_err("sql error");
+        assert!(matches!(err, FloppyError::Plan(_)), "{err}");
+
+        let err = quick_test_fail(&scx, "SELECT reverse('a')")
+            .expect_err("sql error");
+        assert!(matches!(err, FloppyError::NotImplemented(_)), "{err}");
+    }
+
+    #[test]
+    fn select_having() {
+        let catalog = seeder::seed_catalog();
+        let scx = StatementContext::new(Arc::new(catalog));
+
+        quick_test_eq(
+            &scx,
+            "SELECT c1, SUM(c2) FROM test GROUP BY c1 HAVING COUNT(*) > 1",
+            "Projection: c1, SUM(c2)\n  Filter: COUNT(*) > Int64(1)\n    \
+             Aggregate: groupBy=[c1], aggr=[SUM(c2), COUNT(*)]\n      \
+             Table: test",
+        )
+        .expect("HAVING COUNT(*) > 1");
+
+        // `c2` is neither grouped nor aggregated.
+        let err = quick_test_fail(
+            &scx,
+            "SELECT c1 FROM test GROUP BY c1 HAVING c2 > 1",
+        )
+        .expect_err("sql error");
+        assert!(matches!(err, FloppyError::Catalog(_)), "{err}");
+    }
+
+    #[test]
+    fn select_distinct() {
+        let catalog = seeder::seed_catalog();
+        let scx = StatementContext::new(Arc::new(catalog));
+
+        quick_test_eq(
+            &scx,
+            "SELECT DISTINCT c1 FROM test",
+            "Distinct\n  Projection: c1\n    Table: test",
+        )
+        .expect("SELECT DISTINCT c1 FROM test");
+    }
+
+    #[test]
+    fn select_union() {
+        let catalog = seeder::seed_catalog();
+        let scx = StatementContext::new(Arc::new(catalog));
+
+        quick_test_eq(
+            &scx,
+            "SELECT c1 FROM test UNION ALL SELECT c2 FROM test UNION ALL \
+             SELECT 1",
+            "Union All\n  Projection: c1\n    Table: test\n  \
+             Projection: c2\n    Table: test\n  Projection: Int64(1)\n    \
+             EmptyTable",
+        )
+        .expect("UNION ALL");
+
+        // the integer is cast to numeric.
+        quick_test_eq(
+            &scx,
+            "SELECT 1 UNION SELECT 2.5",
+            "Union\n  Projection: Numeric(1)\n    EmptyTable\n  \
+             Projection: Numeric(2.5)\n    EmptyTable",
+        )
+        .expect("UNION");
+
+        let err =
+            quick_test_fail(&scx, "SELECT c1 FROM test UNION SELECT 1, 2")
+                .expect_err("sql error");
+        assert!(matches!(err, FloppyError::Plan(_)), "{err}");
+
+        let err = quick_test_fail(&scx, "SELECT 1 UNION SELECT 1 > 0")
+            .expect_err("sql error");
+        assert!(matches!(err, FloppyError::Plan(_)), "{err}");
+    }
+
+    #[test]
+    fn select_subquery() {
+        let catalog = seeder::seed_catalog();
+        let scx = StatementContext::new(Arc::new(catalog));
+
+        let sql = "SELECT x FROM (SELECT c1 AS x FROM test) t";
+        let plan = logical_plan(&scx, sql).expect(sql);
+        assert_eq!(
+            format!("{plan}"),
+            "Projection: x\n  Projection: c1\n    Table: test"
+        );
+        assert_eq!(plan.rel_desc().column_names(), &["x"]);
+
+        quick_test_eq(
+            &scx,
+            "SELECT y FROM (SELECT x + 1 AS y FROM \
+             (SELECT c1 AS x FROM test WHERE c2 > 1) t1) t2",
+            "Projection: y\n  Projection: x + Int64(1)\n    \
+             Projection: c1\n      Filter: c2 > Int64(1)\n        \
+             Table: test",
+        )
+        .expect("nested subquery");
+
+        let err = quick_test_fail(&scx, "SELECT c1 FROM (SELECT c1 FROM test)")
+            .expect_err("sql error");
+        assert!(matches!(err, FloppyError::Plan(_)), "{err}");
+
+        // the column is renamed by the alias.
+        let err = quick_test_fail(
+            &scx,
+            "SELECT c1 FROM (SELECT c1 AS x FROM test) t",
+        )
+        .expect_err("sql error");
+        assert!(matches!(err, FloppyError::Catalog(_)), "{err}");
+    }
+
+    #[test]
+    fn update() {
+        let catalog = seeder::seed_catalog();
+        let scx = StatementContext::new(Arc::new(catalog));
+        let plan = |sql: &str| {
+            let ast = &crate::sql::planner::parse(sql)?[0];
+            transform_statement(&scx, ast)
+        };
+
+        let sql = "UPDATE test SET c2 = c1 + 1 WHERE c1 > 1";
+        assert_eq!(
+            format!("{}", plan(sql).expect(sql)),
+            "Update: test, set: [c2 = c1 + Int64(1)], filter: c1 > Int64(1)"
+        );
+        let sql = "UPDATE test SET c2 = '5', c1 = 1";
+        assert_eq!(
+            format!("{}", plan(sql).expect(sql)),
+            "Update: test, set: [c2 = Int64(5), c1 = Int64(1)]"
+        );
+
+        let sql = "UPDATE test SET c3 = 1";
+        let err = plan(sql).expect_err(sql);
+        assert!(matches!(err, FloppyError::Catalog(_)), "{err}");
+
+        let sql = "UPDATE test SET c1 = 1, c1 = 2";
+        let err = plan(sql).expect_err(sql);
+        assert!(matches!(err, FloppyError::Plan(_)), "{err}");
+
+        let sql = "UPDATE test SET c1 = 1 WHERE c2";
+        let err = plan(sql).expect_err(sql);
+        assert!(matches!(err, FloppyError::Plan(_)), "{err}");
+    }
+
+    #[test]
+    fn transaction_control() {
+        let catalog = seeder::seed_catalog();
+        let scx = StatementContext::new(Arc::new(catalog));
+        for sql in ["BEGIN", "COMMIT", "ROLLBACK", "EXPLAIN COMMIT"] {
+            let ast = &crate::sql::planner::parse(sql).expect(sql)[0];
+            let err = transform_statement(&scx, ast).expect_err(sql);
+            assert!(matches!(err, FloppyError::Plan(_)), "{err}");
+        }
+    }
+
+    #[test]
+    fn select_values() {
+        let catalog = seeder::seed_catalog();
+        let scx = StatementContext::new(Arc::new(catalog));
+
+        let plan = logical_plan(&scx, "VALUES (1, 'a'), (2.5, NULL)")
+            .expect("VALUES (1, 'a'), (2.5, NULL)");
+        assert_eq!(
+            format!("{plan}"),
+            "Values: (Numeric(1), Text(a)), (Numeric(2.5), Text(NULL))"
+        );
+        assert_eq!(
+            plan.rel_desc().column_types(),
+            &vec![
+                ColumnType::new(ScalarType::Numeric(None), false),
+                ColumnType::new(ScalarType::Text, true),
+            ]
+        );
+        assert_eq!(plan.rel_desc().column_names(), &["column1", "column2"]);
+
+        quick_test_eq(
+            &scx,
+            "SELECT b FROM (VALUES (1, 2)) t(a, b)",
+            "Projection: b\n  Projection: column1, column2\n    \
+             Values: (Int64(1), Int64(2))",
+        )
+        .expect("SELECT b FROM (VALUES (1, 2)) t(a, b)");
+
+        let err =
+            quick_test_fail(&scx, "VALUES (1, 2), (3)").expect_err("sql error");
+        assert!(matches!(err, FloppyError::Plan(_)), "{err}");
+
+        let err = quick_test_fail(&scx, "VALUES (1), ('a' || 'b')")
+            .expect_err("sql error");
+        assert!(matches!(err, FloppyError::Plan(_)), "{err}");
+
+        let err = quick_test_fail(&scx, "SELECT * FROM (VALUES (1)) t(a, b)")
+            .expect_err("sql error");
+        assert!(matches!(err, FloppyError::Plan(_)), "{err}");
+    }
 }
@@ -1,28 +1,38 @@
-use crate::catalog::CatalogStore;
+use crate::catalog::{self, CatalogStore};
 use crate::common::relation::RelationDesc;
 use crate::common::scalar::{Datum, ScalarType};
+use crate::sql::LogicalPlan;
 use crate::storage::TableStore;
 use std::cell::RefCell;
 use std::collections::BTreeMap;
+use std::rc::Rc;
 use std::sync::Arc;
 
 #[derive(Debug, Clone)]
 pub struct StatementContext {
     pub catalog: Arc<dyn CatalogStore>,
+    /// The schemas to resolve unqualified names in.
+    pub search_path: Vec<String>,
     /// The types of the parameters in the query. This is
-    /// filled in as planning occurs.
-    pub param_types: RefCell<BTreeMap<usize, ScalarType>>,
+    /// filled in as planning occurs, and shared by the clones of the
+    /// context in which the parameters are coerced.
+    pub param_types: Rc<RefCell<BTreeMap<usize, ScalarType>>>,
     /// The datums of the parameters in the query. This is
     /// filled in as Binding occurs.
     pub param_values: RefCell<BTreeMap<usize, Datum>>,
+    /// The plans of the common table expressions of `WITH` that are
+    /// visible to the query being planned, by their names.
+    pub ctes: RefCell<BTreeMap<String, LogicalPlan>>,
 }
 
 impl StatementContext {
     pub fn new(catalog: Arc<dyn CatalogStore>) -> Self {
         Self {
             catalog,
-            param_types: RefCell::default(),
+            search_path: catalog::default_search_path(),
+            param_types: Rc::default(),
             param_values: RefCell::default(),
+            ctes: RefCell::default(),
         }
     }
 }
@@ -1,7 +1,10 @@
-use super::primitive::expr::Expr;
+use super::primitive::agg::AggregateExpr;
+use super::primitive::expr::{Expr, ScalarSubquery};
+use super::primitive::sort::SortExpr;
 use super::visitor::{ExprVisitor, IndentVisitor};
 use crate::catalog::names::FullObjectName;
-use crate::common::relation::{GlobalId, RelationDesc};
+use crate::common::relation::{ColumnType, GlobalId, IndexRange, RelationDesc};
+use crate::common::scalar::ScalarType;
 use std::fmt;
 use std::fmt::Formatter;
 
@@ -9,13 +12,22 @@ use std::fmt::Formatter;
 /// It represents a tree of data flow where each node in the
 /// graph computes a table from the input of the node.
 /// The `LogicalPlan` is not ready to be executed yet.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum LogicalPlan {
     /// An empty relation exists in queries without a `From`
     /// clause, eg ```sql
     /// SELECT 1 + 1;
     /// ```
     Empty,
+    /// A constant relation with a row per list of `VALUES`, eg
+    /// ```sql
+    /// VALUES (1, 'a'), (2, 'b');
+    /// ```
+    Values {
+        rows: Vec<Vec<Expr>>,
+        /// The relation description of the output.
+        rel_desc: RelationDesc,
+    },
     /// Table is the leaf of the LogicalPlan tree.
     Table {
         table_id: GlobalId,
@@ -23,6 +35,11 @@ pub enum LogicalPlan {
         rel_desc: RelationDesc,
         /// Partial table name.
         name: FullObjectName,
+        /// The range of the primary key to scan.
+        range: IndexRange,
+        /// The columns of the table in `rel_desc`, all the
+        /// columns if it is `None`.
+        projection: Option<Vec<usize>>,
     },
     Projection {
         /// The list of expressions
@@ -36,6 +53,78 @@ pub enum LogicalPlan {
         input: Box<LogicalPlan>,
         predicate: Expr,
     },
+    /// Groups the input by `group_by` and computes the
+    /// `aggregates` for each group. The output contains the
+    /// group by columns followed by the aggregates.
+    Aggregate {
+        input: Box<LogicalPlan>,
+        group_by: Vec<Expr>,
+        aggregates: Vec<AggregateExpr>,
+        /// The relation description of the output
+        rel_desc: RelationDesc,
+    },
+    /// Removes the duplicate rows of `input`.
+    Distinct { input: Box<LogicalPlan> },
+    /// Sorts the rows of `input` by `keys`, the rows whose keys are
+    /// equal remain in the order of `input`.
+    Sort {
+        input: Box<LogicalPlan>,
+        keys: Vec<SortExpr>,
+    },
+    /// Skips the first `offset` rows of `input` and returns at most
+    /// `limit` of the rest, all of them if `limit` is `None`.
+    Limit {
+        input: Box<LogicalPlan>,
+        limit: Option<usize>,
+        offset: usize,
+    },
+    /// The first `k` rows of `input` sorted by `keys`, which is a
+    /// `Limit` over a `Sort` that does not sort all the rows.
+    TopN {
+        input: Box<LogicalPlan>,
+        keys: Vec<SortExpr>,
+        k: usize,
+    },
+    /// Inner joins `left` and `right`, the output rows are a left row
+    /// followed by a right row for which `predicate` is true. It is a
+    /// cross join if `predicate` is always true.
+    Join {
+        left: Box<LogicalPlan>,
+        right: Box<LogicalPlan>,
+        /// The join condition, which refers to the columns of `left`
+        /// followed by the columns of `right`.
+        predicate: Expr,
+    },
+    /// Concatenates the rows of `inputs`, which have the same
+    /// column types. Duplicate rows are removed unless `all` is
+    /// true.
+    Union {
+        inputs: Vec<LogicalPlan>,
+        all: bool,
+        /// The relation description of the output.
+        rel_desc: RelationDesc,
+    },
+    /// Sets the columns of `assignments` to the values of the
+    /// expressions for the rows of the table that satisfy `filter`,
+    /// all the rows if it is `None`. The updated rows are the
+    /// output.
+    Update {
+        table_id: GlobalId,
+        /// The relation description of the table.
+        rel_desc: RelationDesc,
+        /// Partial table name.
+        name: FullObjectName,
+        /// The column of the table and the expression of its new
+        /// value, which refers to the columns of the old row.
+        assignments: Vec<(usize, Expr)>,
+        filter: Option<Expr>,
+    },
+    /// Describes `plan` with a line of text per node, the column
+    /// types of each node are appended if `verbose` is true.
+    Explain {
+        plan: Box<LogicalPlan>,
+        verbose: bool,
+    },
 }
 
 impl LogicalPlan {
@@ -43,8 +132,101 @@ impl LogicalPlan {
         match self {
             Self::Empty => RelationDesc::empty(),
             Self::Filter { input, .. } => input.rel_desc(),
+            Self::Distinct { input } => input.rel_desc(),
+            Self::Sort { input, .. } => input.rel_desc(),
+            Self::Limit { input, .. } => input.rel_desc(),
+            Self::TopN { input, .. } => input.rel_desc(),
             Self::Projection { rel_desc, .. } => rel_desc.clone(),
+            Self::Aggregate { rel_desc, .. } => rel_desc.clone(),
             Self::Table { rel_desc, .. } => rel_desc.clone(),
+            Self::Values { rel_desc, .. } => rel_desc.clone(),
+            Self::Union { rel_desc, .. } => rel_desc.clone(),
+            Self::Join { left, right, .. } => {
+                left.rel_desc().concat(&right.rel_desc())
+            }
+            Self::Update { rel_desc, .. } => rel_desc.clone(),
+            Self::Explain { .. } => RelationDesc::new(
+                vec![ColumnType::new(ScalarType::Text, false)],
+                vec!["QUERY PLAN".to_string()],
+                vec![],
+                vec![],
+            ),
+        }
+    }
+
+    /// The lines describing the plan, see [`LogicalPlan::Explain`].
+    pub fn explain(&self, verbose: bool) -> Vec<String> {
+        let tree = if verbose {
+            self.display_tree_with_types().to_string()
+        } else {
+            self.display_tree().to_string()
+        };
+        tree.lines().map(|line| line.to_string()).collect()
+    }
+
+    /// Appends the scalar subqueries of the expressions of the plan to
+    /// `subqueries` in the order they are to be executed, the
+    /// subqueries nested in a subquery come before it.
+    pub fn collect_subqueries(&self, subqueries: &mut Vec<ScalarSubquery>) {
+        let exprs: Vec<&Expr> = match self {
+            Self::Values { rows, .. } => rows.iter().flatten().collect(),
+            Self::Projection { exprs, .. } => exprs.iter().collect(),
+            Self::Filter { predicate, .. } | Self::Join { predicate, .. } => {
+                vec![predicate]
+            }
+            Self::Aggregate {
+                group_by,
+                aggregates,
+                ..
+            } => group_by
+                .iter()
+                .chain(aggregates.iter().map(|a| a.expr.as_ref()))
+                .collect(),
+            Self::Sort { keys, .. } | Self::TopN { keys, .. } => {
+                keys.iter().map(|k| &k.expr).collect()
+            }
+            Self::Update {
+                assignments,
+                filter,
+                ..
+            } => assignments
+                .iter()
+                .map(|(_, e)| e)
+                .chain(filter.iter())
+                .collect(),
+            Self::Empty
+            | Self::Table { .. }
+            | Self::Distinct { .. }
+            | Self::Limit { .. }
+            | Self::Union { .. }
+            | Self::Explain { .. } => vec![],
+        };
+        for e in exprs {
+            e.collect_subqueries(subqueries);
+        }
+        match self {
+            Self::Projection { input, .. }
+            | Self::Filter { input, .. }
+            | Self::Aggregate { input, .. }
+            | Self::Distinct { input }
+            | Self::Sort { input, .. }
+            | Self::Limit { input, .. }
+            | Self::TopN { input, .. } => input.collect_subqueries(subqueries),
+            Self::Join { left, right, .. } => {
+                left.collect_subqueries(subqueries);
+                right.collect_subqueries(subqueries);
+            }
+            Self::Union { inputs, .. } => {
+                for input in inputs {
+                    input.collect_subqueries(subqueries);
+                }
+            }
+            // the plan of EXPLAIN is not executed.
+            Self::Empty
+            | Self::Explain { .. }
+            | Self::Values { .. }
+            | Self::Table { .. }
+            | Self::Update { .. } => {}
         }
     }
 }
@@ -64,7 +246,29 @@ impl LogicalPlan {
         let recurse = match self {
             Self::Projection { input, .. } => input.accept(visitor)?,
             Self::Filter { input, .. } => input.accept(visitor)?,
-            Self::Table { .. } | Self::Empty => true,
+            Self::Distinct { input } => input.accept(visitor)?,
+            Self::Sort { input, .. } => input.accept(visitor)?,
+            Self::Limit { input, .. } => input.accept(visitor)?,
+            Self::TopN { input, .. } => input.accept(visitor)?,
+            Self::Aggregate { input, .. } => input.accept(visitor)?,
+            Self::Explain { plan, .. } => plan.accept(visitor)?,
+            Self::Join { left, right, .. } => {
+                left.accept(visitor)? && right.accept(visitor)?
+            }
+            Self::Union { inputs, .. } => {
+                let mut recurse = true;
+                for input in inputs {
+                    if !input.accept(visitor)? {
+                        recurse = false;
+                        break;
+                    }
+                }
+                recurse
+            }
+            Self::Table { .. }
+            | Self::Values { .. }
+            | Self::Update { .. }
+            | Self::Empty => true,
         };
 
         if !recurse {
@@ -99,14 +303,44 @@ impl LogicalPlan {
         Wrapper(self)
     }
 
+    /// Like [`LogicalPlan::display_tree`], the output columns of each
+    /// node are appended to its line.
+    pub fn display_tree_with_types(&self) -> impl fmt::Display + '_ {
+        struct Wrapper<'a>(&'a LogicalPlan);
+        impl<'a> fmt::Display for Wrapper<'a> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let mut visitor = IndentVisitor::with_types(f);
+                self.0.accept(&mut visitor).unwrap();
+                Ok(())
+            }
+        }
+        Wrapper(self)
+    }
+
     /// display a single node in the tree.
     pub fn display_node(&self) -> impl fmt::Display + '_ {
         struct Wrapper<'a>(&'a LogicalPlan);
         impl<'a> fmt::Display for Wrapper<'a> {
             fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
                 match self.0 {
-                    LogicalPlan::Table { name, .. } => {
+                    LogicalPlan::Table {
+                        name,
+                        range,
+                        projection,
+                        rel_desc,
+                        ..
+                    } => {
                         write!(f, "Table: {}", name.item)?;
+                        if !range.is_full() {
+                            write!(f, ", range: {range}")?;
+                        }
+                        if projection.is_some() {
+                            let names = rel_desc
+                                .iter_names()
+                                .map(|n| n.to_string())
+                                .collect::<Vec<_>>();
+                            write!(f, ", projection: [{}]", names.join(", "))?;
+                        }
                         Ok(())
                     }
                     LogicalPlan::Projection { exprs, .. } => {
@@ -122,7 +356,99 @@ impl LogicalPlan {
                     LogicalPlan::Filter { predicate, .. } => {
                         write!(f, "Filter: {predicate}",)
                     }
+                    LogicalPlan::Aggregate {
+                        group_by,
+                        aggregates,
+                        ..
+                    } => {
+                        let group_by = group_by
+                            .iter()
+                            .map(|e| format!("{e}"))
+                            .collect::<Vec<String>>();
+                        let aggregates = aggregates
+                            .iter()
+                            .map(|e| format!("{e}"))
+                            .collect::<Vec<String>>();
+                        write!(
+                            f,
+                            "Aggregate: groupBy=[{}], aggr=[{}]",
+                            group_by.join(", "),
+                            aggregates.join(", ")
+                        )
+                    }
+                    LogicalPlan::Values { rows, .. } => {
+                        let rows = rows
+                            .iter()
+                            .map(|row| {
+                                let row = row
+                                    .iter()
+                                    .map(|e| format!("{e}"))
+                                    .collect::<Vec<String>>();
+                                format!("({})", row.join(", "))
+                            })
+                            .collect::<Vec<String>>();
+                        write!(f, "Values: {}", rows.join(", "))
+                    }
+                    LogicalPlan::Distinct { .. } => write!(f, "Distinct"),
+                    LogicalPlan::Sort { keys, .. } => {
+                        let keys = keys
+                            .iter()
+                            .map(|k| format!("{k}"))
+                            .collect::<Vec<String>>();
+                        write!(f, "Sort: {}", keys.join(", "))
+                    }
+                    LogicalPlan::Limit { limit, offset, .. } => {
+                        match limit {
+                            Some(limit) => write!(f, "Limit: {limit}")?,
+                            None => write!(f, "Limit: ALL")?,
+                        }
+                        if *offset > 0 {
+                            write!(f, ", offset: {offset}")?;
+                        }
+                        Ok(())
+                    }
+                    LogicalPlan::TopN { keys, k, .. } => {
+                        let keys = keys
+                            .iter()
+                            .map(|k| format!("{k}"))
+                            .collect::<Vec<String>>();
+                        write!(f, "TopN: {k}, keys: [{}]", keys.join(", "))
+                    }
+                    LogicalPlan::Join { predicate, .. } => {
+                        write!(f, "Join: {predicate}")
+                    }
+                    LogicalPlan::Union { all: true, .. } => {
+                        write!(f, "Union All")
+                    }
+                    LogicalPlan::Union { all: false, .. } => {
+                        write!(f, "Union")
+                    }
+                    LogicalPlan::Update {
+                        name,
+                        rel_desc,
+                        assignments,
+                        filter,
+                        ..
+                    } => {
+                        let assignments = assignments
+                            .iter()
+                            .map(|(i, e)| {
+                                format!("{} = {e}", rel_desc.column_name(*i))
+                            })
+                            .collect::<Vec<String>>();
+                        write!(
+                            f,
+                            "Update: {}, set: [{}]",
+                            name.item,
+                            assignments.join(", ")
+                        )?;
+                        if let Some(filter) = filter {
+                            write!(f, ", filter: {filter}")?;
+                        }
+                        Ok(())
+                    }
                     LogicalPlan::Empty => write!(f, "EmptyTable"),
+                    LogicalPlan::Explain { .. } => write!(f, "Explain"),
                 }
             }
         }
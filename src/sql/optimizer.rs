@@ -0,0 +1,557 @@
+/// Rewrites of a [`LogicalPlan`] that compute the same table more
+/// efficiently.
+use crate::common::relation::{IndexKeyDatums, IndexRange, RelationDesc};
+use crate::sql::primitive::expr::Literal;
+use crate::sql::primitive::func::{and, conjuncts, BinaryExpr, BinaryFunc};
+use crate::sql::{Expr, LogicalPlan};
+use std::collections::BTreeSet;
+use std::ops::Bound;
+
+pub fn optimize(plan: LogicalPlan) -> LogicalPlan {
+    fuse_top_n(push_down_projection(push_down_filter(plan)))
+}
+
+/// Push the comparisons of the primary key with constants in a
+/// `Filter` over a `Table` into the range of the `Table`. The other
+/// predicates remain in the `Filter`.
+fn push_down_filter(plan: LogicalPlan) -> LogicalPlan {
+    match plan {
+        LogicalPlan::Filter { input, predicate } => match *input {
+            LogicalPlan::Table {
+                table_id,
+                rel_desc,
+                name,
+                mut range,
+                projection,
+            } => {
+                let mut residual = vec![];
+                for predicate in conjuncts(predicate) {
+                    match prim_key_range(&predicate, &rel_desc) {
+                        Some(r) => range = range.intersect(r),
+                        None => residual.push(predicate),
+                    }
+                }
+                let table = LogicalPlan::Table {
+                    table_id,
+                    rel_desc,
+                    name,
+                    range,
+                    projection,
+                };
+                match residual.len() {
+                    0 => table,
+                    1 => LogicalPlan::Filter {
+                        input: Box::new(table),
+                        predicate: residual.pop().unwrap(),
+                    },
+                    _ => LogicalPlan::Filter {
+                        input: Box::new(table),
+                        predicate: and(residual),
+                    },
+                }
+            }
+            input => LogicalPlan::Filter {
+                input: Box::new(push_down_filter(input)),
+                predicate,
+            },
+        },
+        LogicalPlan::Projection {
+            exprs,
+            input,
+            rel_desc,
+        } => LogicalPlan::Projection {
+            exprs,
+            input: Box::new(push_down_filter(*input)),
+            rel_desc,
+        },
+        LogicalPlan::Aggregate {
+            input,
+            group_by,
+            aggregates,
+            rel_desc,
+        } => LogicalPlan::Aggregate {
+            input: Box::new(push_down_filter(*input)),
+            group_by,
+            aggregates,
+            rel_desc,
+        },
+        LogicalPlan::Explain { plan, verbose } => LogicalPlan::Explain {
+            plan: Box::new(push_down_filter(*plan)),
+            verbose,
+        },
+        LogicalPlan::Distinct { input } => LogicalPlan::Distinct {
+            input: Box::new(push_down_filter(*input)),
+        },
+        LogicalPlan::Sort { input, keys } => LogicalPlan::Sort {
+            input: Box::new(push_down_filter(*input)),
+            keys,
+        },
+        LogicalPlan::Limit {
+            input,
+            limit,
+            offset,
+        } => LogicalPlan::Limit {
+            input: Box::new(push_down_filter(*input)),
+            limit,
+            offset,
+        },
+        LogicalPlan::TopN { input, keys, k } => LogicalPlan::TopN {
+            input: Box::new(push_down_filter(*input)),
+            keys,
+            k,
+        },
+        LogicalPlan::Union {
+            inputs,
+            all,
+            rel_desc,
+        } => LogicalPlan::Union {
+            inputs: inputs.into_iter().map(push_down_filter).collect(),
+            all,
+            rel_desc,
+        },
+        LogicalPlan::Join {
+            left,
+            right,
+            predicate,
+        } => LogicalPlan::Join {
+            left: Box::new(push_down_filter(*left)),
+            right: Box::new(push_down_filter(*right)),
+            predicate,
+        },
+        plan @ (LogicalPlan::Empty
+        | LogicalPlan::Values { .. }
+        | LogicalPlan::Update { .. }
+        | LogicalPlan::Table { .. }) => plan,
+    }
+}
+
+/// Only scan the columns of a `Table` that are referenced by the
+/// `Projection` or `Aggregate` above it and the `Filter`s in
+/// between.
+fn push_down_projection(plan: LogicalPlan) -> LogicalPlan {
+    match plan {
+        LogicalPlan::Projection {
+            mut exprs,
+            input,
+            rel_desc,
+        } => {
+            let mut required = BTreeSet::new();
+            for expr in exprs.iter_mut() {
+                expr.visit_columns_mut(&mut |c| {
+                    required.insert(c.id);
+                });
+            }
+            let (input, columns) = prune_columns(*input, required);
+            if let Some(columns) = columns {
+                for expr in exprs.iter_mut() {
+                    remap_columns(expr, &columns);
+                }
+            }
+            LogicalPlan::Projection {
+                exprs,
+                input: Box::new(input),
+                rel_desc,
+            }
+        }
+        LogicalPlan::Aggregate {
+            input,
+            mut group_by,
+            mut aggregates,
+            rel_desc,
+        } => {
+            let mut exprs = group_by
+                .iter_mut()
+                .chain(aggregates.iter_mut().map(|a| &mut *a.expr))
+                .collect::<Vec<_>>();
+            let mut required = BTreeSet::new();
+            for expr in exprs.iter_mut() {
+                expr.visit_columns_mut(&mut |c| {
+                    required.insert(c.id);
+                });
+            }
+            let (input, columns) = prune_columns(*input, required);
+            if let Some(columns) = columns {
+                for expr in exprs {
+                    remap_columns(expr, &columns);
+                }
+            }
+            LogicalPlan::Aggregate {
+                input: Box::new(input),
+                group_by,
+                aggregates,
+                rel_desc,
+            }
+        }
+        LogicalPlan::Filter { input, predicate } => LogicalPlan::Filter {
+            input: Box::new(push_down_projection(*input)),
+            predicate,
+        },
+        LogicalPlan::Explain { plan, verbose } => LogicalPlan::Explain {
+            plan: Box::new(push_down_projection(*plan)),
+            verbose,
+        },
+        LogicalPlan::Distinct { input } => LogicalPlan::Distinct {
+            input: Box::new(push_down_projection(*input)),
+        },
+        LogicalPlan::Sort { input, keys } => LogicalPlan::Sort {
+            input: Box::new(push_down_projection(*input)),
+            keys,
+        },
+        LogicalPlan::Limit {
+            input,
+            limit,
+            offset,
+        } => LogicalPlan::Limit {
+            input: Box::new(push_down_projection(*input)),
+            limit,
+            offset,
+        },
+        LogicalPlan::TopN { input, keys, k } => LogicalPlan::TopN {
+            input: Box::new(push_down_projection(*input)),
+            keys,
+            k,
+        },
+        LogicalPlan::Union {
+            inputs,
+            all,
+            rel_desc,
+        } => LogicalPlan::Union {
+            inputs: inputs.into_iter().map(push_down_projection).collect(),
+            all,
+            rel_desc,
+        },
+        LogicalPlan::Join {
+            left,
+            right,
+            predicate,
+        } => LogicalPlan::Join {
+            left: Box::new(push_down_projection(*left)),
+            right: Box::new(push_down_projection(*right)),
+            predicate,
+        },
+        plan @ (LogicalPlan::Empty
+        | LogicalPlan::Values { .. }
+        | LogicalPlan::Update { .. }
+        | LogicalPlan::Table { .. }) => plan,
+    }
+}
+
+/// Replace a `Limit` over a `Sort`, or over a `Projection` of a
+/// `Sort`, with a `TopN` that keeps the rows of the limit and the
+/// offset only. The `Limit` remains to skip the offset rows.
+fn fuse_top_n(plan: LogicalPlan) -> LogicalPlan {
+    match plan {
+        LogicalPlan::Limit {
+            input,
+            limit: Some(limit),
+            offset,
+        } => fuse_limit(fuse_top_n(*input), limit, offset),
+        LogicalPlan::Limit {
+            input,
+            limit: None,
+            offset,
+        } => LogicalPlan::Limit {
+            input: Box::new(fuse_top_n(*input)),
+            limit: None,
+            offset,
+        },
+        LogicalPlan::Projection {
+            exprs,
+            input,
+            rel_desc,
+        } => LogicalPlan::Projection {
+            exprs,
+            input: Box::new(fuse_top_n(*input)),
+            rel_desc,
+        },
+        LogicalPlan::Aggregate {
+            input,
+            group_by,
+            aggregates,
+            rel_desc,
+        } => LogicalPlan::Aggregate {
+            input: Box::new(fuse_top_n(*input)),
+            group_by,
+            aggregates,
+            rel_desc,
+        },
+        LogicalPlan::Filter { input, predicate } => LogicalPlan::Filter {
+            input: Box::new(fuse_top_n(*input)),
+            predicate,
+        },
+        LogicalPlan::Explain { plan, verbose } => LogicalPlan::Explain {
+            plan: Box::new(fuse_top_n(*plan)),
+            verbose,
+        },
+        LogicalPlan::Distinct { input } => LogicalPlan::Distinct {
+            input: Box::new(fuse_top_n(*input)),
+        },
+        LogicalPlan::Sort { input, keys } => LogicalPlan::Sort {
+            input: Box::new(fuse_top_n(*input)),
+            keys,
+        },
+        LogicalPlan::TopN { input, keys, k } => LogicalPlan::TopN {
+            input: Box::new(fuse_top_n(*input)),
+            keys,
+            k,
+        },
+        LogicalPlan::Union {
+            inputs,
+            all,
+            rel_desc,
+        } => LogicalPlan::Union {
+            inputs: inputs.into_iter().map(fuse_top_n).collect(),
+            all,
+            rel_desc,
+        },
+        LogicalPlan::Join {
+            left,
+            right,
+            predicate,
+        } => LogicalPlan::Join {
+            left: Box::new(fuse_top_n(*left)),
+            right: Box::new(fuse_top_n(*right)),
+            predicate,
+        },
+        plan @ (LogicalPlan::Empty
+        | LogicalPlan::Values { .. }
+        | LogicalPlan::Update { .. }
+        | LogicalPlan::Table { .. }) => plan,
+    }
+}
+
+/// Limits `input` to `limit` rows after skipping `offset` rows.
+fn fuse_limit(input: LogicalPlan, limit: usize, offset: usize) -> LogicalPlan {
+    match input {
+        LogicalPlan::Sort { input, keys } => {
+            let top_n = LogicalPlan::TopN {
+                input,
+                keys,
+                k: limit.saturating_add(offset),
+            };
+            if offset == 0 {
+                return top_n;
+            }
+            LogicalPlan::Limit {
+                input: Box::new(top_n),
+                limit: Some(limit),
+                offset,
+            }
+        }
+        // the projection keeps the order and the number of the rows.
+        LogicalPlan::Projection {
+            exprs,
+            input,
+            rel_desc,
+        } if matches!(*input, LogicalPlan::Sort { .. }) => {
+            LogicalPlan::Projection {
+                exprs,
+                input: Box::new(fuse_limit(*input, limit, offset)),
+                rel_desc,
+            }
+        }
+        input => LogicalPlan::Limit {
+            input: Box::new(input),
+            limit: Some(limit),
+            offset,
+        },
+    }
+}
+
+/// Removes the columns of `plan` that are not in `required`.
+/// Returns the ascending columns of the original output that
+/// are kept, or `None` if the output is not changed.
+fn prune_columns(
+    plan: LogicalPlan,
+    mut required: BTreeSet<usize>,
+) -> (LogicalPlan, Option<Vec<usize>>) {
+    match plan {
+        LogicalPlan::Table {
+            table_id,
+            rel_desc,
+            name,
+            range,
+            projection: None,
+        } if required.len() < rel_desc.column_types().len() => {
+            let columns = required.into_iter().collect::<Vec<_>>();
+            let table = LogicalPlan::Table {
+                table_id,
+                rel_desc: rel_desc.project(&columns),
+                name,
+                range,
+                projection: Some(columns.clone()),
+            };
+            (table, Some(columns))
+        }
+        LogicalPlan::Filter {
+            input,
+            mut predicate,
+        } => {
+            predicate.visit_columns_mut(&mut |c| {
+                required.insert(c.id);
+            });
+            let (input, columns) = prune_columns(*input, required);
+            if let Some(columns) = &columns {
+                remap_columns(&mut predicate, columns);
+            }
+            let filter = LogicalPlan::Filter {
+                input: Box::new(input),
+                predicate,
+            };
+            (filter, columns)
+        }
+        plan => (push_down_projection(plan), None),
+    }
+}
+
+/// Points the column references of `expr` to the position of the
+/// column in `columns`.
+fn remap_columns(expr: &mut Expr, columns: &[usize]) {
+    expr.visit_columns_mut(&mut |c| {
+        c.id = columns
+            .binary_search(&c.id)
+            .expect("referenced column is pruned");
+    });
+}
+
+/// Returns the range of the primary key selected by `predicate`, if
+/// it compares the primary key with a constant. Only single column
+/// primary keys are supported.
+fn prim_key_range(
+    predicate: &Expr,
+    rel_desc: &RelationDesc,
+) -> Option<IndexRange> {
+    let key = match rel_desc.primary_key() {
+        [key] => *key,
+        _ => return None,
+    };
+    let (func, expr1, expr2) = match predicate {
+        Expr::CallBinary(BinaryExpr { func, expr1, expr2 }) => {
+            (func, expr1, expr2)
+        }
+        _ => return None,
+    };
+    // normalize the predicate to `key func literal`.
+    let (func, literal) = match (&**expr1, &**expr2) {
+        (Expr::Column(c), Expr::Literal(l)) if c.id == key => (func.clone(), l),
+        (Expr::Literal(l), Expr::Column(c)) if c.id == key => {
+            let func = match func {
+                BinaryFunc::Lt => BinaryFunc::Gt,
+                BinaryFunc::Lte => BinaryFunc::Gte,
+                BinaryFunc::Gt => BinaryFunc::Lt,
+                BinaryFunc::Gte => BinaryFunc::Lte,
+                func => func.clone(),
+            };
+            (func, l)
+        }
+        _ => return None,
+    };
+    let Literal { datum, scalar_type } = literal;
+    let key_type = &rel_desc.rel_type().column_type(key).scalar_type;
+    if datum.is_null() || !scalar_type.base_eq(key_type) {
+        return None;
+    }
+
+    let datums = || IndexKeyDatums::from_iter([datum.clone()]);
+    let (lo, hi) = match func {
+        BinaryFunc::Eq => {
+            (Bound::Included(datums()), Bound::Included(datums()))
+        }
+        BinaryFunc::Lt => (Bound::Unbounded, Bound::Excluded(datums())),
+        BinaryFunc::Lte => (Bound::Unbounded, Bound::Included(datums())),
+        BinaryFunc::Gt => (Bound::Excluded(datums()), Bound::Unbounded),
+        BinaryFunc::Gte => (Bound::Included(datums()), Bound::Unbounded),
+        _ => return None,
+    };
+    Some(IndexRange { lo, hi })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::memory::MemCatalog;
+    use crate::common::error::Result;
+    use crate::common::relation::ColumnType;
+    use crate::common::scalar::ScalarType;
+    use crate::sql::analyzer;
+    use crate::sql::context::StatementContext;
+    use crate::sql::planner;
+    use std::sync::Arc;
+
+    fn optimized_plan(sql: &str) -> Result<String> {
+        let mut catalog = MemCatalog::default();
+        let desc = RelationDesc::new(
+            vec![
+                ColumnType::new(ScalarType::Int64, false),
+                ColumnType::new(ScalarType::Int64, false),
+            ],
+            vec!["pk".to_string(), "c2".to_string()],
+            vec![0],
+            vec![],
+        );
+        catalog.insert_table("t", 1, desc);
+        let scx = StatementContext::new(Arc::new(catalog));
+        let statement = &planner::parse(sql)?[0];
+        let plan = analyzer::transform_statement(&scx, statement)?;
+        Ok(optimize(plan).to_string())
+    }
+
+    #[test]
+    fn push_down_filter() -> Result<()> {
+        let tests = [
+            (
+                "SELECT * FROM t WHERE pk BETWEEN 10 AND 20",
+                "Projection: pk, c2\n  Table: t, range: [10, 20]",
+            ),
+            (
+                "SELECT * FROM t WHERE pk = 5",
+                "Projection: pk, c2\n  Table: t, range: [5, 5]",
+            ),
+            (
+                "SELECT * FROM t WHERE pk > 10 AND c2 < 3 AND 20 > pk",
+                "Projection: pk, c2\n  Filter: c2 < Int64(3)\n    \
+                 Table: t, range: (10, 20)",
+            ),
+            // non-sargable predicates remain in the filter.
+            (
+                "SELECT * FROM t WHERE pk + 1 = 5",
+                "Projection: pk, c2\n  Filter: pk + Int64(1) = Int64(5)\n    \
+                 Table: t",
+            ),
+            (
+                "SELECT * FROM t WHERE pk > 1 OR c2 > 1",
+                "Projection: pk, c2\n  \
+                 Filter: pk > Int64(1) OR c2 > Int64(1)\n    Table: t",
+            ),
+        ];
+        for (sql, expected) in tests {
+            assert_eq!(optimized_plan(sql)?, expected, "{sql}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn push_down_projection() -> Result<()> {
+        let tests = [
+            (
+                "SELECT c2 + 1 FROM t",
+                "Projection: c2 + Int64(1)\n  Table: t, projection: [c2]",
+            ),
+            (
+                "SELECT c2 FROM t WHERE pk + 1 = 5",
+                "Projection: c2\n  Filter: pk + Int64(1) = Int64(5)\n    \
+                 Table: t",
+            ),
+            (
+                "SELECT COUNT(*) FROM t",
+                "Projection: COUNT(*)\n  Aggregate: groupBy=[], \
+                 aggr=[COUNT(*)]\n    Table: t, projection: []",
+            ),
+            ("SELECT * FROM t", "Projection: pk, c2\n  Table: t"),
+        ];
+        for (sql, expected) in tests {
+            assert_eq!(optimized_plan(sql)?, expected, "{sql}");
+        }
+        Ok(())
+    }
+}
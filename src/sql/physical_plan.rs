@@ -1,31 +1,75 @@
+mod aggregate;
+mod distinct;
 mod empty;
+mod explain;
 mod filter;
+mod hash_join;
+mod limit;
+mod nested_loop_join;
 pub mod planner;
 mod pri_scan;
 mod projection;
 mod sec_scan;
+mod sort;
+mod subquery;
+mod top_n;
+mod union;
+mod update;
+mod values;
 
 use crate::common::error::{FloppyError, Result};
 use crate::common::relation::Row;
 use crate::sql::context::ExecutionContext;
+use crate::sql::physical_plan::aggregate::AggregateExec;
+use crate::sql::physical_plan::distinct::DistinctExec;
 use crate::sql::physical_plan::empty::EmptyExec;
+use crate::sql::physical_plan::explain::ExplainExec;
 use crate::sql::physical_plan::filter::FilterExec;
+use crate::sql::physical_plan::hash_join::HashJoinExec;
+use crate::sql::physical_plan::limit::LimitExec;
+use crate::sql::physical_plan::nested_loop_join::NestedLoopJoinExec;
 use crate::sql::physical_plan::pri_scan::PriKeyScanExec;
 use crate::sql::physical_plan::projection::ProjectionExec;
 use crate::sql::physical_plan::sec_scan::SecKeyScan;
+use crate::sql::physical_plan::sort::SortExec;
+use crate::sql::physical_plan::subquery::SubqueryExec;
+use crate::sql::physical_plan::top_n::TopNExec;
+use crate::sql::physical_plan::union::UnionExec;
+use crate::sql::physical_plan::update::UpdateExec;
+use crate::sql::physical_plan::values::ValuesExec;
 use futures::Stream;
 use std::pin::Pin;
 use std::sync::Arc;
 
 #[derive(Debug)]
 pub enum PhysicalPlan {
+    /// Yields a single empty row, or no row.
     Empty(EmptyExec),
+    /// Yields a row per list of `VALUES`.
+    Values(ValuesExec),
     /// Scan the table with primary index range.
     PriKeyScan(PriKeyScanExec),
     /// Scan the table using secondary index range.
     SecKeyScan(SecKeyScan),
     Filter(FilterExec),
     Projection(ProjectionExec),
+    Aggregate(AggregateExec),
+    Distinct(DistinctExec),
+    Sort(SortExec),
+    Limit(LimitExec),
+    /// The first rows of a sort, without sorting all the rows.
+    TopN(TopNExec),
+    Union(UnionExec),
+    /// Joins each row of the left input with the rows of the right
+    /// input that satisfy the join predicate.
+    NestedLoopJoin(NestedLoopJoinExec),
+    /// Joins the rows of the inputs whose key columns are equal.
+    HashJoin(HashJoinExec),
+    /// Update the rows of a table, the updated rows are returned.
+    Update(UpdateExec),
+    /// Executes the scalar subqueries of the input first.
+    Subquery(SubqueryExec),
+    Explain(ExplainExec),
 }
 
 impl PhysicalPlan {
@@ -34,9 +78,21 @@ impl PhysicalPlan {
     pub fn stream(&self, exec_ctx: Arc<ExecutionContext>) -> Result<RowStream> {
         match self {
             Self::Empty(p) => p.stream(exec_ctx),
+            Self::Values(p) => p.stream(exec_ctx),
             Self::Filter(p) => p.stream(exec_ctx),
             Self::Projection(p) => p.stream(exec_ctx),
             Self::PriKeyScan(p) => p.stream(exec_ctx),
+            Self::Aggregate(p) => p.stream(exec_ctx),
+            Self::Distinct(p) => p.stream(exec_ctx),
+            Self::Sort(p) => p.stream(exec_ctx),
+            Self::Limit(p) => p.stream(exec_ctx),
+            Self::TopN(p) => p.stream(exec_ctx),
+            Self::Union(p) => p.stream(exec_ctx),
+            Self::NestedLoopJoin(p) => p.stream(exec_ctx),
+            Self::HashJoin(p) => p.stream(exec_ctx),
+            Self::Update(p) => p.stream(exec_ctx),
+            Self::Subquery(p) => p.stream(exec_ctx),
+            Self::Explain(p) => p.stream(exec_ctx),
             _ => Err(FloppyError::NotImplemented(format!(
                 "physical sql not implemented: {self:?}"
             ))),
@@ -0,0 +1,113 @@
+use crate::common::error::Result;
+use crate::common::relation::{RelationDesc, Row};
+use crate::common::scalar::{Datum, DatumKey};
+use crate::sql::context::{ExecutionContext, ExprContext};
+use crate::sql::physical_plan::RowStream;
+use crate::sql::primitive::agg::{self, Accumulator, AggregateExpr};
+use crate::sql::{Expr, PhysicalPlan};
+use futures::{ready, Stream, StreamExt};
+use std::collections::BTreeMap;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+/// `AggregateExec` groups its input by `group_by` and outputs
+/// one row per group. The aggregation is blocking: no row is
+/// produced until the whole input is consumed.
+#[derive(Debug)]
+pub struct AggregateExec {
+    pub group_by: Vec<Expr>,
+    pub aggregates: Vec<AggregateExpr>,
+    pub ecx: ExprContext,
+    pub input: Box<PhysicalPlan>,
+    pub rel_desc: Arc<RelationDesc>,
+}
+
+impl AggregateExec {
+    pub fn stream(&self, exec_ctx: Arc<ExecutionContext>) -> Result<RowStream> {
+        Ok(Box::pin(AggregateExecStream {
+            group_by: self.group_by.clone(),
+            aggregates: self.aggregates.clone(),
+            ecx: self.ecx.clone(),
+            input: self.input.stream(exec_ctx)?,
+            groups: BTreeMap::new(),
+            output: None,
+        }))
+    }
+}
+
+struct AggregateExecStream {
+    group_by: Vec<Expr>,
+    aggregates: Vec<AggregateExpr>,
+    ecx: ExprContext,
+    input: RowStream,
+    /// Accumulators of each group, keyed by the group by
+    /// values so that groups are produced in order. The values
+    /// equal by [`Datum::compare`] are in the same group.
+    groups: BTreeMap<Vec<DatumKey>, Vec<Accumulator>>,
+    output: Option<std::vec::IntoIter<Row>>,
+}
+
+impl AggregateExecStream {
+    fn accumulate(&mut self, row: &Row) -> Result<()> {
+        let key = self
+            .group_by
+            .iter()
+            .map(|e| e.evaluate(&self.ecx, row).map(DatumKey))
+            .collect::<Result<Vec<DatumKey>>>()?;
+        let aggregates = &self.aggregates;
+        let accumulators = self.groups.entry(key).or_insert_with(|| {
+            aggregates.iter().map(Accumulator::new).collect()
+        });
+        agg::accumulate(&self.ecx, aggregates, accumulators, row)
+    }
+
+    fn finish(&mut self) -> Vec<Row> {
+        let mut groups = std::mem::take(&mut self.groups);
+        // without `GROUP BY`, an empty input still produces a
+        // single row, eg `SELECT COUNT(*) FROM t` returns 0.
+        if groups.is_empty() && self.group_by.is_empty() {
+            groups.insert(
+                vec![],
+                self.aggregates.iter().map(Accumulator::new).collect(),
+            );
+        }
+        groups
+            .into_iter()
+            .map(|(key, accumulators)| {
+                let mut datums =
+                    key.into_iter().map(|k| k.0).collect::<Vec<Datum>>();
+                datums.extend(accumulators.into_iter().map(|a| a.finish()));
+                Row::new(datums)
+            })
+            .collect()
+    }
+}
+
+impl Stream for AggregateExecStream {
+    type Item = Result<Row>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some(output) = self.output.as_mut() {
+                return Poll::Ready(output.next().map(Ok));
+            }
+
+            match ready!(self.input.poll_next_unpin(cx)) {
+                Some(Ok(r)) => {
+                    if let Err(e) = self.accumulate(&r) {
+                        return Poll::Ready(Some(Err(e)));
+                    }
+                }
+                Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+                None => {
+                    let rows = self.finish();
+                    self.output = Some(rows.into_iter());
+                }
+            }
+        }
+    }
+}
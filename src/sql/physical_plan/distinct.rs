@@ -0,0 +1,36 @@
+use crate::common::error::Result;
+use crate::common::scalar::DatumKey;
+use crate::sql::context::ExecutionContext;
+use crate::sql::physical_plan::RowStream;
+use crate::sql::PhysicalPlan;
+use futures::{future, StreamExt};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// Returns the first of the duplicate rows of `input`.
+#[derive(Debug)]
+pub struct DistinctExec {
+    pub input: Box<PhysicalPlan>,
+}
+
+impl DistinctExec {
+    pub fn stream(&self, exec_ctx: Arc<ExecutionContext>) -> Result<RowStream> {
+        Ok(distinct(self.input.stream(exec_ctx)?))
+    }
+}
+
+/// Removes the duplicate rows of `rows`, keeping the first one. The
+/// rows that have been returned are kept in memory. The values are
+/// compared as [`DatumKey`]s, NULLs are equal to each other.
+pub fn distinct(rows: RowStream) -> RowStream {
+    let mut seen = HashSet::new();
+    Box::pin(rows.filter(move |row| {
+        let keep = match row {
+            Ok(row) => seen.insert(
+                row.iter().cloned().map(DatumKey).collect::<Vec<DatumKey>>(),
+            ),
+            Err(_) => true,
+        };
+        future::ready(keep)
+    }))
+}
@@ -1,22 +1,17 @@
 use crate::common::error::Result;
 use crate::common::relation::Row;
-use crate::common::scalar::Datum;
 use crate::sql::context::ExecutionContext;
 use crate::sql::physical_plan::RowStream;
-use futures::Stream;
-use std::pin::Pin;
+use futures::stream;
 use std::sync::Arc;
-use std::task::{Context, Poll};
 
+/// A relation without columns, eg the input of a `SELECT` without a
+/// `FROM` clause, whose projection is evaluated once on its single
+/// empty row.
 #[derive(Debug)]
 pub struct EmptyExec {
-    index: usize,
-}
-
-impl EmptyExec {
-    pub fn new() -> Self {
-        Self { index: 0 }
-    }
+    /// Whether a single empty row is produced, otherwise no row is.
+    pub produce_one_row: bool,
 }
 
 impl EmptyExec {
@@ -24,26 +19,11 @@ impl EmptyExec {
         &self,
         _exec_ctx: Arc<ExecutionContext>,
     ) -> Result<RowStream> {
-        Ok(Box::pin(FilterExecStream { index: 0 }))
-    }
-}
-
-struct FilterExecStream {
-    index: usize,
-}
-
-impl Stream for FilterExecStream {
-    type Item = Result<Row>;
-
-    fn poll_next(
-        mut self: Pin<&mut Self>,
-        _cx: &mut Context<'_>,
-    ) -> Poll<Option<Self::Item>> {
-        if self.index == 1 {
-            return Poll::Ready(None);
-        }
-        self.index += 1;
-        let row = Row::new(vec![Datum::Null]);
-        Poll::Ready(Some(Ok(row)))
+        let rows = if self.produce_one_row {
+            vec![Ok(Row::empty())]
+        } else {
+            vec![]
+        };
+        Ok(Box::pin(stream::iter(rows)))
     }
 }
@@ -0,0 +1,27 @@
+use crate::common::error::Result;
+use crate::common::relation::Row;
+use crate::common::scalar::Datum;
+use crate::sql::context::ExecutionContext;
+use crate::sql::physical_plan::RowStream;
+use futures::stream;
+use std::sync::Arc;
+
+/// Returns the lines of an explained plan, one row per line.
+#[derive(Debug)]
+pub struct ExplainExec {
+    pub lines: Vec<String>,
+}
+
+impl ExplainExec {
+    pub fn stream(
+        &self,
+        _exec_ctx: Arc<ExecutionContext>,
+    ) -> Result<RowStream> {
+        let rows = self
+            .lines
+            .iter()
+            .map(|line| Ok(Row::new(vec![Datum::Text(line.clone())])))
+            .collect::<Vec<_>>();
+        Ok(Box::pin(stream::iter(rows)))
+    }
+}
@@ -1,8 +1,13 @@
-use crate::common::error::Result;
+use crate::common::error::{FloppyError, Result};
+use crate::common::relation::Row;
+use crate::common::scalar::Datum;
 use crate::sql::context::{ExecutionContext, ExprContext};
 use crate::sql::physical_plan::RowStream;
 use crate::sql::{Expr, PhysicalPlan};
+use futures::{Stream, StreamExt};
+use std::pin::Pin;
 use std::sync::Arc;
+use std::task::{Context, Poll};
 
 #[derive(Debug)]
 pub struct FilterExec {
@@ -12,30 +17,51 @@ pub struct FilterExec {
 }
 
 impl FilterExec {
-    pub fn stream(
-        &self,
-        _exec_ctx: Arc<ExecutionContext>,
-    ) -> Result<RowStream> {
-        todo!()
-        //     loop {
-        //         if let Some(r) = self.input.evaluate()? {
-        //             let v =
-        // self.predicate.evaluate(&self.ecx, &r)?;
-        //             match v {
-        //                 Datum::Boolean(true) => break
-        // Ok(Some(r)),
-        // Datum::Boolean(false) => continue,
-        //                 other => {
-        //                     break
-        // Err(FloppyError::Internal(format!(
-        //                         "predicate evaluate
-        // error: {:?}",
-        // other                     )))
-        //                 }
-        //             }
-        //         } else {
-        //             break Ok(None);
-        //         }
-        //     }
+    pub fn stream(&self, exec_ctx: Arc<ExecutionContext>) -> Result<RowStream> {
+        Ok(Box::pin(FilterExecStream {
+            predicate: self.predicate.clone(),
+            ecx: self.ecx.clone(),
+            input: self.input.stream(exec_ctx)?,
+        }))
+    }
+}
+
+struct FilterExecStream {
+    predicate: Expr,
+    ecx: ExprContext,
+    input: RowStream,
+}
+
+impl FilterExecStream {
+    /// Returns true if the predicate of `r` is true, the rows whose
+    /// predicate is false or null are filtered out.
+    fn keep(&self, r: &Row) -> Result<bool> {
+        match self.predicate.evaluate(&self.ecx, r)? {
+            Datum::Boolean(b) => Ok(b),
+            Datum::Null => Ok(false),
+            other => Err(FloppyError::Internal(format!(
+                "predicate evaluates to non boolean: {other:?}"
+            ))),
+        }
+    }
+}
+
+impl Stream for FilterExecStream {
+    type Item = Result<Row>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        loop {
+            match self.input.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(r))) => match self.keep(&r) {
+                    Ok(true) => return Poll::Ready(Some(Ok(r))),
+                    Ok(false) => continue,
+                    Err(e) => return Poll::Ready(Some(Err(e))),
+                },
+                other => return other,
+            }
+        }
     }
 }
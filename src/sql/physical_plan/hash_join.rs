@@ -0,0 +1,197 @@
+use crate::common::error::Result;
+use crate::common::relation::Row;
+use crate::common::scalar::{Datum, DatumKey};
+use crate::sql::context::ExecutionContext;
+use crate::sql::physical_plan::RowStream;
+use crate::sql::PhysicalPlan;
+use futures::{Stream, StreamExt};
+use std::collections::{HashMap, VecDeque};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+/// Inner joins `left` and `right` on the equality of the columns
+/// `left_keys` of the left rows and the columns `right_keys` of the
+/// right rows, the output row is a left row followed by a right row.
+///
+/// Both inputs are read in turn until one of them is exhausted, the
+/// smaller one, whose rows are then built into a hash table probed by
+/// the rows of the other input. A key with a NULL never matches, the
+/// keys of different numeric types match if they compare equal.
+#[derive(Debug)]
+pub struct HashJoinExec {
+    pub left: Box<PhysicalPlan>,
+    pub right: Box<PhysicalPlan>,
+    pub left_keys: Vec<usize>,
+    pub right_keys: Vec<usize>,
+}
+
+impl HashJoinExec {
+    pub fn stream(&self, exec_ctx: Arc<ExecutionContext>) -> Result<RowStream> {
+        Ok(Box::pin(HashJoinStream {
+            left: Input::new(
+                self.left.stream(exec_ctx.clone())?,
+                self.left_keys.clone(),
+            ),
+            right: Input::new(
+                self.right.stream(exec_ctx)?,
+                self.right_keys.clone(),
+            ),
+            table: None,
+            output: VecDeque::new(),
+        }))
+    }
+}
+
+/// An input of the join and the rows that have been read from it.
+struct Input {
+    rows: RowStream,
+    keys: Vec<usize>,
+    buffer: VecDeque<Row>,
+    done: bool,
+}
+
+impl Input {
+    fn new(rows: RowStream, keys: Vec<usize>) -> Self {
+        Self {
+            rows,
+            keys,
+            buffer: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    /// Reads a row into `buffer`, or marks the input as `done` if it
+    /// is exhausted.
+    fn poll_buffer(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        if self.done {
+            return Poll::Ready(Ok(()));
+        }
+        match self.rows.poll_next_unpin(cx) {
+            Poll::Ready(Some(Ok(r))) => self.buffer.push_back(r),
+            Poll::Ready(Some(Err(e))) => return Poll::Ready(Err(e)),
+            Poll::Ready(None) => self.done = true,
+            Poll::Pending => return Poll::Pending,
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    /// The next row, the buffered rows first.
+    fn poll_row(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<Row>>> {
+        match self.buffer.pop_front() {
+            Some(r) => Poll::Ready(Some(Ok(r))),
+            None if self.done => Poll::Ready(None),
+            None => self.rows.poll_next_unpin(cx),
+        }
+    }
+
+    /// The key of `row`, `None` if any column of the key is NULL.
+    fn key(&self, row: &Row) -> Option<Vec<DatumKey>> {
+        self.keys
+            .iter()
+            .map(|i| match &row.datums()[*i] {
+                Datum::Null => None,
+                d => Some(DatumKey(d.clone())),
+            })
+            .collect()
+    }
+}
+
+/// The rows of the build input, indexed by their keys.
+struct HashTable {
+    /// Whether the rows are of the left input.
+    left: bool,
+    rows: Vec<Row>,
+    index: HashMap<Vec<DatumKey>, Vec<usize>>,
+}
+
+struct HashJoinStream {
+    left: Input,
+    right: Input,
+    table: Option<HashTable>,
+    /// The joined rows of a probe row that are not returned yet.
+    output: VecDeque<Row>,
+}
+
+impl HashJoinStream {
+    /// Reads both inputs in turn until one of them is exhausted and
+    /// builds the hash table with its rows.
+    fn poll_build(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        while !self.left.done && !self.right.done {
+            let left = self.left.poll_buffer(cx)?;
+            let right = self.right.poll_buffer(cx)?;
+            if left.is_pending() && right.is_pending() {
+                return Poll::Pending;
+            }
+        }
+        let left = self.left.done
+            && (!self.right.done
+                || self.left.buffer.len() < self.right.buffer.len());
+        let build = if left {
+            &mut self.left
+        } else {
+            &mut self.right
+        };
+        let mut index: HashMap<Vec<DatumKey>, Vec<usize>> = HashMap::new();
+        let rows = build.buffer.drain(..).collect::<Vec<_>>();
+        for (i, row) in rows.iter().enumerate() {
+            if let Some(key) = build.key(row) {
+                index.entry(key).or_default().push(i);
+            }
+        }
+        self.table = Some(HashTable { left, rows, index });
+        Poll::Ready(Ok(()))
+    }
+
+    /// Joins the probe row `row` with the matching build rows.
+    fn probe(&mut self, row: Row) {
+        let table = self.table.as_ref().expect("hash table is built");
+        let probe = if table.left { &self.right } else { &self.left };
+        let Some(matches) = probe.key(&row).and_then(|k| table.index.get(&k))
+        else {
+            return;
+        };
+        for i in matches {
+            let build = &table.rows[*i];
+            let (left, right) = if table.left {
+                (build, &row)
+            } else {
+                (&row, build)
+            };
+            self.output.push_back(Row::new(
+                left.iter().chain(right.iter()).cloned().collect(),
+            ));
+        }
+    }
+}
+
+impl Stream for HashJoinStream {
+    type Item = Result<Row>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        if self.table.is_none() {
+            match self.poll_build(cx) {
+                Poll::Ready(Ok(())) => {}
+                Poll::Ready(Err(e)) => return Poll::Ready(Some(Err(e))),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        loop {
+            if let Some(r) = self.output.pop_front() {
+                return Poll::Ready(Some(Ok(r)));
+            }
+            let this = &mut *self;
+            let probe = match &this.table {
+                Some(HashTable { left: true, .. }) => &mut this.right,
+                _ => &mut this.left,
+            };
+            match probe.poll_row(cx) {
+                Poll::Ready(Some(Ok(r))) => self.probe(r),
+                other => return other,
+            }
+        }
+    }
+}
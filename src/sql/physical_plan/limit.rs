@@ -0,0 +1,34 @@
+use crate::common::error::Result;
+use crate::sql::context::ExecutionContext;
+use crate::sql::physical_plan::RowStream;
+use crate::sql::PhysicalPlan;
+use futures::{future, StreamExt};
+use std::sync::Arc;
+
+/// Skips the first `offset` rows of `input` and returns at most
+/// `limit` of the rest, all of them if `limit` is `None`.
+#[derive(Debug)]
+pub struct LimitExec {
+    pub input: Box<PhysicalPlan>,
+    pub limit: Option<usize>,
+    pub offset: usize,
+}
+
+impl LimitExec {
+    pub fn stream(&self, exec_ctx: Arc<ExecutionContext>) -> Result<RowStream> {
+        let offset = self.offset;
+        let mut skipped = 0;
+        // the errors are not skipped.
+        let rows = self.input.stream(exec_ctx)?.filter(move |row| {
+            let skip = row.is_ok() && skipped < offset;
+            if skip {
+                skipped += 1;
+            }
+            future::ready(!skip)
+        });
+        match self.limit {
+            Some(limit) => Ok(Box::pin(rows.take(limit))),
+            None => Ok(Box::pin(rows)),
+        }
+    }
+}
@@ -0,0 +1,224 @@
+use crate::common::error::{FloppyError, Result};
+use crate::common::relation::Row;
+use crate::common::scalar::Datum;
+use crate::sql::context::{ExecutionContext, ExprContext};
+use crate::sql::physical_plan::RowStream;
+use crate::sql::{Expr, PhysicalPlan};
+use futures::{Stream, StreamExt};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+/// Inner joins `left` and `right`, the output row is a left row
+/// followed by a right row for which `predicate` is true. The rows
+/// of `right` are kept in memory to be rescanned for each left row.
+#[derive(Debug)]
+pub struct NestedLoopJoinExec {
+    pub left: Box<PhysicalPlan>,
+    pub right: Box<PhysicalPlan>,
+    pub predicate: Expr,
+    /// The context of `predicate`, whose relation is the columns
+    /// of `left` followed by the columns of `right`.
+    pub ecx: ExprContext,
+}
+
+impl NestedLoopJoinExec {
+    pub fn stream(&self, exec_ctx: Arc<ExecutionContext>) -> Result<RowStream> {
+        Ok(Box::pin(NestedLoopJoinStream {
+            predicate: self.predicate.clone(),
+            ecx: self.ecx.clone(),
+            left: self.left.stream(exec_ctx.clone())?,
+            right: self.right.stream(exec_ctx)?,
+            right_rows: vec![],
+            right_done: false,
+            current: None,
+        }))
+    }
+}
+
+struct NestedLoopJoinStream {
+    predicate: Expr,
+    ecx: ExprContext,
+    left: RowStream,
+    right: RowStream,
+    /// The rows of `right`, which are complete once `right_done`.
+    right_rows: Vec<Row>,
+    right_done: bool,
+    /// The left row being joined and the index of the next right
+    /// row to join with.
+    current: Option<(Row, usize)>,
+}
+
+impl NestedLoopJoinStream {
+    /// Returns true if the predicate of the joined row `r` is true,
+    /// the rows whose predicate is false or null are not returned.
+    fn matches(&self, r: &Row) -> Result<bool> {
+        match self.predicate.evaluate(&self.ecx, r)? {
+            Datum::Boolean(b) => Ok(b),
+            Datum::Null => Ok(false),
+            other => Err(FloppyError::Internal(format!(
+                "join predicate evaluates to non boolean: {other:?}"
+            ))),
+        }
+    }
+
+    /// Joins the current left row with the remaining right rows,
+    /// returns the first joined row that matches.
+    fn next_match(&mut self) -> Result<Option<Row>> {
+        while let Some((left, i)) = &mut self.current {
+            let Some(right) = self.right_rows.get(*i) else {
+                self.current = None;
+                break;
+            };
+            *i += 1;
+            let row =
+                Row::new(left.iter().chain(right.iter()).cloned().collect());
+            if self.matches(&row)? {
+                return Ok(Some(row));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl Stream for NestedLoopJoinStream {
+    type Item = Result<Row>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        while !self.right_done {
+            match self.right.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(r))) => self.right_rows.push(r),
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
+                Poll::Ready(None) => self.right_done = true,
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        loop {
+            match self.next_match() {
+                Ok(Some(r)) => return Poll::Ready(Some(Ok(r))),
+                Ok(None) => {}
+                Err(e) => return Poll::Ready(Some(Err(e))),
+            }
+            match self.left.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(r))) => self.current = Some((r, 0)),
+                other => return other,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::memory::MemCatalog;
+    use crate::common::relation::{ColumnRef, ColumnType, RelationDesc};
+    use crate::common::scalar::ScalarType;
+    use crate::sql::context::StatementContext;
+    use crate::sql::physical_plan::values::ValuesExec;
+    use crate::sql::primitive::expr::Literal;
+    use crate::sql::primitive::func::equal;
+    use crate::test_util::seeder;
+
+    fn values(
+        scx: &Arc<StatementContext>,
+        rows: &[(i64, &str)],
+    ) -> PhysicalPlan {
+        let rows = rows
+            .iter()
+            .map(|(i, s)| {
+                vec![
+                    Expr::Literal(Literal {
+                        datum: Datum::Int64(*i),
+                        scalar_type: ScalarType::Int64,
+                    }),
+                    Expr::Literal(Literal {
+                        datum: Datum::Text(s.to_string()),
+                        scalar_type: ScalarType::Text,
+                    }),
+                ]
+            })
+            .collect();
+        PhysicalPlan::Values(ValuesExec {
+            rows,
+            ecx: ExprContext {
+                scx: scx.clone(),
+                rel_desc: Arc::new(RelationDesc::empty()),
+            },
+        })
+    }
+
+    fn column(id: usize, name: &str) -> Expr {
+        Expr::Column(ColumnRef {
+            id,
+            name: name.to_string(),
+        })
+    }
+
+    #[tokio::test]
+    async fn inner_join() -> Result<()> {
+        let scx =
+            Arc::new(StatementContext::new(Arc::new(MemCatalog::default())));
+        let column_type = |scalar_type| ColumnType::new(scalar_type, false);
+        let ecx = ExprContext {
+            scx: scx.clone(),
+            rel_desc: Arc::new(RelationDesc::new(
+                vec![
+                    column_type(ScalarType::Int64),
+                    column_type(ScalarType::Text),
+                    column_type(ScalarType::Int64),
+                    column_type(ScalarType::Text),
+                ],
+                vec![
+                    "l1".to_string(),
+                    "l2".to_string(),
+                    "r1".to_string(),
+                    "r2".to_string(),
+                ],
+                vec![],
+                vec![],
+            )),
+        };
+        let predicate = equal(&ecx, &column(0, "l1"), &column(2, "r1"))?;
+        let join = PhysicalPlan::NestedLoopJoin(NestedLoopJoinExec {
+            left: Box::new(values(&scx, &[(1, "a"), (2, "b"), (3, "c")])),
+            right: Box::new(values(
+                &scx,
+                &[(3, "x"), (1, "y"), (4, "z"), (1, "w")],
+            )),
+            predicate,
+            ecx,
+        });
+
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&vec![])?;
+        let exec_ctx =
+            Arc::new(ExecutionContext::new(catalog_store, table_store));
+        let rows = join
+            .stream(exec_ctx)?
+            .collect::<Vec<Result<Row>>>()
+            .await
+            .into_iter()
+            .collect::<Result<Vec<Row>>>()?;
+
+        let row = |l1, l2: &str, r1, r2: &str| {
+            Row::new(vec![
+                Datum::Int64(l1),
+                Datum::Text(l2.to_string()),
+                Datum::Int64(r1),
+                Datum::Text(r2.to_string()),
+            ])
+        };
+        assert_eq!(
+            rows,
+            vec![
+                row(1, "a", 1, "y"),
+                row(1, "a", 1, "w"),
+                row(3, "c", 3, "x"),
+            ]
+        );
+        Ok(())
+    }
+}
@@ -1,11 +1,27 @@
 use crate::catalog::names::FullObjectName;
 use crate::common::error::Result;
-use crate::common::relation::{GlobalId, RelationDesc};
+use crate::common::relation::{GlobalId, IndexRange, RelationDesc};
 use crate::sql::context::{ExprContext, StatementContext};
+use crate::sql::physical_plan::aggregate::AggregateExec;
+use crate::sql::physical_plan::distinct::DistinctExec;
 use crate::sql::physical_plan::empty::EmptyExec;
+use crate::sql::physical_plan::explain::ExplainExec;
 use crate::sql::physical_plan::filter::FilterExec;
+use crate::sql::physical_plan::hash_join::HashJoinExec;
+use crate::sql::physical_plan::limit::LimitExec;
+use crate::sql::physical_plan::nested_loop_join::NestedLoopJoinExec;
 use crate::sql::physical_plan::pri_scan::PriKeyScanExec;
 use crate::sql::physical_plan::projection::ProjectionExec;
+use crate::sql::physical_plan::sort::SortExec;
+use crate::sql::physical_plan::subquery::SubqueryExec;
+use crate::sql::physical_plan::top_n::TopNExec;
+use crate::sql::physical_plan::union::UnionExec;
+use crate::sql::physical_plan::update::UpdateExec;
+use crate::sql::physical_plan::values::ValuesExec;
+use crate::sql::primitive::agg::AggregateExpr;
+use crate::sql::primitive::expr::ScalarSubquery;
+use crate::sql::primitive::func::{conjuncts, BinaryExpr, BinaryFunc};
+use crate::sql::primitive::sort::SortExpr;
 use crate::sql::{Expr, LogicalPlan, PhysicalPlan};
 use std::sync::Arc;
 
@@ -16,7 +32,10 @@ pub(crate) fn plan(
     logical_plan: LogicalPlan,
 ) -> Result<PhysicalPlan> {
     match logical_plan {
-        LogicalPlan::Empty => Ok(PhysicalPlan::Empty(EmptyExec::new())),
+        LogicalPlan::Empty => Ok(PhysicalPlan::Empty(EmptyExec {
+            produce_one_row: true,
+        })),
+        LogicalPlan::Values { rows, .. } => plan_values(scx, rows),
         LogicalPlan::Filter { input, predicate } => {
             plan_filter(scx, *input, predicate)
         }
@@ -29,10 +48,71 @@ pub(crate) fn plan(
             table_id,
             rel_desc,
             name,
-        } => plan_table(table_id, rel_desc, name),
+            range,
+            projection,
+        } => plan_table(table_id, rel_desc, name, range, projection),
+        LogicalPlan::Aggregate {
+            input,
+            group_by,
+            aggregates,
+            rel_desc,
+        } => plan_aggregate(scx, *input, group_by, aggregates, rel_desc),
+        LogicalPlan::Distinct { input } => {
+            Ok(PhysicalPlan::Distinct(DistinctExec {
+                input: Box::new(plan(scx, *input)?),
+            }))
+        }
+        LogicalPlan::Sort { input, keys } => plan_sort(scx, *input, keys),
+        LogicalPlan::Limit {
+            input,
+            limit,
+            offset,
+        } => Ok(PhysicalPlan::Limit(LimitExec {
+            input: Box::new(plan(scx, *input)?),
+            limit,
+            offset,
+        })),
+        LogicalPlan::TopN { input, keys, k } => {
+            plan_top_n(scx, *input, keys, k)
+        }
+        LogicalPlan::Join {
+            left,
+            right,
+            predicate,
+        } => plan_join(scx, *left, *right, predicate),
+        LogicalPlan::Union { inputs, all, .. } => {
+            let inputs = inputs
+                .into_iter()
+                .map(|input| plan(scx, input))
+                .collect::<Result<Vec<_>>>()?;
+            Ok(PhysicalPlan::Union(UnionExec { inputs, all }))
+        }
+        LogicalPlan::Update {
+            table_id,
+            rel_desc,
+            assignments,
+            filter,
+            ..
+        } => plan_update(scx, table_id, rel_desc, assignments, filter),
+        LogicalPlan::Explain { plan, verbose } => {
+            Ok(PhysicalPlan::Explain(ExplainExec {
+                lines: plan.explain(verbose),
+            }))
+        }
     }
 }
 
+fn plan_values(
+    scx: &StatementContext,
+    rows: Vec<Vec<Expr>>,
+) -> Result<PhysicalPlan> {
+    let ecx = ExprContext {
+        scx: Arc::new(scx.clone()),
+        rel_desc: Arc::new(RelationDesc::empty()),
+    };
+    Ok(PhysicalPlan::Values(ValuesExec { rows, ecx }))
+}
+
 fn plan_filter(
     scx: &StatementContext,
     input: LogicalPlan,
@@ -71,21 +151,205 @@ fn plan_projection(
     }))
 }
 
+fn plan_aggregate(
+    scx: &StatementContext,
+    input: LogicalPlan,
+    group_by: Vec<Expr>,
+    aggregates: Vec<AggregateExpr>,
+    rel_desc: RelationDesc,
+) -> Result<PhysicalPlan> {
+    let ecx = ExprContext {
+        scx: Arc::new(scx.clone()),
+        rel_desc: Arc::new(input.rel_desc()),
+    };
+
+    let input = plan(scx, input)?;
+
+    Ok(PhysicalPlan::Aggregate(AggregateExec {
+        group_by,
+        aggregates,
+        ecx,
+        input: Box::new(input),
+        rel_desc: Arc::new(rel_desc),
+    }))
+}
+
+fn plan_sort(
+    scx: &StatementContext,
+    input: LogicalPlan,
+    keys: Vec<SortExpr>,
+) -> Result<PhysicalPlan> {
+    let ecx = ExprContext {
+        scx: Arc::new(scx.clone()),
+        rel_desc: Arc::new(input.rel_desc()),
+    };
+    Ok(PhysicalPlan::Sort(SortExec {
+        input: Box::new(plan(scx, input)?),
+        keys,
+        ecx,
+    }))
+}
+
+fn plan_top_n(
+    scx: &StatementContext,
+    input: LogicalPlan,
+    keys: Vec<SortExpr>,
+    k: usize,
+) -> Result<PhysicalPlan> {
+    let ecx = ExprContext {
+        scx: Arc::new(scx.clone()),
+        rel_desc: Arc::new(input.rel_desc()),
+    };
+    Ok(PhysicalPlan::TopN(TopNExec {
+        input: Box::new(plan(scx, input)?),
+        keys,
+        k,
+        ecx,
+    }))
+}
+
+fn plan_update(
+    scx: &StatementContext,
+    table_id: GlobalId,
+    rel_desc: RelationDesc,
+    assignments: Vec<(usize, Expr)>,
+    filter: Option<Expr>,
+) -> Result<PhysicalPlan> {
+    let ecx = ExprContext {
+        scx: Arc::new(scx.clone()),
+        rel_desc: Arc::new(rel_desc),
+    };
+    Ok(PhysicalPlan::Update(UpdateExec {
+        table_id,
+        assignments,
+        filter,
+        ecx,
+    }))
+}
+
+/// Executes the plans of `subqueries` before `input`, each of them
+/// sets the value of its subquery. `input` is returned as is if there
+/// is no subquery.
+pub(crate) fn plan_subqueries(
+    scx: &StatementContext,
+    input: PhysicalPlan,
+    subqueries: Vec<(LogicalPlan, ScalarSubquery)>,
+) -> Result<PhysicalPlan> {
+    if subqueries.is_empty() {
+        return Ok(input);
+    }
+    let subqueries = subqueries
+        .into_iter()
+        .map(|(subquery_plan, subquery)| {
+            Ok((plan(scx, subquery_plan)?, subquery))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(PhysicalPlan::Subquery(SubqueryExec {
+        input: Box::new(input),
+        subqueries,
+    }))
+}
+
+/// Inner joins `left` and `right` on `predicate`, which refers to
+/// the columns of `left` followed by the columns of `right`. A hash
+/// join is used if `predicate` only compares the columns of both
+/// sides for equality, a nested-loop join otherwise.
+pub(crate) fn plan_join(
+    scx: &StatementContext,
+    left: LogicalPlan,
+    right: LogicalPlan,
+    predicate: Expr,
+) -> Result<PhysicalPlan> {
+    let left_desc = left.rel_desc();
+    let left_arity = left_desc.column_types().len();
+    let rel_desc = left_desc.concat(&right.rel_desc());
+    let left = Box::new(plan(scx, left)?);
+    let right = Box::new(plan(scx, right)?);
+    if let Some((left_keys, right_keys)) =
+        equi_join_keys(&predicate, left_arity)
+    {
+        return Ok(PhysicalPlan::HashJoin(HashJoinExec {
+            left,
+            right,
+            left_keys,
+            right_keys,
+        }));
+    }
+    let ecx = ExprContext {
+        scx: Arc::new(scx.clone()),
+        rel_desc: Arc::new(rel_desc),
+    };
+    Ok(PhysicalPlan::NestedLoopJoin(NestedLoopJoinExec {
+        left,
+        right,
+        predicate,
+        ecx,
+    }))
+}
+
+/// Returns the columns of the left rows and the columns of the right
+/// rows that must be equal, if `predicate` is a conjunction of
+/// equalities between a left column and a right column. The columns
+/// whose index is less than `left_arity` are of the left rows.
+fn equi_join_keys(
+    predicate: &Expr,
+    left_arity: usize,
+) -> Option<(Vec<usize>, Vec<usize>)> {
+    let mut left_keys = vec![];
+    let mut right_keys = vec![];
+    for conjunct in conjuncts(predicate.clone()) {
+        let (c1, c2) = match conjunct {
+            Expr::CallBinary(BinaryExpr {
+                func: BinaryFunc::Eq,
+                expr1,
+                expr2,
+            }) => match (*expr1, *expr2) {
+                (Expr::Column(c1), Expr::Column(c2)) => (c1.id, c2.id),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        let (l, r) = match (c1 < left_arity, c2 < left_arity) {
+            (true, false) => (c1, c2),
+            (false, true) => (c2, c1),
+            _ => return None,
+        };
+        left_keys.push(l);
+        right_keys.push(r - left_arity);
+    }
+    Some((left_keys, right_keys))
+}
+
 fn plan_table(
     table_id: GlobalId,
     rel_desc: RelationDesc,
     full_name: FullObjectName,
+    range: IndexRange,
+    projection: Option<Vec<usize>>,
 ) -> Result<PhysicalPlan> {
     Ok(PhysicalPlan::PriKeyScan(PriKeyScanExec {
         table_id,
         rel_desc,
         full_name,
+        range,
+        projection,
     }))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::catalog::memory::MemCatalog;
+    use crate::common::relation::ColumnType;
+    use crate::common::relation::IndexKeyDatums;
+    use crate::common::relation::{ColumnRef, Row};
+    use crate::common::scalar::{Datum, ScalarType};
+    use crate::sql::context::ExecutionContext;
+    use crate::sql::primitive::expr::Literal;
+    use crate::sql::{analyzer, optimizer};
+    use crate::test_util::seeder;
+    use futures::StreamExt;
+    use std::ops::Bound;
 
     fn test_select_no_relation() -> Result<()> {
         Ok(())
@@ -98,4 +362,204 @@ mod tests {
     fn test_filter() -> Result<()> {
         Ok(())
     }
+
+    fn physical_plan(sql: &str) -> Result<PhysicalPlan> {
+        let mut catalog = MemCatalog::default();
+        let desc = RelationDesc::new(
+            vec![
+                ColumnType::new(ScalarType::Int64, false),
+                ColumnType::new(ScalarType::Int64, false),
+            ],
+            vec!["c1".to_string(), "c2".to_string()],
+            vec![0],
+            vec![],
+        );
+        catalog.insert_table("t", 1, desc);
+        let scx = StatementContext::new(Arc::new(catalog));
+        let statement = &crate::sql::planner::parse(sql)?[0];
+        let logical_plan = analyzer::transform_statement(&scx, statement)?;
+        plan(&scx, optimizer::optimize(logical_plan))
+    }
+
+    #[test]
+    fn prim_key_filter() -> Result<()> {
+        // the filter is replaced by the range of the scan.
+        let plan = physical_plan("SELECT c2 FROM t WHERE c1 > 100")?;
+        match plan {
+            PhysicalPlan::Projection(ProjectionExec { input, .. }) => {
+                match *input {
+                    PhysicalPlan::PriKeyScan(scan) => assert_eq!(
+                        scan.range,
+                        IndexRange {
+                            lo: Bound::Excluded(IndexKeyDatums::from_iter([
+                                Datum::Int64(100)
+                            ])),
+                            hi: Bound::Unbounded,
+                        }
+                    ),
+                    plan => panic!("unexpected plan: {plan:?}"),
+                }
+            }
+            plan => panic!("unexpected plan: {plan:?}"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn non_prim_key_filter() -> Result<()> {
+        let plan = physical_plan("SELECT c1 FROM t WHERE c2 > 100")?;
+        match plan {
+            PhysicalPlan::Projection(ProjectionExec { input, .. }) => {
+                match *input {
+                    PhysicalPlan::Filter(FilterExec { input, .. }) => {
+                        match *input {
+                            PhysicalPlan::PriKeyScan(scan) => {
+                                assert!(scan.range.is_full())
+                            }
+                            plan => panic!("unexpected plan: {plan:?}"),
+                        }
+                    }
+                    plan => panic!("unexpected plan: {plan:?}"),
+                }
+            }
+            plan => panic!("unexpected plan: {plan:?}"),
+        }
+        Ok(())
+    }
+
+    /// A `VALUES` of a nullable integer column and a text column.
+    fn values(prefix: &str, rows: &[(Option<i64>, &str)]) -> LogicalPlan {
+        let rows = rows
+            .iter()
+            .map(|(i, s)| {
+                vec![
+                    Expr::Literal(Literal {
+                        datum: i.map_or(Datum::Null, Datum::Int64),
+                        scalar_type: ScalarType::Int64,
+                    }),
+                    Expr::Literal(Literal {
+                        datum: Datum::Text(s.to_string()),
+                        scalar_type: ScalarType::Text,
+                    }),
+                ]
+            })
+            .collect();
+        let rel_desc = RelationDesc::new(
+            vec![
+                ColumnType::new(ScalarType::Int64, true),
+                ColumnType::new(ScalarType::Text, false),
+            ],
+            vec![format!("{prefix}1"), format!("{prefix}2")],
+            vec![],
+            vec![],
+        );
+        LogicalPlan::Values { rows, rel_desc }
+    }
+
+    fn compare_columns(func: BinaryFunc, c1: usize, c2: usize) -> Expr {
+        let column = |id| {
+            Expr::Column(ColumnRef {
+                id,
+                name: format!("c{id}"),
+            })
+        };
+        Expr::CallBinary(BinaryExpr {
+            func,
+            expr1: Box::new(column(c1)),
+            expr2: Box::new(column(c2)),
+        })
+    }
+
+    /// The rows of `plan`, sorted.
+    async fn join_rows(plan: PhysicalPlan) -> Result<Vec<Row>> {
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&vec![])?;
+        let exec_ctx =
+            Arc::new(ExecutionContext::new(catalog_store, table_store));
+        let mut rows = plan
+            .stream(exec_ctx)?
+            .collect::<Vec<Result<Row>>>()
+            .await
+            .into_iter()
+            .collect::<Result<Vec<Row>>>()?;
+        rows.sort_by(|r1, r2| r1.datums().cmp(r2.datums()));
+        Ok(rows)
+    }
+
+    #[tokio::test]
+    async fn hash_join() -> Result<()> {
+        let scx = StatementContext::new(Arc::new(MemCatalog::default()));
+        let inputs = [
+            [
+                (Some(1), "a"),
+                (Some(2), "b"),
+                (Some(3), "c"),
+                (Some(1), "d"),
+            ]
+            .as_slice(),
+            [(Some(3), "x"), (Some(1), "y"), (Some(4), "z")].as_slice(),
+            [(None, "n"), (Some(1), "o"), (None, "p")].as_slice(),
+            [].as_slice(),
+        ];
+        for left in inputs {
+            for right in inputs {
+                let predicate = compare_columns(BinaryFunc::Eq, 2, 0);
+                let hash_join = plan_join(
+                    &scx,
+                    values("l", left),
+                    values("r", right),
+                    predicate.clone(),
+                )?;
+                assert!(matches!(hash_join, PhysicalPlan::HashJoin(_)));
+
+                let (left_plan, right_plan) =
+                    (values("l", left), values("r", right));
+                let rel_desc =
+                    left_plan.rel_desc().concat(&right_plan.rel_desc());
+                let nested_loop_join =
+                    PhysicalPlan::NestedLoopJoin(NestedLoopJoinExec {
+                        left: Box::new(plan(&scx, left_plan)?),
+                        right: Box::new(plan(&scx, right_plan)?),
+                        predicate,
+                        ecx: ExprContext {
+                            scx: Arc::new(scx.clone()),
+                            rel_desc: Arc::new(rel_desc),
+                        },
+                    });
+
+                let rows = join_rows(hash_join).await?;
+                assert_eq!(rows, join_rows(nested_loop_join).await?);
+                // the NULL keys never match.
+                assert!(rows.iter().all(|r| !r.datums()[0].is_null()));
+                let matches = left
+                    .iter()
+                    .flat_map(|(l, _)| {
+                        right.iter().filter(move |(r, _)| l.is_some() && l == r)
+                    })
+                    .count();
+                assert_eq!(rows.len(), matches);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn non_equi_join() -> Result<()> {
+        let scx = StatementContext::new(Arc::new(MemCatalog::default()));
+        let predicates = [
+            compare_columns(BinaryFunc::Lt, 0, 2),
+            // both columns are of the left input.
+            compare_columns(BinaryFunc::Eq, 0, 1),
+        ];
+        for predicate in predicates {
+            let join = plan_join(
+                &scx,
+                values("l", &[(Some(1), "a")]),
+                values("r", &[(Some(1), "b")]),
+                predicate,
+            )?;
+            assert!(matches!(join, PhysicalPlan::NestedLoopJoin(_)));
+        }
+        Ok(())
+    }
 }
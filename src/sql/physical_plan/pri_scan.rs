@@ -1,6 +1,6 @@
 use crate::catalog::names::FullObjectName;
 use crate::common::error::Result;
-use crate::common::relation::{GlobalId, RelationDesc, Row};
+use crate::common::relation::{GlobalId, IndexRange, RelationDesc, Row};
 use crate::sql::context::ExecutionContext;
 use crate::sql::physical_plan::RowStream;
 use crate::storage::RowIter;
@@ -14,11 +14,34 @@ pub struct PriKeyScanExec {
     pub table_id: GlobalId,
     pub rel_desc: RelationDesc,
     pub full_name: FullObjectName,
+    /// The range of the primary key to scan.
+    pub range: IndexRange,
+    /// The columns of the table to scan, all the columns are
+    /// scanned if it is `None`.
+    pub projection: Option<Vec<usize>>,
 }
 
 impl PriKeyScanExec {
     pub fn stream(&self, exec_ctx: Arc<ExecutionContext>) -> Result<RowStream> {
-        let row_iter = exec_ctx.table_store.full_scan(&self.table_id)?;
+        let item = exec_ctx.catalog_store.get_item(&self.table_id)?;
+        let projection = self.projection.as_deref();
+        let row_iter: RowIter = match item.system_rows() {
+            Some(rows) => {
+                let rows = rows
+                    .iter()
+                    .map(|r| match projection {
+                        Some(columns) => Ok(r.project(columns)),
+                        None => Ok(r.clone()),
+                    })
+                    .collect::<Vec<_>>();
+                Box::new(rows.into_iter())
+            }
+            None => exec_ctx.table_store.primary_index_range(
+                &self.table_id,
+                &self.range,
+                projection,
+            )?,
+        };
         Ok(Box::pin(PriKeyScanExecStream { row_iter }))
     }
 }
@@ -41,3 +64,99 @@ impl Stream for PriKeyScanExecStream {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::names::PartialObjectName;
+    use crate::common::relation::IndexKeyDatums;
+    use crate::common::scalar::Datum;
+    use crate::sql::PhysicalPlan;
+    use crate::test_util::seeder;
+    use futures::TryStreamExt;
+    use std::ops::Bound;
+
+    #[tokio::test]
+    async fn scan_seeded_table() -> Result<()> {
+        let rows = (0..100)
+            .map(|i| Row::new(vec![Datum::Int64(i), Datum::Int64(i * 10)]))
+            .collect::<Vec<Row>>();
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&rows)?;
+        let exec_ctx =
+            Arc::new(ExecutionContext::new(catalog_store, table_store));
+        // the primary key is (c1, c2).
+        let key = |i: i64| {
+            IndexKeyDatums::from_iter([Datum::Int64(i), Datum::Int64(i * 10)])
+        };
+
+        let tests = [
+            (IndexRange::full(), None, 100),
+            (
+                IndexRange {
+                    lo: Bound::Included(key(10)),
+                    hi: Bound::Excluded(key(20)),
+                },
+                Some(vec![1]),
+                10,
+            ),
+            (
+                IndexRange {
+                    lo: Bound::Excluded(key(90)),
+                    hi: Bound::Unbounded,
+                },
+                Some(vec![0, 1]),
+                9,
+            ),
+        ];
+        for (range, projection, count) in tests {
+            let arity = projection.as_ref().map_or(2, Vec::len);
+            let plan = PhysicalPlan::PriKeyScan(PriKeyScanExec {
+                table_id: 1,
+                rel_desc: RelationDesc::empty(),
+                full_name: PartialObjectName::from("test").into(),
+                range,
+                projection,
+            });
+            let rows = plan
+                .stream(exec_ctx.clone())?
+                .try_collect::<Vec<Row>>()
+                .await?;
+            assert_eq!(rows.len(), count);
+            assert!(rows.iter().all(|r| r.datums().len() == arity));
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn independent_table_stores() -> Result<()> {
+        // each execution reads the table store of its own context.
+        let seeded = |n: i64| -> Result<Arc<ExecutionContext>> {
+            let rows = (0..n)
+                .map(|i| Row::new(vec![Datum::Int64(i), Datum::Int64(i)]))
+                .collect::<Vec<Row>>();
+            let (catalog_store, table_store) =
+                seeder::seed_catalog_and_table(&rows)?;
+            Ok(Arc::new(ExecutionContext::new(catalog_store, table_store)))
+        };
+        let contexts: Vec<Arc<ExecutionContext>> = vec![seeded(3)?, seeded(5)?];
+        contexts[0]
+            .table_store
+            .insert(&1, &Row::new(vec![Datum::Int64(9), Datum::Int64(9)]))?;
+
+        let plan = PhysicalPlan::PriKeyScan(PriKeyScanExec {
+            table_id: 1,
+            rel_desc: RelationDesc::empty(),
+            full_name: PartialObjectName::from("test").into(),
+            range: IndexRange::full(),
+            projection: None,
+        });
+        let mut counts = vec![];
+        for exec_ctx in contexts {
+            let rows = plan.stream(exec_ctx)?.try_collect::<Vec<Row>>().await?;
+            counts.push(rows.len());
+        }
+        assert_eq!(counts, vec![4, 5]);
+        Ok(())
+    }
+}
@@ -18,6 +18,8 @@ pub struct ProjectionExec {
 
 impl ProjectionExec {
     pub fn stream(&self, exec_ctx: Arc<ExecutionContext>) -> Result<RowStream> {
+        // the output row has a datum per column of `rel_desc`.
+        debug_assert_eq!(self.exprs.len(), self.rel_desc.column_types().len());
         Ok(Box::pin(ProjectionExecStream {
             ecx: self.ecx.clone(),
             input: self.input.stream(exec_ctx)?,
@@ -0,0 +1,174 @@
+use crate::common::error::Result;
+use crate::common::relation::Row;
+use crate::common::scalar::Datum;
+use crate::sql::context::{ExecutionContext, ExprContext};
+use crate::sql::physical_plan::RowStream;
+use crate::sql::primitive::sort::SortExpr;
+use crate::sql::PhysicalPlan;
+use futures::{stream, StreamExt, TryStreamExt};
+use std::cmp::Ordering;
+use std::sync::Arc;
+
+/// Sorts the rows of `input` by `keys`. All the rows are kept in
+/// memory, the rows whose keys are equal remain in the order of
+/// `input`.
+#[derive(Debug)]
+pub struct SortExec {
+    pub input: Box<PhysicalPlan>,
+    pub keys: Vec<SortExpr>,
+    pub ecx: ExprContext,
+}
+
+impl SortExec {
+    pub fn stream(&self, exec_ctx: Arc<ExecutionContext>) -> Result<RowStream> {
+        let input = self.input.stream(exec_ctx)?;
+        let keys = self.keys.clone();
+        let ecx = self.ecx.clone();
+        let sorted = async move {
+            let rows = input.try_collect::<Vec<Row>>().await?;
+            sort(rows, &keys, &ecx)
+        };
+        Ok(Box::pin(
+            stream::once(sorted)
+                .map_ok(|rows| stream::iter(rows.into_iter().map(Ok)))
+                .try_flatten(),
+        ))
+    }
+}
+
+/// Sorts `rows` by `keys` with a stable sort.
+fn sort(
+    rows: Vec<Row>,
+    keys: &[SortExpr],
+    ecx: &ExprContext,
+) -> Result<Vec<Row>> {
+    let mut keyed = rows
+        .into_iter()
+        .map(|row| {
+            let datums = keys
+                .iter()
+                .map(|k| k.expr.evaluate(ecx, &row))
+                .collect::<Result<Vec<Datum>>>()?;
+            Ok((datums, row))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    keyed.sort_by(|(d1, _), (d2, _)| {
+        keys.iter()
+            .zip(d1.iter().zip(d2))
+            .map(|(k, (d1, d2))| k.compare(d1, d2))
+            .find(|ord| ord.is_ne())
+            .unwrap_or(Ordering::Equal)
+    });
+    Ok(keyed.into_iter().map(|(_, row)| row).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::memory::MemCatalog;
+    use crate::common::relation::{ColumnRef, ColumnType, RelationDesc};
+    use crate::common::scalar::ScalarType;
+    use crate::sql::context::StatementContext;
+    use crate::sql::physical_plan::values::ValuesExec;
+    use crate::sql::primitive::expr::Literal;
+    use crate::sql::Expr;
+    use crate::test_util::seeder;
+
+    fn sort_key(id: usize, asc: bool, nulls_first: bool) -> SortExpr {
+        SortExpr {
+            expr: Expr::Column(ColumnRef {
+                id,
+                name: format!("c{}", id + 1),
+            }),
+            asc,
+            nulls_first,
+        }
+    }
+
+    #[tokio::test]
+    async fn sort_keys() -> Result<()> {
+        let scx =
+            Arc::new(StatementContext::new(Arc::new(MemCatalog::default())));
+        let literal = |i: Option<i64>| {
+            Expr::Literal(Literal {
+                datum: i.map_or(Datum::Null, Datum::Int64),
+                scalar_type: ScalarType::Int64,
+            })
+        };
+        // the third column is the position in the input.
+        let input = [
+            (Some(1), Some(1)),
+            (None, Some(2)),
+            (Some(2), None),
+            (Some(1), Some(3)),
+            (Some(2), Some(1)),
+            (None, None),
+            (Some(1), Some(1)),
+        ]
+        .iter()
+        .enumerate()
+        .map(|(i, (c1, c2))| {
+            vec![literal(*c1), literal(*c2), literal(Some(i as i64))]
+        })
+        .collect::<Vec<_>>();
+        let ecx = ExprContext {
+            scx: scx.clone(),
+            rel_desc: Arc::new(RelationDesc::new(
+                vec![
+                    ColumnType::new(ScalarType::Int64, true),
+                    ColumnType::new(ScalarType::Int64, true),
+                    ColumnType::new(ScalarType::Int64, false),
+                ],
+                vec!["c1".to_string(), "c2".to_string(), "c3".to_string()],
+                vec![],
+                vec![],
+            )),
+        };
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&vec![])?;
+        let exec_ctx =
+            Arc::new(ExecutionContext::new(catalog_store, table_store));
+
+        let tests = [
+            // c1, c2
+            (
+                vec![sort_key(0, true, false), sort_key(1, true, false)],
+                vec![0, 6, 3, 4, 2, 1, 5],
+            ),
+            // c1 DESC, c2
+            (
+                vec![sort_key(0, false, true), sort_key(1, true, false)],
+                vec![1, 5, 4, 2, 0, 6, 3],
+            ),
+            // c1 NULLS FIRST, c2 DESC NULLS LAST
+            (
+                vec![sort_key(0, true, true), sort_key(1, false, false)],
+                vec![1, 5, 3, 0, 6, 4, 2],
+            ),
+            // c2 DESC, the equal keys remain in the input order.
+            (vec![sort_key(1, false, true)], vec![2, 5, 3, 1, 0, 4, 6]),
+        ];
+        for (keys, expected) in tests {
+            let plan = PhysicalPlan::Sort(SortExec {
+                input: Box::new(PhysicalPlan::Values(ValuesExec {
+                    rows: input.clone(),
+                    ecx: ExprContext {
+                        scx: scx.clone(),
+                        rel_desc: Arc::new(RelationDesc::empty()),
+                    },
+                })),
+                keys,
+                ecx: ecx.clone(),
+            });
+            let positions = plan
+                .stream(exec_ctx.clone())?
+                .map_ok(|row| row.datums()[2].clone())
+                .try_collect::<Vec<Datum>>()
+                .await?;
+            let expected =
+                expected.into_iter().map(Datum::Int64).collect::<Vec<_>>();
+            assert_eq!(positions, expected);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,58 @@
+use crate::common::error::{FloppyError, Result};
+use crate::common::scalar::Datum;
+use crate::sql::context::ExecutionContext;
+use crate::sql::physical_plan::RowStream;
+use crate::sql::primitive::expr::ScalarSubquery;
+use crate::sql::PhysicalPlan;
+use futures::{stream, TryStreamExt};
+use std::sync::Arc;
+
+/// Executes the scalar subqueries of `input` before its rows are
+/// computed, each of them is a plan and the subquery it sets the
+/// value of.
+#[derive(Debug)]
+pub struct SubqueryExec {
+    pub input: Box<PhysicalPlan>,
+    /// The subqueries in the order they are executed, a subquery
+    /// comes after the subqueries nested in it.
+    pub subqueries: Vec<(PhysicalPlan, ScalarSubquery)>,
+}
+
+impl SubqueryExec {
+    pub fn stream(&self, exec_ctx: Arc<ExecutionContext>) -> Result<RowStream> {
+        let subqueries = self
+            .subqueries
+            .iter()
+            .map(|(plan, subquery)| {
+                Ok((plan.stream(exec_ctx.clone())?, subquery.clone()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let input = self.input.stream(exec_ctx)?;
+        Ok(Box::pin(
+            stream::once(execute(subqueries, input)).try_flatten(),
+        ))
+    }
+}
+
+/// Sets the values of `subqueries` in turn, then returns `input`.
+async fn execute(
+    subqueries: Vec<(RowStream, ScalarSubquery)>,
+    input: RowStream,
+) -> Result<RowStream> {
+    for (rows, subquery) in subqueries {
+        subquery.set_value(single_value(rows).await?);
+    }
+    Ok(input)
+}
+
+/// The value of the single column of `rows`, which has at most one
+/// row. It is NULL if there is no row.
+async fn single_value(mut rows: RowStream) -> Result<Datum> {
+    let Some(row) = rows.try_next().await? else {
+        return Ok(Datum::Null);
+    };
+    if rows.try_next().await?.is_some() {
+        return Err(FloppyError::CardinalityViolation);
+    }
+    row.column_value(0)
+}
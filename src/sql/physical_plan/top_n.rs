@@ -0,0 +1,285 @@
+use crate::common::error::Result;
+use crate::common::relation::Row;
+use crate::common::scalar::Datum;
+use crate::sql::context::{ExecutionContext, ExprContext};
+use crate::sql::physical_plan::RowStream;
+use crate::sql::primitive::sort::SortExpr;
+use crate::sql::PhysicalPlan;
+use futures::{future, stream, StreamExt, TryStreamExt};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::sync::Arc;
+
+/// The first `k` rows of `input` sorted by `keys`, the same as a
+/// `SortExec` followed by a limit of `k`. Only `k` rows are kept in
+/// memory.
+#[derive(Debug)]
+pub struct TopNExec {
+    pub input: Box<PhysicalPlan>,
+    pub keys: Vec<SortExpr>,
+    pub k: usize,
+    pub ecx: ExprContext,
+}
+
+impl TopNExec {
+    pub fn stream(&self, exec_ctx: Arc<ExecutionContext>) -> Result<RowStream> {
+        let input = self.input.stream(exec_ctx)?;
+        let top_n = TopN::new(self.keys.clone(), self.k, self.ecx.clone());
+        let sorted = async move {
+            let top_n = input
+                .try_fold(top_n, |mut top_n, row| {
+                    future::ready(top_n.push(row).map(|_| top_n))
+                })
+                .await?;
+            Ok(top_n.into_rows())
+        };
+        Ok(Box::pin(
+            stream::once(sorted)
+                .map_ok(|rows| stream::iter(rows.into_iter().map(Ok)))
+                .try_flatten(),
+        ))
+    }
+}
+
+/// The first `k` of the rows pushed so far, in a max-heap whose top
+/// is the last of them.
+struct TopN {
+    keys: Arc<Vec<SortExpr>>,
+    k: usize,
+    ecx: ExprContext,
+    heap: BinaryHeap<Entry>,
+    /// The number of rows pushed, which orders the rows whose keys
+    /// are equal as a stable sort does.
+    seq: usize,
+}
+
+impl TopN {
+    fn new(keys: Vec<SortExpr>, k: usize, ecx: ExprContext) -> Self {
+        Self {
+            keys: Arc::new(keys),
+            k,
+            ecx,
+            heap: BinaryHeap::new(),
+            seq: 0,
+        }
+    }
+
+    fn push(&mut self, row: Row) -> Result<()> {
+        if self.k == 0 {
+            return Ok(());
+        }
+        let datums = self
+            .keys
+            .iter()
+            .map(|k| k.expr.evaluate(&self.ecx, &row))
+            .collect::<Result<Vec<Datum>>>()?;
+        let entry = Entry {
+            keys: self.keys.clone(),
+            datums,
+            seq: self.seq,
+            row,
+        };
+        self.seq += 1;
+        if self.heap.len() < self.k {
+            self.heap.push(entry);
+        } else if let Some(mut last) = self.heap.peek_mut() {
+            if entry < *last {
+                *last = entry;
+            }
+        }
+        Ok(())
+    }
+
+    /// The rows in the order of the keys.
+    fn into_rows(self) -> Vec<Row> {
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|e| e.row)
+            .collect()
+    }
+}
+
+struct Entry {
+    keys: Arc<Vec<SortExpr>>,
+    datums: Vec<Datum>,
+    seq: usize,
+    row: Row,
+}
+
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.keys
+            .iter()
+            .zip(self.datums.iter().zip(&other.datums))
+            .map(|(k, (d1, d2))| k.compare(d1, d2))
+            .find(|ord| ord.is_ne())
+            .unwrap_or_else(|| self.seq.cmp(&other.seq))
+    }
+}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Entry {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::memory::MemCatalog;
+    use crate::common::relation::{ColumnRef, ColumnType, RelationDesc};
+    use crate::common::scalar::ScalarType;
+    use crate::sql::context::StatementContext;
+    use crate::sql::physical_plan::limit::LimitExec;
+    use crate::sql::physical_plan::sort::SortExec;
+    use crate::sql::physical_plan::values::ValuesExec;
+    use crate::sql::primitive::expr::Literal;
+    use crate::sql::Expr;
+    use crate::test_util::seeder;
+
+    /// Rows of a nullable column with duplicate values and a column
+    /// of the position of the row.
+    fn input() -> Vec<Row> {
+        (0..20)
+            .map(|i| {
+                let c1 = if i % 6 == 0 {
+                    Datum::Null
+                } else {
+                    Datum::Int64(i * 7 % 5)
+                };
+                Row::new(vec![c1, Datum::Int64(i)])
+            })
+            .collect()
+    }
+
+    fn ecx() -> ExprContext {
+        ExprContext {
+            scx: Arc::new(StatementContext::new(Arc::new(
+                MemCatalog::default(),
+            ))),
+            rel_desc: Arc::new(RelationDesc::new(
+                vec![
+                    ColumnType::new(ScalarType::Int64, true),
+                    ColumnType::new(ScalarType::Int64, false),
+                ],
+                vec!["c1".to_string(), "c2".to_string()],
+                vec![],
+                vec![],
+            )),
+        }
+    }
+
+    fn sort_keys() -> Vec<Vec<SortExpr>> {
+        let key = |id, asc, nulls_first| SortExpr {
+            expr: Expr::Column(ColumnRef {
+                id,
+                name: format!("c{}", id + 1),
+            }),
+            asc,
+            nulls_first,
+        };
+        vec![
+            vec![key(0, true, false)],
+            vec![key(0, false, true)],
+            vec![key(0, true, true), key(1, false, false)],
+        ]
+    }
+
+    #[test]
+    fn retains_k_rows() -> Result<()> {
+        let ecx = ecx();
+        for keys in sort_keys() {
+            for k in [0, 1, 5, 20, 30] {
+                let mut top_n = TopN::new(keys.clone(), k, ecx.clone());
+                for row in input() {
+                    top_n.push(row)?;
+                    assert!(top_n.heap.len() <= k);
+                }
+
+                let mut expected = input();
+                expected.sort_by(|r1, r2| {
+                    keys.iter()
+                        .map(|key| {
+                            let d1 = key.expr.evaluate(&ecx, r1).unwrap();
+                            let d2 = key.expr.evaluate(&ecx, r2).unwrap();
+                            key.compare(&d1, &d2)
+                        })
+                        .find(|ord| ord.is_ne())
+                        .unwrap_or(Ordering::Equal)
+                });
+                expected.truncate(k);
+                assert_eq!(top_n.into_rows(), expected);
+            }
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn same_as_sort_and_limit() -> Result<()> {
+        let ecx = ecx();
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&vec![])?;
+        let exec_ctx =
+            Arc::new(ExecutionContext::new(catalog_store, table_store));
+        let values = || {
+            let rows = input()
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .map(|datum| {
+                            Expr::Literal(Literal {
+                                datum: datum.clone(),
+                                scalar_type: ScalarType::Int64,
+                            })
+                        })
+                        .collect()
+                })
+                .collect();
+            Box::new(PhysicalPlan::Values(ValuesExec {
+                rows,
+                ecx: ExprContext {
+                    scx: ecx.scx.clone(),
+                    rel_desc: Arc::new(RelationDesc::empty()),
+                },
+            }))
+        };
+        for keys in sort_keys() {
+            for k in [0, 3, 20, 30] {
+                let top_n = PhysicalPlan::TopN(TopNExec {
+                    input: values(),
+                    keys: keys.clone(),
+                    k,
+                    ecx: ecx.clone(),
+                });
+                let sort_and_limit = PhysicalPlan::Limit(LimitExec {
+                    input: Box::new(PhysicalPlan::Sort(SortExec {
+                        input: values(),
+                        keys: keys.clone(),
+                        ecx: ecx.clone(),
+                    })),
+                    limit: Some(k),
+                    offset: 0,
+                });
+                let rows = top_n
+                    .stream(exec_ctx.clone())?
+                    .try_collect::<Vec<Row>>()
+                    .await?;
+                let expected = sort_and_limit
+                    .stream(exec_ctx.clone())?
+                    .try_collect::<Vec<Row>>()
+                    .await?;
+                assert_eq!(rows, expected);
+            }
+        }
+        Ok(())
+    }
+}
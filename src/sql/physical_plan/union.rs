@@ -0,0 +1,32 @@
+use crate::common::error::Result;
+use crate::sql::context::ExecutionContext;
+use crate::sql::physical_plan::distinct::distinct;
+use crate::sql::physical_plan::RowStream;
+use crate::sql::PhysicalPlan;
+use futures::{stream, StreamExt};
+use std::sync::Arc;
+
+/// Returns the rows of `inputs` one input after another. Only
+/// the first of the duplicate rows is returned unless `all` is
+/// true.
+#[derive(Debug)]
+pub struct UnionExec {
+    pub inputs: Vec<PhysicalPlan>,
+    pub all: bool,
+}
+
+impl UnionExec {
+    pub fn stream(&self, exec_ctx: Arc<ExecutionContext>) -> Result<RowStream> {
+        let inputs = self
+            .inputs
+            .iter()
+            .map(|input| input.stream(exec_ctx.clone()))
+            .collect::<Result<Vec<_>>>()?;
+        let rows: RowStream = Box::pin(stream::iter(inputs).flatten());
+        if self.all {
+            Ok(rows)
+        } else {
+            Ok(distinct(rows))
+        }
+    }
+}
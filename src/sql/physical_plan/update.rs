@@ -0,0 +1,71 @@
+use crate::common::error::{FloppyError, Result};
+use crate::common::relation::{GlobalId, Row};
+use crate::common::scalar::Datum;
+use crate::sql::context::{ExecutionContext, ExprContext};
+use crate::sql::physical_plan::RowStream;
+use crate::sql::Expr;
+use futures::stream;
+use std::sync::Arc;
+
+/// Scans the table and writes back the rows that satisfy `filter`
+/// with the columns of `assignments` set. The updated rows are
+/// returned.
+///
+/// The new rows are all computed before the first one is written, so
+/// an error of the assignments writes nothing. A write may still fail,
+/// eg with a duplicate primary key, after the previous rows are
+/// written, which are then dropped with the transaction of the
+/// statement.
+#[derive(Debug)]
+pub struct UpdateExec {
+    pub table_id: GlobalId,
+    pub assignments: Vec<(usize, Expr)>,
+    pub filter: Option<Expr>,
+    pub ecx: ExprContext,
+}
+
+impl UpdateExec {
+    pub fn stream(&self, exec_ctx: Arc<ExecutionContext>) -> Result<RowStream> {
+        // all the rows are scanned before the first update, so
+        // an updated row is never scanned again.
+        let rows = exec_ctx
+            .table_store
+            .full_scan(&self.table_id)?
+            .collect::<Result<Vec<Row>>>()?;
+        let mut updates = vec![];
+        for row in rows {
+            if !self.matches(&row)? {
+                continue;
+            }
+            let mut values = row.iter().cloned().collect::<Vec<_>>();
+            for (i, expr) in &self.assignments {
+                values[*i] = expr.evaluate(&self.ecx, &row)?;
+            }
+            let new_row = Row::new(values);
+            self.ecx.rel_desc.check_not_null(&new_row)?;
+            updates.push((row, new_row));
+        }
+        let mut updated = vec![];
+        for (row, new_row) in updates {
+            exec_ctx
+                .table_store
+                .update(&self.table_id, &row, &new_row)?;
+            updated.push(Ok(new_row));
+        }
+        Ok(Box::pin(stream::iter(updated)))
+    }
+
+    fn matches(&self, row: &Row) -> Result<bool> {
+        let filter = match &self.filter {
+            Some(filter) => filter,
+            None => return Ok(true),
+        };
+        match filter.evaluate(&self.ecx, row)? {
+            Datum::Boolean(b) => Ok(b),
+            Datum::Null => Ok(false),
+            other => Err(FloppyError::Internal(format!(
+                "predicate evaluates to non boolean: {other:?}"
+            ))),
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use crate::common::error::Result;
+use crate::common::relation::Row;
+use crate::sql::context::{ExecutionContext, ExprContext};
+use crate::sql::physical_plan::RowStream;
+use crate::sql::Expr;
+use futures::stream;
+use std::sync::Arc;
+
+/// Evaluates the expressions of `rows`, one output row per row.
+#[derive(Debug)]
+pub struct ValuesExec {
+    pub rows: Vec<Vec<Expr>>,
+    pub ecx: ExprContext,
+}
+
+impl ValuesExec {
+    pub fn stream(
+        &self,
+        _exec_ctx: Arc<ExecutionContext>,
+    ) -> Result<RowStream> {
+        let empty = Row::empty();
+        let rows = self
+            .rows
+            .iter()
+            .map(|row| {
+                let values = row
+                    .iter()
+                    .map(|e| e.evaluate(&self.ecx, &empty))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(Row::new(values))
+            })
+            .collect::<Vec<_>>();
+        Ok(Box::pin(stream::iter(rows)))
+    }
+}
@@ -1,27 +1,124 @@
-use crate::common::error::Result;
-use crate::sql::analyzer;
+use crate::common::error::{FloppyError, Result};
+use crate::common::relation::StatementDesc;
+use crate::common::scalar::ScalarType;
 use crate::sql::context::StatementContext;
 use crate::sql::physical_plan::planner;
-use crate::sql::PhysicalPlan;
+use crate::sql::{analyzer, optimizer};
+use crate::sql::{LogicalPlan, PhysicalPlan};
+use sqlparser::ast::{CopyTarget, Statement};
 use sqlparser::dialect::PostgreSqlDialect;
 use sqlparser::parser::Parser;
+use sqlparser::tokenizer::{Token, Tokenizer};
 
 pub fn plan(scx: &StatementContext, sql: &str) -> Result<PhysicalPlan> {
+    let statement = &parse(sql)?[0];
+    let (plan, _) = plan_statement(scx, statement)?;
+    Ok(plan)
+}
+
+/// Parse `sql` into statements with PostgreSQL's dialect.
+pub fn parse(sql: &str) -> Result<Vec<Statement>> {
+    let dialect = PostgreSqlDialect {};
+    match Parser::parse_sql(&dialect, sql) {
+        Ok(statements) => Ok(statements),
+        Err(e) => {
+            // sqlparser expects `COPY ... FROM STDIN` to be followed by
+            // `;` and the inline data, which is sent in `CopyData`
+            // messages instead.
+            match Parser::parse_sql(&dialect, &format!("{sql};")) {
+                Ok(statements)
+                    if matches!(
+                        statements.as_slice(),
+                        [Statement::Copy {
+                            target: CopyTarget::Stdin,
+                            ..
+                        }]
+                    ) =>
+                {
+                    Ok(statements)
+                }
+                _ if has_returning(sql) => {
+                    Err(FloppyError::NotImplemented("RETURNING".to_string()))
+                }
+                _ => Err(e.into()),
+            }
+        }
+    }
+}
+
+/// Whether `sql` has a `RETURNING` clause, which is not parsed by
+/// sqlparser 0.18, so it is reported as not supported rather than a
+/// syntax error.
+// todo! RETURNING needs a newer sqlparser, and INSERT and DELETE to be
+// planned.
+fn has_returning(sql: &str) -> bool {
     let dialect = PostgreSqlDialect {};
-    let statement = &Parser::parse_sql(&dialect, sql)?[0];
+    let Ok(tokens) = Tokenizer::new(&dialect, sql).tokenize() else {
+        return false;
+    };
+    tokens.iter().any(|token| {
+        matches!(token, Token::Word(w)
+            if w.quote_style.is_none()
+                && w.value.eq_ignore_ascii_case("returning"))
+    })
+}
 
+/// Plan the statement and describe its output and parameters.
+pub fn plan_statement(
+    scx: &StatementContext,
+    statement: &Statement,
+) -> Result<(PhysicalPlan, StatementDesc)> {
     let logical_plan = analyzer::transform_statement(scx, statement)?;
-    planner::plan(scx, logical_plan)
+    let rel_desc = match logical_plan {
+        // the updated rows are not returned to the client.
+        LogicalPlan::Update { .. } => None,
+        _ => Some(logical_plan.rel_desc()),
+    };
+    let desc = StatementDesc {
+        rel_desc,
+        param_types: param_types(scx)?,
+    };
+    let logical_plan = optimizer::optimize(logical_plan);
+    let mut subqueries = vec![];
+    logical_plan.collect_subqueries(&mut subqueries);
+    let subqueries = subqueries
+        .into_iter()
+        .map(|s| (optimizer::optimize(*s.plan.clone()), s))
+        .collect();
+    let plan = planner::plan(scx, logical_plan)?;
+    let plan = planner::plan_subqueries(scx, plan, subqueries)?;
+    Ok((plan, desc))
+}
+
+/// Returns the types of the parameters `$1..$n` determined during
+/// planning.
+fn param_types(scx: &StatementContext) -> Result<Vec<ScalarType>> {
+    let param_types = scx.param_types.borrow();
+    let count = param_types.keys().max().copied().unwrap_or(0);
+    (1..=count)
+        .map(|n| {
+            param_types.get(&n).cloned().ok_or_else(|| {
+                FloppyError::Plan(format!(
+                    "could not determine data type of parameter ${n}"
+                ))
+            })
+        })
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::common::relation::Row;
+    use crate::catalog::memory::MemCatalog;
+    use crate::catalog::CatalogStore;
+    use crate::common::datetime;
+    use crate::common::error::CatalogError;
+    use crate::common::relation::{ColumnType, RelationDesc, Row};
     use crate::common::scalar::Datum;
     use crate::sql::context::ExecutionContext;
     use crate::test_util::seeder;
     use futures::StreamExt;
+    use rust_decimal::Decimal;
     use std::sync::Arc;
 
     #[tokio::test]
@@ -43,6 +140,423 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_returning_not_supported() {
+        for sql in [
+            "UPDATE test SET c2 = 1 WHERE c1 = 1 RETURNING c1",
+            "INSERT INTO test VALUES (1, 2) RETURNING *",
+            "DELETE FROM test RETURNING c1, c2",
+        ] {
+            let e = parse(sql).unwrap_err();
+            assert_eq!(e.code(), "0A000", "{sql}");
+        }
+        let e = parse("SELECT \"returning\" FROM").unwrap_err();
+        assert_eq!(e.code(), "42601");
+    }
+
+    #[tokio::test]
+    async fn test_integer_overflow() -> Result<()> {
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&vec![])?;
+        let scx = StatementContext::new(catalog_store.clone());
+        let exec_ctx = Arc::new(ExecutionContext::new(
+            catalog_store.clone(),
+            table_store.clone(),
+        ));
+        for sql in [
+            "SELECT 9223372036854775807 + 1",
+            "SELECT 0 - 9223372036854775807 - 2",
+            "SELECT -(0 - 9223372036854775807 - 1)",
+        ] {
+            let mut stream = plan(&scx, sql)?.stream(exec_ctx.clone())?;
+            match stream.next().await.expect("have a result") {
+                Err(e) => assert_eq!(e.code(), "22003", "{sql}"),
+                Ok(row) => panic!("expect overflow, got {row:?}"),
+            }
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unary_operator() -> Result<()> {
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&vec![])?;
+        let scx = StatementContext::new(catalog_store.clone());
+        let exec_ctx = Arc::new(ExecutionContext::new(
+            catalog_store.clone(),
+            table_store.clone(),
+        ));
+        let tests = [
+            ("SELECT NOT TRUE", Datum::Boolean(false)),
+            ("SELECT NOT NULL", Datum::Null),
+            ("SELECT NOT (2 > 1)", Datum::Boolean(false)),
+            ("SELECT -5", Datum::Int64(-5)),
+            ("SELECT -(2 - 5)", Datum::Int64(3)),
+            ("SELECT -2.5", Datum::Numeric(Decimal::new(-25, 1))),
+        ];
+        for (sql, expected) in tests {
+            let mut stream = plan(&scx, sql)?.stream(exec_ctx.clone())?;
+            let row = stream.next().await.expect("have a result")?;
+            assert_eq!(row, Row::new(vec![expected]), "{sql}");
+        }
+        assert!(plan(&scx, "SELECT -(1 > 0)").is_err());
+        assert!(plan(&scx, "SELECT -'1'").is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_concat() -> Result<()> {
+        let rows = vec![Row::new(vec![Datum::Int64(1), Datum::Int64(2)])];
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&rows)?;
+        let scx = StatementContext::new(catalog_store.clone());
+        let exec_ctx = Arc::new(ExecutionContext::new(
+            catalog_store.clone(),
+            table_store.clone(),
+        ));
+        let tests = [
+            ("SELECT 'a' || 'b'", Datum::Text("ab".to_string())),
+            (
+                "SELECT c1 || '-' || c2 FROM test",
+                Datum::Text("1-2".to_string()),
+            ),
+            ("SELECT 'a' || (1 > 0)", Datum::Text("atrue".to_string())),
+            ("SELECT 'a' || 1.50", Datum::Text("a1.50".to_string())),
+            ("SELECT 'a' || NULL", Datum::Null),
+            ("SELECT NULL || 'a' || 'b'", Datum::Null),
+        ];
+        for (sql, expected) in tests {
+            let mut stream = plan(&scx, sql)?.stream(exec_ctx.clone())?;
+            let row = stream.next().await.expect("have a result")?;
+            assert_eq!(row, Row::new(vec![expected]), "{sql}");
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_datetime_literal() -> Result<()> {
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&vec![])?;
+        let scx = StatementContext::new(catalog_store.clone());
+        let exec_ctx = Arc::new(ExecutionContext::new(
+            catalog_store.clone(),
+            table_store.clone(),
+        ));
+        let text = |s: &str| Datum::Text(s.to_string());
+        let tests = [
+            ("SELECT DATE '2024-01-01'", Datum::Date(8766)),
+            ("SELECT DATE '1999-12-31'", Datum::Date(-1)),
+            (
+                "SELECT TIMESTAMP '2000-01-01 00:00:01.5'",
+                Datum::Timestamp(1_500_000),
+            ),
+            ("SELECT TIMESTAMP '2000-01-02'", Datum::Timestamp(86_400_000_000)),
+            ("SELECT DATE '2024-01-01' < DATE '2024-01-02'", Datum::Boolean(true)),
+            ("SELECT DATE '2024-01-01' = '2024-1-1'", Datum::Boolean(true)),
+            (
+                "SELECT TIMESTAMP '2024-01-01 12:00' > '2024-01-01 11:59:59.999'",
+                Datum::Boolean(true),
+            ),
+            (
+                "SELECT '' || TIMESTAMP '2024-01-01 12:00:00.250'",
+                text("2024-01-01 12:00:00.25"),
+            ),
+        ];
+        for (sql, expected) in tests {
+            let mut stream = plan(&scx, sql)?.stream(exec_ctx.clone())?;
+            let row = stream.next().await.expect("have a result")?;
+            assert_eq!(row, Row::new(vec![expected]), "{sql}");
+        }
+        for sql in [
+            "SELECT DATE '2024-02-30'",
+            "SELECT DATE 'today'",
+            "SELECT TIMESTAMP '2024-01-01 25:00'",
+            "SELECT DATE '2024-01-01' = TIMESTAMP '2024-01-01'",
+            "SELECT DATE '2024-01-01' + 1",
+        ] {
+            assert!(plan(&scx, sql).is_err(), "{sql}");
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_bytea_literal() -> Result<()> {
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&vec![])?;
+        let scx = StatementContext::new(catalog_store.clone());
+        let exec_ctx = Arc::new(ExecutionContext::new(
+            catalog_store.clone(),
+            table_store.clone(),
+        ));
+        let tests = [
+            (
+                "SELECT BYTEA '\\xDEADBEEF'",
+                Datum::Bytea(vec![0xde, 0xad, 0xbe, 0xef]),
+            ),
+            ("SELECT BYTEA '\\x'", Datum::Bytea(vec![])),
+            ("SELECT BYTEA ''", Datum::Bytea(vec![])),
+            ("SELECT BYTEA 'ab'", Datum::Bytea(b"ab".to_vec())),
+            ("SELECT BYTEA '\\x0a' = '\\012'", Datum::Boolean(true)),
+            ("SELECT BYTEA '\\x61' = 'a'", Datum::Boolean(true)),
+            (
+                "SELECT '' || BYTEA '\\xDEADBEEF'",
+                Datum::Text("\\xdeadbeef".to_string()),
+            ),
+        ];
+        for (sql, expected) in tests {
+            let mut stream = plan(&scx, sql)?.stream(exec_ctx.clone())?;
+            let row = stream.next().await.expect("have a result")?;
+            assert_eq!(row, Row::new(vec![expected]), "{sql}");
+        }
+        assert!(plan(&scx, "SELECT BYTEA '\\xabc'").is_err());
+        assert!(plan(&scx, "SELECT BYTEA '\\xzz'").is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_uuid_literal() -> Result<()> {
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&vec![])?;
+        let scx = StatementContext::new(catalog_store.clone());
+        let exec_ctx = Arc::new(ExecutionContext::new(
+            catalog_store.clone(),
+            table_store.clone(),
+        ));
+        let mut uuid = [0; 16];
+        uuid[15] = 1;
+        let tests = [
+            (
+                "SELECT UUID '00000000-0000-0000-0000-000000000001'",
+                Datum::Uuid(uuid),
+            ),
+            (
+                "SELECT UUID '{00000000-0000-0000-0000-000000000001}' \
+                 = '00000000000000000000000000000001'",
+                Datum::Boolean(true),
+            ),
+            (
+                "SELECT UUID 'ffffffff-0000-0000-0000-000000000000' \
+                 > '0fffffff-ffff-ffff-ffff-ffffffffffff'",
+                Datum::Boolean(true),
+            ),
+        ];
+        for (sql, expected) in tests {
+            let mut stream = plan(&scx, sql)?.stream(exec_ctx.clone())?;
+            let row = stream.next().await.expect("have a result")?;
+            assert_eq!(row, Row::new(vec![expected]), "{sql}");
+        }
+        assert!(plan(&scx, "SELECT UUID '1234'").is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_interval() -> Result<()> {
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&vec![])?;
+        let scx = StatementContext::new(catalog_store.clone());
+        let exec_ctx = Arc::new(ExecutionContext::new(
+            catalog_store.clone(),
+            table_store.clone(),
+        ));
+        let timestamp = |s| datetime::parse_timestamp(s).map(Datum::Timestamp);
+        let interval = |s| datetime::parse_interval(s).map(Datum::Interval);
+        let tests = [
+            ("SELECT INTERVAL '1 day'", interval("1 day")?),
+            (
+                "SELECT INTERVAL '1 year 2 mons 03:00'",
+                interval("14 mons 3 hours")?,
+            ),
+            ("SELECT INTERVAL '2' DAY", interval("2 days")?),
+            (
+                "SELECT TIMESTAMP '2024-01-31 10:00:00' + INTERVAL '1 month'",
+                timestamp("2024-02-29 10:00:00")?,
+            ),
+            (
+                "SELECT INTERVAL '1 day' + TIMESTAMP '2024-12-31 12:00'",
+                timestamp("2025-01-01 12:00:00")?,
+            ),
+            (
+                "SELECT TIMESTAMP '2024-03-01' - '1 day 01:00'",
+                timestamp("2024-02-28 23:00:00")?,
+            ),
+            (
+                "SELECT INTERVAL '1 day' + INTERVAL '2 hours' - '1 week'",
+                interval("-6 days 2 hours")?,
+            ),
+            (
+                "SELECT INTERVAL '1 mon' = INTERVAL '30 days'",
+                Datum::Boolean(true),
+            ),
+            ("SELECT INTERVAL '25 hours' > '1 day'", Datum::Boolean(true)),
+        ];
+        for (sql, expected) in tests {
+            let mut stream = plan(&scx, sql)?.stream(exec_ctx.clone())?;
+            let row = stream.next().await.expect("have a result")?;
+            assert_eq!(row, Row::new(vec![expected]), "{sql}");
+        }
+        assert!(plan(&scx, "SELECT INTERVAL '1 fortnight'").is_err());
+        assert!(plan(&scx, "SELECT TIMESTAMP '2024-01-01' + 1").is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_array_literal() -> Result<()> {
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&vec![])?;
+        let scx = StatementContext::new(catalog_store.clone());
+        let exec_ctx = Arc::new(ExecutionContext::new(
+            catalog_store.clone(),
+            table_store.clone(),
+        ));
+        let int = Datum::Int64;
+        let numeric = |s: &str| Datum::Numeric(s.parse().unwrap());
+        let text = |s: &str| Datum::Text(s.to_string());
+        let tests = [
+            (
+                "SELECT ARRAY[1, 2, 3]",
+                Datum::Array(vec![int(1), int(2), int(3)]),
+                ScalarType::Int64,
+            ),
+            // the elements are unified to the widest type.
+            (
+                "SELECT ARRAY[1, 2.5, NULL]",
+                Datum::Array(vec![numeric("1"), numeric("2.5"), Datum::Null]),
+                ScalarType::Numeric(None),
+            ),
+            (
+                "SELECT ARRAY['1', 2]",
+                Datum::Array(vec![int(1), int(2)]),
+                ScalarType::Int64,
+            ),
+            (
+                "SELECT ARRAY['a', NULL]",
+                Datum::Array(vec![text("a"), Datum::Null]),
+                ScalarType::Text,
+            ),
+        ];
+        for (sql, expected, elem) in tests {
+            let (plan, desc) = plan_statement(&scx, &parse(sql)?[0])?;
+            let ty = desc.rel_desc.unwrap().column_type(0)?.clone();
+            assert_eq!(ty.scalar_type, ScalarType::Array(Box::new(elem)));
+            let mut stream = plan.stream(exec_ctx.clone())?;
+            let row = stream.next().await.expect("have a result")?;
+            assert_eq!(row, Row::new(vec![expected]), "{sql}");
+        }
+        assert_eq!(
+            Datum::Array(vec![int(1), int(2), int(3)]).to_string(),
+            "{1,2,3}"
+        );
+
+        let tests = [
+            ("SELECT ARRAY[1, 2] < ARRAY[1, 3]", true),
+            ("SELECT ARRAY[1, 2] = ARRAY[1, 2]", true),
+            ("SELECT ARRAY[1, 2] > ARRAY[1, 2, 0]", false),
+        ];
+        for (sql, expected) in tests {
+            let mut stream = plan(&scx, sql)?.stream(exec_ctx.clone())?;
+            let row = stream.next().await.expect("have a result")?;
+            assert_eq!(row, Row::new(vec![Datum::Boolean(expected)]), "{sql}");
+        }
+
+        for sql in [
+            "SELECT ARRAY[1, 'a']",
+            "SELECT ARRAY[1, true]",
+            "SELECT ARRAY[]",
+            "SELECT ARRAY[1] = ARRAY['a']",
+        ] {
+            assert!(plan(&scx, sql).is_err(), "{sql}");
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_string_function() -> Result<()> {
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&vec![])?;
+        let scx = StatementContext::new(catalog_store.clone());
+        let exec_ctx = Arc::new(ExecutionContext::new(
+            catalog_store.clone(),
+            table_store.clone(),
+        ));
+        let text = |s: &str| Datum::Text(s.to_string());
+        let tests = [
+            ("SELECT length('héllo')", Datum::Int64(5)),
+            ("SELECT upper('héllo')", text("HÉLLO")),
+            ("SELECT lower('HeLLo')", text("hello")),
+            ("SELECT substring('hello', 2, 3)", text("ell")),
+            ("SELECT substring('hello' FROM 3)", text("llo")),
+            ("SELECT substring('hello' FOR 2)", text("he")),
+            ("SELECT length(NULL)", Datum::Null),
+            ("SELECT upper(NULL)", Datum::Null),
+            ("SELECT substring('hello', NULL, 2)", Datum::Null),
+        ];
+        for (sql, expected) in tests {
+            let mut stream = plan(&scx, sql)?.stream(exec_ctx.clone())?;
+            let row = stream.next().await.expect("have a result")?;
+            assert_eq!(row, Row::new(vec![expected]), "{sql}");
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_numeric_function() -> Result<()> {
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&vec![])?;
+        let scx = StatementContext::new(catalog_store.clone());
+        let exec_ctx = Arc::new(ExecutionContext::new(
+            catalog_store.clone(),
+            table_store.clone(),
+        ));
+        let int = Datum::Int64;
+        let numeric =
+            |n: i64, scale: u32| Datum::Numeric(Decimal::new(n, scale));
+        let tests = [
+            ("SELECT abs(0 - 5)", int(5)),
+            ("SELECT abs(0 - 1.25)", numeric(125, 2)),
+            ("SELECT ceil(0 - 5)", int(-5)),
+            ("SELECT ceil(1.2)", numeric(2, 0)),
+            ("SELECT ceiling(0 - 1.2)", numeric(-1, 0)),
+            ("SELECT floor(7)", int(7)),
+            ("SELECT floor(1.8)", numeric(1, 0)),
+            ("SELECT floor(0 - 1.2)", numeric(-2, 0)),
+            ("SELECT round(3)", int(3)),
+            ("SELECT round(2.5)", numeric(3, 0)),
+            ("SELECT round(0 - 2.5)", numeric(-3, 0)),
+            ("SELECT round(1.2345, 2)", numeric(123, 2)),
+            ("SELECT round(0 - 1.2355, 3)", numeric(-1236, 3)),
+            ("SELECT round(1.5, 3)", numeric(1500, 3)),
+            ("SELECT round(1250.5, 0 - 2)", numeric(1300, 0)),
+            ("SELECT round(7, 1)", numeric(70, 1)),
+            ("SELECT mod(7, 3)", int(1)),
+            ("SELECT mod(0 - 7, 3)", int(-1)),
+            ("SELECT 7 % (0 - 3)", int(1)),
+            ("SELECT mod(7.5, 2)", numeric(15, 1)),
+            ("SELECT mod(0 - 7.5, 2)", numeric(-15, 1)),
+            ("SELECT abs(NULL + 1)", Datum::Null),
+            ("SELECT mod(NULL, 3)", Datum::Null),
+        ];
+        for (sql, expected) in tests {
+            let mut stream = plan(&scx, sql)?.stream(exec_ctx.clone())?;
+            let row = stream.next().await.expect("have a result")?;
+            assert_eq!(row, Row::new(vec![expected]), "{sql}");
+        }
+
+        for (sql, code) in [
+            ("SELECT mod(1, 0)", "22012"),
+            ("SELECT 1.5 % 0.0", "22012"),
+            ("SELECT abs(0 - 9223372036854775807 - 1)", "22003"),
+        ] {
+            let mut stream = plan(&scx, sql)?.stream(exec_ctx.clone())?;
+            match stream.next().await.expect("have a result") {
+                Err(e) => assert_eq!(e.code(), code, "{sql}"),
+                Ok(row) => panic!("expect error, got {row:?}"),
+            }
+        }
+        assert!(plan(&scx, "SELECT abs('a')").is_err());
+        assert!(plan(&scx, "SELECT mod(1, 2, 3)").is_err());
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_simple_scan() -> Result<()> {
         let r1 = Row::new(vec![Datum::Int64(1), Datum::Int64(2)]);
@@ -69,4 +583,881 @@ mod tests {
         assert_eq!(row, r2);
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_count_distinct() -> Result<()> {
+        let rows = vec![
+            Row::new(vec![Datum::Int64(1), Datum::Int64(10)]),
+            Row::new(vec![Datum::Int64(2), Datum::Int64(10)]),
+            Row::new(vec![Datum::Int64(3), Datum::Int64(20)]),
+            Row::new(vec![Datum::Int64(4), Datum::Int64(20)]),
+            Row::new(vec![Datum::Int64(5), Datum::Int64(30)]),
+        ];
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&rows)?;
+        let scx = StatementContext::new(catalog_store.clone());
+        let exec_ctx =
+            ExecutionContext::new(catalog_store.clone(), table_store.clone());
+        let sql = "SELECT COUNT(DISTINCT c2), COUNT(c2), SUM(DISTINCT c2), \
+                   SUM(c2) FROM test";
+        let mut stream = plan(&scx, sql)?.stream(Arc::new(exec_ctx))?;
+        let row = stream
+            .next()
+            .await
+            .expect("have a result")
+            .expect("no error");
+        assert_eq!(
+            row,
+            Row::new(vec![
+                Datum::Int64(3),
+                Datum::Int64(5),
+                Datum::Int64(60),
+                Datum::Int64(90)
+            ])
+        );
+        assert!(stream.next().await.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_filter() -> Result<()> {
+        let rows = (0..100)
+            .map(|i| Row::new(vec![Datum::Int64(i), Datum::Int64(i * 10)]))
+            .collect::<Vec<Row>>();
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&rows)?;
+        let scx = StatementContext::new(catalog_store.clone());
+        let exec_ctx =
+            ExecutionContext::new(catalog_store.clone(), table_store.clone());
+        let sql = "SELECT c1, c2 FROM test WHERE c2 > 100";
+        let rows = plan(&scx, sql)?
+            .stream(Arc::new(exec_ctx))?
+            .collect::<Vec<Result<Row>>>()
+            .await
+            .into_iter()
+            .collect::<Result<Vec<Row>>>()?;
+        assert_eq!(rows.len(), 89);
+        assert_eq!(
+            rows[0],
+            Row::new(vec![Datum::Int64(11), Datum::Int64(110)])
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_boolean_test() -> Result<()> {
+        let rel_desc = RelationDesc::new(
+            vec![
+                ColumnType::new(ScalarType::Int64, false),
+                ColumnType::new(ScalarType::Boolean, true),
+            ],
+            vec!["c1".to_string(), "c2".to_string()],
+            vec![0],
+            vec![],
+        );
+        let mut catalog = MemCatalog::default();
+        catalog.insert_table("flags", 1, rel_desc.clone());
+        let catalog_store: Arc<dyn CatalogStore> = Arc::new(catalog);
+        let rows = [Datum::Boolean(true), Datum::Boolean(false), Datum::Null]
+            .into_iter()
+            .zip(1..)
+            .map(|(flag, i)| Row::new(vec![Datum::Int64(i), flag]))
+            .collect::<Vec<Row>>();
+        let table_store = seeder::seed_table(rel_desc, &rows)?;
+        let scx = StatementContext::new(catalog_store.clone());
+        let exec_ctx =
+            Arc::new(ExecutionContext::new(catalog_store, table_store));
+
+        let tests = [
+            ("c2 IS TRUE", vec![1]),
+            ("c2 IS FALSE", vec![2]),
+            ("c2 IS NULL", vec![3]),
+            ("c2 IS NOT NULL", vec![1, 2]),
+            ("NOT c2 IS TRUE", vec![2, 3]),
+            ("(c2 AND c1 > 1) IS FALSE", vec![1, 2]),
+            ("NULL IS NULL", vec![1, 2, 3]),
+        ];
+        for (predicate, expected) in tests {
+            let sql = format!("SELECT c1 FROM flags WHERE {predicate}");
+            let rows = plan(&scx, &sql)?
+                .stream(exec_ctx.clone())?
+                .collect::<Vec<Result<Row>>>()
+                .await
+                .into_iter()
+                .collect::<Result<Vec<Row>>>()?;
+            let expected = expected
+                .into_iter()
+                .map(|i| Row::new(vec![Datum::Int64(i)]))
+                .collect::<Vec<Row>>();
+            assert_eq!(rows, expected, "{sql}");
+        }
+
+        // the tests are never NULL.
+        let sql = "SELECT c2 IS TRUE, c2 IS FALSE, c2 IS NULL FROM flags";
+        let (physical_plan, desc) = plan_statement(&scx, &parse(sql)?[0])?;
+        let types = desc.rel_desc.unwrap().column_types().clone();
+        assert!(types.iter().all(|ty| !ty.nullable), "{types:?}");
+        let rows = physical_plan
+            .stream(exec_ctx.clone())?
+            .collect::<Vec<Result<Row>>>()
+            .await
+            .into_iter()
+            .collect::<Result<Vec<Row>>>()?;
+        let b = Datum::Boolean;
+        assert_eq!(
+            rows,
+            vec![
+                Row::new(vec![b(true), b(false), b(false)]),
+                Row::new(vec![b(false), b(true), b(false)]),
+                Row::new(vec![b(false), b(false), b(true)]),
+            ]
+        );
+        assert!(plan(&scx, "SELECT c1 IS TRUE FROM flags").is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_projection() -> Result<()> {
+        let rows = (0..3)
+            .map(|i| Row::new(vec![Datum::Int64(i), Datum::Int64(i * 10)]))
+            .collect::<Vec<Row>>();
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&rows)?;
+        let scx = StatementContext::new(catalog_store.clone());
+        let exec_ctx =
+            ExecutionContext::new(catalog_store.clone(), table_store.clone());
+        let sql = "SELECT c1, c1 + c2, 7 FROM test";
+        let rows = plan(&scx, sql)?
+            .stream(Arc::new(exec_ctx))?
+            .collect::<Vec<Result<Row>>>()
+            .await
+            .into_iter()
+            .collect::<Result<Vec<Row>>>()?;
+        let expected = (0..3)
+            .map(|i| {
+                Row::new(vec![
+                    Datum::Int64(i),
+                    Datum::Int64(i * 11),
+                    Datum::Int64(7),
+                ])
+            })
+            .collect::<Vec<Row>>();
+        assert_eq!(rows, expected);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_filter_null() -> Result<()> {
+        let rows = (0..5)
+            .map(|i| Row::new(vec![Datum::Int64(i), Datum::Int64(i * 10)]))
+            .collect::<Vec<Row>>();
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&rows)?;
+        let scx = StatementContext::new(catalog_store.clone());
+        let exec_ctx = Arc::new(ExecutionContext::new(
+            catalog_store.clone(),
+            table_store.clone(),
+        ));
+        let tests = [
+            // rows whose predicate is NULL are filtered out.
+            ("SELECT c1 FROM test WHERE c2 > NULL", vec![]),
+            ("SELECT c1 FROM test WHERE NOT c2 = NULL", vec![]),
+            ("SELECT c1 FROM test WHERE c2 > NULL OR c1 < 2", vec![0, 1]),
+            ("SELECT c1 FROM test WHERE NOT (c2 > 20)", vec![0, 1, 2]),
+        ];
+        for (sql, expected) in tests {
+            let rows = plan(&scx, sql)?
+                .stream(exec_ctx.clone())?
+                .collect::<Vec<Result<Row>>>()
+                .await
+                .into_iter()
+                .collect::<Result<Vec<Row>>>()?;
+            let expected = expected
+                .into_iter()
+                .map(|i| Row::new(vec![Datum::Int64(i)]))
+                .collect::<Vec<Row>>();
+            assert_eq!(rows, expected, "{sql}");
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_projection_pushdown() -> Result<()> {
+        let rows = (0..10)
+            .map(|i| Row::new(vec![Datum::Int64(i), Datum::Int64(i * 10)]))
+            .collect::<Vec<Row>>();
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&rows)?;
+        let scx = StatementContext::new(catalog_store.clone());
+        let exec_ctx =
+            ExecutionContext::new(catalog_store.clone(), table_store.clone());
+        // only `c2` is scanned.
+        let sql = "SELECT c2 + 1 FROM test WHERE c2 >= 50";
+        let rows = plan(&scx, sql)?
+            .stream(Arc::new(exec_ctx))?
+            .collect::<Vec<Result<Row>>>()
+            .await
+            .into_iter()
+            .collect::<Result<Vec<Row>>>()?;
+        let expected = (5..10)
+            .map(|i| Row::new(vec![Datum::Int64(i * 10 + 1)]))
+            .collect::<Vec<Row>>();
+        assert_eq!(rows, expected);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_values() -> Result<()> {
+        let rows = vec![Row::new(vec![Datum::Int64(1), Datum::Int64(2)])];
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&rows)?;
+        let scx = StatementContext::new(catalog_store.clone());
+        let exec_ctx = Arc::new(ExecutionContext::new(
+            catalog_store.clone(),
+            table_store.clone(),
+        ));
+        let text = |s: &str| Datum::Text(s.to_string());
+        let tests = [
+            (
+                "VALUES (1, 2), (3, 4)",
+                vec![
+                    Row::new(vec![Datum::Int64(1), Datum::Int64(2)]),
+                    Row::new(vec![Datum::Int64(3), Datum::Int64(4)]),
+                ],
+            ),
+            (
+                "SELECT * FROM (VALUES (1, 'a'), (2.5, NULL)) t",
+                vec![
+                    Row::new(vec![Datum::Numeric(Decimal::from(1)), text("a")]),
+                    Row::new(vec![
+                        Datum::Numeric(Decimal::new(25, 1)),
+                        Datum::Null,
+                    ]),
+                ],
+            ),
+            (
+                "SELECT b || a FROM (VALUES (1, 'x'), (2, 'y')) t(a, b) \
+                 WHERE a > 1",
+                vec![Row::new(vec![text("y2")])],
+            ),
+        ];
+        for (sql, expected) in tests {
+            let rows = plan(&scx, sql)?
+                .stream(exec_ctx.clone())?
+                .collect::<Vec<Result<Row>>>()
+                .await
+                .into_iter()
+                .collect::<Result<Vec<Row>>>()?;
+            assert_eq!(rows, expected, "{sql}");
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_having() -> Result<()> {
+        let rows = [(1, 10), (1, 20), (2, 30), (3, 40), (3, 50)]
+            .into_iter()
+            .map(|(c1, c2)| Row::new(vec![Datum::Int64(c1), Datum::Int64(c2)]))
+            .collect::<Vec<Row>>();
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&rows)?;
+        let scx = StatementContext::new(catalog_store.clone());
+        let exec_ctx = Arc::new(ExecutionContext::new(
+            catalog_store.clone(),
+            table_store.clone(),
+        ));
+        let sql = "SELECT c1, SUM(c2) FROM test GROUP BY c1 \
+                   HAVING COUNT(*) > 1 AND c1 > 1";
+        let rows = plan(&scx, sql)?
+            .stream(exec_ctx)?
+            .collect::<Vec<Result<Row>>>()
+            .await
+            .into_iter()
+            .collect::<Result<Vec<Row>>>()?;
+        assert_eq!(rows, [Row::new(vec![Datum::Int64(3), Datum::Int64(90)])]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_distinct() -> Result<()> {
+        let rows = [(1, 10), (1, 20), (2, 30), (3, 30)]
+            .into_iter()
+            .map(|(c1, c2)| Row::new(vec![Datum::Int64(c1), Datum::Int64(c2)]))
+            .collect::<Vec<Row>>();
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&rows)?;
+        let scx = StatementContext::new(catalog_store.clone());
+        let exec_ctx = Arc::new(ExecutionContext::new(
+            catalog_store.clone(),
+            table_store.clone(),
+        ));
+        let row = |datums: &[Datum]| Row::new(datums.to_vec());
+        let int = Datum::Int64;
+        let interval = |s| datetime::parse_interval(s).map(Datum::Interval);
+        let tests = [
+            (
+                "SELECT DISTINCT c1 FROM test",
+                vec![row(&[int(1)]), row(&[int(2)]), row(&[int(3)])],
+            ),
+            (
+                "SELECT DISTINCT c1 > 1, c2 FROM test",
+                vec![
+                    row(&[Datum::Boolean(false), int(10)]),
+                    row(&[Datum::Boolean(false), int(20)]),
+                    row(&[Datum::Boolean(true), int(30)]),
+                ],
+            ),
+            // NULLs are not distinct from each other.
+            (
+                "SELECT DISTINCT c1 + NULL, 1 FROM test",
+                vec![row(&[Datum::Null, int(1)])],
+            ),
+            // the values equal by comparison are not distinct, the first
+            // one is kept.
+            (
+                "SELECT DISTINCT i FROM (VALUES (INTERVAL '1 mon'), \
+                 (INTERVAL '30 days'), (INTERVAL '1 day')) t(i)",
+                vec![row(&[interval("1 mon")?]), row(&[interval("1 day")?])],
+            ),
+            (
+                "SELECT i, COUNT(*) FROM (VALUES (INTERVAL '30 days'), \
+                 (INTERVAL '1 day'), (INTERVAL '1 mon')) t(i) GROUP BY i",
+                vec![
+                    row(&[interval("1 day")?, int(1)]),
+                    row(&[interval("30 days")?, int(2)]),
+                ],
+            ),
+        ];
+        for (sql, expected) in tests {
+            let rows = plan(&scx, sql)?
+                .stream(exec_ctx.clone())?
+                .collect::<Vec<Result<Row>>>()
+                .await
+                .into_iter()
+                .collect::<Result<Vec<Row>>>()?;
+            assert_eq!(rows, expected, "{sql}");
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_union() -> Result<()> {
+        let rows = [(1, 10), (1, 20), (2, 30)]
+            .into_iter()
+            .map(|(c1, c2)| Row::new(vec![Datum::Int64(c1), Datum::Int64(c2)]))
+            .collect::<Vec<Row>>();
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&rows)?;
+        let scx = StatementContext::new(catalog_store.clone());
+        let exec_ctx = Arc::new(ExecutionContext::new(
+            catalog_store.clone(),
+            table_store.clone(),
+        ));
+        let int = |i| Row::new(vec![Datum::Int64(i)]);
+        let numeric =
+            |i, scale| Row::new(vec![Datum::Numeric(Decimal::new(i, scale))]);
+        let tests = [
+            (
+                "SELECT c1 FROM test UNION ALL SELECT c2 FROM test WHERE c2 > 20",
+                vec![int(1), int(1), int(2), int(30)],
+            ),
+            (
+                "SELECT c1 FROM test UNION SELECT 2 UNION SELECT 3",
+                vec![int(1), int(2), int(3)],
+            ),
+            ("SELECT 1 UNION SELECT 2.5", vec![numeric(1, 0), numeric(25, 1)]),
+        ];
+        for (sql, expected) in tests {
+            let rows = plan(&scx, sql)?
+                .stream(exec_ctx.clone())?
+                .collect::<Vec<Result<Row>>>()
+                .await
+                .into_iter()
+                .collect::<Result<Vec<Row>>>()?;
+            assert_eq!(rows, expected, "{sql}");
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_order_by() -> Result<()> {
+        let rows = [(1, 30), (2, 10), (3, 20), (4, 10)]
+            .into_iter()
+            .map(|(c1, c2)| Row::new(vec![Datum::Int64(c1), Datum::Int64(c2)]))
+            .collect::<Vec<Row>>();
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&rows)?;
+        let scx = StatementContext::new(catalog_store.clone());
+        let exec_ctx = Arc::new(ExecutionContext::new(
+            catalog_store.clone(),
+            table_store.clone(),
+        ));
+        let row = |datums: &[Datum]| Row::new(datums.to_vec());
+        let int = Datum::Int64;
+        let tests = [
+            (
+                "SELECT c1, c2 FROM test ORDER BY c2 DESC, c1",
+                vec![
+                    row(&[int(1), int(30)]),
+                    row(&[int(3), int(20)]),
+                    row(&[int(2), int(10)]),
+                    row(&[int(4), int(10)]),
+                ],
+            ),
+            // the key is not in the output.
+            (
+                "SELECT c2 FROM test ORDER BY c1 DESC",
+                vec![
+                    row(&[int(10)]),
+                    row(&[int(20)]),
+                    row(&[int(10)]),
+                    row(&[int(30)]),
+                ],
+            ),
+            (
+                "SELECT c2, c1 FROM test ORDER BY 1, 2 DESC",
+                vec![
+                    row(&[int(10), int(4)]),
+                    row(&[int(10), int(2)]),
+                    row(&[int(20), int(3)]),
+                    row(&[int(30), int(1)]),
+                ],
+            ),
+            (
+                "SELECT c1 FROM test ORDER BY c2 % 20, c1 DESC",
+                vec![
+                    row(&[int(3)]),
+                    row(&[int(4)]),
+                    row(&[int(2)]),
+                    row(&[int(1)]),
+                ],
+            ),
+            (
+                "VALUES (1), (NULL), (2) ORDER BY 1 DESC",
+                vec![row(&[Datum::Null]), row(&[int(2)]), row(&[int(1)])],
+            ),
+            (
+                "VALUES (1), (NULL), (2) ORDER BY column1 NULLS FIRST",
+                vec![row(&[Datum::Null]), row(&[int(1)]), row(&[int(2)])],
+            ),
+            // the explicit options are the same as the defaults.
+            (
+                "VALUES (1), (NULL), (2) ORDER BY column1 DESC NULLS FIRST",
+                vec![row(&[Datum::Null]), row(&[int(2)]), row(&[int(1)])],
+            ),
+            (
+                "VALUES (1), (NULL), (2) ORDER BY column1 ASC NULLS LAST",
+                vec![row(&[int(1)]), row(&[int(2)]), row(&[Datum::Null])],
+            ),
+            (
+                "VALUES (1), (NULL), (2) ORDER BY column1 DESC NULLS LAST",
+                vec![row(&[int(2)]), row(&[int(1)]), row(&[Datum::Null])],
+            ),
+        ];
+        for (sql, expected) in tests {
+            let rows = plan(&scx, sql)?
+                .stream(exec_ctx.clone())?
+                .collect::<Vec<Result<Row>>>()
+                .await
+                .into_iter()
+                .collect::<Result<Vec<Row>>>()?;
+            assert_eq!(rows, expected, "{sql}");
+        }
+
+        for sql in [
+            "SELECT c1 FROM test ORDER BY 2",
+            "SELECT c1 FROM test ORDER BY c3",
+        ] {
+            assert!(plan(&scx, sql).is_err(), "{sql}");
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_limit() -> Result<()> {
+        let rows = [(1, 30), (2, 10), (3, 20), (4, 10)]
+            .into_iter()
+            .map(|(c1, c2)| Row::new(vec![Datum::Int64(c1), Datum::Int64(c2)]))
+            .collect::<Vec<Row>>();
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&rows)?;
+        let scx = StatementContext::new(catalog_store.clone());
+        let exec_ctx = Arc::new(ExecutionContext::new(
+            catalog_store.clone(),
+            table_store.clone(),
+        ));
+        let int = |i| Row::new(vec![Datum::Int64(i)]);
+        let tests = [
+            ("SELECT c1 FROM test LIMIT 2", vec![int(1), int(2)]),
+            ("SELECT c1 FROM test LIMIT 2 OFFSET 3", vec![int(4)]),
+            ("SELECT c1 FROM test OFFSET 1", vec![int(2), int(3), int(4)]),
+            (
+                "SELECT c1 FROM test LIMIT NULL",
+                vec![int(1), int(2), int(3), int(4)],
+            ),
+            ("SELECT c1 FROM test ORDER BY c1 LIMIT 0", vec![]),
+            (
+                "SELECT c1 FROM test ORDER BY c2, c1 DESC LIMIT 2",
+                vec![int(4), int(2)],
+            ),
+            (
+                "SELECT c1 FROM test ORDER BY c2 DESC LIMIT 2 OFFSET 1",
+                vec![int(3), int(2)],
+            ),
+        ];
+        for (sql, expected) in tests {
+            let rows = plan(&scx, sql)?
+                .stream(exec_ctx.clone())?
+                .collect::<Vec<Result<Row>>>()
+                .await
+                .into_iter()
+                .collect::<Result<Vec<Row>>>()?;
+            assert_eq!(rows, expected, "{sql}");
+        }
+
+        for sql in ["SELECT c1 FROM test LIMIT -1", "SELECT 1 OFFSET c1"] {
+            assert!(plan(&scx, sql).is_err(), "{sql}");
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_scalar_subquery() -> Result<()> {
+        let rows = [(1, 30), (2, 10), (3, 20), (4, 10)]
+            .into_iter()
+            .map(|(c1, c2)| Row::new(vec![Datum::Int64(c1), Datum::Int64(c2)]))
+            .collect::<Vec<Row>>();
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&rows)?;
+        let scx = StatementContext::new(catalog_store.clone());
+        let exec_ctx = Arc::new(ExecutionContext::new(
+            catalog_store.clone(),
+            table_store.clone(),
+        ));
+        let int = |i| Row::new(vec![Datum::Int64(i)]);
+        let tests = [
+            ("SELECT (SELECT SUM(c2) FROM test)", vec![int(70)]),
+            (
+                "SELECT c1 FROM test WHERE c2 = (SELECT c2 FROM test WHERE c1 = 3)",
+                vec![int(3)],
+            ),
+            (
+                "SELECT c1 + (SELECT (SELECT 10) + c1 FROM test WHERE c1 = 2) \
+                 FROM test WHERE c1 < 3",
+                vec![int(13), int(14)],
+            ),
+            // NULL if the subquery returns no row.
+            (
+                "SELECT (SELECT c1 FROM test WHERE c1 > 100)",
+                vec![Row::new(vec![Datum::Null])],
+            ),
+        ];
+        for (sql, expected) in tests {
+            let rows = plan(&scx, sql)?
+                .stream(exec_ctx.clone())?
+                .collect::<Vec<Result<Row>>>()
+                .await
+                .into_iter()
+                .collect::<Result<Vec<Row>>>()?;
+            assert_eq!(rows, expected, "{sql}");
+        }
+
+        let mut stream =
+            plan(&scx, "SELECT (SELECT c1 FROM test)")?.stream(exec_ctx)?;
+        match stream.next().await.expect("have a result") {
+            Err(e) => assert_eq!(e.code(), "21000"),
+            Ok(row) => panic!("expect more than one row, got {row:?}"),
+        }
+        assert!(plan(&scx, "SELECT (SELECT c1, c2 FROM test)").is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_with() -> Result<()> {
+        let rows = [(1, 30), (2, 10), (3, 20)]
+            .into_iter()
+            .map(|(c1, c2)| Row::new(vec![Datum::Int64(c1), Datum::Int64(c2)]))
+            .collect::<Vec<Row>>();
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&rows)?;
+        let scx = StatementContext::new(catalog_store.clone());
+        let exec_ctx = Arc::new(ExecutionContext::new(
+            catalog_store.clone(),
+            table_store.clone(),
+        ));
+        let int = |i| Row::new(vec![Datum::Int64(i)]);
+        let tests = [
+            (
+                "WITH t AS (SELECT c1 FROM test WHERE c2 > 15) \
+                 SELECT c1 FROM t ORDER BY c1 DESC",
+                vec![int(3), int(1)],
+            ),
+            (
+                "WITH t(a) AS (SELECT c1 FROM test WHERE c1 < 3) \
+                 SELECT a FROM t UNION ALL SELECT a + 10 FROM t",
+                vec![int(1), int(2), int(11), int(12)],
+            ),
+            // a CTE refers to the earlier ones.
+            (
+                "WITH t1 AS (SELECT c1, c2 FROM test), \
+                 t2 AS (SELECT c1 FROM t1 WHERE c2 = 10) SELECT c1 FROM t2",
+                vec![int(2)],
+            ),
+            // a CTE takes precedence over a table of the same name.
+            ("WITH test AS (SELECT 7) SELECT * FROM test", vec![int(7)]),
+        ];
+        for (sql, expected) in tests {
+            let rows = plan(&scx, sql)?
+                .stream(exec_ctx.clone())?
+                .collect::<Vec<Result<Row>>>()
+                .await
+                .into_iter()
+                .collect::<Result<Vec<Row>>>()?;
+            assert_eq!(rows, expected, "{sql}");
+        }
+
+        // the CTEs are not visible outside of the query.
+        assert!(plan(&scx, "SELECT * FROM t").is_err());
+        assert!(plan(
+            &scx,
+            "SELECT (WITH t AS (SELECT 1) SELECT * FROM t), * FROM t"
+        )
+        .is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_qualified_wildcard() -> Result<()> {
+        let rows = [(1, 30), (2, 10), (3, 20)]
+            .into_iter()
+            .map(|(c1, c2)| Row::new(vec![Datum::Int64(c1), Datum::Int64(c2)]))
+            .collect::<Vec<Row>>();
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&rows)?;
+        let scx = StatementContext::new(catalog_store.clone());
+        let exec_ctx = Arc::new(ExecutionContext::new(
+            catalog_store.clone(),
+            table_store.clone(),
+        ));
+        let int = |i| Row::new(vec![Datum::Int64(i)]);
+        let tests = [
+            (
+                "SELECT t1.* FROM test t1 \
+                 JOIN (SELECT c1 AS k FROM test WHERE c2 = 10) t2 ON c1 = k",
+                vec![Row::new(vec![Datum::Int64(2), Datum::Int64(10)])],
+            ),
+            (
+                "SELECT t2.* FROM test, (SELECT 7) t2",
+                vec![int(7), int(7), int(7)],
+            ),
+            // a table is named by itself without an alias.
+            (
+                "SELECT test.*, t.* FROM test CROSS JOIN (SELECT 7) t \
+                 WHERE c1 = 1",
+                vec![Row::new(vec![
+                    Datum::Int64(1),
+                    Datum::Int64(30),
+                    Datum::Int64(7),
+                ])],
+            ),
+        ];
+        for (sql, expected) in tests {
+            let rows = plan(&scx, sql)?
+                .stream(exec_ctx.clone())?
+                .collect::<Vec<Result<Row>>>()
+                .await
+                .into_iter()
+                .collect::<Result<Vec<Row>>>()?;
+            assert_eq!(rows, expected, "{sql}");
+        }
+
+        assert!(plan(&scx, "SELECT t3.* FROM test t1, test t2").is_err());
+        // the alias hides the name of the table.
+        assert!(plan(&scx, "SELECT test.* FROM test t").is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ambiguous_column() -> Result<()> {
+        let rows = [(1, 30), (2, 10), (3, 20)]
+            .into_iter()
+            .map(|(c1, c2)| Row::new(vec![Datum::Int64(c1), Datum::Int64(c2)]))
+            .collect::<Vec<Row>>();
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&rows)?;
+        let scx = StatementContext::new(catalog_store.clone());
+        let exec_ctx = Arc::new(ExecutionContext::new(
+            catalog_store.clone(),
+            table_store.clone(),
+        ));
+
+        for sql in [
+            "SELECT c1 FROM test t1, test t2",
+            "SELECT t1.c1 FROM test t1 JOIN test t2 ON c2 = t2.c2",
+        ] {
+            let err = plan(&scx, sql).expect_err(sql);
+            assert!(
+                matches!(
+                    err,
+                    FloppyError::Catalog(CatalogError::AmbiguousColumn(_))
+                ),
+                "{sql}: {err}"
+            );
+        }
+        let err = plan(&scx, "SELECT c1 FROM test t1, test t2").unwrap_err();
+        assert_eq!(err.code(), "42702");
+        assert_eq!(err.message(), "column reference \"c1\" is ambiguous");
+        let err = plan(&scx, "SELECT t3.c1 FROM test t1, test t2").unwrap_err();
+        assert!(matches!(
+            err,
+            FloppyError::Catalog(CatalogError::ColumnNotFound { .. })
+        ));
+        // both relations are named `test`.
+        for sql in [
+            "SELECT test.c1 FROM test, test",
+            "SELECT 1 FROM test JOIN test ON true",
+        ] {
+            let err = plan(&scx, sql).expect_err(sql);
+            assert!(
+                matches!(
+                    err,
+                    FloppyError::Catalog(CatalogError::DuplicateAlias(_))
+                ),
+                "{sql}: {err}"
+            );
+        }
+
+        // the qualified columns resolve.
+        let sql = "SELECT t1.c1, t2.c2 FROM test t1 \
+                   JOIN test t2 ON t1.c1 = t2.c1 WHERE t1.c2 > 15";
+        let rows = plan(&scx, sql)?
+            .stream(exec_ctx.clone())?
+            .collect::<Vec<Result<Row>>>()
+            .await
+            .into_iter()
+            .collect::<Result<Vec<Row>>>()?;
+        let expected = [(1, 30), (3, 20)]
+            .into_iter()
+            .map(|(c1, c2)| Row::new(vec![Datum::Int64(c1), Datum::Int64(c2)]))
+            .collect::<Vec<Row>>();
+        assert_eq!(rows, expected, "{sql}");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_update() -> Result<()> {
+        let rows = (1..=3)
+            .map(|i| Row::new(vec![Datum::Int64(i), Datum::Int64(i * 10)]))
+            .collect::<Vec<Row>>();
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&rows)?;
+        let scx = StatementContext::new(catalog_store.clone());
+        let exec_ctx = Arc::new(ExecutionContext::new(
+            catalog_store.clone(),
+            table_store.clone(),
+        ));
+        let collect = |sql: &str| {
+            let exec_ctx = exec_ctx.clone();
+            let plan = plan(&scx, sql);
+            async move {
+                plan?
+                    .stream(exec_ctx)?
+                    .collect::<Vec<Result<Row>>>()
+                    .await
+                    .into_iter()
+                    .collect::<Result<Vec<Row>>>()
+            }
+        };
+        let row = |c1, c2| Row::new(vec![Datum::Int64(c1), Datum::Int64(c2)]);
+
+        let sql = "UPDATE test SET c2 = c2 + c1 WHERE c1 >= 2";
+        assert_eq!(collect(sql).await?, [row(2, 22), row(3, 33)]);
+        let sql = "SELECT * FROM test";
+        assert_eq!(collect(sql).await?, [row(1, 10), row(2, 22), row(3, 33)]);
+
+        // no row satisfies the filter.
+        let sql = "UPDATE test SET c2 = 0 WHERE c1 > 3";
+        assert_eq!(collect(sql).await?, []);
+        let sql = "UPDATE test SET c2 = 0";
+        assert_eq!(collect(sql).await?, [row(1, 0), row(2, 0), row(3, 0)]);
+
+        let sql = "UPDATE test SET c2 = NULL WHERE c1 = 1";
+        let err = collect(sql).await.expect_err(sql);
+        assert_eq!(err.code(), "23502");
+        // the new rows are computed before any of them is written, so
+        // the first row is not updated when the second one fails.
+        let sql = "UPDATE test SET c2 = 10 / (c1 - 2)";
+        let err = collect(sql).await.expect_err(sql);
+        assert_eq!(err.code(), "22012");
+        let sql = "UPDATE test SET c1 = c1 + 1 WHERE c1 = 2";
+        let err = collect(sql).await.expect_err(sql);
+        assert_eq!(err.code(), "23505");
+        let sql = "SELECT * FROM test";
+        assert_eq!(collect(sql).await?, [row(1, 0), row(2, 0), row(3, 0)]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_explain() -> Result<()> {
+        let (catalog_store, table_store) =
+            seeder::seed_catalog_and_table(&vec![])?;
+        let scx = StatementContext::new(catalog_store.clone());
+        let exec_ctx = Arc::new(ExecutionContext::new(
+            catalog_store.clone(),
+            table_store.clone(),
+        ));
+        let explain = |sql: &str| {
+            let exec_ctx = exec_ctx.clone();
+            let plan = plan(&scx, sql);
+            async move {
+                plan?
+                    .stream(exec_ctx)?
+                    .map(|row| match row?.iter().next() {
+                        Some(Datum::Text(line)) => Ok(line.clone()),
+                        d => panic!("unexpected datum: {d:?}"),
+                    })
+                    .collect::<Vec<Result<String>>>()
+                    .await
+                    .into_iter()
+                    .collect::<Result<Vec<String>>>()
+            }
+        };
+
+        let sql = "EXPLAIN SELECT c1 FROM test WHERE c2 > 100";
+        assert_eq!(
+            explain(sql).await?,
+            [
+                "Projection: c1",
+                "  Filter: c2 > Int64(100)",
+                "    Table: test",
+            ]
+        );
+
+        let sql = "EXPLAIN VERBOSE SELECT c1 FROM test WHERE c2 > 100";
+        assert_eq!(
+            explain(sql).await?,
+            [
+                "Projection: c1 [c1:Int64]",
+                "  Filter: c2 > Int64(100) [c1:Int64, c2:Int64]",
+                "    Table: test [c1:Int64, c2:Int64]",
+            ]
+        );
+
+        // the sort and the limit are fused.
+        let sql =
+            "EXPLAIN SELECT c1 FROM test ORDER BY c2 DESC LIMIT 2 OFFSET 1";
+        assert_eq!(
+            explain(sql).await?,
+            [
+                "Projection: c1",
+                "  Limit: 2, offset: 1",
+                "    TopN: 3, keys: [c2 DESC]",
+                "      Table: test",
+            ]
+        );
+
+        let sql = "EXPLAIN ANALYZE SELECT c1 FROM test";
+        assert!(explain(sql).await.is_err());
+        Ok(())
+    }
 }
@@ -1,4 +1,6 @@
 /// Definitions for "primitive" expressions, these
 /// are executable expressions.
+pub mod agg;
 pub mod expr;
 pub mod func;
+pub mod sort;
@@ -0,0 +1,187 @@
+use crate::common::error::{FloppyError, Result};
+use crate::common::relation::{ColumnType, Row};
+use crate::common::scalar::{Datum, DatumKey, ScalarType};
+use crate::sql::context::ExprContext;
+use crate::sql::primitive::expr::{literal_true, Expr};
+use std::collections::HashSet;
+use std::fmt;
+use std::fmt::Formatter;
+
+/// An `AggregateExpr` computes a single scalar from a group
+/// of rows, eg `COUNT(DISTINCT c1)`.
+#[derive(Debug, Clone)]
+pub struct AggregateExpr {
+    pub func: AggregateFunc,
+    /// The expression evaluated on every input row.
+    /// It is ignored by [`AggregateFunc::CountStar`].
+    pub expr: Box<Expr>,
+    /// Whether duplicated input values should be eliminated
+    /// before accumulating.
+    pub distinct: bool,
+}
+
+impl AggregateExpr {
+    pub fn typ(&self, ecx: &ExprContext) -> ColumnType {
+        match self.func {
+            AggregateFunc::Count | AggregateFunc::CountStar => {
+                ScalarType::Int64.nullable(false)
+            }
+            // SUM of an empty group is NULL.
+            AggregateFunc::Sum => self.expr.typ(ecx).scalar_type.nullable(true),
+        }
+    }
+}
+
+impl fmt::Display for AggregateExpr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.func {
+            AggregateFunc::CountStar => write!(f, "{}(*)", self.func),
+            _ => {
+                let distinct = if self.distinct { "DISTINCT " } else { "" };
+                write!(f, "{}({distinct}{})", self.func, self.expr)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AggregateFunc {
+    Count,
+    /// `COUNT(*)` counts rows, including rows with NULLs.
+    CountStar,
+    Sum,
+}
+
+impl fmt::Display for AggregateFunc {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Count | Self::CountStar => write!(f, "COUNT"),
+            Self::Sum => write!(f, "SUM"),
+        }
+    }
+}
+
+/// Returns true if `name` is an aggregate function.
+pub fn is_aggregate_func(name: &str) -> bool {
+    matches!(name.to_lowercase().as_str(), "count" | "sum")
+}
+
+/// Build an [`AggregateExpr`] from the function name and its
+/// argument. `expr` is `None` for `COUNT(*)`.
+pub fn aggregate(
+    ecx: &ExprContext,
+    name: &str,
+    expr: Option<Expr>,
+    distinct: bool,
+) -> Result<AggregateExpr> {
+    let name = name.to_lowercase();
+    match (name.as_str(), expr) {
+        ("count", None) => Ok(AggregateExpr {
+            func: AggregateFunc::CountStar,
+            expr: Box::new(literal_true()),
+            distinct: false,
+        }),
+        ("count", Some(expr)) => Ok(AggregateExpr {
+            func: AggregateFunc::Count,
+            expr: Box::new(expr),
+            distinct,
+        }),
+        ("sum", Some(expr)) => {
+            let ty = expr.typ(ecx).scalar_type;
+            if !ty.is_numeric() {
+                return Err(FloppyError::Plan(format!(
+                    "function sum({ty}) does not exist"
+                )));
+            }
+            Ok(AggregateExpr {
+                func: AggregateFunc::Sum,
+                expr: Box::new(expr),
+                distinct,
+            })
+        }
+        (name, _) => Err(FloppyError::Plan(format!(
+            "aggregate function {name} with these arguments does not exist"
+        ))),
+    }
+}
+
+/// `Accumulator` holds the running state of an
+/// [`AggregateExpr`] for a single group.
+#[derive(Debug)]
+pub struct Accumulator {
+    func: AggregateFunc,
+    /// Values that have been accumulated, only tracked for
+    /// `DISTINCT` aggregates.
+    seen: Option<HashSet<DatumKey>>,
+    state: Datum,
+}
+
+impl Accumulator {
+    pub fn new(agg: &AggregateExpr) -> Self {
+        let state = match agg.func {
+            AggregateFunc::Count | AggregateFunc::CountStar => Datum::Int64(0),
+            AggregateFunc::Sum => Datum::Null,
+        };
+        let seen = if agg.distinct {
+            Some(HashSet::new())
+        } else {
+            None
+        };
+        Self {
+            func: agg.func.clone(),
+            seen,
+            state,
+        }
+    }
+
+    pub fn update(&mut self, datum: Datum) -> Result<()> {
+        if self.func == AggregateFunc::CountStar {
+            self.state = (self.state.clone() + Datum::Int64(1))?;
+            return Ok(());
+        }
+
+        // aggregates other than `COUNT(*)` ignore NULLs.
+        if datum.is_null() {
+            return Ok(());
+        }
+
+        if let Some(seen) = self.seen.as_mut() {
+            if !seen.insert(DatumKey(datum.clone())) {
+                return Ok(());
+            }
+        }
+
+        self.state = match self.func {
+            AggregateFunc::Count | AggregateFunc::CountStar => {
+                (self.state.clone() + Datum::Int64(1))?
+            }
+            AggregateFunc::Sum => match self.state {
+                Datum::Null => datum,
+                _ => (self.state.clone() + datum)?,
+            },
+        };
+        Ok(())
+    }
+
+    pub fn finish(self) -> Datum {
+        self.state
+    }
+}
+
+/// Evaluate the input of every aggregate on `row` and feed
+/// the results into the accumulators.
+pub fn accumulate(
+    ecx: &ExprContext,
+    aggregates: &[AggregateExpr],
+    accumulators: &mut [Accumulator],
+    row: &Row,
+) -> Result<()> {
+    for (agg, acc) in aggregates.iter().zip(accumulators.iter_mut()) {
+        let datum = match agg.func {
+            AggregateFunc::CountStar => Datum::Null,
+            _ => agg.expr.evaluate(ecx, row)?,
+        };
+        acc.update(datum)?;
+    }
+    Ok(())
+}
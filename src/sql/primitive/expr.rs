@@ -1,11 +1,19 @@
+use crate::common::datetime::{self, Interval};
 use crate::common::error::{FloppyError, Result};
 use crate::common::relation::{ColumnRef, ColumnType, RelationDesc, Row};
-use crate::common::scalar::{Datum, ScalarType};
+use crate::common::scalar::{
+    numeric_out_of_range, parse_bytea, parse_uuid, Datum, ScalarType,
+};
 use crate::sql::context::ExprContext;
-use crate::sql::primitive::func::{BinaryExpr, VariadicExpr};
+use crate::sql::primitive::func::{
+    BinaryExpr, UnaryExpr, UnaryFunc, VariadicExpr,
+};
+use crate::sql::LogicalPlan;
+use rust_decimal::prelude::ToPrimitive;
 use rust_decimal::Decimal;
 use std::fmt;
 use std::fmt::Formatter;
+use std::sync::{Arc, Mutex};
 
 /// A `Expr` computes a scalar.
 ///  https://www.postgresql.org/docs/current/sql-expressions.html
@@ -18,12 +26,17 @@ pub enum Expr {
     Parameter(usize),
     /// A constant value.
     Literal(Literal),
+    /// An expression with a single parameter, eg `NOT c1`.
+    CallUnary(UnaryExpr),
     /// A binary expression.
     CallBinary(BinaryExpr),
     /// An expression that have variable number of
     /// parameters. for example: 1 == 2 AND 2 == 3 OR 4
     /// > 5
     CallVariadic(VariadicExpr),
+    /// A subquery that returns a single value, eg
+    /// `(SELECT SUM(c1) FROM t)`.
+    ScalarSubquery(ScalarSubquery),
 }
 
 impl Expr {
@@ -39,22 +52,62 @@ impl Expr {
                 scalar_type: scalar_type.clone(),
                 nullable: datum.is_null(),
             },
-            Self::CallBinary(e) => e.typ(),
-            Self::CallVariadic(e) => e.typ(),
+            Self::CallUnary(e) => e.typ(ecx),
+            Self::CallBinary(e) => e.typ(ecx),
+            Self::CallVariadic(e) => e.typ(ecx),
+            // the subquery may return no row.
+            Self::ScalarSubquery(s) => {
+                s.column_type.scalar_type.clone().nullable(true)
+            }
         }
     }
 
     pub fn cast_to(&self, ecx: &ExprContext, ty: &ScalarType) -> Result<Expr> {
-        if self.typ(ecx).scalar_type == *ty {
+        if self.typ(ecx).scalar_type.base_eq(ty) {
             return Ok(self.clone());
         }
 
         match self {
+            Self::Literal(Literal {
+                datum: Datum::Int64(n),
+                scalar_type: _,
+            }) if matches!(ty, ScalarType::Numeric(_)) => {
+                Ok(literal_numeric(Decimal::from(*n)))
+            }
+            Self::Literal(Literal {
+                datum: Datum::Int64(n),
+                scalar_type: _,
+            }) if matches!(ty, ScalarType::Float64) => {
+                Ok(literal_f64(*n as f64))
+            }
+            Self::Literal(Literal {
+                datum: Datum::Numeric(d),
+                scalar_type: _,
+            }) if matches!(ty, ScalarType::Float64) => Ok(literal_f64(
+                d.to_f64().ok_or_else(numeric_out_of_range)?,
+            )),
+            // an integer or numeric column is converted to float at
+            // runtime.
+            _ if matches!(ty, ScalarType::Float64)
+                && self.typ(ecx).scalar_type.is_numeric() =>
+            {
+                Ok(Expr::CallUnary(UnaryExpr {
+                    func: UnaryFunc::CastFloat64,
+                    expr: Box::new(self.clone()),
+                }))
+            }
             Self::Literal(Literal {
                 datum: Datum::Text(s),
                 scalar_type: _,
             }) => match ty {
                 ScalarType::Int64 => Ok(literal_i64(Decimal::from_str_exact(s)?.try_into()?)),
+                ScalarType::Numeric(_) => Ok(literal_numeric(Decimal::from_str_exact(s)?)),
+                ScalarType::Float64 => parse_float64(s).map(literal_f64),
+                ScalarType::Date => datetime::parse_date(s).map(literal_date),
+                ScalarType::Timestamp => datetime::parse_timestamp(s).map(literal_timestamp),
+                ScalarType::Interval => datetime::parse_interval(s).map(literal_interval),
+                ScalarType::Bytea => parse_bytea(s).map(literal_bytea),
+                ScalarType::Uuid => parse_uuid(s).map(literal_uuid),
                 _ => Err(FloppyError::NotImplemented(format!(
                     "only support implicit cast from string to numeric, explicit cast also not \
                      supported. err from {self} to {ty}"
@@ -67,13 +120,69 @@ impl Expr {
         }
     }
 
+    /// Calls `f` on every column referenced by the expression.
+    pub fn visit_columns_mut<F>(&mut self, f: &mut F)
+    where
+        F: FnMut(&mut ColumnRef),
+    {
+        match self {
+            Self::Column(c) => f(c),
+            Self::Parameter(_) | Self::Literal(_) => {}
+            // the subquery does not refer to the columns of the outer
+            // query.
+            Self::ScalarSubquery(_) => {}
+            Self::CallUnary(UnaryExpr { expr, .. }) => {
+                expr.visit_columns_mut(f)
+            }
+            Self::CallBinary(BinaryExpr { expr1, expr2, .. }) => {
+                expr1.visit_columns_mut(f);
+                expr2.visit_columns_mut(f);
+            }
+            Self::CallVariadic(VariadicExpr { exprs, .. }) => {
+                for e in exprs {
+                    e.visit_columns_mut(f);
+                }
+            }
+        }
+    }
+
     pub fn evaluate(&self, ecx: &ExprContext, row: &Row) -> Result<Datum> {
         match self {
             Self::Column(ColumnRef { id, .. }) => row.column_value(*id),
-            Self::Parameter(n) => Ok(ecx.param_values().borrow()[n].clone()),
+            Self::Parameter(n) => {
+                ecx.param_values().borrow().get(n).cloned().ok_or_else(|| {
+                    FloppyError::Plan(format!("there is no parameter ${n}"))
+                })
+            }
             Self::Literal(Literal { datum, .. }) => Ok(datum.clone()),
+            Self::CallUnary(e) => e.evaluate(ecx, row),
             Self::CallBinary(e) => e.evaluate(ecx, row),
             Self::CallVariadic(e) => e.evaluate(ecx, row),
+            Self::ScalarSubquery(s) => s.value(),
+        }
+    }
+
+    /// Appends the subqueries of the expression to `subqueries`, the
+    /// subqueries nested in a subquery come before it.
+    pub fn collect_subqueries(&self, subqueries: &mut Vec<ScalarSubquery>) {
+        match self {
+            Self::Column(_) | Self::Parameter(_) | Self::Literal(_) => {}
+            Self::CallUnary(UnaryExpr { expr, .. }) => {
+                expr.collect_subqueries(subqueries)
+            }
+            Self::CallBinary(BinaryExpr { expr1, expr2, .. }) => {
+                expr1.collect_subqueries(subqueries);
+                expr2.collect_subqueries(subqueries);
+            }
+            Self::CallVariadic(VariadicExpr { exprs, .. }) => {
+                for e in exprs {
+                    e.collect_subqueries(subqueries);
+                }
+            }
+            Self::ScalarSubquery(s) => {
+                s.plan.collect_subqueries(subqueries);
+                subqueries.push(s.clone());
+            }
         }
     }
 }
@@ -84,10 +193,49 @@ impl fmt::Display for Expr {
             Self::Column(c) => write!(f, "{}", c.name),
             Self::Parameter(n) => write!(f, "${n}"),
             Self::Literal(l) => write!(f, "{l}"),
+            Self::CallUnary(e) => write!(f, "{e}"),
             Self::CallBinary(e) => write!(f, "{e}"),
             Self::CallVariadic(e) => write!(f, "{e}"),
+            Self::ScalarSubquery(_) => write!(f, "(subquery)"),
+        }
+    }
+}
+
+/// An uncorrelated subquery used as an expression, whose plan has a
+/// single column. The subquery is executed once before the rows of
+/// the statement are computed, see [`ScalarSubquery::set_value`].
+#[derive(Debug, Clone)]
+pub struct ScalarSubquery {
+    pub plan: Box<LogicalPlan>,
+    /// The type of the single column of `plan`.
+    pub column_type: ColumnType,
+    /// The result of the subquery, which is shared by the clones of
+    /// the expression.
+    value: Arc<Mutex<Option<Datum>>>,
+}
+
+impl ScalarSubquery {
+    pub fn new(plan: LogicalPlan, column_type: ColumnType) -> Self {
+        Self {
+            plan: Box::new(plan),
+            column_type,
+            value: Arc::new(Mutex::new(None)),
         }
     }
+
+    /// Sets the result of the subquery, NULL if it returns no row.
+    pub fn set_value(&self, datum: Datum) {
+        *self.value.lock().unwrap() = Some(datum);
+    }
+
+    fn value(&self) -> Result<Datum> {
+        self.value.lock().unwrap().clone().ok_or_else(|| {
+            FloppyError::Internal(
+                "scalar subquery is evaluated before it is executed"
+                    .to_string(),
+            )
+        })
+    }
 }
 
 pub fn wildcard_column_ref(rel_desc: &RelationDesc) -> Vec<Expr> {
@@ -144,6 +292,20 @@ pub fn literal_i64(i: i64) -> Expr {
     })
 }
 
+pub fn literal_numeric(d: Decimal) -> Expr {
+    Expr::Literal(Literal {
+        datum: Datum::Numeric(d),
+        scalar_type: ScalarType::Numeric(None),
+    })
+}
+
+pub fn literal_f64(f: f64) -> Expr {
+    Expr::Literal(Literal {
+        datum: Datum::Float64(f.into()),
+        scalar_type: ScalarType::Float64,
+    })
+}
+
 pub fn literal_text(s: &str) -> Expr {
     Expr::Literal(Literal {
         datum: Datum::Text(s.to_string()),
@@ -151,6 +313,43 @@ pub fn literal_text(s: &str) -> Expr {
     })
 }
 
+/// A date literal of the days since 2000-01-01.
+pub fn literal_date(days: i32) -> Expr {
+    Expr::Literal(Literal {
+        datum: Datum::Date(days),
+        scalar_type: ScalarType::Date,
+    })
+}
+
+/// A timestamp literal of the microseconds since 2000-01-01.
+pub fn literal_timestamp(usecs: i64) -> Expr {
+    Expr::Literal(Literal {
+        datum: Datum::Timestamp(usecs),
+        scalar_type: ScalarType::Timestamp,
+    })
+}
+
+pub fn literal_interval(interval: Interval) -> Expr {
+    Expr::Literal(Literal {
+        datum: Datum::Interval(interval),
+        scalar_type: ScalarType::Interval,
+    })
+}
+
+pub fn literal_bytea(bytes: Vec<u8>) -> Expr {
+    Expr::Literal(Literal {
+        datum: Datum::Bytea(bytes),
+        scalar_type: ScalarType::Bytea,
+    })
+}
+
+pub fn literal_uuid(uuid: [u8; 16]) -> Expr {
+    Expr::Literal(Literal {
+        datum: Datum::Uuid(uuid),
+        scalar_type: ScalarType::Uuid,
+    })
+}
+
 pub fn literal_null(ty: ScalarType) -> Expr {
     Expr::Literal(Literal {
         datum: Datum::Null,
@@ -245,9 +444,16 @@ impl CoercibleExpr {
                 cast(&Datum::Text(s.clone()), &ScalarType::Text, ty)?
             }
             Self::Parameter(n) => {
-                let prev =
-                    ecx.param_types().borrow_mut().insert(*n, ty.clone());
-                assert!(prev.is_none());
+                // a parameter is typed again if it is used more than
+                // once before its type is known, eg `$1 = $1`.
+                let mut param_types = ecx.param_types().borrow_mut();
+                let prev = param_types.entry(*n).or_insert_with(|| ty.clone());
+                if prev != ty {
+                    return Err(FloppyError::Plan(format!(
+                        "inconsistent types deduced for parameter ${n}, \
+                         {prev} versus {ty}"
+                    )));
+                }
                 Expr::Parameter(*n)
             }
         };
@@ -261,17 +467,32 @@ impl From<Expr> for CoercibleExpr {
     }
 }
 
+/// Parse a number literal. It is an integer if it fits in an `i64`
+/// and has no fractional part, otherwise a numeric. A number out of
+/// the range of numerics is a float.
 pub fn parse_sql_number(n: &str) -> Result<Expr> {
-    let d = Decimal::from_str_exact(n)?;
-    if let Ok(n) = d.try_into() {
-        Ok(literal_i64(n))
-    } else {
-        Err(FloppyError::NotImplemented(format!(
-            "sql number not supported: {n:?}"
-        )))
+    match Decimal::from_str_exact(n) {
+        Ok(d) if d.scale() == 0 => match d.try_into() {
+            Ok(n) => Ok(literal_i64(n)),
+            Err(_) => Ok(literal_numeric(d)),
+        },
+        Ok(d) => Ok(literal_numeric(d)),
+        _ => n.parse::<f64>().map(literal_f64).map_err(|_| {
+            FloppyError::Plan(format!("invalid sql number: {n:?}"))
+        }),
     }
 }
 
+/// Parse the text form of a float. Like PostgreSQL, `NaN`,
+/// `Infinity` and `-Infinity` are accepted in any case.
+fn parse_float64(s: &str) -> Result<f64> {
+    s.trim().to_lowercase().parse::<f64>().map_err(|_| {
+        FloppyError::Plan(format!(
+            "invalid input syntax for type double precision: {s:?}"
+        ))
+    })
+}
+
 fn cast(
     datum: &Datum,
     scalar_type: &ScalarType,
@@ -288,6 +509,24 @@ fn cast(
                 )))
             }
         }
+        (Datum::Text(s), ScalarType::Text, ScalarType::Float64) => {
+            parse_float64(s).map(literal_f64)
+        }
+        (Datum::Text(s), ScalarType::Text, ScalarType::Date) => {
+            datetime::parse_date(s).map(literal_date)
+        }
+        (Datum::Text(s), ScalarType::Text, ScalarType::Timestamp) => {
+            datetime::parse_timestamp(s).map(literal_timestamp)
+        }
+        (Datum::Text(s), ScalarType::Text, ScalarType::Interval) => {
+            datetime::parse_interval(s).map(literal_interval)
+        }
+        (Datum::Text(s), ScalarType::Text, ScalarType::Bytea) => {
+            parse_bytea(s).map(literal_bytea)
+        }
+        (Datum::Text(s), ScalarType::Text, ScalarType::Uuid) => {
+            parse_uuid(s).map(literal_uuid)
+        }
         (Datum::Text(s), ScalarType::Text, ScalarType::Text) => {
             Ok(literal_text(s))
         }
@@ -354,6 +593,30 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn sql_number() -> Result<()> {
+        let tests = [
+            ("42", Datum::Int64(42)),
+            ("1.25", Datum::Numeric(Decimal::new(125, 2))),
+            ("2.0", Datum::Numeric(Decimal::new(20, 1))),
+            (
+                "99999999999999999999",
+                Datum::Numeric(Decimal::from_str_exact(
+                    "99999999999999999999",
+                )?),
+            ),
+            ("1e3", Datum::Float64(1000.0.into())),
+        ];
+        for (n, datum) in tests {
+            match parse_sql_number(n)? {
+                Expr::Literal(literal) => assert_eq!(literal.datum, datum),
+                e => panic!("expect literal, got {e}"),
+            }
+        }
+        assert!(parse_sql_number("1.5.5").is_err());
+        Ok(())
+    }
+
     #[test]
     fn logical_expr() -> Result<()> {
         let catalog = Arc::new(catalog::memory::MemCatalog::default());
@@ -1,11 +1,202 @@
 use crate::common::error::FloppyError;
 use crate::common::error::Result;
+use crate::common::ordered_float::OrderedFloat;
 use crate::common::relation::{ColumnType, Row};
-use crate::common::scalar::{Datum, ScalarType};
+use crate::common::scalar::{
+    int64_out_of_range, numeric_out_of_range, Datum, ScalarType,
+};
 use crate::sql::context::ExprContext;
 use crate::sql::primitive::expr::Expr;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::{Decimal, RoundingStrategy};
 use std::fmt;
 use std::fmt::Formatter;
+
+#[derive(Debug, Clone)]
+pub struct UnaryExpr {
+    pub func: UnaryFunc,
+    pub expr: Box<Expr>,
+}
+
+impl fmt::Display for UnaryExpr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.func {
+            UnaryFunc::Not => write!(f, "{} {}", self.func, self.expr),
+            UnaryFunc::IsNull | UnaryFunc::IsTrue | UnaryFunc::IsFalse => {
+                write!(f, "{} {}", self.expr, self.func)
+            }
+            UnaryFunc::NegInt64
+            | UnaryFunc::NegNumeric
+            | UnaryFunc::NegFloat64 => write!(f, "{}{}", self.func, self.expr),
+            _ => write!(f, "{}({})", self.func, self.expr),
+        }
+    }
+}
+
+impl UnaryExpr {
+    pub fn typ(&self, ecx: &ExprContext) -> ColumnType {
+        let scalar_type = match self.func {
+            UnaryFunc::Not => ScalarType::Boolean,
+            // the tests are never NULL.
+            UnaryFunc::IsNull | UnaryFunc::IsTrue | UnaryFunc::IsFalse => {
+                return ScalarType::Boolean.nullable(false)
+            }
+            UnaryFunc::Length | UnaryFunc::NegInt64 | UnaryFunc::AbsInt64 => {
+                ScalarType::Int64
+            }
+            UnaryFunc::Upper | UnaryFunc::Lower => ScalarType::Text,
+            UnaryFunc::NegNumeric
+            | UnaryFunc::AbsNumeric
+            | UnaryFunc::CeilNumeric
+            | UnaryFunc::FloorNumeric
+            | UnaryFunc::RoundNumeric => ScalarType::Numeric(None),
+            UnaryFunc::NegFloat64
+            | UnaryFunc::AbsFloat64
+            | UnaryFunc::CeilFloat64
+            | UnaryFunc::FloorFloat64
+            | UnaryFunc::RoundFloat64
+            | UnaryFunc::CastFloat64 => ScalarType::Float64,
+        };
+        ColumnType {
+            scalar_type,
+            nullable: self.expr.typ(ecx).nullable,
+        }
+    }
+
+    pub fn evaluate(&self, ecx: &ExprContext, row: &Row) -> Result<Datum> {
+        let datum = self.expr.evaluate(ecx, row)?;
+        match self.func {
+            UnaryFunc::IsNull => return Ok(Datum::Boolean(datum.is_null())),
+            UnaryFunc::IsTrue => {
+                return Ok(Datum::Boolean(datum == Datum::Boolean(true)))
+            }
+            UnaryFunc::IsFalse => {
+                return Ok(Datum::Boolean(datum == Datum::Boolean(false)))
+            }
+            _ => {}
+        }
+        if datum.is_null() {
+            return Ok(Datum::Null);
+        }
+        let float = |f: f64| Ok(Datum::Float64(OrderedFloat(f)));
+        match (&self.func, datum) {
+            (UnaryFunc::Not, d) => d.logical_not(),
+            (UnaryFunc::Length, Datum::Text(s)) => {
+                Ok(Datum::Int64(s.chars().count() as i64))
+            }
+            (UnaryFunc::Upper, Datum::Text(s)) => {
+                Ok(Datum::Text(s.to_uppercase()))
+            }
+            (UnaryFunc::Lower, Datum::Text(s)) => {
+                Ok(Datum::Text(s.to_lowercase()))
+            }
+            (UnaryFunc::NegInt64, Datum::Int64(i)) => i
+                .checked_neg()
+                .map(Datum::Int64)
+                .ok_or_else(int64_out_of_range),
+            (UnaryFunc::NegNumeric, Datum::Numeric(d)) => {
+                Ok(Datum::Numeric(-d))
+            }
+            (UnaryFunc::NegFloat64, Datum::Float64(f)) => float(-f.0),
+            (UnaryFunc::AbsInt64, Datum::Int64(i)) => i
+                .checked_abs()
+                .map(Datum::Int64)
+                .ok_or_else(int64_out_of_range),
+            (UnaryFunc::AbsNumeric, Datum::Numeric(d)) => {
+                Ok(Datum::Numeric(d.abs()))
+            }
+            (UnaryFunc::AbsFloat64, Datum::Float64(f)) => float(f.0.abs()),
+            (UnaryFunc::CeilNumeric, Datum::Numeric(d)) => {
+                Ok(Datum::Numeric(d.ceil()))
+            }
+            (UnaryFunc::CeilFloat64, Datum::Float64(f)) => float(f.0.ceil()),
+            (UnaryFunc::FloorNumeric, Datum::Numeric(d)) => {
+                Ok(Datum::Numeric(d.floor()))
+            }
+            (UnaryFunc::FloorFloat64, Datum::Float64(f)) => float(f.0.floor()),
+            (UnaryFunc::RoundNumeric, Datum::Numeric(d)) => {
+                round_numeric(d, 0).map(Datum::Numeric)
+            }
+            (UnaryFunc::RoundFloat64, Datum::Float64(f)) => {
+                float(round_ties_even(f.0))
+            }
+            (UnaryFunc::CastFloat64, Datum::Int64(i)) => float(i as f64),
+            (UnaryFunc::CastFloat64, Datum::Float32(f)) => float(f.0 as f64),
+            (UnaryFunc::CastFloat64, Datum::Numeric(d)) => {
+                float(d.to_f64().ok_or_else(numeric_out_of_range)?)
+            }
+            (func, d) => Err(FloppyError::Internal(format!(
+                "unexpected argument of {func}: {d:?}"
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum UnaryFunc {
+    Not,
+    /// `expr IS NULL`, unlike the other functions it is not NULL on
+    /// NULL input, nor are `IsTrue` and `IsFalse`.
+    IsNull,
+    /// `expr IS TRUE`, false if `expr` is false or NULL.
+    IsTrue,
+    /// `expr IS FALSE`, false if `expr` is true or NULL.
+    IsFalse,
+    /// The number of characters in a string.
+    Length,
+    Upper,
+    Lower,
+    NegInt64,
+    NegNumeric,
+    NegFloat64,
+    AbsInt64,
+    AbsNumeric,
+    AbsFloat64,
+    CeilNumeric,
+    CeilFloat64,
+    FloorNumeric,
+    FloorFloat64,
+    RoundNumeric,
+    RoundFloat64,
+    /// Converts a number to a float.
+    CastFloat64,
+}
+
+impl fmt::Display for UnaryFunc {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Not => write!(f, "NOT"),
+            Self::IsNull => write!(f, "IS NULL"),
+            Self::IsTrue => write!(f, "IS TRUE"),
+            Self::IsFalse => write!(f, "IS FALSE"),
+            Self::Length => write!(f, "length"),
+            Self::Upper => write!(f, "upper"),
+            Self::Lower => write!(f, "lower"),
+            Self::NegInt64 | Self::NegNumeric | Self::NegFloat64 => {
+                write!(f, "-")
+            }
+            Self::AbsInt64 | Self::AbsNumeric | Self::AbsFloat64 => {
+                write!(f, "abs")
+            }
+            Self::CeilNumeric | Self::CeilFloat64 => write!(f, "ceil"),
+            Self::FloorNumeric | Self::FloorFloat64 => write!(f, "floor"),
+            Self::RoundNumeric | Self::RoundFloat64 => write!(f, "round"),
+            Self::CastFloat64 => write!(f, "float8"),
+        }
+    }
+}
+
+pub fn call_unary(func: UnaryFunc, expr: Expr) -> Expr {
+    Expr::CallUnary(UnaryExpr {
+        func,
+        expr: Box::new(expr),
+    })
+}
+
+pub fn not(expr: Expr) -> Expr {
+    call_unary(UnaryFunc::Not, expr)
+}
+
 #[derive(Debug, Clone)]
 pub struct BinaryExpr {
     pub func: BinaryFunc,
@@ -15,25 +206,53 @@ pub struct BinaryExpr {
 
 impl fmt::Display for BinaryExpr {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "{} {} {}", self.expr1, self.func, self.expr2)
+        match self.func {
+            BinaryFunc::RoundNumericScale => {
+                write!(f, "{}({}, {})", self.func, self.expr1, self.expr2)
+            }
+            _ => write!(f, "{} {} {}", self.expr1, self.func, self.expr2),
+        }
     }
 }
 
 impl BinaryExpr {
-    pub fn typ(&self) -> ColumnType {
+    pub fn typ(&self, ecx: &ExprContext) -> ColumnType {
         let scalar_type = match self.func {
             BinaryFunc::AddInt64 => ScalarType::Int64,
             BinaryFunc::SubInt64 => ScalarType::Int64,
+            BinaryFunc::AddNumeric => ScalarType::Numeric(None),
+            BinaryFunc::SubNumeric => ScalarType::Numeric(None),
+            BinaryFunc::AddFloat64 | BinaryFunc::SubFloat64 => {
+                ScalarType::Float64
+            }
+            BinaryFunc::AddTimestampInterval
+            | BinaryFunc::SubTimestampInterval => ScalarType::Timestamp,
+            BinaryFunc::AddInterval | BinaryFunc::SubInterval => {
+                ScalarType::Interval
+            }
+            BinaryFunc::MulInt64 | BinaryFunc::DivInt64 => ScalarType::Int64,
+            BinaryFunc::MulNumeric | BinaryFunc::DivNumeric => {
+                ScalarType::Numeric(None)
+            }
+            BinaryFunc::MulFloat64 | BinaryFunc::DivFloat64 => {
+                ScalarType::Float64
+            }
             BinaryFunc::Eq => ScalarType::Boolean,
             BinaryFunc::NotEq => ScalarType::Boolean,
             BinaryFunc::Lt => ScalarType::Boolean,
             BinaryFunc::Lte => ScalarType::Boolean,
             BinaryFunc::Gt => ScalarType::Boolean,
             BinaryFunc::Gte => ScalarType::Boolean,
+            BinaryFunc::Concat => ScalarType::Text,
+            BinaryFunc::ModInt64 => ScalarType::Int64,
+            BinaryFunc::ModNumeric => ScalarType::Numeric(None),
+            BinaryFunc::RoundNumericScale => ScalarType::Numeric(None),
         };
+        // all the binary functions return NULL on NULL input.
         ColumnType {
             scalar_type,
-            nullable: false,
+            nullable: self.expr1.typ(ecx).nullable
+                || self.expr2.typ(ecx).nullable,
         }
     }
 
@@ -41,22 +260,81 @@ impl BinaryExpr {
         let datum1 = self.expr1.evaluate(ecx, row)?;
         let datum2 = self.expr2.evaluate(ecx, row)?;
 
-        if self.expr1.typ(ecx) != self.expr2.typ(ecx) {
+        let ty1 = self.expr1.typ(ecx).scalar_type;
+        let same_type = ty1.base_eq(&self.expr2.typ(ecx).scalar_type);
+        let mixed_types = matches!(
+            self.func,
+            BinaryFunc::Concat
+                | BinaryFunc::RoundNumericScale
+                | BinaryFunc::AddTimestampInterval
+                | BinaryFunc::SubTimestampInterval
+        );
+        if !same_type && !mixed_types {
             return Err(FloppyError::Internal(
                 "expression should have the same type for binary function"
                     .to_string(),
             ));
         }
+        if datum1.is_null() || datum2.is_null() {
+            return Ok(Datum::Null);
+        }
 
         match self.func {
             BinaryFunc::AddInt64 => datum1 + datum2,
             BinaryFunc::SubInt64 => datum1 - datum2,
-            BinaryFunc::Eq => Ok(Datum::Boolean(datum1 == datum2)),
-            BinaryFunc::NotEq => Ok(Datum::Boolean(datum1 != datum2)),
-            BinaryFunc::Lt => Ok(Datum::Boolean(datum1 < datum2)),
-            BinaryFunc::Lte => Ok(Datum::Boolean(datum1 <= datum2)),
-            BinaryFunc::Gt => Ok(Datum::Boolean(datum1 > datum2)),
-            BinaryFunc::Gte => Ok(Datum::Boolean(datum1 >= datum2)),
+            BinaryFunc::AddNumeric => datum1 + datum2,
+            BinaryFunc::SubNumeric => datum1 - datum2,
+            BinaryFunc::AddFloat64 => datum1 + datum2,
+            BinaryFunc::SubFloat64 => datum1 - datum2,
+            BinaryFunc::AddTimestampInterval | BinaryFunc::AddInterval => {
+                datum1 + datum2
+            }
+            BinaryFunc::SubTimestampInterval | BinaryFunc::SubInterval => {
+                datum1 - datum2
+            }
+            BinaryFunc::MulInt64
+            | BinaryFunc::MulNumeric
+            | BinaryFunc::MulFloat64 => datum1 * datum2,
+            BinaryFunc::DivInt64
+            | BinaryFunc::DivNumeric
+            | BinaryFunc::DivFloat64 => datum1 / datum2,
+            BinaryFunc::Eq
+            | BinaryFunc::NotEq
+            | BinaryFunc::Lt
+            | BinaryFunc::Lte
+            | BinaryFunc::Gt
+            | BinaryFunc::Gte => {
+                let ordering = datum1.compare(&datum2).ok_or_else(|| {
+                    FloppyError::Internal(format!(
+                        "cannot compare {datum1:?} with {datum2:?}"
+                    ))
+                })?;
+                let b = match self.func {
+                    BinaryFunc::Eq => ordering.is_eq(),
+                    BinaryFunc::NotEq => ordering.is_ne(),
+                    BinaryFunc::Lt => ordering.is_lt(),
+                    BinaryFunc::Lte => ordering.is_le(),
+                    BinaryFunc::Gt => ordering.is_gt(),
+                    _ => ordering.is_ge(),
+                };
+                Ok(Datum::Boolean(b))
+            }
+            BinaryFunc::ModInt64 | BinaryFunc::ModNumeric => {
+                modulo_datums(datum1, datum2)
+            }
+            BinaryFunc::RoundNumericScale => match (datum1, datum2) {
+                (Datum::Numeric(d), Datum::Int64(scale)) => {
+                    round_numeric(d, scale).map(Datum::Numeric)
+                }
+                (d1, d2) => Err(FloppyError::Internal(format!(
+                    "unexpected arguments of round: {d1:?}, {d2:?}"
+                ))),
+            },
+            BinaryFunc::Concat => Ok(Datum::Text(format!(
+                "{}{}",
+                datum1.cast_to_text(),
+                datum2.cast_to_text()
+            ))),
         }
     }
 }
@@ -65,25 +343,68 @@ impl BinaryExpr {
 pub enum BinaryFunc {
     AddInt64,
     SubInt64,
+    AddNumeric,
+    SubNumeric,
+    AddFloat64,
+    SubFloat64,
+    /// Adds an interval to a timestamp, the months are added first
+    /// and the day is clamped to the end of the month.
+    AddTimestampInterval,
+    SubTimestampInterval,
+    AddInterval,
+    SubInterval,
+    MulInt64,
+    MulNumeric,
+    MulFloat64,
+    /// Integer division truncates towards zero.
+    DivInt64,
+    DivNumeric,
+    DivFloat64,
     Eq,
     NotEq,
     Lt,
     Lte,
     Gt,
     Gte,
+    /// Concatenates the text representations of the operands.
+    Concat,
+    /// The remainder of the division, which has the sign of the
+    /// dividend.
+    ModInt64,
+    ModNumeric,
+    /// Rounds a numeric to the number of decimal places, which
+    /// can be negative.
+    RoundNumericScale,
 }
 
 impl fmt::Display for BinaryFunc {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
-            Self::AddInt64 => write!(f, "+"),
-            Self::SubInt64 => write!(f, "-"),
+            Self::AddInt64
+            | Self::AddNumeric
+            | Self::AddFloat64
+            | Self::AddTimestampInterval
+            | Self::AddInterval => write!(f, "+"),
+            Self::SubInt64
+            | Self::SubNumeric
+            | Self::SubFloat64
+            | Self::SubTimestampInterval
+            | Self::SubInterval => write!(f, "-"),
+            Self::MulInt64 | Self::MulNumeric | Self::MulFloat64 => {
+                write!(f, "*")
+            }
+            Self::DivInt64 | Self::DivNumeric | Self::DivFloat64 => {
+                write!(f, "/")
+            }
             Self::Eq => write!(f, "="),
             Self::NotEq => write!(f, "!="),
             Self::Lt => write!(f, "<"),
             Self::Lte => write!(f, "<="),
             Self::Gt => write!(f, ">"),
             Self::Gte => write!(f, ">="),
+            Self::Concat => write!(f, "||"),
+            Self::ModInt64 | Self::ModNumeric => write!(f, "%"),
+            Self::RoundNumericScale => write!(f, "round"),
         }
     }
 }
@@ -92,7 +413,21 @@ pub fn add(ecx: &ExprContext, expr1: &Expr, expr2: &Expr) -> Result<Expr> {
     let ty1 = expr1.typ(ecx).scalar_type;
     let ty2 = expr2.typ(ecx).scalar_type;
 
-    if ty1 != ty2 {
+    match (&ty1, &ty2) {
+        (ScalarType::Timestamp, ScalarType::Interval) => {
+            return Ok(Expr::CallBinary(BinaryExpr {
+                func: BinaryFunc::AddTimestampInterval,
+                expr1: Box::new(expr1.clone()),
+                expr2: Box::new(expr2.clone()),
+            }))
+        }
+        (ScalarType::Interval, ScalarType::Timestamp) => {
+            return add(ecx, expr2, expr1)
+        }
+        _ => {}
+    }
+
+    if !ty1.base_eq(&ty2) {
         return Err(FloppyError::Internal(format!(
             "add two different type, expr1: {ty1}, expr2: {ty2}"
         )));
@@ -100,9 +435,12 @@ pub fn add(ecx: &ExprContext, expr1: &Expr, expr2: &Expr) -> Result<Expr> {
 
     let f = match ty1 {
         ScalarType::Int64 => BinaryFunc::AddInt64,
+        ScalarType::Numeric(_) => BinaryFunc::AddNumeric,
+        ScalarType::Float64 => BinaryFunc::AddFloat64,
+        ScalarType::Interval => BinaryFunc::AddInterval,
         _ => {
             return Err(FloppyError::Internal(format!(
-                "add only supports numeric types: {ty1}"
+                "add only supports numeric and interval types: {ty1}"
             )))
         }
     };
@@ -114,35 +452,288 @@ pub fn add(ecx: &ExprContext, expr1: &Expr, expr2: &Expr) -> Result<Expr> {
     }))
 }
 
-pub fn equal(ecx: &ExprContext, expr1: &Expr, expr2: &Expr) -> Result<Expr> {
+pub fn sub(ecx: &ExprContext, expr1: &Expr, expr2: &Expr) -> Result<Expr> {
     let ty1 = expr1.typ(ecx).scalar_type;
     let ty2 = expr2.typ(ecx).scalar_type;
 
-    if ty1 != ty2 {
+    if matches!((&ty1, &ty2), (ScalarType::Timestamp, ScalarType::Interval)) {
+        return Ok(Expr::CallBinary(BinaryExpr {
+            func: BinaryFunc::SubTimestampInterval,
+            expr1: Box::new(expr1.clone()),
+            expr2: Box::new(expr2.clone()),
+        }));
+    }
+
+    if !ty1.base_eq(&ty2) {
         return Err(FloppyError::Internal(format!(
-            "compare two different type, expr1: {ty1}, expr2: {ty2}"
+            "subtract two different type, expr1: {ty1}, expr2: {ty2}"
+        )));
+    }
+
+    let f = match ty1 {
+        ScalarType::Int64 => BinaryFunc::SubInt64,
+        ScalarType::Numeric(_) => BinaryFunc::SubNumeric,
+        ScalarType::Float64 => BinaryFunc::SubFloat64,
+        ScalarType::Interval => BinaryFunc::SubInterval,
+        _ => {
+            return Err(FloppyError::Internal(format!(
+                "subtract only supports numeric and interval types: {ty1}"
+            )))
+        }
+    };
+
+    Ok(Expr::CallBinary(BinaryExpr {
+        func: f,
+        expr1: Box::new(expr1.clone()),
+        expr2: Box::new(expr2.clone()),
+    }))
+}
+
+pub fn mul(ecx: &ExprContext, expr1: &Expr, expr2: &Expr) -> Result<Expr> {
+    let ty1 = expr1.typ(ecx).scalar_type;
+    let ty2 = expr2.typ(ecx).scalar_type;
+
+    if !ty1.base_eq(&ty2) {
+        return Err(FloppyError::Internal(format!(
+            "multiply two different type, expr1: {ty1}, expr2: {ty2}"
+        )));
+    }
+
+    let f = match ty1 {
+        ScalarType::Int64 => BinaryFunc::MulInt64,
+        ScalarType::Numeric(_) => BinaryFunc::MulNumeric,
+        ScalarType::Float64 => BinaryFunc::MulFloat64,
+        _ => {
+            return Err(FloppyError::Internal(format!(
+                "multiply only supports numeric types: {ty1}"
+            )))
+        }
+    };
+
+    Ok(Expr::CallBinary(BinaryExpr {
+        func: f,
+        expr1: Box::new(expr1.clone()),
+        expr2: Box::new(expr2.clone()),
+    }))
+}
+
+pub fn div(ecx: &ExprContext, expr1: &Expr, expr2: &Expr) -> Result<Expr> {
+    let ty1 = expr1.typ(ecx).scalar_type;
+    let ty2 = expr2.typ(ecx).scalar_type;
+
+    if !ty1.base_eq(&ty2) {
+        return Err(FloppyError::Internal(format!(
+            "divide two different type, expr1: {ty1}, expr2: {ty2}"
+        )));
+    }
+
+    let f = match ty1 {
+        ScalarType::Int64 => BinaryFunc::DivInt64,
+        ScalarType::Numeric(_) => BinaryFunc::DivNumeric,
+        ScalarType::Float64 => BinaryFunc::DivFloat64,
+        _ => {
+            return Err(FloppyError::Internal(format!(
+                "divide only supports numeric types: {ty1}"
+            )))
+        }
+    };
+
+    Ok(Expr::CallBinary(BinaryExpr {
+        func: f,
+        expr1: Box::new(expr1.clone()),
+        expr2: Box::new(expr2.clone()),
+    }))
+}
+
+/// The remainder of `expr1 / expr2`, both of which are integers or
+/// numerics.
+pub fn modulo(ecx: &ExprContext, expr1: &Expr, expr2: &Expr) -> Result<Expr> {
+    let ty1 = expr1.typ(ecx).scalar_type;
+    let ty2 = expr2.typ(ecx).scalar_type;
+
+    let func = match (&ty1, &ty2) {
+        (ScalarType::Int64, ScalarType::Int64) => BinaryFunc::ModInt64,
+        (ScalarType::Numeric(_), ScalarType::Numeric(_)) => {
+            BinaryFunc::ModNumeric
+        }
+        _ => {
+            return Err(FloppyError::Plan(format!(
+                "function mod({ty1}, {ty2}) does not exist"
+            )))
+        }
+    };
+
+    Ok(Expr::CallBinary(BinaryExpr {
+        func,
+        expr1: Box::new(expr1.clone()),
+        expr2: Box::new(expr2.clone()),
+    }))
+}
+
+/// `-expr`, where `expr` is a number.
+pub fn negate(ecx: &ExprContext, expr: &Expr) -> Result<Expr> {
+    let ty = expr.typ(ecx).scalar_type;
+    let func = match ty {
+        ScalarType::Int64 => UnaryFunc::NegInt64,
+        ScalarType::Numeric(_) => UnaryFunc::NegNumeric,
+        ScalarType::Float64 => UnaryFunc::NegFloat64,
+        _ => {
+            return Err(FloppyError::Plan(format!(
+                "operator does not exist: - {ty}"
+            )))
+        }
+    };
+    Ok(call_unary(func, expr.clone()))
+}
+
+/// Plan `name(expr)` of the numeric functions: `abs`, `ceil`,
+/// `floor` and `round`. `ceil`, `floor` and `round` of an integer
+/// is the integer itself.
+pub fn numeric_unary(
+    ecx: &ExprContext,
+    name: &str,
+    expr: &Expr,
+) -> Result<Expr> {
+    let ty = expr.typ(ecx).scalar_type;
+    let func = match (name, &ty) {
+        ("abs", ScalarType::Int64) => UnaryFunc::AbsInt64,
+        ("abs", ScalarType::Numeric(_)) => UnaryFunc::AbsNumeric,
+        ("abs", ScalarType::Float64) => UnaryFunc::AbsFloat64,
+        ("ceil" | "floor" | "round", ScalarType::Int64) => {
+            return Ok(expr.clone())
+        }
+        ("ceil", ScalarType::Numeric(_)) => UnaryFunc::CeilNumeric,
+        ("ceil", ScalarType::Float64) => UnaryFunc::CeilFloat64,
+        ("floor", ScalarType::Numeric(_)) => UnaryFunc::FloorNumeric,
+        ("floor", ScalarType::Float64) => UnaryFunc::FloorFloat64,
+        ("round", ScalarType::Numeric(_)) => UnaryFunc::RoundNumeric,
+        ("round", ScalarType::Float64) => UnaryFunc::RoundFloat64,
+        _ => {
+            return Err(FloppyError::Plan(format!(
+                "function {name}({ty}) does not exist"
+            )))
+        }
+    };
+    Ok(call_unary(func, expr.clone()))
+}
+
+/// `round(expr, scale)`, where `expr` is a numeric and `scale` is
+/// an integer.
+pub fn round_scale(
+    ecx: &ExprContext,
+    expr: &Expr,
+    scale: &Expr,
+) -> Result<Expr> {
+    let ty = expr.typ(ecx).scalar_type;
+    let scale_ty = scale.typ(ecx).scalar_type;
+    if !matches!(ty, ScalarType::Numeric(_)) || scale_ty != ScalarType::Int64 {
+        return Err(FloppyError::Plan(format!(
+            "function round({ty}, {scale_ty}) does not exist"
+        )));
+    }
+    Ok(Expr::CallBinary(BinaryExpr {
+        func: BinaryFunc::RoundNumericScale,
+        expr1: Box::new(expr.clone()),
+        expr2: Box::new(scale.clone()),
+    }))
+}
+
+fn modulo_datums(datum1: Datum, datum2: Datum) -> Result<Datum> {
+    match (datum1, datum2) {
+        (Datum::Int64(_), Datum::Int64(0)) => Err(FloppyError::DivisionByZero),
+        // `i64::MIN % -1` overflows in Rust, but is 0.
+        (Datum::Int64(i1), Datum::Int64(i2)) => {
+            Ok(Datum::Int64(i1.wrapping_rem(i2)))
+        }
+        (Datum::Numeric(d1), Datum::Numeric(d2)) => {
+            if d2.is_zero() {
+                return Err(FloppyError::DivisionByZero);
+            }
+            d1.checked_rem(d2)
+                .map(Datum::Numeric)
+                .ok_or_else(numeric_out_of_range)
+        }
+        (d1, d2) => Err(FloppyError::Internal(format!(
+            "unexpected arguments of mod: {d1:?}, {d2:?}"
+        ))),
+    }
+}
+
+/// Rounds `d` to `scale` decimal places, half away from zero. A
+/// negative `scale` rounds to the left of the decimal point.
+fn round_numeric(d: Decimal, scale: i64) -> Result<Decimal> {
+    if scale >= 0 {
+        let mut d = d;
+        d.rescale(scale.min(28) as u32);
+        return Ok(d);
+    }
+    let k = scale.unsigned_abs();
+    if k > 28 {
+        return Ok(Decimal::ZERO);
+    }
+    let factor = Decimal::from_i128_with_scale(10i128.pow(k as u32), 0);
+    (d / factor)
+        .round_dp_with_strategy(0, RoundingStrategy::MidpointAwayFromZero)
+        .checked_mul(factor)
+        .ok_or_else(numeric_out_of_range)
+}
+
+/// Rounds `f` to the nearest integer like PostgreSQL, a half way
+/// value is rounded to the even one.
+fn round_ties_even(f: f64) -> f64 {
+    if f.fract().abs() == 0.5 {
+        2.0 * (f / 2.0).round()
+    } else {
+        f.round()
+    }
+}
+
+/// Concatenate two expressions, at least one of which is text. The
+/// other one is converted to text.
+pub fn concat(ecx: &ExprContext, expr1: &Expr, expr2: &Expr) -> Result<Expr> {
+    let ty1 = expr1.typ(ecx).scalar_type;
+    let ty2 = expr2.typ(ecx).scalar_type;
+
+    if ty1 != ScalarType::Text && ty2 != ScalarType::Text {
+        return Err(FloppyError::Plan(format!(
+            "operator does not exist: {ty1} || {ty2}"
         )));
     }
 
     Ok(Expr::CallBinary(BinaryExpr {
-        func: BinaryFunc::Eq,
+        func: BinaryFunc::Concat,
         expr1: Box::new(expr1.clone()),
         expr2: Box::new(expr2.clone()),
     }))
 }
 
+pub fn equal(ecx: &ExprContext, expr1: &Expr, expr2: &Expr) -> Result<Expr> {
+    compare(ecx, BinaryFunc::Eq, expr1, expr2)
+}
+
 pub fn gt(ecx: &ExprContext, expr1: &Expr, expr2: &Expr) -> Result<Expr> {
+    compare(ecx, BinaryFunc::Gt, expr1, expr2)
+}
+
+/// Compare two expressions of the same type with `func`, which is one
+/// of the comparison functions.
+pub fn compare(
+    ecx: &ExprContext,
+    func: BinaryFunc,
+    expr1: &Expr,
+    expr2: &Expr,
+) -> Result<Expr> {
     let ty1 = expr1.typ(ecx).scalar_type;
     let ty2 = expr2.typ(ecx).scalar_type;
 
-    if ty1 != ty2 {
+    if !ty1.base_eq(&ty2) {
         return Err(FloppyError::Internal(format!(
             "compare two different type, expr1: {ty1}, expr2: {ty2}"
         )));
     }
 
     Ok(Expr::CallBinary(BinaryExpr {
-        func: BinaryFunc::Gt,
+        func,
         expr1: Box::new(expr1.clone()),
         expr2: Box::new(expr2.clone()),
     }))
@@ -150,31 +741,45 @@ pub fn gt(ecx: &ExprContext, expr1: &Expr, expr2: &Expr) -> Result<Expr> {
 
 #[derive(Debug, Clone)]
 pub struct VariadicExpr {
-    func: VariadicFunc,
-    exprs: Vec<Expr>,
+    pub func: VariadicFunc,
+    pub exprs: Vec<Expr>,
 }
 
 impl fmt::Display for VariadicExpr {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let exprs = self
+            .exprs
+            .iter()
+            .map(|e| format!("{e}"))
+            .collect::<Vec<String>>();
         match self.func {
             VariadicFunc::And | VariadicFunc::Or => {
-                let exprs = self
-                    .exprs
-                    .iter()
-                    .map(|e| format!("{e}"))
-                    .collect::<Vec<String>>();
-                write!(f, "{}", exprs.join(format!("{}", self.func).as_str()))
+                write!(f, "{}", exprs.join(&format!(" {} ", self.func)))
+            }
+            VariadicFunc::Substring => {
+                write!(f, "{}({})", self.func, exprs.join(", "))
+            }
+            VariadicFunc::ArrayCreate(_) => {
+                write!(f, "{}[{}]", self.func, exprs.join(", "))
             }
         }
     }
 }
 
 impl VariadicExpr {
-    pub fn typ(&self) -> ColumnType {
-        // we only support `AND`, `OR` function right now.
+    pub fn typ(&self, ecx: &ExprContext) -> ColumnType {
+        let scalar_type = match self.func {
+            VariadicFunc::And | VariadicFunc::Or => ScalarType::Boolean,
+            VariadicFunc::Substring => ScalarType::Text,
+            // an array is not NULL even if its elements are.
+            VariadicFunc::ArrayCreate(elem) => {
+                return ScalarType::Array(Box::new(elem.clone()))
+                    .nullable(false)
+            }
+        };
         ColumnType {
-            scalar_type: ScalarType::Boolean,
-            nullable: false,
+            scalar_type,
+            nullable: self.exprs.iter().any(|e| e.typ(ecx).nullable),
         }
     }
 
@@ -185,14 +790,15 @@ impl VariadicExpr {
             .map(|e| e.evaluate(ecx, row))
             .collect::<Result<Vec<Datum>>>()?;
 
+        if let VariadicFunc::ArrayCreate(_) = self.func {
+            return Ok(Datum::Array(datums));
+        }
         if datums.len() < 2 {
             return Err(FloppyError::EvalExpr(
                 "at least two expression is required".to_string(),
             ));
         }
 
-        // since we only support "AND", "OR", let's simplify the
-        // logic here.
         match self.func {
             VariadicFunc::And => {
                 datums.iter().try_fold(Datum::Boolean(true), |acc, item| {
@@ -204,6 +810,7 @@ impl VariadicExpr {
                     acc.logical_or(item)
                 })
             }
+            VariadicFunc::Substring => substring_datums(&datums),
         }
     }
 }
@@ -212,6 +819,10 @@ impl VariadicExpr {
 pub enum VariadicFunc {
     And,
     Or,
+    /// `substring(string, start [, count])`
+    Substring,
+    /// `ARRAY[e1, e2, ...]` of the element type.
+    ArrayCreate(ScalarType),
 }
 
 impl fmt::Display for VariadicFunc {
@@ -219,10 +830,23 @@ impl fmt::Display for VariadicFunc {
         match self {
             Self::And => write!(f, "AND"),
             Self::Or => write!(f, "OR"),
+            Self::Substring => write!(f, "substring"),
+            Self::ArrayCreate(_) => write!(f, "ARRAY"),
         }
     }
 }
 
+/// Split `expr` into the expressions combined by `AND`.
+pub fn conjuncts(expr: Expr) -> Vec<Expr> {
+    match expr {
+        Expr::CallVariadic(VariadicExpr {
+            func: VariadicFunc::And,
+            exprs,
+        }) => exprs.into_iter().flat_map(conjuncts).collect(),
+        expr => vec![expr],
+    }
+}
+
 pub fn and(exprs: Vec<Expr>) -> Expr {
     Expr::CallVariadic(VariadicExpr {
         func: VariadicFunc::And,
@@ -236,3 +860,271 @@ pub fn or(exprs: Vec<Expr>) -> Expr {
         exprs,
     })
 }
+
+/// `exprs` are the string, the 1-based start position and the
+/// optional number of characters.
+pub fn substring(exprs: Vec<Expr>) -> Expr {
+    Expr::CallVariadic(VariadicExpr {
+        func: VariadicFunc::Substring,
+        exprs,
+    })
+}
+
+/// An array of `exprs`, which are of the type `elem`.
+pub fn array_create(elem: ScalarType, exprs: Vec<Expr>) -> Expr {
+    Expr::CallVariadic(VariadicExpr {
+        func: VariadicFunc::ArrayCreate(elem),
+        exprs,
+    })
+}
+
+/// The characters of a string in `[start, start + count)`, the
+/// positions before the string are ignored as in PostgreSQL.
+fn substring_datums(datums: &[Datum]) -> Result<Datum> {
+    if datums.iter().any(|d| d.is_null()) {
+        return Ok(Datum::Null);
+    }
+    let (s, start, count) = match datums {
+        [Datum::Text(s), Datum::Int64(start)] => (s, *start, None),
+        [Datum::Text(s), Datum::Int64(start), Datum::Int64(count)] => {
+            (s, *start, Some(*count))
+        }
+        _ => {
+            return Err(FloppyError::Internal(format!(
+                "invalid arguments of substring: {datums:?}"
+            )))
+        }
+    };
+    let end = match count {
+        Some(count) if count < 0 => {
+            return Err(FloppyError::EvalExpr(
+                "negative substring length not allowed".to_string(),
+            ))
+        }
+        Some(count) => start.saturating_add(count),
+        None => i64::MAX,
+    };
+    let start = start.max(1);
+    if end <= start {
+        return Ok(Datum::Text(String::new()));
+    }
+    Ok(Datum::Text(
+        s.chars()
+            .skip(start as usize - 1)
+            .take((end - start) as usize)
+            .collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::memory::MemCatalog;
+    use crate::common::relation::RelationDesc;
+    use crate::sql::context::StatementContext;
+    use crate::sql::primitive::expr::{
+        literal_boolean, literal_f64, literal_i64, literal_null,
+    };
+    use std::sync::Arc;
+
+    fn ecx() -> ExprContext {
+        ExprContext {
+            scx: Arc::new(StatementContext::new(Arc::new(
+                MemCatalog::default(),
+            ))),
+            rel_desc: Arc::new(RelationDesc::empty()),
+        }
+    }
+
+    fn boolean(b: Option<bool>) -> Expr {
+        match b {
+            Some(b) => literal_boolean(b),
+            None => literal_null(ScalarType::Boolean),
+        }
+    }
+
+    fn datum(b: Option<bool>) -> Datum {
+        b.map_or(Datum::Null, Datum::Boolean)
+    }
+
+    #[test]
+    fn three_valued_logic() -> Result<()> {
+        let ecx = ecx();
+        let (t, f, n) = (Some(true), Some(false), None);
+        // (a, b, a AND b, a OR b)
+        let tests = [
+            (t, t, t, t),
+            (t, f, f, t),
+            (t, n, n, t),
+            (f, t, f, t),
+            (f, f, f, f),
+            (f, n, f, n),
+            (n, t, n, t),
+            (n, f, f, n),
+            (n, n, n, n),
+        ];
+        for (a, b, a_and_b, a_or_b) in tests {
+            let e = and(vec![boolean(a), boolean(b)]);
+            assert_eq!(e.evaluate(&ecx, &Row::empty())?, datum(a_and_b), "{e}");
+            let e = or(vec![boolean(a), boolean(b)]);
+            assert_eq!(e.evaluate(&ecx, &Row::empty())?, datum(a_or_b), "{e}");
+        }
+
+        for (a, not_a) in [(t, f), (f, t), (n, n)] {
+            let e = not(boolean(a));
+            assert_eq!(e.evaluate(&ecx, &Row::empty())?, datum(not_a), "{e}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn substring_positions() -> Result<()> {
+        let text = |s: &str| Datum::Text(s.to_string());
+        let tests = [
+            (2, Some(3), text("ell")),
+            (0, Some(3), text("he")),
+            (-5, Some(3), text("")),
+            (4, None, text("lo")),
+            (10, Some(2), text("")),
+            (2, Some(0), text("")),
+            (1, Some(i64::MAX), text("hello")),
+        ];
+        for (start, count, expected) in tests {
+            let mut datums = vec![text("hello"), Datum::Int64(start)];
+            datums.extend(count.map(Datum::Int64));
+            assert_eq!(substring_datums(&datums)?, expected, "{datums:?}");
+        }
+        assert!(substring_datums(&[
+            text("hello"),
+            Datum::Int64(1),
+            Datum::Int64(-1)
+        ])
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn float_functions() -> Result<()> {
+        let ecx = ecx();
+        let tests = [
+            ("abs", -1.5, 1.5),
+            ("ceil", -1.5, -1.0),
+            ("ceil", 1.2, 2.0),
+            ("floor", -1.5, -2.0),
+            ("floor", 1.8, 1.0),
+            ("round", 2.5, 2.0),
+            ("round", 3.5, 4.0),
+            ("round", -2.5, -2.0),
+            ("round", -3.5, -4.0),
+            ("round", 0.5, 0.0),
+            ("round", 2.4, 2.0),
+            ("round", -2.6, -3.0),
+        ];
+        for (name, f, expected) in tests {
+            let e = numeric_unary(&ecx, name, &literal_f64(f))?;
+            assert_eq!(e.typ(&ecx).scalar_type, ScalarType::Float64);
+            assert_eq!(
+                e.evaluate(&ecx, &Row::empty())?,
+                Datum::Float64(expected.into()),
+                "{e}"
+            );
+        }
+        let e = negate(&ecx, &literal_f64(1.5))?;
+        assert_eq!(
+            e.evaluate(&ecx, &Row::empty())?,
+            Datum::Float64((-1.5).into())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn float_arithmetic() -> Result<()> {
+        let ecx = ecx();
+        let f = literal_f64;
+        let eval = |e: Result<Expr>| e?.evaluate(&ecx, &Row::empty());
+        let tests = [
+            (add(&ecx, &f(1.5), &f(2.0)), 3.5),
+            (sub(&ecx, &f(1.5), &f(2.0)), -0.5),
+            (mul(&ecx, &f(1.5), &f(2.0)), 3.0),
+            (div(&ecx, &f(1.5), &f(2.0)), 0.75),
+            (add(&ecx, &f(f64::INFINITY), &f(1.0)), f64::INFINITY),
+        ];
+        for (e, expected) in tests {
+            assert_eq!(eval(e)?, Datum::Float64(expected.into()));
+        }
+
+        assert!(matches!(
+            eval(div(&ecx, &f(1.0), &f(0.0))),
+            Err(FloppyError::DivisionByZero)
+        ));
+        assert!(matches!(
+            eval(mul(&ecx, &f(f64::MAX), &f(2.0))),
+            Err(FloppyError::NumericOutOfRange(_))
+        ));
+        assert!(matches!(
+            eval(div(&ecx, &f(f64::MIN_POSITIVE), &f(f64::MAX))),
+            Err(FloppyError::NumericOutOfRange(_))
+        ));
+        // like PostgreSQL, NaN equals itself and is greater than any
+        // other float.
+        let nan = f(f64::NAN);
+        let e = compare(&ecx, BinaryFunc::Eq, &nan, &nan)?;
+        assert_eq!(e.evaluate(&ecx, &Row::empty())?, Datum::Boolean(true));
+        let e = compare(&ecx, BinaryFunc::Gt, &nan, &f(f64::INFINITY))?;
+        assert_eq!(e.evaluate(&ecx, &Row::empty())?, Datum::Boolean(true));
+        Ok(())
+    }
+
+    #[test]
+    fn integer_division() -> Result<()> {
+        let ecx = ecx();
+        let i = literal_i64;
+        let e = div(&ecx, &i(-7), &i(2))?;
+        assert_eq!(e.evaluate(&ecx, &Row::empty())?, Datum::Int64(-3));
+        assert!(matches!(
+            div(&ecx, &i(1), &i(0))?.evaluate(&ecx, &Row::empty()),
+            Err(FloppyError::DivisionByZero)
+        ));
+        assert!(matches!(
+            div(&ecx, &i(i64::MIN), &i(-1))?.evaluate(&ecx, &Row::empty()),
+            Err(FloppyError::NumericOutOfRange(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn null_propagation() -> Result<()> {
+        let ecx = ecx();
+        let null = literal_null(ScalarType::Int64);
+        let one = literal_i64(1);
+        let funcs = [
+            BinaryFunc::Eq,
+            BinaryFunc::NotEq,
+            BinaryFunc::Lt,
+            BinaryFunc::Lte,
+            BinaryFunc::Gt,
+            BinaryFunc::Gte,
+        ];
+        for func in funcs {
+            for (e1, e2) in [(&null, &one), (&one, &null), (&null, &null)] {
+                let e = compare(&ecx, func.clone(), e1, e2)?;
+                assert!(e.typ(&ecx).nullable, "{e}");
+                assert_eq!(
+                    e.evaluate(&ecx, &Row::empty())?,
+                    Datum::Null,
+                    "{e}"
+                );
+            }
+        }
+        let e = add(&ecx, &one, &null)?;
+        assert_eq!(e.evaluate(&ecx, &Row::empty())?, Datum::Null);
+
+        // NULL = 1 OR 1 = 1
+        let e = or(vec![
+            compare(&ecx, BinaryFunc::Eq, &null, &one)?,
+            compare(&ecx, BinaryFunc::Eq, &one, &one)?,
+        ]);
+        assert_eq!(e.evaluate(&ecx, &Row::empty())?, Datum::Boolean(true));
+        Ok(())
+    }
+}
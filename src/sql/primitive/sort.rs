@@ -0,0 +1,51 @@
+use crate::common::scalar::Datum;
+use crate::sql::primitive::expr::Expr;
+use std::cmp::Ordering;
+use std::fmt;
+use std::fmt::Formatter;
+
+/// A key of `ORDER BY`, eg `c1 DESC NULLS LAST`.
+#[derive(Debug, Clone)]
+pub struct SortExpr {
+    pub expr: Expr,
+    /// Whether the key is in ascending order.
+    pub asc: bool,
+    /// Whether the NULLs are before the other values, regardless
+    /// of `asc`. Like PostgreSQL, it defaults to `!asc`.
+    pub nulls_first: bool,
+}
+
+impl SortExpr {
+    /// Compares the values of the key of two rows.
+    pub fn compare(&self, d1: &Datum, d2: &Datum) -> Ordering {
+        match (d1.is_null(), d2.is_null()) {
+            (true, true) => Ordering::Equal,
+            (true, false) if self.nulls_first => Ordering::Less,
+            (true, false) => Ordering::Greater,
+            (false, true) if self.nulls_first => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (false, false) => {
+                let ord = d1.compare(d2).unwrap_or(Ordering::Equal);
+                if self.asc {
+                    ord
+                } else {
+                    ord.reverse()
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for SortExpr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.expr)?;
+        if !self.asc {
+            write!(f, " DESC")?;
+        }
+        match (self.asc, self.nulls_first) {
+            (true, true) => write!(f, " NULLS FIRST"),
+            (false, false) => write!(f, " NULLS LAST"),
+            _ => Ok(()),
+        }
+    }
+}
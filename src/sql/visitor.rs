@@ -35,11 +35,27 @@ pub struct IndentVisitor<'a, 'b> {
     f: &'a mut fmt::Formatter<'b>,
     /// The current indent.
     indent: usize,
+    /// Whether to append the output columns of a node.
+    with_types: bool,
 }
 
 impl<'a, 'b> IndentVisitor<'a, 'b> {
     pub fn new(f: &'a mut fmt::Formatter<'b>) -> Self {
-        Self { f, indent: 0 }
+        Self {
+            f,
+            indent: 0,
+            with_types: false,
+        }
+    }
+
+    /// Formats each node followed by its output columns, eg
+    /// `Table: test [c1:Int64, c2:Int64]`.
+    pub fn with_types(f: &'a mut fmt::Formatter<'b>) -> Self {
+        Self {
+            f,
+            indent: 0,
+            with_types: true,
+        }
     }
 }
 
@@ -52,6 +68,14 @@ impl<'a, 'b> ExprVisitor<LogicalPlan> for IndentVisitor<'a, 'b> {
         }
         write!(self.f, "{:indent$}", "", indent = self.indent * 2)?;
         write!(self.f, "{}", node.display_node())?;
+        if self.with_types {
+            let columns = node
+                .rel_desc()
+                .iter()
+                .map(|(name, ty)| format!("{name}:{}", ty.scalar_type))
+                .collect::<Vec<_>>();
+            write!(self.f, " [{}]", columns.join(", "))?;
+        }
         self.indent += 1;
         Ok(true)
     }
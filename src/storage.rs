@@ -5,16 +5,23 @@ use crate::common::relation::{GlobalId, IndexRange, Row};
 use std::fmt;
 use std::ops::Bound;
 use std::sync::Arc;
+use txn::{Write, Writes};
 
+pub mod btree;
 pub mod memory;
+pub mod txn;
 
 pub type RowIter = Box<dyn Iterator<Item = Result<Row>>>;
 
 pub trait TableStore: fmt::Debug + Send + Sync {
+    /// Scans the rows whose primary key is in `range`. Only
+    /// the columns in `projection`, which are in ascending
+    /// order, are returned if it is present.
     fn primary_index_range(
         &self,
         table_id: &GlobalId,
         range: &IndexRange,
+        projection: Option<&[usize]>,
     ) -> Result<RowIter>;
 
     fn full_scan(&self, table_id: &GlobalId) -> Result<RowIter> {
@@ -24,12 +31,53 @@ pub trait TableStore: fmt::Debug + Send + Sync {
                 lo: Bound::Unbounded,
                 hi: Bound::Unbounded,
             },
+            None,
         )
     }
 
+    /// Scans the rows whose key of the secondary index `index`, the
+    /// position of the index in the secondary keys of the table, is in
+    /// `range`, in the order of the index key. `range` may bound only
+    /// the leading columns of the key.
+    fn secondary_index_range(
+        &self,
+        table_id: &GlobalId,
+        index: usize,
+        range: &IndexRange,
+        projection: Option<&[usize]>,
+    ) -> Result<RowIter>;
+
+    /// Inserts `row`, and its entries into the secondary indexes.
     fn insert(&self, table_id: &GlobalId, row: &Row) -> Result<()>;
 
-    // todo! add secondary_index_scan
-}
+    /// Replaces the row `old` with `new`, the primary key of the
+    /// row may be changed. The entries of the secondary indexes
+    /// are replaced too.
+    fn update(&self, table_id: &GlobalId, old: &Row, new: &Row) -> Result<()>;
 
-pub static mut GLOBAL_TABLE_STORE: Option<Arc<dyn TableStore>> = None;
+    /// Deletes the row with the primary key of `row`, and its entries
+    /// in the secondary indexes.
+    fn delete(&self, table_id: &GlobalId, row: &Row) -> Result<()>;
+
+    /// Drops all the rows of the table, and its secondary indexes.
+    fn drop_table(&self, table_id: &GlobalId) -> Result<()>;
+
+    /// A read only view of the rows committed so far, which is not
+    /// changed by the later commits. A transaction reads the snapshot
+    /// taken when it is started.
+    fn snapshot(self: Arc<Self>) -> Arc<dyn TableStore>;
+
+    /// Writes the pending `writes` of a transaction when it is
+    /// committed, they are seen by the snapshots taken afterwards.
+    fn commit_writes(&self, writes: &Writes) -> Result<()> {
+        for (table_id, table_writes) in writes {
+            for write in table_writes.values() {
+                match write {
+                    Write::Put(row) => self.insert(table_id, row)?,
+                    Write::Delete(row) => self.delete(table_id, row)?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
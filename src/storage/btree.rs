@@ -0,0 +1,710 @@
+use crate::common::datetime::Interval;
+use crate::common::error::{FloppyError, Result};
+use crate::common::ordered_float::OrderedFloat;
+use crate::common::relation::{
+    GlobalId, IndexKeyDatums, IndexRange, RelationDesc, Row,
+};
+use crate::common::scalar::Datum;
+use crate::dc2::codec::{Codec, Decoder, Encoder};
+use crate::dc2::tree::Tree;
+use crate::env::Env;
+use crate::storage::{RowIter, TableStore};
+use futures::executor::block_on;
+use rust_decimal::Decimal;
+use std::fmt;
+use std::mem;
+use std::ops::Bound;
+use std::path::Path;
+use std::sync::Arc;
+
+/// A [`TableStore`] that stores a clustered table in a [`Tree`].
+/// Rows are keyed by their primary key, which is encoded so that
+/// the byte order of the keys is the same as the order of the
+/// primary key datums.
+pub struct BTreeTableStore<E: Env> {
+    rel_desc: RelationDesc,
+    tree: Tree<E>,
+}
+
+impl<E> BTreeTableStore<E>
+where
+    E: Env,
+{
+    pub fn open<P: AsRef<Path>>(
+        path: P,
+        env: E,
+        rel_desc: RelationDesc,
+    ) -> Result<Self> {
+        let tree = block_on(Tree::open(path, env))?;
+        Ok(Self { rel_desc, tree })
+    }
+
+    /// Write all the changes to disk.
+    pub fn flush(&self) -> Result<()> {
+        block_on(self.tree.flush())
+    }
+}
+
+impl<E> fmt::Debug for BTreeTableStore<E>
+where
+    E: Env,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BTreeTableStore")
+            .field("rel_desc", &self.rel_desc)
+            .finish()
+    }
+}
+
+impl<E> TableStore for BTreeTableStore<E>
+where
+    E: Env,
+{
+    fn primary_index_range(
+        &self,
+        _: &GlobalId,
+        index_range: &IndexRange,
+        projection: Option<&[usize]>,
+    ) -> Result<RowIter> {
+        let (lo, hi) = encode_range(index_range);
+        let records =
+            block_on(self.tree.range_shared::<(Bound<&[u8]>, Bound<&[u8]>)>(
+                (as_slice(&lo), as_slice(&hi)),
+            ))?;
+        let projection = projection.map(Vec::from);
+        Ok(Box::new(records.into_iter().map(move |(_, v)| {
+            let mut dec = Decoder::new(v.as_ref());
+            Ok(match &projection {
+                Some(columns) => unsafe { decode_columns(&mut dec, columns) },
+                None => unsafe { Row::decode_from(&mut dec) },
+            })
+        })))
+    }
+
+    fn insert(&self, _: &GlobalId, row: &Row) -> Result<()> {
+        let key = encode_key(&row.prim_key_datums(&self.rel_desc)?);
+        let mut value = vec![0; row.encode_size()];
+        let mut enc = Encoder::new(value.as_mut_slice());
+        unsafe { row.encode_to(&mut enc) };
+        block_on(self.tree.insert(key, value))
+    }
+
+    fn secondary_index_range(
+        &self,
+        _: &GlobalId,
+        _: usize,
+        _: &IndexRange,
+        _: Option<&[usize]>,
+    ) -> Result<RowIter> {
+        // todo! the secondary indexes are not stored in trees yet.
+        Err(FloppyError::NotImplemented(
+            "secondary index of a table stored in a btree".to_string(),
+        ))
+    }
+
+    fn update(&self, _: &GlobalId, _: &Row, _: &Row) -> Result<()> {
+        // todo! the tree can't replace or delete a record yet.
+        Err(FloppyError::NotImplemented(
+            "UPDATE of a table stored in a btree".to_string(),
+        ))
+    }
+
+    fn delete(&self, _: &GlobalId, _: &Row) -> Result<()> {
+        Err(FloppyError::NotImplemented(
+            "DELETE of a table stored in a btree".to_string(),
+        ))
+    }
+
+    fn drop_table(&self, _: &GlobalId) -> Result<()> {
+        Err(FloppyError::NotImplemented(
+            "DROP TABLE of a table stored in a btree".to_string(),
+        ))
+    }
+
+    fn snapshot(self: Arc<Self>) -> Arc<dyn TableStore> {
+        // todo! the tree keeps only the latest version of the rows, so
+        // the snapshot sees the later commits.
+        self
+    }
+}
+
+/// Encode the bounds of `range` by [`encode_key`].
+pub(crate) fn encode_range(
+    range: &IndexRange,
+) -> (Bound<Vec<u8>>, Bound<Vec<u8>>) {
+    let encode_bound = |bound: &Bound<IndexKeyDatums>| match bound {
+        Bound::Included(k) => Bound::Included(encode_key(k)),
+        Bound::Excluded(k) => Bound::Excluded(encode_key(k)),
+        Bound::Unbounded => Bound::Unbounded,
+    };
+    (encode_bound(&range.lo), encode_bound(&range.hi))
+}
+
+pub(crate) fn as_slice(bound: &Bound<Vec<u8>>) -> Bound<&[u8]> {
+    match bound {
+        Bound::Included(k) => Bound::Included(k.as_slice()),
+        Bound::Excluded(k) => Bound::Excluded(k.as_slice()),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+const TAG_BOOLEAN: u8 = 1;
+const TAG_INT64: u8 = 2;
+const TAG_FLOAT32: u8 = 3;
+const TAG_FLOAT64: u8 = 4;
+const TAG_NUMERIC: u8 = 5;
+const TAG_TEXT: u8 = 6;
+const TAG_DATE: u8 = 7;
+const TAG_TIMESTAMP: u8 = 8;
+const TAG_INTERVAL: u8 = 9;
+const TAG_BYTEA: u8 = 10;
+const TAG_UUID: u8 = 11;
+const TAG_ARRAY: u8 = 12;
+const TAG_NULL: u8 = 13;
+
+/// Encode the primary key datums into a memcomparable format, the
+/// order of the encoded keys is the same as [`IndexKeyDatums`].
+///
+/// Each datum is prefixed by a tag in the order of the variants of
+/// [`Datum`]. Integers, dates and timestamps are stored in big-endian
+/// with the sign bit flipped. Floats are stored in big-endian with the
+/// sign bit flipped if they are positive, otherwise all the bits are
+/// flipped. Numerics are encoded by [`encode_numeric_key`]. Text and
+/// bytea are terminated by `0x00 0x00` and the `0x00` inside them is
+/// escaped as `0x00 0xFF`. Intervals are stored as their span, a
+/// big-endian `i128` with the sign bit flipped, so the intervals of the
+/// same span have the same key. UUIDs are stored as is. Each element of
+/// an array is prefixed by `0x01`, and the array is terminated by
+/// `0x00`, so a prefix of an array is less than the array.
+pub(crate) fn encode_key(key: &IndexKeyDatums) -> Vec<u8> {
+    let mut buf = vec![];
+    for datum in key.datums() {
+        encode_datum_key(datum, &mut buf);
+    }
+    buf
+}
+
+/// Encode a datum into a memcomparable format, see [`encode_key`].
+fn encode_datum_key(datum: &Datum, buf: &mut Vec<u8>) {
+    match datum {
+        Datum::Boolean(b) => {
+            buf.push(TAG_BOOLEAN);
+            buf.push(*b as u8);
+        }
+        Datum::Int64(i) => {
+            buf.push(TAG_INT64);
+            buf.extend(((*i as u64) ^ (1 << 63)).to_be_bytes());
+        }
+        Datum::Float32(f) => {
+            buf.push(TAG_FLOAT32);
+            let bits = f.canonical().to_bits();
+            let mask = if bits >> 31 == 1 { u32::MAX } else { 1 << 31 };
+            buf.extend((bits ^ mask).to_be_bytes());
+        }
+        Datum::Float64(f) => {
+            buf.push(TAG_FLOAT64);
+            let bits = f.canonical().to_bits();
+            let mask = if bits >> 63 == 1 { u64::MAX } else { 1 << 63 };
+            buf.extend((bits ^ mask).to_be_bytes());
+        }
+        Datum::Numeric(d) => {
+            buf.push(TAG_NUMERIC);
+            encode_numeric_key(d, buf);
+        }
+        Datum::Text(s) => {
+            buf.push(TAG_TEXT);
+            encode_bytes_key(s.as_bytes(), buf);
+        }
+        Datum::Date(d) => {
+            buf.push(TAG_DATE);
+            buf.extend(((*d as u32) ^ (1 << 31)).to_be_bytes());
+        }
+        Datum::Timestamp(t) => {
+            buf.push(TAG_TIMESTAMP);
+            buf.extend(((*t as u64) ^ (1 << 63)).to_be_bytes());
+        }
+        Datum::Interval(i) => {
+            buf.push(TAG_INTERVAL);
+            buf.extend(((i.span() as u128) ^ (1 << 127)).to_be_bytes());
+        }
+        Datum::Bytea(b) => {
+            buf.push(TAG_BYTEA);
+            encode_bytes_key(b, buf);
+        }
+        Datum::Uuid(u) => {
+            buf.push(TAG_UUID);
+            buf.extend(u);
+        }
+        Datum::Array(elems) => {
+            buf.push(TAG_ARRAY);
+            for elem in elems {
+                buf.push(1);
+                encode_datum_key(elem, buf);
+            }
+            buf.push(0);
+        }
+        Datum::Null => buf.push(TAG_NULL),
+    }
+}
+
+/// Encode bytes into a memcomparable format, see [`encode_key`].
+fn encode_bytes_key(bytes: &[u8], buf: &mut Vec<u8>) {
+    for b in bytes {
+        buf.push(*b);
+        if *b == 0 {
+            buf.push(0xFF);
+        }
+    }
+    buf.extend([0, 0]);
+}
+
+/// Encode a decimal into a memcomparable format.
+///
+/// The first byte is the sign: 0 for negative, 1 for zero and 2 for
+/// positive. A non-zero `d` is written as `0.DDD * 10^E`, where `DDD`
+/// are the significant digits without trailing zeros. `E` is stored
+/// as a big-endian `i16` with the sign bit flipped, followed by the
+/// ASCII digits and a `0x00` terminator. All the bytes after the
+/// sign are flipped for negative numbers.
+fn encode_numeric_key(d: &Decimal, buf: &mut Vec<u8>) {
+    if d.is_zero() {
+        buf.push(1);
+        return;
+    }
+    let mantissa = d.mantissa().unsigned_abs().to_string();
+    let exp = mantissa.len() as i16 - d.scale() as i16;
+    let digits = mantissa.trim_end_matches('0');
+
+    let mut bytes = ((exp as u16) ^ (1 << 15)).to_be_bytes().to_vec();
+    bytes.extend(digits.as_bytes());
+    bytes.push(0);
+    if d.is_sign_negative() {
+        buf.push(0);
+        buf.extend(bytes.iter().map(|b| !b));
+    } else {
+        buf.push(2);
+        buf.extend(bytes);
+    }
+}
+
+impl Codec for Datum {
+    fn encode_size(&self) -> usize {
+        let size = match self {
+            Datum::Boolean(_) => mem::size_of::<u8>(),
+            Datum::Int64(_) => mem::size_of::<u64>(),
+            Datum::Float32(_) => mem::size_of::<u32>(),
+            Datum::Float64(_) => mem::size_of::<u64>(),
+            Datum::Numeric(_) => 16,
+            Datum::Text(s) => mem::size_of::<u32>() + s.len(),
+            Datum::Date(_) => mem::size_of::<u32>(),
+            Datum::Timestamp(_) => mem::size_of::<u64>(),
+            Datum::Interval(_) => 16,
+            Datum::Bytea(b) => mem::size_of::<u32>() + b.len(),
+            Datum::Uuid(u) => u.len(),
+            Datum::Array(elems) => {
+                mem::size_of::<u32>()
+                    + elems.iter().map(|d| d.encode_size()).sum::<usize>()
+            }
+            Datum::Null => 0,
+        };
+        mem::size_of::<u8>() + size
+    }
+
+    unsafe fn encode_to(&self, enc: &mut Encoder) {
+        match self {
+            Datum::Boolean(b) => {
+                enc.put_u8(TAG_BOOLEAN);
+                enc.put_u8(*b as u8);
+            }
+            Datum::Int64(i) => {
+                enc.put_u8(TAG_INT64);
+                enc.put_u64(*i as u64);
+            }
+            Datum::Float32(f) => {
+                enc.put_u8(TAG_FLOAT32);
+                enc.put_u32(f.0.to_bits());
+            }
+            Datum::Float64(f) => {
+                enc.put_u8(TAG_FLOAT64);
+                enc.put_u64(f.0.to_bits());
+            }
+            Datum::Numeric(d) => {
+                enc.put_u8(TAG_NUMERIC);
+                enc.put_byte_slice(&d.serialize());
+            }
+            Datum::Text(s) => {
+                enc.put_u8(TAG_TEXT);
+                enc.put_u32(s.len() as u32);
+                enc.put_byte_slice(s.as_bytes());
+            }
+            Datum::Date(d) => {
+                enc.put_u8(TAG_DATE);
+                enc.put_u32(*d as u32);
+            }
+            Datum::Timestamp(t) => {
+                enc.put_u8(TAG_TIMESTAMP);
+                enc.put_u64(*t as u64);
+            }
+            Datum::Interval(i) => {
+                enc.put_u8(TAG_INTERVAL);
+                enc.put_u32(i.months as u32);
+                enc.put_u32(i.days as u32);
+                enc.put_u64(i.micros as u64);
+            }
+            Datum::Bytea(b) => {
+                enc.put_u8(TAG_BYTEA);
+                enc.put_u32(b.len() as u32);
+                enc.put_byte_slice(b);
+            }
+            Datum::Uuid(u) => {
+                enc.put_u8(TAG_UUID);
+                enc.put_byte_slice(u);
+            }
+            Datum::Array(elems) => {
+                enc.put_u8(TAG_ARRAY);
+                enc.put_u32(elems.len() as u32);
+                for elem in elems {
+                    elem.encode_to(enc);
+                }
+            }
+            Datum::Null => enc.put_u8(TAG_NULL),
+        }
+    }
+
+    unsafe fn decode_from(dec: &mut Decoder) -> Self {
+        match dec.get_u8() {
+            TAG_BOOLEAN => Datum::Boolean(dec.get_u8() != 0),
+            TAG_INT64 => Datum::Int64(dec.get_u64() as i64),
+            TAG_FLOAT32 => {
+                Datum::Float32(OrderedFloat(f32::from_bits(dec.get_u32())))
+            }
+            TAG_FLOAT64 => {
+                Datum::Float64(OrderedFloat(f64::from_bits(dec.get_u64())))
+            }
+            TAG_NUMERIC => {
+                let bytes = dec.get_byte_slice(16);
+                Datum::Numeric(Decimal::deserialize(bytes.try_into().unwrap()))
+            }
+            TAG_TEXT => {
+                let len = dec.get_u32() as usize;
+                let bytes = dec.get_byte_slice(len);
+                Datum::Text(String::from_utf8_lossy(bytes).into_owned())
+            }
+            TAG_DATE => Datum::Date(dec.get_u32() as i32),
+            TAG_TIMESTAMP => Datum::Timestamp(dec.get_u64() as i64),
+            TAG_INTERVAL => Datum::Interval(Interval {
+                months: dec.get_u32() as i32,
+                days: dec.get_u32() as i32,
+                micros: dec.get_u64() as i64,
+            }),
+            TAG_BYTEA => {
+                let len = dec.get_u32() as usize;
+                Datum::Bytea(dec.get_byte_slice(len).to_vec())
+            }
+            TAG_UUID => Datum::Uuid(dec.get_byte_slice(16).try_into().unwrap()),
+            TAG_ARRAY => {
+                let len = dec.get_u32() as usize;
+                Datum::Array(
+                    (0..len).map(|_| Datum::decode_from(dec)).collect(),
+                )
+            }
+            TAG_NULL => Datum::Null,
+            tag => panic!("invalid datum tag: {tag}"),
+        }
+    }
+}
+
+/// Skips an encoded datum, see [`Datum::encode_to`].
+unsafe fn skip_datum(dec: &mut Decoder) {
+    let size = match dec.get_u8() {
+        TAG_BOOLEAN => mem::size_of::<u8>(),
+        TAG_INT64 | TAG_FLOAT64 | TAG_TIMESTAMP => mem::size_of::<u64>(),
+        TAG_FLOAT32 | TAG_DATE => mem::size_of::<u32>(),
+        TAG_NUMERIC | TAG_INTERVAL | TAG_UUID => 16,
+        TAG_TEXT | TAG_BYTEA => dec.get_u32() as usize,
+        TAG_ARRAY => {
+            for _ in 0..dec.get_u32() {
+                skip_datum(dec);
+            }
+            0
+        }
+        TAG_NULL => 0,
+        tag => panic!("invalid datum tag: {tag}"),
+    };
+    dec.get_byte_slice(size);
+}
+
+/// Decodes only the ascending `columns` of an encoded row, the
+/// other datums are skipped.
+unsafe fn decode_columns(dec: &mut Decoder, columns: &[usize]) -> Row {
+    let len = dec.get_u16() as usize;
+    let mut datums = Vec::with_capacity(columns.len());
+    let mut columns = columns.iter().peekable();
+    for i in 0..len {
+        if columns.peek().is_none() {
+            break;
+        }
+        if columns.next_if_eq(&&i).is_some() {
+            datums.push(Datum::decode_from(dec));
+        } else {
+            skip_datum(dec);
+        }
+    }
+    Row::new(datums)
+}
+
+impl Codec for Row {
+    fn encode_size(&self) -> usize {
+        mem::size_of::<u16>()
+            + self.iter().map(|d| d.encode_size()).sum::<usize>()
+    }
+
+    unsafe fn encode_to(&self, enc: &mut Encoder) {
+        enc.put_u16(self.iter().len() as u16);
+        for datum in self.iter() {
+            datum.encode_to(enc);
+        }
+    }
+
+    unsafe fn decode_from(dec: &mut Decoder) -> Self {
+        let len = dec.get_u16() as usize;
+        let datums = (0..len).map(|_| Datum::decode_from(dec)).collect();
+        Row::new(datums)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::relation::ColumnType;
+    use crate::common::scalar::ScalarType;
+    use crate::dc2::wal::remove_wal_files;
+    use crate::env::stdenv::StdEnv;
+
+    #[test]
+    fn insert_restart_scan() -> Result<()> {
+        let path = "tmp_test_btree_table_store";
+        let _ = std::fs::remove_file(path);
+        remove_wal_files(path.as_ref());
+        let table_id = 1;
+        let rel_desc = RelationDesc::new(
+            vec![
+                ColumnType::new(ScalarType::Int64, false),
+                ColumnType::new(ScalarType::Text, true),
+            ],
+            vec!["c1".to_string(), "c2".to_string()],
+            vec![0],
+            vec![],
+        );
+        let row = |i: i64| {
+            let c2 = if i % 10 == 0 {
+                Datum::Null
+            } else {
+                Datum::Text(format!("value-{i}"))
+            };
+            Row::new(vec![Datum::Int64(i), c2])
+        };
+
+        {
+            let store = BTreeTableStore::open(path, StdEnv, rel_desc.clone())?;
+            for i in (-1000..1000).rev() {
+                store.insert(&table_id, &row(i))?;
+            }
+            store.flush()?;
+        }
+
+        let store = BTreeTableStore::open(path, StdEnv, rel_desc)?;
+        let rows = store.full_scan(&table_id)?.collect::<Result<Vec<Row>>>()?;
+        assert_eq!(rows, (-1000..1000).map(row).collect::<Vec<Row>>());
+
+        let range = IndexRange {
+            lo: Bound::Included(vec![Datum::Int64(-10)].into_iter().collect()),
+            hi: Bound::Excluded(vec![Datum::Int64(10)].into_iter().collect()),
+        };
+        let rows = store
+            .primary_index_range(&table_id, &range, None)?
+            .collect::<Result<Vec<Row>>>()?;
+        assert_eq!(rows, (-10..10).map(row).collect::<Vec<Row>>());
+
+        let rows = store
+            .primary_index_range(&table_id, &range, Some(&[1]))?
+            .collect::<Result<Vec<Row>>>()?;
+        assert_eq!(
+            rows,
+            (-10..10)
+                .map(|i| row(i).project(&[1]))
+                .collect::<Vec<Row>>()
+        );
+
+        std::fs::remove_file(path)?;
+        remove_wal_files(path.as_ref());
+        Ok(())
+    }
+
+    #[test]
+    fn numeric_key_order() {
+        let mut values = [
+            "-100", "-1.5", "-1.25", "-1", "-0.05", "0", "0.05", "0.5", "1",
+            "1.20", "1.25", "10", "12", "100",
+        ]
+        .map(|s| Decimal::from_str_exact(s).unwrap());
+        let keys = values.map(|d| {
+            let mut buf = vec![];
+            encode_numeric_key(&d, &mut buf);
+            buf
+        });
+        assert!(keys.windows(2).all(|w| w[0] < w[1]), "{keys:?}");
+
+        // the keys of the equal numbers are the same.
+        let key = |d: Decimal| {
+            let mut buf = vec![];
+            encode_numeric_key(&d, &mut buf);
+            buf
+        };
+        assert_eq!(key(Decimal::new(12, 1)), key(Decimal::new(1200, 3)));
+
+        values.reverse();
+        let row = Row::new(values.iter().map(|d| Datum::Numeric(*d)).collect());
+        let mut buf = vec![0; row.encode_size()];
+        unsafe {
+            row.encode_to(&mut Encoder::new(buf.as_mut_slice()));
+            assert_eq!(Row::decode_from(&mut Decoder::new(&buf)), row);
+        }
+    }
+
+    #[test]
+    fn array_key_order() {
+        let array = |elems: &[Option<&str>]| {
+            Datum::Array(
+                elems
+                    .iter()
+                    .map(|e| e.map_or(Datum::Null, |e| Datum::Text(e.into())))
+                    .collect(),
+            )
+        };
+        let arrays = [
+            array(&[]),
+            array(&[Some("")]),
+            array(&[Some(""), Some("a")]),
+            array(&[Some("a")]),
+            array(&[Some("a"), Some("")]),
+            array(&[Some("a"), None]),
+            array(&[Some("a\0")]),
+            array(&[Some("b")]),
+            array(&[None]),
+        ];
+        assert!(arrays
+            .windows(2)
+            .all(|w| w[0].compare(&w[1]).unwrap().is_lt()));
+        let keys = arrays.map(|d| {
+            let mut buf = vec![];
+            encode_datum_key(&d, &mut buf);
+            buf
+        });
+        assert!(keys.windows(2).all(|w| w[0] < w[1]), "{keys:?}");
+    }
+
+    #[test]
+    fn interval_key_order() {
+        let interval = |months, days, micros| {
+            Datum::Interval(Interval {
+                months,
+                days,
+                micros,
+            })
+        };
+        let intervals = [
+            interval(-1, 0, 0),
+            interval(0, 0, -1),
+            interval(0, 0, 0),
+            interval(0, 1, -1),
+            interval(0, 30, 0),
+            interval(0, 0, i64::MAX),
+            interval(i32::MAX, i32::MAX, 0),
+        ];
+        assert!(intervals.windows(2).all(|w| w[0] < w[1]));
+        let key = |d: &Datum| {
+            let mut buf = vec![];
+            encode_datum_key(d, &mut buf);
+            buf
+        };
+        let keys = intervals.map(|d| key(&d));
+        assert!(keys.windows(2).all(|w| w[0] < w[1]), "{keys:?}");
+
+        // the intervals of the same span have the same key.
+        assert_eq!(key(&interval(1, 0, 0)), key(&interval(0, 30, 0)));
+        assert_eq!(
+            key(&interval(0, 1, 0)),
+            key(&interval(0, 0, 24 * 3600 * 1_000_000))
+        );
+    }
+
+    mod pt {
+        use super::*;
+        use proptest::collection::vec;
+        use proptest::prelude::*;
+
+        fn arb_datum() -> impl Strategy<Value = Datum> {
+            let int_array = vec(any::<Option<i64>>(), 0..4).prop_map(|elems| {
+                let elems = elems
+                    .into_iter()
+                    .map(|n| n.map_or(Datum::Null, Datum::Int64))
+                    .collect();
+                Datum::Array(elems)
+            });
+            prop_oneof![
+                any::<bool>().prop_map(Datum::Boolean),
+                any::<i64>().prop_map(Datum::Int64),
+                any::<f32>().prop_map(|f| Datum::Float32(OrderedFloat(f))),
+                any::<f64>().prop_map(|f| Datum::Float64(OrderedFloat(f))),
+                (any::<i64>(), 0..=28u32).prop_map(
+                    |(n, scale)| Datum::Numeric(Decimal::new(n, scale))
+                ),
+                any::<String>().prop_map(Datum::Text),
+                any::<i32>().prop_map(Datum::Date),
+                any::<i64>().prop_map(Datum::Timestamp),
+                any::<(i32, i32, i64)>().prop_map(|(months, days, micros)| {
+                    Datum::Interval(Interval {
+                        months,
+                        days,
+                        micros,
+                    })
+                }),
+                vec(any::<u8>(), 0..16).prop_map(Datum::Bytea),
+                any::<[u8; 16]>().prop_map(Datum::Uuid),
+                int_array,
+                Just(Datum::Null),
+            ]
+        }
+
+        fn arb_row() -> impl Strategy<Value = Row> {
+            vec(arb_datum(), 0..16).prop_map(Row::new)
+        }
+
+        fn encode(row: &Row) -> Vec<u8> {
+            let mut buf = vec![0; row.encode_size()];
+            unsafe { row.encode_to(&mut Encoder::new(buf.as_mut_slice())) };
+            buf
+        }
+
+        proptest! {
+            #[test]
+            fn row_round_trip(row in arb_row()) {
+                let buf = encode(&row);
+                let decoded = unsafe { Row::decode_from(&mut Decoder::new(&buf)) };
+                prop_assert_eq!(decoded, row);
+            }
+
+            #[test]
+            fn decode_projected_row(row in arb_row(), mask in vec(any::<bool>(), 16)) {
+                let columns = (0..row.iter().len())
+                    .filter(|i| mask[*i])
+                    .collect::<Vec<usize>>();
+                let buf = encode(&row);
+                let decoded =
+                    unsafe { decode_columns(&mut Decoder::new(&buf), &columns) };
+                prop_assert_eq!(decoded, row.project(&columns));
+            }
+        }
+    }
+}
@@ -1,56 +1,347 @@
-use crate::common::error::Result;
+use crate::common::error::{unique_violation, FloppyError, Result};
 use crate::common::relation::{
     GlobalId, IndexKeyDatums, IndexRange, RelationDesc, Row,
 };
+use crate::storage::btree::{as_slice, encode_key, encode_range};
+use crate::storage::txn::{Write, Writes};
 use crate::storage::{RowIter, TableStore};
+use crate::tc::txn_mgr::{Timestamp, TxnMgr};
 use std::collections::BTreeMap;
-use std::ops::RangeBounds;
-use std::sync::Mutex;
+use std::ops::Bound;
+use std::sync::{Arc, Mutex};
 
+/// A [`TableStore`] that keeps the versions of the rows in memory,
+/// each of them is stamped with the commit timestamp of the write.
+/// The rows are read as of the last commit, or as of the timestamp
+/// of a [`MemorySnapshot`].
 #[derive(Debug)]
 pub struct MemoryEngine {
     rel_desc: RelationDesc,
-    // Clustered table data that is sorted by primary key.
-    inner: EngineInner,
+    txn_mgr: TxnMgr,
+    inner: Mutex<EngineInner>,
 }
 
 impl MemoryEngine {
     pub fn new(rel_desc: RelationDesc) -> Self {
+        let inner = EngineInner {
+            rows: BTreeMap::new(),
+            indexes: vec![BTreeMap::new(); rel_desc.secondary_keys().len()],
+        };
         Self {
             rel_desc,
-            inner: EngineInner::default(),
+            txn_mgr: TxnMgr::default(),
+            inner: Mutex::new(inner),
         }
     }
+
+    fn primary_index_range_as_of(
+        &self,
+        index_range: &IndexRange,
+        projection: Option<&[usize]>,
+        ts: Timestamp,
+    ) -> Result<RowIter> {
+        let (lo, hi) = encode_range(index_range);
+        if is_empty_range(&lo, &hi) {
+            return Ok(Box::new(std::iter::empty()));
+        }
+        let rows = self
+            .inner
+            .lock()
+            .unwrap()
+            .rows
+            .range::<[u8], _>((as_slice(&lo), as_slice(&hi)))
+            .filter_map(|(_, versions)| visible(versions, ts))
+            .map(|row| Ok(project(row, projection)))
+            .collect::<Vec<Result<Row>>>();
+        Ok(Box::new(rows.into_iter()))
+    }
+
+    fn secondary_index_range_as_of(
+        &self,
+        index: usize,
+        index_range: &IndexRange,
+        projection: Option<&[usize]>,
+        ts: Timestamp,
+    ) -> Result<RowIter> {
+        let inner = self.inner.lock().unwrap();
+        let entries = inner.indexes.get(index).ok_or_else(|| {
+            FloppyError::Storage(format!("secondary index not found: {index}"))
+        })?;
+        let (lo, hi) = encode_secondary_range(index_range);
+        if is_empty_range(&lo, &hi) {
+            return Ok(Box::new(std::iter::empty()));
+        }
+        let rows = entries
+            .range::<[u8], _>((as_slice(&lo), as_slice(&hi)))
+            .filter_map(|(entry, prim_key)| {
+                // the entry may be of another version of the row.
+                let row = inner.get(prim_key, ts)?;
+                let seen = index_entry(&self.rel_desc, index, prim_key, row);
+                (seen == *entry).then(|| Ok(project(row, projection)))
+            })
+            .collect::<Vec<Result<Row>>>();
+        Ok(Box::new(rows.into_iter()))
+    }
+}
+
+#[derive(Debug)]
+struct EngineInner {
+    /// Clustered table data that is sorted by primary key, which is
+    /// encoded the same as `BTreeTableStore` so that both stores
+    /// return the rows in the same order. The versions of a row are
+    /// in the order of their timestamps.
+    rows: BTreeMap<Vec<u8>, Vec<Version>>,
+    /// The entries of each secondary index, the key is the encoded
+    /// index key followed by the encoded primary key, which makes the
+    /// keys unique. The value is the encoded primary key. There are
+    /// entries of all the versions of the rows.
+    // todo! the old versions are not garbage collected yet.
+    indexes: Vec<BTreeMap<Vec<u8>, Vec<u8>>>,
+}
+
+/// A version of a row written at `ts`, a deleted row has a tombstone
+/// version whose `row` is `None`.
+#[derive(Debug)]
+struct Version {
+    ts: Timestamp,
+    row: Option<Row>,
 }
 
-#[derive(Debug, Default)]
-struct EngineInner(Mutex<BTreeMap<IndexKeyDatums, Row>>);
+impl EngineInner {
+    /// The row whose encoded primary key is `prim_key` as of `ts`.
+    fn get(&self, prim_key: &[u8], ts: Timestamp) -> Option<&Row> {
+        visible(self.rows.get(prim_key)?, ts)
+    }
+
+    /// Writes a version of the row whose encoded primary key is
+    /// `prim_key` at `ts`, `row` is `None` if the row is deleted.
+    fn put(
+        &mut self,
+        rel_desc: &RelationDesc,
+        prim_key: Vec<u8>,
+        row: Option<Row>,
+        ts: Timestamp,
+    ) {
+        if let Some(row) = &row {
+            for (i, index) in self.indexes.iter_mut().enumerate() {
+                let entry = index_entry(rel_desc, i, &prim_key, row);
+                index.insert(entry, prim_key.clone());
+            }
+        }
+        self.rows
+            .entry(prim_key)
+            .or_default()
+            .push(Version { ts, row });
+    }
+}
+
+/// The row of `versions` as of `ts`.
+fn visible(versions: &[Version], ts: Timestamp) -> Option<&Row> {
+    versions.iter().rev().find(|v| v.ts <= ts)?.row.as_ref()
+}
+
+/// The entry of `row` in the secondary index `index`, see
+/// [`EngineInner::indexes`].
+pub(super) fn index_entry(
+    rel_desc: &RelationDesc,
+    index: usize,
+    prim_key: &[u8],
+    row: &Row,
+) -> Vec<u8> {
+    let datums = rel_desc.secondary_keys()[index]
+        .iter()
+        .map(|i| row.datums()[*i].clone())
+        .collect::<IndexKeyDatums>();
+    let mut entry = encode_key(&datums);
+    entry.extend_from_slice(prim_key);
+    entry
+}
 
 impl TableStore for MemoryEngine {
     fn primary_index_range(
         &self,
         _: &GlobalId,
         index_range: &IndexRange,
+        projection: Option<&[usize]>,
     ) -> Result<RowIter> {
-        let index_range = index_range.clone();
-        let result_set = self
-            .inner
-            .0
-            .lock()
-            .unwrap()
-            .clone()
-            .into_iter()
-            .filter(move |e| index_range.clone().contains(&e.0))
-            .map(|e| Ok(e.1));
+        let ts = self.txn_mgr.start_ts();
+        self.primary_index_range_as_of(index_range, projection, ts)
+    }
 
-        Ok(Box::new(result_set))
+    fn secondary_index_range(
+        &self,
+        _: &GlobalId,
+        index: usize,
+        index_range: &IndexRange,
+        projection: Option<&[usize]>,
+    ) -> Result<RowIter> {
+        let ts = self.txn_mgr.start_ts();
+        self.secondary_index_range_as_of(index, index_range, projection, ts)
     }
 
     fn insert(&self, _: &GlobalId, row: &Row) -> Result<()> {
-        let key_datums = row.prim_key_datums(&self.rel_desc)?;
-        self.inner.0.lock().unwrap().insert(key_datums, row.clone());
+        let key = encode_key(&row.prim_key_datums(&self.rel_desc)?);
+        self.txn_mgr.commit(|ts| {
+            let mut inner = self.inner.lock().unwrap();
+            inner.put(&self.rel_desc, key, Some(row.clone()), ts);
+            Ok(())
+        })
+    }
+
+    fn update(&self, _: &GlobalId, old: &Row, new: &Row) -> Result<()> {
+        let old_key = encode_key(&old.prim_key_datums(&self.rel_desc)?);
+        let new_datums = new.prim_key_datums(&self.rel_desc)?;
+        let new_key = encode_key(&new_datums);
+        self.txn_mgr.commit(|ts| {
+            let mut inner = self.inner.lock().unwrap();
+            if new_key != old_key {
+                if inner.get(&new_key, ts).is_some() {
+                    return Err(unique_violation(&new_datums));
+                }
+                if inner.get(&old_key, ts).is_some() {
+                    inner.put(&self.rel_desc, old_key, None, ts);
+                }
+            }
+            inner.put(&self.rel_desc, new_key, Some(new.clone()), ts);
+            Ok(())
+        })
+    }
+
+    fn delete(&self, _: &GlobalId, row: &Row) -> Result<()> {
+        let datums = row.prim_key_datums(&self.rel_desc)?;
+        let key = encode_key(&datums);
+        self.txn_mgr.commit(|ts| {
+            let mut inner = self.inner.lock().unwrap();
+            if inner.get(&key, ts).is_none() {
+                return Err(FloppyError::Storage(format!(
+                    "row not found: {datums:?}"
+                )));
+            }
+            inner.put(&self.rel_desc, key, None, ts);
+            Ok(())
+        })
+    }
+
+    fn drop_table(&self, _: &GlobalId) -> Result<()> {
+        // the table is removed from the catalog before, so the rows
+        // are not seen by the snapshots taken before either.
+        let mut inner = self.inner.lock().unwrap();
+        inner.rows.clear();
+        inner.indexes.iter_mut().for_each(BTreeMap::clear);
         Ok(())
     }
+
+    fn snapshot(self: Arc<Self>) -> Arc<dyn TableStore> {
+        let ts = self.txn_mgr.start_ts();
+        Arc::new(MemorySnapshot { engine: self, ts })
+    }
+
+    fn commit_writes(&self, writes: &Writes) -> Result<()> {
+        // all the writes are stamped with the same timestamp, so they
+        // are seen by a snapshot all together.
+        self.txn_mgr.commit(|ts| {
+            let mut inner = self.inner.lock().unwrap();
+            for (key, write) in writes.values().flatten() {
+                let row = match write {
+                    Write::Put(row) => Some(row.clone()),
+                    Write::Delete(_) => None,
+                };
+                inner.put(&self.rel_desc, key.clone(), row, ts);
+            }
+            Ok(())
+        })
+    }
+}
+
+/// The rows of a [`MemoryEngine`] committed at or before `ts`, which
+/// are not changed by the later commits. It is read only.
+#[derive(Debug)]
+pub struct MemorySnapshot {
+    engine: Arc<MemoryEngine>,
+    ts: Timestamp,
+}
+
+impl TableStore for MemorySnapshot {
+    fn primary_index_range(
+        &self,
+        _: &GlobalId,
+        index_range: &IndexRange,
+        projection: Option<&[usize]>,
+    ) -> Result<RowIter> {
+        self.engine
+            .primary_index_range_as_of(index_range, projection, self.ts)
+    }
+
+    fn secondary_index_range(
+        &self,
+        _: &GlobalId,
+        index: usize,
+        index_range: &IndexRange,
+        projection: Option<&[usize]>,
+    ) -> Result<RowIter> {
+        self.engine.secondary_index_range_as_of(
+            index,
+            index_range,
+            projection,
+            self.ts,
+        )
+    }
+
+    fn insert(&self, _: &GlobalId, _: &Row) -> Result<()> {
+        Err(read_only())
+    }
+
+    fn update(&self, _: &GlobalId, _: &Row, _: &Row) -> Result<()> {
+        Err(read_only())
+    }
+
+    fn delete(&self, _: &GlobalId, _: &Row) -> Result<()> {
+        Err(read_only())
+    }
+
+    fn drop_table(&self, _: &GlobalId) -> Result<()> {
+        Err(read_only())
+    }
+
+    fn snapshot(self: Arc<Self>) -> Arc<dyn TableStore> {
+        self
+    }
+}
+
+fn read_only() -> FloppyError {
+    FloppyError::Internal("a snapshot is read only".to_string())
+}
+
+/// Encode the bounds of `range` of a secondary index, so that the
+/// entries whose index keys are in `range` are in the encoded range.
+pub(super) fn encode_secondary_range(
+    range: &IndexRange,
+) -> (Bound<Vec<u8>>, Bound<Vec<u8>>) {
+    // an entry is prefixed by the index key, which is followed by
+    // the primary key starting with a tag less than 0xFF, so the
+    // entries of a key are less than the key followed by 0xFF.
+    let (lo, hi) = encode_range(range);
+    let past_key = |mut key: Vec<u8>| {
+        key.push(0xFF);
+        key
+    };
+    let lo = match lo {
+        Bound::Excluded(key) => Bound::Excluded(past_key(key)),
+        lo => lo,
+    };
+    let hi = match hi {
+        Bound::Included(key) => Bound::Excluded(past_key(key)),
+        hi => hi,
+    };
+    (lo, hi)
+}
+
+/// The columns `projection` of `row`, or all of them.
+pub(super) fn project(row: &Row, projection: Option<&[usize]>) -> Row {
+    match projection {
+        Some(columns) => row.project(columns),
+        None => row.clone(),
+    }
 }
 
 impl MemoryEngine {
@@ -65,3 +356,233 @@ impl MemoryEngine {
         Ok(())
     }
 }
+
+/// Whether no key is in the range of `lo` and `hi`, for which
+/// [`BTreeMap::range`] panics.
+pub(super) fn is_empty_range(lo: &Bound<Vec<u8>>, hi: &Bound<Vec<u8>>) -> bool {
+    match (lo, hi) {
+        (Bound::Included(lo), Bound::Included(hi)) => lo > hi,
+        (
+            Bound::Included(lo) | Bound::Excluded(lo),
+            Bound::Included(hi) | Bound::Excluded(hi),
+        ) => lo >= hi,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::relation::ColumnType;
+    use crate::common::scalar::{Datum, ScalarType};
+
+    #[test]
+    fn scan_in_key_order() -> Result<()> {
+        let rel_desc = RelationDesc::new(
+            vec![
+                ColumnType::new(ScalarType::Text, false),
+                ColumnType::new(ScalarType::Int64, false),
+            ],
+            vec!["c1".to_string(), "c2".to_string()],
+            vec![0],
+            vec![],
+        );
+        let store = MemoryEngine::new(rel_desc);
+        let row = |s: &str, i: i64| {
+            Row::new(vec![Datum::Text(s.to_string()), Datum::Int64(i)])
+        };
+        for (i, s) in ["b", "a\0", "", "ab", "a"].iter().enumerate() {
+            store.insert(&1, &row(s, i as i64))?;
+        }
+        let rows = store.full_scan(&1)?.collect::<Result<Vec<Row>>>()?;
+        let expected = vec![
+            row("", 2),
+            row("a", 4),
+            row("a\0", 1),
+            row("ab", 3),
+            row("b", 0),
+        ];
+        assert_eq!(rows, expected);
+
+        let key =
+            |s: &str| Some(Datum::Text(s.to_string())).into_iter().collect();
+        let range = IndexRange {
+            lo: Bound::Excluded(key("a")),
+            hi: Bound::Included(key("ab")),
+        };
+        let rows = store
+            .primary_index_range(&1, &range, Some(&[1]))?
+            .collect::<Result<Vec<Row>>>()?;
+        assert_eq!(
+            rows,
+            vec![
+                Row::new(vec![Datum::Int64(1)]),
+                Row::new(vec![Datum::Int64(3)])
+            ]
+        );
+
+        // an empty range.
+        for (lo, hi) in [
+            (Bound::Included(key("b")), Bound::Included(key("a"))),
+            (Bound::Excluded(key("a")), Bound::Included(key("a"))),
+            (Bound::Excluded(key("a")), Bound::Excluded(key("a"))),
+        ] {
+            let range = IndexRange { lo, hi };
+            let rows = store.primary_index_range(&1, &range, None)?;
+            assert_eq!(rows.count(), 0);
+        }
+
+        // the primary key is changed by the update.
+        store.update(&1, &row("ab", 3), &row("c", 3))?;
+        let e = store.update(&1, &row("", 2), &row("b", 2)).unwrap_err();
+        assert_eq!(e.code(), "23505");
+        let rows = store.full_scan(&1)?.collect::<Result<Vec<Row>>>()?;
+        assert_eq!(rows.last(), Some(&row("c", 3)));
+        assert_eq!(rows.len(), 5);
+        Ok(())
+    }
+
+    #[test]
+    fn maintain_secondary_index() -> Result<()> {
+        let rel_desc = RelationDesc::new(
+            vec![
+                ColumnType::new(ScalarType::Int64, false),
+                ColumnType::new(ScalarType::Text, true),
+            ],
+            vec!["c1".to_string(), "c2".to_string()],
+            vec![0],
+            vec![vec![1]],
+        );
+        let store = MemoryEngine::new(rel_desc);
+        let row = |i: i64, s: Option<&str>| {
+            let text = s.map_or(Datum::Null, |s| Datum::Text(s.to_string()));
+            Row::new(vec![Datum::Int64(i), text])
+        };
+        let all = IndexRange {
+            lo: Bound::Unbounded,
+            hi: Bound::Unbounded,
+        };
+        // the rows of the secondary index are the rows of the table
+        // ordered by c2 and then by c1.
+        let check = |expected: Vec<i64>| -> Result<()> {
+            let rows = store
+                .secondary_index_range(&1, 0, &all, Some(&[0]))?
+                .collect::<Result<Vec<Row>>>()?;
+            let expected = expected
+                .into_iter()
+                .map(|i| Row::new(vec![Datum::Int64(i)]))
+                .collect::<Vec<_>>();
+            assert_eq!(rows, expected);
+            Ok(())
+        };
+
+        for (i, s) in [(1, "b"), (2, "a"), (3, "c"), (4, "a")] {
+            store.insert(&1, &row(i, Some(s)))?;
+        }
+        store.insert(&1, &row(5, None))?;
+        check(vec![2, 4, 1, 3, 5])?;
+
+        // the indexed column is changed by the update.
+        store.update(&1, &row(2, Some("a")), &row(2, Some("d")))?;
+        check(vec![4, 1, 3, 2, 5])?;
+        // the primary key is changed by the update.
+        store.update(&1, &row(3, Some("c")), &row(0, Some("c")))?;
+        check(vec![4, 1, 0, 2, 5])?;
+        // an insert replaces the row of the same primary key.
+        store.insert(&1, &row(4, Some("e")))?;
+        check(vec![1, 0, 2, 4, 5])?;
+
+        store.delete(&1, &row(0, Some("c")))?;
+        check(vec![1, 2, 4, 5])?;
+        assert!(store.delete(&1, &row(0, Some("c"))).is_err());
+
+        store.insert(&1, &row(6, Some("b")))?;
+        let key =
+            |s: &str| Some(Datum::Text(s.to_string())).into_iter().collect();
+        for (lo, hi, expected) in [
+            (
+                Bound::Included(key("b")),
+                Bound::Included(key("d")),
+                vec![1, 6, 2],
+            ),
+            (
+                Bound::Excluded(key("b")),
+                Bound::Included(key("d")),
+                vec![2],
+            ),
+            (
+                Bound::Included(key("b")),
+                Bound::Excluded(key("d")),
+                vec![1, 6],
+            ),
+            (Bound::Excluded(key("b")), Bound::Excluded(key("d")), vec![]),
+            (Bound::Included(key("d")), Bound::Included(key("b")), vec![]),
+        ] {
+            let range = IndexRange { lo, hi };
+            let rows = store
+                .secondary_index_range(&1, 0, &range, None)?
+                .map(|r| r.map(|r| r.datums()[0].clone()))
+                .collect::<Result<Vec<Datum>>>()?;
+            let expected =
+                expected.into_iter().map(Datum::Int64).collect::<Vec<_>>();
+            assert_eq!(rows, expected);
+        }
+        assert!(store.secondary_index_range(&1, 1, &all, None).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn read_snapshot() -> Result<()> {
+        let rel_desc = RelationDesc::new(
+            vec![
+                ColumnType::new(ScalarType::Int64, false),
+                ColumnType::new(ScalarType::Text, false),
+            ],
+            vec!["c1".to_string(), "c2".to_string()],
+            vec![0],
+            vec![vec![1]],
+        );
+        let store = Arc::new(MemoryEngine::new(rel_desc));
+        let row = |i: i64, s: &str| {
+            Row::new(vec![Datum::Int64(i), Datum::Text(s.to_string())])
+        };
+        let all = IndexRange {
+            lo: Bound::Unbounded,
+            hi: Bound::Unbounded,
+        };
+        let scan = |store: &dyn TableStore| -> Result<Vec<Row>> {
+            store.full_scan(&1)?.collect()
+        };
+        let index_scan = |store: &dyn TableStore| -> Result<Vec<Row>> {
+            store.secondary_index_range(&1, 0, &all, None)?.collect()
+        };
+
+        store.seed(&1, &[row(1, "a"), row(2, "b")])?;
+        let s1 = store.clone().snapshot();
+        store.insert(&1, &row(3, "c"))?;
+        store.update(&1, &row(1, "a"), &row(1, "d"))?;
+        store.delete(&1, &row(2, "b"))?;
+        // the snapshot doesn't see the later commits.
+        assert_eq!(scan(&*s1)?, [row(1, "a"), row(2, "b")]);
+        assert_eq!(index_scan(&*s1)?, [row(1, "a"), row(2, "b")]);
+        let s2 = store.clone().snapshot();
+        assert_eq!(scan(&*s2)?, [row(1, "d"), row(3, "c")]);
+        assert_eq!(index_scan(&*s2)?, [row(3, "c"), row(1, "d")]);
+        assert!(s2.insert(&1, &row(4, "e")).is_err());
+
+        // the writes of a transaction are committed all together.
+        let mut table_writes = BTreeMap::new();
+        for (row, write) in [
+            (row(2, "b"), Write::Put(row(2, "b"))),
+            (row(3, "c"), Write::Delete(row(3, "c"))),
+        ] {
+            let key = encode_key(&row.prim_key_datums(&store.rel_desc)?);
+            table_writes.insert(key, write);
+        }
+        store.commit_writes(&Writes::from([(1, table_writes)]))?;
+        assert_eq!(scan(&*s2)?, [row(1, "d"), row(3, "c")]);
+        assert_eq!(scan(&*store)?, [row(1, "d"), row(2, "b")]);
+        assert_eq!(index_scan(&*store)?, [row(2, "b"), row(1, "d")]);
+        Ok(())
+    }
+}
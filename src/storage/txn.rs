@@ -0,0 +1,364 @@
+use crate::catalog::names::FullObjectName;
+use crate::catalog::CatalogStore;
+use crate::common::error::{unique_violation, FloppyError, Result};
+use crate::common::relation::{
+    GlobalId, IndexKeyDatums, IndexRange, RelationDesc, Row,
+};
+use crate::storage::btree::{as_slice, encode_key, encode_range};
+use crate::storage::memory::{
+    encode_secondary_range, index_entry, is_empty_range, project,
+};
+use crate::storage::{RowIter, TableStore};
+use std::collections::BTreeMap;
+use std::ops::Bound;
+use std::sync::{Arc, Mutex};
+
+/// The pending writes of each table, keyed by the primary keys of the
+/// rows, which are encoded by [`encode_key`].
+pub type Writes = BTreeMap<GlobalId, BTreeMap<Vec<u8>, Write>>;
+
+/// A pending write of a row.
+#[derive(Debug, Clone)]
+pub enum Write {
+    /// The row is inserted, or replaces the row of the same key.
+    Put(Row),
+    /// The row of the key is deleted.
+    Delete(Row),
+}
+
+/// The table store seen by the statements of a transaction, which
+/// reads the snapshot of `base` taken when the transaction is
+/// started. The writes are kept in memory and merged with the rows of
+/// the snapshot when they are scanned, so a statement sees the writes
+/// of the previous ones. They are written to `base` when the
+/// transaction is committed, or dropped if it is rolled back, so they
+/// are not seen by the other transactions before the commit.
+///
+/// The conflicts of the writes of concurrent transactions are not
+/// detected yet, the last committed write of a row wins.
+#[derive(Debug)]
+pub struct TxnTableStore {
+    base: Arc<dyn TableStore>,
+    snapshot: Arc<dyn TableStore>,
+    catalog_store: Arc<dyn CatalogStore>,
+    writes: Mutex<Writes>,
+}
+
+impl TxnTableStore {
+    pub fn new(
+        base: Arc<dyn TableStore>,
+        catalog_store: Arc<dyn CatalogStore>,
+    ) -> Self {
+        Self {
+            snapshot: base.clone().snapshot(),
+            base,
+            catalog_store,
+            writes: Mutex::new(Writes::new()),
+        }
+    }
+
+    /// A copy of the pending writes, which can be restored by
+    /// [`TxnTableStore::restore`].
+    pub fn writes(&self) -> Writes {
+        self.writes.lock().unwrap().clone()
+    }
+
+    /// Replaces the pending writes with `writes`.
+    pub fn restore(&self, writes: Writes) {
+        *self.writes.lock().unwrap() = writes;
+    }
+
+    /// Writes the pending writes to the base store.
+    pub fn commit(&self) -> Result<()> {
+        let writes = std::mem::take(&mut *self.writes.lock().unwrap());
+        self.base.commit_writes(&writes)
+    }
+
+    fn rel_desc(&self, table_id: &GlobalId) -> Result<RelationDesc> {
+        let item = self.catalog_store.get_item(table_id)?;
+        let full_name = FullObjectName::from(item.name().item.as_str());
+        Ok(item.desc(&full_name)?.into_owned())
+    }
+
+    /// Whether the snapshot has a row of the primary key `key`.
+    fn base_contains(
+        &self,
+        table_id: &GlobalId,
+        key: &IndexKeyDatums,
+    ) -> Result<bool> {
+        let range = IndexRange {
+            lo: Bound::Included(key.clone()),
+            hi: Bound::Included(key.clone()),
+        };
+        let mut rows =
+            self.snapshot.primary_index_range(table_id, &range, None)?;
+        Ok(rows.next().transpose()?.is_some())
+    }
+
+    /// Whether a row of the primary key `key` is seen by the
+    /// transaction.
+    fn contains(
+        &self,
+        table_id: &GlobalId,
+        table_writes: &BTreeMap<Vec<u8>, Write>,
+        key: &IndexKeyDatums,
+    ) -> Result<bool> {
+        match table_writes.get(&encode_key(key)) {
+            Some(Write::Put(_)) => Ok(true),
+            Some(Write::Delete(_)) => Ok(false),
+            None => self.base_contains(table_id, key),
+        }
+    }
+
+    /// Removes the row of the primary key of `row`, returns whether
+    /// it is seen by the transaction.
+    fn remove(
+        &self,
+        table_id: &GlobalId,
+        table_writes: &mut BTreeMap<Vec<u8>, Write>,
+        rel_desc: &RelationDesc,
+        row: &Row,
+    ) -> Result<bool> {
+        let key = row.prim_key_datums(rel_desc)?;
+        if !self.contains(table_id, table_writes, &key)? {
+            return Ok(false);
+        }
+        // a row only inserted by the transaction is not in the
+        // snapshot, so it is not deleted from the base store.
+        if self.base_contains(table_id, &key)? {
+            table_writes.insert(encode_key(&key), Write::Delete(row.clone()));
+        } else {
+            table_writes.remove(&encode_key(&key));
+        }
+        Ok(true)
+    }
+}
+
+impl TableStore for TxnTableStore {
+    fn primary_index_range(
+        &self,
+        table_id: &GlobalId,
+        range: &IndexRange,
+        projection: Option<&[usize]>,
+    ) -> Result<RowIter> {
+        let writes = self.writes.lock().unwrap();
+        let Some(table_writes) = writes.get(table_id) else {
+            return self
+                .snapshot
+                .primary_index_range(table_id, range, projection);
+        };
+        let rel_desc = self.rel_desc(table_id)?;
+        let mut rows = self
+            .snapshot
+            .primary_index_range(table_id, range, None)?
+            .map(|row| {
+                let row = row?;
+                Ok((encode_key(&row.prim_key_datums(&rel_desc)?), row))
+            })
+            .collect::<Result<BTreeMap<Vec<u8>, Row>>>()?;
+        let (lo, hi) = encode_range(range);
+        if !is_empty_range(&lo, &hi) {
+            for (key, write) in
+                table_writes.range::<[u8], _>((as_slice(&lo), as_slice(&hi)))
+            {
+                match write {
+                    Write::Put(row) => rows.insert(key.clone(), row.clone()),
+                    Write::Delete(_) => rows.remove(key),
+                };
+            }
+        }
+        let rows = rows
+            .into_values()
+            .map(|row| Ok(project(&row, projection)))
+            .collect::<Vec<Result<Row>>>();
+        Ok(Box::new(rows.into_iter()))
+    }
+
+    fn secondary_index_range(
+        &self,
+        table_id: &GlobalId,
+        index: usize,
+        range: &IndexRange,
+        projection: Option<&[usize]>,
+    ) -> Result<RowIter> {
+        let writes = self.writes.lock().unwrap();
+        let Some(table_writes) = writes.get(table_id) else {
+            return self
+                .snapshot
+                .secondary_index_range(table_id, index, range, projection);
+        };
+        let rel_desc = self.rel_desc(table_id)?;
+        // the rows keyed by their entries of the index, a row of the
+        // snapshot is replaced by the pending write of its key.
+        let mut entries = BTreeMap::new();
+        for row in self
+            .snapshot
+            .secondary_index_range(table_id, index, range, None)?
+        {
+            let row = row?;
+            let prim_key = encode_key(&row.prim_key_datums(&rel_desc)?);
+            if !table_writes.contains_key(&prim_key) {
+                let entry = index_entry(&rel_desc, index, &prim_key, &row);
+                entries.insert(entry, row);
+            }
+        }
+        let (lo, hi) = encode_secondary_range(range);
+        if is_empty_range(&lo, &hi) {
+            return Ok(Box::new(std::iter::empty()));
+        }
+        for (prim_key, write) in table_writes {
+            if let Write::Put(row) = write {
+                let entry = index_entry(&rel_desc, index, prim_key, row);
+                entries.insert(entry, row.clone());
+            }
+        }
+        let rows = entries
+            .range::<[u8], _>((as_slice(&lo), as_slice(&hi)))
+            .map(|(_, row)| Ok(project(row, projection)))
+            .collect::<Vec<Result<Row>>>();
+        Ok(Box::new(rows.into_iter()))
+    }
+
+    fn insert(&self, table_id: &GlobalId, row: &Row) -> Result<()> {
+        let rel_desc = self.rel_desc(table_id)?;
+        let key = encode_key(&row.prim_key_datums(&rel_desc)?);
+        self.writes
+            .lock()
+            .unwrap()
+            .entry(*table_id)
+            .or_default()
+            .insert(key, Write::Put(row.clone()));
+        Ok(())
+    }
+
+    fn update(&self, table_id: &GlobalId, old: &Row, new: &Row) -> Result<()> {
+        let rel_desc = self.rel_desc(table_id)?;
+        let old_key = old.prim_key_datums(&rel_desc)?;
+        let new_key = new.prim_key_datums(&rel_desc)?;
+        let mut writes = self.writes.lock().unwrap();
+        let table_writes = writes.entry(*table_id).or_default();
+        if new_key != old_key
+            && self.contains(table_id, table_writes, &new_key)?
+        {
+            return Err(unique_violation(&new_key));
+        }
+        self.remove(table_id, table_writes, &rel_desc, old)?;
+        table_writes.insert(encode_key(&new_key), Write::Put(new.clone()));
+        Ok(())
+    }
+
+    fn delete(&self, table_id: &GlobalId, row: &Row) -> Result<()> {
+        let rel_desc = self.rel_desc(table_id)?;
+        let mut writes = self.writes.lock().unwrap();
+        let table_writes = writes.entry(*table_id).or_default();
+        if !self.remove(table_id, table_writes, &rel_desc, row)? {
+            return Err(FloppyError::Storage(format!(
+                "row not found: {:?}",
+                row.prim_key_datums(&rel_desc)?
+            )));
+        }
+        Ok(())
+    }
+
+    fn drop_table(&self, _: &GlobalId) -> Result<()> {
+        // todo! the tables are dropped right away, the drop can't be
+        // rolled back with the writes of the transaction yet.
+        Err(FloppyError::NotImplemented(
+            "DROP TABLE in a transaction block".to_string(),
+        ))
+    }
+
+    fn snapshot(self: Arc<Self>) -> Arc<dyn TableStore> {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::memory::MemCatalog;
+    use crate::common::relation::ColumnType;
+    use crate::common::scalar::{Datum, ScalarType};
+    use crate::storage::memory::MemoryEngine;
+
+    #[test]
+    fn merge_pending_writes() -> Result<()> {
+        let rel_desc = RelationDesc::new(
+            vec![
+                ColumnType::new(ScalarType::Int64, false),
+                ColumnType::new(ScalarType::Text, false),
+            ],
+            vec!["c1".to_string(), "c2".to_string()],
+            vec![0],
+            vec![vec![1]],
+        );
+        let mut catalog = MemCatalog::default();
+        catalog.insert_table("test", 1, rel_desc.clone());
+        let row = |i: i64, s: &str| {
+            Row::new(vec![Datum::Int64(i), Datum::Text(s.to_string())])
+        };
+        let base = Arc::new(MemoryEngine::new(rel_desc));
+        base.seed(&1, &[row(1, "d"), row(2, "b"), row(3, "c")])?;
+        let catalog: Arc<dyn CatalogStore> = Arc::new(catalog);
+        let store = TxnTableStore::new(base.clone(), catalog.clone());
+        let reader = TxnTableStore::new(base.clone(), catalog.clone());
+        let all = IndexRange {
+            lo: Bound::Unbounded,
+            hi: Bound::Unbounded,
+        };
+        let scan = |store: &dyn TableStore| -> Result<Vec<Row>> {
+            store.full_scan(&1)?.collect()
+        };
+        let index_scan = |store: &dyn TableStore| -> Result<Vec<Row>> {
+            store.secondary_index_range(&1, 0, &all, None)?.collect()
+        };
+
+        store.insert(&1, &row(4, "a"))?;
+        store.update(&1, &row(2, "b"), &row(2, "e"))?;
+        store.delete(&1, &row(3, "c"))?;
+        assert!(store.delete(&1, &row(3, "c")).is_err());
+        let e = store.update(&1, &row(1, "d"), &row(2, "d")).unwrap_err();
+        assert_eq!(e.code(), "23505");
+        assert_eq!(scan(&store)?, [row(1, "d"), row(2, "e"), row(4, "a")]);
+        assert_eq!(
+            index_scan(&store)?,
+            [row(4, "a"), row(1, "d"), row(2, "e")]
+        );
+        let key =
+            |s: &str| Some(Datum::Text(s.to_string())).into_iter().collect();
+        let range = IndexRange {
+            lo: Bound::Excluded(key("a")),
+            hi: Bound::Included(key("d")),
+        };
+        let rows = store
+            .secondary_index_range(&1, 0, &range, Some(&[0]))?
+            .collect::<Result<Vec<Row>>>()?;
+        assert_eq!(rows, [Row::new(vec![Datum::Int64(1)])]);
+        // the base store is not written before the commit.
+        assert_eq!(scan(&*base)?, [row(1, "d"), row(2, "b"), row(3, "c")]);
+
+        // a row inserted by the transaction is deleted before it is
+        // written to the base store.
+        let writes = store.writes();
+        store.insert(&1, &row(5, "f"))?;
+        store.delete(&1, &row(5, "f"))?;
+        store.update(&1, &row(1, "d"), &row(6, "d"))?;
+        assert_eq!(scan(&store)?, [row(2, "e"), row(4, "a"), row(6, "d")]);
+        store.restore(writes);
+        assert_eq!(scan(&store)?, [row(1, "d"), row(2, "e"), row(4, "a")]);
+
+        base.insert(&1, &row(7, "g"))?;
+        store.commit()?;
+        let rows = [row(1, "d"), row(2, "e"), row(4, "a"), row(7, "g")];
+        assert_eq!(scan(&*base)?, rows);
+        assert_eq!(
+            index_scan(&*base)?,
+            [row(4, "a"), row(1, "d"), row(2, "e"), row(7, "g")]
+        );
+        // a transaction reads the snapshot taken when it is started.
+        assert_eq!(scan(&reader)?, [row(1, "d"), row(2, "b"), row(3, "c")]);
+        let reader = TxnTableStore::new(base, catalog);
+        assert_eq!(scan(&reader)?, rows);
+        Ok(())
+    }
+}
@@ -1,3 +1,4 @@
 mod lock_mgr;
+pub mod txn_mgr;
 /// TC (Transaction Component)
 mod wal;
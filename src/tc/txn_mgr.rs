@@ -0,0 +1,39 @@
+use crate::common::error::Result;
+use std::sync::Mutex;
+
+/// The commit timestamp of a transaction, which orders the commits.
+/// A transaction started at a timestamp sees the writes of the
+/// transactions committed at or before it.
+pub type Timestamp = u64;
+
+/// Allocates the timestamps of the transactions.
+#[derive(Debug, Default)]
+pub struct TxnMgr {
+    /// The timestamp of the last commit, it is locked while a
+    /// transaction is being committed.
+    last_commit: Mutex<Timestamp>,
+}
+
+impl TxnMgr {
+    /// The timestamp of a transaction started now, it sees the
+    /// transactions committed so far but not the ones being committed.
+    pub fn start_ts(&self) -> Timestamp {
+        *self.last_commit.lock().unwrap()
+    }
+
+    /// Commits a transaction by `write`, which writes its changes
+    /// with the commit timestamp. The commits are serialized, and the
+    /// timestamp is seen by the transactions started after `write`
+    /// returns.
+    pub fn commit<T>(
+        &self,
+        write: impl FnOnce(Timestamp) -> Result<T>,
+    ) -> Result<T> {
+        let mut last_commit = self.last_commit.lock().unwrap();
+        // the timestamp is used even if `write` fails, so that the
+        // changes it has written are not mixed up with the next
+        // commit.
+        *last_commit += 1;
+        write(*last_commit)
+    }
+}
@@ -46,7 +46,10 @@ pub fn seed_catalog_and_table(
     let catalog = seed_catalog();
     let partial_name: PartialObjectName = (*TEST_TABLE_NAME).into();
     let full_name: FullObjectName = partial_name.clone().into();
-    let rel_desc = catalog.resolve_item(&partial_name)?.desc(&full_name)?;
-    let table = seed_table(rel_desc.into_owned(), data)?;
+    let rel_desc = catalog
+        .resolve_item(&partial_name, &catalog::default_search_path())?
+        .desc(&full_name)?
+        .into_owned();
+    let table = seed_table(rel_desc, data)?;
     Ok((catalog, table))
 }